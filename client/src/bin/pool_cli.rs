@@ -0,0 +1,199 @@
+use anyhow::{Context, Result};
+use argh::FromArgs;
+use pool_client::{PoolClient, StakerStatus};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::Serialize;
+use url::Url;
+use vrsc_rpc::{bitcoin::BlockHash, json::vrsc::Address};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Args = argh::from_env();
+
+    let client = PoolClient::with_http_client(args.url, http_client(args.admin_key)?);
+
+    match args.command {
+        Command::Stakers(cmd) => {
+            let stakers = client
+                .stakers(&cmd.currency, &cmd.identity, cmd.status)
+                .await?;
+
+            for staker in &stakers {
+                println!(
+                    "{}\t{}\t{:?}\t{:?}\t{}",
+                    staker.identity_address,
+                    staker.identity_name,
+                    staker.status,
+                    staker.participation_kind,
+                    staker.fee
+                );
+            }
+            eprintln!("{} stakers", stakers.len());
+        }
+        Command::Ban(cmd) => print(
+            &client
+                .ban_staker(&cmd.currency, &cmd.identity, &cmd.operator, &cmd.reason)
+                .await?,
+        )?,
+        Command::RunPayouts(cmd) => {
+            client.run_payouts(&cmd.currency).await?;
+            eprintln!("payout run started");
+        }
+        Command::InsertStake(cmd) => print(
+            &client
+                .insert_historical_stake(&cmd.currency, &cmd.block_hash, !cmd.apply)
+                .await?,
+        )?,
+        Command::MergeRounds(cmd) => print(
+            &client
+                .merge_rounds(&cmd.currency, cmd.from_round, cmd.into_round, !cmd.apply)
+                .await?,
+        )?,
+        Command::SyncStatus(_) => print(&client.liveness().await?)?,
+    }
+
+    Ok(())
+}
+
+/// Sends the admin key with every request, as the admin routes require it.
+fn http_client(admin_key: Option<String>) -> Result<reqwest::Client> {
+    let mut headers = HeaderMap::new();
+    if let Some(admin_key) = admin_key.or_else(|| std::env::var("POOL_ADMIN_KEY").ok()) {
+        let mut value =
+            HeaderValue::from_str(&format!("Bearer {admin_key}")).context("invalid admin key")?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    Ok(reqwest::Client::builder()
+        .default_headers(headers)
+        .build()?)
+}
+
+fn print<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+fn parse_staker_status(value: &str) -> Result<StakerStatus, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown staker status: {value}"))
+}
+
+#[derive(FromArgs)]
+/// Runs the common operations of a staking pool through its HTTP API.
+struct Args {
+    /// the url of the pool
+    #[argh(option, default = "Url::parse(\"http://127.0.0.1:3000\").unwrap()")]
+    url: Url,
+
+    /// one of the admin keys of the pool, read from POOL_ADMIN_KEY if not set
+    #[argh(option)]
+    admin_key: Option<String>,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Stakers(StakersCommand),
+    Ban(BanCommand),
+    RunPayouts(RunPayoutsCommand),
+    InsertStake(InsertStakeCommand),
+    MergeRounds(MergeRoundsCommand),
+    SyncStatus(SyncStatusCommand),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "stakers")]
+/// Lists the stakers of a currency.
+struct StakersCommand {
+    /// the currency of the pool
+    #[argh(option)]
+    currency: Address,
+
+    /// only list stakers with this status: active, cooling_down, inactive, expired or banned
+    #[argh(option, from_str_fn(parse_staker_status))]
+    status: Option<StakerStatus>,
+
+    /// only list these VerusIDs
+    #[argh(option)]
+    identity: Vec<Address>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "ban")]
+/// Bans a staker, which forfeits its work in the current round and its unpaid rewards.
+struct BanCommand {
+    /// the currency of the pool
+    #[argh(option)]
+    currency: Address,
+
+    /// the VerusID of the staker
+    #[argh(option)]
+    identity: Address,
+
+    /// who bans the staker
+    #[argh(option)]
+    operator: String,
+
+    /// why the staker is banned
+    #[argh(option)]
+    reason: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "run-payouts")]
+/// Starts a payout run right away.
+struct RunPayoutsCommand {
+    /// the currency of the pool
+    #[argh(option)]
+    currency: Address,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "insert-stake")]
+/// Inserts a stake of the pool that was not counted. Shows what would change, unless --apply is
+/// set.
+struct InsertStakeCommand {
+    /// the currency of the pool
+    #[argh(option)]
+    currency: Address,
+
+    /// the block of the stake
+    #[argh(option)]
+    block_hash: BlockHash,
+
+    /// insert the stake instead of doing a dry run
+    #[argh(switch)]
+    apply: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "merge-rounds")]
+/// Moves the work of a round into another round. Shows what would change, unless --apply is set.
+struct MergeRoundsCommand {
+    /// the currency of the pool
+    #[argh(option)]
+    currency: Address,
+
+    /// the id of the round of which the work is moved
+    #[argh(option)]
+    from_round: u64,
+
+    /// the id of the round that gets the work
+    #[argh(option)]
+    into_round: u64,
+
+    /// merge the rounds instead of doing a dry run
+    #[argh(switch)]
+    apply: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "sync-status")]
+/// Shows how far every chain of the pool is synced.
+struct SyncStatusCommand {}
@@ -0,0 +1,657 @@
+//! A typed client for the HTTP API of the staking pool.
+//!
+//! Every method maps to one endpoint and returns the types from [`poollib::api`], so that
+//! consumers do not have to hand-roll the requests and the JSON parsing.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use url::Url;
+use vrsc_rpc::{bitcoin::BlockHash, json::vrsc::Address};
+
+pub use poollib::api::*;
+use poollib::chunk::ChunkAssembler;
+
+#[derive(Debug, Clone)]
+pub struct PoolClient {
+    base_url: Url,
+    http: reqwest::Client,
+}
+
+/// A response of the pool that was not successful. Returned as the error of every method, so
+/// that callers can downcast to it and branch on the [`ErrorCode`].
+#[derive(Debug)]
+pub struct ResponseError {
+    pub status: reqwest::StatusCode,
+    /// `None` if the body of the response was not an [`ApiError`].
+    pub error: Option<ApiError>,
+}
+
+impl ResponseError {
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.error.as_ref().map(|error| error.code)
+    }
+}
+
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.error {
+            Some(error) => write!(
+                f,
+                "pool responded with {} ({:?}): {}",
+                self.status, error.code, error.message
+            ),
+            None => write!(f, "pool responded with {}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
+impl PoolClient {
+    /// Creates a client for the pool that is reachable at `base_url`, for example
+    /// `http://127.0.0.1:3000`.
+    pub fn new(base_url: Url) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Creates a client that uses an existing [`reqwest::Client`], for example to configure
+    /// timeouts.
+    pub fn with_http_client(base_url: Url, http: reqwest::Client) -> Self {
+        Self { base_url, http }
+    }
+
+    /// Returns the version of the pool.
+    pub async fn info(&self) -> Result<String> {
+        self.get(&["info"], &[]).await
+    }
+
+    /// Returns how far every chain of the pool is synced, and whether its database and daemon
+    /// can be reached. Unlike the other methods, this does not fail if a chain is unhealthy.
+    pub async fn liveness(&self) -> Result<serde_json::Value> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("base url cannot be a base"))?
+            .pop_if_empty()
+            .push("health");
+
+        let response = self.http.get(url).send().await?;
+        if response.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            return response
+                .json()
+                .await
+                .context("could not parse the response of the pool");
+        }
+
+        parse(response).await
+    }
+
+    pub async fn statistics(&self, currency: &Address) -> Result<Stats> {
+        self.get(&["currency", &currency.to_string(), "statistics"], &[])
+            .await
+    }
+
+    /// Returns the primary address that a VerusID needs to include to stake in this pool.
+    pub async fn pool_primary_address(&self, currency: &Address) -> Result<String> {
+        self.get(
+            &["currency", &currency.to_string(), "poolprimaryaddress"],
+            &[],
+        )
+        .await
+    }
+
+    pub async fn rotation_progress(&self, currency: &Address) -> Result<Vec<RotationProgress>> {
+        self.get(
+            &["currency", &currency.to_string(), "rotationprogress"],
+            &[],
+        )
+        .await
+    }
+
+    /// Returns the expected staking returns, taking upcoming halvings into account.
+    pub async fn reward_outlook(&self, currency: &Address) -> Result<RewardOutlook> {
+        self.get(&["currency", &currency.to_string(), "rewardoutlook"], &[])
+            .await
+    }
+
+    pub async fn staking_supply(
+        &self,
+        currency: &Address,
+        identity_addresses: &[Address],
+    ) -> Result<StakingSupply> {
+        let query = repeated("identity_address", identity_addresses);
+
+        self.get(
+            &["currency", &currency.to_string(), "stakingsupply"],
+            &query,
+        )
+        .await
+    }
+
+    pub async fn network_stats(
+        &self,
+        currency: &Address,
+        from_height: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<NetworkStats>> {
+        let mut query = vec![];
+        if let Some(from_height) = from_height {
+            query.push(("from_height", from_height.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+
+        self.get(&["currency", &currency.to_string(), "networkstats"], &query)
+            .await
+    }
+
+    /// Checks the eligibility of a VerusID and returns it as a staker.
+    ///
+    /// Returns `None` if the VerusID is not eligible to stake in this pool.
+    pub async fn staker_status(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+    ) -> Result<Option<Staker>> {
+        let url = self.url(&["currency", &currency.to_string(), "stakerstatus"])?;
+        let response = self
+            .http
+            .put(url)
+            .query(&[("address", identity_address.to_string())])
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        parse(response).await.map(Some)
+    }
+
+    /// Lets the pool stake the funds of `address` on behalf of a VerusID. `signature` is the
+    /// signature by `address` of `delegate staking of {address} on {currency} to
+    /// {identity_address}`.
+    pub async fn delegate_staking(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+        address: &Address,
+        signature: &str,
+    ) -> Result<Staker> {
+        let url = self.url(&["currency", &currency.to_string(), "delegatedstaking"])?;
+        let response = self
+            .http
+            .put(url)
+            .query(&[
+                ("identity_address", identity_address.to_string()),
+                ("address", address.to_string()),
+                ("signature", signature.to_string()),
+            ])
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    pub async fn stakers(
+        &self,
+        currency: &Address,
+        identity_addresses: &[Address],
+        staker_status: Option<StakerStatus>,
+    ) -> Result<Vec<Staker>> {
+        let mut query = repeated("identity_addresses", identity_addresses);
+        if let Some(status) = staker_status {
+            query.push(("staker_status", enum_value(&status)?));
+        }
+
+        self.get(&["currency", &currency.to_string(), "staker"], &query)
+            .await
+    }
+
+    /// Returns the history of a staker, newest first.
+    ///
+    /// Pass the timestamp of the oldest activity as `before` to get the next page.
+    pub async fn staker_activity(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+        before: Option<u64>,
+        limit: Option<u64>,
+    ) -> Result<Vec<StakerActivity>> {
+        let mut query = vec![("identity_address", identity_address.to_string())];
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+
+        self.get(
+            &["currency", &currency.to_string(), "stakeractivity"],
+            &query,
+        )
+        .await
+    }
+
+    /// Returns the final statements of a staker that left the pool, newest first.
+    pub async fn staker_statements(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+    ) -> Result<Vec<StakerStatement>> {
+        self.get(
+            &["currency", &currency.to_string(), "stakerstatement"],
+            &[("identity_address", identity_address.to_string())],
+        )
+        .await
+    }
+
+    pub async fn staker_earnings(
+        &self,
+        currency: &Address,
+        identity_addresses: &[Address],
+    ) -> Result<HashMap<Address, StakerEarnings>> {
+        let query = repeated("identity_address", identity_addresses);
+
+        self.get(
+            &["currency", &currency.to_string(), "stakerearnings"],
+            &query,
+        )
+        .await
+    }
+
+    /// Returns the eligible staking balance per VerusID, in coins.
+    pub async fn staking_balance(
+        &self,
+        currency: &Address,
+        identity_addresses: &[Address],
+    ) -> Result<HashMap<Address, f64>> {
+        let query = repeated("identity_address", identity_addresses);
+
+        self.get(
+            &["currency", &currency.to_string(), "stakingbalance"],
+            &query,
+        )
+        .await
+    }
+
+    pub async fn stakes(
+        &self,
+        currency: &Address,
+        stake_status: Option<StakeStatus>,
+    ) -> Result<Vec<Stake>> {
+        let mut query = vec![];
+        if let Some(status) = stake_status {
+            query.push(("stake_status", enum_value(&status)?));
+        }
+
+        self.get(&["currency", &currency.to_string(), "stake"], &query)
+            .await
+    }
+
+    /// Waits up to `timeout` seconds for the next stake of the pool, `None` if none was found.
+    pub async fn next_stake(
+        &self,
+        currency: &Address,
+        timeout: Option<u64>,
+    ) -> Result<Option<Stake>> {
+        let mut query = vec![];
+        if let Some(timeout) = timeout {
+            query.push(("timeout", timeout.to_string()));
+        }
+
+        self.get(
+            &["currency", &currency.to_string(), "stake", "next"],
+            &query,
+        )
+        .await
+    }
+
+    pub async fn payouts(
+        &self,
+        currency: &Address,
+        identity_addresses: &[Address],
+    ) -> Result<Vec<PayoutMember>> {
+        let query = repeated("identity_addresses", identity_addresses);
+
+        self.get(&["currency", &currency.to_string(), "payout"], &query)
+            .await
+    }
+
+    /// Returns the rewards that are not paid yet, per staker and in total.
+    pub async fn liabilities(&self, currency: &Address) -> Result<Liabilities> {
+        self.get(&["currency", &currency.to_string(), "liabilities"], &[])
+            .await
+    }
+
+    /// Returns the configuration that the pool runs with, per currency, with the secrets
+    /// redacted.
+    pub async fn config(&self) -> Result<HashMap<Address, serde_json::Value>> {
+        self.get(&["admin", "config"], &[]).await
+    }
+
+    /// Returns the delivery statistics of the webhook endpoints, per currency.
+    pub async fn webhook_status(&self) -> Result<HashMap<Address, Vec<EndpointStatus>>> {
+        self.get(&["admin", "webhooks", "status"], &[]).await
+    }
+
+    /// Returns the report of the consistency audit that ran when the pool started, per currency.
+    pub async fn startup_audit(&self) -> Result<HashMap<Address, AuditReport>> {
+        self.get(&["admin", "audit", "startup"], &[]).await
+    }
+
+    /// Returns the latest reconciliation of the wallet with the recorded payments, per currency.
+    pub async fn reconciliation(&self) -> Result<HashMap<Address, Reconciliation>> {
+        self.get(&["admin", "reconciliation"], &[]).await
+    }
+
+    /// Returns the message that a VerusID has to sign with `signmessage` to log in.
+    pub async fn login_challenge(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+    ) -> Result<LoginChallenge> {
+        let url = self.url(&["currency", &currency.to_string(), "login", "challenge"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({ "identity_address": identity_address }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Exchanges the signature of a login challenge for a session token.
+    pub async fn login(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+        signature: &str,
+    ) -> Result<SessionToken> {
+        let url = self.url(&["currency", &currency.to_string(), "login"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({
+                "identity_address": identity_address,
+                "signature": signature,
+            }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Returns the staker of the VerusID of a session.
+    pub async fn me(&self, session: &SessionToken) -> Result<Staker> {
+        let url = self.url(&["currency", &session.currency_address.to_string(), "me"])?;
+        let response = self
+            .http
+            .get(url)
+            .bearer_auth(&session.token)
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Returns the payouts of the VerusID of a session.
+    pub async fn my_payouts(&self, session: &SessionToken) -> Result<Vec<PayoutMember>> {
+        let url = self.url(&[
+            "currency",
+            &session.currency_address.to_string(),
+            "me",
+            "payouts",
+        ])?;
+        let response = self
+            .http
+            .get(url)
+            .bearer_auth(&session.token)
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Merges the work of the round with id `from_round` into the round with id `into_round`.
+    ///
+    /// With `dry_run`, nothing is changed and the returned changes show what the merge would do.
+    pub async fn merge_rounds(
+        &self,
+        currency: &Address,
+        from_round: u64,
+        into_round: u64,
+        dry_run: bool,
+    ) -> Result<RoundMerge> {
+        let url = self.url(&["admin", "rounds", "merge"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({
+                "currency_address": currency,
+                "from_round": from_round,
+                "into_round": into_round,
+                "dry_run": dry_run,
+            }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Pays the outstanding balance of a single staker right away, regardless of its min_payout
+    /// and the payout schedule. The operator and reason are recorded with the payment.
+    pub async fn pay_staker(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+        operator: &str,
+        reason: &str,
+    ) -> Result<ManualPayment> {
+        let url = self.url(&["admin", "stakers", "pay"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({
+                "currency_address": currency,
+                "identity_address": identity_address,
+                "operator": operator,
+                "reason": reason,
+            }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Bans a staker: it forfeits its work in the current round and the rewards that were not
+    /// paid yet. The operator and reason are logged by the pool.
+    pub async fn ban_staker(
+        &self,
+        currency: &Address,
+        identity_address: &Address,
+        operator: &str,
+        reason: &str,
+    ) -> Result<StakerBan> {
+        let url = self.url(&["admin", "stakers", "ban"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({
+                "currency_address": currency,
+                "identity_address": identity_address,
+                "operator": operator,
+                "reason": reason,
+            }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Starts a payout run right away, instead of at the next payout interval. The run happens
+    /// in the background.
+    pub async fn run_payouts(&self, currency: &Address) -> Result<()> {
+        let url = self.url(&["admin", "payouts", "run"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({ "currency_address": currency }))
+            .send()
+            .await?;
+
+        check(response).await.map(|_| ())
+    }
+
+    /// Inserts a stake of the pool that was not counted, for example because it was staked
+    /// while the pool was down.
+    ///
+    /// With `dry_run`, nothing is changed and the returned stake shows what the insert would do.
+    pub async fn insert_historical_stake(
+        &self,
+        currency: &Address,
+        block_hash: &BlockHash,
+        dry_run: bool,
+    ) -> Result<HistoricalStake> {
+        let url = self.url(&["admin", "stakes"])?;
+        let response = self
+            .http
+            .post(url)
+            .json(&serde_json::json!({
+                "currency_address": currency,
+                "block_hash": block_hash,
+                "dry_run": dry_run,
+            }))
+            .send()
+            .await?;
+
+        parse(response).await
+    }
+
+    /// Returns the last `limit` stakes of the pool, newest first. The pool streams them in
+    /// chunks of at most `chunk_size` stakes, which are reassembled here. Requires an admin key.
+    pub async fn recent_stakes(
+        &self,
+        currency: &Address,
+        limit: usize,
+        chunk_size: usize,
+    ) -> Result<Vec<Stake>> {
+        let url = self.url(&["bot", &currency.to_string(), "recentstakes"])?;
+        let response = self
+            .http
+            .get(url)
+            .query(&[("limit", limit), ("chunk_size", chunk_size)])
+            .send()
+            .await?;
+        let mut response = check(response).await?;
+
+        let mut assembler = ChunkAssembler::new();
+        let mut buffer = vec![];
+        while let Some(bytes) = response.chunk().await? {
+            buffer.extend_from_slice(&bytes);
+
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                let chunk = serde_json::from_slice(&line)
+                    .context("could not parse a chunk of the response of the pool")?;
+                assembler.push(chunk)?;
+            }
+        }
+
+        assembler
+            .finish()
+            .context("the response of the pool ended before its last chunk")
+    }
+
+    fn url(&self, segments: &[&str]) -> Result<Url> {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("base url cannot be a base"))?
+            .pop_if_empty()
+            .push("v1")
+            .extend(segments);
+
+        Ok(url)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        segments: &[&str],
+        query: &[(&str, String)],
+    ) -> Result<T> {
+        let url = self.url(segments)?;
+        let response = self.http.get(url).query(query).send().await?;
+
+        parse(response).await
+    }
+}
+
+async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T> {
+    check(response)
+        .await?
+        .json()
+        .await
+        .context("could not parse the response of the pool")
+}
+
+/// Fails with a [`ResponseError`] if the response was not successful.
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_success() {
+        let error = response.json::<ApiError>().await.ok();
+
+        return Err(ResponseError { status, error }.into());
+    }
+
+    Ok(response)
+}
+
+fn repeated<'a>(key: &'a str, addresses: &[Address]) -> Vec<(&'a str, String)> {
+    addresses
+        .iter()
+        .map(|address| (key, address.to_string()))
+        .collect()
+}
+
+/// Returns the value of a unit enum variant as it is used in query strings.
+fn enum_value<T: serde::Serialize>(value: &T) -> Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(value) => Ok(value),
+        other => bail!("expected a string value, got {other}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_is_built_from_base_url() {
+        let client = PoolClient::new(Url::parse("http://127.0.0.1:3000/").unwrap());
+        let url = client
+            .url(&["currency", "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", "stake"])
+            .unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:3000/v1/currency/iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq/stake"
+        );
+
+        let client = PoolClient::new(Url::parse("http://127.0.0.1:3000/pool").unwrap());
+        let url = client.url(&["info"]).unwrap();
+
+        assert_eq!(url.as_str(), "http://127.0.0.1:3000/pool/v1/info");
+    }
+
+    #[test]
+    fn enum_values_match_the_api() {
+        assert_eq!(
+            enum_value(&StakerStatus::CoolingDown).unwrap(),
+            "cooling_down"
+        );
+        assert_eq!(enum_value(&StakeStatus::StakeGuard).unwrap(), "stake_guard");
+    }
+}
@@ -0,0 +1,44 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::json::vrsc::{util::amount::serde::as_sat, Amount};
+
+use super::StakerStatus;
+
+/// An entry in the chronological history of a staker.
+///
+/// Which of the optional fields are set depends on the kind of activity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StakerActivity {
+    pub kind: StakerActivityKind,
+    /// Unix timestamp (in seconds) of when the activity happened.
+    pub timestamp: u64,
+    pub block_height: Option<u64>,
+    /// The block hash of a stake or payout, or the txid of a payment.
+    pub reference: Option<String>,
+    #[serde(with = "as_sat::opt")]
+    pub amount: Option<Amount>,
+    /// The work of the staker that was counted in the round of a stake.
+    pub shares: Option<Decimal>,
+    /// The status of the staker after a status change.
+    pub status: Option<StakerStatus>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "sqlx",
+    derive(sqlx::Type),
+    sqlx(
+        type_name = "staker_activity_kind",
+        rename_all = "SCREAMING_SNAKE_CASE"
+    )
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StakerActivityKind {
+    StatusChanged,
+    /// The work of the staker was counted in the round of a stake of the pool.
+    WorkCounted,
+    /// The staker found a stake for the pool.
+    StakeFound,
+    PayoutCredited,
+    PaymentReceived,
+}
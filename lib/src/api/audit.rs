@@ -0,0 +1,170 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::{
+    bitcoin::{BlockHash, Txid},
+    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+};
+
+use super::Stake;
+
+/// The result of the consistency audit that runs when the pool starts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub currency_address: Address,
+    /// Unix timestamp (in seconds) of when the audit ran.
+    pub created_at: u64,
+    pub findings: Vec<AuditFinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditFinding {
+    pub category: AuditCategory,
+    pub description: String,
+    /// What an operator can do to resolve the finding.
+    pub suggestion: String,
+    /// The finding was repaired during the audit.
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    /// Maturing stakes of which the status no longer matches the daemon.
+    PendingStakes,
+    /// Work that is assigned to a round without a stake.
+    RoundWork,
+    /// Unpaid rewards that exceed the balance of the wallet.
+    UnpaidPayoutMembers,
+    /// Synchronization heights that don't match the chain or the stakes.
+    Synchronization,
+    /// Matured stakes of which the coinbase is missing or different in the wallet.
+    WalletTransactions,
+}
+
+/// The result of comparing the transactions that the wallet of the daemon sent with the payments
+/// that the pool recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reconciliation {
+    pub currency_address: Address,
+    /// Unix timestamp (in seconds) of when the reconciliation ran.
+    pub created_at: u64,
+    /// The number of wallet transactions that were compared.
+    pub wallet_transactions: u64,
+    /// Funds that the wallet sent, but that the pool has no record of. They were sent by an
+    /// operator, or by someone who got access to the wallet.
+    pub unmatched_sends: Vec<UnmatchedSend>,
+    /// Payments of payout members that are recorded as paid, but that the wallet doesn't know or
+    /// that are no longer in the chain, so the members may not have been paid.
+    pub missing_payments: Vec<MissingPayment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnmatchedSend {
+    pub txid: Txid,
+    /// The destinations of the transaction.
+    pub addresses: Vec<String>,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// Unix timestamp (in seconds) of the transaction.
+    pub time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MissingPayment {
+    pub txid: Txid,
+    /// The number of payout members that are recorded as paid by this transaction.
+    pub n_members: u64,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    pub reason: String,
+}
+
+/// The result of merging the work of one round into the round of a stake.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundMerge {
+    pub currency_address: Address,
+    /// The id of the round of which the work was moved.
+    pub from_round: u64,
+    /// The id of the round of the stake that received the work.
+    pub into_round: u64,
+    /// Nothing was changed, the changes show what a merge would do.
+    pub dry_run: bool,
+    /// Whether the payout of the stake was regenerated. A payout is only generated for a
+    /// matured stake, so a merge into a maturing stake only moves the work.
+    pub payout_regenerated: bool,
+    pub changes: Vec<RoundMergeChange>,
+}
+
+/// The work and reward of a staker in the round of the stake, before and after a merge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundMergeChange {
+    pub identity_address: Address,
+    pub shares_before: Decimal,
+    pub shares_after: Decimal,
+    #[serde(with = "as_sat::opt")]
+    pub reward_before: Option<Amount>,
+    #[serde(with = "as_sat::opt")]
+    pub reward_after: Option<Amount>,
+}
+
+/// The result of inserting a stake that the pool missed, after the fact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoricalStake {
+    pub stake: Stake,
+    /// The id of the round that was created for the stake.
+    pub round_id: u64,
+    /// The id of the round that held the work of the stake, of which a part was split off.
+    pub from_round: u64,
+    /// The part of the work of `from_round` that was added up to and including the stake.
+    pub work_fraction: Decimal,
+    /// Nothing was changed, the shares show what an insert would do.
+    pub dry_run: bool,
+    /// Whether a payout was created. A stake after the last payout gets its payout from the
+    /// payout service once it matured.
+    pub payout_created: bool,
+    pub shares: Vec<HistoricalStakeShares>,
+}
+
+/// The work of a staker in the round of a historical stake.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoricalStakeShares {
+    pub identity_address: Address,
+    pub shares: Decimal,
+    #[serde(with = "as_sat::opt")]
+    pub reward: Option<Amount>,
+}
+
+/// The result of recalculating the payout of a stake from the snapshot of the work in its round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutRecalculation {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    /// Nothing was changed, the changes show what replacing the payout would do.
+    pub dry_run: bool,
+    pub changes: Vec<PayoutRecalculationChange>,
+}
+
+/// The work and reward of a staker in the stored payout of a stake, and as recalculated from the
+/// snapshot of the round.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutRecalculationChange {
+    pub identity_address: Address,
+    /// The shares that the round holds now.
+    pub shares_before: Decimal,
+    /// The shares in the snapshot.
+    pub shares_after: Decimal,
+    #[serde(with = "as_sat::opt")]
+    pub reward_before: Option<Amount>,
+    #[serde(with = "as_sat::opt")]
+    pub reward_after: Option<Amount>,
+}
+
+/// Whether an operator paused a chain, and how many blocks arrived while it was paused.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainPause {
+    pub currency_address: Address,
+    pub paused: bool,
+    /// The blocks that were recorded while the chain was paused. On resume, the number of blocks
+    /// that are replayed.
+    pub recorded_blocks: u64,
+}
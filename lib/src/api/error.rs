@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/// Why a request failed, for clients to branch on. The codes are stable, new codes can be
+/// added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request was understood, but can't be handled. The message says why.
+    InvalidRequest,
+    /// The body of the request is not the expected JSON.
+    InvalidJson,
+    Unauthorized,
+    NotFound,
+    /// The pool doesn't stake this currency.
+    CurrencyNotFound,
+    /// The VerusID is not a staker of the pool.
+    StakerNotFound,
+    /// None of the daemons of the chain answers. Retry later.
+    DaemonUnavailable,
+    /// The pool is still catching up with the chain. Retry later.
+    ChainSyncing,
+    Internal,
+    /// A code of a newer version of the pool.
+    #[serde(other)]
+    Unknown,
+}
+
+/// The body of every response that was not successful.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_screaming_snake_case() {
+        let error = ApiError {
+            code: ErrorCode::StakerNotFound,
+            message: "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU is not a staker".to_string(),
+        };
+
+        let json = serde_json::to_string(&error).unwrap();
+        assert!(json.contains(r#""code":"STAKER_NOT_FOUND""#));
+
+        let unknown: ApiError =
+            serde_json::from_str(r#"{"code":"SOMETHING_NEW","message":""}"#).unwrap();
+        assert_eq!(unknown.code, ErrorCode::Unknown);
+    }
+}
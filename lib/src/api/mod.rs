@@ -0,0 +1,33 @@
+//! The types that are exchanged over the HTTP API of the staking pool.
+
+mod activity;
+mod audit;
+mod error;
+mod payout;
+mod session;
+mod stake;
+mod staker;
+mod stats;
+mod webhook;
+
+pub use activity::{StakerActivity, StakerActivityKind};
+pub use audit::{
+    AuditCategory, AuditFinding, AuditReport, ChainPause, HistoricalStake, HistoricalStakeShares,
+    MissingPayment, PayoutRecalculation, PayoutRecalculationChange, Reconciliation, RoundMerge,
+    RoundMergeChange, UnmatchedSend,
+};
+pub use error::{ApiError, ErrorCode};
+pub use payout::{
+    Liabilities, ManualPayment, Payment, PaymentStatus, PayoutMember, PayoutShadowDiff,
+    PayoutSummary, StakerLiability,
+};
+pub use session::{ApiKey, LoginChallenge, SessionToken};
+pub use stake::{FraudEvidence, RedistributedShares, Round, Stake, StakeStatus, StaleStake};
+pub use staker::{
+    DelegatedAddress, EarningsGranularity, EarningsPeriod, Eligibility, EligibilityCheck,
+    EligibilityCondition, FeeOverride, KeyRotation, KeyRotationStatus, ParticipationKind,
+    PendingDeposit, RotationProgress, Staker, StakerBan, StakerEarnings, StakerStatement,
+    StakerStatus, StakerUtxo, UtxoBreakdown, WorkForecast,
+};
+pub use stats::{NetworkStats, PoolLuck, RewardOutlook, StakingSupply, Stats};
+pub use webhook::{DeadLetter, EndpointStatus, StakerWebhook, StakerWebhookEvent};
@@ -0,0 +1,207 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::{
+    bitcoin::{BlockHash, Txid},
+    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutMember {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    pub identity_address: Address,
+    #[serde(with = "as_sat")]
+    pub reward: Amount,
+    pub shares: Decimal,
+    // fee is in basis points: 5% should be entered as 0.05
+    #[serde(with = "as_sat")]
+    pub fee: Amount,
+    pub txid: Option<Txid>,
+    /// Unix timestamp (in seconds) of when the reward was credited, 0 if it wasn't stored yet.
+    #[serde(default)]
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of the last change, such as the reward being paid.
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl PayoutMember {
+    pub fn new(
+        currency_address: Address,
+        block_hash: BlockHash,
+        block_height: u64,
+        identity_address: Address,
+        reward: Amount,
+        shares: Decimal,
+        fee: Amount,
+    ) -> Self {
+        Self {
+            currency_address,
+            block_hash,
+            block_height,
+            identity_address,
+            reward,
+            shares,
+            fee,
+            txid: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+}
+
+/// The payout of a stake, without its members.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutSummary {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    /// The staked reward.
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// The shares of all the members of the round.
+    pub work: Decimal,
+    #[serde(with = "as_sat")]
+    pub fee: Amount,
+    /// The part of the reward that goes to the members.
+    #[serde(with = "as_sat")]
+    pub amount_paid: Amount,
+    pub n_members: u64,
+    /// Unix timestamp (in seconds) of when the payout was created.
+    pub created_at: u64,
+}
+
+/// A transaction that paid out the rewards of one or more payout members.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Payment {
+    pub currency_address: Address,
+    pub txid: Txid,
+    /// The sum of the rewards of all the payout members that were paid in this transaction.
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    pub n_members: u64,
+    /// The network fee of the transaction.
+    #[serde(with = "as_sat")]
+    pub tx_fee: Amount,
+    /// Whether the pool paid the network fee out of its fees. If not, the fee was deducted from
+    /// the amounts that were sent to the stakers.
+    pub tx_fee_paid_by_pool: bool,
+    pub status: PaymentStatus,
+    pub confirmations: u64,
+    /// Unix timestamp (in seconds) of when the payment was sent.
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of the last change, such as a new confirmation.
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl Payment {
+    pub fn new(currency_address: Address, txid: Txid, members: &[PayoutMember]) -> Self {
+        Self {
+            currency_address,
+            txid,
+            amount: members
+                .iter()
+                .fold(Amount::ZERO, |acc, member| acc + member.reward),
+            n_members: members.len() as u64,
+            tx_fee: Amount::ZERO,
+            tx_fee_paid_by_pool: false,
+            status: PaymentStatus::Pending,
+            confirmations: 0,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "sqlx",
+    derive(sqlx::Type),
+    sqlx(type_name = "payment_status", rename_all = "SCREAMING_SNAKE_CASE")
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    /// The payment was sent but has not reached the required number of confirmations.
+    Pending,
+    Confirmed,
+    /// The payment was evicted or double spent. Its payout members were reopened.
+    Failed,
+}
+
+/// A payment of the outstanding balance of a single staker, sent on request of an operator
+/// regardless of the min_payout of the staker and the payout schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualPayment {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    pub txid: Txid,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// The number of stakes of which the reward was paid.
+    pub n_rounds: u64,
+    pub operator: String,
+    pub reason: String,
+}
+
+/// The rewards that the pool owes its stakers: payout members that have not been paid yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Liabilities {
+    pub currency_address: Address,
+    #[serde(with = "as_sat")]
+    pub total: Amount,
+    /// The height of the oldest stake of which a reward is still unpaid.
+    pub oldest_unpaid_height: Option<u64>,
+    /// Unix timestamp (in seconds) of when the oldest unpaid reward was credited.
+    pub oldest_unpaid_at: Option<u64>,
+    /// Ordered by the unpaid amount, highest first.
+    pub stakers: Vec<StakerLiability>,
+}
+
+/// The unpaid rewards of a single staker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakerLiability {
+    pub identity_address: Address,
+    #[serde(with = "as_sat")]
+    pub unpaid: Amount,
+    /// The number of stakes of which the reward is unpaid.
+    pub n_rounds: u64,
+    pub oldest_unpaid_height: u64,
+    /// Unix timestamp (in seconds) of when the oldest unpaid reward was credited.
+    pub oldest_unpaid_at: u64,
+}
+
+impl Liabilities {
+    pub fn new(currency_address: Address, stakers: Vec<StakerLiability>) -> Self {
+        Self {
+            currency_address,
+            total: stakers
+                .iter()
+                .fold(Amount::ZERO, |acc, staker| acc + staker.unpaid),
+            oldest_unpaid_height: stakers
+                .iter()
+                .map(|staker| staker.oldest_unpaid_height)
+                .min(),
+            oldest_unpaid_at: stakers.iter().map(|staker| staker.oldest_unpaid_at).min(),
+            stakers,
+        }
+    }
+}
+
+/// The reward of a staker in the payout of a stake, next to the reward that the shadow payout
+/// scheme calculated for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutShadowDiff {
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    pub identity_address: Address,
+    /// `None` if the staker is not in the payout.
+    #[serde(with = "as_sat::opt")]
+    pub reward: Option<Amount>,
+    /// `None` if the staker is not in the shadow payout.
+    #[serde(with = "as_sat::opt")]
+    pub shadow_reward: Option<Amount>,
+    /// The shadow reward minus the reward, in sats.
+    pub difference: i64,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::json::vrsc::Address;
+
+/// A message that a staker signs with its VerusID (`signmessage`) to log in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub message: String,
+    /// Unix timestamp (in seconds) after which the challenge can no longer be used.
+    pub expires_at: u64,
+}
+
+/// Authorizes the self-service endpoints of a single VerusID, as an `Authorization: Bearer`
+/// header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionToken {
+    pub token: String,
+    pub currency_address: Address,
+    pub identity_address: Address,
+    /// Unix timestamp (in seconds) after which the token can no longer be used.
+    pub expires_at: u64,
+}
+
+/// A key that authorizes the self-service endpoints of a single VerusID like a session token,
+/// but doesn't expire. The key itself is only returned when it is created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: u64,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// Unix timestamp (in seconds) of when the key was created.
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of when the key was last used.
+    pub last_used_at: Option<u64>,
+}
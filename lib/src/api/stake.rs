@@ -0,0 +1,129 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::{
+    bitcoin::{BlockHash, Txid},
+    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stake {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    pub found_by: Address,
+    pub source_txid: Txid,
+    pub source_vout_num: u16,
+    #[serde(with = "as_sat")]
+    pub source_amount: Amount,
+    pub status: StakeStatus,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// Unix timestamp (in seconds) of when the stake was found, 0 if it wasn't stored yet.
+    #[serde(default)]
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of the last change to the stake, such as its maturing.
+    #[serde(default)]
+    pub updated_at: u64,
+}
+
+impl Stake {
+    pub fn new(
+        currency_address: &Address,
+        block_hash: &BlockHash,
+        block_height: u64,
+        found_by: &Address,
+        source_txid: Txid,
+        source_vout_num: u16,
+        source_amount: Amount,
+        status: StakeStatus,
+        amount: Amount,
+    ) -> Self {
+        Self {
+            currency_address: currency_address.clone(),
+            block_hash: *block_hash,
+            block_height,
+            found_by: found_by.clone(),
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status,
+            amount,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "sqlx",
+    derive(sqlx::Type),
+    sqlx(type_name = "stake_status", rename_all = "SCREAMING_SNAKE_CASE")
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeStatus {
+    Maturing,
+    Matured,
+    Stale,
+    StakeGuard,
+    /// Imported from the chain by a backfill. Imported stakes are not followed while they
+    /// mature and get no payout from the payout service.
+    Imported,
+}
+
+/// The work between two stakes of the pool, which is paid out with the stake that closed it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Round {
+    pub id: u64,
+    pub currency_address: Address,
+    /// The stake that closed the round, `None` if the stake is unknown.
+    pub block_hash: Option<BlockHash>,
+    pub block_height: u64,
+}
+
+/// A stake of the pool that went stale, with the work of its round that was moved back to round
+/// 0 to count towards the next stake of the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaleStake {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    pub found_by: Address,
+    pub redistributed: Vec<RedistributedShares>,
+    /// Unix timestamp (in seconds) of when the stake went stale.
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedistributedShares {
+    pub identity_address: Address,
+    pub shares: Decimal,
+}
+
+/// The evidence that a staker staked a competing block with the UTXO of a stake of the pool,
+/// which got the coinbase of the stake spent by StakeGuard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FraudEvidence {
+    pub currency_address: Address,
+    /// The stake of the pool of which the coinbase was spent.
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    /// The staker that staked the block.
+    pub identity_address: Address,
+    pub source_txid: Txid,
+    pub source_vout_num: u16,
+    /// The StakeGuard spend of the coinbase.
+    pub spend_txid: Txid,
+    pub spend_block_hash: Option<BlockHash>,
+    /// The serialized StakeGuard spend, in hex.
+    pub spend_tx: String,
+    /// The data of the OP_RETURN output of the spend, in hex, which holds the competing stake.
+    pub proof: Option<String>,
+    /// The competing stake transaction and the UTXO it staked, if they could be decoded from
+    /// the proof. The UTXO is the same as the one of the stake of the pool.
+    pub competing_txid: Option<Txid>,
+    pub competing_source_txid: Option<Txid>,
+    pub competing_source_vout_num: Option<u32>,
+    /// Unix timestamp (in seconds) of when the evidence was collected.
+    pub created_at: u64,
+}
@@ -0,0 +1,418 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::{
+    bitcoin::Txid,
+    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+};
+
+use super::PayoutMember;
+
+/// Represents a participant in the staking pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct Staker {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    pub identity_name: String,
+    /// The amount in sats that is used to determine when to pay out the rewards of this staker.
+    /// Once the accumulated rewards are higher than this threshold, a payout will be done.
+    #[serde(with = "as_sat")]
+    pub min_payout: Amount,
+    /// Can be one of ["active", "cooling_down", "inactive", "expired"]
+    /// A staker is **active** when the VerusID fulfills all the requirements as set in the
+    /// VerusVaultConditions of this pool.
+    /// A staker is **cooling down** when it updated its VerusID in the last 150 blocks.
+    /// VerusIDs that were updated in the last 150 blocks are ineligible to stake.
+    /// A staker that is **inactive** is a VerusID that was active before but updated its
+    /// VerusID which made it ineligible according to the VerusVaultConditions.
+    /// A staker is **expired** when it was cooling down for longer than the pool allows. It
+    /// becomes cooling down again once its VerusID is updated.
+    pub status: StakerStatus,
+    /// The fee percentage that is used to determine how much fee is kept by the staking pool,
+    /// when doing a payout. It is expressed as basis points, so 1% should be expressed as 0.01,
+    /// 0.3% as 0.003, etc.
+    pub fee: Decimal,
+    /// How this staker takes part in the pool.
+    #[serde(default)]
+    pub participation_kind: ParticipationKind,
+    /// The address that the rewards of a cold staker are paid to. VerusIDs are paid to
+    /// themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_address: Option<Address>,
+    /// How the work of this staker changes once its new deposits become eligible. Only set by
+    /// the staker status endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub forecast: Option<WorkForecast>,
+    /// Unix timestamp (in seconds) of when the staker was first stored, 0 if it wasn't yet.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of the last change to the staker.
+    #[serde(default)]
+    #[cfg_attr(feature = "sqlx", sqlx(skip))]
+    pub updated_at: u64,
+}
+
+impl Staker {
+    pub fn new(
+        currency_address: Address,
+        identity_address: Address,
+        identity_name: String,
+        min_payout: Amount,
+        status: StakerStatus,
+        fee: Decimal,
+    ) -> Self {
+        Self {
+            currency_address,
+            identity_address,
+            identity_name,
+            min_payout,
+            status,
+            fee,
+            participation_kind: ParticipationKind::Vault,
+            payout_address: None,
+            forecast: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    /// A staker that delegates the staking of the funds of `address`, of which the rewards are
+    /// paid to `payout_address`.
+    pub fn cold_staker(
+        currency_address: Address,
+        address: Address,
+        payout_address: Address,
+        min_payout: Amount,
+        status: StakerStatus,
+        fee: Decimal,
+    ) -> Self {
+        Self {
+            identity_name: address.to_string(),
+            participation_kind: ParticipationKind::ColdStaking,
+            payout_address: Some(payout_address),
+            ..Self::new(
+                currency_address,
+                address,
+                String::new(),
+                min_payout,
+                status,
+                fee,
+            )
+        }
+    }
+}
+
+/// How a staker takes part in the pool.
+#[derive(Debug, Default, Deserialize, Clone, Copy, Serialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "sqlx",
+    derive(sqlx::Type),
+    sqlx(type_name = "participation_kind", rename_all = "SCREAMING_SNAKE_CASE")
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipationKind {
+    /// The funds are in a VerusID that adheres to the VerusVaultConditions of the pool.
+    #[default]
+    Vault,
+    /// The staker signed a message with a plain address to let the pool stake its funds. The
+    /// pool follows the balance of the address, and pays the rewards to a payout address.
+    ColdStaking,
+}
+
+/// A UTXO of a staker that does not have the 150 confirmations yet that it needs to stake.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingDeposit {
+    pub address: Address,
+    pub txid: Txid,
+    pub vout: u32,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// The block height at which the deposit was mined.
+    pub height: u64,
+    /// The block height from which the deposit counts towards the work of the staker.
+    pub eligible_at_height: u64,
+}
+
+/// How the share of a staker in the work of the current round changes once its pending
+/// deposits become eligible.
+///
+/// Shares are fractions, so 0.25 means a quarter of the work. The forecast assumes that the
+/// eligible balance of the rest of the pool stays the same.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkForecast {
+    /// The block height the forecast was made at.
+    pub height: u64,
+    pub pending_deposits: Vec<PendingDeposit>,
+    /// The share of the staker in the work that was added in the current round so far.
+    pub round_share: Decimal,
+    /// The share of the staker in the work that is added with every block right now.
+    pub block_share: Decimal,
+    /// The share of the staker in the work that is added with every block, once all pending
+    /// deposits are eligible.
+    pub forecast_block_share: Decimal,
+}
+
+/// A UTXO of a staker, or of an address that lets the pool stake its funds for the staker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakerUtxo {
+    pub address: Address,
+    pub txid: Txid,
+    pub vout: u32,
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    pub confirmations: u64,
+    /// Whether the UTXO counts towards the work of the staker right now: it has the 150
+    /// confirmations it needs to stake, and the staker is active.
+    pub eligible: bool,
+}
+
+/// The UTXOs of a staker, with the part of its balance that counts towards its work.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoBreakdown {
+    pub identity_address: Address,
+    /// Only the UTXOs of an active staker count towards its work.
+    pub status: StakerStatus,
+    /// The block height the breakdown was made at.
+    pub height: u64,
+    /// The UTXOs, the ones with the most confirmations first.
+    pub utxos: Vec<StakerUtxo>,
+    #[serde(with = "as_sat")]
+    pub eligible_balance: Amount,
+    #[serde(with = "as_sat")]
+    pub ineligible_balance: Amount,
+}
+
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "sqlx",
+    derive(sqlx::Type),
+    sqlx(type_name = "staker_status", rename_all = "SCREAMING_SNAKE_CASE")
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StakerStatus {
+    Active,
+    CoolingDown,
+    Inactive,
+    Expired,
+    /// Caught by StakeGuard or banned by an operator. A banned staker doesn't get work, and isn't
+    /// paid anymore.
+    Banned,
+}
+
+impl TryFrom<String> for StakerStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_ref() {
+            "ACTIVE" => Ok(Self::Active),
+            "COOLING_DOWN" => Ok(Self::CoolingDown),
+            "INACTIVE" => Ok(Self::Inactive),
+            "EXPIRED" => Ok(Self::Expired),
+            "BANNED" => Ok(Self::Banned),
+            other => Err(format!("Unexpected StakerStatus: {other}")),
+        }
+    }
+}
+
+/// The final statement of a staker that left the pool and is owed nothing anymore.
+///
+/// It summarizes the entire history of the staker in the pool. A staker that comes back and
+/// leaves again gets a new statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakerStatement {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    pub identity_name: String,
+    /// Unix timestamp (in seconds) of when the staker joined the pool.
+    pub joined_at: u64,
+    /// Unix timestamp (in seconds) of when the staker became inactive.
+    pub left_at: u64,
+    pub staked_time_in_secs: u64,
+    /// The number of stakes of the pool that the staker got a reward of.
+    pub n_rounds: u64,
+    #[serde(with = "as_sat")]
+    pub rewards: Amount,
+    /// The fees that the pool kept of the rewards of this staker.
+    #[serde(with = "as_sat")]
+    pub fees: Amount,
+    /// The unpaid rewards that were too small to be paid when the staker left, which the pool
+    /// kept.
+    #[serde(with = "as_sat")]
+    pub forfeited: Amount,
+    /// Unix timestamp (in seconds) of when the statement was made.
+    pub created_at: u64,
+}
+
+/// Tracks whether a staker updated its VerusID to include the new pool primary address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationProgress {
+    pub identity_address: Address,
+    pub new_address: Address,
+    /// The VerusID includes the new pool primary address.
+    pub migrated: bool,
+    /// The staker was notified to update its VerusID.
+    pub notified: bool,
+}
+
+/// A rotation of the pool primary address that an operator started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub currency_address: Address,
+    pub old_address: Address,
+    pub new_address: Address,
+    /// Unix timestamp (in seconds) at which the transition window ends. Until then, VerusIDs
+    /// that include either address are eligible.
+    pub ends_at: u64,
+    /// Who started the rotation.
+    pub operator: String,
+    /// Unix timestamp (in seconds) of when the old address was retired, `None` during the
+    /// transition.
+    pub retired_at: Option<u64>,
+    /// Who retired the old address.
+    pub retired_by: Option<String>,
+    /// Unix timestamp (in seconds) of when the rotation was started.
+    pub created_at: u64,
+}
+
+/// The pool primary address of a currency and, during a rotation, whether every staker updated
+/// its VerusID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotationStatus {
+    pub currency_address: Address,
+    /// The pool primary address, or the old address during a rotation.
+    pub primary_address: Address,
+    /// The address that is rotated to, `None` if no rotation is in progress.
+    pub new_address: Option<Address>,
+    /// Unix timestamp (in seconds) at which the transition window ends.
+    pub ends_at: Option<u64>,
+    pub stakers: Vec<RotationProgress>,
+}
+
+/// An address of which the funds are staked by the pool on behalf of a staker, without the funds
+/// being moved into the VerusID of the staker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegatedAddress {
+    pub identity_address: Address,
+    pub address: Address,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StakerEarnings {
+    #[serde(with = "as_sat")]
+    pub paid: Amount, // payoutmembers with a txid
+    #[serde(with = "as_sat")]
+    pub pending: Amount, // payoutmembers without a txid
+}
+
+impl From<PayoutMember> for StakerEarnings {
+    fn from(value: PayoutMember) -> Self {
+        if value.txid.is_some() {
+            StakerEarnings {
+                paid: value.reward,
+                pending: Amount::ZERO,
+            }
+        } else {
+            StakerEarnings {
+                paid: Amount::ZERO,
+                pending: value.reward,
+            }
+        }
+    }
+}
+
+/// The length of the periods of an earnings history.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EarningsGranularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl EarningsGranularity {
+    /// The name of the period in `date_trunc`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+}
+
+/// The rewards that a staker was credited in a period of its earnings history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EarningsPeriod {
+    /// Unix timestamp (in seconds) of the start of the period, in UTC.
+    pub period_start: u64,
+    #[serde(with = "as_sat")]
+    pub rewards: Amount,
+    #[serde(with = "as_sat")]
+    pub fees: Amount,
+    /// The number of stakes of which the staker got a reward.
+    pub n_stakes: u64,
+}
+
+/// A fee that an admin set for a staker, which replaces the fee the staker signed up with and
+/// the fee tiers of the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeOverride {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    /// `None` if the override was removed.
+    pub fee: Option<Decimal>,
+    pub reason: Option<String>,
+}
+
+/// A staker that an operator banned. The staker forfeited its work in the current round and the
+/// rewards that were not paid yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakerBan {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    #[serde(with = "as_sat")]
+    pub forfeited: Amount,
+    pub operator: String,
+    pub reason: String,
+}
+
+/// Whether a VerusID can join the pool, with the outcome of every condition of the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eligibility {
+    pub identity_address: Address,
+    pub eligible: bool,
+    /// The checks in the order in which the pool evaluates them.
+    pub checks: Vec<EligibilityCheck>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EligibilityCheck {
+    pub condition: EligibilityCondition,
+    pub passed: bool,
+    /// Explains the outcome, for example the timelock that was found.
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EligibilityCondition {
+    /// On chains with IDSTAKING, the VerusID must be a root ID of the chain.
+    RootIdentity,
+    /// A multisig VerusID does not stake.
+    MinimumSignatures,
+    /// There must be at least 2 primary addresses.
+    PrimaryAddresses,
+    /// One of the primary addresses must be the pool primary address.
+    PoolPrimaryAddress,
+    MaxPrimaryAddresses,
+    /// The revoke and recover authorities must be different from the VerusID itself.
+    RevocationAuthority,
+    RecoveryAuthority,
+    TimeLock,
+    /// A VerusID that doesn't meet the conditions is still eligible if it lets the pool stake
+    /// the funds of one of its primary addresses.
+    DelegatedStaking,
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::bitcoin::BlockHash;
+use vrsc_rpc::json::vrsc::util::amount::serde::as_sat;
+use vrsc_rpc::json::vrsc::Amount;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct StakingSupply {
+    pub staker: f64,
+    pub pool: f64,
+    pub network: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Stats {
+    pub stakes: i64,
+    #[serde(with = "as_sat")]
+    pub pool_staking_supply: Amount,
+    #[serde(with = "as_sat")]
+    pub paid: Amount,
+    pub stakers: i64,
+    /// The luck of the pool over the last 30 days. `None` until the pool stored network
+    /// snapshots for that period.
+    #[serde(default)]
+    pub luck: Option<PoolLuck>,
+}
+
+/// How many stakes the pool found, compared to the stakes it would find on average with its
+/// share of the network staking supply.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PoolLuck {
+    /// The first block of the period.
+    pub from_height: u64,
+    /// The last block of the period.
+    pub to_height: u64,
+    /// The sum of the pool's share of the network staking supply over the PoS blocks of the
+    /// period.
+    pub expected_stakes: f64,
+    /// The stakes the pool found in the period, without stale stakes.
+    pub actual_stakes: u64,
+    /// The actual stakes as a percentage of the expected stakes. Above 100 the pool was lucky.
+    pub luck: Option<f64>,
+    /// The average time between two stakes of the pool in the period.
+    pub mean_time_between_stakes_in_secs: Option<u64>,
+    /// The average time between two stakes at the pool's share of the staking supply.
+    pub expected_time_between_stakes_in_secs: Option<u64>,
+}
+
+/// The expected staking rewards, taking the block reward schedule of the chain into account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RewardOutlook {
+    pub block_height: u64,
+    #[serde(with = "as_sat")]
+    pub block_reward: Amount,
+    /// The height at which the block reward drops next.
+    pub next_halving_height: Option<u64>,
+    #[serde(with = "as_sat::opt")]
+    pub next_block_reward: Option<Amount>,
+    /// The yearly return of staking on the network at the current block reward.
+    pub network_apy: f64,
+    /// The yearly return of staking on the network after the next halving.
+    pub network_apy_after_halving: Option<f64>,
+    /// The yearly return of this pool over the last 30 days, with the rewards of stakes that
+    /// were found before a halving scaled to the current block reward.
+    pub pool_apy: Option<f64>,
+}
+
+/// The network conditions at a certain block height, as recorded by the network stats collector.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NetworkStats {
+    pub block_height: u64,
+    pub block_hash: BlockHash,
+    pub staking_supply: f64,
+    pub difficulty: f64,
+    /// The ratio of PoS blocks over the last 100 recorded blocks, including this one.
+    pub pos_ratio: f64,
+}
@@ -0,0 +1,94 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// The delivery statistics of a webhook endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub endpoint: Url,
+    /// The number of messages that are being sent or wait to be sent to this endpoint.
+    pub queued: u64,
+    pub delivered: u64,
+    pub failed: u64,
+    /// `None` when nothing was sent to this endpoint yet.
+    pub success_rate: Option<f64>,
+    pub average_latency_ms: Option<f64>,
+    pub last_latency_ms: Option<u64>,
+    pub consecutive_failures: u64,
+    /// An endpoint is unhealthy after 3 consecutive failed deliveries.
+    pub healthy: bool,
+    pub last_error: Option<String>,
+}
+
+/// A webhook message that could not be delivered after all retries, and is no longer retried
+/// until it is redriven.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub endpoint: Url,
+    /// The body of the message, as json.
+    pub body: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Unix timestamp (in seconds) of when the message was first stored.
+    pub created_at: u64,
+    /// Unix timestamp (in seconds) of when the message was dead-lettered.
+    pub dead_at: u64,
+}
+
+/// The events about its own VerusID that a staker can receive on its webhook endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StakerWebhookEvent {
+    /// The staker found a stake for the pool.
+    StakeFound,
+    /// A stake that the staker found matured.
+    StakeMatured,
+    /// A payment to the staker was sent.
+    PaymentSent,
+    /// The staker joined, left, expired, was banned or departed, or needs to add the new primary
+    /// address of the pool.
+    StakerStatus,
+}
+
+impl StakerWebhookEvent {
+    pub const ALL: [Self; 4] = [
+        Self::StakeFound,
+        Self::StakeMatured,
+        Self::PaymentSent,
+        Self::StakerStatus,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StakeFound => "stake_found",
+            Self::StakeMatured => "stake_matured",
+            Self::PaymentSent => "payment_sent",
+            Self::StakerStatus => "staker_status",
+        }
+    }
+}
+
+impl FromStr for StakerWebhookEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == s)
+            .ok_or_else(|| format!("unknown staker webhook event `{s}`"))
+    }
+}
+
+/// The endpoint that a staker registered to receive the events about its VerusID.
+///
+/// With a secret, every payload is signed with it, as described at `signature::sign` in the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakerWebhook {
+    pub endpoint: Url,
+    pub events: Vec<StakerWebhookEvent>,
+    /// Only returned when the endpoint is registered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// A part of a reply that is too large to be sent as a single message.
+///
+/// Large result sets are split into chunks that are sent in sequence. The receiver collects
+/// chunks until it receives the chunk where `last` is set, which acts as the terminator.
+/// An empty result set is sent as a single terminating chunk without items.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplyChunk<T> {
+    /// The position of this chunk in the reply, starting at 0.
+    pub seq: u32,
+    /// Set on the final chunk of a reply.
+    pub last: bool,
+    pub items: Vec<T>,
+}
+
+/// Splits `items` into chunks of at most `chunk_size` items.
+///
+/// Always returns at least one chunk, the last of which has `last` set.
+pub fn into_chunks<T>(items: Vec<T>, chunk_size: usize) -> Vec<ReplyChunk<T>> {
+    let chunk_size = chunk_size.max(1);
+    let mut chunks = vec![];
+    let mut items = items.into_iter().peekable();
+
+    loop {
+        let chunk = items.by_ref().take(chunk_size).collect::<Vec<_>>();
+        let last = items.peek().is_none();
+
+        chunks.push(ReplyChunk {
+            seq: chunks.len() as u32,
+            last,
+            items: chunk,
+        });
+
+        if last {
+            break;
+        }
+    }
+
+    chunks
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChunkError {
+    /// A chunk was received out of order, or a chunk was lost.
+    UnexpectedSequence { expected: u32, received: u32 },
+    /// A chunk was received after the terminating chunk.
+    AlreadyComplete,
+}
+
+impl Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkError::UnexpectedSequence { expected, received } => {
+                write!(f, "expected chunk {expected}, received chunk {received}")
+            }
+            ChunkError::AlreadyComplete => write!(f, "reply was already complete"),
+        }
+    }
+}
+
+impl std::error::Error for ChunkError {}
+
+/// Reassembles a chunked reply on the receiving side.
+#[derive(Debug)]
+pub struct ChunkAssembler<T> {
+    next_seq: u32,
+    complete: bool,
+    items: Vec<T>,
+}
+
+impl<T> Default for ChunkAssembler<T> {
+    fn default() -> Self {
+        Self {
+            next_seq: 0,
+            complete: false,
+            items: vec![],
+        }
+    }
+}
+
+impl<T> ChunkAssembler<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a chunk to the reply. Returns true when the terminating chunk was received.
+    pub fn push(&mut self, chunk: ReplyChunk<T>) -> Result<bool, ChunkError> {
+        if self.complete {
+            return Err(ChunkError::AlreadyComplete);
+        }
+
+        if chunk.seq != self.next_seq {
+            return Err(ChunkError::UnexpectedSequence {
+                expected: self.next_seq,
+                received: chunk.seq,
+            });
+        }
+
+        self.next_seq += 1;
+        self.complete = chunk.last;
+        self.items.extend(chunk.items);
+
+        Ok(self.complete)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Returns the reassembled items, or `None` if the terminating chunk was not received yet.
+    pub fn finish(self) -> Option<Vec<T>> {
+        self.complete.then_some(self.items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_reply_is_a_single_terminator() {
+        let chunks = into_chunks(Vec::<u32>::new(), 10);
+
+        assert_eq!(
+            chunks,
+            vec![ReplyChunk {
+                seq: 0,
+                last: true,
+                items: vec![]
+            }]
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let items = (0..25).collect::<Vec<u32>>();
+        let chunks = into_chunks(items.clone(), 10);
+
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.last().unwrap().last);
+
+        let mut assembler = ChunkAssembler::new();
+        for chunk in chunks {
+            let serialized = serde_json::to_string(&chunk).unwrap();
+            assembler
+                .push(serde_json::from_str(&serialized).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(assembler.finish(), Some(items));
+    }
+
+    #[test]
+    fn out_of_order_chunk() {
+        let mut chunks = into_chunks((0..25).collect::<Vec<u32>>(), 10);
+        let mut assembler = ChunkAssembler::new();
+
+        assembler.push(chunks.remove(0)).unwrap();
+
+        assert_eq!(
+            assembler.push(chunks.remove(1)),
+            Err(ChunkError::UnexpectedSequence {
+                expected: 1,
+                received: 2
+            })
+        );
+    }
+}
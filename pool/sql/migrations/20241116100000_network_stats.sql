@@ -0,0 +1,13 @@
+CREATE TABLE network_stats (
+    currency_address TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    block_hash TEXT NOT NULL,
+    staking_supply DOUBLE PRECISION NOT NULL,
+    difficulty DOUBLE PRECISION NOT NULL,
+    is_stake BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(currency_address, block_height)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON network_stats FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
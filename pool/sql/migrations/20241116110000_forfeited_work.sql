@@ -0,0 +1,21 @@
+CREATE TABLE forfeited_work (
+    currency_address TEXT NOT NULL,
+    round BIGINT NOT NULL,
+    staker_address TEXT NOT NULL,
+    shares DECIMAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, round, staker_address)
+);
+
+CREATE TABLE scheduled_forfeits (
+    currency_address TEXT NOT NULL,
+    staker_address TEXT NOT NULL,
+    forfeit_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, staker_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON forfeited_work FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON scheduled_forfeits FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,12 @@
+CREATE TABLE primary_address_rotation (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    new_address TEXT NOT NULL,
+    migrated BOOLEAN NOT NULL DEFAULT FALSE,
+    notified BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address, new_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON primary_address_rotation FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
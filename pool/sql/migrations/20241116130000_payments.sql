@@ -0,0 +1,19 @@
+CREATE TYPE payment_status AS ENUM (
+    'PENDING',
+    'CONFIRMED',
+    'FAILED'
+);
+
+CREATE TABLE payments (
+    currency_address TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    n_members BIGINT NOT NULL,
+    status payment_status NOT NULL,
+    confirmations BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, txid)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payments FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,12 @@
+CREATE TABLE delegated_addresses (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    address TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, address)
+);
+
+CREATE INDEX delegated_addresses_identity_address_idx ON delegated_addresses (currency_address, identity_address);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON delegated_addresses FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,13 @@
+CREATE TABLE payment_items (
+    currency_address TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    -- the block hashes of the payout members of this staker that are paid by this item
+    block_hashes TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, txid, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_items FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,16 @@
+CREATE TYPE accounting_entry_kind AS ENUM (
+    'STAKE_INCOME',
+    'PAYMENT'
+);
+
+CREATE TABLE accounting_exports (
+    currency_address TEXT NOT NULL,
+    kind accounting_entry_kind NOT NULL,
+    -- the block hash of a stake, or the txid of a payment
+    reference TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, kind, reference)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON accounting_exports FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
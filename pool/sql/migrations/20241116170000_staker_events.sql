@@ -0,0 +1,39 @@
+CREATE TYPE staker_activity_kind AS ENUM (
+    'STATUS_CHANGED',
+    'WORK_COUNTED',
+    'STAKE_FOUND',
+    'PAYOUT_CREDITED',
+    'PAYMENT_RECEIVED'
+);
+
+-- the status changes of stakers, the other activity of a staker is derived from existing tables
+CREATE TABLE staker_events (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    status staker_status NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX staker_events_identity_idx ON staker_events (currency_address, identity_address);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON staker_events FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE OR REPLACE FUNCTION trigger_record_staker_status()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' OR NEW.status IS DISTINCT FROM OLD.status THEN
+        INSERT INTO staker_events (currency_address, identity_address, status)
+        VALUES (NEW.currency_address, NEW.identity_address, NEW.status);
+    END IF;
+    RETURN NEW;
+END;
+
+$$ language 'plpgsql';
+
+CREATE TRIGGER record_staker_status AFTER INSERT OR UPDATE ON stakers FOR EACH ROW EXECUTE PROCEDURE trigger_record_staker_status();
+
+-- stakers that existed before status changes were recorded start with their current status
+INSERT INTO staker_events (currency_address, identity_address, status, created_at, updated_at)
+SELECT currency_address, identity_address, status, updated_at, updated_at FROM stakers;
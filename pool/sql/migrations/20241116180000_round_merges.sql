@@ -0,0 +1,13 @@
+-- an audit trail of the rounds that were merged by an operator
+CREATE TABLE round_merges (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    from_round BIGINT NOT NULL,
+    into_round BIGINT NOT NULL,
+    -- the changes to the work and rewards of every staker, as json
+    changes TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON round_merges FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,55 @@
+-- work used to be assigned to the height of the stake that closed its round. Two stakes at the
+-- same height (after a reorg) or stakes that are processed out of order while catching up would
+-- then share a round. Rounds now get their own id, which maps to the stake that closed it.
+CREATE TABLE rounds (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    -- the stake that closed the round, NULL for rounds of which the stake is unknown
+    block_hash TEXT,
+    block_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (currency_address, block_hash)
+);
+
+CREATE INDEX rounds_block_height_idx ON rounds (currency_address, block_height);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON rounds FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- every stake that can still be paid out gets a round, even if no work was assigned to it
+INSERT INTO rounds (currency_address, block_hash, block_height)
+SELECT currency_address, block_hash, block_height
+FROM stakes
+WHERE status != 'STALE'
+ORDER BY block_height ASC;
+
+-- work that is assigned to a height without a stake keeps its height, so that it can be merged
+INSERT INTO rounds (currency_address, block_height)
+SELECT DISTINCT w.currency_address, w.round
+FROM (
+    SELECT currency_address, round FROM work
+    UNION
+    SELECT currency_address, round FROM forfeited_work
+) w
+WHERE w.round <> 0
+    AND NOT EXISTS (
+        SELECT 1 FROM rounds r
+        WHERE r.currency_address = w.currency_address AND r.block_height = w.round
+    );
+
+ALTER TABLE work RENAME COLUMN round TO round_id;
+ALTER TABLE forfeited_work RENAME COLUMN round TO round_id;
+
+-- round 0 is still the open round. Ids are negated first, so that an id that equals the height
+-- of another round doesn't violate the primary key halfway through the update.
+UPDATE work w SET round_id = -r.id
+FROM rounds r
+WHERE r.currency_address = w.currency_address AND r.block_height = w.round_id AND w.round_id <> 0;
+
+UPDATE work SET round_id = -round_id WHERE round_id < 0;
+
+UPDATE forfeited_work w SET round_id = -r.id
+FROM rounds r
+WHERE r.currency_address = w.currency_address AND r.block_height = w.round_id AND w.round_id <> 0;
+
+UPDATE forfeited_work SET round_id = -round_id WHERE round_id < 0;
@@ -0,0 +1,2 @@
+-- stakers that stayed cooling down for too long are expired and no longer checked every block
+ALTER TYPE staker_status ADD VALUE 'EXPIRED';
@@ -0,0 +1,21 @@
+-- one record per processed block, to follow what the pool did with every block
+CREATE TABLE block_summaries (
+    currency_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    stakers_counted BIGINT NOT NULL,
+    shares_added DECIMAL NOT NULL,
+    identities_checked BIGINT NOT NULL,
+    stake_found BOOLEAN NOT NULL,
+    daemon_staking BOOLEAN NOT NULL,
+    -- the duration of every phase in milliseconds, as json
+    phases TEXT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, block_hash)
+);
+
+CREATE INDEX block_summaries_height_idx ON block_summaries (currency_address, block_height);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON block_summaries FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
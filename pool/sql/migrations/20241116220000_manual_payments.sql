@@ -0,0 +1,15 @@
+-- an audit trail of the payments that were sent to a single staker on request of an operator
+CREATE TABLE manual_payments (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    n_rounds BIGINT NOT NULL,
+    operator TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON manual_payments FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,26 @@
+-- addresses, next to the ones in the configuration, to which the pool is allowed to send funds.
+-- There is deliberately no API to change this table.
+CREATE TABLE destination_whitelist (
+    currency_address TEXT NOT NULL,
+    address TEXT NOT NULL,
+    -- who whitelisted the address, and why
+    note TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON destination_whitelist FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the pool fees that were moved to a cold wallet
+CREATE TABLE fee_sweeps (
+    currency_address TEXT NOT NULL,
+    txid TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, txid)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON fee_sweeps FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,12 @@
+-- webhook messages that did not fit in the queue of their endpoint, waiting to be sent
+CREATE TABLE webhook_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    -- the body of the message, as json
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON webhook_outbox FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,13 @@
+-- the size and duration of every payment to a batch of stakers, including the ones that failed
+CREATE TABLE payment_batches (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    -- NULL if the payment failed
+    txid TEXT,
+    size BIGINT NOT NULL,
+    latency_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_batches FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
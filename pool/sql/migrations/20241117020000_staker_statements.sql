@@ -0,0 +1,18 @@
+-- the final statements of stakers that left the pool and are owed nothing anymore
+CREATE TABLE staker_statements (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    identity_name TEXT NOT NULL,
+    joined_at TIMESTAMPTZ NOT NULL,
+    left_at TIMESTAMPTZ NOT NULL,
+    n_rounds BIGINT NOT NULL,
+    rewards BIGINT NOT NULL,
+    fees BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX staker_statements_identity_idx ON staker_statements (currency_address, identity_address);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON staker_statements FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
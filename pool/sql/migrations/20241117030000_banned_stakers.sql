@@ -0,0 +1,2 @@
+-- stakers that were caught by StakeGuard are banned from the pool
+ALTER TYPE staker_status ADD VALUE 'BANNED';
@@ -0,0 +1,13 @@
+-- every block height of which the work was added. When the daemon forks and blocks are
+-- reconsidered, the same height is processed again; its work is only added once and the
+-- revision counts how many times the height was processed.
+CREATE TABLE work_revisions (
+    currency_address TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    revision INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, block_height)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON work_revisions FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
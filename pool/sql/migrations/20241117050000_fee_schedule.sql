@@ -0,0 +1,36 @@
+-- fees that an admin set for a staker, instead of the fee the staker signed up with
+CREATE TABLE fee_overrides (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    fee DECIMAL NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON fee_overrides FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the fee that was applied to every payout member and why. A payout that is generated again
+-- adds new rows.
+CREATE TABLE fee_history (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    -- the fee the staker signed up with
+    staker_fee DECIMAL NOT NULL,
+    -- 'staker', 'override' or 'tier'
+    source TEXT NOT NULL,
+    -- the promotional discount that was subtracted
+    discount DECIMAL NOT NULL,
+    fee DECIMAL NOT NULL,
+    -- the average balance of the staker over the blocks of the payout, in sats
+    average_balance BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX fee_history_block_hash_idx ON fee_history (currency_address, block_hash);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON fee_history FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,12 @@
+-- the payout members that were in a payment batch, also when the payment failed and the
+-- members got no txid
+CREATE TABLE payment_batch_members (
+    payment_batch_id BIGINT NOT NULL REFERENCES payment_batches (id),
+    identity_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (payment_batch_id, identity_address, block_hash)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_batch_members FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,7 @@
+-- failed deliveries are retried from the outbox with a backoff, until they are dead-lettered
+ALTER TABLE webhook_outbox
+    ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN last_error TEXT,
+    -- set when the message is no longer retried, until an admin redrives it
+    ADD COLUMN dead_at TIMESTAMPTZ;
@@ -0,0 +1,37 @@
+-- the work of a round as it was when the stake that closed it was found. Work keeps moving
+-- between rounds after that, but a snapshot never changes, so the payout of a stake can always
+-- be recalculated from it. Forfeited work is stored per staker in rows of its own, without a fee.
+CREATE TABLE work_snapshots (
+    currency_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    forfeited BOOLEAN NOT NULL,
+    shares NUMERIC NOT NULL,
+    fee NUMERIC,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, block_hash, identity_address, forfeited)
+);
+
+CREATE OR REPLACE FUNCTION trigger_reject_snapshot_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'work snapshots cannot be changed';
+END;
+
+$$ language 'plpgsql';
+
+CREATE TRIGGER reject_snapshot_change BEFORE UPDATE OR DELETE ON work_snapshots FOR EACH ROW EXECUTE PROCEDURE trigger_reject_snapshot_change();
+
+-- rounds that were closed before snapshots were taken get a snapshot of their work as it is now
+INSERT INTO work_snapshots (currency_address, block_hash, identity_address, forfeited, shares, fee)
+SELECT r.currency_address, r.block_hash, w.staker_address, FALSE, w.shares, s.fee
+FROM work w
+JOIN rounds r ON r.id = w.round_id AND r.currency_address = w.currency_address
+JOIN stakers s ON s.identity_address = w.staker_address AND s.currency_address = w.currency_address
+WHERE r.block_hash IS NOT NULL;
+
+INSERT INTO work_snapshots (currency_address, block_hash, identity_address, forfeited, shares)
+SELECT r.currency_address, r.block_hash, f.staker_address, TRUE, f.shares
+FROM forfeited_work f
+JOIN rounds r ON r.id = f.round_id AND r.currency_address = f.currency_address
+WHERE r.block_hash IS NOT NULL;
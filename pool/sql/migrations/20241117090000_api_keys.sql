@@ -0,0 +1,29 @@
+-- keys that stakers create to use the API as their VerusID, without logging in every hour. Only
+-- the SHA-256 hash of a key is stored.
+CREATE TABLE api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX api_keys_identity_idx ON api_keys (currency_address, identity_address);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON api_keys FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the endpoint that a staker registered to receive the events about its own VerusID
+CREATE TABLE staker_webhooks (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON staker_webhooks FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
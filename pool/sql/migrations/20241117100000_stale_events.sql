@@ -0,0 +1,16 @@
+-- the work of a stale stake that was moved back to round 0, per staker, so that stakers can see
+-- where the shares of a stake that went stale ended up.
+CREATE TABLE stale_events (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    found_by TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    shares DECIMAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (currency_address, block_hash, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON stale_events FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,3 @@
+-- stakes that were imported from the chain by a backfill, for a pool that moved its history over
+-- from another system
+ALTER TYPE stake_status ADD VALUE 'IMPORTED';
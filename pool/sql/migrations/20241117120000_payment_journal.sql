@@ -0,0 +1,37 @@
+CREATE TYPE payment_journal_status AS ENUM (
+    'OPEN',
+    'SENT',
+    'FAILED'
+);
+
+-- the intent to pay a set of payout members, recorded before the payment is sent. An entry that
+-- is still open after a restart may have been sent, so it is reconciled against the daemon
+-- before new payments are sent.
+CREATE TABLE payment_journal (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    -- NULL until the daemon accepted the sendcurrency operation
+    opid TEXT,
+    status payment_journal_status NOT NULL DEFAULT 'OPEN',
+    -- NULL unless the payment was sent
+    txid TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX payment_journal_status_idx ON payment_journal (currency_address, status);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_journal FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the payout members in a journal entry, with the reward that the payment sends them
+CREATE TABLE payment_journal_members (
+    payment_journal_id BIGINT NOT NULL REFERENCES payment_journal (id),
+    identity_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    reward BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (payment_journal_id, identity_address, block_hash)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_journal_members FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
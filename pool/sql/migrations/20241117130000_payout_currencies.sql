@@ -0,0 +1,11 @@
+-- the currency that a staker wants its rewards to be converted into when they are paid
+CREATE TABLE payout_currencies (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    payout_currency TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payout_currencies FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
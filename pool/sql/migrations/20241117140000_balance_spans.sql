@@ -0,0 +1,16 @@
+-- the eligible balance of a staker, held from from_height up to and including to_height. A new
+-- span only starts when the balance changes, so the spans are the balance deltas of a staker.
+CREATE TABLE balance_spans (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    from_height BIGINT NOT NULL,
+    to_height BIGINT NOT NULL,
+    balance BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address, from_height)
+);
+
+CREATE INDEX balance_spans_to_height_idx ON balance_spans (currency_address, to_height);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON balance_spans FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,20 @@
+-- monthly rollups of the payout members of a staker that were paid by a confirmed payment
+CREATE TABLE payout_member_archive (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    month DATE NOT NULL,
+    n_rounds BIGINT NOT NULL,
+    shares DECIMAL NOT NULL,
+    reward BIGINT NOT NULL,
+    fee BIGINT NOT NULL,
+    first_block_height BIGINT NOT NULL,
+    last_block_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address, month)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payout_member_archive FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the payout members that still need to be paid
+CREATE INDEX payout_members_unpaid_idx ON payout_members (currency_address, identity_address) WHERE txid IS NULL;
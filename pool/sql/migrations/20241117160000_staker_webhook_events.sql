@@ -0,0 +1,5 @@
+-- the events that a staker receives on its webhook endpoint, and the secret that signs the
+-- payloads. Endpoints that were registered before receive all events, unsigned.
+ALTER TABLE staker_webhooks
+    ADD COLUMN events TEXT[] NOT NULL DEFAULT ARRAY['stake_found', 'stake_matured', 'payment_sent', 'staker_status'],
+    ADD COLUMN secret TEXT;
@@ -0,0 +1,10 @@
+-- the id of the last webhook message of a currency, so message ids keep increasing across
+-- restarts and receivers can detect gaps and replays
+CREATE TABLE webhook_event_ids (
+    currency_address TEXT PRIMARY KEY,
+    last_event_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON webhook_event_ids FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,20 @@
+CREATE TABLE fraud_evidence (
+    currency_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    identity_address TEXT NOT NULL,
+    source_txid TEXT NOT NULL,
+    source_vout_num INT NOT NULL,
+    spend_txid TEXT NOT NULL,
+    spend_block_hash TEXT,
+    spend_tx TEXT NOT NULL,
+    proof TEXT,
+    competing_txid TEXT,
+    competing_source_txid TEXT,
+    competing_source_vout_num BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(currency_address, block_hash)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON fraud_evidence FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
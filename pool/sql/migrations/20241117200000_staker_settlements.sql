@@ -0,0 +1,26 @@
+-- the final payout of a staker that became inactive: once `settle_at` has passed, its unpaid
+-- rewards are paid regardless of its min_payout, after which the staker is closed
+CREATE TABLE staker_settlements (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    settle_at TIMESTAMPTZ NOT NULL,
+    settled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON staker_settlements FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- stakers that were already inactive are settled right away, like they were before
+INSERT INTO staker_settlements (currency_address, identity_address, settle_at, settled_at)
+SELECT s.currency_address, s.identity_address, NOW(), st.created_at
+FROM stakers s
+LEFT JOIN LATERAL (
+    SELECT MAX(created_at) AS created_at
+    FROM staker_statements st
+    WHERE st.currency_address = s.currency_address
+        AND st.identity_address = s.identity_address
+        AND st.created_at >= s.updated_at
+) st ON TRUE
+WHERE s.status = 'INACTIVE';
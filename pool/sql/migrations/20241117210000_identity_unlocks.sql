@@ -0,0 +1,12 @@
+-- the height at which the VerusID of a staker unlocks, for VerusIDs that unlock at a fixed
+-- height, so the staker is checked again before its VerusID unlocks
+CREATE TABLE identity_unlocks (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    unlock_height BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON identity_unlocks FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,16 @@
+-- the payout members of a stake as the shadow payout scheme would have created them, to compare
+-- a candidate scheme against the payouts that are actually made before switching to it
+CREATE TABLE payout_shadow (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    shares DECIMAL NOT NULL,
+    reward BIGINT NOT NULL,
+    fee BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY(currency_address, identity_address, block_hash)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payout_shadow FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
@@ -0,0 +1,9 @@
+CREATE TYPE participation_kind AS ENUM (
+    'VAULT',
+    'COLD_STAKING'
+);
+
+-- cold stakers are plain addresses that let the pool stake their funds, and that are paid to
+-- the payout address they registered
+ALTER TABLE stakers ADD COLUMN participation_kind participation_kind NOT NULL DEFAULT 'VAULT';
+ALTER TABLE stakers ADD COLUMN payout_address TEXT;
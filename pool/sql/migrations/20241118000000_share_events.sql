@@ -0,0 +1,83 @@
+CREATE TYPE share_event_kind AS ENUM (
+    -- the work of a block
+    'WORK',
+    -- the work of a staker that was forfeited
+    'FORFEITED',
+    -- the work of a round that went back to the open round, like the round of a stale stake
+    'RESTORED',
+    -- work that was split off into the round of a stake that the pool missed
+    'SPLIT',
+    -- the work in the open round when the ledger was introduced
+    'CARRIED_OVER'
+);
+
+-- every change to the shares of the open round. Rows are never changed or removed: the shares
+-- of a staker in the open round are the sum of its events since the last round was closed.
+CREATE TABLE share_events (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    staker_address TEXT NOT NULL,
+    -- the block of the work, or the last block of which work was added when the event happened
+    block_height BIGINT NOT NULL,
+    kind share_event_kind NOT NULL,
+    delta NUMERIC NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- the work of a block is counted once, also when the block is processed again after a fork
+CREATE UNIQUE INDEX share_events_work_idx ON share_events (currency_address, staker_address, block_height) WHERE kind = 'WORK';
+
+-- the position in share_events up to which the work was assigned to a round when its stake was
+-- found. The open round holds the events after the last closure of a currency.
+CREATE TABLE round_closures (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    round_id BIGINT NOT NULL,
+    last_share_event_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX round_closures_last_share_event_id_idx ON round_closures (currency_address, last_share_event_id);
+
+CREATE OR REPLACE FUNCTION trigger_reject_ledger_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'the share ledger is append-only';
+END;
+
+$$ language 'plpgsql';
+
+CREATE TRIGGER reject_ledger_change BEFORE UPDATE OR DELETE ON share_events FOR EACH ROW EXECUTE PROCEDURE trigger_reject_ledger_change();
+CREATE TRIGGER reject_ledger_change BEFORE UPDATE OR DELETE ON round_closures FOR EACH ROW EXECUTE PROCEDURE trigger_reject_ledger_change();
+
+-- the shares of every staker in the open round
+CREATE VIEW open_work AS
+SELECT e.currency_address, e.staker_address, SUM(e.delta) AS shares
+FROM share_events e
+WHERE e.id > COALESCE(
+    (
+        SELECT MAX(c.last_share_event_id) FROM round_closures c
+        WHERE c.currency_address = e.currency_address
+    ),
+    0
+)
+GROUP BY e.currency_address, e.staker_address
+HAVING SUM(e.delta) <> 0;
+
+-- the open round used to be round 0 in the work table, which was updated in place
+INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+SELECT
+    w.currency_address,
+    w.staker_address,
+    COALESCE(
+        (SELECT MAX(r.block_height) FROM work_revisions r WHERE r.currency_address = w.currency_address),
+        0
+    ),
+    'CARRIED_OVER',
+    w.shares
+FROM work w
+WHERE w.round_id = 0 AND w.shares <> 0;
+
+DELETE FROM work WHERE round_id = 0;
+
+ALTER TABLE work ADD CONSTRAINT work_closed_round CHECK (round_id <> 0);
@@ -0,0 +1,11 @@
+-- the network fee of a payment, and whether the pool paid it out of its fees instead of the
+-- stakers
+ALTER TABLE payments
+    ADD COLUMN tx_fee BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN tx_fee_paid_by_pool BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- who bears the fee decides the amounts that a journal entry sends, which are needed to find
+-- its payment in the wallet
+ALTER TABLE payment_journal
+    ADD COLUMN tx_fee BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN tx_fee_paid_by_pool BOOLEAN NOT NULL DEFAULT FALSE;
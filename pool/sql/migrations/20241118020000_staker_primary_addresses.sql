@@ -0,0 +1,16 @@
+-- the pool primary address that the VerusID of a staker includes, to follow a rotation of the
+-- pool primary address per staker
+CREATE TABLE staker_primary_addresses (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    primary_address TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON staker_primary_addresses FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the pool primary address with which the pool signed the stake of a payout, NULL if the pool
+-- staked it itself or the address was not known
+ALTER TABLE payouts ADD COLUMN primary_address TEXT;
@@ -0,0 +1,21 @@
+-- the rotations of the pool primary address that an operator started. A rotation is in its
+-- transition window until the old address is retired
+CREATE TABLE key_rotations (
+    id BIGSERIAL PRIMARY KEY,
+    currency_address TEXT NOT NULL,
+    old_address TEXT NOT NULL,
+    new_address TEXT NOT NULL,
+    -- unix timestamp (in seconds) at which the transition window ends
+    ends_at BIGINT NOT NULL,
+    operator TEXT NOT NULL,
+    -- NULL during the transition window
+    retired_at TIMESTAMPTZ,
+    retired_by TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- a currency rotates to one address at a time
+CREATE UNIQUE INDEX key_rotations_open_idx ON key_rotations (currency_address) WHERE retired_at IS NULL;
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON key_rotations FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
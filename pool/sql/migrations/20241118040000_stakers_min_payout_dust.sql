@@ -0,0 +1,4 @@
+-- a min_payout below the dust threshold of 5460 sats would make payments that can't be sent
+UPDATE stakers SET min_payout = 5460 WHERE min_payout < 5460;
+
+ALTER TABLE stakers ADD CONSTRAINT stakers_min_payout_above_dust CHECK (min_payout >= 5460);
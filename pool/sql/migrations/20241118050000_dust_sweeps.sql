@@ -0,0 +1,2 @@
+-- when the unpaid balances below the min_payout of their staker were last paid
+ALTER TABLE synchronization ADD COLUMN last_dust_sweep_at TIMESTAMPTZ;
@@ -0,0 +1,23 @@
+-- the outputs that the payment of a journal entry sends, as they were prepared: one per staker,
+-- to its destination and with the currency it was converted into, if any. The payment is found
+-- in the wallet by these outputs, so a payout address or payout currency that changes before
+-- the entry is reconciled doesn't matter.
+CREATE TABLE payment_journal_items (
+    payment_journal_id BIGINT NOT NULL REFERENCES payment_journal (id),
+    identity_address TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    -- NULL if the output pays in the staked currency
+    convert_to TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (payment_journal_id, identity_address)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON payment_journal_items FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- where an item of a payment was sent, and the currency it was converted into. NULL for the
+-- items that were stored before, and for items that were paid in the staked currency.
+ALTER TABLE payment_items
+    ADD COLUMN destination TEXT,
+    ADD COLUMN payout_currency TEXT;
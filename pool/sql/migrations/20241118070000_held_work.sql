@@ -0,0 +1,9 @@
+-- the work of a departed staker that is held out of the next rounds, and that came back
+ALTER TYPE share_event_kind ADD VALUE 'HELD';
+ALTER TYPE share_event_kind ADD VALUE 'RELEASED';
+
+-- a hold is counted in stakes found instead of blocks. The shares are the work that was taken
+-- out of the open round, NULL until the hold starts.
+ALTER TABLE scheduled_forfeits DROP COLUMN forfeit_height;
+ALTER TABLE scheduled_forfeits ADD COLUMN rounds_left BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE scheduled_forfeits ADD COLUMN held_shares NUMERIC;
@@ -0,0 +1,35 @@
+-- the unpaid payout members of departed stakers that were too small to be paid. Their reward
+-- went to the fee of the payout.
+CREATE TABLE forfeited_rewards (
+    currency_address TEXT NOT NULL,
+    identity_address TEXT NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_height BIGINT NOT NULL,
+    shares DECIMAL NOT NULL,
+    reward BIGINT NOT NULL,
+    fee BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (currency_address, identity_address, block_hash)
+);
+
+CREATE TRIGGER set_updated_timestamp BEFORE UPDATE ON forfeited_rewards FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- forfeits used to zero the reward and leave the payout member unpaid. The amount is gone, so
+-- only the rounds are kept.
+WITH zeroed AS (
+    DELETE FROM payout_members pm
+    WHERE pm.txid IS NULL
+        AND pm.reward = 0
+        AND EXISTS (
+            SELECT 1 FROM staker_statements st
+            WHERE st.currency_address = pm.currency_address
+                AND st.identity_address = pm.identity_address
+        )
+    RETURNING currency_address, identity_address, block_hash, block_height, shares, reward, fee
+)
+INSERT INTO forfeited_rewards (currency_address, identity_address, block_hash, block_height, shares, reward, fee)
+SELECT currency_address, identity_address, block_hash, block_height, shares, reward, fee
+FROM zeroed;
+
+ALTER TABLE staker_statements ADD COLUMN forfeited BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,14 @@
+INSERT INTO network_snapshots (
+    currency_address,
+    block_height,
+    block_hash,
+    network_staking_supply,
+    pool_staking_supply,
+    is_stake
+) VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (currency_address, block_height) DO
+UPDATE SET
+    block_hash = $3,
+    network_staking_supply = $4,
+    pool_staking_supply = $5,
+    is_stake = $6;
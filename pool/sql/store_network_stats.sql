@@ -0,0 +1,14 @@
+INSERT INTO network_stats (
+    currency_address,
+    block_height,
+    block_hash,
+    staking_supply,
+    difficulty,
+    is_stake
+) VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (currency_address, block_height) DO
+UPDATE SET
+    block_hash = $3,
+    staking_supply = $4,
+    difficulty = $5,
+    is_stake = $6;
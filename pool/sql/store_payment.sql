@@ -0,0 +1,14 @@
+INSERT INTO payments (
+    currency_address,
+    txid,
+    amount,
+    n_members,
+    status,
+    confirmations,
+    tx_fee,
+    tx_fee_paid_by_pool
+) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+ON CONFLICT (currency_address, txid) DO
+UPDATE SET
+    status = $5,
+    confirmations = $6;
@@ -6,6 +6,7 @@ INSERT INTO payouts (
     work, 
     fee, 
     amount_paid, 
-    n_subs
+    n_subs,
+    primary_address
 ) 
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);
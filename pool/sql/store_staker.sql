@@ -4,12 +4,15 @@ INSERT INTO stakers (
     identity_name,
     status,
     min_payout, 
-    fee
+    fee,
+    participation_kind,
+    payout_address
 ) VALUES (
-    $1, $2, $3, $4, $5, $6
+    $1, $2, $3, $4, $5, $6, $7, $8
 ) 
 ON CONFLICT (currency_address, identity_address) DO 
 UPDATE SET 
     status = $4,
     min_payout = $5,
-    fee = $6;
+    fee = $6,
+    payout_address = $8;
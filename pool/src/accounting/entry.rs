@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use vrsc_rpc::json::vrsc::{util::amount::serde::as_sat, Address, Amount};
+
+/// A line in the bookkeeping of the pool operator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccountingEntry {
+    pub kind: AccountingEntryKind,
+    pub currency_address: Address,
+    /// The block hash of the stake, or the txid of the payment.
+    pub reference: String,
+    pub block_height: Option<u64>,
+    /// The staked reward, or the amount that was paid out to stakers.
+    #[serde(with = "as_sat")]
+    pub amount: Amount,
+    /// The part of the staked reward that is kept by the pool, or the network fee of a payment.
+    #[serde(with = "as_sat")]
+    pub fee: Amount,
+    /// Unix timestamp (in seconds) of when the stake was paid out or the payment was sent.
+    pub timestamp: u64,
+}
+
+impl AccountingEntry {
+    /// Returns the request body for the bookkeeping API.
+    ///
+    /// `mapping` maps the name of a field in the request body to the name of a field of this
+    /// entry. Without a mapping, the entry is sent as is.
+    pub fn to_body(&self, mapping: &HashMap<String, String>) -> Result<serde_json::Value> {
+        let entry = serde_json::to_value(self)?;
+
+        if mapping.is_empty() {
+            return Ok(entry);
+        }
+
+        let body = mapping
+            .iter()
+            .map(|(target, source)| {
+                let value = entry
+                    .get(source)
+                    .cloned()
+                    .with_context(|| format!("unknown accounting entry field `{source}`"))?;
+
+                Ok((target.clone(), value))
+            })
+            .collect::<Result<serde_json::Map<_, _>>>()?;
+
+        Ok(serde_json::Value::Object(body))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[sqlx(
+    type_name = "accounting_entry_kind",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountingEntryKind {
+    /// A stake of the pool that was paid out to its stakers.
+    StakeIncome,
+    /// A confirmed payment to stakers.
+    Payment,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn entry() -> AccountingEntry {
+        AccountingEntry {
+            kind: AccountingEntryKind::StakeIncome,
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            reference: "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0"
+                .to_string(),
+            block_height: Some(513251),
+            amount: Amount::from_sat(600_000_000),
+            fee: Amount::from_sat(3_000_000),
+            timestamp: 1731715200,
+        }
+    }
+
+    #[test]
+    fn entry_without_mapping_is_sent_as_is() {
+        let body = entry().to_body(&HashMap::new()).unwrap();
+
+        assert_eq!(body["kind"], json!("stake_income"));
+        assert_eq!(body["amount"], json!(600_000_000));
+        assert_eq!(body["block_height"], json!(513251));
+    }
+
+    #[test]
+    fn entry_fields_are_mapped() {
+        let mapping = HashMap::from([
+            ("date".to_string(), "timestamp".to_string()),
+            ("value".to_string(), "fee".to_string()),
+        ]);
+
+        let body = entry().to_body(&mapping).unwrap();
+
+        assert_eq!(body, json!({ "date": 1731715200, "value": 3_000_000 }));
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let mapping = HashMap::from([("date".to_string(), "created_at".to_string())]);
+
+        assert!(entry().to_body(&mapping).is_err());
+    }
+}
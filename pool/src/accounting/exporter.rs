@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error};
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::{coinstaker::AccountingExportConfig, database};
+
+/// The maximum number of entries that are exported in one round.
+const EXPORT_BATCH_SIZE: u64 = 100;
+
+/// Pushes the stake income and confirmed payments of a currency to a bookkeeping API.
+///
+/// Entries are exported in chronological order. An entry that could not be exported is retried
+/// in the next round, before any newer entry.
+pub struct Exporter {
+    database: PgPool,
+    config: AccountingExportConfig,
+    chain_id: Address,
+    client: reqwest::Client,
+}
+
+impl Exporter {
+    pub fn new(
+        config: AccountingExportConfig,
+        database: PgPool,
+        chain_id: Address,
+    ) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in config.headers.iter() {
+            let mut value = HeaderValue::from_str(value.expose_secret()).context(format!(
+                "invalid value for accounting export header `{name}`"
+            ))?;
+            value.set_sensitive(true);
+
+            headers.insert(HeaderName::from_bytes(name.as_bytes())?, value);
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            database,
+            config,
+            chain_id,
+            client,
+        })
+    }
+
+    async fn export(&self) -> Result<()> {
+        let entries = database::get_unexported_accounting_entries(
+            &self.database,
+            &self.chain_id,
+            EXPORT_BATCH_SIZE,
+        )
+        .await?;
+
+        for entry in entries {
+            let body = entry.to_body(&self.config.mapping)?;
+
+            self.client
+                .post(self.config.endpoint.clone())
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            database::store_accounting_export(
+                &self.database,
+                &self.chain_id,
+                entry.kind,
+                &entry.reference,
+            )
+            .await?;
+
+            debug!(kind = ?entry.kind, reference = entry.reference, "exported accounting entry");
+        }
+
+        Ok(())
+    }
+
+    async fn keep_exporting(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.export().await {
+                error!(error = ?e, "Failed to export accounting entries");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for Exporter {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_exporting(&subsys).await
+    }
+}
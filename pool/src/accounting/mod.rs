@@ -0,0 +1,6 @@
+mod entry;
+mod exporter;
+
+pub use entry::AccountingEntry;
+pub use entry::AccountingEntryKind;
+pub use exporter::Exporter;
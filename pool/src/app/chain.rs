@@ -0,0 +1,365 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use tokio::sync::{mpsc, Semaphore};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, SubsystemHandle};
+use vrsc_rpc::{client::Client as VerusClient, json::vrsc::Address};
+
+use crate::{
+    accounting,
+    coinstaker::{
+        coinstaker::{CoinStaker, CoinStakerMessage},
+        halt::{HaltFlag, HaltWatcher},
+        http::{Webhook, WebhookSubscriber},
+        pause::PauseFlag,
+        probe_capabilities,
+        replay::{Recorder, RpcTraffic},
+        staker_webhooks::StakerWebhookSubscriber,
+        staking_watch::StakingWatcher,
+        Config as CoinstakerConfig, Feature, PayoutScheme,
+    },
+    controller::{CacheRefresher, ChainCache, Controller},
+    events::EventBus,
+    metrics::Metrics,
+    notifications::{self, Notifier},
+    payout_service, status_page,
+    util::explorer::Explorer,
+};
+
+/// Builds the services of a currency from its coin configuration.
+#[derive(Clone)]
+pub struct ChainBuilder {
+    pub pool: PgPool,
+    pub metrics: Metrics,
+    pub webhook_outbound: Arc<Semaphore>,
+    pub notification_channels: notifications::Channels,
+    pub start_staking: bool,
+}
+
+/// Every service of a currency, before it is started.
+pub struct ChainServices {
+    pub currency_id: Address,
+    pub tx: mpsc::Sender<CoinStakerMessage>,
+    pub webhooks: Webhook,
+    pub events: EventBus,
+    pub cache: ChainCache,
+    pub reconciliation: payout_service::LatestReconciliation,
+    coin_staker: CoinStaker,
+    webhook_subscriber: WebhookSubscriber,
+    staker_webhook_subscriber: StakerWebhookSubscriber,
+    notifier: Option<Notifier>,
+    payout: payout_service::Service,
+    staking_watcher: StakingWatcher,
+    cache_refresher: CacheRefresher,
+    halt_watcher: Option<HaltWatcher>,
+    accounting_exporter: Option<accounting::Exporter>,
+    status_publisher: Option<status_page::Publisher>,
+    utxo_sweeper: Option<payout_service::Sweeper>,
+    fee_sweeper: Option<payout_service::FeeSweeper>,
+    reconciler: payout_service::Reconciler,
+}
+
+impl ChainBuilder {
+    /// With `record_to`, the RPC traffic of the coinstaker is recorded in that directory.
+    pub async fn build(
+        &self,
+        coin_config: CoinstakerConfig,
+        record_to: Option<&Path>,
+    ) -> Result<ChainServices> {
+        let payout_config = &coin_config.payout_config;
+        if std::iter::once(&payout_config.scheme)
+            .chain(&payout_config.shadow_scheme)
+            .any(|scheme| matches!(scheme, PayoutScheme::Pplns { .. }))
+            && !coin_config.features.is_enabled(Feature::Pplns)
+        {
+            bail!(
+                "the pplns payout scheme of {} requires the pplns feature",
+                coin_config.currency_name
+            );
+        }
+
+        if let Some(endpoint) = coin_config
+            .webhook_secrets
+            .keys()
+            .find(|endpoint| !coin_config.webhook_endpoints.contains(endpoint))
+        {
+            bail!(
+                "the webhook secret of {} is for {endpoint}, which is not a webhook endpoint",
+                coin_config.currency_name
+            );
+        }
+
+        if let Some(quorum) = &coin_config.chain_config.quorum {
+            if quorum.min_agreeing == 0 || quorum.min_agreeing > quorum.nodes.len() + 1 {
+                bail!(
+                    "the quorum of {} needs between 1 and {} agreeing daemons, got {}",
+                    coin_config.currency_name,
+                    quorum.nodes.len() + 1,
+                    quorum.min_agreeing
+                );
+            }
+        }
+
+        let explorer = coin_config
+            .explorer_url_template
+            .as_deref()
+            .map(Explorer::new)
+            .transpose()?;
+
+        // fail before anything starts, instead of during a payout
+        let client: VerusClient = (&coin_config.chain_config).try_into()?;
+        probe_capabilities(&coin_config, &client)?;
+
+        let (tx, rx) = mpsc::channel::<CoinStakerMessage>(1024);
+        let currency_id = coin_config.currency_id.clone();
+        let webhooks = Webhook::new(coin_config.webhook_endpoints.clone())?
+            .with_limits(
+                coin_config.webhook_limits.clone(),
+                self.webhook_outbound.clone(),
+            )
+            .with_outbox(self.pool.clone(), currency_id.clone())
+            .with_explorer(explorer.clone())
+            .with_secrets(coin_config.webhook_secrets.clone());
+        let events = EventBus::new();
+        let webhook_subscriber = WebhookSubscriber::new(webhooks.clone(), events.subscribe());
+        let staker_webhook_subscriber = StakerWebhookSubscriber::new(
+            self.pool.clone(),
+            currency_id.clone(),
+            events.subscribe(),
+        )?
+        .with_explorer(explorer);
+        let notifier = (!self.notification_channels.is_empty()).then(|| {
+            Notifier::new(
+                self.notification_channels.clone(),
+                coin_config.currency_name.clone(),
+                events.subscribe(),
+            )
+        });
+
+        let payout_trigger = payout_service::PayoutTrigger::default();
+        let pause_flag = PauseFlag::default();
+        let mut coin_staker = CoinStaker::new(
+            self.pool.clone(),
+            coin_config.clone(),
+            tx.clone(),
+            rx,
+            events.clone(),
+        )?
+        .with_payout_trigger(payout_trigger.clone())
+        .with_pause_flag(pause_flag.clone())
+        .with_metrics(self.metrics.clone());
+        if let Some(dir) = record_to {
+            coin_staker =
+                coin_staker.with_traffic(RpcTraffic::Record(Recorder::create(dir, &currency_id)?));
+        }
+
+        let halt_flag = HaltFlag::default();
+        let halt_watcher = coin_config.halt_detection.clone().map(|halt_config| {
+            HaltWatcher::new(
+                halt_config,
+                coin_config.chain_config.clone(),
+                tx.clone(),
+                events.clone(),
+                halt_flag.clone(),
+            )
+        });
+
+        let staking_watcher = StakingWatcher::new(
+            coin_config.staking_watch.clone(),
+            coin_config.chain_config.clone(),
+            tx.clone(),
+            events.clone(),
+            halt_flag.clone(),
+            self.start_staking,
+        );
+
+        let cache = ChainCache::default();
+        let cache_refresher = CacheRefresher::new(cache.clone(), tx.clone(), &events);
+
+        let payout = payout_service::Service::new(
+            coin_config.payout_config.clone(),
+            self.pool.clone(),
+            currency_id.clone(),
+            coin_config.pool_address.clone(),
+            coin_config.chain_config.clone(),
+            coin_config.tx_fee,
+            events.clone(),
+        )
+        .with_halt_flag(halt_flag)
+        .with_pause_flag(pause_flag)
+        .with_payout_trigger(payout_trigger)
+        .with_metrics(self.metrics.clone());
+
+        let accounting_exporter = coin_config
+            .accounting_export
+            .clone()
+            .map(|export_config| {
+                accounting::Exporter::new(export_config, self.pool.clone(), currency_id.clone())
+            })
+            .transpose()?;
+
+        let status_publisher = coin_config
+            .status_page
+            .clone()
+            .map(|status_page_config| {
+                status_page::Publisher::new(
+                    status_page_config,
+                    self.pool.clone(),
+                    currency_id.clone(),
+                    coin_config.currency_name.clone(),
+                    coin_config.chain_config.clone(),
+                )
+            })
+            .transpose()?;
+
+        let utxo_sweeper = coin_config.utxo_sweep.clone().map(|sweep_config| {
+            payout_service::Sweeper::new(
+                sweep_config,
+                self.pool.clone(),
+                currency_id.clone(),
+                coin_config.pool_address.clone(),
+                coin_config.chain_config.clone(),
+                coin_config.tx_fee,
+            )
+        });
+
+        let fee_sweeper = coin_config.fee_sweep.clone().map(|fee_sweep_config| {
+            payout_service::FeeSweeper::new(
+                fee_sweep_config,
+                coin_config.destination_whitelist.clone(),
+                self.pool.clone(),
+                currency_id.clone(),
+                coin_config.pool_address.clone(),
+                coin_config.chain_config.clone(),
+            )
+        });
+
+        let reconciliation = payout_service::LatestReconciliation::default();
+        let reconciler = payout_service::Reconciler::new(
+            coin_config.reconciliation.clone(),
+            self.pool.clone(),
+            currency_id.clone(),
+            coin_config.pool_address.clone(),
+            coin_config.chain_config.clone(),
+            reconciliation.clone(),
+        );
+
+        if self.start_staking {
+            tx.send(CoinStakerMessage::SetStaking(true)).await?;
+        }
+
+        Ok(ChainServices {
+            currency_id,
+            tx,
+            webhooks,
+            events,
+            cache,
+            reconciliation,
+            coin_staker,
+            webhook_subscriber,
+            staker_webhook_subscriber,
+            notifier,
+            payout,
+            staking_watcher,
+            cache_refresher,
+            halt_watcher,
+            accounting_exporter,
+            status_publisher,
+            utxo_sweeper,
+            fee_sweeper,
+            reconciler,
+        })
+    }
+}
+
+impl ChainServices {
+    /// Makes the currency available to the HTTP handlers.
+    pub fn register(&self, controller: &Controller) {
+        controller
+            .coin_stakers
+            .insert(self.currency_id.clone(), self.tx.clone());
+        controller
+            .webhooks
+            .insert(self.currency_id.clone(), self.webhooks.clone());
+        controller
+            .events
+            .insert(self.currency_id.clone(), self.events.clone());
+        controller
+            .caches
+            .insert(self.currency_id.clone(), self.cache.clone());
+        controller
+            .reconciliations
+            .insert(self.currency_id.clone(), self.reconciliation.clone());
+    }
+
+    /// Starts every service as a subsystem of `s`.
+    pub fn start(self, s: &SubsystemHandle) {
+        let name = self.currency_id;
+
+        s.start(SubsystemBuilder::new(
+            format!("WebhookService.{name}"),
+            self.webhook_subscriber.into_subsystem(),
+        ));
+        s.start(SubsystemBuilder::new(
+            format!("StakerWebhookService.{name}"),
+            self.staker_webhook_subscriber.into_subsystem(),
+        ));
+        if let Some(notifier) = self.notifier {
+            s.start(SubsystemBuilder::new(
+                format!("NotificationService.{name}"),
+                notifier.into_subsystem(),
+            ));
+        }
+        s.start(SubsystemBuilder::new(
+            format!("CoinStakerService.{name}"),
+            self.coin_staker.into_subsystem(),
+        ));
+        s.start(SubsystemBuilder::new(
+            format!("CoinStakerPayoutService.{name}"),
+            self.payout.into_subsystem(),
+        ));
+        if let Some(exporter) = self.accounting_exporter {
+            s.start(SubsystemBuilder::new(
+                format!("AccountingExportService.{name}"),
+                exporter.into_subsystem(),
+            ));
+        }
+        if let Some(watcher) = self.halt_watcher {
+            s.start(SubsystemBuilder::new(
+                format!("HaltWatchService.{name}"),
+                watcher.into_subsystem(),
+            ));
+        }
+        s.start(SubsystemBuilder::new(
+            format!("StakingWatchService.{name}"),
+            self.staking_watcher.into_subsystem(),
+        ));
+        s.start(SubsystemBuilder::new(
+            format!("CacheRefreshService.{name}"),
+            self.cache_refresher.into_subsystem(),
+        ));
+        if let Some(publisher) = self.status_publisher {
+            s.start(SubsystemBuilder::new(
+                format!("StatusPageService.{name}"),
+                publisher.into_subsystem(),
+            ));
+        }
+        if let Some(sweeper) = self.utxo_sweeper {
+            s.start(SubsystemBuilder::new(
+                format!("UtxoSweepService.{name}"),
+                sweeper.into_subsystem(),
+            ));
+        }
+        if let Some(sweeper) = self.fee_sweeper {
+            s.start(SubsystemBuilder::new(
+                format!("FeeSweepService.{name}"),
+                sweeper.into_subsystem(),
+            ));
+        }
+        s.start(SubsystemBuilder::new(
+            format!("ReconciliationService.{name}"),
+            self.reconciler.into_subsystem(),
+        ));
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use tokio_graceful_shutdown::{IntoSubsystem, NestedSubsystem, SubsystemBuilder, SubsystemHandle};
+use tracing::{debug, error, info, warn};
+use vrsc_rpc::{
+    client::{Client as VerusClient, RpcApi},
+    json::vrsc::Address,
+};
+
+use crate::{
+    coinstaker::{ChainConfig, Config as CoinstakerConfig},
+    config::ChainDiscoveryConfig,
+    controller::Controller,
+};
+
+use super::chain::ChainBuilder;
+
+/// A PBaaS chain that the daemon of the parent currency knows about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredChain {
+    pub currency_id: Address,
+    pub name: String,
+}
+
+/// Reads the PBaaS chains from the result of `listcurrencies`. Entries that can't be read are
+/// left out.
+pub fn parse_currencies(currencies: &Value) -> Vec<DiscoveredChain> {
+    currencies
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|currency| {
+            let definition = currency.get("currencydefinition")?;
+
+            Some(DiscoveredChain {
+                currency_id: Address::from_str(definition.get("currencyid")?.as_str()?).ok()?,
+                name: definition.get("name")?.as_str()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the RPC and ZMQ settings from the configuration file of a daemon.
+///
+/// The ZMQ port is taken from `zmqpubhashblock`, which the coinstaker needs to learn about new
+/// blocks.
+pub fn parse_daemon_conf(contents: &str, rpc_host: &str) -> Result<ChainConfig> {
+    let settings: HashMap<&str, &str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    let setting = |key: &str| {
+        settings
+            .get(key)
+            .copied()
+            .with_context(|| format!("no `{key}` in the daemon configuration"))
+    };
+
+    let zmq = setting("zmqpubhashblock")?;
+    let zmq_port_blocknotify = zmq
+        .rsplit_once(':')
+        .and_then(|(_, port)| port.parse().ok())
+        .with_context(|| format!("invalid `zmqpubhashblock`: {zmq}"))?;
+
+    Ok(ChainConfig {
+        rpc_user: setting("rpcuser")?.to_string(),
+        rpc_password: setting("rpcpassword")?.to_string(),
+        rpc_host: rpc_host.to_string(),
+        rpc_port: setting("rpcport")?.parse().context("invalid `rpcport`")?,
+        zmq_port_blocknotify,
+        fallback_endpoints: vec![],
+        quorum: None,
+    })
+}
+
+/// Generates the coin configuration of a discovered chain from the template.
+pub fn coin_config(
+    template: &Path,
+    chain: &DiscoveredChain,
+    chain_config: &ChainConfig,
+) -> Result<CoinstakerConfig> {
+    let config = config::Config::builder()
+        .add_source(config::File::from(template))
+        .set_override("currency_id", chain.currency_id.to_string())?
+        .set_override("currency_name", chain.name.clone())?
+        .set_override("chain_config.rpc_user", chain_config.rpc_user.clone())?
+        .set_override(
+            "chain_config.rpc_password",
+            chain_config.rpc_password.clone(),
+        )?
+        .set_override("chain_config.rpc_host", chain_config.rpc_host.clone())?
+        .set_override("chain_config.rpc_port", chain_config.rpc_port)?
+        .set_override(
+            "chain_config.zmq_port_blocknotify",
+            chain_config.zmq_port_blocknotify,
+        )?
+        .build()?
+        .try_deserialize::<CoinstakerConfig>()?;
+
+    Ok(config)
+}
+
+/// Stakes the PBaaS chains that the daemon of the parent currency lists, without a coin
+/// configuration file per chain.
+///
+/// Every chain that matches the filter gets the services of a configured currency, started at
+/// runtime. A chain that is no longer listed is shut down and its routes return 404.
+pub struct ChainDiscovery {
+    config: ChainDiscoveryConfig,
+    parent: ChainConfig,
+    /// The currencies that are configured with a file, which are never discovered.
+    configured: HashSet<Address>,
+    builder: ChainBuilder,
+    controller: Arc<Controller>,
+    running: HashMap<Address, NestedSubsystem<anyhow::Error>>,
+}
+
+impl ChainDiscovery {
+    pub fn new(
+        config: ChainDiscoveryConfig,
+        parent: ChainConfig,
+        configured: HashSet<Address>,
+        builder: ChainBuilder,
+        controller: Arc<Controller>,
+    ) -> Self {
+        Self {
+            config,
+            parent,
+            configured,
+            builder,
+            controller,
+            running: HashMap::new(),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<DiscoveredChain>> {
+        let client: VerusClient = (&self.parent).try_into()?;
+        let currencies =
+            client.call::<Value>("listcurrencies", &[json!({ "systemtype": "pbaas" })])?;
+
+        Ok(parse_currencies(&currencies)
+            .into_iter()
+            .filter(|chain| !self.configured.contains(&chain.currency_id))
+            .filter(|chain| self.config.matches(&chain.name))
+            .collect())
+    }
+
+    async fn onboard(&mut self, chain: &DiscoveredChain, subsys: &SubsystemHandle) -> Result<()> {
+        let conf_path = self
+            .config
+            .conf_path(&chain.name, &chain.currency_id.to_string());
+        let contents = std::fs::read_to_string(&conf_path)
+            .with_context(|| format!("could not read {}", conf_path.display()))?;
+        let chain_config = parse_daemon_conf(&contents, &self.parent.rpc_host)?;
+        let coin_config = coin_config(&self.config.template, chain, &chain_config)?;
+
+        let services = self.builder.build(coin_config, None).await?;
+        services.register(&self.controller);
+
+        let nested = subsys.start(SubsystemBuilder::new(
+            format!("Chain.{}", chain.currency_id),
+            move |s: SubsystemHandle| async move {
+                services.start(&s);
+                s.on_shutdown_requested().await;
+
+                Ok::<(), anyhow::Error>(())
+            },
+        ));
+        self.running.insert(chain.currency_id.clone(), nested);
+
+        info!(currency = chain.name, "started staking a discovered chain");
+
+        Ok(())
+    }
+
+    fn teardown(&mut self, currency_id: &Address) {
+        self.controller.coin_stakers.remove(currency_id);
+        self.controller.webhooks.remove(currency_id);
+        self.controller.events.remove(currency_id);
+        self.controller.caches.remove(currency_id);
+        self.controller.reconciliations.remove(currency_id);
+
+        if let Some(nested) = self.running.remove(currency_id) {
+            nested.initiate_shutdown();
+        }
+
+        info!(%currency_id, "stopped staking a chain that is no longer listed");
+    }
+
+    async fn discover(&mut self, subsys: &SubsystemHandle) -> Result<()> {
+        let chains = self.list()?;
+        debug!(?chains, "discovered chains");
+
+        let listed: HashSet<Address> = chains
+            .iter()
+            .map(|chain| chain.currency_id.clone())
+            .collect();
+        let deactivated: Vec<Address> = self
+            .running
+            .keys()
+            .filter(|currency_id| !listed.contains(currency_id))
+            .cloned()
+            .collect();
+        for currency_id in deactivated {
+            self.teardown(&currency_id);
+        }
+
+        for chain in chains {
+            if self.running.contains_key(&chain.currency_id) {
+                continue;
+            }
+
+            // a chain that can't be onboarded yet is tried again at the next discovery
+            if let Err(e) = self.onboard(&chain, subsys).await {
+                warn!(currency = chain.name, error = ?e, "could not onboard a discovered chain");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn keep_discovering(&mut self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.discover(subsys).await {
+                error!(error = ?e, "Failed to discover chains");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for ChainDiscovery {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_discovering(&subsys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_pbaas_chains_from_listcurrencies() {
+        let currencies = json!([
+            {
+                "currencydefinition": {
+                    "name": "vARRR",
+                    "currencyid": "iExBJfZYK7KREDpuhj6PzZBzqMAKaFg7d2",
+                    "options": 264
+                },
+                "bestheight": 402312
+            },
+            { "currencydefinition": { "name": "broken" } }
+        ]);
+
+        assert_eq!(
+            parse_currencies(&currencies),
+            vec![DiscoveredChain {
+                currency_id: Address::from_str("iExBJfZYK7KREDpuhj6PzZBzqMAKaFg7d2").unwrap(),
+                name: "vARRR".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_the_rpc_and_zmq_settings_of_a_daemon() {
+        let conf = "
+            # generated by the daemon
+            rpcuser=user
+            rpcpassword=secret=with=equals
+            rpcport=20778
+            zmqpubhashblock=tcp://127.0.0.1:20779
+        ";
+
+        let chain_config = parse_daemon_conf(conf, "127.0.0.1").unwrap();
+        assert_eq!(chain_config.rpc_user, "user");
+        assert_eq!(chain_config.rpc_password, "secret=with=equals");
+        assert_eq!(chain_config.rpc_port, 20778);
+        assert_eq!(chain_config.zmq_port_blocknotify, 20779);
+
+        assert!(parse_daemon_conf("rpcuser=user\nrpcpassword=p\nrpcport=1", "127.0.0.1").is_err());
+    }
+
+    #[test]
+    fn filters_chains_by_name() {
+        let mut config = ChainDiscoveryConfig {
+            parent: "VRSC".to_string(),
+            template: "discovery/pbaas.toml".into(),
+            conf_path: "/verus/pbaas/{id}/{name}.conf".to_string(),
+            include: vec![],
+            exclude: vec!["vDEX".to_string()],
+            interval_in_secs: 600,
+        };
+
+        assert!(config.matches("vARRR"));
+        assert!(!config.matches("vdex"));
+
+        config.include = vec!["varrr".to_string()];
+        assert!(config.matches("vARRR"));
+        assert!(!config.matches("CHIPS"));
+
+        assert_eq!(
+            config.conf_path("vARRR", "iExB"),
+            std::path::PathBuf::from("/verus/pbaas/iExB/vARRR.conf")
+        );
+    }
+}
@@ -1,20 +1,38 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+mod chain;
+mod discovery;
 
 use crate::{
     coinstaker::{
-        coinstaker::{CoinStaker, CoinStakerMessage},
+        backfill::{self, BackfillReport},
         get_coin_configurations,
+        replay::{self, Recording},
     },
     config::Config,
     controller::Controller,
+    database::{self, SchemaReport},
     http::HttpService,
-    payout_service,
+    metrics::Metrics,
+    notifications,
+    util::bootstrap,
+    MIGRATOR,
 };
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use secrecy::ExposeSecret;
-use sqlx::{pool::PoolOptions, postgres::PgConnectOptions, PgPool};
-use tokio::sync::mpsc;
+use sqlx::{
+    pool::PoolOptions, postgres::PgConnectOptions, Connection, Executor, PgConnection, PgPool,
+};
+use tokio::sync::Semaphore;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemBuilder, Toplevel};
+use tracing::{info, warn};
+
+use self::{chain::ChainBuilder, discovery::ChainDiscovery};
 
 pub struct App {
     pool: PgPool,
@@ -30,46 +48,192 @@ impl App {
                 .port(config.database.port)
                 .username(&config.database.username)
                 .database(&config.database.name)
-                .password(config.database.password.expose_secret()),
+                .password(config.database.password.expose_secret())
+                // timestamps are stored as TIMESTAMPTZ and returned as unix timestamps, but
+                // anything that is formatted by the database is formatted in UTC
+                .options([("timezone", "UTC")]),
         );
 
         Ok(Self { pool, config })
     }
 
-    pub async fn services(self, start_staking: bool) -> Result<Toplevel> {
+    /// Compares the database with the migrations, without changing anything.
+    pub async fn check_schema(&self) -> Result<SchemaReport> {
+        database::check_schema(&self.pool).await
+    }
+
+    /// Replays a recording against a new scratch database, which is left behind for inspection.
+    pub async fn replay(self, path: &Path) -> Result<()> {
+        let recording = Recording::load(path)?;
+        let coin_config = get_coin_configurations()?
+            .into_iter()
+            .find(|config| config.currency_id == recording.currency_id)
+            .with_context(|| {
+                format!(
+                    "No coin configuration for the recorded currency {}",
+                    recording.currency_id
+                )
+            })?;
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let database_name = format!("{}_replay_{timestamp}", self.config.database.name);
+
+        let mut conn =
+            PgConnection::connect(&self.config.database.connection_string_without_db()).await?;
+        conn.execute(format!(r#"CREATE DATABASE "{database_name}""#).as_str())
+            .await?;
+        conn.close().await?;
+
+        let pool = PgPool::connect_with(
+            PgConnectOptions::new_without_pgpass()
+                .host(&self.config.database.host)
+                .port(self.config.database.port)
+                .username(&self.config.database.username)
+                .database(&database_name)
+                .password(self.config.database.password.expose_secret())
+                .options([("timezone", "UTC")]),
+        )
+        .await?;
+        MIGRATOR.run(&pool).await?;
+
+        info!(
+            database = database_name,
+            "created scratch database for replay"
+        );
+
+        replay::replay(pool, coin_config, recording).await
+    }
+
+    /// Provisions the daemon of `currency` (its name or id) with the configured bootstrap.
+    pub async fn bootstrap(&self, currency: &str) -> Result<()> {
+        let coin_config = get_coin_configurations()?
+            .into_iter()
+            .find(|config| {
+                config.currency_name.eq_ignore_ascii_case(currency)
+                    || config.currency_id.to_string() == currency
+            })
+            .with_context(|| format!("No coin configuration for {currency}"))?;
+        let bootstrap = coin_config
+            .bootstrap
+            .as_ref()
+            .with_context(|| format!("No bootstrap configured for {currency}"))?;
+
+        bootstrap::provision(bootstrap, &coin_config.chain_config).await
+    }
+
+    /// Imports the stakes of `currency` (its name or id) between two heights from the chain.
+    pub async fn backfill(
+        &self,
+        currency: &str,
+        from_height: u64,
+        to_height: u64,
+        with_payouts: bool,
+    ) -> Result<BackfillReport> {
+        if from_height > to_height {
+            bail!("the backfill starts at {from_height}, after it ends at {to_height}");
+        }
+
+        let coin_config = get_coin_configurations()?
+            .into_iter()
+            .find(|config| {
+                config.currency_name.eq_ignore_ascii_case(currency)
+                    || config.currency_id.to_string() == currency
+            })
+            .with_context(|| format!("No coin configuration for {currency}"))?;
+
+        let schema = self.check_schema().await?;
+        if schema.has_financial_drift() {
+            bail!("the financial tables drifted from the migrations");
+        }
+
+        backfill::backfill(
+            &self.pool,
+            &coin_config,
+            from_height,
+            to_height,
+            with_payouts,
+        )
+        .await
+    }
+
+    /// Starts all services. With `record_to`, the RPC traffic of every coinstaker is recorded in
+    /// that directory, so that it can be replayed later.
+    pub async fn services(
+        self,
+        start_staking: bool,
+        record_to: Option<PathBuf>,
+    ) -> Result<Toplevel> {
+        // the coinstakers and payout services write to the financial tables, so a drifted schema
+        // could lead to wrong payouts
+        let schema = self.check_schema().await?;
+        if let Some(plan) = schema.remediation_plan() {
+            if schema.has_financial_drift() {
+                bail!("the database schema drifted from the migrations:\n{plan}");
+            }
+
+            warn!("the database schema differs from the migrations:\n{plan}");
+        }
+
         let coin_configs = get_coin_configurations()?;
-        let mut coin_stakers = vec![];
-        let mut coin_staker_payouts = vec![];
-        let mut coin_staker_map = HashMap::new();
-        for coin_config in coin_configs {
-            let (tx, rx) = mpsc::channel::<CoinStakerMessage>(1024);
-            let currency_id = coin_config.currency_id.clone();
-            let coin_staker =
-                CoinStaker::new(self.pool.clone(), coin_config.clone(), tx.clone(), rx)?;
-            coin_stakers.push(coin_staker);
-
-            let payout = payout_service::Service::new(
-                coin_config.payout_config,
-                self.pool.clone(),
-                currency_id.clone(),
-                coin_config.pool_address.clone(),
-                coin_config.chain_config.clone(),
-            );
-            coin_staker_payouts.push((currency_id.clone(), payout));
-
-            if start_staking {
-                tx.send(CoinStakerMessage::SetStaking(true)).await?;
+        let builder = ChainBuilder {
+            pool: self.pool.clone(),
+            metrics: Metrics::default(),
+            webhook_outbound: Arc::new(Semaphore::new(
+                self.config.webhooks.max_concurrent_deliveries,
+            )),
+            notification_channels: notifications::Channels::new(&self.config.notifications)?,
+            start_staking,
+        };
+        let controller = Arc::new(Controller::new(
+            String::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            builder.metrics.clone(),
+        ));
+
+        let discovery = match self.config.chain_discovery {
+            Some(discovery_config) => {
+                let parent = coin_configs
+                    .iter()
+                    .find(|config| {
+                        config
+                            .currency_name
+                            .eq_ignore_ascii_case(&discovery_config.parent)
+                            || config.currency_id.to_string() == discovery_config.parent
+                    })
+                    .with_context(|| {
+                        format!(
+                            "No coin configuration for the discovery parent {}",
+                            discovery_config.parent
+                        )
+                    })?;
+
+                Some(ChainDiscovery::new(
+                    discovery_config,
+                    parent.chain_config.clone(),
+                    coin_configs
+                        .iter()
+                        .map(|config| config.currency_id.clone())
+                        .collect(),
+                    builder.clone(),
+                    controller.clone(),
+                ))
             }
+            None => None,
+        };
 
-            coin_staker_map.insert(currency_id, tx);
+        let mut chains = vec![];
+        for coin_config in coin_configs {
+            let services = builder.build(coin_config, record_to.as_deref()).await?;
+            services.register(&controller);
+            chains.push(services);
         }
 
         let http_service = HttpService {
-            state: Arc::new(Controller {
-                database: String::new(),
-                coin_stakers: coin_staker_map,
-            }),
+            state: controller,
             config: self.config.http,
+            pool: self.pool.clone(),
         };
 
         let toplevel = Toplevel::new(|s| async move {
@@ -78,17 +242,14 @@ impl App {
                 http_service.into_subsystem(),
             ));
 
-            for cs in coin_stakers {
-                s.start(SubsystemBuilder::new(
-                    format!("CoinStakerService.{}", cs.chain_id),
-                    cs.into_subsystem(),
-                ));
+            for chain in chains {
+                chain.start(&s);
             }
 
-            for (name, payout) in coin_staker_payouts {
+            if let Some(discovery) = discovery {
                 s.start(SubsystemBuilder::new(
-                    format!("CoinStakerPayoutService.{name}"),
-                    payout.into_subsystem(),
+                    "ChainDiscoveryService",
+                    discovery.into_subsystem(),
                 ));
             }
         });
@@ -113,8 +274,14 @@ impl App {
     }
 
     pub fn services(self) -> Result<Toplevel> {
-        use crate::coinstaker::Config as CoinstakerConfig;
+        use crate::coinstaker::{
+            coinstaker::CoinStaker,
+            http::{Webhook, WebhookSubscriber},
+            Config as CoinstakerConfig,
+        };
+        use crate::events::EventBus;
         use std::path::{Path, PathBuf};
+        use tokio::sync::mpsc;
 
         let pool = self.pool.clone();
 
@@ -126,9 +293,16 @@ impl App {
             .build()?
             .try_deserialize::<CoinstakerConfig>()?;
 
-        let coinstaker = CoinStaker::new(pool, config, tx, rx)?;
+        let webhooks = Webhook::new(config.webhook_endpoints.clone())?;
+        let events = EventBus::new();
+        let subscriber = WebhookSubscriber::new(webhooks, events.subscribe());
+        let coinstaker = CoinStaker::new(pool, config, tx, rx, events)?;
 
         Ok(Toplevel::new(|toplevel| async move {
+            toplevel.start(SubsystemBuilder::new(
+                "mock.webhooks".to_string(),
+                subscriber.into_subsystem(),
+            ));
             toplevel.start(SubsystemBuilder::new(
                 "mock".to_string(),
                 coinstaker.into_subsystem(),
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use argh::FromArgs;
 
@@ -34,7 +34,50 @@ async fn main() -> Result<(), anyhow::Error> {
     let config = app_config().await?;
 
     let app = App::new(config).await?;
-    let services = app.services(app_args.staking).await?;
+
+    if app_args.check_schema {
+        let report = app.check_schema().await?;
+        match report.remediation_plan() {
+            Some(plan) => println!("the database schema differs from the migrations:\n{plan}"),
+            None => println!("the database schema matches the migrations"),
+        }
+
+        if report.has_financial_drift() {
+            anyhow::bail!("the financial tables drifted from the migrations");
+        }
+
+        return Ok(());
+    }
+
+    if let Some(path) = app_args.replay {
+        return app.replay(&path).await;
+    }
+
+    if let Some(currency) = app_args.bootstrap {
+        return app.bootstrap(&currency).await;
+    }
+
+    if let Some(currency) = app_args.backfill {
+        let (Some(from_height), Some(to_height)) = (app_args.from_height, app_args.to_height)
+        else {
+            anyhow::bail!("a backfill needs --from-height and --to-height");
+        };
+
+        let report = app
+            .backfill(&currency, from_height, to_height, app_args.with_payouts)
+            .await?;
+        println!(
+            "scanned {} blocks, imported {} stakes ({} were already known), created {} payouts",
+            report.blocks_scanned,
+            report.stakes_imported,
+            report.stakes_known,
+            report.payouts_created
+        );
+
+        return Ok(());
+    }
+
+    let services = app.services(app_args.staking, app_args.record).await?;
 
     info!("starting services");
     services
@@ -50,4 +93,38 @@ struct AppArgs {
     /// enable staking on startup
     #[argh(switch, short = 's')]
     staking: bool,
+
+    /// check the database schema against the migrations, print a remediation plan and exit
+    #[argh(switch)]
+    check_schema: bool,
+
+    /// record the block hashes and rpc responses of every coinstaker in this directory
+    #[argh(option)]
+    record: Option<PathBuf>,
+
+    /// replay a recording against a scratch database and exit
+    #[argh(option)]
+    replay: Option<PathBuf>,
+
+    /// download the configured bootstrap of a currency, verify it and unpack it for its stopped
+    /// daemon, then exit
+    #[argh(option)]
+    bootstrap: Option<String>,
+
+    /// import the stakes of a currency from the chain between --from-height and --to-height,
+    /// then exit
+    #[argh(option)]
+    backfill: Option<String>,
+
+    /// the first height of a backfill
+    #[argh(option)]
+    from_height: Option<u64>,
+
+    /// the last height of a backfill
+    #[argh(option)]
+    to_height: Option<u64>,
+
+    /// create payouts for the stakes of a backfill, to be paid by the payout service
+    #[argh(switch)]
+    with_payouts: bool,
 }
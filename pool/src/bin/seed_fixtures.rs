@@ -0,0 +1,80 @@
+use argh::FromArgs;
+use pool::{
+    config::app_config,
+    util::fixtures::{seed, FixtureConfig},
+    MIGRATOR,
+};
+use secrecy::ExposeSecret;
+use sqlx::{postgres::PgConnectOptions, PgPool};
+use vrsc_rpc::json::vrsc::Address;
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let args: Args = argh::from_env();
+
+    tracing_subscriber::fmt::init();
+
+    let config = app_config().await?;
+    let pool = PgPool::connect_with(
+        PgConnectOptions::new_without_pgpass()
+            .host(&config.database.host)
+            .port(config.database.port)
+            .username(&config.database.username)
+            .database(&config.database.name)
+            .password(config.database.password.expose_secret()),
+    )
+    .await?;
+    MIGRATOR.run(&pool).await?;
+
+    let summary = seed(
+        &pool,
+        &FixtureConfig {
+            currency_address: args.currency,
+            n_stakers: args.stakers,
+            n_rounds: args.rounds,
+            blocks_per_round: args.blocks_per_round,
+            rounds_per_payment: args.rounds_per_payment,
+            seed: args.seed,
+        },
+    )
+    .await?;
+
+    println!(
+        "seeded {} stakers, {} stakes, {} payouts and {} payments up to height {}",
+        summary.stakers, summary.stakes, summary.payouts, summary.payments, summary.chain_tip
+    );
+
+    Ok(())
+}
+
+#[derive(FromArgs)]
+/// Seeds the configured database with a synthetic pool. Never run this against a production
+/// database.
+struct Args {
+    /// the currency to seed the pool of
+    #[argh(
+        option,
+        default = "\"iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq\".parse().unwrap()"
+    )]
+    currency: Address,
+
+    /// the number of stakers
+    #[argh(option, default = "1000")]
+    stakers: usize,
+
+    /// the number of rounds, each ending in a stake
+    #[argh(option, default = "1000")]
+    rounds: u64,
+
+    /// the average number of blocks in a round
+    #[argh(option, default = "20")]
+    blocks_per_round: u64,
+
+    /// the number of rounds between payments
+    #[argh(option, default = "10")]
+    rounds_per_payment: u64,
+
+    /// the seed of the random numbers
+    #[argh(option, default = "1")]
+    seed: u64,
+}
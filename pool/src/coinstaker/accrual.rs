@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use vrsc_rpc::json::vrsc::{Address, Amount};
+
+/// A UTXO that counts towards the work of a staker at the heights from `from_height` up to and
+/// including `until_height`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EligibleUtxo {
+    pub identity_address: Address,
+    pub amount: Amount,
+    pub from_height: u64,
+    /// `None` while the UTXO is unspent.
+    pub until_height: Option<u64>,
+}
+
+impl EligibleUtxo {
+    /// A UTXO that was mined `confirmations` blocks before the chain tip at `tip_height`, which
+    /// becomes eligible once it has `eligibility_confirmations`.
+    pub fn unspent(
+        identity_address: Address,
+        amount: Amount,
+        confirmations: u64,
+        tip_height: u64,
+        eligibility_confirmations: u64,
+    ) -> Self {
+        let mined_at = (tip_height + 1).saturating_sub(confirmations);
+
+        Self {
+            identity_address,
+            amount,
+            from_height: mined_at + eligibility_confirmations.saturating_sub(1),
+            until_height: None,
+        }
+    }
+
+    pub(super) fn is_eligible_at(&self, height: u64) -> bool {
+        self.from_height <= height && self.until_height.map_or(true, |until| height <= until)
+    }
+}
+
+/// The eligible balance of a staker from `from_height` up to and including `to_height`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceSpan {
+    pub identity_address: Address,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub balance: Amount,
+}
+
+impl BalanceSpan {
+    /// The work of holding the balance during the span: balance × blocks held.
+    pub fn shares(&self) -> Decimal {
+        Decimal::from(self.balance.as_sat()) * Decimal::from(self.to_height - self.from_height + 1)
+    }
+}
+
+/// Splits the heights from `from_height` up to and including `to_height` into spans in which
+/// the eligible balance of a staker doesn't change. Heights without an eligible balance get no
+/// span.
+///
+/// As the heights at which every UTXO became eligible are known, the balances of blocks that
+/// were not processed when they were mined are reconstructed exactly, as long as the UTXOs are
+/// still unspent.
+pub fn balance_spans(utxos: &[EligibleUtxo], from_height: u64, to_height: u64) -> Vec<BalanceSpan> {
+    let mut by_staker: HashMap<&Address, Vec<&EligibleUtxo>> = HashMap::new();
+    for utxo in utxos {
+        by_staker
+            .entry(&utxo.identity_address)
+            .or_default()
+            .push(utxo);
+    }
+
+    let mut spans = vec![];
+    for (identity_address, utxos) in by_staker {
+        // the balance can only change where a UTXO starts or stops being eligible
+        let mut changes = vec![from_height];
+        for utxo in &utxos {
+            changes.push(utxo.from_height);
+            if let Some(until_height) = utxo.until_height {
+                changes.push(until_height + 1);
+            }
+        }
+        changes.retain(|height| (from_height..=to_height).contains(height));
+        changes.sort_unstable();
+        changes.dedup();
+
+        for (i, &start) in changes.iter().enumerate() {
+            let end = changes.get(i + 1).map_or(to_height, |next| next - 1);
+            let balance = utxos
+                .iter()
+                .filter(|utxo| utxo.is_eligible_at(start))
+                .fold(Amount::ZERO, |acc, utxo| acc + utxo.amount);
+
+            if balance == Amount::ZERO {
+                continue;
+            }
+
+            match spans.last_mut() {
+                Some(BalanceSpan {
+                    identity_address: last_address,
+                    to_height: last_to,
+                    balance: last_balance,
+                    ..
+                }) if last_address == identity_address
+                    && *last_to + 1 == start
+                    && *last_balance == balance =>
+                {
+                    *last_to = end;
+                }
+                _ => spans.push(BalanceSpan {
+                    identity_address: identity_address.clone(),
+                    from_height: start,
+                    to_height: end,
+                    balance,
+                }),
+            }
+        }
+    }
+
+    spans.sort_by(|a, b| {
+        (a.identity_address.to_string(), a.from_height)
+            .cmp(&(b.identity_address.to_string(), b.from_height))
+    });
+
+    spans
+}
+
+/// Adds up the work of the spans per staker.
+pub fn time_weighted_shares(spans: &[BalanceSpan]) -> HashMap<Address, Decimal> {
+    spans.iter().fold(HashMap::new(), |mut acc, span| {
+        *acc.entry(span.identity_address.clone())
+            .or_insert(Decimal::ZERO) += span.shares();
+
+        acc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn alice() -> Address {
+        Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap()
+    }
+
+    fn bob() -> Address {
+        Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap()
+    }
+
+    #[test]
+    fn utxos_become_eligible_at_150_confirmations() {
+        // mined at 1_000, so it has 150 confirmations at 1_149
+        let utxo = EligibleUtxo::unspent(alice(), Amount::from_sat(100), 201, 1_200, 150);
+
+        assert_eq!(utxo.from_height, 1_149);
+        assert!(!utxo.is_eligible_at(1_148));
+        assert!(utxo.is_eligible_at(1_149));
+    }
+
+    #[test]
+    fn one_block_is_the_balance() {
+        let utxos = vec![
+            EligibleUtxo::unspent(alice(), Amount::from_sat(100), 500, 1_000, 150),
+            EligibleUtxo::unspent(alice(), Amount::from_sat(50), 400, 1_000, 150),
+            EligibleUtxo::unspent(bob(), Amount::from_sat(30), 300, 1_000, 150),
+        ];
+
+        let shares = time_weighted_shares(&balance_spans(&utxos, 1_000, 1_000));
+        assert_eq!(shares[&alice()], Decimal::from(150));
+        assert_eq!(shares[&bob()], Decimal::from(30));
+    }
+
+    #[test]
+    fn missed_blocks_are_reconstructed_from_the_utxos() {
+        let utxos = vec![
+            // eligible long before the pool went down
+            EligibleUtxo::unspent(alice(), Amount::from_sat(100), 500, 1_010, 150),
+            // mined at 865, eligible from 1_014 on, while the pool was down
+            EligibleUtxo::unspent(alice(), Amount::from_sat(40), 146, 1_010, 150),
+            // the source of a stake at 1_002, compensated until it could have staked again
+            EligibleUtxo {
+                identity_address: alice(),
+                amount: Amount::from_sat(10),
+                from_height: 1_003,
+                until_height: Some(1_005),
+            },
+        ];
+
+        // the pool processed 1_000 and is back at 1_020
+        let spans = balance_spans(&utxos, 1_001, 1_020);
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| (span.from_height, span.to_height, span.balance.as_sat()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1_001, 1_002, 100),
+                (1_003, 1_005, 110),
+                (1_006, 1_013, 100),
+                (1_014, 1_020, 140)
+            ]
+        );
+
+        let shares = time_weighted_shares(&spans);
+        assert_eq!(
+            shares[&alice()],
+            Decimal::from(100 * 2 + 110 * 3 + 100 * 8 + 140 * 7)
+        );
+    }
+
+    #[test]
+    fn no_spans_without_an_eligible_balance() {
+        let utxos = vec![EligibleUtxo::unspent(
+            alice(),
+            Amount::from_sat(100),
+            10,
+            1_000,
+            150,
+        )];
+
+        assert!(balance_spans(&utxos, 1_000, 1_000).is_empty());
+    }
+}
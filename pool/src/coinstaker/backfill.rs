@@ -0,0 +1,112 @@
+use anyhow::Result;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tracing::{debug, info, warn};
+use vrsc_rpc::{
+    client::{Client as VerusClient, RpcApi},
+    json::ValidationType,
+};
+
+use crate::{
+    database,
+    payout_service::{Fees, Payout, Worker},
+    util::verus::{check_stake_guard, postxddest},
+};
+
+use super::{
+    constants::{stake_from_block, StakeStatus},
+    maturity::Maturity,
+    Config,
+};
+
+/// What a backfill imported.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BackfillReport {
+    pub blocks_scanned: u64,
+    pub stakes_imported: u64,
+    /// Stakes of the pool that were already in the database, which are left alone.
+    pub stakes_known: u64,
+    pub payouts_created: u64,
+}
+
+/// Imports the stakes of the pool between two heights from the chain, for a pool that moves its
+/// history over from another system.
+///
+/// A block is a stake of the pool if it was staked by the pool address or by a known staker, so
+/// the stakers need to be imported first. Imported stakes get the `imported` status and no
+/// round, so the coinstaker and the payout service leave them alone.
+///
+/// The work of the rounds of the old system is not known. With `with_payouts`, the staker that
+/// found a matured stake gets its whole reward, minus its fee. These payouts are paid by the
+/// payout service, so leave them out for rewards that the old system already paid.
+pub async fn backfill(
+    pool: &PgPool,
+    config: &Config,
+    from_height: u64,
+    to_height: u64,
+    with_payouts: bool,
+) -> Result<BackfillReport> {
+    let client: VerusClient = (&config.chain_config).try_into()?;
+    let mut report = BackfillReport::default();
+
+    for height in from_height..=to_height {
+        let block = client.get_block_by_height(height, 2)?;
+        report.blocks_scanned += 1;
+
+        if !matches!(block.validation_type, ValidationType::Stake) {
+            continue;
+        }
+        let Ok(found_by) = postxddest(&block) else {
+            continue;
+        };
+
+        let staker =
+            database::get_staker(&mut *pool.acquire().await?, &config.currency_id, &found_by)
+                .await?;
+        if staker.is_none() && found_by != config.pool_address {
+            continue;
+        }
+        if let Some(spend_txid) = check_stake_guard(&block).await? {
+            warn!(%height, %spend_txid, "not importing a stake that was spent by StakeGuard");
+
+            continue;
+        }
+
+        let mut stake = stake_from_block(&config.currency_id, &block)?;
+        stake.status = StakeStatus::Imported;
+
+        let mut tx = pool.begin().await?;
+        if !database::store_imported_stake(&mut tx, &stake).await? {
+            debug!(%height, "stake is already known");
+            report.stakes_known += 1;
+
+            continue;
+        }
+        report.stakes_imported += 1;
+
+        if let (true, Some(staker)) = (with_payouts, staker) {
+            if Maturity::of(block.confirmations, config.maturity_confirmations) == Maturity::Matured
+            {
+                let workers = vec![Worker {
+                    identity_address: staker.identity_address,
+                    shares: Decimal::from(stake.source_amount.as_sat()),
+                    fee: staker.fee,
+                }];
+                let fees =
+                    Fees::load_for_round(pool, &config.payout_config.fee_schedule, &stake).await?;
+                let payout = Payout::new(&stake, workers, Decimal::ZERO, &fees)?;
+
+                database::store_payout(&mut tx, &payout).await?;
+                report.payouts_created += 1;
+            } else {
+                warn!(%height, "no payout for an imported stake that did not mature yet");
+            }
+        }
+
+        tx.commit().await?;
+    }
+
+    info!(?report, "finished backfill");
+
+    Ok(report)
+}
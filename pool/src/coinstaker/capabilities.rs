@@ -0,0 +1,151 @@
+use anyhow::{bail, Result};
+use tracing::{debug, info};
+use vrsc_rpc::client::{Client as VerusClient, RpcApi};
+
+use super::config::Config;
+
+/// An RPC method that the pool needs, and what it needs it for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability {
+    pub method: &'static str,
+    pub used_for: &'static str,
+}
+
+/// Calls every RPC method that this coinstaker relies on, with harmless arguments, and fails
+/// with a list of the methods that the daemon or the configured credentials don't allow.
+///
+/// Only read-only methods are probed: `sendcurrency` cannot be called without side effects,
+/// but a wallet that allows `getwalletinfo` and `listunspent` normally allows it too.
+pub fn probe_capabilities(config: &Config, client: &VerusClient) -> Result<()> {
+    let currency_id = config.currency_id.to_string();
+    let pool_address = vec![config.pool_address.clone()];
+
+    let mut probes: Vec<(Capability, Box<dyn Fn() -> Result<()> + '_>)> = vec![
+        (
+            capability("getblockchaininfo", "following the chain"),
+            Box::new(|| Ok(client.get_blockchain_info().map(drop)?)),
+        ),
+        (
+            capability("getblock", "processing blocks and stakes"),
+            Box::new(|| Ok(client.get_block_by_height(1, 2).map(drop)?)),
+        ),
+        (
+            capability("getmininginfo", "the network staking supply"),
+            Box::new(|| Ok(client.get_mining_info().map(drop)?)),
+        ),
+        (
+            capability("getcurrency", "currency names and options"),
+            Box::new(|| Ok(client.get_currency(&currency_id).map(drop)?)),
+        ),
+        (
+            capability("getidentity", "checking the eligibility of stakers"),
+            Box::new(|| {
+                Ok(client
+                    .get_identity(&format!("{}@", config.currency_name))
+                    .map(drop)?)
+            }),
+        ),
+        (
+            capability("getwalletinfo", "the staking balance of the pool"),
+            Box::new(|| Ok(client.get_wallet_info().map(drop)?)),
+        ),
+        (
+            capability("listunspent", "work and payouts"),
+            Box::new(|| {
+                Ok(client
+                    .list_unspent(Some(0), None, Some(pool_address.as_ref()))
+                    .map(drop)?)
+            }),
+        ),
+        (
+            capability("z_getoperationstatus", "following sent payments"),
+            Box::new(|| Ok(client.z_get_operation_status(vec![]).map(drop)?)),
+        ),
+    ];
+
+    if config.utxo_sweep.is_some() {
+        probes.push((
+            capability("listlockunspent", "sweeping the pool utxos"),
+            Box::new(|| Ok(client.list_lock_unspent().map(drop)?)),
+        ));
+    }
+
+    let missing = probes
+        .iter()
+        .filter_map(|(capability, probe)| {
+            let error = probe().err()?;
+            debug!(
+                method = capability.method,
+                ?error,
+                "capability probe failed"
+            );
+
+            Some((*capability, error.to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(report) = missing_capabilities_report(&config.currency_name, &missing) {
+        bail!(report);
+    }
+
+    info!(
+        n_methods = probes.len(),
+        "daemon allows all required rpc methods"
+    );
+
+    Ok(())
+}
+
+fn capability(method: &'static str, used_for: &'static str) -> Capability {
+    Capability { method, used_for }
+}
+
+fn missing_capabilities_report(
+    currency_name: &str,
+    missing: &[(Capability, String)],
+) -> Option<String> {
+    if missing.is_empty() {
+        return None;
+    }
+
+    let lines = missing
+        .iter()
+        .map(|(capability, error)| {
+            format!(
+                "- {} (needed for {}): {error}",
+                capability.method, capability.used_for
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(format!(
+        "the daemon of {currency_name} does not allow {} required rpc method(s):\n{}",
+        missing.len(),
+        lines.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_missing_methods() {
+        assert_eq!(missing_capabilities_report("VRSC", &[]), None);
+
+        let report = missing_capabilities_report(
+            "VRSC",
+            &[(
+                capability("getwalletinfo", "the staking balance of the pool"),
+                "Method not found".to_string(),
+            )],
+        )
+        .unwrap();
+
+        assert_eq!(
+            report,
+            "the daemon of VRSC does not allow 1 required rpc method(s):\n\
+             - getwalletinfo (needed for the staking balance of the pool): Method not found"
+        );
+    }
+}
@@ -1,31 +1,102 @@
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use axum::async_trait;
-use rust_decimal::prelude::FromPrimitive;
+use poollib::api::ApiKey;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
 use tokio::select;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc::{self, error::TryRecvError};
+use tokio::sync::oneshot;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 use tracing::{debug, error, info, instrument, trace, warn};
-use vrsc_rpc::bitcoin::BlockHash;
+use vrsc_rpc::bitcoin::{BlockHash, Txid};
 use vrsc_rpc::client::{Client as VerusClient, RpcApi};
 use vrsc_rpc::json::identity::IdentityPrimary;
 use vrsc_rpc::json::vrsc::{Address, Amount};
 use vrsc_rpc::json::{Block, ValidationType};
 
-use crate::coinstaker::constants::{Stake, StakeStatus};
-use crate::coinstaker::http::WebhookMessage;
+use crate::coinstaker::constants::{
+    stake_from_block, AuditCategory, AuditFinding, AuditReport, ChainPause, DelegatedAddress,
+    EarningsGranularity, EarningsPeriod, Eligibility, EligibilityCheck, EligibilityCondition,
+    FeeOverride, FraudEvidence, HistoricalStake, HistoricalStakeShares, KeyRotation,
+    KeyRotationStatus, ParticipationKind, PayoutRecalculation, PayoutRecalculationChange,
+    RotationProgress, RoundMerge, RoundMergeChange, Stake, StakeStatus, StakerBan, StakerUtxo,
+    StaleStake, UtxoBreakdown,
+};
 use crate::database;
-use crate::http::constants::{StakingSupply, Stats};
-use crate::payout_service::PayoutMember;
+use crate::error::PoolError;
+use crate::events::{EventBus, PoolEvent};
+use crate::http::constants::{NetworkStats, RewardOutlook, StakingSupply, Stats};
+use crate::metrics::{Metric, Metrics};
+use crate::payout_service::{
+    prepare_payment, reconcile_payment_journal, send_journaled_payment, store_sent_payment,
+    Converter, Fees, JournalStatus, Liabilities, ManualPayment, PaymentItem, Payout, PayoutMember,
+    PayoutTrigger, TxFee, Worker,
+};
+use crate::status_page::{self, ChainHealth, ChainLiveness, SubsystemObservation};
+use crate::util::explorer::Explorer;
+use crate::util::reward::BLOCKS_PER_YEAR;
 use crate::util::verus::*;
 
-use super::config::Config as CoinstakerConfig;
-use super::constants::{Staker, StakerEarnings};
-use super::http::Webhook;
-use super::StakerStatus;
+use super::accrual::{balance_spans, time_weighted_shares, EligibleUtxo};
+use super::cold_staking;
+use super::config::{
+    default_status_page_max_blocks_behind, Config as CoinstakerConfig, PrimaryAddressRotation,
+};
+use super::constants::{Staker, StakerActivity, StakerEarnings, StakerStatement, WorkForecast};
+use super::eligibility::{check_identity, check_root_identity, delegation_message};
+use super::forecast::{forecast_work, pending_deposit, utxo_breakdown};
+use super::fraud;
+use super::gap::GapDetector;
+use super::gate::BlockGate;
+use super::http::StakerWebhook;
+use super::key_rotation::{self, AddressValidation};
+use super::maturity::{get_blocks, Maturity};
+use super::pause::{PauseFlag, PausedBlocks};
+use super::quorum::{NoQuorum, Quorum};
+use super::reorg::{find_orphaned, REORG_WINDOW};
+use super::replay::{RecordedEntry, RpcTraffic};
+use super::rpc_pool::RpcPool;
+use super::summary::BlockSummary;
+use super::wallet_check::check_stake_in_wallet;
+use super::{InactiveWorkPolicy, MinPayoutBounds, StakerStatus};
+
+/// The number of historical blocks that are checked during preflight, before pending messages
+/// are handled again.
+const PREFLIGHT_BATCH_SIZE: u64 = 100;
+/// How long the coinstaker waits before it tries again when no daemon is reachable.
+const DAEMON_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The number of blocks over which the historical APY and the luck of the pool are calculated,
+/// about 30 days.
+const POOL_APY_WINDOW: u64 = 43_200;
+
+/// The share of blocks that is assumed to be staked when no network stats are collected.
+const DEFAULT_POS_RATIO: f64 = 0.5;
+
+/// A metric update that is held back while a block is being processed.
+#[derive(Debug, Clone, Copy)]
+enum MetricUpdate {
+    Inc(Metric),
+    Add(Metric, f64),
+    Set(Metric, f64),
+    SetNow(Metric),
+}
+
+impl MetricUpdate {
+    fn apply(self, metrics: &Metrics, currency: &Address) {
+        match self {
+            MetricUpdate::Inc(metric) => metrics.inc(currency, metric),
+            MetricUpdate::Add(metric, value) => metrics.add(currency, metric, value),
+            MetricUpdate::Set(metric, value) => metrics.set(currency, metric, value),
+            MetricUpdate::SetNow(metric) => metrics.set_now(currency, metric),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct CoinStaker {
@@ -34,7 +105,28 @@ pub struct CoinStaker {
     tx: mpsc::Sender<CoinStakerMessage>,
     rx: mpsc::Receiver<CoinStakerMessage>,
     pub chain_id: Address,
-    webhooks: Webhook,
+    events: EventBus,
+    /// The events of the block that is being processed, which are published once the block is
+    /// committed.
+    held_events: Mutex<Option<Vec<PoolEvent>>>,
+    /// The metric updates of the block that is being processed, which are applied once the
+    /// block is committed.
+    held_metrics: Mutex<Option<Vec<MetricUpdate>>>,
+    gate: BlockGate,
+    gaps: GapDetector,
+    /// The height from which the pool is catching up with the chain.
+    catch_up_from: Option<u64>,
+    startup_audit: Option<AuditReport>,
+    traffic: Option<RpcTraffic>,
+    rpc: RpcPool,
+    /// The other daemons that have to agree before the work or a stake of a block is recorded.
+    quorum: Option<Quorum>,
+    payout_trigger: PayoutTrigger,
+    pause: PauseFlag,
+    paused_blocks: PausedBlocks,
+    /// When the coinstaker found that no daemon was reachable, to notify the operator once.
+    unreachable_since: Option<Instant>,
+    metrics: Metrics,
 }
 
 impl CoinStaker {
@@ -43,9 +135,18 @@ impl CoinStaker {
         config: CoinstakerConfig,
         tx: mpsc::Sender<CoinStakerMessage>,
         rx: mpsc::Receiver<CoinStakerMessage>,
+        events: EventBus,
     ) -> Result<Self> {
-        let webhooks = Webhook::new(config.webhook_endpoints.clone())?;
         let chain_id = config.currency_id.clone();
+        // live blocks are held back until the preflight checks caught up with the chain
+        let gate = if config.skip_preflight {
+            BlockGate::opened()
+        } else {
+            BlockGate::default()
+        };
+
+        let rpc = RpcPool::new(&config.chain_config);
+        let quorum = config.chain_config.quorum.as_ref().map(Quorum::new);
 
         Ok(Self {
             pool,
@@ -53,467 +154,3166 @@ impl CoinStaker {
             tx,
             rx,
             chain_id,
-            webhooks,
+            events,
+            held_events: Mutex::new(None),
+            held_metrics: Mutex::new(None),
+            gate,
+            gaps: GapDetector::default(),
+            catch_up_from: None,
+            startup_audit: None,
+            traffic: None,
+            rpc,
+            quorum,
+            payout_trigger: PayoutTrigger::default(),
+            pause: PauseFlag::default(),
+            paused_blocks: PausedBlocks::default(),
+            unreachable_since: None,
+            metrics: Metrics::default(),
         })
     }
 
-    pub fn verusd(&self) -> Result<VerusClient> {
-        let verus_client = (&self.config.chain_config).try_into()?;
+    /// Records the RPC traffic of this coinstaker, or answers it from a recording.
+    ///
+    /// A replay processes the recorded blocks in order, without catching up with the chain.
+    pub fn with_traffic(mut self, traffic: RpcTraffic) -> Self {
+        if matches!(traffic, RpcTraffic::Replay(_)) {
+            self.gate = BlockGate::opened();
+        }
+        self.traffic = Some(traffic);
+
+        self
+    }
+
+    /// Lets `ProcessPayments` start a payout run of the payout service of this chain.
+    pub fn with_payout_trigger(mut self, payout_trigger: PayoutTrigger) -> Self {
+        self.payout_trigger = payout_trigger;
+
+        self
+    }
+
+    /// Lets `Pause` and `Resume` pause and resume the payout service of this chain.
+    pub fn with_pause_flag(mut self, pause: PauseFlag) -> Self {
+        self.pause = pause;
+
+        self
+    }
+
+    /// Pushes the progress of this coinstaker into the metrics that are exposed on `/metrics`.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+
+        self
+    }
+
+    pub fn verusd(&self) -> Result<Arc<VerusClient>> {
+        if let Some(traffic) = &self.traffic {
+            return Ok(Arc::new(traffic.client(&self.config.chain_config)?));
+        }
+
+        self.rpc.client()
+    }
+
+    /// Keeps the coinstaker running through an error that happened while no daemon was
+    /// reachable, or while the daemons of the quorum didn't agree. The gate is closed, so the
+    /// blocks that are missed in the meantime are caught up with once a daemon answers again,
+    /// like after a restart of the pool.
+    ///
+    /// Other errors are returned.
+    async fn survive_daemon_outage(
+        &mut self,
+        e: anyhow::Error,
+        block_hash: Option<BlockHash>,
+    ) -> Result<()> {
+        if e.downcast_ref::<NoQuorum>().is_some() {
+            warn!(error = %e, "the daemons don't agree, catching up once they do");
+        } else if self.traffic.is_some() || self.rpc.is_reachable() {
+            return Err(e);
+        } else {
+            warn!(error = ?e, "no daemon is reachable, catching up once one is");
+
+            if self.unreachable_since.is_none() {
+                self.unreachable_since = Some(Instant::now());
+                self.publish(PoolEvent::DaemonUnreachable {
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+        self.gate.close();
+        if let Some(block_hash) = block_hash {
+            self.gate.admit(block_hash);
+        }
+
+        tokio::time::sleep(DAEMON_RETRY_INTERVAL).await;
+
+        Ok(())
+    }
+
+    /// Publishes that a daemon answers again, if none did before.
+    fn daemon_reachable(&mut self) {
+        if let Some(since) = self.unreachable_since.take() {
+            info!("a daemon is reachable again");
 
-        Ok(verus_client)
+            self.publish(PoolEvent::DaemonReachable {
+                unreachable_for_secs: since.elapsed().as_secs(),
+            });
+        }
     }
 
     #[instrument(skip(self), fields(coin = self.config.currency_name))]
-    async fn listen(&mut self) -> Result<()> {
+    pub(super) async fn listen(&mut self) -> Result<()> {
         trace!("listening for messages");
 
-        while let Some(msg) = self.rx.recv().await {
-            trace!(?msg, "received new ZMQ message");
-            match msg {
-                CoinStakerMessage::Block(block_hash) => {
-                    // 1. check subscription of currently active subscribers.
-                    // 2. check if any pending stakes have matured
-                    // 3. check if daemon is staking
-                    // 4. add work
-                    // 5. check if the current block hash is a stake (this moves work until now into pending stake)
-                    let verus_client = self.verusd()?;
-                    let block = verus_client.get_block(&block_hash, 2)?;
-                    info!(?block_hash, height = %block.height, "received new block");
-                    // if a staker leaves this round, a last round of work needs to be added to his address,
-                    // as he still could have staked this round's block, he needs to be counted
-                    // in add_work()
-                    // because stakers are active up to and including this round, we need to
-                    // count them towards work and check if they staked, **before** we remove them
-                    // as active stakers
-                    let active_stakers = database::get_stakers_by_status(
-                        &self.pool,
-                        &self.chain_id,
-                        StakerStatus::Active,
-                    )
-                    .await?;
-                    self.check_stakers(&verus_client, &block).await?;
-                    self.check_maturing_stakes(&verus_client).await?;
+        loop {
+            // a paused chain doesn't catch up, its blocks are replayed once it is resumed
+            let msg = if self.gate.is_open() || self.pause.is_paused() {
+                match self.rx.recv().await {
+                    Some(msg) => msg,
+                    None => break,
+                }
+            } else {
+                // catch up with the chain whenever there are no pending messages
+                match self.rx.try_recv() {
+                    Ok(msg) => msg,
+                    Err(TryRecvError::Empty) => {
+                        match self.catch_up().await {
+                            Ok(()) => self.daemon_reachable(),
+                            Err(e) => self.survive_daemon_outage(e, None).await?,
+                        }
+
+                        continue;
+                    }
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            };
 
-                    if self.daemon_is_staking(&verus_client).await? == false {
-                        continue; // don't add work for not staking daemon
-                    };
+            trace!(?msg, "received new ZMQ message");
+            let block_hash = match &msg {
+                CoinStakerMessage::Block(block_hash) => Some(*block_hash),
+                _ => None,
+            };
+            if let Err(e) = self.handle_message(msg).await {
+                self.survive_daemon_outage(e, block_hash).await?;
+            }
+        }
 
-                    self.add_work(&active_stakers, block.height).await?;
-                    database::update_last_height(&self.pool, &self.chain_id, block.height).await?;
+        Ok(())
+    }
 
-                    self.check_for_stake(&block_hash).await?;
+    async fn handle_message(&mut self, msg: CoinStakerMessage) -> Result<()> {
+        match msg {
+            CoinStakerMessage::Block(block_hash) => {
+                if let Some(RpcTraffic::Record(recorder)) = &self.traffic {
+                    recorder.record(&RecordedEntry::Block(block_hash));
                 }
-                CoinStakerMessage::StakingSupply(os_tx, identity_addresses) => {
-                    let res = self.get_staking_supply(identity_addresses).await?;
 
-                    if os_tx.send(res).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if self.pause.is_paused() {
+                    debug!(?block_hash, "chain is paused, recording the block");
+                    self.paused_blocks.record(block_hash);
+                } else {
+                    self.handle_block(block_hash).await?;
+                }
+            }
+            CoinStakerMessage::CheckForGaps => {
+                // while catching up, the missed blocks are handled by the catch-up, and while
+                // paused, by the gap check after the chain is resumed
+                if self.gate.is_open() && !self.pause.is_paused() {
+                    self.recover_missed_blocks()?;
+                }
+            }
+            CoinStakerMessage::Pause(os_tx) => {
+                if !self.pause.is_paused() {
+                    warn!("chain paused by an operator, no work is added and no payouts are made");
+                    self.pause.set(true);
                 }
-                CoinStakerMessage::StakerStatus(os_tx, identity_address) => {
-                    let verus_client = self.verusd()?;
-                    let opt_staker = self
-                        .check_staker_status(&verus_client, &identity_address)
-                        .await?;
 
-                    os_tx
-                        .send(opt_staker)
-                        .expect("a oneshot message failed to send");
-                }
-                CoinStakerMessage::GetStakers(os_tx, identity_addresses, staker_status) => {
-                    let staker = if let Some(status) = staker_status {
-                        // TODO build a better query for this:
-                        database::get_stakers_by_status(&self.pool, &self.chain_id, status)
-                            .await?
-                            .into_iter()
-                            .filter(|s| identity_addresses.contains(&s.identity_address))
-                            .collect::<Vec<_>>()
-                    } else {
-                        database::get_stakers_by_identity_address(
-                            &self.pool,
-                            &self.chain_id,
-                            &identity_addresses,
-                        )
-                        .await?
-                    };
-                    if os_tx.send(staker).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if os_tx.send(self.pause_status()).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::Resume(os_tx) => {
+                let status = self.pause_status();
+                let recorded = self.paused_blocks.take();
+                if self.pause.is_paused() {
+                    info!(blocks = %recorded.len(), "chain resumed by an operator, replaying the recorded blocks");
+                    self.pause.set(false);
                 }
-                CoinStakerMessage::GetPayouts(os_tx, identity_addresses) => {
-                    let mut conn = self.pool.acquire().await?;
-                    let payout_members = database::get_payout_members(
-                        &mut conn,
-                        &self.chain_id,
-                        &identity_addresses,
-                    )
-                    .await?;
 
-                    if os_tx.send(payout_members).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if os_tx
+                    .send(ChainPause {
+                        paused: false,
+                        ..status
+                    })
+                    .is_err()
+                {
+                    Err(anyhow!("the sender dropped"))?
                 }
-                CoinStakerMessage::GetStakes(os_tx, stake_status) => {
-                    let stakes = if let Some(status) = stake_status {
-                        database::get_stakes_by_status(&self.pool, &self.chain_id, status, None)
-                            .await?
-                    } else {
-                        database::get_stakes(&self.pool, &self.chain_id, None).await?
-                    };
 
-                    if os_tx.send(stakes).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                self.replay_paused_blocks(recorded).await?;
+            }
+            CoinStakerMessage::StakingSupply(os_tx, identity_addresses) => {
+                let res = self.get_staking_supply(identity_addresses).await?;
+
+                if os_tx.send(res).is_err() {
+                    Err(anyhow!("the sender dropped"))?
                 }
-                CoinStakerMessage::GetStakerEarnings(os_tx, identity_addresses) => {
-                    let mut conn = self.pool.acquire().await?;
-                    let payout_members = database::get_payout_members(
-                        &mut conn,
-                        &self.chain_id,
-                        &identity_addresses,
-                    )
+            }
+            CoinStakerMessage::StakerStatus(os_tx, identity_address) => {
+                let verus_client = self.verusd()?;
+                let mut conn = self.pool.acquire().await?;
+                let mut opt_staker = self
+                    .check_staker_status(&mut conn, &verus_client, &identity_address)
                     .await?;
 
-                    let mut hm = HashMap::new();
-
-                    for pm in payout_members {
-                        hm.entry(pm.identity_address.clone())
-                            .and_modify(|bal: &mut StakerEarnings| {
-                                if pm.txid.is_none() {
-                                    bal.pending += pm.reward
-                                } else {
-                                    bal.paid += pm.reward
-                                }
-                            })
-                            .or_insert(StakerEarnings::from(pm));
-                    }
+                if let Some(staker) = opt_staker.as_mut() {
+                    staker.forecast = Some(self.forecast_work(&verus_client, staker).await?);
 
-                    if os_tx.send(hm).is_err() {
-                        Err(anyhow!("the sender dropped"))?
+                    if let Some(stored) =
+                        database::get_staker(&mut conn, &self.chain_id, &identity_address).await?
+                    {
+                        staker.created_at = stored.created_at;
+                        staker.updated_at = stored.updated_at;
                     }
                 }
-                CoinStakerMessage::GetStakingBalance(os_tx, identity_addresses) => {
-                    let verus_client = self.verusd()?;
 
-                    let active_addresses = database::get_stakers_by_identity_address(
+                os_tx
+                    .send(opt_staker)
+                    .expect("a oneshot message failed to send");
+            }
+            CoinStakerMessage::DelegateStaking(os_tx, identity_address, address, signature) => {
+                let verus_client = self.verusd()?;
+                let result = self
+                    .delegate_staking(&verus_client, &identity_address, &address, &signature)
+                    .await;
+
+                if os_tx.send(result).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetStakers(os_tx, identity_addresses, staker_status) => {
+                let staker = if let Some(status) = staker_status {
+                    // TODO build a better query for this:
+                    database::get_stakers_by_status(
+                        &mut *self.pool.acquire().await?,
+                        &self.chain_id,
+                        status,
+                    )
+                    .await?
+                    .into_iter()
+                    .filter(|s| identity_addresses.contains(&s.identity_address))
+                    .collect::<Vec<_>>()
+                } else {
+                    database::get_stakers_by_identity_address(
                         &self.pool,
                         &self.chain_id,
                         &identity_addresses,
                     )
                     .await?
-                    .iter()
-                    .map(|staker| staker.identity_address.clone())
-                    .collect::<Vec<_>>();
-
-                    let utxos = if !active_addresses.is_empty() {
-                        verus_client.list_unspent(
-                            Some(150),
-                            None,
-                            Some(active_addresses.as_ref()),
-                        )?
-                    } else {
-                        vec![]
-                    };
-
-                    let payload = utxos
-                        .into_iter()
-                        .filter(|utxo| utxo.amount.is_positive())
-                        // unwrap because we already filtered the positive
-                        .map(|utxo| (utxo.address.unwrap(), utxo.amount.to_unsigned().unwrap()))
-                        .fold(HashMap::new(), |mut acc, (address, amount)| {
-                            let _ = *acc
-                                .entry(address)
-                                .and_modify(|a| *a += amount)
-                                .or_insert(amount);
-                            acc
-                        });
+                };
+                if os_tx.send(staker).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetStakerActivity(os_tx, identity_address, before, limit) => {
+                let activity = database::get_staker_activity(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_address,
+                    before,
+                    limit,
+                )
+                .await?;
 
-                    if os_tx.send(payload).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if os_tx.send(activity).is_err() {
+                    Err(anyhow!("the sender dropped"))?
                 }
-                CoinStakerMessage::PoolPrimaryAddress(os_tx) => {
-                    let pool_address = self.config.pool_primary_address.to_string();
+            }
+            CoinStakerMessage::GetStakerStatements(os_tx, identity_address) => {
+                let statements =
+                    database::get_staker_statements(&self.pool, &self.chain_id, &identity_address)
+                        .await?;
 
-                    if os_tx.send(pool_address).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if os_tx.send(statements).is_err() {
+                    Err(anyhow!("the sender dropped"))?
                 }
-                CoinStakerMessage::SetStaking(enable_staking) => {
-                    let verus_client = self.verusd()?;
+            }
+            CoinStakerMessage::GetEarningsTimeseries(os_tx, identity_address, granularity) => {
+                let periods = database::get_earnings_timeseries(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_address,
+                    granularity,
+                )
+                .await?;
 
-                    verus_client.set_generate(enable_staking, 0)?;
+                if os_tx.send(periods).is_err() {
+                    Err(anyhow!("the sender dropped"))?
                 }
-                CoinStakerMessage::GetStatistics(os_tx) => {
-                    let (stakes, stakers, rewards) = tokio::try_join!(
-                        database::get_number_of_matured_stakes(&self.pool, &self.chain_id),
-                        database::get_number_of_active_stakers(&self.pool, &self.chain_id),
-                        database::get_total_rewards(&self.pool, &self.chain_id)
-                    )?;
+            }
+            CoinStakerMessage::GetStaleStakes(os_tx, identity_address) => {
+                let stale_stakes =
+                    database::get_stale_stakes(&self.pool, &self.chain_id, &identity_address)
+                        .await?;
 
-                    let pool_staking_supply =
-                        self.verusd()?.get_wallet_info()?.eligible_staking_balance;
+                if os_tx.send(stale_stakes).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::CheckEligibility(os_tx, identity) => {
+                let eligibility = self.check_eligibility(&self.verusd()?, &identity).await?;
 
-                    let stats = Stats {
-                        stakes,
-                        pool_staking_supply,
-                        paid: rewards,
-                        stakers,
-                    };
+                if os_tx.send(eligibility).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetUtxoBreakdown(os_tx, identity_address) => {
+                let breakdown = self
+                    .utxo_breakdown(&self.verusd()?, &identity_address)
+                    .await?;
 
-                    if os_tx.send(stats).is_err() {
-                        Err(anyhow!("the sender dropped"))?
-                    }
+                if os_tx.send(breakdown).is_err() {
+                    Err(anyhow!("the sender dropped"))?
                 }
             }
-        }
+            CoinStakerMessage::GetPayouts(os_tx, identity_addresses) => {
+                let mut conn = self.pool.acquire().await?;
+                let payout_members =
+                    database::get_payout_members(&mut conn, &self.chain_id, &identity_addresses)
+                        .await?;
 
-        Ok(())
-    }
+                if os_tx.send(payout_members).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetLiabilities(os_tx) => {
+                let stakers = database::get_staker_liabilities(&self.pool, &self.chain_id).await?;
 
-    async fn check_maturing_stakes(&self, client: &VerusClient) -> Result<()> {
-        let maturing_stakes =
-            database::get_stakes_by_status(&self.pool, &self.chain_id, StakeStatus::Maturing, None)
-                .await?;
+                if os_tx
+                    .send(Liabilities::new(self.chain_id.clone(), stakers))
+                    .is_err()
+                {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetStakes(os_tx, stake_status) => {
+                let mut conn = self.pool.acquire().await?;
+                let stakes = if let Some(status) = stake_status {
+                    database::get_stakes_by_status(&mut conn, &self.chain_id, status, None).await?
+                } else {
+                    database::get_stakes(&self.pool, &self.chain_id, None).await?
+                };
 
-        for mut stake in maturing_stakes {
-            let block = client.get_block(&stake.block_hash, 2)?;
+                if os_tx.send(stakes).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetStakerEarnings(os_tx, identity_addresses) => {
+                let mut conn = self.pool.acquire().await?;
+                let payout_members =
+                    database::get_payout_members(&mut conn, &self.chain_id, &identity_addresses)
+                        .await?;
 
-            if block.confirmations < 0 {
-                trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake is stale");
+                let mut hm = HashMap::new();
 
-                database::move_work_to_round_zero(&self.pool, &self.chain_id, block.height).await?;
-                stake.status = StakeStatus::Stale;
-                database::store_stake(&self.pool, &stake).await?;
+                for pm in payout_members {
+                    hm.entry(pm.identity_address.clone())
+                        .and_modify(|bal: &mut StakerEarnings| {
+                            if pm.txid.is_none() {
+                                bal.pending += pm.reward
+                            } else {
+                                bal.paid += pm.reward
+                            }
+                        })
+                        .or_insert(StakerEarnings::from(pm));
+                }
 
-                self.webhooks
-                    .send(WebhookMessage::StakeStale {
-                        hash: stake.block_hash,
-                        height: stake.block_height,
-                    })
-                    .await;
+                let archived =
+                    database::get_archived_rewards(&mut conn, &self.chain_id, &identity_addresses)
+                        .await?;
+                for (identity_address, reward) in archived {
+                    hm.entry(identity_address)
+                        .or_insert(StakerEarnings {
+                            paid: Amount::ZERO,
+                            pending: Amount::ZERO,
+                        })
+                        .paid += reward;
+                }
 
-                return Ok(());
+                if os_tx.send(hm).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
             }
+            CoinStakerMessage::GetStakingBalance(os_tx, identity_addresses) => {
+                let verus_client = self.verusd()?;
 
-            if block.confirmations < 100 {
-                if check_stake_guard(&block).await? {
-                    trace!("The transaction was spent by stakeguard");
-                    stake.status = StakeStatus::StakeGuard;
+                let mut active_addresses = database::get_stakers_by_identity_address(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_addresses,
+                )
+                .await?
+                .iter()
+                .map(|staker| staker.identity_address.clone())
+                .collect::<Vec<_>>();
 
-                    database::store_stake(&self.pool, &stake).await?;
-                    // TODO punish perpetrator
-                    // TODO send webhook message
+                let delegators = self
+                    .get_delegators(&mut *self.pool.acquire().await?, &active_addresses)
+                    .await?;
+                active_addresses.extend(delegators.keys().cloned());
 
-                    return Ok(());
-                }
+                let utxos = if !active_addresses.is_empty() {
+                    verus_client.list_unspent(
+                        Some(self.config.utxo_eligibility_confirmations as usize),
+                        None,
+                        Some(active_addresses.as_ref()),
+                    )?
+                } else {
+                    vec![]
+                };
 
-                trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake still maturing");
-            } else {
-                trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake has matured");
+                let payload = utxos
+                    .into_iter()
+                    .filter(|utxo| utxo.amount.is_positive())
+                    // unwrap because we already filtered the positive
+                    .map(|utxo| (utxo.address.unwrap(), utxo.amount.to_unsigned().unwrap()))
+                    .map(|(address, amount)| {
+                        (delegators.get(&address).cloned().unwrap_or(address), amount)
+                    })
+                    .fold(HashMap::new(), |mut acc, (address, amount)| {
+                        let _ = *acc
+                            .entry(address)
+                            .and_modify(|a| *a += amount)
+                            .or_insert(amount);
+                        acc
+                    });
 
-                stake.status = StakeStatus::Matured;
-                database::store_stake(&self.pool, &stake).await?;
+                if os_tx.send(payload).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::PoolPrimaryAddress(os_tx) => {
+                // new stakers should use the new address while a rotation is going on
+                let pool_address = match &self.config.primary_address_rotation {
+                    Some(rotation) => rotation.new_address.to_string(),
+                    None => self.config.pool_primary_address.to_string(),
+                };
 
-                self.webhooks
-                    .send(WebhookMessage::StakeMatured {
-                        hash: stake.block_hash,
-                        height: stake.block_height,
-                    })
-                    .await;
+                if os_tx.send(pool_address).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
             }
-        }
-        // get pending stakes from database
-        // check if any has matured
-        // check if stake was stolen
-        // if stake matured
-        // - send webhooks message
-        // - send matured_block message to self
-        Ok(())
-    }
+            CoinStakerMessage::SetStaking(enable_staking) => {
+                let verus_client = self.verusd()?;
 
-    async fn daemon_is_staking(&self, client: &VerusClient) -> Result<bool> {
-        if !client.get_mining_info()?.staking {
-            error!("daemon not staking, not counting work");
+                verus_client.set_generate(enable_staking, 0)?;
+            }
+            CoinStakerMessage::GetStatistics(os_tx) => {
+                let verus_client = self.verusd()?;
+                let luck_from_height = verus_client
+                    .get_blockchain_info()?
+                    .blocks
+                    .saturating_sub(POOL_APY_WINDOW);
 
-            return Ok(false);
-        }
+                let (stakes, stakers, rewards, luck) = tokio::try_join!(
+                    database::get_number_of_matured_stakes(&self.pool, &self.chain_id),
+                    database::get_number_of_active_stakers(&self.pool, &self.chain_id),
+                    database::get_total_rewards(&self.pool, &self.chain_id),
+                    database::get_pool_luck(&self.pool, &self.chain_id, luck_from_height)
+                )?;
 
-        Ok(true)
-    }
+                let pool_staking_supply = verus_client.get_wallet_info()?.eligible_staking_balance;
 
-    /// Add work for every staker that was active until this round
-    ///
-    /// For a staker to have work added, the following conditions apply:
-    /// - the verusid is not cooling down (150 blocks after a change)
-    /// - the UTXOs that are used for staking must have 150+ confirmations
-    ///
-    /// An exception is made when an UTXO is cooling down after mining a block
-    /// for the staking pool. It is still counted towards work.
-    async fn add_work(&self, active_stakers: &[Staker], blockheight: u64) -> Result<()> {
-        let verus_client = self.verusd()?;
+                let stats = Stats {
+                    stakes,
+                    pool_staking_supply,
+                    paid: rewards,
+                    stakers,
+                    luck,
+                };
 
-        let active_staker_addresses = active_stakers
-            .iter()
-            .map(|subscriber| subscriber.identity_address.clone())
-            .collect::<Vec<Address>>();
+                if os_tx.send(stats).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetKeyRotation(os_tx) => {
+                let status = self.key_rotation_status().await?;
 
-        if active_staker_addresses.is_empty() {
-            return Ok(());
-        }
+                if os_tx.send(status).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::StartKeyRotation(os_tx, new_address, ends_at, operator) => {
+                let rotation = self
+                    .start_key_rotation(new_address, ends_at, operator)
+                    .await;
 
-        let eligible_stakers =
-            verus_client.list_unspent(Some(150), None, Some(active_staker_addresses.as_ref()))?;
+                if os_tx.send(rotation).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::RetireKeyRotation(os_tx, operator) => {
+                let rotation = self.retire_key_rotation(operator).await;
 
-        let mut payload = eligible_stakers
-            .into_iter()
-            .filter(|lu| lu.amount.is_positive())
-            .map(|lu| {
-                (
-                    lu.address.unwrap(),
-                    Decimal::from_u64(lu.amount.to_unsigned().unwrap().as_sat()).unwrap(),
-                )
-            })
-            .fold(HashMap::new(), |mut acc, (address, amount)| {
-                let _ = *acc
-                    .entry(address)
-                    .and_modify(|mut a| a += amount)
-                    .or_insert(amount);
-                acc
-            });
+                if os_tx.send(rotation).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetRotationProgress(os_tx) => {
+                let progress = if let Some(rotation) = &self.config.primary_address_rotation {
+                    database::get_rotation_progress(
+                        &mut *self.pool.acquire().await?,
+                        &self.chain_id,
+                        &rotation.new_address,
+                    )
+                    .await?
+                } else {
+                    vec![]
+                };
 
-        let stakes_to_compensate =
-            database::get_stakes_to_compensate(&self.pool, &self.chain_id, blockheight as i64)
-                .await?;
+                if os_tx.send(progress).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::MergeRounds(os_tx, from_round, into_round, dry_run) => {
+                let merge = self.merge_rounds(from_round, into_round, dry_run).await;
 
-        stakes_to_compensate.iter().for_each(|stake| {
-            if payload.contains_key(&stake.found_by) {
-                payload.entry(stake.found_by.clone()).and_modify(|v| {
-                    debug!(
-                        amount_to_add = %stake.source_amount.as_vrsc(),
-                        staker = %stake.found_by,
-                        blockheight = &stake.block_height,
-                        "compensate work of immature utxo because it staked"
-                    );
-                    *v += Decimal::from_i64(stake.source_amount.as_sat() as i64).unwrap()
-                });
+                if os_tx.send(merge).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
             }
-        });
+            CoinStakerMessage::RecalculatePayout(os_tx, block_hash, dry_run) => {
+                let recalculation = self.recalculate_payout(block_hash, dry_run).await;
 
-        debug!(?payload, "storing work");
+                if os_tx.send(recalculation).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::InsertHistoricalStake(os_tx, block_hash, dry_run) => {
+                let stake = self.insert_historical_stake(block_hash, dry_run).await;
 
-        database::store_work(&self.pool, &self.chain_id, payload, blockheight).await?;
+                if os_tx.send(stake).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::SetFeeOverride(os_tx, identity_address, fee, reason) => {
+                let fee_override = self.set_fee_override(identity_address, fee, reason).await;
 
-        Ok(())
-    }
+                if os_tx.send(fee_override).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::RegisterColdStaker(os_tx, address, payout_address, signature) => {
+                let staker = self
+                    .register_cold_staker(address, payout_address, &signature)
+                    .await;
 
-    #[instrument(skip(self))]
-    async fn check_for_stake(&self, block_hash: &BlockHash) -> Result<()> {
-        if let Some(stake) = self.is_stake(block_hash).await? {
-            info!(height = %stake.block_height, ">>>>>>>>>>>>>>> stake found");
+                if os_tx.send(staker).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::PayStaker(os_tx, identity_address, operator, reason) => {
+                let payment = self.pay_staker(identity_address, operator, reason).await;
 
-            database::store_new_stake(&self.pool, &stake).await?;
+                if os_tx.send(payment).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::BanStaker(os_tx, identity_address, operator, reason) => {
+                let ban = self
+                    .ban_staker_on_request(identity_address, operator, reason)
+                    .await;
 
-            let client = self.verusd()?;
-            let currency_name = client
-                .get_currency(&stake.currency_address.to_string())?
-                .fullyqualifiedname;
+                if os_tx.send(ban).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::ProcessPayments(os_tx) => {
+                info!("starting a payout run on request");
+                self.payout_trigger.trigger();
 
-            self.webhooks
-                .send(WebhookMessage::new_stake(currency_name, &stake))
-                .await;
-        }
+                if os_tx.send(()).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::VerifyMessage(os_tx, identity_address, message, signature) => {
+                let valid = self.verify_message(&identity_address, &message, &signature)?;
 
-        Ok(())
-    }
+                if os_tx.send(valid).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::CreateApiKey(os_tx, identity_address, key_hash, label) => {
+                let api_key = database::store_api_key(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_address,
+                    &key_hash,
+                    &label,
+                )
+                .await?;
 
-    async fn is_stake(&self, block_hash: &BlockHash) -> Result<Option<Stake>> {
-        let client = self.verusd()?;
-        let block = client.get_block(block_hash, 2)?;
+                if os_tx.send(api_key).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetApiKeys(os_tx, identity_address) => {
+                let api_keys =
+                    database::get_api_keys(&self.pool, &self.chain_id, &identity_address).await?;
 
-        // block.confirmations == -1 indicates it is stale and should be ignored
-        if matches!(block.validation_type, ValidationType::Stake) && block.confirmations >= 0 {
-            let postxddest = postxddest(&block)?;
+                if os_tx.send(api_keys).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::RevokeApiKey(os_tx, identity_address, id) => {
+                let revoked =
+                    database::revoke_api_key(&self.pool, &self.chain_id, &identity_address, id)
+                        .await?;
 
-            if let Some(stake) = self.is_staked_by_pool(&block, &postxddest).await? {
-                return Ok(Some(stake));
+                if os_tx.send(revoked).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::UseApiKey(os_tx, key_hash) => {
+                let identity_address =
+                    database::use_api_key(&self.pool, &self.chain_id, &key_hash).await?;
+
+                if os_tx.send(identity_address).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::SetMinPayout(os_tx, identity_address, min_payout) => {
+                let result = self.set_min_payout(&identity_address, min_payout).await;
+
+                if os_tx.send(result).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::SetPayoutCurrency(os_tx, identity_address, payout_currency) => {
+                let result = self
+                    .set_payout_currency(&identity_address, payout_currency)
+                    .await;
+
+                if os_tx.send(result).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::SetStakerWebhook(os_tx, identity_address, webhook) => {
+                let result = database::set_staker_webhook(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_address,
+                    webhook.as_ref(),
+                )
+                .await;
+
+                if os_tx.send(result).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetStartupAudit(os_tx) => {
+                if os_tx.send(self.startup_audit.clone()).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetFraudEvidence(os_tx) => {
+                let evidence = database::get_fraud_evidence(&self.pool, &self.chain_id).await?;
+
+                if os_tx.send(evidence).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetMinPayoutBounds(os_tx) => {
+                if os_tx.send(self.config.min_payout_bounds()).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetConfig(os_tx) => {
+                if os_tx.send(self.config.clone()).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetExplorer(os_tx) => {
+                let explorer = self
+                    .config
+                    .explorer_url_template
+                    .as_deref()
+                    .map(Explorer::new)
+                    .transpose()?;
+
+                if os_tx.send(explorer).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetHealth(os_tx) => {
+                let max_blocks_behind = self
+                    .config
+                    .status_page
+                    .as_ref()
+                    .map(|status_page| status_page.max_blocks_behind)
+                    .unwrap_or_else(default_status_page_max_blocks_behind);
+                let observation =
+                    status_page::observe(&self.config.chain_config, &self.pool, &self.chain_id)
+                        .await
+                        .map_err(|e| e.to_string());
+
+                let health = ChainHealth::new(
+                    self.chain_id.clone(),
+                    self.config.currency_name.clone(),
+                    observation,
+                    max_blocks_behind,
+                    SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                );
+
+                if os_tx.send(health).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetLiveness(os_tx) => {
+                let liveness = self.liveness().await?;
+
+                if os_tx.send(liveness).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetRewardOutlook(os_tx) => {
+                let outlook = self.reward_outlook(&self.verusd()?).await?;
+
+                if os_tx.send(outlook).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+            CoinStakerMessage::GetNetworkStats(os_tx, from_height, limit) => {
+                let network_stats =
+                    database::get_network_stats(&self.pool, &self.chain_id, from_height, limit)
+                        .await?;
+
+                if os_tx.send(network_stats).is_err() {
+                    Err(anyhow!("the sender dropped"))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Processes a block of a notification, unless it was already processed or the coinstaker is
+    /// catching up with the chain.
+    async fn handle_block(&mut self, block_hash: BlockHash) -> Result<()> {
+        if self.gaps.is_processed(&block_hash) {
+            trace!(?block_hash, "block was already processed");
+        } else if let Some(block_hash) = self.gate.admit(block_hash) {
+            self.handle_reorg(&block_hash).await?;
+            self.process_block(block_hash).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Processes the blocks that were recorded while the chain was paused, in the order they
+    /// arrived.
+    ///
+    /// If a block fails, it and the blocks after it are held back by the gate, to be processed
+    /// after the catch-up that follows the failure.
+    async fn replay_paused_blocks(&mut self, recorded: Vec<BlockHash>) -> Result<()> {
+        let mut recorded = recorded.into_iter();
+
+        while let Some(block_hash) = recorded.next() {
+            if let Err(e) = self.handle_block(block_hash).await {
+                self.gate.close();
+                for block_hash in std::iter::once(block_hash).chain(recorded) {
+                    self.gate.admit(block_hash);
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn pause_status(&self) -> ChainPause {
+        ChainPause {
+            currency_address: self.chain_id.clone(),
+            paused: self.pause.is_paused(),
+            recorded_blocks: self.paused_blocks.len() as u64,
+        }
+    }
+
+    /// Processes a block in a single database transaction, so that a block is applied
+    /// all-or-nothing and can be processed again after a crash.
+    ///
+    /// The events and metric updates of the block are held back until the transaction is
+    /// committed, so that nothing is published or counted for a block that is rolled back.
+    async fn process_block(&mut self, block_hash: BlockHash) -> Result<()> {
+        let mut tx = database::process_block(&self.pool).await?;
+
+        self.hold_events();
+        let applied = self.apply_block(&mut tx, block_hash).await;
+        let block_height = self.commit_block(tx, applied).await?;
+        self.gaps.processed(block_hash, block_height);
+
+        Ok(())
+    }
+
+    /// Commits the transaction of a block and publishes the events and applies the metric
+    /// updates that were held back, or drops them all if the block could not be applied.
+    async fn commit_block<T>(
+        &self,
+        tx: Transaction<'static, Postgres>,
+        applied: Result<T>,
+    ) -> Result<T> {
+        let events = self
+            .held_events
+            .lock()
+            .expect("lock poisoned")
+            .take()
+            .unwrap_or_default();
+        let metric_updates = self
+            .held_metrics
+            .lock()
+            .expect("lock poisoned")
+            .take()
+            .unwrap_or_default();
+        let value = applied?;
+
+        tx.commit().await?;
+        for event in events {
+            self.events.publish(event);
+        }
+        for update in metric_updates {
+            update.apply(&self.metrics, &self.chain_id);
+        }
+
+        Ok(value)
+    }
+
+    fn hold_events(&self) {
+        *self.held_events.lock().expect("lock poisoned") = Some(vec![]);
+        *self.held_metrics.lock().expect("lock poisoned") = Some(vec![]);
+    }
+
+    /// Publishes an event, or holds it back while a block is being processed.
+    fn publish(&self, event: PoolEvent) {
+        match self.held_events.lock().expect("lock poisoned").as_mut() {
+            Some(held) => held.push(event),
+            None => self.events.publish(event),
+        }
+    }
+
+    /// Applies a metric update, or holds it back while a block is being processed.
+    fn record(&self, update: MetricUpdate) {
+        match self.held_metrics.lock().expect("lock poisoned").as_mut() {
+            Some(held) => held.push(update),
+            None => update.apply(&self.metrics, &self.chain_id),
+        }
+    }
+
+    /// Applies a block and returns its height.
+    async fn apply_block(&self, conn: &mut PgConnection, block_hash: BlockHash) -> Result<u64> {
+        // 1. check subscription of currently active subscribers.
+        // 2. check if any pending stakes have matured
+        // 3. check if daemon is staking
+        // 4. add work
+        // 5. check if the current block hash is a stake (this moves work until now into pending stake)
+        let verus_client = self.verusd()?;
+        let rpc_started = Instant::now();
+        let block = verus_client.get_block(&block_hash, 2)?;
+        self.metrics
+            .observe_rpc_latency(&self.chain_id, rpc_started.elapsed());
+        info!(?block_hash, height = %block.height, "received new block");
+
+        if let Some(quorum) = &self.quorum {
+            quorum.confirm_block(block.height, &block_hash).await?;
+        }
+
+        let mut summary = BlockSummary::new(block_hash, block.height);
+        let mut started = Instant::now();
+
+        // if a staker leaves this round, a last round of work needs to be added to his address,
+        // as he still could have staked this round's block, he needs to be counted
+        // in add_work()
+        // because stakers are active up to and including this round, we need to
+        // count them towards work and check if they staked, **before** we remove them
+        // as active stakers
+        let active_stakers =
+            database::get_stakers_by_status(conn, &self.chain_id, StakerStatus::Active).await?;
+        summary.identities_checked = self.check_stakers(conn, &verus_client, &block).await?;
+        summary.phase_done("stakers", &mut started);
+
+        self.process_primary_address_rotation(conn, &verus_client)
+            .await?;
+        summary.phase_done("rotation", &mut started);
+
+        self.check_maturing_stakes(conn, &verus_client).await?;
+        summary.phase_done("maturing_stakes", &mut started);
+
+        if self.config.collect_network_stats {
+            self.collect_network_stats(conn, &verus_client, &block)
+                .await?;
+            summary.phase_done("network_stats", &mut started);
+        }
+
+        // don't add work for not staking daemon
+        summary.daemon_staking = self.daemon_is_staking(&verus_client).await?;
+
+        self.store_network_snapshot(conn, &verus_client, &block, summary.daemon_staking)
+            .await?;
+        summary.phase_done("network_snapshot", &mut started);
+
+        if summary.daemon_staking {
+            (summary.stakers_counted, summary.shares_added) =
+                self.add_work(conn, &active_stakers, block.height).await?;
+            database::update_last_height(conn, &self.chain_id, block.height).await?;
+            summary.phase_done("work", &mut started);
+
+            summary.stake_found = self.check_for_stake(conn, &block_hash).await?;
+            summary.phase_done("stake", &mut started);
+
+            self.forfeit_departed_work(conn, summary.stake_found)
+                .await?;
+            summary.phase_done("forfeits", &mut started);
+        } else {
+            // the pool couldn't have staked this block, so it is not credited to the stakers
+            // when the daemon stakes again
+            database::store_time_weighted_work(conn, &self.chain_id, &[], block.height).await?;
+        }
+
+        self.close_departed_stakers(conn).await?;
+        summary.phase_done("statements", &mut started);
+
+        self.record_block_summary(conn, summary).await?;
+
+        Ok(block.height)
+    }
+
+    /// Rolls back the blocks that were processed but are no longer in the chain, and processes
+    /// the blocks that replaced them, up to the new block `block_hash`.
+    ///
+    /// The stakes in orphaned blocks become stale: their work moves back to round 0 and their
+    /// payout is deleted. A payout that was already (partially) paid can't be rolled back and
+    /// is logged instead. The work of a height is only added once, so replaying the chain
+    /// doesn't add work again.
+    async fn handle_reorg(&mut self, block_hash: &BlockHash) -> Result<()> {
+        let client = self.verusd()?;
+
+        let processed =
+            database::get_processed_blocks(&self.pool, &self.chain_id, REORG_WINDOW).await?;
+        let orphaned = find_orphaned(&processed, |height| {
+            Ok(client.call::<BlockHash>("getblockhash", &[height.into()])?)
+        })?;
+
+        let Some(fork_height) = orphaned.iter().map(|(height, _)| *height).min() else {
+            return Ok(());
+        };
+        let orphaned_hashes = orphaned
+            .iter()
+            .map(|(_, block_hash)| *block_hash)
+            .collect::<Vec<_>>();
+        warn!(%fork_height, depth = %orphaned.len(), "chain reorganized, rolling back orphaned blocks");
+        self.gaps.forget(&orphaned_hashes);
+
+        let mut orphaned_stakes = vec![];
+        let mut conn = self.pool.acquire().await?;
+        for status in [StakeStatus::Maturing, StakeStatus::Matured] {
+            for mut stake in database::get_stakes_by_status(
+                &mut conn,
+                &self.chain_id,
+                status,
+                Some(fork_height.saturating_sub(1)),
+            )
+            .await?
+            .into_iter()
+            .filter(|stake| orphaned_hashes.contains(&stake.block_hash))
+            {
+                let mut tx = self.pool.begin().await?;
+                if let Err(e) =
+                    database::delete_unpaid_payout(&mut tx, &self.chain_id, &stake.block_hash).await
+                {
+                    error!(block_hash = %stake.block_hash, error = ?e, "could not roll back the payout of an orphaned stake");
+
+                    continue;
+                }
+
+                if let Some(round_id) =
+                    database::get_round_id(&mut tx, &self.chain_id, &stake.block_hash).await?
+                {
+                    database::move_stale_work_to_round_zero(&mut tx, &stake, round_id).await?;
+                }
+                stake.status = StakeStatus::Stale;
+                database::store_stake(&mut tx, &stake).await?;
+                tx.commit().await?;
+
+                orphaned_stakes.push(stake.block_hash);
+                self.metrics.inc(&self.chain_id, Metric::StakesStale);
+                self.publish(PoolEvent::StakeStale(stake));
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+        database::delete_block_summaries(&mut tx, &self.chain_id, &orphaned_hashes).await?;
+        tx.commit().await?;
+
+        self.publish(PoolEvent::ChainReorganized {
+            fork_height,
+            depth: orphaned.len() as u64,
+            orphaned_stakes,
+        });
+
+        let replay_until = orphaned
+            .iter()
+            .map(|(height, _)| *height)
+            .max()
+            .unwrap_or(fork_height);
+        for height in fork_height..=replay_until {
+            let canonical = client.call::<BlockHash>("getblockhash", &[height.into()])?;
+            if &canonical == block_hash {
+                break;
+            }
+
+            self.process_block(canonical).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs, stores and publishes what happened while processing a block.
+    async fn record_block_summary(
+        &self,
+        conn: &mut PgConnection,
+        summary: BlockSummary,
+    ) -> Result<()> {
+        info!(
+            height = %summary.block_height,
+            stakers_counted = %summary.stakers_counted,
+            shares_added = %summary.shares_added,
+            identities_checked = %summary.identities_checked,
+            stake_found = %summary.stake_found,
+            daemon_staking = %summary.daemon_staking,
+            duration_ms = %summary.total_duration().as_millis(),
+            phases = %summary.phases_json(),
+            "processed block"
+        );
+
+        database::store_block_summary(conn, &self.chain_id, &summary).await?;
+
+        self.record(MetricUpdate::Inc(Metric::BlocksProcessed));
+        self.record(MetricUpdate::SetNow(Metric::LastBlockProcessedAt));
+        if summary.stake_found {
+            self.record(MetricUpdate::Inc(Metric::StakesFound));
+            self.record(MetricUpdate::Set(Metric::RoundShares, 0.0));
+        } else {
+            self.record(MetricUpdate::Add(
+                Metric::RoundShares,
+                summary.shares_added.to_f64().unwrap_or_default(),
+            ));
+        }
+
+        self.publish(PoolEvent::BlockProcessed(summary));
+
+        Ok(())
+    }
+
+    /// Queues the blocks of which the ZMQ notification was missed, as if their notification
+    /// arrived.
+    fn recover_missed_blocks(&self) -> Result<()> {
+        let client = self.verusd()?;
+        let tip = client.get_blockchain_info()?.blocks;
+
+        let Some(missed) = self.gaps.missed(tip) else {
+            return Ok(());
+        };
+        warn!(from_height = %missed.start(), to_height = %missed.end(), "missed block notifications, recovering the blocks");
+
+        for height in missed {
+            let block_hash = client.call::<BlockHash>("getblockhash", &[height.into()])?;
+
+            // the coinstaker can't wait for room in its own queue
+            if self
+                .tx
+                .try_send(CoinStakerMessage::Block(block_hash))
+                .is_err()
+            {
+                warn!(%height, "message queue is full, recovering the rest with the next gap check");
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a batch of the blocks that were added to the chain since the last processed block.
+    ///
+    /// Once the preflight checks reached the chain tip, the live blocks that arrived in the
+    /// meantime are processed in the order they arrived.
+    ///
+    /// The start and end of a catch-up are published, so that consumers can treat the events of
+    /// historical blocks differently.
+    async fn catch_up(&mut self) -> Result<()> {
+        let client = self.verusd()?;
+        let mut chain_tip = None;
+
+        if let Some(last_height) = database::get_last_height(&self.pool, &self.chain_id).await? {
+            if self.catch_up_from.is_none() {
+                self.catch_up_from = Some(last_height);
+                self.publish(PoolEvent::CatchUpStarted);
+            }
+
+            let tip = client.get_blockchain_info()?.blocks;
+
+            if last_height < tip {
+                let until = tip.min(last_height + PREFLIGHT_BATCH_SIZE);
+                trace!(%last_height, %until, "Do some preflight checks");
+
+                let mut tx = database::process_block(&self.pool).await?;
+
+                self.hold_events();
+                let checked = self.preflight(&mut tx, &client, last_height, until).await;
+                self.commit_block(tx, checked).await?;
+
+                if until < tip {
+                    return Ok(());
+                }
+            }
+
+            self.check_maturing_stakes(&mut *self.pool.acquire().await?, &client)
+                .await?;
+
+            trace!(chain_tip = %tip, "Finished doing preflight checks");
+            chain_tip = Some(tip);
+        }
+
+        for block_hash in self.gate.release() {
+            self.process_block(block_hash).await?;
+        }
+
+        if let (Some(from_height), Some(to_height)) = (self.catch_up_from.take(), chain_tip) {
+            self.publish(PoolEvent::CatchUpFinished {
+                from_height,
+                to_height,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks the stakers in the blocks from `from_height` up to and including `until`, and stores
+    /// `until` as the last processed height.
+    async fn preflight(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        from_height: u64,
+        until: u64,
+    ) -> Result<()> {
+        for height in from_height..=until {
+            let block = client.get_block_by_height(height, 2)?;
+
+            self.check_stakers(conn, client, &block).await?;
+        }
+
+        database::update_last_height(conn, &self.chain_id, until).await
+    }
+
+    /// Compares the state in the database with the daemon, to find inconsistencies that were
+    /// left behind by a crash or a reorg while the pool was offline.
+    ///
+    /// Work in rounds without a stake is always moved back to round 0, as it can only get there
+    /// when the pool stopped while storing a stake. Pending stakes are only repaired if
+    /// `startup_audit_repair` is enabled, as refreshing their status is what happens with every
+    /// new block anyway.
+    async fn run_startup_audit(&self, client: &VerusClient) -> Result<AuditReport> {
+        let mut findings = vec![];
+        let mut conn = self.pool.acquire().await?;
+
+        let maturing_stakes =
+            database::get_stakes_by_status(&mut conn, &self.chain_id, StakeStatus::Maturing, None)
+                .await?;
+        let block_hashes = maturing_stakes
+            .iter()
+            .map(|stake| stake.block_hash)
+            .collect::<Vec<_>>();
+
+        for (stake, block) in maturing_stakes
+            .iter()
+            .zip(get_blocks(client, &block_hashes)?)
+        {
+            let maturity = Maturity::of(block.confirmations, self.config.maturity_confirmations);
+
+            if maturity == Maturity::Stale {
+                findings.push(AuditFinding {
+                    category: AuditCategory::PendingStakes,
+                    description: format!(
+                        "maturing stake at height {} is stale",
+                        stake.block_height
+                    ),
+                    suggestion: "mark the stake as stale and move its work back to round 0"
+                        .to_string(),
+                    repaired: false,
+                });
+            } else if maturity == Maturity::Matured {
+                findings.push(AuditFinding {
+                    category: AuditCategory::PendingStakes,
+                    description: format!(
+                        "maturing stake at height {} has {} confirmations",
+                        stake.block_height, block.confirmations
+                    ),
+                    suggestion: "mark the stake as matured".to_string(),
+                    repaired: false,
+                });
+            }
+        }
+
+        for round in database::get_orphaned_work_rounds(&self.pool, &self.chain_id).await? {
+            database::restore_orphaned_work(&self.pool, &self.chain_id, round).await?;
+
+            findings.push(AuditFinding {
+                category: AuditCategory::RoundWork,
+                description: format!("work was assigned to round id {round}, which has no stake"),
+                suggestion: "none, the work of this round was moved back to round 0".to_string(),
+                repaired: true,
+            });
+        }
+
+        let unpaid_rewards = database::get_unpaid_rewards(&self.pool, &self.chain_id).await?;
+        let balance = client.get_wallet_info()?.balance;
+        if unpaid_rewards > balance {
+            findings.push(AuditFinding {
+                category: AuditCategory::UnpaidPayoutMembers,
+                description: format!(
+                    "unpaid rewards of {} exceed the wallet balance of {}",
+                    unpaid_rewards.as_vrsc(),
+                    balance.as_vrsc()
+                ),
+                suggestion: "check for missing wallet transactions or double payments".to_string(),
+                repaired: false,
+            });
+        }
+
+        for stake in
+            database::get_stakes_by_status(&mut conn, &self.chain_id, StakeStatus::Matured, None)
+                .await?
+        {
+            if let Some(reason) =
+                check_stake_in_wallet(client, &stake, self.config.maturity_confirmations)?
+            {
+                findings.push(AuditFinding {
+                    category: AuditCategory::WalletTransactions,
+                    description: reason,
+                    suggestion: "check that the wallet was not restored from an old backup"
+                        .to_string(),
+                    repaired: false,
+                });
+            }
+        }
+
+        let chain_tip = client.get_blockchain_info()?.blocks;
+        if let Some(last_height) = database::get_last_height(&self.pool, &self.chain_id).await? {
+            if last_height > chain_tip {
+                findings.push(AuditFinding {
+                    category: AuditCategory::Synchronization,
+                    description: format!(
+                        "last processed height {last_height} is ahead of the chain tip {chain_tip}"
+                    ),
+                    suggestion: "check that the daemon is synced and runs the right chain"
+                        .to_string(),
+                    repaired: false,
+                });
+            } else if last_height < chain_tip && self.config.skip_preflight {
+                findings.push(AuditFinding {
+                    category: AuditCategory::Synchronization,
+                    description: format!(
+                        "last processed height {last_height} is {} blocks behind the chain tip",
+                        chain_tip - last_height
+                    ),
+                    suggestion: "disable skip_preflight to check the missed blocks".to_string(),
+                    repaired: false,
+                });
+            }
+        }
+
+        if let Some(payout_height) =
+            database::get_payout_sync_id(&self.pool, &self.chain_id).await?
+        {
+            for height in database::get_matured_stakes_without_payout(
+                &self.pool,
+                &self.chain_id,
+                payout_height,
+            )
+            .await?
+            {
+                findings.push(AuditFinding {
+                    category: AuditCategory::Synchronization,
+                    description: format!(
+                        "matured stake at height {height} is below the last payout height \
+                        {payout_height} but has no payout"
+                    ),
+                    suggestion: "create the payout of this stake manually".to_string(),
+                    repaired: false,
+                });
+            }
+        }
+
+        if self.config.startup_audit_repair
+            && findings
+                .iter()
+                .any(|finding| finding.category == AuditCategory::PendingStakes)
+        {
+            self.check_maturing_stakes(&mut *self.pool.acquire().await?, client)
+                .await?;
+
+            for finding in findings.iter_mut() {
+                if finding.category == AuditCategory::PendingStakes {
+                    finding.repaired = true;
+                }
+            }
+        }
+
+        Ok(AuditReport {
+            currency_address: self.chain_id.clone(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            findings,
+        })
+    }
+
+    /// Refuses a change to the rounds while the coinstaker catches up with the chain, as the
+    /// blocks it processes in the meantime change the rounds too.
+    fn ensure_caught_up(&self) -> Result<()> {
+        if !self.gate.is_open() {
+            Err(PoolError::ChainSyncing)?
+        }
+
+        Ok(())
+    }
+
+    /// Merges the work of the round with id `from_round` into the round with id `into_round`, for
+    /// when work of the same stake ended up in two rounds.
+    ///
+    /// If the stake already has a payout, the payout is generated again from the merged work.
+    /// A payout that was already (partially) paid cannot be changed. With `dry_run`, nothing is
+    /// changed and the returned changes show what the merge would do. A merge is recorded in the
+    /// `round_merges` table.
+    async fn merge_rounds(
+        &self,
+        from_round: u64,
+        into_round: u64,
+        dry_run: bool,
+    ) -> Result<RoundMerge> {
+        self.ensure_caught_up()?;
+        if from_round == 0 || into_round == 0 || from_round == into_round {
+            bail!("can only merge two different rounds other than round 0");
+        }
+
+        let Some(stake) =
+            database::get_stake_by_round(&self.pool, &self.chain_id, into_round).await?
+        else {
+            bail!("round {into_round} has no stake");
+        };
+
+        if let Some(from_stake) =
+            database::get_stake_by_round(&self.pool, &self.chain_id, from_round).await?
+        {
+            if !database::get_payout_members_by_block_hash(
+                &self.pool,
+                &self.chain_id,
+                &from_stake.block_hash,
+            )
+            .await?
+            .is_empty()
+            {
+                bail!("round {from_round} already has a payout");
+            }
+        }
+
+        let payout_members = database::get_payout_members_by_block_hash(
+            &self.pool,
+            &self.chain_id,
+            &stake.block_hash,
+        )
+        .await?;
+        if payout_members.iter().any(|member| member.txid.is_some()) {
+            bail!("the payout of round {into_round} was already (partially) paid");
+        }
+
+        let (from_workers, into_workers, from_forfeited, into_forfeited) = tokio::try_join!(
+            database::get_workers_by_round(&self.pool, &self.chain_id, from_round),
+            database::get_workers_by_round(&self.pool, &self.chain_id, into_round),
+            database::get_forfeited_shares_by_round(&self.pool, &self.chain_id, from_round),
+            database::get_forfeited_shares_by_round(&self.pool, &self.chain_id, into_round),
+        )?;
+
+        let shares_before = into_workers
+            .iter()
+            .map(|worker| (worker.identity_address.clone(), worker.shares))
+            .collect::<HashMap<_, _>>();
+        let merged = Worker::merge(into_workers, from_workers);
+
+        let payout = if payout_members.is_empty() {
+            None
+        } else {
+            let fees =
+                Fees::load_for_round(&self.pool, &self.config.payout_config.fee_schedule, &stake)
+                    .await?;
+
+            Some(Payout::new(
+                &stake,
+                merged.clone(),
+                from_forfeited + into_forfeited,
+                &fees,
+            )?)
+        };
+
+        let reward = |members: &[PayoutMember], address: &Address| {
+            members
+                .iter()
+                .find(|member| &member.identity_address == address)
+                .map(|member| member.reward)
+        };
+
+        let changes = merged
+            .iter()
+            .map(|worker| RoundMergeChange {
+                identity_address: worker.identity_address.clone(),
+                shares_before: shares_before
+                    .get(&worker.identity_address)
+                    .copied()
+                    .unwrap_or(Decimal::ZERO),
+                shares_after: worker.shares,
+                reward_before: reward(&payout_members, &worker.identity_address),
+                reward_after: payout
+                    .as_ref()
+                    .and_then(|payout| reward(&payout.members, &worker.identity_address)),
+            })
+            .collect();
+
+        let merge = RoundMerge {
+            currency_address: self.chain_id.clone(),
+            from_round,
+            into_round,
+            dry_run,
+            payout_regenerated: payout.is_some(),
+            changes,
+        };
+
+        if dry_run {
+            return Ok(merge);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        database::merge_work_rounds(&mut tx, &self.chain_id, from_round, into_round).await?;
+
+        if let Some(payout) = payout {
+            database::delete_unpaid_payout(&mut tx, &self.chain_id, &stake.block_hash).await?;
+            database::store_payout(&mut tx, &payout).await?;
+
+            for member in payout.members.iter() {
+                database::store_payout_member(&mut tx, member).await?;
+            }
+        }
+
+        database::store_round_merge(&mut tx, &merge).await?;
+
+        tx.commit().await?;
+
+        info!(%from_round, %into_round, "merged rounds");
+
+        Ok(merge)
+    }
+
+    /// Changes the min_payout of a staker, which must be within the min_payout bounds of the pool.
+    async fn set_min_payout(&self, identity_address: &Address, min_payout: Amount) -> Result<()> {
+        if !database::update_min_payout(
+            &self.pool,
+            &self.chain_id,
+            identity_address,
+            min_payout,
+            &self.config.min_payout_bounds(),
+        )
+        .await?
+        {
+            Err(PoolError::StakerNotFound(identity_address.clone()))?
+        }
+
+        Ok(())
+    }
+
+    /// Sets the currency that the rewards of a staker are converted into when they are paid. A
+    /// staker without a payout currency, or with the staked currency, is paid in the staked
+    /// currency.
+    async fn set_payout_currency(
+        &self,
+        identity_address: &Address,
+        payout_currency: Option<Address>,
+    ) -> Result<()> {
+        if database::get_staker(
+            &mut *self.pool.acquire().await?,
+            &self.chain_id,
+            identity_address,
+        )
+        .await?
+        .is_none()
+        {
+            Err(PoolError::StakerNotFound(identity_address.clone()))?
+        }
+
+        match payout_currency.filter(|currency| *currency != self.chain_id) {
+            Some(payout_currency) => {
+                let converts_into = self
+                    .config
+                    .payout_config
+                    .conversion
+                    .as_ref()
+                    .is_some_and(|conversion| conversion.currencies.contains(&payout_currency));
+                if !converts_into {
+                    bail!("this pool doesn't pay out in {payout_currency}");
+                }
+
+                database::store_payout_currency(
+                    &self.pool,
+                    &self.chain_id,
+                    identity_address,
+                    &payout_currency,
+                )
+                .await?;
+            }
+            None => {
+                database::delete_payout_currency(&self.pool, &self.chain_id, identity_address)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recalculates the payout of the stake in `block_hash` from the snapshot of the work in its
+    /// round, as it was when the stake was found, for when a bug changed the work afterwards.
+    ///
+    /// The payout is replaced with the recalculated one, unless it was already (partially) paid.
+    /// With `dry_run`, nothing is changed and the returned changes show what replacing the payout
+    /// would do.
+    async fn recalculate_payout(
+        &self,
+        block_hash: BlockHash,
+        dry_run: bool,
+    ) -> Result<PayoutRecalculation> {
+        self.ensure_caught_up()?;
+        let mut conn = self.pool.acquire().await?;
+        let Some(round_id) = database::get_round_id(&mut conn, &self.chain_id, &block_hash).await?
+        else {
+            bail!("stake {block_hash} has no round");
+        };
+        let Some(stake) =
+            database::get_stake_by_round(&self.pool, &self.chain_id, round_id).await?
+        else {
+            bail!("round {round_id} has no stake");
+        };
+        if stake.status != StakeStatus::Matured {
+            bail!("stake {block_hash} did not mature");
+        }
+
+        let Some((workers, forfeited_shares)) =
+            database::get_work_snapshot(&self.pool, &self.chain_id, &block_hash).await?
+        else {
+            bail!("stake {block_hash} has no snapshot of its work");
+        };
+
+        let payout_members =
+            database::get_payout_members_by_block_hash(&self.pool, &self.chain_id, &block_hash)
+                .await?;
+        if payout_members.iter().any(|member| member.txid.is_some()) {
+            bail!("the payout of {block_hash} was already (partially) paid");
+        }
+
+        let shares_before = database::get_workers_by_round(&self.pool, &self.chain_id, round_id)
+            .await?
+            .into_iter()
+            .map(|worker| (worker.identity_address, worker.shares))
+            .collect::<HashMap<_, _>>();
+
+        let fees =
+            Fees::load_for_round(&self.pool, &self.config.payout_config.fee_schedule, &stake)
+                .await?;
+        let payout = Payout::new(&stake, workers.clone(), forfeited_shares, &fees)?;
+
+        let reward = |members: &[PayoutMember], address: &Address| {
+            members
+                .iter()
+                .find(|member| &member.identity_address == address)
+                .map(|member| member.reward)
+        };
+
+        let changes = workers
+            .iter()
+            .map(|worker| PayoutRecalculationChange {
+                identity_address: worker.identity_address.clone(),
+                shares_before: shares_before
+                    .get(&worker.identity_address)
+                    .copied()
+                    .unwrap_or(Decimal::ZERO),
+                shares_after: worker.shares,
+                reward_before: reward(&payout_members, &worker.identity_address),
+                reward_after: reward(&payout.members, &worker.identity_address),
+            })
+            .collect();
+
+        let recalculation = PayoutRecalculation {
+            currency_address: self.chain_id.clone(),
+            block_hash,
+            dry_run,
+            changes,
+        };
+
+        if dry_run {
+            return Ok(recalculation);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        database::delete_unpaid_payout(&mut tx, &self.chain_id, &stake.block_hash).await?;
+        database::store_payout(&mut tx, &payout).await?;
+
+        for member in payout.members.iter() {
+            database::store_payout_member(&mut tx, member).await?;
+        }
+
+        tx.commit().await?;
+
+        info!(%block_hash, "recalculated payout from the work snapshot");
+
+        Ok(recalculation)
+    }
+
+    /// Inserts a stake of the pool that was missed, for example because it was staked while the
+    /// pool was down, and gives it its own round.
+    ///
+    /// The pool doesn't keep the work of every block, so the work of the stake is split off from
+    /// the round that holds it, in proportion to the shares that were added before and after the
+    /// stake according to the block summaries. The round that holds the work can't have a
+    /// payout yet, as a payout can't be changed once it is created.
+    ///
+    /// The payout service only creates payouts for stakes after the last payout, so a matured
+    /// stake before it gets its payout right away, and a maturing stake before it can't be
+    /// inserted until it matured. With `dry_run`, nothing is changed.
+    async fn insert_historical_stake(
+        &self,
+        block_hash: BlockHash,
+        dry_run: bool,
+    ) -> Result<HistoricalStake> {
+        self.ensure_caught_up()?;
+        let mut conn = self.pool.acquire().await?;
+        if database::get_round_id(&mut conn, &self.chain_id, &block_hash)
+            .await?
+            .is_some()
+        {
+            bail!("stake {block_hash} is already known");
+        }
+
+        let client = self.verusd()?;
+        let block = client.get_block(&block_hash, 2)?;
+        if !matches!(block.validation_type, ValidationType::Stake) || block.confirmations < 0 {
+            bail!("block {block_hash} is not a stake in the main chain");
+        }
+
+        let found_by = postxddest(&block)?;
+        if found_by != self.config.pool_address
+            && database::get_staker(&mut *self.pool.acquire().await?, &self.chain_id, &found_by)
+                .await?
+                .is_none()
+        {
+            bail!("block {block_hash} was not staked by the pool");
+        }
+        if check_stake_guard(&block).await?.is_some() {
+            bail!("the coinbase of {block_hash} was spent by StakeGuard");
+        }
+
+        let mut stake = stake_from_block(&self.chain_id, &block)?;
+        if Maturity::of(block.confirmations, self.config.maturity_confirmations)
+            == Maturity::Matured
+        {
+            stake.status = StakeStatus::Matured;
+        }
+
+        let (from_round, start_height) =
+            database::get_round_containing(&self.pool, &self.chain_id, stake.block_height).await?;
+        let end_height =
+            match database::get_stake_by_round(&self.pool, &self.chain_id, from_round).await? {
+                Some(from_stake) => {
+                    if !database::get_payout_members_by_block_hash(
+                        &self.pool,
+                        &self.chain_id,
+                        &from_stake.block_hash,
+                    )
+                    .await?
+                    .is_empty()
+                    {
+                        bail!("round {from_round} that holds the work already has a payout");
+                    }
+
+                    from_stake.block_height
+                }
+                None => database::get_last_height(&self.pool, &self.chain_id)
+                    .await?
+                    .unwrap_or(stake.block_height),
+            };
+
+        let (shares_before, shares_total) = tokio::try_join!(
+            database::get_shares_added(
+                &self.pool,
+                &self.chain_id,
+                start_height,
+                stake.block_height
+            ),
+            database::get_shares_added(&self.pool, &self.chain_id, start_height, end_height),
+        )?;
+        if shares_total.is_zero() {
+            bail!("no work was recorded in round {from_round} to split off");
+        }
+        let work_fraction = shares_before / shares_total;
+
+        let (workers, forfeited_shares) = tokio::try_join!(
+            database::get_workers_by_round(&self.pool, &self.chain_id, from_round),
+            database::get_forfeited_shares_by_round(&self.pool, &self.chain_id, from_round),
+        )?;
+        let workers = workers
+            .into_iter()
+            .map(|worker| Worker {
+                shares: worker.shares * work_fraction,
+                ..worker
+            })
+            .collect::<Vec<_>>();
+
+        let last_payout_height = database::get_payout_sync_id(&self.pool, &self.chain_id)
+            .await?
+            .unwrap_or(0);
+        let fees =
+            Fees::load_for_round(&self.pool, &self.config.payout_config.fee_schedule, &stake)
+                .await?;
+        let payout = match stake.status {
+            _ if stake.block_height > last_payout_height => None,
+            StakeStatus::Matured => Some(Payout::new(
+                &stake,
+                workers.clone(),
+                forfeited_shares * work_fraction,
+                &fees,
+            )?),
+            _ => bail!("stake {block_hash} is behind the last payout, insert it once it matured"),
+        };
+
+        let shares = workers
+            .iter()
+            .map(|worker| HistoricalStakeShares {
+                identity_address: worker.identity_address.clone(),
+                shares: worker.shares,
+                reward: payout.as_ref().and_then(|payout| {
+                    payout
+                        .members
+                        .iter()
+                        .find(|member| member.identity_address == worker.identity_address)
+                        .map(|member| member.reward)
+                }),
+            })
+            .collect();
+
+        let mut historical = HistoricalStake {
+            stake,
+            round_id: 0,
+            from_round,
+            work_fraction,
+            dry_run,
+            payout_created: payout.is_some(),
+            shares,
+        };
+
+        if dry_run {
+            return Ok(historical);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        historical.round_id =
+            database::store_historical_stake(&mut tx, &historical.stake, from_round, work_fraction)
+                .await?;
+
+        // the payout height is not updated, the stake is behind it
+        if let Some(payout) = payout {
+            database::store_payout(&mut tx, &payout).await?;
+
+            for member in payout.members.iter() {
+                database::store_payout_member(&mut tx, member).await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        info!(%block_hash, round_id = %historical.round_id, %from_round, %work_fraction, "inserted historical stake");
+
+        Ok(historical)
+    }
+
+    /// Checks that `signature` is a signature of `message` by the current primary addresses of
+    /// the VerusID, as created with `signmessage`. A malformed signature is not valid.
+    fn verify_message(
+        &self,
+        identity_address: &Address,
+        message: &str,
+        signature: &str,
+    ) -> Result<bool> {
+        let client = self.verusd()?;
+
+        match client.call::<bool>(
+            "verifymessage",
+            &[
+                identity_address.to_string().into(),
+                signature.into(),
+                message.into(),
+                true.into(),
+            ],
+        ) {
+            Ok(valid) => Ok(valid),
+            Err(e) => {
+                debug!(%identity_address, error = ?e, "could not verify message");
+
+                Ok(false)
+            }
+        }
+    }
+
+    /// Sets the fee of a staker, which replaces the fee the staker signed up with and the fee
+    /// tiers in the payouts that are created from now on. Removes the override if `fee` is
+    /// `None`.
+    async fn set_fee_override(
+        &self,
+        identity_address: Address,
+        fee: Option<Decimal>,
+        reason: Option<String>,
+    ) -> Result<FeeOverride> {
+        if database::get_staker(
+            &mut *self.pool.acquire().await?,
+            &self.chain_id,
+            &identity_address,
+        )
+        .await?
+        .is_none()
+        {
+            bail!("{identity_address} is not a staker of this pool");
+        }
+
+        match fee {
+            Some(fee) if fee < Decimal::ZERO || fee > Decimal::ONE => {
+                bail!("a fee must be between 0 and 1, got {fee}")
+            }
+            Some(fee) => {
+                database::store_fee_override(
+                    &self.pool,
+                    &self.chain_id,
+                    &identity_address,
+                    fee,
+                    reason.as_deref(),
+                )
+                .await?;
+                info!(%identity_address, %fee, ?reason, "fee override set");
+            }
+            None => {
+                if !database::delete_fee_override(&self.pool, &self.chain_id, &identity_address)
+                    .await?
+                {
+                    bail!("{identity_address} has no fee override");
+                }
+                info!(%identity_address, ?reason, "fee override removed");
+            }
+        }
+
+        Ok(FeeOverride {
+            currency_address: self.chain_id.clone(),
+            identity_address,
+            fee,
+            reason,
+        })
+    }
+
+    /// Pays the outstanding balance of a single staker right away, regardless of its min_payout
+    /// and the payout schedule. The payment is sent and recorded like a regular payment, and the
+    /// operator and reason are recorded in the `manual_payments` table.
+    async fn pay_staker(
+        &self,
+        identity_address: Address,
+        operator: String,
+        reason: String,
+    ) -> Result<ManualPayment> {
+        let client = self.verusd()?;
+        if !reconcile_payment_journal(&self.pool, &client, &self.chain_id).await? {
+            bail!("a previous payment is not resolved yet, try again later");
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let members = database::get_unpaid_payout_members_of_staker(
+            &mut tx,
+            &self.chain_id,
+            &identity_address,
+        )
+        .await?;
+        if members.is_empty() {
+            bail!("{identity_address} has no outstanding balance");
+        }
+
+        let tx_fee = self.tx_fee();
+        let mut items = PaymentItem::aggregate(&members);
+        tx_fee.deduct_from(&mut items)?;
+        PaymentItem::set_payout_currencies(
+            &mut items,
+            &database::get_payout_currencies(&self.pool, &self.chain_id).await?,
+        );
+        PaymentItem::set_payout_addresses(
+            &mut items,
+            &database::get_payout_addresses(&self.pool, &self.chain_id).await?,
+        );
+        let converter = self
+            .config
+            .payout_config
+            .conversion
+            .as_ref()
+            .map(|conversion| Converter::new(&client, &self.chain_id, conversion));
+        let outputs = prepare_payment(&mut items, converter.as_ref());
+
+        let (journal_id, txid) = send_journaled_payment(
+            &self.pool,
+            &self.chain_id,
+            &members,
+            &items,
+            tx_fee,
+            outputs,
+            &self.config.pool_address,
+            &client,
+        )
+        .await?;
+        let Some(txid) = txid else {
+            bail!("the payment to {identity_address} was not sent");
+        };
+
+        let payment =
+            store_sent_payment(&mut tx, &self.chain_id, txid, &members, &items, tx_fee).await?;
+        database::close_payment_journal_entry(
+            &mut tx,
+            journal_id,
+            JournalStatus::Sent,
+            Some(&txid),
+        )
+        .await?;
+
+        let manual_payment = ManualPayment {
+            currency_address: self.chain_id.clone(),
+            identity_address,
+            txid,
+            amount: payment.amount,
+            n_rounds: members.len() as u64,
+            operator,
+            reason,
+        };
+
+        if let Err(e) = database::store_manual_payment(&mut tx, &manual_payment).await {
+            error!(?manual_payment, ?e);
+
+            bail!("A payment was sent but the database failed to update.");
+        }
+
+        tx.commit().await?;
+
+        warn!(
+            identity_address = %manual_payment.identity_address,
+            %txid,
+            operator = manual_payment.operator,
+            reason = manual_payment.reason,
+            "sent manual payment"
+        );
+
+        Ok(manual_payment)
+    }
+
+    /// Stores the network conditions of this block, so that luck calculations and historical
+    /// network statistics don't need to query the daemon.
+    async fn collect_network_stats(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        block: &Block,
+    ) -> Result<()> {
+        let mining_info = client.get_mining_info()?;
+
+        database::store_network_stats(
+            conn,
+            &self.chain_id,
+            block.height,
+            &block.hash,
+            mining_info.stakingsupply,
+            mining_info.difficulty,
+            matches!(block.validation_type, ValidationType::Stake),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stores the staking supply of the network and the pool at this block, for the luck
+    /// statistics. A daemon that is not staking has no staking supply.
+    async fn store_network_snapshot(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        block: &Block,
+        daemon_staking: bool,
+    ) -> Result<()> {
+        let network_staking_supply = client.get_mining_info()?.stakingsupply;
+        let pool_staking_supply = if daemon_staking {
+            client.get_wallet_info()?.eligible_staking_balance
+        } else {
+            Amount::ZERO
+        };
+
+        database::store_network_snapshot(
+            conn,
+            &self.chain_id,
+            block.height,
+            &block.hash,
+            network_staking_supply,
+            pool_staking_supply,
+            matches!(block.validation_type, ValidationType::Stake),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Calculates the expected staking returns at the current and the next block reward.
+    ///
+    /// The pool APY is based on the stakes of the last 30 days. Stakes that were found before a
+    /// halving are scaled to the current block reward, so the APY doesn't drop suddenly once
+    /// the window passes the halving.
+    async fn reward_outlook(&self, client: &VerusClient) -> Result<RewardOutlook> {
+        let schedule = &self.config.reward_schedule;
+        let block_height = client.get_blockchain_info()?.blocks;
+        let network_supply = client.get_mining_info()?.stakingsupply;
+        let pool_supply = client.get_wallet_info()?.eligible_staking_balance;
+
+        let pos_ratio = database::get_network_stats(
+            &self.pool,
+            &self.chain_id,
+            Some(block_height.saturating_sub(100)),
+            100,
+        )
+        .await?
+        .last()
+        .map(|stats| stats.pos_ratio)
+        .unwrap_or(DEFAULT_POS_RATIO);
+
+        let apy = |reward: Amount| {
+            (network_supply > 0.0)
+                .then(|| BLOCKS_PER_YEAR as f64 * pos_ratio * reward.as_vrsc() / network_supply)
+        };
+
+        let block_reward = schedule.reward_at(block_height);
+        let next_halving_height = schedule.next_halving(block_height);
+        let next_block_reward = next_halving_height.map(|height| schedule.reward_at(height));
+
+        let window_start = block_height.saturating_sub(POOL_APY_WINDOW);
+        let mut conn = self.pool.acquire().await?;
+        let mut stakes = database::get_stakes_by_status(
+            &mut conn,
+            &self.chain_id,
+            StakeStatus::Matured,
+            Some(window_start),
+        )
+        .await?;
+        stakes.extend(
+            database::get_stakes_by_status(
+                &mut conn,
+                &self.chain_id,
+                StakeStatus::Maturing,
+                Some(window_start),
+            )
+            .await?,
+        );
+
+        let earned = stakes.iter().fold(Amount::ZERO, |acc, stake| {
+            acc + schedule.normalize(stake.amount, stake.block_height, block_height)
+        });
+        let window = block_height.min(POOL_APY_WINDOW).max(1);
+        let pool_apy = (pool_supply > Amount::ZERO).then(|| {
+            earned.as_vrsc() / pool_supply.as_vrsc() * (BLOCKS_PER_YEAR as f64 / window as f64)
+        });
+
+        Ok(RewardOutlook {
+            block_height,
+            block_reward,
+            next_halving_height,
+            next_block_reward,
+            network_apy: apy(block_reward).unwrap_or_default(),
+            network_apy_after_halving: next_block_reward.and_then(apy),
+            pool_apy,
+        })
+    }
+
+    /// Updates the pending stakes, which are read from the database on every block so that none
+    /// are lost when the pool restarts. The blocks of all pending stakes are fetched in a single
+    /// batched call to the daemon.
+    async fn check_maturing_stakes(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+    ) -> Result<()> {
+        let maturing_stakes =
+            database::get_stakes_by_status(conn, &self.chain_id, StakeStatus::Maturing, None)
+                .await?;
+        let block_hashes = maturing_stakes
+            .iter()
+            .map(|stake| stake.block_hash)
+            .collect::<Vec<_>>();
+        let blocks = get_blocks(client, &block_hashes)?;
+
+        for (mut stake, block) in maturing_stakes.into_iter().zip(blocks) {
+            match Maturity::of(block.confirmations, self.config.maturity_confirmations) {
+                Maturity::Stale => {
+                    trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake is stale");
+
+                    if let Some(round_id) =
+                        database::get_round_id(conn, &self.chain_id, &stake.block_hash).await?
+                    {
+                        database::move_stale_work_to_round_zero(conn, &stake, round_id).await?;
+                    }
+                    stake.status = StakeStatus::Stale;
+                    database::store_stake(conn, &stake).await?;
+
+                    self.record(MetricUpdate::Inc(Metric::StakesStale));
+                    self.publish(PoolEvent::StakeStale(stake));
+                }
+                Maturity::Maturing => {
+                    if let Some(spend_txid) = check_stake_guard(&block).await? {
+                        trace!(%spend_txid, "The transaction was spent by stakeguard");
+                        stake.status = StakeStatus::StakeGuard;
+
+                        database::store_stake(conn, &stake).await?;
+                        self.record(MetricUpdate::Inc(Metric::StakesStolen));
+                        self.collect_fraud_evidence(conn, client, &block, &stake, spend_txid)
+                            .await?;
+                        self.ban_staker(conn, &block, stake, spend_txid).await?;
+
+                        continue;
+                    }
+
+                    trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake still maturing");
+                }
+                Maturity::Matured => {
+                    trace!(block_hash = %block.hash, height = %block.height, amount = %stake.amount.as_vrsc(), "stake has matured");
+
+                    stake.status = StakeStatus::Matured;
+                    database::store_stake(conn, &stake).await?;
+
+                    if let Some(reason) =
+                        check_stake_in_wallet(client, &stake, self.config.maturity_confirmations)?
+                    {
+                        error!(block_hash = %stake.block_hash, %reason, "wallet disagrees with matured stake");
+
+                        self.publish(PoolEvent::StakeWalletMismatch {
+                            stake: stake.clone(),
+                            reason,
+                        });
+                    }
+
+                    self.publish(PoolEvent::StakeMatured(stake));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stores the StakeGuard spend of a stake and the competing stake it proves, for the
+    /// operator to confront the staker with. The staker is banned even if the evidence could not
+    /// be collected.
+    async fn collect_fraud_evidence(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        block: &Block,
+        stake: &Stake,
+        spend_txid: Txid,
+    ) -> Result<()> {
+        let offender = postxddest(block)?;
+
+        match fraud::collect_evidence(client, stake, &offender, spend_txid) {
+            Ok(evidence) => {
+                if evidence.competing_txid.is_none() {
+                    warn!(block_hash = %stake.block_hash, %spend_txid, "could not decode the competing stake from the StakeGuard spend");
+                }
+
+                database::store_fraud_evidence(conn, &evidence).await?;
+            }
+            Err(e) => {
+                warn!(block_hash = %stake.block_hash, %spend_txid, "could not collect the StakeGuard evidence: {e:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Bans the staker that staked a block of which the coinbase was spent by StakeGuard.
+    ///
+    /// The staker forfeits its work in the current round and the rewards that were not paid yet.
+    async fn ban_staker(
+        &self,
+        conn: &mut PgConnection,
+        block: &Block,
+        stake: Stake,
+        spend_txid: Txid,
+    ) -> Result<()> {
+        let offender = postxddest(block)?;
+
+        let Some(mut staker) = database::get_staker(conn, &self.chain_id, &offender).await? else {
+            warn!(%offender, block_hash = %stake.block_hash, "stake caught by StakeGuard was not staked by a staker of the pool");
+
+            return Ok(());
+        };
+
+        if staker.status == StakerStatus::Banned {
+            return Ok(());
+        }
+
+        database::forfeit_work(conn, &self.chain_id, &offender).await?;
+        let forfeited = database::ban_staker(conn, &self.chain_id, &offender).await?;
+        staker.status = StakerStatus::Banned;
+
+        error!(%offender, block_hash = %stake.block_hash, %spend_txid, forfeited = %forfeited.as_vrsc(), "staker caught by StakeGuard, banned");
+
+        self.publish(PoolEvent::StakerBanned {
+            staker,
+            stake,
+            spend_txid,
+            forfeited,
+        });
+
+        Ok(())
+    }
+
+    /// Bans a staker on request of an operator, like a staker that was caught by StakeGuard: the
+    /// staker forfeits its work in the current round and the rewards that were not paid yet.
+    async fn ban_staker_on_request(
+        &self,
+        identity_address: Address,
+        operator: String,
+        reason: String,
+    ) -> Result<StakerBan> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(staker) = database::get_staker(&mut tx, &self.chain_id, &identity_address).await?
+        else {
+            bail!("{identity_address} is not a staker of this pool");
+        };
+
+        if staker.status == StakerStatus::Banned {
+            bail!("{identity_address} is already banned");
+        }
+
+        database::forfeit_work(&mut tx, &self.chain_id, &identity_address).await?;
+        let forfeited = database::ban_staker(&mut tx, &self.chain_id, &identity_address).await?;
+
+        tx.commit().await?;
+
+        warn!(
+            %identity_address,
+            forfeited = %forfeited.as_vrsc(),
+            %operator,
+            %reason,
+            "staker banned on request"
+        );
+
+        Ok(StakerBan {
+            currency_address: self.chain_id.clone(),
+            identity_address,
+            forfeited,
+            operator,
+            reason,
+        })
+    }
+
+    /// Checks the database and the daemon, and how long ago the last block and payout run were.
+    async fn liveness(&self) -> Result<ChainLiveness> {
+        let observation = SubsystemObservation {
+            last_block: database::get_last_height(&self.pool, &self.chain_id)
+                .await
+                .map_err(|e| e.to_string()),
+            chain_tip: self
+                .verusd()
+                .and_then(|client| Ok(client.get_blockchain_info()?.blocks))
+                .map_err(|e| e.to_string()),
+            last_block_processed_at: self
+                .metrics
+                .get(&self.chain_id, Metric::LastBlockProcessedAt)
+                .map(|at| at as u64),
+            last_payout_run_at: self
+                .metrics
+                .get(&self.chain_id, Metric::LastPayoutRunAt)
+                .map(|at| at as u64),
+        };
+
+        Ok(ChainLiveness::new(
+            self.chain_id.clone(),
+            self.config.currency_name.clone(),
+            observation,
+            self.config.health_max_blocks_behind,
+            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        ))
+    }
+
+    async fn daemon_is_staking(&self, client: &VerusClient) -> Result<bool> {
+        if !client.get_mining_info()?.staking {
+            error!("daemon not staking, not counting work");
+
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Add work for every staker that was active until this round
+    ///
+    /// For a staker to have work added, the following conditions apply:
+    /// - the verusid is not cooling down (150 blocks after a change)
+    /// - the UTXOs that are used for staking must have 150+ confirmations
+    ///
+    /// An exception is made when an UTXO is cooling down after mining a block
+    /// for the staking pool. It is still counted towards work.
+    ///
+    /// The UTXOs of addresses that delegate their staking to the pool are counted towards the
+    /// work of the staker that delegated them.
+    ///
+    /// The work is time-weighted: it is added for every block since the last block of which work
+    /// was added, as the eligible balance times the number of blocks it was held. The blocks that
+    /// were missed while the pool was down are reconstructed from the heights at which the UTXOs
+    /// became eligible, up to `max_missed_blocks`. UTXOs that were spent while the pool was down
+    /// are not known anymore and don't count towards the missed blocks.
+    ///
+    /// Returns the number of stakers that got work and the total shares that were added.
+    async fn add_work(
+        &self,
+        conn: &mut PgConnection,
+        active_stakers: &[Staker],
+        blockheight: u64,
+    ) -> Result<(u64, Decimal)> {
+        let verus_client = self.verusd()?;
+
+        let (cold_stakers, vault_stakers): (Vec<_>, Vec<_>) = active_stakers
+            .iter()
+            .partition(|staker| staker.participation_kind == ParticipationKind::ColdStaking);
+        let mut active_staker_addresses = vault_stakers
+            .iter()
+            .map(|subscriber| subscriber.identity_address.clone())
+            .collect::<Vec<Address>>();
+        let cold_staking_addresses = cold_stakers
+            .iter()
+            .map(|staker| staker.identity_address.clone())
+            .collect::<Vec<Address>>();
+
+        let from_height = match database::get_last_work_height(conn, &self.chain_id).await? {
+            Some(last) if last < blockheight => {
+                let missed = blockheight - last - 1;
+                if missed > self.config.max_missed_blocks {
+                    warn!(
+                        %missed,
+                        "too many blocks missed to reconstruct their work, only adding the work of this block"
+                    );
+
+                    blockheight
+                } else {
+                    last + 1
+                }
+            }
+            _ => blockheight,
+        };
+
+        if active_staker_addresses.is_empty() && cold_staking_addresses.is_empty() {
+            // still record the height, so the next block doesn't accrue over these blocks
+            database::store_time_weighted_work(conn, &self.chain_id, &[], blockheight).await?;
+
+            return Ok((0, Decimal::ZERO));
+        }
+
+        let delegators = self.get_delegators(conn, &active_staker_addresses).await?;
+        active_staker_addresses.extend(delegators.keys().cloned());
+
+        let eligibility_confirmations = self.config.utxo_eligibility_confirmations as u64;
+        let eligible_utxos = |client: &VerusClient| {
+            eligible_utxos(
+                client,
+                &active_staker_addresses,
+                &delegators,
+                &cold_staking_addresses,
+                eligibility_confirmations,
+            )
+        };
+
+        let mut utxos = eligible_utxos(&verus_client)?;
+
+        if let Some(quorum) = &self.quorum {
+            quorum.agree(
+                &format!("the eligible balances at height {blockheight}"),
+                &cold_staking::eligible_balances(&utxos, blockheight),
+                |client| {
+                    Ok(cold_staking::eligible_balances(
+                        &eligible_utxos(client)?,
+                        blockheight,
+                    ))
+                },
+            )?;
+        }
+
+        let stakes_to_compensate = database::get_stakes_to_compensate(
+            conn,
+            &self.chain_id,
+            from_height as i64,
+            self.config.utxo_eligibility_confirmations,
+        )
+        .await?;
+
+        for stake in stakes_to_compensate {
+            if utxos
+                .iter()
+                .any(|utxo| utxo.identity_address == stake.found_by)
+            {
+                debug!(
+                    amount_to_add = %stake.source_amount.as_vrsc(),
+                    staker = %stake.found_by,
+                    blockheight = &stake.block_height,
+                    "compensate work of immature utxo because it staked"
+                );
+
+                utxos.push(EligibleUtxo {
+                    identity_address: stake.found_by.clone(),
+                    amount: stake.source_amount,
+                    from_height: stake.block_height,
+                    until_height: Some(stake.block_height + eligibility_confirmations - 1),
+                });
+            }
+        }
+
+        let spans = balance_spans(&utxos, from_height, blockheight);
+        let payload = time_weighted_shares(&spans);
+
+        debug!(?payload, %from_height, "storing work");
+
+        let stakers_counted = payload.len() as u64;
+        let shares_added = payload.values().sum::<Decimal>();
+
+        if !database::store_time_weighted_work(conn, &self.chain_id, &spans, blockheight).await? {
+            warn!(%blockheight, "work of this height was added before, not adding it again");
+
+            return Ok((0, Decimal::ZERO));
+        }
+
+        Ok((stakers_counted, shares_added))
+    }
+
+    /// Stores the stake if this block is a stake of the pool, and returns whether it was.
+    #[instrument(skip(self))]
+    async fn check_for_stake(
+        &self,
+        conn: &mut PgConnection,
+        block_hash: &BlockHash,
+    ) -> Result<bool> {
+        if let Some(stake) = self.is_stake(conn, block_hash).await? {
+            info!(height = %stake.block_height, ">>>>>>>>>>>>>>> stake found");
+
+            database::store_new_stake(conn, &stake).await?;
+
+            let client = self.verusd()?;
+            let currency_name = client
+                .get_currency(&stake.currency_address.to_string())?
+                .fullyqualifiedname;
+
+            self.publish(PoolEvent::StakeFound {
+                currency_name,
+                stake,
+            });
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    async fn is_stake(
+        &self,
+        conn: &mut PgConnection,
+        block_hash: &BlockHash,
+    ) -> Result<Option<Stake>> {
+        let client = self.verusd()?;
+        let block = client.get_block(block_hash, 2)?;
+
+        // block.confirmations == -1 indicates it is stale and should be ignored
+        if matches!(block.validation_type, ValidationType::Stake) && block.confirmations >= 0 {
+            let postxddest = postxddest(&block)?;
+
+            if let Some(stake) = self.is_staked_by_pool(&block, &postxddest).await? {
+                return Ok(Some(stake));
             }
 
             let active_stakers =
-                database::get_stakers_by_status(&self.pool, &self.chain_id, StakerStatus::Active)
-                    .await?;
+                database::get_stakers_by_status(conn, &self.chain_id, StakerStatus::Active).await?;
 
-            let Some(staker) = active_stakers.iter().find(|s| {
-                &s.identity_address == &postxddest && s.currency_address == self.chain_id
-            }) else {
-                return Ok(None);
-            };
+            if let Some(staker) = active_stakers
+                .iter()
+                .find(|s| &s.identity_address == &postxddest && s.currency_address == self.chain_id)
+            {
+                trace!("{} staked a block", staker.identity_address);
+
+                let stake = stake_from_block(&self.chain_id, &block)?;
 
-            trace!("{} staked a block", staker.identity_address);
+                return Ok(Some(stake));
+            }
+
+            let active_staker_addresses = active_stakers
+                .iter()
+                .map(|staker| staker.identity_address.clone())
+                .collect::<Vec<_>>();
+
+            if let Some(identity_address) = self
+                .get_delegators(conn, &active_staker_addresses)
+                .await?
+                .remove(&postxddest)
+            {
+                trace!(%postxddest, "{} staked a block with a delegated address", identity_address);
 
-            let stake = Stake::try_new(&self.chain_id, &block)?;
+                // the stake is attributed to the staker, so that its work gets compensated
+                let mut stake = stake_from_block(&self.chain_id, &block)?;
+                stake.found_by = identity_address;
 
-            return Ok(Some(stake));
+                return Ok(Some(stake));
+            }
         }
 
         Ok(None)
     }
 
-    async fn is_staked_by_pool(
+    async fn is_staked_by_pool(
+        &self,
+        block: &Block,
+        postxddest: &Address,
+    ) -> Result<Option<Stake>> {
+        if &self.config.pool_address == postxddest {
+            info!(?postxddest, "staked by pool address");
+
+            let stake = stake_from_block(&self.chain_id, &block)?;
+
+            Ok(Some(stake))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Schedules the forfeit of the round 0 work of a staker that just became inactive,
+    /// according to the configured `InactiveWorkPolicy`.
+    ///
+    /// The forfeit itself happens in `forfeit_departed_work`, after the work of the current
+    /// block was added, so a staker that leaves is still counted in the block it left in.
+    async fn apply_inactive_work_policy(
+        &self,
+        conn: &mut PgConnection,
+        staker: &Staker,
+    ) -> Result<()> {
+        let rounds = match self.config.inactive_work_policy {
+            InactiveWorkPolicy::Keep | InactiveWorkPolicy::PayOnDeparture => return Ok(()),
+            InactiveWorkPolicy::Hold { rounds } => rounds,
+            InactiveWorkPolicy::Forfeit => 0,
+        };
+
+        debug!(staker = %staker.identity_address, %rounds, "scheduled forfeit of work");
+
+        database::schedule_forfeit(conn, &self.chain_id, &staker.identity_address, rounds).await?;
+
+        Ok(())
+    }
+
+    /// Schedules the final payout of a staker that became inactive, after the grace period in
+    /// which it can still become active again. A staker is paid right away with
+    /// `InactiveWorkPolicy::PayOnDeparture`.
+    async fn schedule_settlement(&self, conn: &mut PgConnection, staker: &Staker) -> Result<()> {
+        let grace_period = match self.config.inactive_work_policy {
+            InactiveWorkPolicy::PayOnDeparture => 0,
+            _ => self.config.settlement_grace_period_in_hours * 3600,
+        };
+        let settle_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + grace_period;
+
+        debug!(staker = %staker.identity_address, %settle_at, "scheduled settlement");
+
+        database::schedule_settlement(conn, &self.chain_id, &staker.identity_address, settle_at)
+            .await?;
+
+        self.publish(PoolEvent::SettlementScheduled {
+            staker: staker.clone(),
+            settle_at,
+        });
+
+        Ok(())
+    }
+
+    /// Holds the round 0 work of stakers that just left under `InactiveWorkPolicy::Hold`, counts
+    /// the stake that was found in this block towards the holds that were already running and
+    /// forfeits the work of which the hold is over.
+    async fn forfeit_departed_work(
+        &self,
+        conn: &mut PgConnection,
+        stake_found: bool,
+    ) -> Result<()> {
+        if stake_found {
+            database::count_held_round(conn, &self.chain_id).await?;
+        }
+
+        for staker_address in database::hold_departed_work(conn, &self.chain_id).await? {
+            info!(staker = %staker_address, "holding work of inactive staker");
+        }
+
+        for staker_address in database::get_due_forfeits(conn, &self.chain_id).await? {
+            info!(staker = %staker_address, "forfeiting work of inactive staker");
+
+            database::forfeit_work(conn, &self.chain_id, &staker_address).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes a final statement for every inactive staker of which the settlement is due and that
+    /// is owed nothing anymore, which closes the staker. A remainder that is too small to be paid
+    /// is forfeited first.
+    async fn close_departed_stakers(&self, conn: &mut PgConnection) -> Result<()> {
+        for staker in database::get_departed_stakers_without_statement(
+            conn,
+            &self.chain_id,
+            self.tx_fee().min_payable(),
+        )
+        .await?
+        {
+            let forfeited =
+                database::forfeit_unpaid_rewards(conn, &self.chain_id, &staker.identity_address)
+                    .await?;
+            if forfeited > Amount::ZERO {
+                info!(
+                    staker = %staker.identity_address,
+                    %forfeited,
+                    "forfeited the remainder that is too small to be paid"
+                );
+            }
+
+            let statement =
+                database::create_staker_statement(conn, &self.chain_id, &staker.identity_address)
+                    .await?;
+
+            info!(staker = %staker.identity_address, rewards = %statement.rewards, "staker departed");
+
+            self.publish(PoolEvent::StakerDeparted(statement));
+        }
+
+        Ok(())
+    }
+
+    /// The tx fee of a payment of this chain, and who bears it.
+    fn tx_fee(&self) -> TxFee {
+        TxFee {
+            amount: self.config.tx_fee,
+            paid_by_pool: self.config.payout_config.pool_pays_tx_fee,
+        }
+    }
+
+    /// Returns the pool primary addresses of which one must be in the VerusID of a staker, the
+    /// address that new stakers are given first.
+    ///
+    /// During a rotation of the pool primary address, both the current and the new address
+    /// are accepted. Once the rotation has ended, only the new address is accepted. The
+    /// `accepted_primary_addresses` of the config are always accepted.
+    fn accepted_primary_addresses(&self) -> Vec<&Address> {
+        let mut addresses = match &self.config.primary_address_rotation {
+            Some(rotation) if rotation.has_ended() => vec![&rotation.new_address],
+            Some(rotation) => vec![&rotation.new_address, &self.config.pool_primary_address],
+            None => vec![&self.config.pool_primary_address],
+        };
+
+        for address in &self.config.accepted_primary_addresses {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+
+        addresses
+    }
+
+    /// Stores which of the accepted pool primary addresses the VerusID of a staker includes, so
+    /// that a rotation of the pool primary address can be followed per staker. If it includes
+    /// more than one, the one that new stakers are given is preferred.
+    async fn track_primary_address(
+        &self,
+        conn: &mut PgConnection,
+        staker: &Staker,
+        identity: &IdentityPrimary,
+    ) -> Result<()> {
+        let primary_address = self
+            .accepted_primary_addresses()
+            .into_iter()
+            .find(|address| identity.primaryaddresses.contains(address));
+
+        database::store_staker_primary_address(
+            conn,
+            &self.chain_id,
+            &staker.identity_address,
+            primary_address,
+        )
+        .await
+    }
+
+    /// Notifies stakers that have not yet added the new pool primary address to their VerusID.
+    ///
+    /// Every staker is notified only once. Once the rotation has ended, stakers that did not
+    /// update their VerusID are checked again, which makes them inactive.
+    async fn process_primary_address_rotation(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+    ) -> Result<()> {
+        let Some(rotation) = &self.config.primary_address_rotation else {
+            return Ok(());
+        };
+
+        let progress = database::get_rotation_progress(conn, &self.chain_id, &rotation.new_address)
+            .await?
+            .into_iter()
+            .map(|progress| (progress.identity_address.clone(), progress))
+            .collect::<HashMap<_, _>>();
+
+        let mut stakers =
+            database::get_stakers_by_status(conn, &self.chain_id, StakerStatus::Active).await?;
+        stakers.extend(
+            database::get_stakers_by_status(conn, &self.chain_id, StakerStatus::CoolingDown)
+                .await?,
+        );
+        // cold stakers have no primary addresses to rotate
+        stakers.retain(|staker| staker.participation_kind == ParticipationKind::Vault);
+
+        for staker in stakers {
+            let progress = progress.get(&staker.identity_address);
+
+            if progress.is_some_and(|p| p.migrated) {
+                continue;
+            }
+
+            if rotation.has_ended() {
+                self.check_staker_status(conn, client, &staker.identity_address)
+                    .await?;
+
+                continue;
+            }
+
+            if progress.is_some_and(|p| p.notified) {
+                continue;
+            }
+
+            let identity = client.get_identity(&staker.identity_address.to_string())?;
+            let migrated = identity
+                .identity
+                .primaryaddresses
+                .contains(&rotation.new_address);
+
+            if !migrated {
+                self.publish(PoolEvent::PrimaryAddressRotation {
+                    staker: staker.clone(),
+                    new_address: rotation.new_address.clone(),
+                    ends_at: rotation.ends_at,
+                });
+            }
+
+            database::store_rotation_progress(
+                conn,
+                &self.chain_id,
+                &RotationProgress {
+                    identity_address: staker.identity_address,
+                    new_address: rotation.new_address.clone(),
+                    migrated,
+                    notified: !migrated,
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the rotations of the pool primary address that an operator started or retired to
+    /// the pool primary address and rotation of the config.
+    async fn load_key_rotations(&mut self) -> Result<()> {
+        let rotations = database::get_key_rotations(&self.pool, &self.chain_id).await?;
+
+        let (pool_primary_address, rotation) = key_rotation::apply(
+            &self.config.pool_primary_address,
+            self.config.primary_address_rotation.as_ref(),
+            &rotations,
+        );
+
+        if pool_primary_address != self.config.pool_primary_address {
+            info!(%pool_primary_address, "pool primary address was rotated by an operator");
+        }
+
+        self.config.pool_primary_address = pool_primary_address;
+        self.config.primary_address_rotation = rotation;
+
+        Ok(())
+    }
+
+    /// Starts a rotation of the pool primary address on request of an operator. Until `ends_at`,
+    /// VerusIDs that include either address are eligible, and from the next block on stakers
+    /// are notified to add the new address to their VerusID.
+    async fn start_key_rotation(
+        &mut self,
+        new_address: Address,
+        ends_at: u64,
+        operator: String,
+    ) -> Result<KeyRotation> {
+        if let Some(rotation) = &self.config.primary_address_rotation {
+            bail!("a rotation to {} is already going on", rotation.new_address);
+        }
+
+        if new_address == self.config.pool_primary_address {
+            bail!("{new_address} is the pool primary address already");
+        }
+
+        if ends_at <= SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() {
+            bail!("the transition window must end in the future");
+        }
+
+        // stakers that move to the new address can only be staked for if the pool holds its key
+        let validation = self
+            .verusd()?
+            .call::<AddressValidation>("validateaddress", &[new_address.to_string().into()])?;
+        if !validation.can_sign() {
+            bail!("the pool wallet can't sign with {new_address}, import its key first");
+        }
+
+        let rotation = database::store_key_rotation(
+            &self.pool,
+            &self.chain_id,
+            &self.config.pool_primary_address,
+            &new_address,
+            ends_at,
+            &operator,
+        )
+        .await?;
+
+        warn!(
+            old_address = %rotation.old_address,
+            %new_address,
+            ends_at,
+            %operator,
+            "rotation of the pool primary address started on request"
+        );
+
+        self.config.primary_address_rotation = Some(PrimaryAddressRotation {
+            new_address,
+            ends_at,
+        });
+
+        Ok(rotation)
+    }
+
+    /// Retires the old pool primary address once the transition window of a rotation that an
+    /// operator started has ended. The stakers that did not add the new address to their
+    /// VerusID are checked again, which makes them inactive.
+    async fn retire_key_rotation(&mut self, operator: String) -> Result<KeyRotation> {
+        let Some(rotation) = self.config.primary_address_rotation.clone() else {
+            bail!("no rotation is going on");
+        };
+
+        if !rotation.has_ended() {
+            bail!(
+                "the transition window of the rotation to {} ends at {}",
+                rotation.new_address,
+                rotation.ends_at
+            );
+        }
+
+        let Some(key_rotation) =
+            database::retire_key_rotation(&self.pool, &self.chain_id, &operator).await?
+        else {
+            bail!(
+                "the rotation to {} is configured, remove it from the config to retire it",
+                rotation.new_address
+            );
+        };
+
+        let mut conn = self.pool.acquire().await?;
+        let migrated =
+            database::get_rotation_progress(&mut conn, &self.chain_id, &rotation.new_address)
+                .await?
+                .into_iter()
+                .filter(|progress| progress.migrated)
+                .map(|progress| progress.identity_address)
+                .collect::<Vec<_>>();
+
+        self.config.pool_primary_address = rotation.new_address;
+        self.config.primary_address_rotation = None;
+
+        warn!(
+            old_address = %key_rotation.old_address,
+            new_address = %key_rotation.new_address,
+            %operator,
+            "old pool primary address retired on request"
+        );
+
+        let client = self.verusd()?;
+        let mut stakers =
+            database::get_stakers_by_status(&mut conn, &self.chain_id, StakerStatus::Active)
+                .await?;
+        stakers.extend(
+            database::get_stakers_by_status(&mut conn, &self.chain_id, StakerStatus::CoolingDown)
+                .await?,
+        );
+
+        for staker in stakers {
+            if staker.participation_kind != ParticipationKind::Vault
+                || migrated.contains(&staker.identity_address)
+            {
+                continue;
+            }
+
+            self.check_staker_status(&mut conn, &client, &staker.identity_address)
+                .await?;
+        }
+
+        Ok(key_rotation)
+    }
+
+    /// Returns the pool primary address, the rotation that is going on and the migration of
+    /// every staker to its new address.
+    async fn key_rotation_status(&self) -> Result<KeyRotationStatus> {
+        let rotation = self.config.primary_address_rotation.as_ref();
+
+        let stakers = match rotation {
+            Some(rotation) => {
+                database::get_rotation_progress(
+                    &mut *self.pool.acquire().await?,
+                    &self.chain_id,
+                    &rotation.new_address,
+                )
+                .await?
+            }
+            None => vec![],
+        };
+
+        Ok(KeyRotationStatus {
+            currency_address: self.chain_id.clone(),
+            primary_address: self.config.pool_primary_address.clone(),
+            new_address: rotation.map(|rotation| rotation.new_address.clone()),
+            ends_at: rotation.map(|rotation| rotation.ends_at),
+            stakers,
+        })
+    }
+
+    /// A staker is eligible if its VerusID adheres to the conditions of this pool, or, if this
+    /// chain supports delegated staking, if it delegates the staking of at least one address.
+    async fn staker_is_eligible(
+        &self,
+        conn: &mut PgConnection,
+        identity: &IdentityPrimary,
+        block_height: u64,
+    ) -> Result<bool> {
+        if self.identity_is_eligible(identity, block_height) {
+            return Ok(true);
+        }
+
+        if !self.config.delegated_staking {
+            return Ok(false);
+        }
+
+        let delegated_addresses = database::get_delegated_addresses(
+            conn,
+            &self.chain_id,
+            &[identity.identityaddress.clone()],
+        )
+        .await?;
+
+        Ok(!delegated_addresses.is_empty())
+    }
+
+    /// Returns the identity address of the staker per delegated address of the given stakers.
+    ///
+    /// Always returns an empty map if this chain does not support delegated staking.
+    async fn get_delegators(
+        &self,
+        conn: &mut PgConnection,
+        identity_addresses: &[Address],
+    ) -> Result<HashMap<Address, Address>> {
+        if !self.config.delegated_staking {
+            return Ok(HashMap::new());
+        }
+
+        let delegators =
+            database::get_delegated_addresses(conn, &self.chain_id, identity_addresses)
+                .await?
+                .into_iter()
+                .map(|delegated| (delegated.address, delegated.identity_address))
+                .collect();
+
+        Ok(delegators)
+    }
+
+    /// Removes the delegated addresses of a staker that are no longer a primary address of its
+    /// VerusID.
+    async fn prune_delegated_addresses(
+        &self,
+        conn: &mut PgConnection,
+        identity: &IdentityPrimary,
+    ) -> Result<()> {
+        if !self.config.delegated_staking {
+            return Ok(());
+        }
+
+        let delegated_addresses = database::get_delegated_addresses(
+            conn,
+            &self.chain_id,
+            &[identity.identityaddress.clone()],
+        )
+        .await?;
+
+        for delegated in delegated_addresses {
+            if !identity.primaryaddresses.contains(&delegated.address) {
+                debug!(address = %delegated.address, "address is no longer delegated");
+
+                database::remove_delegated_address(conn, &self.chain_id, &delegated.address)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lets the pool stake the funds of `address` on behalf of a staker, without the funds being
+    /// moved into the VerusID of the staker.
+    ///
+    /// The address must be a primary address of the VerusID, which proves the staker controls it,
+    /// and the daemon must be allowed to stake its funds.
+    async fn delegate_staking(
+        &self,
+        client: &VerusClient,
+        identity_address: &Address,
+        address: &Address,
+        signature: &str,
+    ) -> Result<Staker> {
+        if !self.config.delegated_staking {
+            bail!("delegated staking is not supported on this chain");
+        }
+
+        // the funds of the pool are not the work of a staker
+        if *address == self.config.pool_address
+            || self.accepted_primary_addresses().contains(&address)
+        {
+            bail!("{address} is an address of the pool");
+        }
+
+        let identity = client.get_identity(&identity_address.to_string())?.identity;
+        if !identity.primaryaddresses.contains(address) {
+            bail!("{address} is not a primary address of {identity_address}");
+        }
+
+        let message = delegation_message(&self.chain_id, address, &identity.identityaddress);
+        if !self.verify_message(address, &message, signature)? {
+            bail!("the signature is not valid for {address}");
+        }
+
+        let addresses = vec![address.clone()];
+        if client
+            .list_unspent(Some(0), None, Some(addresses.as_ref()))?
+            .is_empty()
+        {
+            bail!("the pool wallet can't stake the funds of {address}");
+        }
+
+        if !database::store_delegated_address(
+            &self.pool,
+            &self.chain_id,
+            &DelegatedAddress {
+                identity_address: identity.identityaddress.clone(),
+                address: address.clone(),
+            },
+        )
+        .await?
+        {
+            bail!("{address} is delegated by another staker");
+        }
+
+        self.check_staker_status(&mut *self.pool.acquire().await?, client, identity_address)
+            .await?
+            .ok_or_else(|| anyhow!("{identity_address} is not eligible to stake with the pool"))
+    }
+
+    fn identity_is_eligible(&self, identity: &IdentityPrimary, block_height: u64) -> bool {
+        check_identity(
+            identity,
+            &self.accepted_primary_addresses(),
+            self.config.vault_conditions.as_ref(),
+            block_height,
+        )
+        .iter()
+        .all(|check| check.passed)
+    }
+
+    /// Runs the checks of [`Self::check_staker_status`] against the current state of a VerusID,
+    /// without storing anything. Returns `None` if the daemon doesn't know the VerusID.
+    async fn check_eligibility(
         &self,
-        block: &Block,
-        postxddest: &Address,
-    ) -> Result<Option<Stake>> {
-        if &self.config.pool_address == postxddest {
-            info!(?postxddest, "staked by pool address");
+        client: &VerusClient,
+        identity: &str,
+    ) -> Result<Option<Eligibility>> {
+        let Ok(identity) = client.get_identity(identity) else {
+            return Ok(None);
+        };
+        let identity = identity.identity;
+        let currency = client.get_currency(&self.chain_id.to_string())?;
+        let block_height = client.get_blockchain_info()?.blocks;
 
-            let stake = Stake::try_new(&self.chain_id, &block)?;
+        let root_identity =
+            (currency.options & 0b100 != 0).then(|| check_root_identity(&identity, &self.chain_id));
+        let is_root_identity = root_identity.as_ref().map(|check| check.passed) != Some(false);
+        let identity_checks = check_identity(
+            &identity,
+            &self.accepted_primary_addresses(),
+            self.config.vault_conditions.as_ref(),
+            block_height,
+        );
+        let mut eligible = identity_checks.iter().all(|check| check.passed);
 
-            Ok(Some(stake))
-        } else {
-            Ok(None)
-        }
-    }
+        let mut checks = root_identity.into_iter().collect::<Vec<_>>();
+        checks.extend(identity_checks);
 
-    fn identity_is_eligible(&self, identity: &IdentityPrimary) -> bool {
-        // general conditions that need to be true regardless of vault conditions
-        if identity.minimumsignatures == 1
-            && identity.primaryaddresses.len() > 1
-            && identity
-                .primaryaddresses
-                .contains(&self.config.pool_primary_address)
-        {
-            if let Some(conditions) = &self.config.vault_conditions {
-                // check vault conditions
-                if identity.primaryaddresses.len() <= conditions.max_primary_addresses as usize
-                    && if conditions.strict_recovery_id {
-                        identity.recoveryauthority != identity.identityaddress
-                            && identity.revocationauthority != identity.identityaddress
-                    } else {
-                        true
-                    }
-                {
-                    match identity.flags {
-                        0 => {
-                            // no time lock set
-                            return true;
-                        }
-                        // fixed time lock; unlock at x seconds (epoch)
-                        1 => {
-                            // TODO v2, ineligible until then
-                            return false;
-                        }
-                        // delay lock; unlock after x seconds
-                        2 => return identity.timelock >= conditions.min_time_lock as u64,
-                        _ => return false,
-                    }
-                }
-            } else {
-                return true;
-            }
+        if self.config.delegated_staking {
+            let delegated_addresses = database::get_delegated_addresses(
+                &mut *self.pool.acquire().await?,
+                &self.chain_id,
+                &[identity.identityaddress.clone()],
+            )
+            .await?;
+
+            eligible |= !delegated_addresses.is_empty();
+            checks.push(EligibilityCheck {
+                condition: EligibilityCondition::DelegatedStaking,
+                passed: !delegated_addresses.is_empty(),
+                detail: format!(
+                    "the pool stakes the funds of {} primary addresses",
+                    delegated_addresses.len()
+                ),
+            });
         }
 
-        false
+        Ok(Some(Eligibility {
+            identity_address: identity.identityaddress,
+            eligible: eligible && is_root_identity,
+            checks,
+        }))
     }
 
     /// Gets the staking supply of the given addresses
@@ -533,7 +3333,7 @@ impl CoinStaker {
         )
         .await?;
 
-        let identity_addresses = stakers
+        let mut identity_addresses = stakers
             .into_iter()
             .filter(|s| {
                 let is_subscribed = s.status == StakerStatus::Active;
@@ -552,24 +3352,69 @@ impl CoinStaker {
             .map(|s| s.identity_address)
             .collect::<Vec<_>>();
 
-        let staking_supply =
-            get_staking_supply(&self.chain_id, &identity_addresses, &verus_client)?;
+        let delegators = self
+            .get_delegators(&mut *self.pool.acquire().await?, &identity_addresses)
+            .await?;
+        identity_addresses.extend(delegators.into_keys());
+
+        let staking_supply = get_staking_supply(
+            &self.chain_id,
+            &identity_addresses,
+            &verus_client,
+            self.config.utxo_eligibility_confirmations,
+        )?;
 
         Ok(staking_supply)
     }
 
-    async fn check_stakers(&self, verus_client: &VerusClient, block: &Block) -> Result<()> {
+    /// Checks the status of the VerusIDs that were updated in this block, of the stakers of which
+    /// the VerusID unlocks soon, of the cold stakers and of the stakers that are cooling down,
+    /// and returns the number of VerusIDs that were checked.
+    async fn check_stakers(
+        &self,
+        conn: &mut PgConnection,
+        verus_client: &VerusClient,
+        block: &Block,
+    ) -> Result<u64> {
+        let mut identities_checked = 0;
+
         for tx in &block.tx {
             for vout in &tx.vout {
                 if let Some(identity_primary) = &vout.script_pubkey.identityprimary {
-                    self.check_staker_status(verus_client, &identity_primary.identityaddress)
+                    self.check_staker_status(conn, verus_client, &identity_primary.identityaddress)
                         .await?;
+                    identities_checked += 1;
                 }
             }
         }
 
+        if let Some(conditions) = &self.config.vault_conditions {
+            let threshold = block.height + conditions.min_remaining_lock as u64;
+
+            for identity_address in
+                database::get_unlocking_stakers(conn, &self.chain_id, threshold).await?
+            {
+                debug!(%identity_address, "verusid unlocks soon, checking it again");
+
+                self.check_staker_status(conn, verus_client, &identity_address)
+                    .await?;
+                identities_checked += 1;
+            }
+        }
+
+        self.check_cold_stakers(conn, verus_client, block.height)
+            .await?;
+
+        if let Some(days) = self.config.staker_expiry_in_days {
+            for staker in database::expire_cooling_down_stakers(conn, &self.chain_id, days).await? {
+                info!(identity = %staker.identity_name, "staker was cooling down for too long, expired");
+
+                self.publish(PoolEvent::ExpiredStaker(staker));
+            }
+        }
+
         let cooling_down_stakers =
-            database::get_stakers_by_status(&self.pool, &self.chain_id, StakerStatus::CoolingDown)
+            database::get_stakers_by_status(conn, &self.chain_id, StakerStatus::CoolingDown)
                 .await?;
 
         for mut cooling_down_staker in cooling_down_stakers {
@@ -578,65 +3423,87 @@ impl CoinStaker {
                 0,
                 99999999,
             )?;
+            identities_checked += 1;
+
             if identity.blockheight < block.height.saturating_sub(6) as i64 {
                 trace!(?cooling_down_staker, "id has cooled down, activate");
                 cooling_down_staker.status = StakerStatus::Active;
 
-                database::store_staker(&self.pool, &cooling_down_staker).await?;
+                database::store_staker(conn, &cooling_down_staker).await?;
 
-                self.webhooks
-                    .send(WebhookMessage::NewStaker {
-                        identity_address: cooling_down_staker.identity_address,
-                        identity_name: cooling_down_staker.identity_name,
-                    })
-                    .await;
+                self.publish(PoolEvent::NewStaker(cooling_down_staker));
             } else {
                 trace!(?cooling_down_staker, "staker still cooling down");
             }
         }
 
-        Ok(())
+        Ok(identities_checked)
     }
 
     async fn check_staker_status(
         &self,
+        conn: &mut PgConnection,
         client: &VerusClient,
         identity_address: &Address,
     ) -> Result<Option<Staker>> {
+        if let Some(staker) = database::get_staker(conn, &self.chain_id, identity_address)
+            .await?
+            .filter(|staker| staker.participation_kind == ParticipationKind::ColdStaking)
+        {
+            return Ok(Some(self.check_cold_staker(conn, client, staker).await?));
+        }
+
         let identity = client.get_identity(&identity_address.to_string())?;
         let currency = client.get_currency(&self.chain_id.to_string())?;
+        let block_height = client.get_blockchain_info()?.blocks;
 
         // if the chain has IDSTAKING enabled, check if this staker has a root id for this chain
         // if not, it's not eligible.
         if currency.options & 0b100 != 0
-            && (identity.identity.systemid != self.chain_id
-                || identity.identity.parent != self.chain_id)
+            && !check_root_identity(&identity.identity, &self.chain_id).passed
         {
             return Ok(None);
         }
 
-        if let Some(mut staker) = database::get_staker(
-            &self.pool,
-            &self.chain_id,
-            &identity.identity.identityaddress,
+        self.update_staker_status(
+            conn,
+            &identity.identity,
+            &identity.fullyqualifiedname,
+            block_height,
         )
-        .await?
+        .await
+    }
+
+    /// Follows a change to the VerusID `identity`, named `name`, that was looked up at
+    /// `block_height`: a staker that is no longer eligible becomes inactive, and an eligible
+    /// VerusID becomes a staker again or for the first time.
+    async fn update_staker_status(
+        &self,
+        conn: &mut PgConnection,
+        identity: &IdentityPrimary,
+        name: &str,
+        block_height: u64,
+    ) -> Result<Option<Staker>> {
+        self.prune_delegated_addresses(conn, identity).await?;
+
+        if let Some(mut staker) =
+            database::get_staker(conn, &self.chain_id, &identity.identityaddress).await?
         {
             debug!(?staker, "staker found in database");
 
             match staker.status {
                 StakerStatus::Active => {
-                    if !self.identity_is_eligible(&identity.identity) {
+                    if !self
+                        .staker_is_eligible(conn, identity, block_height)
+                        .await?
+                    {
                         trace!(?identity, "a change to this verusid made it inactive");
                         staker.status = StakerStatus::Inactive;
-                        database::store_staker(&self.pool, &staker).await?;
-
-                        self.webhooks
-                            .send(WebhookMessage::LeavingStaker {
-                                identity_address: staker.identity_address.clone(),
-                                identity_name: staker.identity_name.clone(),
-                            })
-                            .await;
+                        database::store_staker(conn, &staker).await?;
+                        self.apply_inactive_work_policy(conn, &staker).await?;
+                        self.schedule_settlement(conn, &staker).await?;
+
+                        self.publish(PoolEvent::LeavingStaker(staker.clone()));
                         // TODO any change to a verusid was supposed to set eligibility for
                         // staking to false, so we would have to wait for that time to pass.
                         // but this doesn't seem to be the case, at least not for some kinds
@@ -648,42 +3515,85 @@ impl CoinStaker {
                 }
                 StakerStatus::CoolingDown => {
                     // an update was made to a staker that was already cooling down.
-                    if !self.identity_is_eligible(&identity.identity) {
+                    if !self
+                        .staker_is_eligible(conn, identity, block_height)
+                        .await?
+                    {
                         trace!(?identity, "a change to this verusid made it inactive");
 
                         staker.status = StakerStatus::Inactive;
-                        database::store_staker(&self.pool, &staker).await?;
+                        database::store_staker(conn, &staker).await?;
+                        self.apply_inactive_work_policy(conn, &staker).await?;
+                        self.schedule_settlement(conn, &staker).await?;
                     }
                 }
-                StakerStatus::Inactive => {
-                    if self.identity_is_eligible(&identity.identity) {
+                StakerStatus::Banned => {
+                    trace!(?staker, "banned staker stays banned");
+                }
+                StakerStatus::Inactive | StakerStatus::Expired => {
+                    if self
+                        .staker_is_eligible(conn, identity, block_height)
+                        .await?
+                    {
                         trace!(?staker, "inactive staker got reactivated");
                         staker.status = StakerStatus::CoolingDown;
-                        database::store_staker(&self.pool, &staker).await?;
+                        database::store_staker(conn, &staker).await?;
+                        database::cancel_scheduled_forfeit(
+                            conn,
+                            &self.chain_id,
+                            &staker.identity_address,
+                        )
+                        .await?;
+                        database::cancel_settlement(conn, &self.chain_id, &staker.identity_address)
+                            .await?;
                     }
                 }
             }
 
+            if let Some(rotation) = &self.config.primary_address_rotation {
+                database::store_rotation_progress(
+                    conn,
+                    &self.chain_id,
+                    &RotationProgress {
+                        identity_address: staker.identity_address.clone(),
+                        new_address: rotation.new_address.clone(),
+                        migrated: identity.primaryaddresses.contains(&rotation.new_address),
+                        notified: false,
+                    },
+                )
+                .await?;
+            }
+
+            self.track_unlock_height(conn, &staker, identity, block_height)
+                .await?;
+            self.track_primary_address(conn, &staker, identity).await?;
+
             return Ok(Some(staker));
         } else {
             trace!("verusid not found in database");
 
-            if self.identity_is_eligible(&identity.identity) {
+            if self
+                .staker_is_eligible(conn, identity, block_height)
+                .await?
+            {
                 let staker = Staker::new(
                     self.chain_id.clone(),
-                    identity.identity.identityaddress.clone(),
-                    identity.fullyqualifiedname.clone(),
+                    identity.identityaddress.clone(),
+                    name.to_string(),
                     self.config.min_payout,
                     StakerStatus::CoolingDown,
                     self.config.fee,
                 );
 
-                database::store_staker(&self.pool, &staker).await?;
+                database::store_staker(conn, &staker).await?;
                 trace!("new staker stored in database.");
+                self.track_unlock_height(conn, &staker, identity, block_height)
+                    .await?;
+                self.track_primary_address(conn, &staker, identity).await?;
 
                 return Ok(Some(staker));
             } else {
-                trace!(id = &identity.fullyqualifiedname, "verusid not eligible");
+                trace!(id = name, "verusid not eligible");
             }
             // if the staker does not yet exist, we should check if it contains
             // the primary address of the pool
@@ -692,49 +3602,446 @@ impl CoinStaker {
 
         Ok(None)
     }
+
+    /// Registers `address` as a cold staker of which the rewards are paid to `payout_address`,
+    /// or changes the payout address of a cold staker that registered before. `signature` is
+    /// the signature of the registration message by `address`.
+    async fn register_cold_staker(
+        &self,
+        address: Address,
+        payout_address: Address,
+        signature: &str,
+    ) -> Result<Staker> {
+        if self.config.cold_staking.is_none() {
+            bail!("cold staking is not supported on this chain");
+        }
+
+        let client = self.verusd()?;
+        if client.get_identity(&address.to_string()).is_ok() {
+            bail!("{address} is a VerusID, which takes part as a vault");
+        }
+
+        let message = cold_staking::registration_message(&self.chain_id, &address, &payout_address);
+        if !self.verify_message(&address, &message, signature)? {
+            bail!("the signature is not valid for {address}");
+        }
+
+        // the funds of an address that the pool wallet doesn't list can't be staked by the pool
+        let addresses = vec![address.clone()];
+        if client
+            .list_unspent(Some(0), None, Some(addresses.as_ref()))?
+            .is_empty()
+        {
+            bail!("the pool wallet can't stake the funds of {address}");
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        let staker = match database::get_staker(&mut conn, &self.chain_id, &address).await? {
+            Some(staker) if staker.participation_kind != ParticipationKind::ColdStaking => {
+                bail!("{address} is already a staker")
+            }
+            Some(mut staker) => {
+                staker.payout_address = Some(payout_address);
+
+                staker
+            }
+            None => Staker::cold_staker(
+                self.chain_id.clone(),
+                address,
+                payout_address,
+                self.config.min_payout,
+                StakerStatus::Inactive,
+                self.config.fee,
+            ),
+        };
+        database::store_staker(&mut conn, &staker).await?;
+        info!(address = %staker.identity_address, "registered cold staker");
+
+        self.check_cold_staker(&mut conn, &client, staker).await
+    }
+
+    /// Checks the status of every cold staker against its eligible balance at `block_height`.
+    async fn check_cold_stakers(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        block_height: u64,
+    ) -> Result<()> {
+        let stakers = database::get_cold_stakers(conn, &self.chain_id).await?;
+        if stakers.is_empty() {
+            return Ok(());
+        }
+
+        let addresses = stakers
+            .iter()
+            .map(|staker| staker.identity_address.clone())
+            .collect::<Vec<_>>();
+        let balances = self.cold_staking_balances(client, &addresses, block_height)?;
+
+        for staker in stakers {
+            let balance = balances.get(&staker.identity_address).copied();
+            self.update_cold_staker(conn, client, staker, balance)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the status of a single cold staker against its eligible balance.
+    async fn check_cold_staker(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        staker: Staker,
+    ) -> Result<Staker> {
+        let block_height = client.get_blockchain_info()?.blocks;
+        let balance = self
+            .cold_staking_balances(client, &[staker.identity_address.clone()], block_height)?
+            .remove(&staker.identity_address);
+
+        self.update_cold_staker(conn, client, staker, balance).await
+    }
+
+    /// The balances of cold staking addresses that are eligible to stake at `block_height`.
+    /// Without cold staking on this chain, no balance is eligible.
+    fn cold_staking_balances(
+        &self,
+        client: &VerusClient,
+        addresses: &[Address],
+        block_height: u64,
+    ) -> Result<HashMap<Address, Amount>> {
+        if self.config.cold_staking.is_none() {
+            return Ok(HashMap::new());
+        }
+
+        let utxos = eligible_utxos(
+            client,
+            &[],
+            &HashMap::new(),
+            addresses,
+            self.config.utxo_eligibility_confirmations as u64,
+        )?;
+
+        Ok(cold_staking::eligible_balances(&utxos, block_height))
+    }
+
+    /// A cold staker is active while its eligible balance is at least the `min_balance` of the
+    /// cold staking config. Unlike a VerusID, an address has nothing to cool down from, so an
+    /// eligible cold staker becomes active right away.
+    async fn update_cold_staker(
+        &self,
+        conn: &mut PgConnection,
+        client: &VerusClient,
+        mut staker: Staker,
+        balance: Option<Amount>,
+    ) -> Result<Staker> {
+        let eligible = self
+            .config
+            .cold_staking
+            .as_ref()
+            .zip(balance)
+            .is_some_and(|(cold_staking, balance)| balance >= cold_staking.min_balance);
+
+        match staker.status {
+            StakerStatus::Active | StakerStatus::CoolingDown if !eligible => {
+                trace!(?staker, "cold staker is no longer eligible");
+
+                staker.status = StakerStatus::Inactive;
+                database::store_staker(conn, &staker).await?;
+                self.apply_inactive_work_policy(conn, client, &staker)
+                    .await?;
+                self.schedule_settlement(conn, &staker).await?;
+
+                self.publish(PoolEvent::LeavingStaker(staker.clone()));
+            }
+            StakerStatus::Inactive | StakerStatus::Expired if eligible => {
+                trace!(?staker, "cold staker became eligible");
+
+                staker.status = StakerStatus::Active;
+                database::store_staker(conn, &staker).await?;
+                database::cancel_scheduled_forfeit(conn, &self.chain_id, &staker.identity_address)
+                    .await?;
+                database::cancel_settlement(conn, &self.chain_id, &staker.identity_address).await?;
+
+                self.publish(PoolEvent::NewStaker(staker.clone()));
+            }
+            _ => {}
+        }
+
+        Ok(staker)
+    }
+
+    /// Remembers when the VerusID of an active or cooling down staker unlocks, if it unlocks at
+    /// a fixed height, so the staker is checked again once the VerusID unlocks within the
+    /// `min_remaining_lock` of the vault conditions.
+    async fn track_unlock_height(
+        &self,
+        conn: &mut PgConnection,
+        staker: &Staker,
+        identity: &IdentityPrimary,
+        block_height: u64,
+    ) -> Result<()> {
+        let recheck = matches!(
+            staker.status,
+            StakerStatus::Active | StakerStatus::CoolingDown
+        ) && identity.flags == 1
+            && self
+                .config
+                .vault_conditions
+                .as_ref()
+                .is_some_and(|conditions| {
+                    identity.timelock >= block_height + conditions.min_remaining_lock as u64
+                });
+
+        if recheck {
+            database::store_unlock_height(
+                conn,
+                &self.chain_id,
+                &staker.identity_address,
+                identity.timelock,
+            )
+            .await?;
+        } else {
+            database::remove_unlock_height(conn, &self.chain_id, &staker.identity_address).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Forecasts how the work of a staker changes once the UTXOs that don't have the
+    /// `utxo_eligibility_confirmations` yet become eligible to stake.
+    async fn forecast_work(&self, client: &VerusClient, staker: &Staker) -> Result<WorkForecast> {
+        let height = client.get_blockchain_info()?.blocks;
+
+        let mut addresses = vec![staker.identity_address.clone()];
+        let delegators = self
+            .get_delegators(&mut *self.pool.acquire().await?, &addresses)
+            .await?;
+        addresses.extend(delegators.into_keys());
+
+        let eligibility_confirmations = self.config.utxo_eligibility_confirmations as u64;
+        let mut eligible = Amount::ZERO;
+        let mut pending_deposits = vec![];
+
+        for utxo in client.list_unspent(Some(0), None, Some(addresses.as_ref()))? {
+            let (Some(address), Ok(amount)) = (utxo.address, utxo.amount.to_unsigned()) else {
+                continue;
+            };
+
+            if utxo.confirmations as u64 >= eligibility_confirmations {
+                eligible += amount;
+            } else {
+                pending_deposits.push(pending_deposit(
+                    address,
+                    utxo.txid,
+                    utxo.vout,
+                    amount,
+                    utxo.confirmations as u64,
+                    height,
+                    eligibility_confirmations,
+                ));
+            }
+        }
+
+        let pool_eligible = client.get_wallet_info()?.eligible_staking_balance;
+
+        let workers = database::get_workers_by_round(&self.pool, &self.chain_id, 0).await?;
+        let round_total = workers.iter().map(|worker| worker.shares).sum::<Decimal>();
+        let round_shares = workers
+            .iter()
+            .find(|worker| worker.identity_address == staker.identity_address)
+            .map(|worker| worker.shares)
+            .unwrap_or(Decimal::ZERO);
+
+        Ok(forecast_work(
+            height,
+            pending_deposits,
+            eligible,
+            pool_eligible,
+            round_shares,
+            round_total,
+        ))
+    }
+
+    /// Lists the UTXOs of a staker and of the addresses that delegate their staking to it, with
+    /// whether they count towards its work. Returns `None` if the VerusID is not a staker.
+    async fn utxo_breakdown(
+        &self,
+        client: &VerusClient,
+        identity_address: &Address,
+    ) -> Result<Option<UtxoBreakdown>> {
+        let mut conn = self.pool.acquire().await?;
+        let Some(staker) =
+            database::get_staker(&mut conn, &self.chain_id, identity_address).await?
+        else {
+            return Ok(None);
+        };
+
+        let height = client.get_blockchain_info()?.blocks;
+
+        let mut addresses = vec![staker.identity_address.clone()];
+        let delegators = self.get_delegators(&mut conn, &addresses).await?;
+        addresses.extend(delegators.into_keys());
+
+        let utxos = client
+            .list_unspent(Some(0), None, Some(addresses.as_ref()))?
+            .into_iter()
+            .filter_map(|utxo| {
+                let (Some(address), Ok(amount)) = (utxo.address, utxo.amount.to_unsigned()) else {
+                    return None;
+                };
+
+                Some(StakerUtxo {
+                    address,
+                    txid: utxo.txid,
+                    vout: utxo.vout,
+                    amount,
+                    confirmations: utxo.confirmations as u64,
+                    eligible: false,
+                })
+            })
+            .collect();
+
+        Ok(Some(utxo_breakdown(
+            &staker,
+            height,
+            self.config.utxo_eligibility_confirmations as u64,
+            utxos,
+        )))
+    }
+}
+
+/// Lists the UTXOs of stakers that count towards their work, with the UTXOs of delegated
+/// addresses counted for the VerusID they are delegated to.
+///
+/// Only the UTXOs that the pool wallet lists count, as those are the only ones the daemon can
+/// stake. This includes the UTXOs of cold staking addresses, which are listed together with the
+/// VerusIDs and delegated addresses.
+fn eligible_utxos(
+    client: &VerusClient,
+    addresses: &[Address],
+    delegators: &HashMap<Address, Address>,
+    cold_staking_addresses: &[Address],
+    eligibility_confirmations: u64,
+) -> Result<Vec<EligibleUtxo>> {
+    let tip_height = client.get_blockchain_info()?.blocks;
+    let addresses = addresses
+        .iter()
+        .chain(cold_staking_addresses)
+        .cloned()
+        .collect::<Vec<_>>();
+    let unspent = if addresses.is_empty() {
+        vec![]
+    } else {
+        client.list_unspent(
+            Some(eligibility_confirmations as usize),
+            None,
+            Some(addresses.as_ref()),
+        )?
+    };
+
+    Ok(credit_unspent(
+        unspent
+            .into_iter()
+            .filter(|lu| lu.amount.is_positive())
+            .map(|lu| {
+                (
+                    lu.address.unwrap(),
+                    lu.amount.to_unsigned().unwrap(),
+                    lu.confirmations as u64,
+                )
+            }),
+        delegators,
+        tip_height,
+        eligibility_confirmations,
+    ))
+}
+
+/// Credits every listed UTXO, an address with its amount and confirmations, to the staker it
+/// counts for: a delegated address counts for the VerusID it is delegated to, any other address
+/// for itself.
+fn credit_unspent(
+    unspent: impl IntoIterator<Item = (Address, Amount, u64)>,
+    delegators: &HashMap<Address, Address>,
+    tip_height: u64,
+    eligibility_confirmations: u64,
+) -> Vec<EligibleUtxo> {
+    unspent
+        .into_iter()
+        .map(|(address, amount, confirmations)| {
+            EligibleUtxo::unspent(
+                delegators.get(&address).cloned().unwrap_or(address),
+                amount,
+                confirmations,
+                tip_height,
+                eligibility_confirmations,
+            )
+        })
+        .collect()
 }
 
 #[cfg(not(feature = "mock"))]
 #[async_trait]
 impl IntoSubsystem<anyhow::Error> for CoinStaker {
     async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
-        info!("starting coinstaker {}", self.config.currency_name);
-        let client = self.verusd()?;
+        info!(
+            features = ?self.config.features.enabled(),
+            "starting coinstaker {}", self.config.currency_name
+        );
 
         tokio::spawn(super::zmq::tmq_block_listen(
+            "127.0.0.1".to_string(),
             self.config.chain_config.zmq_port_blocknotify,
             self.tx.clone(),
+            CoinStakerMessage::Block,
         ));
+        // the daemon in use may not have the block of another daemon yet, so their
+        // notifications let the gap check pick up the blocks that the daemon in use has
+        for node in self
+            .config
+            .chain_config
+            .quorum
+            .iter()
+            .flat_map(|quorum| &quorum.nodes)
+        {
+            if let Some(port) = node.zmq_port_blocknotify {
+                tokio::spawn(super::zmq::tmq_block_listen(
+                    node.rpc_host.clone(),
+                    port,
+                    self.tx.clone(),
+                    |_| CoinStakerMessage::CheckForGaps,
+                ));
+            }
+        }
+        tokio::spawn(super::zmq::gap_check(self.tx.clone()));
 
-        if !self.config.skip_preflight {
-            // some preflight checks are needed:
-            if let Some(mut last_height) =
-                database::get_last_height(&self.pool, &self.chain_id).await?
-            {
-                trace!(%last_height, "Do some preflight checks");
-
-                let chain_tip = client.get_blockchain_info()?.blocks;
-
-                for i in last_height..=chain_tip {
-                    let block = client.get_block_by_height(i, 2)?;
+        if let Err(e) = self.load_key_rotations().await {
+            error!(error = ?e, "failed to load the rotations of the pool primary address");
+        }
 
-                    self.check_stakers(&client, &block).await?;
-                    last_height += 1;
+        match self.run_startup_audit(&self.verusd()?).await {
+            Ok(report) => {
+                for finding in &report.findings {
+                    warn!(
+                        category = ?finding.category,
+                        repaired = finding.repaired,
+                        suggestion = finding.suggestion,
+                        "startup audit: {}",
+                        finding.description
+                    );
                 }
+                info!(n_findings = report.findings.len(), "finished startup audit");
 
-                self.check_maturing_stakes(&client).await?;
-
-                trace!(%last_height, "Finished doing preflight checks");
-
-                database::update_last_height(&self.pool, &self.chain_id, last_height).await?;
+                self.startup_audit = Some(report);
             }
+            Err(e) => error!(error = ?e, "failed to run startup audit"),
         }
 
         select! {
             _ = subsys.on_shutdown_requested() => {
                 info!("shutting down coinstaker, disable staking");
 
-                disable_staking(self.verusd()?)?;
+                disable_staking(&self.verusd()?)?;
             },
             r = self.listen() => {
                 warn!("stopped listening");
@@ -750,8 +4057,15 @@ impl IntoSubsystem<anyhow::Error> for CoinStaker {
 #[derive(Debug)]
 pub enum CoinStakerMessage {
     Block(BlockHash),
+    /// Recovers the blocks of which the notification was missed.
+    CheckForGaps,
+    /// Stops adding work and making payouts until `Resume`. The blocks that arrive in the
+    /// meantime are recorded, and processed on `Resume`.
+    Pause(oneshot::Sender<ChainPause>),
+    Resume(oneshot::Sender<ChainPause>),
     StakingSupply(oneshot::Sender<StakingSupply>, Vec<Address>),
     StakerStatus(oneshot::Sender<Option<Staker>>, Address),
+    DelegateStaking(oneshot::Sender<Result<Staker>>, Address, Address, String),
     GetStakers(
         oneshot::Sender<Vec<Staker>>,
         Vec<Address>,
@@ -762,9 +4076,356 @@ pub enum CoinStakerMessage {
         Vec<Address>,
     ),
     GetStakingBalance(oneshot::Sender<HashMap<Address, Amount>>, Vec<Address>),
+    GetStakerActivity(
+        oneshot::Sender<Vec<StakerActivity>>,
+        Address,
+        Option<u64>,
+        u64,
+    ),
+    GetStakerStatements(oneshot::Sender<Vec<StakerStatement>>, Address),
+    GetEarningsTimeseries(
+        oneshot::Sender<Vec<EarningsPeriod>>,
+        Address,
+        EarningsGranularity,
+    ),
+    GetStaleStakes(oneshot::Sender<Vec<StaleStake>>, Address),
+    GetUtxoBreakdown(oneshot::Sender<Option<UtxoBreakdown>>, Address),
     GetPayouts(oneshot::Sender<Vec<PayoutMember>>, Vec<Address>),
+    GetLiabilities(oneshot::Sender<Liabilities>),
     GetStakes(oneshot::Sender<Vec<Stake>>, Option<StakeStatus>),
     GetStatistics(oneshot::Sender<Stats>),
+    GetNetworkStats(oneshot::Sender<Vec<NetworkStats>>, Option<u64>, u64),
+    GetRewardOutlook(oneshot::Sender<RewardOutlook>),
+    GetRotationProgress(oneshot::Sender<Vec<RotationProgress>>),
+    GetKeyRotation(oneshot::Sender<KeyRotationStatus>),
+    /// Starts a rotation of the pool primary address: the new address, the end of the
+    /// transition window and the operator.
+    StartKeyRotation(oneshot::Sender<Result<KeyRotation>>, Address, u64, String),
+    /// Retires the old pool primary address after the transition window: the operator.
+    RetireKeyRotation(oneshot::Sender<Result<KeyRotation>>, String),
+    GetStartupAudit(oneshot::Sender<Option<AuditReport>>),
+    CheckEligibility(oneshot::Sender<Option<Eligibility>>, String),
+    GetFraudEvidence(oneshot::Sender<Vec<FraudEvidence>>),
+    GetConfig(oneshot::Sender<CoinstakerConfig>),
+    GetExplorer(oneshot::Sender<Option<Explorer>>),
+    GetHealth(oneshot::Sender<ChainHealth>),
+    GetLiveness(oneshot::Sender<ChainLiveness>),
+    MergeRounds(oneshot::Sender<Result<RoundMerge>>, u64, u64, bool),
+    InsertHistoricalStake(oneshot::Sender<Result<HistoricalStake>>, BlockHash, bool),
+    RecalculatePayout(
+        oneshot::Sender<Result<PayoutRecalculation>>,
+        BlockHash,
+        bool,
+    ),
+    SetFeeOverride(
+        oneshot::Sender<Result<FeeOverride>>,
+        Address,
+        Option<Decimal>,
+        Option<String>,
+    ),
+    /// Registers a plain address as a cold staker: the address, its payout address and the
+    /// signature of the registration message.
+    RegisterColdStaker(oneshot::Sender<Result<Staker>>, Address, Address, String),
+    PayStaker(
+        oneshot::Sender<Result<ManualPayment>>,
+        Address,
+        String,
+        String,
+    ),
+    /// Bans a staker on request of an operator: the staker, the operator and the reason.
+    BanStaker(oneshot::Sender<Result<StakerBan>>, Address, String, String),
+    /// Starts a payout run right away: payouts for the matured stakes, then their payments.
+    ProcessPayments(oneshot::Sender<()>),
+    VerifyMessage(oneshot::Sender<bool>, Address, String, String),
+    CreateApiKey(oneshot::Sender<ApiKey>, Address, String, String),
+    GetApiKeys(oneshot::Sender<Vec<ApiKey>>, Address),
+    RevokeApiKey(oneshot::Sender<bool>, Address, u64),
+    UseApiKey(oneshot::Sender<Option<Address>>, String),
+    GetMinPayoutBounds(oneshot::Sender<MinPayoutBounds>),
+    SetMinPayout(oneshot::Sender<Result<()>>, Address, Amount),
+    SetPayoutCurrency(oneshot::Sender<Result<()>>, Address, Option<Address>),
+    SetStakerWebhook(oneshot::Sender<Result<()>>, Address, Option<StakerWebhook>),
     PoolPrimaryAddress(oneshot::Sender<String>),
     SetStaking(bool),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+    use sqlx::Row;
+
+    use super::*;
+
+    const CURRENCY: &str = "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq";
+    const ALICE: &str = "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU";
+    const POOL_PRIMARY_ADDRESS: &str = "RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2";
+
+    fn coin_staker(pool: PgPool, policy: InactiveWorkPolicy) -> CoinStaker {
+        let mut config: CoinstakerConfig = serde_json::from_value(json!({
+            "currency_name": "VRSCTEST",
+            "currency_id": CURRENCY,
+            "pool_address": "iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi",
+            "pool_primary_address": POOL_PRIMARY_ADDRESS,
+            "fee": "5",
+            "min_payout": 100_000_000,
+            "tx_fee": 10_000,
+            "webhook_endpoints": [],
+            "settlement_grace_period_in_hours": 24,
+            "chain_config": {
+                "rpc_user": "user",
+                "rpc_password": "password",
+                "rpc_host": "127.0.0.1",
+                "rpc_port": 27486,
+                "zmq_port_blocknotify": 59790
+            },
+            "payout_config": {
+                "check_interval_in_secs": 60,
+                "send_interval_in_secs": 60
+            }
+        }))
+        .unwrap();
+        config.inactive_work_policy = policy;
+
+        let (tx, rx) = mpsc::channel(1);
+        CoinStaker::new(pool, config, tx, rx, EventBus::new()).unwrap()
+    }
+
+    /// The VerusID of alice, which is eligible as long as it includes the pool primary address.
+    fn alice(primary_addresses: &[&str]) -> IdentityPrimary {
+        serde_json::from_value(json!({
+            "version": 3,
+            "flags": 0,
+            "primaryaddresses": primary_addresses,
+            "minimumsignatures": 1,
+            "name": "alice",
+            "identityaddress": ALICE,
+            "parent": CURRENCY,
+            "systemid": CURRENCY,
+            "contentmap": {},
+            "revocationauthority": ALICE,
+            "recoveryauthority": ALICE,
+            "timelock": 0
+        }))
+        .unwrap()
+    }
+
+    fn eligible() -> IdentityPrimary {
+        alice(&["RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG", POOL_PRIMARY_ADDRESS])
+    }
+
+    fn ineligible() -> IdentityPrimary {
+        alice(&[
+            "RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG",
+            "RLXCv2dQPB4NPqKUz4HD7mtLqCi7oxtZQn",
+        ])
+    }
+
+    /// Stores alice as an active staker with 50 shares in round 0, after which the VerusID of
+    /// alice drops the pool primary address in block 11.
+    async fn leave(coin_staker: &CoinStaker, conn: &mut PgConnection) -> Staker {
+        let currency_address = Address::from_str(CURRENCY).unwrap();
+        let alice = Address::from_str(ALICE).unwrap();
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        database::store_staker(conn, &staker).await.unwrap();
+        database::store_work(
+            &coin_staker.pool,
+            &currency_address,
+            HashMap::from([(alice, Decimal::from(50))]),
+            10,
+        )
+        .await
+        .unwrap();
+
+        let staker = coin_staker
+            .update_staker_status(conn, &ineligible(), "alice@", 11)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(staker.status, StakerStatus::Inactive);
+
+        staker
+    }
+
+    async fn open_shares(pool: &PgPool) -> Decimal {
+        sqlx::query("SELECT COALESCE(SUM(shares), 0) AS shares FROM open_work")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get("shares")
+    }
+
+    async fn forfeited_shares(pool: &PgPool) -> Decimal {
+        database::get_forfeited_shares_by_round(pool, &Address::from_str(CURRENCY).unwrap(), 0)
+            .await
+            .unwrap()
+    }
+
+    /// The seconds from now until the settlement of alice is due.
+    async fn settles_in(pool: &PgPool) -> i64 {
+        sqlx::query(
+            "SELECT EXTRACT(EPOCH FROM settle_at - NOW())::bigint AS settles_in
+            FROM staker_settlements",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap()
+        .get("settles_in")
+    }
+
+    #[test]
+    fn vaults_delegators_and_cold_stakers_are_credited_from_one_listing() {
+        let vault = Address::from_str(ALICE).unwrap();
+        let delegated_identity = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let delegated = Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap();
+        let cold_staker = Address::from_str("RLXCv2dQPB4NPqKUz4HD7mtLqCi7oxtZQn").unwrap();
+        let delegators = HashMap::from([(delegated.clone(), delegated_identity.clone())]);
+
+        // the UTXOs of the pool wallet of the VerusID, the delegated address and the cold
+        // staking address, as the one listunspent of eligible_utxos returns them
+        let utxos = credit_unspent(
+            [
+                (vault.clone(), Amount::from_sat(100), 200),
+                (delegated, Amount::from_sat(50), 200),
+                (cold_staker.clone(), Amount::from_sat(70), 160),
+            ],
+            &delegators,
+            1_000,
+            150,
+        );
+
+        assert_eq!(
+            utxos,
+            vec![
+                EligibleUtxo::unspent(vault, Amount::from_sat(100), 200, 1_000, 150),
+                EligibleUtxo::unspent(delegated_identity, Amount::from_sat(50), 200, 1_000, 150),
+                EligibleUtxo::unspent(cold_staker.clone(), Amount::from_sat(70), 160, 1_000, 150),
+            ]
+        );
+
+        let balances = cold_staking::eligible_balances(&utxos, 1_000);
+        assert_eq!(balances.len(), 3);
+        assert_eq!(balances[&cold_staker], Amount::from_sat(70));
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn keep_pays_the_work_after_the_grace_period(pool: PgPool) {
+        let coin_staker = coin_staker(pool.clone(), InactiveWorkPolicy::Keep);
+        let mut conn = pool.acquire().await.unwrap();
+
+        leave(&coin_staker, &mut conn).await;
+        coin_staker
+            .forfeit_departed_work(&mut conn, false)
+            .await
+            .unwrap();
+
+        assert_eq!(open_shares(&pool).await, Decimal::from(50));
+        assert_eq!(forfeited_shares(&pool).await, Decimal::ZERO);
+        assert!(settles_in(&pool).await > 23 * 3600);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn pay_on_departure_settles_right_away(pool: PgPool) {
+        let coin_staker = coin_staker(pool.clone(), InactiveWorkPolicy::PayOnDeparture);
+        let mut conn = pool.acquire().await.unwrap();
+
+        leave(&coin_staker, &mut conn).await;
+        coin_staker
+            .forfeit_departed_work(&mut conn, false)
+            .await
+            .unwrap();
+
+        assert_eq!(open_shares(&pool).await, Decimal::from(50));
+        assert_eq!(forfeited_shares(&pool).await, Decimal::ZERO);
+        assert!(settles_in(&pool).await <= 0);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn hold_forfeits_the_work_after_the_rounds(pool: PgPool) {
+        let coin_staker = coin_staker(pool.clone(), InactiveWorkPolicy::Hold { rounds: 2 });
+        let mut conn = pool.acquire().await.unwrap();
+
+        leave(&coin_staker, &mut conn).await;
+        coin_staker
+            .forfeit_departed_work(&mut conn, false)
+            .await
+            .unwrap();
+
+        // the work is out of the open round, so the stakes of the hold don't pay for it
+        assert_eq!(open_shares(&pool).await, Decimal::ZERO);
+        assert_eq!(forfeited_shares(&pool).await, Decimal::ZERO);
+
+        // blocks without a stake don't count
+        coin_staker
+            .forfeit_departed_work(&mut conn, false)
+            .await
+            .unwrap();
+        coin_staker
+            .forfeit_departed_work(&mut conn, true)
+            .await
+            .unwrap();
+        assert_eq!(forfeited_shares(&pool).await, Decimal::ZERO);
+
+        coin_staker
+            .forfeit_departed_work(&mut conn, true)
+            .await
+            .unwrap();
+        assert_eq!(forfeited_shares(&pool).await, Decimal::from(50));
+        assert_eq!(open_shares(&pool).await, Decimal::ZERO);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn hold_gives_the_work_back_to_a_returning_staker(pool: PgPool) {
+        let coin_staker = coin_staker(pool.clone(), InactiveWorkPolicy::Hold { rounds: 2 });
+        let mut conn = pool.acquire().await.unwrap();
+
+        leave(&coin_staker, &mut conn).await;
+        coin_staker
+            .forfeit_departed_work(&mut conn, true)
+            .await
+            .unwrap();
+        assert_eq!(open_shares(&pool).await, Decimal::ZERO);
+
+        let staker = coin_staker
+            .update_staker_status(&mut conn, &eligible(), "alice@", 12)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(staker.status, StakerStatus::CoolingDown);
+
+        coin_staker
+            .forfeit_departed_work(&mut conn, true)
+            .await
+            .unwrap();
+        coin_staker
+            .forfeit_departed_work(&mut conn, true)
+            .await
+            .unwrap();
+
+        assert_eq!(open_shares(&pool).await, Decimal::from(50));
+        assert_eq!(forfeited_shares(&pool).await, Decimal::ZERO);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn forfeit_forfeits_the_work_in_the_block_of_departure(pool: PgPool) {
+        let coin_staker = coin_staker(pool.clone(), InactiveWorkPolicy::Forfeit);
+        let mut conn = pool.acquire().await.unwrap();
+
+        leave(&coin_staker, &mut conn).await;
+        coin_staker
+            .forfeit_departed_work(&mut conn, false)
+            .await
+            .unwrap();
+
+        assert_eq!(open_shares(&pool).await, Decimal::ZERO);
+        assert_eq!(forfeited_shares(&pool).await, Decimal::from(50));
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use vrsc_rpc::json::vrsc::{Address, Amount};
+
+use super::accrual::EligibleUtxo;
+
+/// The message that a cold staker signs with `address` to let the pool stake its funds, with
+/// the rewards paid to `payout_address`.
+pub(super) fn registration_message(
+    currency_address: &Address,
+    address: &Address,
+    payout_address: &Address,
+) -> String {
+    format!("cold stake {address} on {currency_address}, pay rewards to {payout_address}")
+}
+
+/// Adds up the balance per address that is eligible to stake at `height`.
+pub(super) fn eligible_balances(utxos: &[EligibleUtxo], height: u64) -> HashMap<Address, Amount> {
+    utxos
+        .iter()
+        .filter(|utxo| utxo.is_eligible_at(height))
+        .fold(HashMap::new(), |mut acc, utxo| {
+            *acc.entry(utxo.identity_address.clone())
+                .or_insert(Amount::ZERO) += utxo.amount;
+
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn address() -> Address {
+        Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap()
+    }
+
+    #[test]
+    fn utxos_become_eligible_after_the_confirmations() {
+        let utxos = vec![
+            EligibleUtxo::unspent(address(), Amount::from_sat(100), 160, 1_159, 150),
+            EligibleUtxo::unspent(address(), Amount::from_sat(50), 60, 1_159, 150),
+        ];
+
+        assert_eq!(eligible_balances(&utxos, 1_148).get(&address()), None);
+        assert_eq!(
+            eligible_balances(&utxos, 1_149)[&address()],
+            Amount::from_sat(100)
+        );
+        assert_eq!(
+            eligible_balances(&utxos, 1_249)[&address()],
+            Amount::from_sat(150)
+        );
+    }
+}
@@ -1,8 +1,13 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use rust_decimal::Decimal;
-use serde::{Deserialize, Serialize};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize, Serializer};
 use tracing::debug;
 use url::Url;
 use vrsc_rpc::{
@@ -10,7 +15,9 @@ use vrsc_rpc::{
     json::vrsc::{util::amount::serde::as_sat, Address, Amount},
 };
 
-#[derive(Debug, Deserialize, Clone)]
+use crate::util::reward::RewardSchedule;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub currency_name: String,
     pub currency_id: Address,
@@ -19,23 +26,296 @@ pub struct Config {
     pub fee: Decimal,                  // basis points
     #[serde(with = "as_sat")]
     pub min_payout: Amount,
+    /// The lowest min_payout that a staker can set. Defaults to the `min_payout`.
+    #[serde(default, with = "as_sat::opt")]
+    pub min_payout_floor: Option<Amount>,
+    /// The highest min_payout that a staker can set. Unbounded if not set.
+    #[serde(default, with = "as_sat::opt")]
+    pub min_payout_ceiling: Option<Amount>,
     #[serde(with = "as_sat")]
     pub tx_fee: Amount,
+    /// Other pool primary addresses that a VerusID can include instead of the
+    /// `pool_primary_address`, such as a cold address or the address of an earlier rotation. New
+    /// stakers are never given one of these.
+    #[serde(default)]
+    pub accepted_primary_addresses: Vec<Address>,
     pub vault_conditions: Option<VaultConditions>,
     pub webhook_endpoints: Vec<Url>,
+    #[serde(default)]
+    pub webhook_limits: WebhookLimits,
+    /// The secrets with which the messages to a webhook endpoint are signed, by endpoint.
+    /// Messages to an endpoint without a secret are not signed.
+    #[serde(default, serialize_with = "redact_values")]
+    pub webhook_secrets: HashMap<Url, Secret<String>>,
     pub chain_config: ChainConfig,
     pub payout_config: PayoutConfig,
     #[serde(default)]
     pub skip_preflight: bool,
+    /// Records the network staking supply, difficulty and PoS ratio for every block.
+    #[serde(default)]
+    pub collect_network_stats: bool,
+    #[serde(default)]
+    pub inactive_work_policy: InactiveWorkPolicy,
+    /// How long an inactive staker keeps its unpaid rewards below its min_payout, in case it
+    /// becomes active again. After that, they are paid in a final payout regardless of the
+    /// min_payout, and the staker is closed. Settled at the next payout run if not set.
+    #[serde(default)]
+    pub settlement_grace_period_in_hours: u64,
+    pub primary_address_rotation: Option<PrimaryAddressRotation>,
+    /// The daemon of this chain can stake the funds of addresses that allow this pool to stake
+    /// them, so stakers can delegate their staking without moving funds into their VerusID.
+    #[serde(default)]
+    pub delegated_staking: bool,
+    pub cold_staking: Option<ColdStakingConfig>,
+    pub accounting_export: Option<AccountingExportConfig>,
+    pub status_page: Option<StatusPageConfig>,
+    /// Repairs the findings of the startup audit that are safe to repair automatically.
+    #[serde(default)]
+    pub startup_audit_repair: bool,
+    #[serde(default)]
+    pub features: Features,
+    pub utxo_sweep: Option<UtxoSweepConfig>,
+    pub fee_sweep: Option<FeeSweepConfig>,
+    /// The only addresses, next to the VerusIDs of stakers, to which the pool sends funds.
+    /// Addresses in the `destination_whitelist` table of the database are allowed too.
+    #[serde(default)]
+    pub destination_whitelist: Vec<Address>,
+    /// The block reward schedule of this chain. Defaults to the schedule of VRSC.
+    #[serde(default)]
+    pub reward_schedule: RewardSchedule,
+    /// Stakers that are still cooling down after this many days are expired, so they are no
+    /// longer checked with every block. Stakers never expire if not set.
+    pub staker_expiry_in_days: Option<u32>,
+    pub bootstrap: Option<BootstrapConfig>,
+    pub halt_detection: Option<HaltDetectionConfig>,
+    #[serde(default)]
+    pub staking_watch: StakingWatchConfig,
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
+    /// The number of confirmations after which a coinbase can be spent, so a stake has matured.
+    #[serde(default = "default_maturity_confirmations")]
+    pub maturity_confirmations: u32,
+    /// The number of confirmations a UTXO needs before it can stake, and before it counts
+    /// towards the work of a staker.
+    #[serde(default = "default_utxo_eligibility_confirmations")]
+    pub utxo_eligibility_confirmations: u32,
+    /// The number of blocks that were not processed, for example while the pool was down, of
+    /// which the work is reconstructed from the UTXOs of the stakers. After a longer gap only
+    /// the work of the current block is added.
+    #[serde(default = "default_max_missed_blocks")]
+    pub max_missed_blocks: u64,
+    /// The number of blocks the pool can be behind the daemon before `GET /health` reports the
+    /// chain as unhealthy.
+    #[serde(default = "default_health_max_blocks_behind")]
+    pub health_max_blocks_behind: u64,
+    /// Links the blocks and transactions in webhook messages and API responses to a block
+    /// explorer. `{kind}` is replaced by `block` or `tx`, and `{id}` by the block hash or txid:
+    /// `https://insight.verus.io/{kind}/{id}`.
+    pub explorer_url_template: Option<String>,
+}
+
+/// The smallest output that the daemon relays at the default min relay fee of 0.0001, in sats.
+/// Payments with smaller outputs can't be sent.
+pub const DUST_THRESHOLD_IN_SATS: u64 = 5460;
+
+impl Config {
+    pub fn min_payout_bounds(&self) -> MinPayoutBounds {
+        MinPayoutBounds {
+            floor: self
+                .min_payout_floor
+                .unwrap_or(self.min_payout)
+                .max(Amount::from_sat(DUST_THRESHOLD_IN_SATS)),
+            ceiling: self.min_payout_ceiling,
+        }
+    }
+
+    /// Checks the settings that are valid on their own, but not together.
+    fn validate(&self) -> Result<()> {
+        let bounds = self.min_payout_bounds();
+
+        if self
+            .min_payout_floor
+            .is_some_and(|floor| floor.as_sat() < DUST_THRESHOLD_IN_SATS)
+        {
+            bail!("min_payout_floor is below the dust threshold of {DUST_THRESHOLD_IN_SATS}");
+        }
+
+        if bounds.ceiling.is_some_and(|ceiling| ceiling < bounds.floor) {
+            bail!("min_payout_ceiling is below the min_payout_floor");
+        }
+
+        bounds.check(self.min_payout).context("invalid min_payout")
+    }
+}
+
+/// The range of min_payouts that a staker can set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinPayoutBounds {
+    pub floor: Amount,
+    pub ceiling: Option<Amount>,
+}
+
+impl MinPayoutBounds {
+    pub fn check(&self, min_payout: Amount) -> Result<()> {
+        if min_payout < self.floor {
+            bail!("the min_payout can't be lower than {}", self.floor.as_sat());
+        }
+
+        if let Some(ceiling) = self.ceiling.filter(|ceiling| min_payout > *ceiling) {
+            bail!("the min_payout can't be higher than {}", ceiling.as_sat());
+        }
+
+        Ok(())
+    }
+}
+
+fn default_maturity_confirmations() -> u32 {
+    100
+}
+
+fn default_utxo_eligibility_confirmations() -> u32 {
+    150
 }
 
-#[derive(Debug, Deserialize, Clone)]
+fn default_max_missed_blocks() -> u64 {
+    1440
+}
+
+fn default_health_max_blocks_behind() -> u64 {
+    5
+}
+
+/// Experimental behavior that can be enabled per chain, so it can be tried on a testnet first.
+///
+/// Features that are not listed are disabled. Unknown features are rejected when the
+/// configuration is loaded.
+///
+/// ```toml
+/// [features]
+/// pplns = true
+/// utxo_level_work = false
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Features(HashMap<Feature, bool>);
+
+impl Features {
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        self.0.get(&feature).copied().unwrap_or(false)
+    }
+
+    /// Returns the features that are enabled.
+    pub fn enabled(&self) -> Vec<Feature> {
+        self.0
+            .iter()
+            .filter_map(|(feature, enabled)| enabled.then_some(*feature))
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Work is accrued per UTXO instead of per staker.
+    UtxoLevelWork,
+    /// Allows the PPLNS payout scheme, in which rewards are shared over the last N shares
+    /// instead of the round of the stake.
+    Pplns,
+    /// Payouts are converted to the currency that a staker prefers.
+    AutoConvertPayouts,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChainConfig {
     pub rpc_user: String,
+    #[serde(serialize_with = "redact")]
     pub rpc_password: String,
     pub rpc_host: String,
     pub rpc_port: u16,
     pub zmq_port_blocknotify: u16,
+    /// Daemons of the same chain that the coinstaker uses while the daemon above is unreachable,
+    /// in order of preference.
+    ///
+    /// ```toml
+    /// [[chain_config.fallback_endpoints]]
+    /// rpc_user = "user"
+    /// rpc_password = "password"
+    /// rpc_host = "10.0.0.2"
+    /// rpc_port = 27486
+    /// ```
+    #[serde(default)]
+    pub fallback_endpoints: Vec<RpcEndpoint>,
+    /// Other daemons of the same chain that have to agree with the daemon in use before the work
+    /// or a stake of a block is recorded, so that a single forked daemon can't poison the work.
+    /// Block notifications are accepted from every daemon.
+    ///
+    /// ```toml
+    /// [chain_config.quorum]
+    /// min_agreeing = 2
+    ///
+    /// [[chain_config.quorum.nodes]]
+    /// rpc_user = "user"
+    /// rpc_password = "password"
+    /// rpc_host = "10.0.0.3"
+    /// rpc_port = 27486
+    /// zmq_port_blocknotify = 59790
+    /// ```
+    #[serde(default)]
+    pub quorum: Option<QuorumConfig>,
+}
+
+impl ChainConfig {
+    /// Returns the RPC endpoint of the daemon in this config, followed by the fallbacks.
+    pub fn endpoints(&self) -> Vec<RpcEndpoint> {
+        let mut endpoints = vec![RpcEndpoint {
+            rpc_user: self.rpc_user.clone(),
+            rpc_password: self.rpc_password.clone(),
+            rpc_host: self.rpc_host.clone(),
+            rpc_port: self.rpc_port,
+        }];
+        endpoints.extend(self.fallback_endpoints.iter().cloned());
+
+        endpoints
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuorumConfig {
+    /// The number of daemons, including the daemon in use, that have to agree.
+    pub min_agreeing: usize,
+    pub nodes: Vec<QuorumNode>,
+}
+
+/// A daemon of a quorum. Its wallet needs to watch the same addresses as the wallet of the pool,
+/// including the addresses of cold stakers.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuorumNode {
+    pub rpc_user: String,
+    #[serde(serialize_with = "redact")]
+    pub rpc_password: String,
+    pub rpc_host: String,
+    pub rpc_port: u16,
+    /// Listens for block notifications of this daemon, if set.
+    pub zmq_port_blocknotify: Option<u16>,
+}
+
+impl QuorumNode {
+    pub fn endpoint(&self) -> RpcEndpoint {
+        RpcEndpoint {
+            rpc_user: self.rpc_user.clone(),
+            rpc_password: self.rpc_password.clone(),
+            rpc_host: self.rpc_host.clone(),
+            rpc_port: self.rpc_port,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RpcEndpoint {
+    pub rpc_user: String,
+    #[serde(serialize_with = "redact")]
+    pub rpc_password: String,
+    pub rpc_host: String,
+    pub rpc_port: u16,
 }
 
 /// Sets the conditions a VerusID must adhere to before being accepted as a staker in this pool.
@@ -57,6 +337,9 @@ pub struct VaultConditions {
     ///
     /// A VerusID is unlocked when the min_time_lock is 0.
     pub min_time_lock: u32,
+    /// The number of blocks that a VerusID that unlocks at a fixed height must still be locked
+    /// for. A staker becomes inactive once its VerusID unlocks within this many blocks.
+    pub min_remaining_lock: u32,
     /// When set to true, the Revoke and Recover authority must be different
     /// from the main VerusID.
     pub strict_recovery_id: bool,
@@ -68,16 +351,661 @@ impl Default for VaultConditions {
     fn default() -> Self {
         Self {
             min_time_lock: 0,
+            min_remaining_lock: 1440,
             strict_recovery_id: false,
             max_primary_addresses: 64,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Limits the webhook deliveries per endpoint, so a burst of messages doesn't exhaust the
+/// outbound connections.
+///
+/// Messages that don't fit in the queue of an endpoint are stored in the webhook outbox in the
+/// database, and sent once the endpoint catches up.
+///
+/// ```toml
+/// [webhook_limits]
+/// max_concurrent = 4
+/// max_queued = 100
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebhookLimits {
+    /// The maximum number of requests to an endpoint that are in flight at the same time.
+    #[serde(default = "default_webhook_max_concurrent")]
+    pub max_concurrent: usize,
+    /// The maximum number of messages that wait for a request to an endpoint to finish.
+    #[serde(default = "default_webhook_max_queued")]
+    pub max_queued: usize,
+}
+
+impl Default for WebhookLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_webhook_max_concurrent(),
+            max_queued: default_webhook_max_queued(),
+        }
+    }
+}
+
+fn default_webhook_max_concurrent() -> usize {
+    4
+}
+
+fn default_webhook_max_queued() -> usize {
+    100
+}
+
+/// Determines what happens to the work in round 0 of a staker that becomes inactive.
+///
+/// Work in round 0 is only paid out when the pool finds its next stake, which can take a long
+/// time after the staker left the pool.
+///
+/// ```toml
+/// [inactive_work_policy]
+/// policy = "hold"
+/// rounds = 3
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum InactiveWorkPolicy {
+    /// The work stays in round 0 and is paid out with the next stake of the pool. The unpaid
+    /// rewards are settled after the `settlement_grace_period_in_hours`.
+    #[default]
+    Keep,
+    /// Like `Keep`, but without a grace period: the unpaid rewards, and the reward for the work
+    /// in round 0 once the next stake is paid, are paid out at the next payout run regardless of
+    /// the min_payout of the staker.
+    PayOnDeparture,
+    /// The work is taken out of round 0 until the pool found the given number of stakes. A
+    /// staker that becomes active again in that time gets its work back, otherwise the work is
+    /// forfeited to the pool.
+    Hold { rounds: u64 },
+    /// The work is forfeited to the pool the moment the staker becomes inactive.
+    Forfeit,
+}
+
+/// Rotates the pool primary address to a new address.
+///
+/// Until `ends_at`, VerusIDs that include either the current `pool_primary_address` or the
+/// `new_address` are eligible. Stakers are notified once to update their VerusID.
+/// After `ends_at`, only the `new_address` is accepted and stakers that did not update their
+/// VerusID become inactive.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrimaryAddressRotation {
+    pub new_address: Address,
+    /// Unix timestamp (in seconds) at which the transition window ends.
+    pub ends_at: u64,
+}
+
+impl PrimaryAddressRotation {
+    pub fn has_ended(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        now >= self.ends_at
+    }
+}
+
+/// Pushes the stake income and confirmed payments of this pool to a bookkeeping API, as a JSON
+/// POST request per entry.
+///
+/// Every entry has the fields `kind` ("stake_income" or "payment"), `currency_address`,
+/// `reference` (block hash or txid), `block_height`, `amount` and `fee` (in sats) and
+/// `timestamp`. The `mapping` renames these fields to the fields the API expects; fields that
+/// are not mapped are left out. Without a mapping, entries are sent as is.
+///
+/// ```toml
+/// [accounting_export]
+/// endpoint = "https://books.example.com/api/entries"
+///
+/// [accounting_export.headers]
+/// Authorization = "Bearer 123"
+///
+/// [accounting_export.mapping]
+/// date = "timestamp"
+/// description = "kind"
+/// value = "amount"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountingExportConfig {
+    pub endpoint: Url,
+    #[serde(default = "default_accounting_export_interval_in_secs")]
+    pub interval_in_secs: u64,
+    #[serde(default, serialize_with = "redact_values")]
+    pub headers: HashMap<String, Secret<String>>,
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
+}
+
+fn default_accounting_export_interval_in_secs() -> u64 {
+    300
+}
+
+/// Pushes the health of this chain to a status page, so stakers see outages without asking.
+///
+/// The health is one of "up", "degraded" or "down". A chain is down when the daemon or the
+/// database can't be reached, and degraded when the daemon is not staking or the pool is more
+/// than `max_blocks_behind` blocks behind the chain tip.
+///
+/// With the `generic` format, the health is POSTed as JSON with the fields `currency_address`,
+/// `currency_name`, `status`, `reason`, `chain_tip`, `last_block`, `last_payout` and
+/// `timestamp`. The `statuspage` and `instatus` formats update the status of the component in
+/// the `endpoint` URL.
+///
+/// ```toml
+/// [status_page]
+/// endpoint = "https://api.statuspage.io/v1/pages/abc/components/def"
+/// format = "statuspage"
+///
+/// [status_page.headers]
+/// Authorization = "OAuth 123"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusPageConfig {
+    pub endpoint: Url,
+    #[serde(default)]
+    pub format: StatusPageFormat,
+    #[serde(default = "default_status_page_interval_in_secs")]
+    pub interval_in_secs: u64,
+    #[serde(default = "default_status_page_max_blocks_behind")]
+    pub max_blocks_behind: u64,
+    #[serde(default, serialize_with = "redact_values")]
+    pub headers: HashMap<String, Secret<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusPageFormat {
+    /// The health of the chain as JSON, POSTed to the endpoint.
+    #[default]
+    Generic,
+    /// A component update for Atlassian Statuspage.
+    Statuspage,
+    /// A component update for Instatus.
+    Instatus,
+}
+
+fn default_status_page_interval_in_secs() -> u64 {
+    60
+}
+
+pub(crate) fn default_status_page_max_blocks_behind() -> u64 {
+    5
+}
+
+/// A chain bootstrap to recover the daemon from a corrupted chainstate, see `--bootstrap`.
+///
+/// The archive is only unpacked into `data_dir` if its SHA-256 checksum matches `sha256`. The
+/// `blocks` and `chainstate` directories that it replaces are kept next to it.
+///
+/// ```toml
+/// [bootstrap]
+/// url = "https://bootstrap.verus.io/VRSC-bootstrap.tar.gz"
+/// sha256 = "5f0c6a1e2b7d4c8f9a3e1d2c4b6a8f0e1d3c5b7a9f2e4d6c8b0a1f3e5d7c9b2a"
+/// data_dir = "/home/verus/.komodo/VRSC"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BootstrapConfig {
+    pub url: Url,
+    pub sha256: String,
+    pub data_dir: PathBuf,
+}
+
+/// Consolidates small UTXOs of the pool address into a single output.
+///
+/// Fee and change outputs accumulate on the pool address over time, which fragments the wallet
+/// and slows down `listunspent`. Only UTXOs of the pool address are swept, so the funds of
+/// stakers are never touched. A sweep is skipped while payments are pending, and, if a window is
+/// set, outside of the given UTC hours.
+///
+/// ```toml
+/// [utxo_sweep]
+/// max_utxo_amount = 100000000 # in sats
+/// min_utxos = 50
+/// window_start_hour = 2
+/// window_end_hour = 5
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UtxoSweepConfig {
+    #[serde(default = "default_utxo_sweep_interval_in_secs")]
+    pub interval_in_secs: u64,
+    /// UTXOs with a value of at most this amount are swept.
+    #[serde(with = "as_sat")]
+    pub max_utxo_amount: Amount,
+    /// The minimum number of small UTXOs before a sweep is done.
+    #[serde(default = "default_utxo_sweep_min_utxos")]
+    pub min_utxos: usize,
+    /// The maximum number of UTXOs that are swept in one transaction, to limit its size.
+    #[serde(default = "default_utxo_sweep_max_inputs")]
+    pub max_inputs: usize,
+    pub window_start_hour: Option<u8>,
+    pub window_end_hour: Option<u8>,
+}
+
+impl UtxoSweepConfig {
+    /// Returns whether a sweep is allowed at the given unix timestamp.
+    ///
+    /// The window can wrap around midnight, for example from 22 to 4.
+    pub fn in_window(&self, timestamp: u64) -> bool {
+        let (Some(start), Some(end)) = (self.window_start_hour, self.window_end_hour) else {
+            return true;
+        };
+
+        let hour = ((timestamp / 3600) % 24) as u8;
+
+        if start <= end {
+            (start..end).contains(&hour)
+        } else {
+            hour >= start || hour < end
+        }
+    }
+}
+
+fn default_utxo_sweep_interval_in_secs() -> u64 {
+    3600
+}
+
+fn default_utxo_sweep_min_utxos() -> usize {
+    50
+}
+
+fn default_utxo_sweep_max_inputs() -> usize {
+    200
+}
+
+/// Disables staking and pauses payouts when the chain tip doesn't advance, to not stake on a
+/// dead branch after a network halt or when the daemon is isolated on a fork.
+///
+/// Only a daemon that is reachable can be halted. Staking (if it was enabled) and payouts resume
+/// once the tip moves again.
+///
+/// ```toml
+/// [halt_detection]
+/// max_stall_in_secs = 1800
+/// check_interval_in_secs = 60
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HaltDetectionConfig {
+    #[serde(default = "default_halt_max_stall_in_secs")]
+    pub max_stall_in_secs: u64,
+    #[serde(default = "default_halt_check_interval_in_secs")]
+    pub check_interval_in_secs: u64,
+}
+
+fn default_halt_max_stall_in_secs() -> u64 {
+    1800
+}
+
+fn default_halt_check_interval_in_secs() -> u64 {
+    60
+}
+
+/// Lets stakers without a VerusID take part with the funds of a plain address. A staker
+/// registers the address with a message that it signed, together with the address that its
+/// rewards are paid to.
+///
+/// Only the funds that the pool wallet can stake count, so the pool wallet must list the UTXOs
+/// of a registered address. An address is active while its balance with the
+/// `utxo_eligibility_confirmations` is at least `min_balance`.
+///
+/// ```toml
+/// [cold_staking]
+/// min_balance = 100000000000 # in sats
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ColdStakingConfig {
+    #[serde(with = "as_sat")]
+    pub min_balance: Amount,
+}
+
+/// Checks whether the daemon is staking, to notice a daemon that stopped staking, for example
+/// after a restart, without restarting the pool.
+///
+/// While the daemon is not staking, the checks back off up to `max_backoff_in_secs`.
+///
+/// ```toml
+/// [staking_watch]
+/// check_interval_in_secs = 60
+/// max_backoff_in_secs = 900
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StakingWatchConfig {
+    #[serde(default = "default_staking_watch_check_interval_in_secs")]
+    pub check_interval_in_secs: u64,
+    #[serde(default = "default_staking_watch_max_backoff_in_secs")]
+    pub max_backoff_in_secs: u64,
+}
+
+impl Default for StakingWatchConfig {
+    fn default() -> Self {
+        Self {
+            check_interval_in_secs: default_staking_watch_check_interval_in_secs(),
+            max_backoff_in_secs: default_staking_watch_max_backoff_in_secs(),
+        }
+    }
+}
+
+fn default_staking_watch_check_interval_in_secs() -> u64 {
+    60
+}
+
+fn default_staking_watch_max_backoff_in_secs() -> u64 {
+    900
+}
+
+/// Compares the transactions that the wallet sent with the payments that the pool recorded, to
+/// notice funds that left the pool address without a record and payments that never made it
+/// into the chain.
+///
+/// The last `wallet_transactions` transactions of the wallet are compared, and the payments of
+/// the last `lookback_in_days` days are looked up in the wallet.
+///
+/// ```toml
+/// [reconciliation]
+/// interval_in_secs = 86400
+/// wallet_transactions = 10000
+/// lookback_in_days = 30
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReconciliationConfig {
+    #[serde(default = "default_reconciliation_interval_in_secs")]
+    pub interval_in_secs: u64,
+    #[serde(default = "default_reconciliation_wallet_transactions")]
+    pub wallet_transactions: u32,
+    #[serde(default = "default_reconciliation_lookback_in_days")]
+    pub lookback_in_days: u32,
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            interval_in_secs: default_reconciliation_interval_in_secs(),
+            wallet_transactions: default_reconciliation_wallet_transactions(),
+            lookback_in_days: default_reconciliation_lookback_in_days(),
+        }
+    }
+}
+
+fn default_reconciliation_interval_in_secs() -> u64 {
+    86400
+}
+
+fn default_reconciliation_wallet_transactions() -> u32 {
+    10000
+}
+
+fn default_reconciliation_lookback_in_days() -> u32 {
+    30
+}
+
+/// Moves the fees that the pool earned to a cold wallet.
+///
+/// The fees of all payouts that were not swept yet are sent in one transaction, once they add up
+/// to `min_amount`. The destination must be whitelisted, either in `destination_whitelist` or in
+/// the `destination_whitelist` table, or the sweep is refused.
+///
+/// ```toml
+/// destination_whitelist = ["RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7"]
+///
+/// [fee_sweep]
+/// destination = "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7"
+/// min_amount = 1000000000 # in sats
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeeSweepConfig {
+    pub destination: Address,
+    #[serde(with = "as_sat")]
+    pub min_amount: Amount,
+    #[serde(default = "default_fee_sweep_interval_in_secs")]
+    pub interval_in_secs: u64,
+}
+
+fn default_fee_sweep_interval_in_secs() -> u64 {
+    86400
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PayoutConfig {
+    /// How often matured stakes are turned into payouts.
     pub check_interval_in_secs: u64,
+    /// How often the unpaid payouts are paid. An operator can start a payout run in between with
+    /// `POST /admin/payouts/run`.
+    #[serde(alias = "payout_interval_secs")]
     pub send_interval_in_secs: u64,
+    /// The number of confirmations after which a payment is considered final.
+    #[serde(default = "default_required_confirmations")]
+    pub required_confirmations: u64,
+    /// A payment that is still unconfirmed after this time is abandoned and paid again, once it
+    /// is no longer in the mempool.
+    #[serde(default = "default_unconfirmed_timeout_in_secs")]
+    pub unconfirmed_timeout_in_secs: u64,
+    #[serde(alias = "netting")]
+    pub alignment: Option<PayoutAlignment>,
+    pub batching: Option<PayoutBatching>,
+    pub conversion: Option<PayoutConversion>,
+    #[serde(default)]
+    pub scheme: PayoutScheme,
+    /// A candidate scheme that runs next to `scheme` without paying anything: its payouts are
+    /// stored in the `payout_shadow` table, to compare them with `GET /admin/payouts/shadow`
+    /// before switching schemes.
+    ///
+    /// ```toml
+    /// [payout_config.shadow_scheme.pplns]
+    /// window = 50000000000000
+    /// ```
+    pub shadow_scheme: Option<PayoutScheme>,
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    /// The payout members of a confirmed payment are rolled up into monthly totals per staker
+    /// once their month ended this many months ago. They are kept as they are if not set.
+    pub archive_after_months: Option<u32>,
+    /// The pool pays the `tx_fee` of every payment out of its fees, which is the default. If
+    /// false, it is deducted from the amounts sent to the stakers in the payment, in proportion
+    /// to their amounts.
+    #[serde(default = "default_pool_pays_tx_fee")]
+    pub pool_pays_tx_fee: bool,
+    pub dust_sweep: Option<DustSweepConfig>,
+}
+
+/// Pays the unpaid balances that stay below the min_payout of their staker once every
+/// `interval_in_days`, so that the balances of stakers that stopped staking don't stay in the
+/// pool forever. Balances below the dust threshold can't be sent and are left until they grow.
+///
+/// ```toml
+/// [payout_config.dust_sweep]
+/// interval_in_days = 30
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DustSweepConfig {
+    #[serde(default = "default_dust_sweep_interval_in_days")]
+    pub interval_in_days: u64,
+}
+
+impl DustSweepConfig {
+    /// Whether a dust sweep is due at `now`, after the last one at `last_swept_at` (both Unix
+    /// timestamps in seconds).
+    pub fn is_due(&self, last_swept_at: Option<u64>, now: u64) -> bool {
+        last_swept_at.map_or(true, |last_swept_at| {
+            now >= last_swept_at + self.interval_in_days * 86400
+        })
+    }
+}
+
+fn default_dust_sweep_interval_in_days() -> u64 {
+    30
+}
+
+fn default_pool_pays_tx_fee() -> bool {
+    true
+}
+
+/// How the reward of a stake is shared among the stakers.
+///
+/// With PPLNS (pay per last N shares), the reward is shared over the last `window` shares,
+/// across the rounds before the stake, so a staker doesn't depend on the luck of a single
+/// round. The oldest round in the window only counts in part. PPLNS requires the `pplns`
+/// feature.
+///
+/// ```toml
+/// [payout_config.scheme.pplns]
+/// window = 50000000000000
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutScheme {
+    /// The reward is shared by the work in the round of the stake.
+    #[default]
+    Proportional,
+    Pplns {
+        window: u64,
+    },
+}
+
+/// Lowers the fee of stakers with a large balance, and of every staker during a promotion.
+///
+/// A staker gets the fee of the highest tier of which the `min_balance` is at most the average
+/// balance that the staker staked during the blocks of the payout, if that fee is lower than the
+/// fee the staker signed up with. A fee override that an admin set for a staker replaces both.
+///
+/// Promotions subtract their `discount` from the fee of stakes at heights from `from_height` up to
+/// and including `until_height`, except from overridden fees. Promotions that overlap don't add
+/// up; the highest discount applies.
+///
+/// ```toml
+/// [[payout_config.fee_schedule.tiers]]
+/// min_balance = 1000000000000
+/// fee = "0.01"
+///
+/// [[payout_config.fee_schedule.promotions]]
+/// from_height = 3200000
+/// until_height = 3243200
+/// discount = "0.02"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FeeSchedule {
+    #[serde(default)]
+    pub tiers: Vec<FeeTier>,
+    #[serde(default)]
+    pub promotions: Vec<FeePromotion>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeeTier {
+    #[serde(with = "as_sat")]
+    pub min_balance: Amount,
+    pub fee: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeePromotion {
+    pub from_height: u64,
+    pub until_height: u64,
+    pub discount: Decimal,
+}
+
+/// Splits a payment run into payments to at most `max_size` stakers, and adapts the size to how
+/// the daemon handles them.
+///
+/// A payment that fails halves the size of the next payment. A payment of which the
+/// sendcurrency operation took longer than `max_latency_in_secs` shrinks it by a quarter, and
+/// any other payment grows it by half, up to `max_size`. Without batching, all stakers are paid
+/// in one payment.
+///
+/// Every payment is recorded in `payment_batches`, and the payout members it included in
+/// `payment_batch_members`, also when the payment failed. A fixed size is set with `min_size`
+/// equal to `max_size`.
+///
+/// ```toml
+/// [payout_config.batching]
+/// max_size = 500
+/// min_size = 10
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoutBatching {
+    pub max_size: usize,
+    #[serde(default = "default_batching_min_size")]
+    pub min_size: usize,
+    #[serde(default = "default_batching_max_latency_in_secs")]
+    pub max_latency_in_secs: u64,
+}
+
+fn default_batching_min_size() -> usize {
+    10
+}
+
+fn default_batching_max_latency_in_secs() -> u64 {
+    60
+}
+
+/// Lets stakers have their rewards converted into one of `currencies` when they are paid, for
+/// example to be paid in VRSC for staking on a PBaaS chain. The conversion goes through the
+/// fractional currency `via`, such as the bridge of the chain, unless `currencies` can be
+/// converted into directly.
+///
+/// Before a reward is converted, its conversion is estimated. If the rate of the reward is more
+/// than `max_slippage` worse than the rate of a small amount, the reward is paid in the staked
+/// currency instead.
+///
+/// ```toml
+/// [payout_config.conversion]
+/// currencies = ["i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV"]
+/// via = "iHog9UCTrn95qpUBFCZ7kKz7qWdMA8MQ6N"
+/// max_slippage = 0.02
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoutConversion {
+    pub currencies: Vec<Address>,
+    pub via: Option<Address>,
+    #[serde(default = "default_conversion_max_slippage")]
+    pub max_slippage: Decimal,
+}
+
+fn default_conversion_max_slippage() -> Decimal {
+    Decimal::new(2, 2)
+}
+
+/// Pays a VerusID on this chain whenever it is paid on a primary chain, so that a staker that
+/// stakes on multiple chains is paid on all of them at the same time.
+///
+/// Once a staker was paid on the primary chain, its unpaid rewards on this chain are paid with
+/// the next payment, even if they are below the min_payout of this chain. Each chain still pays
+/// in its own currency and the balances are not added up across chains, so this doesn't reduce
+/// the number of payments: it can add payments of balances that are below the min_payout of this
+/// chain, as long as they are above the dust threshold.
+///
+/// ```toml
+/// [payout_config.alignment]
+/// primary_chain = "i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV"
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PayoutAlignment {
+    pub primary_chain: Address,
+}
+
+fn default_required_confirmations() -> u64 {
+    10
+}
+
+fn default_unconfirmed_timeout_in_secs() -> u64 {
+    3600
+}
+
+/// Secrets are replaced by this value when a configuration is serialized, so it can be shown
+/// through the API.
+const REDACTED: &str = "<redacted>";
+
+fn redact<S: Serializer>(_: &String, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+fn redact_values<K: Serialize, S: Serializer>(
+    map: &HashMap<K, Secret<String>>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(map.keys().map(|key| (key, REDACTED)))
 }
 
 impl TryFrom<&ChainConfig> for VerusClient {
@@ -96,6 +1024,22 @@ impl TryFrom<&ChainConfig> for VerusClient {
     }
 }
 
+impl TryFrom<&RpcEndpoint> for VerusClient {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &RpcEndpoint) -> Result<VerusClient> {
+        VerusClient::rpc(vrsc_rpc::Auth::UserPass(
+            format!("{}:{}", value.rpc_host, value.rpc_port),
+            value.rpc_user.to_string(),
+            value.rpc_password.to_string(),
+        ))
+        .context(format!(
+            "Could not make Verus client for {}:{}",
+            value.rpc_host, value.rpc_port
+        ))
+    }
+}
+
 pub fn get_coin_configurations() -> Result<Vec<Config>> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let config_dir = base_path.join("coin_config");
@@ -111,6 +1055,9 @@ pub fn get_coin_configurations() -> Result<Vec<Config>> {
                         .add_source(config::File::from(config_dir.join(&path)))
                         .build()?
                         .try_deserialize::<Config>()?;
+                    settings
+                        .validate()
+                        .with_context(|| format!("invalid configuration in {}", path.display()))?;
 
                     coin_settings.push(settings);
                 }
@@ -123,3 +1070,91 @@ pub fn get_coin_configurations() -> Result<Vec<Config>> {
 
     Ok(coin_settings)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_are_disabled_by_default() {
+        let features: Features =
+            serde_json::from_str(r#"{ "pplns": true, "utxo_level_work": false }"#).unwrap();
+
+        assert!(features.is_enabled(Feature::Pplns));
+        assert!(!features.is_enabled(Feature::UtxoLevelWork));
+        assert!(!features.is_enabled(Feature::AutoConvertPayouts));
+        assert_eq!(features.enabled(), vec![Feature::Pplns]);
+    }
+
+    #[test]
+    fn min_payout_is_bounded() {
+        let mut bounds = MinPayoutBounds {
+            floor: Amount::from_sat(100_000_000),
+            ceiling: Some(Amount::from_sat(10_000_000_000)),
+        };
+
+        assert!(bounds.check(Amount::from_sat(100_000_000)).is_ok());
+        assert!(bounds.check(Amount::from_sat(10_000_000_000)).is_ok());
+        assert!(bounds.check(Amount::from_sat(99_999_999)).is_err());
+        assert!(bounds.check(Amount::from_sat(10_000_000_001)).is_err());
+
+        bounds.ceiling = None;
+        assert!(bounds.check(Amount::from_sat(u64::MAX)).is_ok());
+    }
+
+    #[test]
+    fn dust_sweep_is_due_once_per_interval() {
+        let config: DustSweepConfig = serde_json::from_str("{}").unwrap();
+
+        assert!(config.is_due(None, 1_000));
+        assert!(!config.is_due(Some(1_000), 1_000 + 29 * 86400));
+        assert!(config.is_due(Some(1_000), 1_000 + 30 * 86400));
+    }
+
+    #[test]
+    fn sweep_window_wraps_around_midnight() {
+        let mut config: UtxoSweepConfig =
+            serde_json::from_str(r#"{ "max_utxo_amount": 100000000 }"#).unwrap();
+
+        assert!(config.in_window(0));
+
+        config.window_start_hour = Some(22);
+        config.window_end_hour = Some(4);
+
+        assert!(config.in_window(23 * 3600));
+        assert!(config.in_window(3 * 3600 + 3599));
+        assert!(!config.in_window(4 * 3600));
+        assert!(!config.in_window(12 * 3600));
+
+        config.window_start_hour = Some(2);
+        config.window_end_hour = Some(5);
+
+        assert!(config.in_window(86400 + 2 * 3600));
+        assert!(!config.in_window(86400 + 5 * 3600));
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let chain_config: ChainConfig = serde_json::from_str(
+            r#"{ "rpc_user": "user", "rpc_password": "hunter2", "rpc_host": "127.0.0.1",
+            "rpc_port": 27486, "zmq_port_blocknotify": 59790 }"#,
+        )
+        .unwrap();
+        let export: AccountingExportConfig = serde_json::from_str(
+            r#"{ "endpoint": "https://books.example.com", "headers": { "Authorization": "Bearer 123" } }"#,
+        )
+        .unwrap();
+
+        let chain_config = serde_json::to_value(chain_config).unwrap();
+        let export = serde_json::to_value(export).unwrap();
+
+        assert_eq!(chain_config["rpc_user"], "user");
+        assert_eq!(chain_config["rpc_password"], REDACTED);
+        assert_eq!(export["headers"]["Authorization"], REDACTED);
+    }
+
+    #[test]
+    fn unknown_features_are_rejected() {
+        assert!(serde_json::from_str::<Features>(r#"{ "ppnls": true }"#).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use vrsc_rpc::json::{identity::IdentityPrimary, vrsc::Address};
+
+use super::{
+    config::VaultConditions,
+    constants::{EligibilityCheck, EligibilityCondition},
+};
+
+/// On chains with IDSTAKING, only root IDs of the chain can stake.
+pub fn check_root_identity(identity: &IdentityPrimary, chain_id: &Address) -> EligibilityCheck {
+    check(
+        EligibilityCondition::RootIdentity,
+        identity.systemid == *chain_id && identity.parent == *chain_id,
+        format!("the parent is {}, it must be {chain_id}", identity.parent),
+    )
+}
+
+/// The message that `address` signs to let the pool stake its funds on behalf of the VerusID
+/// `identity_address`.
+pub fn delegation_message(
+    currency_address: &Address,
+    address: &Address,
+    identity_address: &Address,
+) -> String {
+    format!("delegate staking of {address} on {currency_address} to {identity_address}")
+}
+
+/// Checks a VerusID against the conditions of the pool, in the order in which they are
+/// evaluated. The VerusID adheres to the conditions if it passes all of them.
+///
+/// The timelock and authorities are only checked if the pool has vault conditions. A VerusID that
+/// unlocks at a fixed height must stay locked for at least `min_remaining_lock` blocks after
+/// `block_height`.
+pub fn check_identity(
+    identity: &IdentityPrimary,
+    accepted_primary_addresses: &[&Address],
+    vault_conditions: Option<&VaultConditions>,
+    block_height: u64,
+) -> Vec<EligibilityCheck> {
+    let mut checks = vec![
+        check(
+            EligibilityCondition::MinimumSignatures,
+            identity.minimumsignatures == 1,
+            format!(
+                "minimumsignatures is {}, it must be 1",
+                identity.minimumsignatures
+            ),
+        ),
+        check(
+            EligibilityCondition::PrimaryAddresses,
+            identity.primaryaddresses.len() > 1,
+            format!(
+                "{} primary addresses, at least 2 are required",
+                identity.primaryaddresses.len()
+            ),
+        ),
+        check(
+            EligibilityCondition::PoolPrimaryAddress,
+            accepted_primary_addresses
+                .iter()
+                .any(|address| identity.primaryaddresses.contains(address)),
+            format!(
+                "one of the primary addresses must be {}",
+                accepted_primary_addresses
+                    .iter()
+                    .map(|address| address.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        ),
+    ];
+
+    let Some(conditions) = vault_conditions else {
+        return checks;
+    };
+
+    checks.push(check(
+        EligibilityCondition::MaxPrimaryAddresses,
+        identity.primaryaddresses.len() <= conditions.max_primary_addresses as usize,
+        format!(
+            "{} primary addresses, at most {} are allowed",
+            identity.primaryaddresses.len(),
+            conditions.max_primary_addresses
+        ),
+    ));
+
+    if conditions.strict_recovery_id {
+        checks.push(check(
+            EligibilityCondition::RevocationAuthority,
+            identity.revocationauthority != identity.identityaddress,
+            format!(
+                "the revocation authority is {}, it must not be the VerusID itself",
+                identity.revocationauthority
+            ),
+        ));
+        checks.push(check(
+            EligibilityCondition::RecoveryAuthority,
+            identity.recoveryauthority != identity.identityaddress,
+            format!(
+                "the recovery authority is {}, it must not be the VerusID itself",
+                identity.recoveryauthority
+            ),
+        ));
+    }
+
+    let (passed, detail) = match identity.flags {
+        0 => (true, "the VerusID is not locked".to_string()),
+        1 => (
+            identity.timelock >= block_height + conditions.min_remaining_lock as u64,
+            format!(
+                "the VerusID unlocks at height {}, it must stay locked until at least height {}",
+                identity.timelock,
+                block_height + conditions.min_remaining_lock as u64
+            ),
+        ),
+        2 => (
+            identity.timelock >= conditions.min_time_lock as u64,
+            format!(
+                "the timelock is {}, at least {} is required",
+                identity.timelock, conditions.min_time_lock
+            ),
+        ),
+        flags => (false, format!("unknown flags {flags}")),
+    };
+    checks.push(check(EligibilityCondition::TimeLock, passed, detail));
+
+    checks
+}
+
+fn check(condition: EligibilityCondition, passed: bool, detail: String) -> EligibilityCheck {
+    EligibilityCheck {
+        condition,
+        passed,
+        detail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use serde_json::json;
+
+    use super::*;
+
+    const POOL_ADDRESS: &str = "RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2";
+
+    fn identity(flags: u32, timelock: u64, primary_addresses: &[&str]) -> IdentityPrimary {
+        serde_json::from_value(json!({
+            "version": 3,
+            "flags": flags,
+            "primaryaddresses": primary_addresses,
+            "minimumsignatures": 1,
+            "name": "alice",
+            "identityaddress": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+            "parent": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+            "systemid": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+            "contentmap": {},
+            "revocationauthority": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+            "recoveryauthority": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+            "timelock": timelock
+        }))
+        .unwrap()
+    }
+
+    fn failed(checks: &[EligibilityCheck]) -> Vec<EligibilityCondition> {
+        checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.condition)
+            .collect()
+    }
+
+    #[test]
+    fn every_failed_condition_is_reported() {
+        let pool_address = Address::from_str(POOL_ADDRESS).unwrap();
+        let conditions = VaultConditions {
+            min_time_lock: 1440,
+            min_remaining_lock: 1440,
+            strict_recovery_id: true,
+            max_primary_addresses: 64,
+        };
+
+        let identity = identity(2, 10, &[POOL_ADDRESS]);
+        assert_eq!(
+            failed(&check_identity(
+                &identity,
+                &[&pool_address],
+                Some(&conditions),
+                1000
+            )),
+            vec![
+                EligibilityCondition::PrimaryAddresses,
+                EligibilityCondition::RevocationAuthority,
+                EligibilityCondition::RecoveryAuthority,
+                EligibilityCondition::TimeLock,
+            ]
+        );
+
+        // without vault conditions only the general conditions are checked
+        let checks = check_identity(&identity, &[&pool_address], None, 1000);
+        assert_eq!(checks.len(), 3);
+        assert_eq!(
+            failed(&checks),
+            vec![EligibilityCondition::PrimaryAddresses]
+        );
+    }
+
+    #[test]
+    fn an_identity_with_the_pool_address_is_eligible() {
+        let pool_address = Address::from_str(POOL_ADDRESS).unwrap();
+        let identity = identity(
+            2,
+            1440,
+            &[POOL_ADDRESS, "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7"],
+        );
+
+        assert!(failed(&check_identity(
+            &identity,
+            &[&pool_address],
+            Some(&VaultConditions::default()),
+            1000
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn a_fixed_time_lock_must_not_end_within_the_min_remaining_lock() {
+        let pool_address = Address::from_str(POOL_ADDRESS).unwrap();
+        let addresses = [POOL_ADDRESS, "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7"];
+        let conditions = VaultConditions::default();
+
+        let locked = identity(1, 1000 + 1440, &addresses);
+        assert!(failed(&check_identity(
+            &locked,
+            &[&pool_address],
+            Some(&conditions),
+            1000
+        ))
+        .is_empty());
+
+        // one block later the VerusID unlocks too soon
+        assert_eq!(
+            failed(&check_identity(
+                &locked,
+                &[&pool_address],
+                Some(&conditions),
+                1001
+            )),
+            vec![EligibilityCondition::TimeLock]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use rust_decimal::Decimal;
+use vrsc_rpc::{
+    bitcoin::Txid,
+    json::vrsc::{Address, Amount},
+};
+
+use super::constants::{
+    PendingDeposit, Staker, StakerStatus, StakerUtxo, UtxoBreakdown, WorkForecast,
+};
+
+/// Returns the deposit of a UTXO with `confirmations` at `height`. Unconfirmed UTXOs are expected
+/// in the next block. The deposit becomes eligible once it has `eligibility_confirmations`.
+pub fn pending_deposit(
+    address: Address,
+    txid: Txid,
+    vout: u32,
+    amount: Amount,
+    confirmations: u64,
+    height: u64,
+    eligibility_confirmations: u64,
+) -> PendingDeposit {
+    let deposit_height = (height + 1).saturating_sub(confirmations);
+
+    PendingDeposit {
+        address,
+        txid,
+        vout,
+        amount,
+        height: deposit_height,
+        eligible_at_height: deposit_height + eligibility_confirmations,
+    }
+}
+
+/// Forecasts how the share of a staker in the work changes once its pending deposits become
+/// eligible.
+///
+/// `eligible` is the balance of the staker that counts towards its work and `pool_eligible` the
+/// balance of the whole pool, including the staker. Both are added as work with every block.
+/// `round_shares` and `round_total` are the work of the staker and of the pool in the current
+/// round.
+pub fn forecast_work(
+    height: u64,
+    mut pending_deposits: Vec<PendingDeposit>,
+    eligible: Amount,
+    pool_eligible: Amount,
+    round_shares: Decimal,
+    round_total: Decimal,
+) -> WorkForecast {
+    pending_deposits.sort_by_key(|deposit| deposit.eligible_at_height);
+
+    let pending = Decimal::from(
+        pending_deposits
+            .iter()
+            .map(|deposit| deposit.amount.as_sat())
+            .sum::<u64>(),
+    );
+    let eligible = Decimal::from(eligible.as_sat());
+    let pool_eligible = Decimal::from(pool_eligible.as_sat());
+
+    WorkForecast {
+        height,
+        pending_deposits,
+        round_share: share(round_shares, round_total),
+        block_share: share(eligible, pool_eligible),
+        forecast_block_share: share(eligible + pending, pool_eligible + pending),
+    }
+}
+
+/// Breaks the balance of a staker down into its UTXOs at `height`. A UTXO counts towards the work
+/// of the staker if it has `eligibility_confirmations` and the staker is active.
+pub fn utxo_breakdown(
+    staker: &Staker,
+    height: u64,
+    eligibility_confirmations: u64,
+    mut utxos: Vec<StakerUtxo>,
+) -> UtxoBreakdown {
+    let mut eligible_balance = Amount::ZERO;
+    let mut ineligible_balance = Amount::ZERO;
+
+    for utxo in utxos.iter_mut() {
+        utxo.eligible = staker.status == StakerStatus::Active
+            && utxo.confirmations >= eligibility_confirmations;
+
+        if utxo.eligible {
+            eligible_balance += utxo.amount;
+        } else {
+            ineligible_balance += utxo.amount;
+        }
+    }
+
+    utxos.sort_by(|a, b| b.confirmations.cmp(&a.confirmations));
+
+    UtxoBreakdown {
+        identity_address: staker.identity_address.clone(),
+        status: staker.status.clone(),
+        height,
+        utxos,
+        eligible_balance,
+        ineligible_balance,
+    }
+}
+
+fn share(part: Decimal, total: Decimal) -> Decimal {
+    part.checked_div(total).unwrap_or(Decimal::ZERO).round_dp(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn deposits_become_eligible_after_150_blocks() {
+        let address = Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap();
+        let txid =
+            Txid::from_str("1f6c7e5c3bbd0b2bfa9d5e9a2e2b0d6a3c3f3d3c8f3ad6d35a4d6c0d5f6a7b8c")
+                .unwrap();
+
+        let deposit = pending_deposit(
+            address.clone(),
+            txid,
+            0,
+            Amount::from_sat(100),
+            10,
+            1_000,
+            150,
+        );
+        assert_eq!(deposit.height, 991);
+        assert_eq!(deposit.eligible_at_height, 1_141);
+
+        let unconfirmed = pending_deposit(address, txid, 1, Amount::from_sat(300), 0, 1_000, 150);
+        assert_eq!(unconfirmed.height, 1_001);
+
+        let forecast = forecast_work(
+            1_000,
+            vec![unconfirmed, deposit],
+            Amount::from_sat(100),
+            Amount::from_sat(400),
+            Decimal::from(50),
+            Decimal::from(200),
+        );
+
+        assert_eq!(forecast.pending_deposits[0].eligible_at_height, 1_141);
+        assert_eq!(forecast.round_share, Decimal::new(25, 2));
+        assert_eq!(forecast.block_share, Decimal::new(25, 2));
+        // (100 + 400) / (400 + 400)
+        assert_eq!(forecast.forecast_block_share, Decimal::new(625, 3));
+    }
+
+    #[test]
+    fn empty_pool_has_no_share() {
+        let forecast = forecast_work(
+            1,
+            vec![],
+            Amount::ZERO,
+            Amount::ZERO,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+
+        assert_eq!(forecast.block_share, Decimal::ZERO);
+        assert_eq!(forecast.forecast_block_share, Decimal::ZERO);
+    }
+
+    #[test]
+    fn only_confirmed_utxos_of_active_stakers_count_towards_work() {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let txid =
+            Txid::from_str("1f6c7e5c3bbd0b2bfa9d5e9a2e2b0d6a3c3f3d3c8f3ad6d35a4d6c0d5f6a7b8c")
+                .unwrap();
+        let utxo = |vout: u32, amount: u64, confirmations: u64| StakerUtxo {
+            address: alice.clone(),
+            txid,
+            vout,
+            amount: Amount::from_sat(amount),
+            confirmations,
+            eligible: false,
+        };
+
+        let mut staker = Staker::new(
+            currency_address,
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::new(5, 2),
+        );
+        let utxos = vec![utxo(0, 100, 10), utxo(1, 300, 150), utxo(2, 500, 2_000)];
+
+        let breakdown = utxo_breakdown(&staker, 1_000, 150, utxos.clone());
+        assert_eq!(
+            breakdown
+                .utxos
+                .iter()
+                .map(|utxo| (utxo.vout, utxo.eligible))
+                .collect::<Vec<_>>(),
+            vec![(2, true), (1, true), (0, false)]
+        );
+        assert_eq!(breakdown.eligible_balance, Amount::from_sat(800));
+        assert_eq!(breakdown.ineligible_balance, Amount::from_sat(100));
+
+        staker.status = StakerStatus::CoolingDown;
+        let breakdown = utxo_breakdown(&staker, 1_000, 150, utxos);
+        assert_eq!(breakdown.eligible_balance, Amount::ZERO);
+        assert_eq!(breakdown.ineligible_balance, Amount::from_sat(900));
+    }
+}
@@ -0,0 +1,302 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use vrsc_rpc::{
+    bitcoin::{hashes::Hash, BlockHash, Txid},
+    client::{Client as VerusClient, RpcApi},
+    json::vrsc::Address,
+};
+
+use crate::coinstaker::constants::{FraudEvidence, Stake};
+
+const OP_RETURN: u8 = 0x6a;
+/// The header of a Sapling (v4, overwintered) transaction, which is how stakes are serialized.
+const SAPLING_HEADER: [u8; 4] = [0x04, 0x00, 0x00, 0x80];
+
+/// Collects the evidence of a stake of which the coinbase was spent by StakeGuard.
+///
+/// The StakeGuard spend proves that the staker also staked a competing block with the same
+/// UTXO: the competing stake transaction is serialized in the OP_RETURN output of the spend. The
+/// proof is stored as is, and the competing stake is decoded from it when possible.
+pub fn collect_evidence(
+    client: &VerusClient,
+    stake: &Stake,
+    offender: &Address,
+    spend_txid: Txid,
+) -> Result<FraudEvidence> {
+    let spend = client.call::<Value>(
+        "getrawtransaction",
+        &[json!(spend_txid.to_string()), json!(1)],
+    )?;
+
+    let spend_tx = spend
+        .get("hex")
+        .and_then(Value::as_str)
+        .and_then(decode_hex)
+        .context("the StakeGuard spend has no valid hex")?;
+    let spend_block_hash = spend
+        .get("blockhash")
+        .and_then(Value::as_str)
+        .and_then(|hash| hash.parse::<BlockHash>().ok());
+
+    let proof = stake_proof(&spend_tx);
+    let competing = proof.as_deref().and_then(competing_stake);
+
+    Ok(FraudEvidence {
+        currency_address: stake.currency_address.clone(),
+        block_hash: stake.block_hash,
+        block_height: stake.block_height,
+        identity_address: offender.clone(),
+        source_txid: stake.source_txid,
+        source_vout_num: stake.source_vout_num,
+        spend_txid,
+        spend_block_hash,
+        spend_tx: encode_hex(&spend_tx),
+        proof: proof.as_deref().map(encode_hex),
+        competing_txid: competing.as_ref().map(|competing| competing.txid),
+        competing_source_txid: competing.as_ref().map(|competing| competing.source_txid),
+        competing_source_vout_num: competing.map(|competing| competing.source_vout_num),
+        created_at: 0,
+    })
+}
+
+/// The competing stake transaction, decoded from the proof of a StakeGuard spend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CompetingStake {
+    txid: Txid,
+    /// The UTXO that was staked, which is the first input of a stake transaction.
+    source_txid: Txid,
+    source_vout_num: u32,
+}
+
+/// Returns the data of the first OP_RETURN output of the StakeGuard spend.
+fn stake_proof(spend_tx: &[u8]) -> Option<Vec<u8>> {
+    parse_transaction(spend_tx)?
+        .output_scripts
+        .iter()
+        .find_map(|script| op_return_data(script))
+}
+
+/// Finds the serialized stake transaction in the proof.
+fn competing_stake(proof: &[u8]) -> Option<CompetingStake> {
+    (0..proof.len())
+        .filter(|&start| proof[start..].starts_with(&SAPLING_HEADER))
+        .find_map(|start| {
+            let tx = parse_transaction(&proof[start..])?;
+            let &(source_txid, source_vout_num) = tx.inputs.first()?;
+
+            Some(CompetingStake {
+                txid: Txid::hash(&proof[start..start + tx.size]),
+                source_txid,
+                source_vout_num,
+            })
+        })
+}
+
+fn op_return_data(script: &[u8]) -> Option<Vec<u8>> {
+    let (&op, script) = script.split_first()?;
+    if op != OP_RETURN {
+        return None;
+    }
+
+    let mut reader = Reader::new(script);
+    let len = match reader.take(1)?[0] {
+        len @ 0x01..=0x4b => len as usize,
+        0x4c => reader.take(1)?[0] as usize,
+        0x4d => u16::from_le_bytes(reader.take(2)?.try_into().ok()?) as usize,
+        0x4e => reader.u32()? as usize,
+        _ => return None,
+    };
+
+    reader.take(len).map(<[u8]>::to_vec)
+}
+
+struct RawTransaction {
+    /// The outpoints that are spent.
+    inputs: Vec<(Txid, u32)>,
+    output_scripts: Vec<Vec<u8>>,
+    /// The length of the serialized transaction.
+    size: usize,
+}
+
+/// Parses a transparent transaction in the serialization of the daemon, including the Sapling
+/// fields of overwintered transactions.
+fn parse_transaction(data: &[u8]) -> Option<RawTransaction> {
+    let mut reader = Reader::new(data);
+
+    let header = reader.u32()?;
+    let overwintered = header >> 31 == 1;
+    let version = header & 0x7fff_ffff;
+    let sapling = overwintered && version >= 4;
+    if overwintered {
+        // version group id
+        reader.take(4)?;
+    }
+
+    let mut inputs = vec![];
+    for _ in 0..reader.compact_size()? {
+        let txid = Txid::from_slice(reader.take(32)?).ok()?;
+        let vout = reader.u32()?;
+        reader.bytes()?;
+        // sequence
+        reader.take(4)?;
+        inputs.push((txid, vout));
+    }
+
+    let mut output_scripts = vec![];
+    for _ in 0..reader.compact_size()? {
+        // value
+        reader.take(8)?;
+        output_scripts.push(reader.bytes()?.to_vec());
+    }
+
+    // lock time
+    reader.take(4)?;
+    let mut shielded = 0;
+    if overwintered {
+        // expiry height
+        reader.take(4)?;
+    }
+    if sapling {
+        // value balance
+        reader.take(8)?;
+        let spends = reader.compact_size()?;
+        reader.take((spends as usize).checked_mul(384)?)?;
+        let outputs = reader.compact_size()?;
+        reader.take((outputs as usize).checked_mul(948)?)?;
+        shielded = spends + outputs;
+    }
+    if version >= 2 {
+        let joinsplits = reader.compact_size()?;
+        reader.take((joinsplits as usize).checked_mul(if sapling { 1698 } else { 1802 })?)?;
+        if joinsplits > 0 {
+            // joinsplit pubkey and signature
+            reader.take(32 + 64)?;
+        }
+    }
+    if sapling && shielded > 0 {
+        // binding signature
+        reader.take(64)?;
+    }
+
+    Some(RawTransaction {
+        inputs,
+        output_scripts,
+        size: reader.pos,
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+
+        Some(bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn compact_size(&mut self) -> Option<u64> {
+        let size = match self.take(1)?[0] {
+            0xfd => u16::from_le_bytes(self.take(2)?.try_into().ok()?) as u64,
+            0xfe => self.u32()? as u64,
+            0xff => u64::from_le_bytes(self.take(8)?.try_into().ok()?),
+            size => size as u64,
+        };
+
+        Some(size)
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.compact_size()?;
+
+        self.take(usize::try_from(len).ok()?)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)?;
+
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Sapling transaction that spends `source` and has the given output scripts.
+    fn transaction(source: (Txid, u32), scripts: &[Vec<u8>]) -> Vec<u8> {
+        let mut tx = SAPLING_HEADER.to_vec();
+        tx.extend([0x85, 0x20, 0x2f, 0x89]);
+        tx.push(1);
+        tx.extend_from_slice(source.0.as_ref());
+        tx.extend(source.1.to_le_bytes());
+        tx.extend([0x01, 0x00]);
+        tx.extend([0xff; 4]);
+        tx.push(scripts.len() as u8);
+        for script in scripts {
+            tx.extend(0u64.to_le_bytes());
+            tx.push(script.len() as u8);
+            tx.extend(script);
+        }
+        // lock time, expiry height, value balance, no spends, outputs or joinsplits
+        tx.extend([0; 4 + 4 + 8]);
+        tx.extend([0, 0, 0]);
+
+        tx
+    }
+
+    #[test]
+    fn the_competing_stake_is_decoded_from_the_proof() {
+        let source = (Txid::hash(b"staked utxo"), 1);
+        let competing = transaction(source, &[vec![0x51]]);
+
+        // the proof has a prefix before the serialized stake
+        let mut proof = vec![0x01, 0x00];
+        proof.extend(&competing);
+        let mut op_return = vec![OP_RETURN, 0x4c, proof.len() as u8];
+        op_return.extend(&proof);
+
+        let spend = transaction((Txid::hash(b"coinbase"), 0), &[vec![0x51], op_return]);
+
+        assert_eq!(stake_proof(&spend), Some(proof.clone()));
+        assert_eq!(
+            competing_stake(&proof),
+            Some(CompetingStake {
+                txid: Txid::hash(&competing),
+                source_txid: source.0,
+                source_vout_num: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn truncated_transactions_are_not_decoded() {
+        let tx = transaction((Txid::hash(b"coinbase"), 0), &[vec![OP_RETURN, 0x01, 0x02]]);
+
+        assert!(parse_transaction(&tx[..tx.len() - 1]).is_none());
+        assert_eq!(stake_proof(&tx), Some(vec![0x02]));
+        assert_eq!(decode_hex(&encode_hex(&tx)), Some(tx));
+    }
+}
@@ -0,0 +1,87 @@
+use std::{collections::VecDeque, ops::RangeInclusive};
+
+use vrsc_rpc::bitcoin::BlockHash;
+
+/// The number of recently processed blocks that are remembered, to skip a block of which the
+/// notification arrives after it was already recovered.
+const RECENT_BLOCKS: usize = 128;
+/// The maximum number of missed blocks that are recovered per gap check.
+const MAX_RECOVERED_BLOCKS: u64 = 100;
+
+/// Detects blocks of which the ZMQ notification was missed.
+///
+/// Notifications get lost when the ZMQ socket dies or the daemon drops messages. Every gap check
+/// compares the chain tip against the last processed height, and the heights in between are
+/// recovered as if their notification arrived.
+#[derive(Debug, Default)]
+pub(super) struct GapDetector {
+    last_height: Option<u64>,
+    recent: VecDeque<BlockHash>,
+}
+
+impl GapDetector {
+    pub fn processed(&mut self, block_hash: BlockHash, height: u64) {
+        self.last_height = Some(height);
+
+        if self.recent.len() == RECENT_BLOCKS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(block_hash);
+    }
+
+    /// Forgets blocks that were orphaned by a reorg, as they may become part of the chain again.
+    pub fn forget(&mut self, block_hashes: &[BlockHash]) {
+        self.recent
+            .retain(|block_hash| !block_hashes.contains(block_hash));
+    }
+
+    pub fn is_processed(&self, block_hash: &BlockHash) -> bool {
+        self.recent.contains(block_hash)
+    }
+
+    /// Returns the heights that were missed up to `tip`, or `None` if there is no gap. Nothing
+    /// is missed before the first block is processed, as the catch-up takes care of that.
+    pub fn missed(&self, tip: u64) -> Option<RangeInclusive<u64>> {
+        let last_height = self.last_height?;
+
+        (last_height < tip).then(|| last_height + 1..=tip.min(last_height + MAX_RECOVERED_BLOCKS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn block_hash(n: u64) -> BlockHash {
+        BlockHash::from_str(&format!("{:064x}", n)).unwrap()
+    }
+
+    #[test]
+    fn heights_after_the_last_processed_block_are_missed() {
+        let mut detector = GapDetector::default();
+        assert_eq!(detector.missed(10), None);
+
+        detector.processed(block_hash(10), 10);
+        assert_eq!(detector.missed(10), None);
+        assert_eq!(detector.missed(13), Some(11..=13));
+        assert_eq!(detector.missed(1000), Some(11..=110));
+    }
+
+    #[test]
+    fn only_recent_blocks_are_remembered() {
+        let mut detector = GapDetector::default();
+
+        for height in 0..=RECENT_BLOCKS as u64 {
+            detector.processed(block_hash(height), height);
+        }
+
+        assert!(!detector.is_processed(&block_hash(0)));
+        assert!(detector.is_processed(&block_hash(1)));
+        assert!(detector.is_processed(&block_hash(RECENT_BLOCKS as u64)));
+
+        detector.forget(&[block_hash(1)]);
+        assert!(!detector.is_processed(&block_hash(1)));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use vrsc_rpc::bitcoin::BlockHash;
+
+/// Holds back live block notifications while the coinstaker catches up with the chain.
+///
+/// Blocks that arrive through ZMQ during the catch-up would otherwise be processed in between
+/// historical blocks. They are buffered in the order they arrived and released once the
+/// catch-up reached the chain tip.
+#[derive(Debug, Default)]
+pub(super) struct BlockGate {
+    open: bool,
+    buffered: VecDeque<BlockHash>,
+}
+
+impl BlockGate {
+    /// A gate that lets every block through, for when no catch-up is needed.
+    pub fn opened() -> Self {
+        Self {
+            open: true,
+            buffered: VecDeque::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns the block hash if it can be processed right away, or buffers it until the gate
+    /// is released. A block hash that is already buffered is ignored.
+    pub fn admit(&mut self, block_hash: BlockHash) -> Option<BlockHash> {
+        if self.open {
+            return Some(block_hash);
+        }
+
+        if !self.buffered.contains(&block_hash) {
+            self.buffered.push_back(block_hash);
+        }
+
+        None
+    }
+
+    /// Holds back blocks again, until the coinstaker caught up with the chain.
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Opens the gate and returns the buffered block hashes in the order they arrived.
+    pub fn release(&mut self) -> Vec<BlockHash> {
+        self.open = true;
+
+        self.buffered.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn block_hash(n: u8) -> BlockHash {
+        BlockHash::from_str(&format!("{:064x}", n)).unwrap()
+    }
+
+    #[test]
+    fn closed_gate_buffers_blocks_in_order() {
+        let mut gate = BlockGate::default();
+
+        assert!(!gate.is_open());
+        assert_eq!(gate.admit(block_hash(2)), None);
+        assert_eq!(gate.admit(block_hash(1)), None);
+        assert_eq!(gate.admit(block_hash(2)), None);
+        assert_eq!(gate.admit(block_hash(3)), None);
+
+        assert_eq!(
+            gate.release(),
+            vec![block_hash(2), block_hash(1), block_hash(3)]
+        );
+        assert!(gate.is_open());
+        assert!(gate.release().is_empty());
+
+        gate.close();
+        assert_eq!(gate.admit(block_hash(4)), None);
+        assert_eq!(gate.release(), vec![block_hash(4)]);
+    }
+
+    #[test]
+    fn opened_gate_lets_blocks_through() {
+        let mut gate = BlockGate::opened();
+
+        assert_eq!(gate.admit(block_hash(1)), Some(block_hash(1)));
+        assert!(gate.release().is_empty());
+    }
+}
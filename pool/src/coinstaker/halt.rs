@@ -0,0 +1,226 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error, info, warn};
+use vrsc_rpc::client::{Client as VerusClient, RpcApi};
+
+use crate::events::{EventBus, PoolEvent};
+
+use super::{coinstaker::CoinStakerMessage, ChainConfig, HaltDetectionConfig};
+
+/// Whether the chain of a currency is halted. Clones share the same state.
+///
+/// Set by the [`HaltWatcher`], read by the services that must not act on a halted chain.
+#[derive(Debug, Clone, Default)]
+pub struct HaltFlag(Arc<AtomicBool>);
+
+impl HaltFlag {
+    pub fn is_halted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn set(&self, halted: bool) {
+        self.0.store(halted, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HaltTransition {
+    Halted { height: u64, stalled_for_secs: u64 },
+    Resumed { height: u64 },
+}
+
+/// Decides from the chain tips it observes whether the chain halted.
+#[derive(Debug, Clone)]
+pub struct HaltDetector {
+    max_stall_in_secs: u64,
+    /// The last tip and the unix timestamp (in seconds) at which it was first observed.
+    tip: Option<(u64, u64)>,
+    halted: bool,
+}
+
+impl HaltDetector {
+    pub fn new(max_stall_in_secs: u64) -> Self {
+        Self {
+            max_stall_in_secs,
+            tip: None,
+            halted: false,
+        }
+    }
+
+    /// Observes the chain tip at `now`. Returns a transition if the chain halted or resumed.
+    pub fn observe(&mut self, height: u64, now: u64) -> Option<HaltTransition> {
+        match self.tip {
+            Some((tip, since)) if tip == height => {
+                let stalled_for_secs = now.saturating_sub(since);
+                if !self.halted && stalled_for_secs >= self.max_stall_in_secs {
+                    self.halted = true;
+
+                    return Some(HaltTransition::Halted {
+                        height,
+                        stalled_for_secs,
+                    });
+                }
+
+                None
+            }
+            _ => {
+                self.tip = Some((height, now));
+
+                if self.halted {
+                    self.halted = false;
+
+                    return Some(HaltTransition::Resumed { height });
+                }
+
+                None
+            }
+        }
+    }
+}
+
+/// Watches the chain tip of a currency and disables staking while the chain is halted.
+pub struct HaltWatcher {
+    config: HaltDetectionConfig,
+    chain_config: ChainConfig,
+    coinstaker: mpsc::Sender<CoinStakerMessage>,
+    events: EventBus,
+    flag: HaltFlag,
+    detector: HaltDetector,
+    /// Whether the daemon was staking when the chain halted, to only enable it again if it was.
+    was_staking: bool,
+}
+
+impl HaltWatcher {
+    pub fn new(
+        config: HaltDetectionConfig,
+        chain_config: ChainConfig,
+        coinstaker: mpsc::Sender<CoinStakerMessage>,
+        events: EventBus,
+        flag: HaltFlag,
+    ) -> Self {
+        let detector = HaltDetector::new(config.max_stall_in_secs);
+
+        Self {
+            config,
+            chain_config,
+            coinstaker,
+            events,
+            flag,
+            detector,
+            was_staking: false,
+        }
+    }
+
+    async fn check(&mut self) -> Result<()> {
+        let client: VerusClient = (&self.chain_config).try_into()?;
+        // an unreachable daemon is not a halted chain
+        let height = client.get_blockchain_info()?.blocks;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        match self.detector.observe(height, now) {
+            Some(HaltTransition::Halted {
+                height,
+                stalled_for_secs,
+            }) => {
+                warn!(%height, %stalled_for_secs, "chain halted, disabling staking and pausing payouts");
+
+                self.was_staking = client.get_mining_info()?.staking;
+                self.flag.set(true);
+                self.coinstaker
+                    .send(CoinStakerMessage::SetStaking(false))
+                    .await
+                    .context("Could not send Coinstaker message")?;
+
+                self.events.publish(PoolEvent::ChainHalted {
+                    height,
+                    stalled_for_secs,
+                });
+            }
+            Some(HaltTransition::Resumed { height }) => {
+                info!(%height, "chain resumed, resuming payouts");
+
+                self.flag.set(false);
+                if self.was_staking {
+                    self.coinstaker
+                        .send(CoinStakerMessage::SetStaking(true))
+                        .await
+                        .context("Could not send Coinstaker message")?;
+                }
+
+                self.events.publish(PoolEvent::ChainResumed { height });
+            }
+            None => debug!(%height, "chain tip checked"),
+        }
+
+        Ok(())
+    }
+
+    async fn keep_checking(&mut self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.check().await {
+                error!(error = ?e, "Failed to check the chain tip");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.check_interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for HaltWatcher {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_checking(&subsys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halts_once_the_tip_stalls_and_resumes_when_it_moves() {
+        let mut detector = HaltDetector::new(600);
+
+        assert_eq!(detector.observe(100, 0), None);
+        assert_eq!(detector.observe(100, 599), None);
+        assert_eq!(
+            detector.observe(100, 600),
+            Some(HaltTransition::Halted {
+                height: 100,
+                stalled_for_secs: 600
+            })
+        );
+        // only the start of a halt is reported
+        assert_eq!(detector.observe(100, 1200), None);
+
+        assert_eq!(
+            detector.observe(101, 1260),
+            Some(HaltTransition::Resumed { height: 101 })
+        );
+        // the stall is measured from when the new tip was first seen
+        assert_eq!(detector.observe(101, 1800), None);
+    }
+
+    #[test]
+    fn a_reorg_to_a_lower_tip_is_progress() {
+        let mut detector = HaltDetector::new(600);
+
+        detector.observe(100, 0);
+        assert_eq!(detector.observe(99, 500), None);
+        assert_eq!(detector.observe(99, 1000), None);
+    }
+}
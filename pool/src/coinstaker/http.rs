@@ -1,45 +1,548 @@
-use std::fmt::Display;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 
+use reqwest::header::CONTENT_TYPE;
+use secrecy::{ExposeSecret, Secret};
 use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Semaphore,
+};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
 use url::Url;
 use vrsc_rpc::{
-    bitcoin::BlockHash,
+    bitcoin::{BlockHash, Txid},
     json::vrsc::{util::amount::serde::as_sat, Address, Amount},
 };
 
-use super::constants::Stake;
+use crate::{config::WebhooksConfig, database, events::PoolEvent, util::explorer::Explorer};
+
+use super::{constants::Stake, signature, WebhookLimits};
+
+pub use poollib::api::{DeadLetter, EndpointStatus, StakerWebhook, StakerWebhookEvent};
+
+/// The number of consecutive failed deliveries after which an endpoint is marked unhealthy.
+const UNHEALTHY_AFTER_CONSECUTIVE_FAILURES: u64 = 3;
+/// How often the messages in the outbox are offered to their endpoints again.
+const OUTBOX_DRAIN_INTERVAL: Duration = Duration::from_secs(10);
+/// The number of messages that are taken from the outbox at a time.
+const OUTBOX_BATCH_SIZE: u64 = 500;
+/// The number of failed deliveries after which a message is dead-lettered.
+const MAX_DELIVERY_ATTEMPTS: u32 = 10;
+/// The wait before the first retry of a failed delivery, which doubles with every attempt.
+const RETRY_BACKOFF: Duration = Duration::from_secs(30);
+/// The longest wait between two retries.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(6 * 60 * 60);
 
 // send webhook message to registered endpoints
-#[derive(Debug)]
+//
+// Every message gets an id that is one higher than the id of the message before it, so an
+// endpoint can detect the messages it missed, and the messages it received twice. Messages to an
+// endpoint with a secret are signed (see `signature::sign`).
+//
+// Clones share their delivery statistics, queues, event ids and catch-up mode.
+#[derive(Debug, Clone)]
 pub struct Webhook {
     client: reqwest::Client,
     endpoints: Vec<Url>,
+    limits: WebhookLimits,
+    queues: Arc<HashMap<Url, Arc<EndpointQueue>>>,
+    /// Limits the requests that are in flight, shared by the webhooks of all currencies.
+    outbound: Arc<Semaphore>,
+    outbox: Option<Outbox>,
+    explorer: Option<Explorer>,
+    secrets: Arc<HashMap<Url, Secret<String>>>,
+    /// The id of the last message, if the ids are not kept in the outbox.
+    last_event_id: Arc<AtomicU64>,
+    deliveries: Arc<Mutex<HashMap<Url, Deliveries>>>,
+    /// The number of suppressed messages per message type, while catching up.
+    suppressed: Arc<Mutex<Option<HashMap<String, u64>>>>,
+}
+
+/// The deliveries to an endpoint that are in flight or waiting for a connection.
+#[derive(Debug)]
+struct EndpointQueue {
+    connections: Semaphore,
+    queued: AtomicUsize,
+}
+
+/// Where messages go that don't fit in the queue of their endpoint.
+#[derive(Debug, Clone)]
+struct Outbox {
+    pool: PgPool,
+    currency_address: Address,
+}
+
+/// A webhook message that waits in the outbox of the database.
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub endpoint: Url,
+    pub body: serde_json::Value,
+    /// The number of failed deliveries of this message.
+    pub attempts: u32,
 }
 
 impl Webhook {
     pub fn new(endpoints: Vec<Url>) -> Result<Self> {
         let client = reqwest::ClientBuilder::new().build()?;
+        let outbound = Arc::new(Semaphore::new(
+            WebhooksConfig::default().max_concurrent_deliveries,
+        ));
+
+        Ok(Self {
+            client,
+            endpoints,
+            limits: WebhookLimits::default(),
+            queues: Arc::new(HashMap::new()),
+            outbound,
+            outbox: None,
+            explorer: None,
+            secrets: Arc::new(HashMap::new()),
+            last_event_id: Arc::new(AtomicU64::new(0)),
+            deliveries: Arc::new(Mutex::new(HashMap::new())),
+            suppressed: Arc::new(Mutex::new(None)),
+        }
+        .with_queues())
+    }
+
+    /// Limits the deliveries per endpoint to `limits`, and all deliveries to the permits of
+    /// `outbound`.
+    pub fn with_limits(self, limits: WebhookLimits, outbound: Arc<Semaphore>) -> Self {
+        Self {
+            limits,
+            outbound,
+            ..self
+        }
+        .with_queues()
+    }
 
-        Ok(Self { client, endpoints })
+    /// Stores the messages that don't fit in the queue of their endpoint in the database, instead
+    /// of dropping them, and retries the messages of which the delivery failed.
+    pub fn with_outbox(self, pool: PgPool, currency_address: Address) -> Self {
+        Self {
+            outbox: Some(Outbox {
+                pool,
+                currency_address,
+            }),
+            ..self
+        }
+    }
+
+    /// Adds links to the block explorer of the chain to the messages.
+    pub fn with_explorer(self, explorer: Option<Explorer>) -> Self {
+        Self { explorer, ..self }
+    }
+
+    /// Signs the messages to the endpoints in `secrets` with their secret.
+    pub fn with_secrets(self, secrets: HashMap<Url, Secret<String>>) -> Self {
+        Self {
+            secrets: Arc::new(secrets),
+            ..self
+        }
+    }
+
+    fn with_queues(self) -> Self {
+        let queues = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                (
+                    endpoint.clone(),
+                    Arc::new(EndpointQueue {
+                        connections: Semaphore::new(self.limits.max_concurrent.max(1)),
+                        queued: AtomicUsize::new(0),
+                    }),
+                )
+            })
+            .collect();
+
+        Self {
+            queues: Arc::new(queues),
+            ..self
+        }
     }
 
     pub async fn send(&self, msg: WebhookMessage) {
+        if !msg.is_alert() {
+            if let Some(suppressed) = self
+                .suppressed
+                .lock()
+                .expect("webhook lock poisoned")
+                .as_mut()
+            {
+                *suppressed.entry(msg.to_string()).or_default() += 1;
+
+                return;
+            }
+        }
+
+        let body = WebhookBody::new(msg.clone(), self.explorer.as_ref())
+            .with_id(self.next_event_id().await);
+        let body = match serde_json::to_value(body) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!(error = ?e, ?msg, "Could not serialize webhook message");
+
+                return;
+            }
+        };
+
         for endpoint in self.endpoints.iter() {
-            if let Err(e) = self
-                .client
-                .post(endpoint.clone().join("/webhook").unwrap())
-                .json(&WebhookBody::from(msg.clone()))
-                .send()
+            if !self.enqueue(endpoint, body.clone(), 0) {
+                self.spill(endpoint, &body).await;
+            }
+        }
+    }
+
+    /// Returns the id of the next message. The ids are kept in the database with an outbox, so
+    /// they keep increasing across restarts.
+    async fn next_event_id(&self) -> Option<u64> {
+        let Some(outbox) = &self.outbox else {
+            return Some(self.last_event_id.fetch_add(1, Ordering::SeqCst) + 1);
+        };
+
+        match database::next_webhook_event_id(&outbox.pool, &outbox.currency_address).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                tracing::error!(error = ?e, "Could not get the id of the next webhook message");
+
+                None
+            }
+        }
+    }
+
+    /// Queues a delivery to `endpoint`, and returns false if the queue of the endpoint is full.
+    ///
+    /// `attempts` is the number of earlier deliveries of the message that failed.
+    fn enqueue(&self, endpoint: &Url, body: serde_json::Value, attempts: u32) -> bool {
+        let Some(queue) = self.queues.get(endpoint).cloned() else {
+            return false;
+        };
+
+        let capacity = self.limits.max_concurrent.max(1) + self.limits.max_queued;
+        if queue
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < capacity).then_some(queued + 1)
+            })
+            .is_err()
+        {
+            return false;
+        }
+
+        let webhook = self.clone();
+        let endpoint = endpoint.clone();
+        tokio::spawn(async move {
+            if let (Ok(_outbound), Ok(_connection)) = (
+                webhook.outbound.acquire().await,
+                queue.connections.acquire().await,
+            ) {
+                webhook.deliver(&endpoint, &body, attempts).await;
+            }
+
+            queue.queued.fetch_sub(1, Ordering::SeqCst);
+        });
+
+        true
+    }
+
+    async fn deliver(&self, endpoint: &Url, body: &serde_json::Value, attempts: u32) {
+        let start = Instant::now();
+
+        let payload = body.to_string().into_bytes();
+        let mut request = self
+            .client
+            .post(endpoint.clone().join("/webhook").unwrap())
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(secret) = self.secrets.get(endpoint) {
+            request = signature::sign(request, secret.expose_secret(), &payload);
+        }
+
+        let result = request
+            .body(payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        self.record_delivery(endpoint, start.elapsed(), result.as_ref().err());
+
+        if let Err(e) = result {
+            tracing::error!(error = ?e, %body, "Could not send webhook message");
+
+            self.retry(endpoint, body, attempts + 1, &e.to_string())
+                .await;
+        }
+    }
+
+    /// Stores a message of which the delivery failed in the outbox, to be retried with an
+    /// exponential backoff, or dead-lettered after `MAX_DELIVERY_ATTEMPTS`.
+    async fn retry(&self, endpoint: &Url, body: &serde_json::Value, attempts: u32, error: &str) {
+        let Some(outbox) = &self.outbox else {
+            return;
+        };
+
+        let retry_in = retry_backoff(attempts);
+        if retry_in.is_none() {
+            tracing::warn!(%endpoint, %body, %attempts, "webhook message dead-lettered");
+        }
+
+        if let Err(e) = database::store_failed_webhook(
+            &outbox.pool,
+            &outbox.currency_address,
+            endpoint,
+            body,
+            attempts,
+            error,
+            retry_in,
+        )
+        .await
+        {
+            tracing::error!(error = ?e, %endpoint, %body, "Could not store failed webhook message in the outbox");
+        }
+    }
+
+    /// Returns the messages that are no longer retried.
+    pub async fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let Some(outbox) = &self.outbox else {
+            return Ok(vec![]);
+        };
+
+        database::get_dead_webhooks(&outbox.pool, &outbox.currency_address).await
+    }
+
+    /// Retries the dead-lettered messages with `ids`, or all of them. Returns the number of
+    /// messages that are retried.
+    pub async fn redrive(&self, ids: Option<&[u64]>) -> Result<u64> {
+        let Some(outbox) = &self.outbox else {
+            return Ok(0);
+        };
+
+        database::redrive_dead_webhooks(&outbox.pool, &outbox.currency_address, ids).await
+    }
+
+    /// Stores a message that did not fit in the queue of its endpoint in the outbox.
+    async fn spill(&self, endpoint: &Url, body: &serde_json::Value) {
+        let Some(outbox) = &self.outbox else {
+            tracing::warn!(%endpoint, %body, "webhook queue is full, message dropped");
+
+            return;
+        };
+
+        tracing::debug!(%endpoint, "webhook queue is full, message stored in the outbox");
+
+        if let Err(e) =
+            database::store_webhook_outbox(&outbox.pool, &outbox.currency_address, endpoint, body)
                 .await
+        {
+            tracing::error!(error = ?e, %endpoint, %body, "Could not store webhook message in the outbox");
+        }
+    }
+
+    /// Queues the messages in the outbox of which the endpoint has room again.
+    ///
+    /// Messages of an endpoint that is no longer configured are removed.
+    pub async fn drain_outbox(&self) -> Result<()> {
+        let Some(outbox) = &self.outbox else {
+            return Ok(());
+        };
+
+        let mut full = HashSet::new();
+
+        for entry in
+            database::get_webhook_outbox(&outbox.pool, &outbox.currency_address, OUTBOX_BATCH_SIZE)
+                .await?
+        {
+            if !self.queues.contains_key(&entry.endpoint) {
+                tracing::warn!(endpoint = %entry.endpoint, "webhook endpoint is no longer configured, message dropped");
+            } else if full.contains(&entry.endpoint)
+                || !self.enqueue(&entry.endpoint, entry.body, entry.attempts)
             {
-                tracing::error!(error = ?e, ?msg, "Could not send webhook message");
+                full.insert(entry.endpoint);
+
+                continue;
+            }
+
+            database::delete_webhook_outbox_entry(&outbox.pool, entry.id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Suppresses all messages except alerts, until the catch-up is finished.
+    ///
+    /// Reprocessing a lot of blocks would otherwise send a message for every historical event.
+    pub fn start_catch_up(&self) {
+        self.suppressed
+            .lock()
+            .expect("webhook lock poisoned")
+            .get_or_insert_with(HashMap::new);
+    }
+
+    /// Ends the catch-up mode and sends a single summary of the suppressed messages.
+    pub async fn finish_catch_up(&self, from_height: u64, to_height: u64) {
+        if let Some(suppressed) = self.stop_suppressing() {
+            self.send(WebhookMessage::CatchUpCompleted {
+                from_height,
+                to_height,
+                suppressed,
+            })
+            .await;
+        }
+    }
+
+    fn stop_suppressing(&self) -> Option<HashMap<String, u64>> {
+        self.suppressed
+            .lock()
+            .expect("webhook lock poisoned")
+            .take()
+    }
+
+    /// Returns the delivery statistics of every configured endpoint.
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let deliveries = self.deliveries.lock().expect("webhook lock poisoned");
+
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let d = deliveries.get(endpoint).cloned().unwrap_or_default();
+                let attempts = d.delivered + d.failed;
+
+                EndpointStatus {
+                    endpoint: endpoint.clone(),
+                    queued: self
+                        .queues
+                        .get(endpoint)
+                        .map(|queue| queue.queued.load(Ordering::SeqCst) as u64)
+                        .unwrap_or_default(),
+                    delivered: d.delivered,
+                    failed: d.failed,
+                    success_rate: (attempts > 0).then(|| d.delivered as f64 / attempts as f64),
+                    average_latency_ms: (attempts > 0)
+                        .then(|| d.total_latency.as_millis() as f64 / attempts as f64),
+                    last_latency_ms: d.last_latency.map(|latency| latency.as_millis() as u64),
+                    consecutive_failures: d.consecutive_failures,
+                    healthy: d.consecutive_failures < UNHEALTHY_AFTER_CONSECUTIVE_FAILURES,
+                    last_error: d.last_error,
+                }
+            })
+            .collect()
+    }
+
+    fn record_delivery(&self, endpoint: &Url, latency: Duration, error: Option<&reqwest::Error>) {
+        let mut deliveries = self.deliveries.lock().expect("webhook lock poisoned");
+        let d = deliveries.entry(endpoint.clone()).or_default();
+
+        d.total_latency += latency;
+        d.last_latency = Some(latency);
+
+        if let Some(e) = error {
+            d.failed += 1;
+            d.consecutive_failures += 1;
+            d.last_error = Some(e.to_string());
+
+            if d.consecutive_failures == UNHEALTHY_AFTER_CONSECUTIVE_FAILURES {
+                tracing::warn!(%endpoint, "webhook endpoint marked unhealthy");
+            }
+        } else {
+            if d.consecutive_failures >= UNHEALTHY_AFTER_CONSECUTIVE_FAILURES {
+                tracing::info!(%endpoint, "webhook endpoint is healthy again");
+            }
+
+            d.delivered += 1;
+            d.consecutive_failures = 0;
+        }
+    }
+}
+
+/// The wait before retrying a message that failed `attempts` times, or `None` if it is
+/// dead-lettered.
+fn retry_backoff(attempts: u32) -> Option<Duration> {
+    if attempts >= MAX_DELIVERY_ATTEMPTS {
+        return None;
+    }
+
+    Some(
+        RETRY_BACKOFF
+            .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+            .min(MAX_RETRY_BACKOFF),
+    )
+}
+
+/// Sends the events of a currency to its webhook endpoints.
+pub struct WebhookSubscriber {
+    webhook: Webhook,
+    events: broadcast::Receiver<PoolEvent>,
+}
+
+impl WebhookSubscriber {
+    pub fn new(webhook: Webhook, events: broadcast::Receiver<PoolEvent>) -> Self {
+        Self { webhook, events }
+    }
+
+    async fn handle(&self, event: PoolEvent) {
+        match event {
+            PoolEvent::CatchUpStarted => self.webhook.start_catch_up(),
+            PoolEvent::CatchUpFinished {
+                from_height,
+                to_height,
+            } => self.webhook.finish_catch_up(from_height, to_height).await,
+            event => {
+                if let Some(msg) = WebhookMessage::from_event(event) {
+                    self.webhook.send(msg).await;
+                }
             }
         }
     }
 }
 
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for WebhookSubscriber {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        let mut drain = tokio::time::interval(OUTBOX_DRAIN_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                _ = drain.tick() => {
+                    if let Err(e) = self.webhook.drain_outbox().await {
+                        tracing::error!(error = ?e, "Could not drain the webhook outbox");
+                    }
+                }
+                event = self.events.recv() => match event {
+                    Ok(event) => self.handle(event).await,
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!(missed = n, "webhooks fell behind, events were dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Deliveries {
+    delivered: u64,
+    failed: u64,
+    consecutive_failures: u64,
+    total_latency: Duration,
+    last_latency: Option<Duration>,
+    last_error: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WebhookMessage {
@@ -60,6 +563,11 @@ pub enum WebhookMessage {
         hash: BlockHash,
         height: u64,
     },
+    StakeWalletMismatch {
+        hash: BlockHash,
+        height: u64,
+        reason: String,
+    },
     NewStaker {
         identity_address: Address,
         identity_name: String,
@@ -68,6 +576,91 @@ pub enum WebhookMessage {
         identity_address: Address,
         identity_name: String,
     },
+    ExpiredStaker {
+        identity_address: Address,
+        identity_name: String,
+    },
+    /// The unpaid rewards of the staker are paid at `settle_at`, regardless of its min_payout.
+    SettlementScheduled {
+        identity_address: Address,
+        identity_name: String,
+        settle_at: u64,
+    },
+    /// The evidence is the stake of which the coinbase was spent by StakeGuard, and the spend.
+    StakerBanned {
+        identity_address: Address,
+        identity_name: String,
+        hash: BlockHash,
+        height: u64,
+        spend_txid: Txid,
+        #[serde(with = "as_sat")]
+        forfeited: Amount,
+    },
+    StakerDeparted {
+        identity_address: Address,
+        identity_name: String,
+        #[serde(with = "as_sat")]
+        rewards: Amount,
+        #[serde(with = "as_sat")]
+        fees: Amount,
+        #[serde(with = "as_sat")]
+        forfeited: Amount,
+        staked_time_in_secs: u64,
+    },
+    PrimaryAddressRotation {
+        identity_address: Address,
+        identity_name: String,
+        new_address: Address,
+        ends_at: u64,
+    },
+    PaymentSent {
+        txid: Txid,
+        n_stakers: u64,
+        #[serde(with = "as_sat")]
+        amount: Amount,
+    },
+    /// Only sent to the webhook endpoint of a staker: the part of a payment that paid the staker.
+    StakerPaid {
+        identity_address: Address,
+        txid: Txid,
+        #[serde(with = "as_sat")]
+        amount: Amount,
+    },
+    PaymentFailed {
+        currency_address: Address,
+        txid: Txid,
+        #[serde(with = "as_sat")]
+        amount: Amount,
+        n_members: u64,
+        confirmations: i64,
+    },
+    ChainReorganized {
+        fork_height: u64,
+        depth: u64,
+        orphaned_stakes: Vec<BlockHash>,
+    },
+    ChainHalted {
+        height: u64,
+        stalled_for_secs: u64,
+    },
+    ChainResumed {
+        height: u64,
+    },
+    DaemonStakingStopped {
+        height: u64,
+    },
+    DaemonStakingResumed {
+        height: u64,
+        stopped_for_secs: u64,
+    },
+    /// Sent once the pool caught up with the chain, instead of the messages of every event that
+    /// happened in the processed blocks.
+    CatchUpCompleted {
+        from_height: u64,
+        to_height: u64,
+        /// The number of messages that were not sent, per message type.
+        suppressed: HashMap<String, u64>,
+    },
 }
 
 impl WebhookMessage {
@@ -81,6 +674,168 @@ impl WebhookMessage {
             amount: stake.amount,
         }
     }
+
+    /// Returns the message for an event, if the event has one.
+    pub fn from_event(event: PoolEvent) -> Option<Self> {
+        let msg = match event {
+            PoolEvent::StakeFound {
+                currency_name,
+                stake,
+            } => Self::new_stake(currency_name, &stake),
+            PoolEvent::StakeMatured(stake) => Self::StakeMatured {
+                hash: stake.block_hash,
+                height: stake.block_height,
+            },
+            PoolEvent::StakeStale(stake) => Self::StakeStale {
+                hash: stake.block_hash,
+                height: stake.block_height,
+            },
+            PoolEvent::StakeWalletMismatch { stake, reason } => Self::StakeWalletMismatch {
+                hash: stake.block_hash,
+                height: stake.block_height,
+                reason,
+            },
+            PoolEvent::NewStaker(staker) => Self::NewStaker {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+            },
+            PoolEvent::LeavingStaker(staker) => Self::LeavingStaker {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+            },
+            PoolEvent::ExpiredStaker(staker) => Self::ExpiredStaker {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+            },
+            PoolEvent::SettlementScheduled { staker, settle_at } => Self::SettlementScheduled {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+                settle_at,
+            },
+            PoolEvent::StakerBanned {
+                staker,
+                stake,
+                spend_txid,
+                forfeited,
+            } => Self::StakerBanned {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+                hash: stake.block_hash,
+                height: stake.block_height,
+                spend_txid,
+                forfeited,
+            },
+            PoolEvent::StakerDeparted(statement) => Self::StakerDeparted {
+                identity_address: statement.identity_address,
+                identity_name: statement.identity_name,
+                rewards: statement.rewards,
+                fees: statement.fees,
+                forfeited: statement.forfeited,
+                staked_time_in_secs: statement.staked_time_in_secs,
+            },
+            PoolEvent::PrimaryAddressRotation {
+                staker,
+                new_address,
+                ends_at,
+            } => Self::PrimaryAddressRotation {
+                identity_address: staker.identity_address,
+                identity_name: staker.identity_name,
+                new_address,
+                ends_at,
+            },
+            PoolEvent::PaymentSent {
+                txid,
+                n_stakers,
+                amount,
+                ..
+            } => Self::PaymentSent {
+                txid,
+                n_stakers,
+                amount,
+            },
+            PoolEvent::PaymentFailed {
+                payment,
+                confirmations,
+            } => Self::PaymentFailed {
+                currency_address: payment.currency_address,
+                txid: payment.txid,
+                amount: payment.amount,
+                n_members: payment.n_members,
+                confirmations,
+            },
+            PoolEvent::ChainReorganized {
+                fork_height,
+                depth,
+                orphaned_stakes,
+            } => Self::ChainReorganized {
+                fork_height,
+                depth,
+                orphaned_stakes,
+            },
+            PoolEvent::ChainHalted {
+                height,
+                stalled_for_secs,
+            } => Self::ChainHalted {
+                height,
+                stalled_for_secs,
+            },
+            PoolEvent::ChainResumed { height } => Self::ChainResumed { height },
+            PoolEvent::DaemonStakingStopped { height } => Self::DaemonStakingStopped { height },
+            PoolEvent::DaemonStakingResumed {
+                height,
+                stopped_for_secs,
+            } => Self::DaemonStakingResumed {
+                height,
+                stopped_for_secs,
+            },
+            // the operator is notified of the daemon through the notification channels
+            PoolEvent::DaemonUnreachable { .. }
+            | PoolEvent::DaemonReachable { .. }
+            | PoolEvent::CatchUpStarted
+            | PoolEvent::CatchUpFinished { .. }
+            | PoolEvent::BlockProcessed(_) => return None,
+        };
+
+        Some(msg)
+    }
+
+    /// The blocks that the message is about.
+    pub fn block_hashes(&self) -> Vec<BlockHash> {
+        match self {
+            WebhookMessage::StakeFound { hash, .. }
+            | WebhookMessage::StakeMatured { hash, .. }
+            | WebhookMessage::StakeStale { hash, .. }
+            | WebhookMessage::StakeWalletMismatch { hash, .. }
+            | WebhookMessage::StakerBanned { hash, .. } => vec![*hash],
+            WebhookMessage::ChainReorganized {
+                orphaned_stakes, ..
+            } => orphaned_stakes.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// The transactions that the message is about.
+    pub fn txids(&self) -> Vec<Txid> {
+        match self {
+            WebhookMessage::StakerBanned { spend_txid, .. } => vec![*spend_txid],
+            WebhookMessage::PaymentSent { txid, .. }
+            | WebhookMessage::StakerPaid { txid, .. }
+            | WebhookMessage::PaymentFailed { txid, .. } => vec![*txid],
+            _ => vec![],
+        }
+    }
+
+    /// Alerts require the attention of the operator and are never suppressed.
+    pub fn is_alert(&self) -> bool {
+        matches!(
+            self,
+            WebhookMessage::PaymentFailed { .. }
+                | WebhookMessage::StakeWalletMismatch { .. }
+                | WebhookMessage::StakerBanned { .. }
+                | WebhookMessage::ChainHalted { .. }
+                | WebhookMessage::DaemonStakingStopped { .. }
+        )
+    }
 }
 
 impl Display for WebhookMessage {
@@ -89,23 +844,221 @@ impl Display for WebhookMessage {
             WebhookMessage::StakeFound { .. } => write!(f, "stake_found"),
             WebhookMessage::StakeMatured { .. } => write!(f, "stake_matured"),
             WebhookMessage::StakeStale { .. } => write!(f, "stake_stale"),
+            WebhookMessage::StakeWalletMismatch { .. } => write!(f, "stake_wallet_mismatch"),
             WebhookMessage::NewStaker { .. } => write!(f, "new_staker"),
             WebhookMessage::LeavingStaker { .. } => write!(f, "leaving_staker"),
+            WebhookMessage::ExpiredStaker { .. } => write!(f, "expired_staker"),
+            WebhookMessage::SettlementScheduled { .. } => write!(f, "settlement_scheduled"),
+            WebhookMessage::StakerBanned { .. } => write!(f, "staker_banned"),
+            WebhookMessage::StakerDeparted { .. } => write!(f, "staker_departed"),
+            WebhookMessage::PrimaryAddressRotation { .. } => {
+                write!(f, "primary_address_rotation")
+            }
+            WebhookMessage::PaymentSent { .. } => write!(f, "payment_sent"),
+            WebhookMessage::StakerPaid { .. } => write!(f, "staker_paid"),
+            WebhookMessage::PaymentFailed { .. } => write!(f, "payment_failed"),
+            WebhookMessage::ChainReorganized { .. } => write!(f, "chain_reorganized"),
+            WebhookMessage::ChainHalted { .. } => write!(f, "chain_halted"),
+            WebhookMessage::ChainResumed { .. } => write!(f, "chain_resumed"),
+            WebhookMessage::DaemonStakingStopped { .. } => write!(f, "daemon_staking_stopped"),
+            WebhookMessage::DaemonStakingResumed { .. } => write!(f, "daemon_staking_resumed"),
+            WebhookMessage::CatchUpCompleted { .. } => write!(f, "catch_up_completed"),
         }
     }
 }
 
 #[derive(Serialize)]
 pub struct WebhookBody {
+    /// Increases by one with every message of the pool. Not set in the messages to stakers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
     message: String,
     data: String,
+    /// The explorer links of the blocks and transactions in the message, by block hash or txid.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    links: BTreeMap<String, Url>,
 }
 
-impl From<WebhookMessage> for WebhookBody {
-    fn from(value: WebhookMessage) -> Self {
+impl WebhookBody {
+    pub fn new(value: WebhookMessage, explorer: Option<&Explorer>) -> Self {
+        let mut links = BTreeMap::new();
+        if let Some(explorer) = explorer {
+            for hash in value.block_hashes() {
+                if let Some(url) = explorer.block_url(&hash) {
+                    links.insert(hash.to_string(), url);
+                }
+            }
+            for txid in value.txids() {
+                if let Some(url) = explorer.tx_url(&txid) {
+                    links.insert(txid.to_string(), url);
+                }
+            }
+        }
+
         Self {
+            id: None,
             message: value.to_string(),
             data: serde_json::to_string(&value).unwrap(),
+            links,
         }
     }
+
+    pub fn with_id(self, id: Option<u64>) -> Self {
+        Self { id, ..self }
+    }
+}
+
+impl From<WebhookMessage> for WebhookBody {
+    fn from(value: WebhookMessage) -> Self {
+        Self::new(value, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn bodies_link_to_the_explorer() {
+        let explorer = Explorer::new("https://insight.verus.io/{kind}/{id}").unwrap();
+        let hash = "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0";
+        let msg = WebhookMessage::StakeMatured {
+            hash: BlockHash::from_str(hash).unwrap(),
+            height: 513251,
+        };
+
+        let body = serde_json::to_value(WebhookBody::new(msg.clone(), Some(&explorer))).unwrap();
+        assert_eq!(
+            body["links"][hash],
+            format!("https://insight.verus.io/block/{hash}")
+        );
+
+        let body = serde_json::to_value(WebhookBody::from(msg)).unwrap();
+        assert!(body.get("links").is_none());
+    }
+
+    #[tokio::test]
+    async fn messages_are_suppressed_during_catch_up() {
+        let webhook = Webhook::new(vec![]).unwrap();
+        let new_staker = WebhookMessage::NewStaker {
+            identity_address: Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap(),
+            identity_name: "alice@".to_string(),
+        };
+
+        webhook.send(new_staker.clone()).await;
+        webhook.start_catch_up();
+        webhook.send(new_staker.clone()).await;
+        webhook.send(new_staker).await;
+        webhook
+            .send(WebhookMessage::StakeStale {
+                hash: BlockHash::from_str(
+                    "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+                )
+                .unwrap(),
+                height: 513251,
+            })
+            .await;
+
+        let suppressed = webhook.stop_suppressing().unwrap();
+
+        assert_eq!(suppressed.get("new_staker"), Some(&2));
+        assert_eq!(suppressed.get("stake_stale"), Some(&1));
+        assert!(webhook.stop_suppressing().is_none());
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn full_queues_spill_into_the_outbox(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let endpoint = Url::parse("http://127.0.0.1:9").unwrap();
+        // no outbound permits, so every delivery stays queued
+        let webhook = Webhook::new(vec![endpoint.clone()])
+            .unwrap()
+            .with_limits(
+                WebhookLimits {
+                    max_concurrent: 1,
+                    max_queued: 1,
+                },
+                Arc::new(Semaphore::new(0)),
+            )
+            .with_outbox(pool.clone(), currency_address.clone());
+
+        for height in 0..3 {
+            webhook
+                .send(WebhookMessage::StakeMatured {
+                    hash: BlockHash::from_str(
+                        "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+                    )
+                    .unwrap(),
+                    height,
+                })
+                .await;
+        }
+
+        assert_eq!(webhook.status()[0].queued, 2);
+
+        let outbox = database::get_webhook_outbox(&pool, &currency_address, 10)
+            .await
+            .unwrap();
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox[0].endpoint, endpoint);
+
+        // the queue is still full, so the message stays in the outbox
+        webhook.drain_outbox().await.unwrap();
+        assert_eq!(
+            database::get_webhook_outbox(&pool, &currency_address, 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn retries_back_off_until_dead_lettered() {
+        assert_eq!(retry_backoff(1), Some(Duration::from_secs(30)));
+        assert_eq!(retry_backoff(2), Some(Duration::from_secs(60)));
+        assert_eq!(retry_backoff(5), Some(Duration::from_secs(480)));
+        assert_eq!(retry_backoff(MAX_DELIVERY_ATTEMPTS), None);
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn failed_deliveries_are_retried_and_redriven(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        // nothing listens on the discard port
+        let endpoint = Url::parse("http://127.0.0.1:9").unwrap();
+        let webhook = Webhook::new(vec![endpoint.clone()])
+            .unwrap()
+            .with_outbox(pool.clone(), currency_address.clone());
+        let body = serde_json::json!({ "message": "stake_matured", "data": "{}" });
+
+        webhook.deliver(&endpoint, &body, 0).await;
+
+        // the retry is not due yet
+        assert!(database::get_webhook_outbox(&pool, &currency_address, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(webhook.dead_letters().await.unwrap().is_empty());
+
+        webhook
+            .deliver(&endpoint, &body, MAX_DELIVERY_ATTEMPTS - 1)
+            .await;
+
+        let dead = webhook.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, MAX_DELIVERY_ATTEMPTS);
+        assert!(dead[0].last_error.is_some());
+
+        assert_eq!(webhook.redrive(None).await.unwrap(), 1);
+        assert!(webhook.dead_letters().await.unwrap().is_empty());
+
+        let outbox = database::get_webhook_outbox(&pool, &currency_address, 10)
+            .await
+            .unwrap();
+        assert_eq!(outbox.len(), 1);
+        assert_eq!(outbox[0].attempts, 0);
+        assert_eq!(outbox[0].body, body);
+    }
 }
@@ -0,0 +1,158 @@
+//! Rotations of the pool primary address that an operator starts and retires with the
+//! `/admin/key-rotation` endpoints. Unlike the `primary_address_rotation` of the config, they are
+//! stored, so they survive a restart without changing the config.
+
+use serde::Deserialize;
+use vrsc_rpc::json::vrsc::Address;
+
+use super::{constants::KeyRotation, PrimaryAddressRotation};
+
+/// The part of the `validateaddress` response of the daemon that tells whether its wallet holds
+/// the key of an address. The pool can only sign stakes with a primary address it holds.
+#[derive(Debug, Deserialize)]
+pub(super) struct AddressValidation {
+    pub isvalid: bool,
+    #[serde(default)]
+    pub ismine: bool,
+}
+
+impl AddressValidation {
+    pub fn can_sign(&self) -> bool {
+        self.isvalid && self.ismine
+    }
+}
+
+/// Returns the pool primary address and the rotation that is in its transition window, after
+/// the stored `rotations` (the most recent first) are applied to the config.
+///
+/// A retired rotation only replaces the configured pool primary address if the config still
+/// names the address it rotated away from, so that an operator can still change the address in
+/// the config afterwards. A rotation that is in its transition window replaces the configured
+/// one.
+pub(super) fn apply(
+    pool_primary_address: &Address,
+    configured: Option<&PrimaryAddressRotation>,
+    rotations: &[KeyRotation],
+) -> (Address, Option<PrimaryAddressRotation>) {
+    let mut primary_address = pool_primary_address.clone();
+    let mut rotation = configured.cloned();
+
+    for key_rotation in rotations.iter().rev() {
+        match key_rotation.retired_at {
+            Some(_) if key_rotation.old_address == primary_address => {
+                primary_address = key_rotation.new_address.clone();
+            }
+            Some(_) => {}
+            None => {
+                rotation = Some(PrimaryAddressRotation {
+                    new_address: key_rotation.new_address.clone(),
+                    ends_at: key_rotation.ends_at,
+                });
+            }
+        }
+    }
+
+    // a configured rotation to the address that was rotated to already is done
+    if rotation
+        .as_ref()
+        .is_some_and(|rotation| rotation.new_address == primary_address)
+    {
+        rotation = None;
+    }
+
+    (primary_address, rotation)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const CURRENCY: &str = "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq";
+    const FIRST: &str = "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7";
+    const SECOND: &str = "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK";
+    const THIRD: &str = "RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2";
+
+    fn address(address: &str) -> Address {
+        Address::from_str(address).unwrap()
+    }
+
+    fn key_rotation(old: &str, new: &str, retired: bool) -> KeyRotation {
+        KeyRotation {
+            currency_address: address(CURRENCY),
+            old_address: address(old),
+            new_address: address(new),
+            ends_at: 2_000_000,
+            operator: "alice".to_string(),
+            retired_at: retired.then_some(2_000_100),
+            retired_by: retired.then(|| "alice".to_string()),
+            created_at: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn only_addresses_of_the_wallet_can_sign() {
+        let validation = |value: serde_json::Value| {
+            serde_json::from_value::<AddressValidation>(value)
+                .unwrap()
+                .can_sign()
+        };
+
+        assert!(validation(serde_json::json!({
+            "isvalid": true,
+            "address": THIRD,
+            "ismine": true
+        })));
+        assert!(!validation(serde_json::json!({
+            "isvalid": true,
+            "address": THIRD,
+            "ismine": false
+        })));
+        assert!(!validation(serde_json::json!({ "isvalid": false })));
+    }
+
+    #[test]
+    fn retired_rotations_replace_the_primary_address() {
+        let (primary_address, rotation) = apply(
+            &address(FIRST),
+            None,
+            &[
+                key_rotation(SECOND, THIRD, false),
+                key_rotation(FIRST, SECOND, true),
+            ],
+        );
+
+        assert_eq!(primary_address, address(SECOND));
+        assert_eq!(rotation.unwrap().new_address, address(THIRD));
+    }
+
+    #[test]
+    fn a_changed_config_is_not_overridden() {
+        let (primary_address, rotation) =
+            apply(&address(THIRD), None, &[key_rotation(FIRST, SECOND, true)]);
+
+        assert_eq!(primary_address, address(THIRD));
+        assert!(rotation.is_none());
+    }
+
+    #[test]
+    fn a_configured_rotation_that_was_retired_is_done() {
+        let configured = PrimaryAddressRotation {
+            new_address: address(SECOND),
+            ends_at: 2_000_000,
+        };
+
+        let (primary_address, rotation) = apply(
+            &address(FIRST),
+            Some(&configured),
+            &[key_rotation(FIRST, SECOND, true)],
+        );
+
+        assert_eq!(primary_address, address(SECOND));
+        assert!(rotation.is_none());
+
+        let (_, rotation) = apply(&address(FIRST), Some(&configured), &[]);
+        assert_eq!(rotation.unwrap().new_address, address(SECOND));
+    }
+}
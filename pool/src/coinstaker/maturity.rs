@@ -0,0 +1,74 @@
+use anyhow::{Context, Result};
+use serde_json::{json, value::to_raw_value};
+use vrsc_rpc::{bitcoin::BlockHash, client::Client as VerusClient, json::Block};
+
+/// Where a pending stake is in its maturing, according to the confirmations of its block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Maturity {
+    /// The block is no longer in the chain.
+    Stale,
+    Maturing,
+    Matured,
+}
+
+impl Maturity {
+    /// A stake has matured once its block has `maturity_confirmations`.
+    pub(super) fn of(confirmations: impl Into<i64>, maturity_confirmations: u32) -> Self {
+        let confirmations = confirmations.into();
+
+        if confirmations < 0 {
+            Self::Stale
+        } else if confirmations < maturity_confirmations as i64 {
+            Self::Maturing
+        } else {
+            Self::Matured
+        }
+    }
+}
+
+/// Gets the blocks of the pending stakes in a single batched call to the daemon, in the order of
+/// `block_hashes`.
+pub(super) fn get_blocks(client: &VerusClient, block_hashes: &[BlockHash]) -> Result<Vec<Block>> {
+    if block_hashes.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let params = block_hashes
+        .iter()
+        .map(|block_hash| to_raw_value(&json!([block_hash, 2])))
+        .collect::<Result<Vec<_>, _>>()?;
+    let rpc = client.get_jsonrpc_client();
+    let requests = params
+        .iter()
+        .map(|params| rpc.build_request("getblock", Some(params)))
+        .collect::<Vec<_>>();
+
+    rpc.send_batch(&requests)?
+        .into_iter()
+        .zip(block_hashes)
+        .map(|(response, block_hash)| {
+            response
+                .with_context(|| format!("no response for block {block_hash}"))?
+                .result::<Block>()
+                .with_context(|| format!("could not get block {block_hash}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_the_maturity_from_the_confirmations() {
+        assert_eq!(Maturity::of(-1, 100), Maturity::Stale);
+        assert_eq!(Maturity::of(0, 100), Maturity::Maturing);
+        assert_eq!(Maturity::of(99, 100), Maturity::Maturing);
+        assert_eq!(Maturity::of(100, 100), Maturity::Matured);
+        assert_eq!(Maturity::of(250, 100), Maturity::Matured);
+
+        // a PBaaS chain can have another coinbase maturity
+        assert_eq!(Maturity::of(100, 150), Maturity::Maturing);
+        assert_eq!(Maturity::of(150, 150), Maturity::Matured);
+    }
+}
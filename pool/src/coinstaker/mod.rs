@@ -1,13 +1,64 @@
+pub mod accrual;
+pub mod backfill;
+mod capabilities;
 pub mod coinstaker;
+mod cold_staking;
 mod config;
+mod eligibility;
 pub mod constants;
+mod forecast;
+mod fraud;
+mod gap;
+mod gate;
+pub mod halt;
 pub mod http;
+mod key_rotation;
+mod maturity;
 #[cfg(feature = "mock")]
 mod mock;
+pub mod pause;
+mod quorum;
+mod reorg;
+pub mod replay;
+mod rpc_pool;
+mod signature;
+pub mod staker_webhooks;
+pub mod staking_watch;
+pub mod summary;
+mod wallet_check;
 mod zmq;
 
+pub use capabilities::probe_capabilities;
 pub use config::get_coin_configurations;
+pub use config::AccountingExportConfig;
+pub use config::BootstrapConfig;
 pub use config::ChainConfig;
+pub use config::ColdStakingConfig;
 pub use config::Config;
+pub use config::DustSweepConfig;
+pub use config::Feature;
+pub use config::Features;
+pub use config::FeePromotion;
+pub use config::FeeSchedule;
+pub use config::FeeSweepConfig;
+pub use config::FeeTier;
+pub use config::HaltDetectionConfig;
+pub use config::InactiveWorkPolicy;
+pub use config::MinPayoutBounds;
+pub use config::PayoutBatching;
 pub use config::PayoutConfig;
+pub use config::PayoutConversion;
+pub use config::PayoutAlignment;
+pub use config::PayoutScheme;
+pub use config::PrimaryAddressRotation;
+pub use config::QuorumConfig;
+pub use config::QuorumNode;
+pub use config::ReconciliationConfig;
+pub use config::RpcEndpoint;
+pub use config::StakingWatchConfig;
+pub use config::StatusPageConfig;
+pub use config::StatusPageFormat;
+pub use config::UtxoSweepConfig;
+pub use config::WebhookLimits;
+pub use config::DUST_THRESHOLD_IN_SATS;
 pub use constants::StakerStatus;
@@ -0,0 +1,75 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use vrsc_rpc::bitcoin::BlockHash;
+
+/// Whether an operator paused the chain of a currency. Clones share the same state.
+///
+/// Set by the coinstaker on a `Pause` or `Resume` message, read by the services that must not
+/// act on a paused chain.
+#[derive(Debug, Clone, Default)]
+pub struct PauseFlag(Arc<AtomicBool>);
+
+impl PauseFlag {
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub(super) fn set(&self, paused: bool) {
+        self.0.store(paused, Ordering::SeqCst);
+    }
+}
+
+/// The blocks that arrived while the chain was paused, to process them once it is resumed.
+///
+/// Nothing is stored, so the blocks of a pause are lost when the pool restarts. The pool isn't
+/// paused after a restart either, and the missed blocks are caught up with like any other.
+#[derive(Debug, Default)]
+pub(super) struct PausedBlocks(VecDeque<BlockHash>);
+
+impl PausedBlocks {
+    /// Records a block. A block that is already recorded is ignored.
+    pub fn record(&mut self, block_hash: BlockHash) {
+        if !self.0.contains(&block_hash) {
+            self.0.push_back(block_hash);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns the recorded blocks in the order they arrived.
+    pub fn take(&mut self) -> Vec<BlockHash> {
+        self.0.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn block_hash(n: u8) -> BlockHash {
+        BlockHash::from_str(&format!("{:064x}", n)).unwrap()
+    }
+
+    #[test]
+    fn records_blocks_once_in_order() {
+        let mut paused = PausedBlocks::default();
+
+        paused.record(block_hash(2));
+        paused.record(block_hash(1));
+        paused.record(block_hash(2));
+        assert_eq!(paused.len(), 2);
+
+        assert_eq!(paused.take(), vec![block_hash(2), block_hash(1)]);
+        assert!(paused.take().is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use std::{fmt, time::Duration};
+
+use anyhow::Result;
+use tracing::{debug, warn};
+use vrsc_rpc::{
+    bitcoin::BlockHash,
+    client::{Client as VerusClient, RpcApi},
+};
+
+use super::config::{QuorumConfig, RpcEndpoint};
+
+/// How often a block is checked against the quorum before it is given up on. Other daemons may
+/// not have received a block yet when its notification arrives.
+const BLOCK_ATTEMPTS: u32 = 3;
+const BLOCK_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// The daemons of a quorum did not agree with the daemon in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct NoQuorum {
+    pub what: String,
+    pub agreeing: usize,
+    pub required: usize,
+}
+
+impl fmt::Display for NoQuorum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "only {} of the required {} daemons agree on {}",
+            self.agreeing, self.required, self.what
+        )
+    }
+}
+
+impl std::error::Error for NoQuorum {}
+
+/// Cross-checks what the daemon in use answered against the other daemons of the chain.
+///
+/// The daemon in use counts as agreeing. A daemon that can't be reached doesn't.
+pub(super) struct Quorum {
+    nodes: Vec<RpcEndpoint>,
+    min_agreeing: usize,
+}
+
+impl Quorum {
+    pub fn new(config: &QuorumConfig) -> Self {
+        Self {
+            nodes: config.nodes.iter().map(|node| node.endpoint()).collect(),
+            min_agreeing: config.min_agreeing,
+        }
+    }
+
+    /// Fails with [`NoQuorum`] unless enough daemons answer `query` with `value`.
+    pub fn agree<T: PartialEq>(
+        &self,
+        what: &str,
+        value: &T,
+        query: impl Fn(&VerusClient) -> Result<T>,
+    ) -> Result<()> {
+        let answers = self
+            .nodes
+            .iter()
+            .map(|endpoint| {
+                let answer = VerusClient::try_from(endpoint).and_then(|client| query(&client));
+                match &answer {
+                    Ok(answer) if answer != value => {
+                        warn!(
+                            host = endpoint.rpc_host,
+                            port = endpoint.rpc_port,
+                            "daemon disagrees on {what}"
+                        )
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!(
+                            host = endpoint.rpc_host,
+                            port = endpoint.rpc_port,
+                            error = ?e,
+                            "daemon could not be asked about {what}"
+                        )
+                    }
+                }
+
+                answer
+            })
+            .collect::<Vec<_>>();
+
+        let agreeing = count_agreeing(value, &answers);
+        if agreeing < self.min_agreeing {
+            Err(NoQuorum {
+                what: what.to_string(),
+                agreeing,
+                required: self.min_agreeing,
+            })?
+        }
+
+        debug!(%agreeing, "quorum agrees on {what}");
+
+        Ok(())
+    }
+
+    /// Fails with [`NoQuorum`] unless enough daemons have `block_hash` at `height` in their
+    /// chain.
+    pub async fn confirm_block(&self, height: u64, block_hash: &BlockHash) -> Result<()> {
+        let mut attempt = 1;
+
+        loop {
+            let agreed = self.agree(&format!("block {block_hash}"), block_hash, |client| {
+                Ok(client.call::<BlockHash>("getblockhash", &[height.into()])?)
+            });
+
+            match agreed {
+                Err(e) if attempt < BLOCK_ATTEMPTS && e.downcast_ref::<NoQuorum>().is_some() => {
+                    attempt += 1;
+                    tokio::time::sleep(BLOCK_RETRY_DELAY).await;
+                }
+                agreed => return agreed,
+            }
+        }
+    }
+}
+
+/// The daemon in use and the daemons that answered with the same value.
+fn count_agreeing<T: PartialEq>(value: &T, answers: &[Result<T>]) -> usize {
+    1 + answers
+        .iter()
+        .filter(|answer| answer.as_ref().is_ok_and(|answer| answer == value))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn unreachable_and_disagreeing_daemons_do_not_count() {
+        assert_eq!(count_agreeing(&1, &[]), 1);
+        assert_eq!(count_agreeing(&1, &[Ok(1), Ok(1)]), 3);
+        assert_eq!(
+            count_agreeing(&1, &[Ok(2), Err(anyhow!("connection refused")), Ok(1)]),
+            2
+        );
+    }
+}
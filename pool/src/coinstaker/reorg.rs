@@ -0,0 +1,61 @@
+use anyhow::Result;
+use vrsc_rpc::bitcoin::BlockHash;
+
+/// The number of processed blocks that are compared with the chain. A stake matures after 100
+/// blocks, so a deeper reorg would have to undo payouts.
+pub(super) const REORG_WINDOW: u64 = 100;
+
+/// Returns the processed blocks that are no longer in the chain.
+///
+/// `processed` are the heights and hashes of the processed blocks, highest first. `canonical`
+/// returns the hash of the block at a height in the chain of the daemon. The comparison stops
+/// at the first processed block that is still in the chain, as the blocks below it are too.
+pub(super) fn find_orphaned(
+    processed: &[(u64, BlockHash)],
+    mut canonical: impl FnMut(u64) -> Result<BlockHash>,
+) -> Result<Vec<(u64, BlockHash)>> {
+    let mut orphaned = vec![];
+
+    for (height, block_hash) in processed {
+        if &canonical(*height)? == block_hash {
+            break;
+        }
+
+        orphaned.push((*height, *block_hash));
+    }
+
+    Ok(orphaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use anyhow::Context;
+
+    use super::*;
+
+    fn block_hash(n: u64) -> BlockHash {
+        BlockHash::from_str(&format!("{:064x}", n)).unwrap()
+    }
+
+    #[test]
+    fn finds_the_blocks_above_the_fork() {
+        // blocks 8 and 9 were replaced by 108 and 109
+        let chain = HashMap::from([
+            (7, block_hash(7)),
+            (8, block_hash(108)),
+            (9, block_hash(109)),
+        ]);
+        let canonical = |height| chain.get(&height).copied().context("unknown height");
+
+        let processed = [(9, block_hash(9)), (8, block_hash(8)), (7, block_hash(7))];
+        assert_eq!(
+            find_orphaned(&processed, canonical).unwrap(),
+            vec![(9, block_hash(9)), (8, block_hash(8))]
+        );
+
+        let processed = [(9, block_hash(109)), (8, block_hash(108))];
+        assert!(find_orphaned(&processed, canonical).unwrap().is_empty());
+    }
+}
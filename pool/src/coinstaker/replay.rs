@@ -0,0 +1,373 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use jsonrpc::{simple_http::SimpleHttpTransport, Request, Response, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use vrsc_rpc::{bitcoin::BlockHash, client::Client as VerusClient, json::vrsc::Address};
+
+use crate::events::EventBus;
+
+use super::{
+    coinstaker::{CoinStaker, CoinStakerMessage},
+    config::{ChainConfig, Config},
+};
+
+/// A line in a recording. Recordings are JSON lines, so that a recording of a pool that crashed
+/// can still be replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedEntry {
+    Start { currency_id: Address },
+    Block(BlockHash),
+    Call(RecordedCall),
+}
+
+/// An RPC call to the daemon, with the response it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub method: String,
+    pub params: Value,
+    pub response: Response,
+}
+
+/// Where the RPC traffic of a coinstaker goes, when it doesn't go to the daemon alone.
+#[derive(Debug, Clone)]
+pub enum RpcTraffic {
+    /// Calls go to the daemon, and are written to a recording together with their responses.
+    Record(Recorder),
+    /// Calls are answered from a recording, without a daemon.
+    Replay(ReplayTransport),
+}
+
+impl RpcTraffic {
+    pub fn client(&self, chain_config: &ChainConfig) -> Result<VerusClient> {
+        let client = match self {
+            Self::Record(recorder) => jsonrpc::Client::with_transport(RecordingTransport {
+                inner: SimpleHttpTransport::builder()
+                    .url(&format!(
+                        "{}:{}",
+                        chain_config.rpc_host, chain_config.rpc_port
+                    ))?
+                    .auth(&chain_config.rpc_user, Some(&chain_config.rpc_password))
+                    .build(),
+                recorder: recorder.clone(),
+            }),
+            Self::Replay(transport) => jsonrpc::Client::with_transport(transport.clone()),
+        };
+
+        Ok(VerusClient::from_jsonrpc(client))
+    }
+}
+
+/// Writes the block hashes that a coinstaker received and the RPC calls it made to a file.
+#[derive(Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Arc<Mutex<BufWriter<File>>>,
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Recorder {
+    /// Starts a new recording for `currency_id` in `dir`.
+    pub fn create(dir: &Path, currency_id: &Address) -> Result<Self> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = dir.join(format!("{currency_id}-{timestamp}.jsonl"));
+        let file = File::create(&path)
+            .with_context(|| format!("Could not create recording {}", path.display()))?;
+
+        let recorder = Self {
+            path,
+            file: Arc::new(Mutex::new(BufWriter::new(file))),
+        };
+        recorder.record(&RecordedEntry::Start {
+            currency_id: currency_id.clone(),
+        });
+
+        info!(path = %recorder.path.display(), "recording rpc traffic");
+
+        Ok(recorder)
+    }
+
+    /// Appends an entry. A recording is a debugging aid, so failing to write it doesn't stop
+    /// the pool.
+    pub fn record(&self, entry: &RecordedEntry) {
+        let result = serde_json::to_string(entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| {
+                let mut file = self
+                    .file
+                    .lock()
+                    .map_err(|_| anyhow!("recording lock poisoned"))?;
+                writeln!(file, "{line}")?;
+                file.flush()?;
+
+                Ok(())
+            });
+
+        if let Err(e) = result {
+            warn!(path = %self.path.display(), error = ?e, "could not write to recording");
+        }
+    }
+}
+
+struct RecordingTransport {
+    inner: SimpleHttpTransport,
+    recorder: Recorder,
+}
+
+impl RecordingTransport {
+    fn record(&self, request: &Request, response: &Response) {
+        self.recorder.record(&RecordedEntry::Call(RecordedCall {
+            method: request.method.to_string(),
+            params: params(request),
+            response: response.clone(),
+        }));
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send_request(&self, request: Request) -> Result<Response, jsonrpc::Error> {
+        let call = Request {
+            id: Value::Null,
+            ..request
+        };
+        let response = self.inner.send_request(request)?;
+        self.record(&call, &response);
+
+        Ok(response)
+    }
+
+    fn send_batch(&self, requests: &[Request]) -> Result<Vec<Response>, jsonrpc::Error> {
+        let responses = self.inner.send_batch(requests)?;
+
+        for (request, response) in requests.iter().zip(responses.iter()) {
+            self.record(request, response);
+        }
+
+        Ok(responses)
+    }
+
+    fn fmt_target(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "recording ")?;
+        self.inner.fmt_target(f)
+    }
+}
+
+/// Answers RPC calls with the responses from a recording.
+///
+/// Calls with the same method and parameters get their recorded responses in the order they
+/// were recorded. Once these run out, the last response is repeated, as the coinstaker can ask
+/// for the chain tip more often during a replay than it did while recording.
+#[derive(Debug, Clone)]
+pub struct ReplayTransport {
+    responses: Arc<Mutex<HashMap<(String, String), VecDeque<Response>>>>,
+}
+
+impl ReplayTransport {
+    pub fn new(calls: &[RecordedCall]) -> Self {
+        let mut responses: HashMap<(String, String), VecDeque<Response>> = HashMap::new();
+
+        for call in calls {
+            responses
+                .entry((call.method.clone(), call.params.to_string()))
+                .or_default()
+                .push_back(call.response.clone());
+        }
+
+        Self {
+            responses: Arc::new(Mutex::new(responses)),
+        }
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send_request(&self, request: Request) -> Result<Response, jsonrpc::Error> {
+        let key = (request.method.to_string(), params(&request).to_string());
+        let mut responses = self
+            .responses
+            .lock()
+            .map_err(|_| jsonrpc::Error::Transport("replay lock poisoned".into()))?;
+
+        let response = responses.get_mut(&key).and_then(|queue| {
+            if queue.len() > 1 {
+                queue.pop_front()
+            } else {
+                queue.front().cloned()
+            }
+        });
+        let Some(mut response) = response else {
+            return Err(jsonrpc::Error::Transport(
+                format!("no recorded response for {} {}", key.0, key.1).into(),
+            ));
+        };
+
+        // the client checks that the response belongs to its request
+        response.id = request.id;
+
+        Ok(response)
+    }
+
+    fn send_batch(&self, requests: &[Request]) -> Result<Vec<Response>, jsonrpc::Error> {
+        requests
+            .iter()
+            .map(|request| {
+                self.send_request(Request {
+                    method: request.method,
+                    params: request.params,
+                    id: request.id.clone(),
+                    jsonrpc: request.jsonrpc,
+                })
+            })
+            .collect()
+    }
+
+    fn fmt_target(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "replay")
+    }
+}
+
+fn params(request: &Request) -> Value {
+    request
+        .params
+        .and_then(|params| serde_json::from_str(params.get()).ok())
+        .unwrap_or(Value::Null)
+}
+
+/// The block hashes and RPC calls of a recording.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub currency_id: Address,
+    pub blocks: Vec<BlockHash>,
+    pub calls: Vec<RecordedCall>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open recording {}", path.display()))?;
+
+        let mut currency_id = None;
+        let mut blocks = vec![];
+        let mut calls = vec![];
+
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line)
+                .with_context(|| format!("Invalid entry on line {} of the recording", i + 1))?
+            {
+                RecordedEntry::Start { currency_id: id } => currency_id = Some(id),
+                RecordedEntry::Block(block_hash) => blocks.push(block_hash),
+                RecordedEntry::Call(call) => calls.push(call),
+            }
+        }
+
+        Ok(Self {
+            currency_id: currency_id.context("The recording does not name its currency")?,
+            blocks,
+            calls,
+        })
+    }
+}
+
+/// Feeds the blocks of a recording through a coinstaker that gets its RPC responses from the
+/// same recording, so that an incident can be reproduced without a daemon.
+///
+/// `pool` should be a scratch database, as the coinstaker writes to it as it would in
+/// production.
+pub async fn replay(pool: PgPool, config: Config, recording: Recording) -> Result<()> {
+    let (tx, rx) = mpsc::channel(recording.blocks.len().max(1));
+    // the coinstaker keeps a sender for the ZMQ listener, which doesn't run during a replay. The
+    // channel closes once the recorded blocks are sent, which stops the coinstaker.
+    let (unused_tx, _) = mpsc::channel(1);
+
+    let mut coin_staker = CoinStaker::new(pool, config, unused_tx, rx, EventBus::new())?
+        .with_traffic(RpcTraffic::Replay(ReplayTransport::new(&recording.calls)));
+
+    for block_hash in &recording.blocks {
+        tx.send(CoinStakerMessage::Block(*block_hash)).await?;
+    }
+    drop(tx);
+
+    info!(
+        n_blocks = recording.blocks.len(),
+        n_calls = recording.calls.len(),
+        "replaying recording"
+    );
+
+    coin_staker.listen().await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, value::to_raw_value};
+
+    use super::*;
+
+    fn call(method: &str, params: Value, result: Value) -> RecordedCall {
+        RecordedCall {
+            method: method.to_string(),
+            params,
+            response: Response {
+                result: Some(to_raw_value(&result).unwrap()),
+                error: None,
+                id: json!(1),
+                jsonrpc: Some("2.0".to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn replay_answers_in_recorded_order() {
+        let transport = ReplayTransport::new(&[
+            call("getblockcount", json!([]), json!(10)),
+            call("getblockcount", json!([]), json!(11)),
+            call("getblockhash", json!([10]), json!("abc")),
+        ]);
+        let client = jsonrpc::Client::with_transport(transport);
+        let no_params = to_raw_value(&json!([])).unwrap();
+        let height = to_raw_value(&json!([10])).unwrap();
+
+        let count = |client: &jsonrpc::Client| {
+            client
+                .call::<u64>("getblockcount", Some(&no_params))
+                .unwrap()
+        };
+
+        assert_eq!(count(&client), 10);
+        assert_eq!(count(&client), 11);
+        // the last response is repeated
+        assert_eq!(count(&client), 11);
+        assert_eq!(
+            client
+                .call::<String>("getblockhash", Some(&height))
+                .unwrap(),
+            "abc"
+        );
+        assert!(client
+            .call::<String>("getblockhash", Some(&no_params))
+            .is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
+use vrsc_rpc::client::{Client as VerusClient, RpcApi};
+
+use crate::error::PoolError;
+
+use super::config::{ChainConfig, RpcEndpoint};
+
+/// How long a client is used without probing its daemon again.
+const PROBE_INTERVAL: Duration = Duration::from_secs(10);
+/// How long an endpoint is skipped after it failed. Doubles with every consecutive failure.
+const MIN_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Skips an endpoint that is failing, for longer with every consecutive failure.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct CircuitBreaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn is_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|until| now < until)
+    }
+
+    fn fail(&mut self, now: Instant) {
+        let backoff = MIN_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(MAX_BACKOFF);
+
+        self.failures = self.failures.saturating_add(1);
+        self.open_until = Some(now + backoff);
+    }
+
+    fn succeed(&mut self) {
+        *self = Self::default();
+    }
+}
+
+struct Connection {
+    endpoint: usize,
+    client: Arc<VerusClient>,
+    probed_at: Instant,
+}
+
+struct State {
+    breakers: Vec<CircuitBreaker>,
+    connection: Option<Connection>,
+}
+
+/// The RPC clients of the daemons of a chain: the daemon of the chain config, followed by its
+/// fallbacks.
+///
+/// A client is reused for as long as its daemon answers the health probe. Once it stops
+/// answering, the next endpoint that is not cooling down is used, so a daemon that restarts
+/// doesn't stop the coinstaker. The daemon of the chain config is preferred again as soon as
+/// it answers.
+pub(super) struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+    state: Mutex<State>,
+}
+
+impl RpcPool {
+    pub fn new(chain_config: &ChainConfig) -> Self {
+        let endpoints = chain_config.endpoints();
+
+        Self {
+            state: Mutex::new(State {
+                breakers: vec![CircuitBreaker::default(); endpoints.len()],
+                connection: None,
+            }),
+            endpoints,
+        }
+    }
+
+    /// Returns a client of a daemon that answered the health probe within the last 10 seconds.
+    pub fn client(&self) -> Result<Arc<VerusClient>> {
+        let now = Instant::now();
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("rpc pool lock poisoned"))?;
+
+        if let Some(connection) = &state.connection {
+            if now.duration_since(connection.probed_at) < PROBE_INTERVAL {
+                return Ok(connection.client.clone());
+            }
+        }
+
+        let connected_to = state
+            .connection
+            .take()
+            .map(|connection| connection.endpoint);
+
+        for (i, endpoint) in self.endpoints.iter().enumerate() {
+            if state.breakers[i].is_open(now) {
+                continue;
+            }
+
+            match probe(endpoint) {
+                Ok(client) => {
+                    state.breakers[i].succeed();
+                    if connected_to != Some(i) {
+                        info!(
+                            host = endpoint.rpc_host,
+                            port = endpoint.rpc_port,
+                            "using daemon"
+                        );
+                    }
+
+                    let client = Arc::new(client);
+                    state.connection = Some(Connection {
+                        endpoint: i,
+                        client: client.clone(),
+                        probed_at: now,
+                    });
+
+                    return Ok(client);
+                }
+                Err(e) => {
+                    warn!(host = endpoint.rpc_host, port = endpoint.rpc_port, error = ?e, "daemon did not answer the health probe");
+                    state.breakers[i].fail(now);
+                }
+            }
+        }
+
+        Err(PoolError::DaemonUnavailable {
+            daemons: self.endpoints.len(),
+        }
+        .into())
+    }
+
+    /// Probes the daemons right away, instead of trusting the last probe.
+    pub fn is_reachable(&self) -> bool {
+        if let Ok(mut state) = self.state.lock() {
+            state.connection = None;
+        }
+
+        self.client().is_ok()
+    }
+}
+
+fn probe(endpoint: &RpcEndpoint) -> Result<VerusClient> {
+    let client: VerusClient = endpoint.try_into()?;
+    client.call::<u64>("getblockcount", &[])?;
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_endpoints_are_skipped_for_longer_each_time() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::default();
+        assert!(!breaker.is_open(now));
+
+        breaker.fail(now);
+        assert!(breaker.is_open(now + Duration::from_secs(4)));
+        assert!(!breaker.is_open(now + Duration::from_secs(5)));
+
+        breaker.fail(now);
+        assert!(breaker.is_open(now + Duration::from_secs(9)));
+        assert!(!breaker.is_open(now + Duration::from_secs(10)));
+
+        for _ in 0..20 {
+            breaker.fail(now);
+        }
+        assert!(!breaker.is_open(now + MAX_BACKOFF));
+
+        breaker.succeed();
+        assert!(!breaker.is_open(now));
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::RequestBuilder;
+use uuid::Uuid;
+use vrsc_rpc::bitcoin::hashes::{
+    hmac::{Hmac, HmacEngine},
+    sha256, Hash, HashEngine,
+};
+
+/// The header with the HMAC-SHA256 of the signed payload, for endpoints that have a secret.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+/// The header with the unix timestamp (in seconds) of the delivery.
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+/// The header with a random nonce, which is new for every delivery.
+pub const NONCE_HEADER: &str = "X-Webhook-Nonce";
+
+/// Signs a webhook delivery with `secret`.
+///
+/// The `X-Signature-256` header holds `sha256=` followed by the hex HMAC-SHA256 of
+/// `{timestamp}.{nonce}.{body}`, in which the timestamp and nonce are the values of the
+/// `X-Webhook-Timestamp` and `X-Webhook-Nonce` headers. A receiver rejects deliveries with an old
+/// timestamp or a nonce that it has seen before, so a captured delivery can't be replayed.
+pub fn sign(request: RequestBuilder, secret: &str, body: &[u8]) -> RequestBuilder {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let nonce = Uuid::new_v4().simple().to_string();
+
+    request
+        .header(TIMESTAMP_HEADER, timestamp)
+        .header(NONCE_HEADER, &nonce)
+        .header(
+            SIGNATURE_HEADER,
+            signature(secret, &signed_payload(timestamp, &nonce, body)),
+        )
+}
+
+fn signed_payload(timestamp: u64, nonce: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{timestamp}.{nonce}.").into_bytes();
+    payload.extend_from_slice(body);
+
+    payload
+}
+
+/// The value of the signature header: `sha256=` followed by the hex HMAC-SHA256 of `payload`.
+fn signature(secret: &str, payload: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(payload);
+
+    format!("sha256={}", Hmac::<sha256::Hash>::from_engine(engine))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_are_signed_with_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn the_timestamp_and_nonce_are_signed() {
+        let body = br#"{"id":1}"#;
+
+        assert_eq!(
+            signed_payload(1731715200, "0f1e2d", body),
+            br#"1731715200.0f1e2d.{"id":1}"#.to_vec()
+        );
+        assert_ne!(
+            signature("secret", &signed_payload(1731715200, "0f1e2d", body)),
+            signature("secret", &signed_payload(1731715200, "a1b2c3", body))
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect,
+};
+use sqlx::PgPool;
+use tokio::{
+    net::lookup_host,
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, warn};
+use url::{Host, Url};
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::{database, events::PoolEvent, util::explorer::Explorer};
+
+use super::{
+    http::{StakerWebhookEvent, WebhookBody, WebhookMessage},
+    signature,
+};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Rejects an endpoint that a staker can't register, so that the pool can't be made to send
+/// requests into its own network: the endpoint must be an https URL of which the host is not,
+/// and doesn't resolve to, a loopback, private or link-local address.
+pub async fn check_endpoint(endpoint: &Url) -> Result<()> {
+    match check_url(endpoint)? {
+        Some(domain) => resolve_public(domain).await.map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// Checks the scheme of an endpoint and its host if that is an IP address. Returns the domain
+/// that still has to be resolved otherwise.
+fn check_url(endpoint: &Url) -> Result<Option<&str>> {
+    if endpoint.scheme() != "https" {
+        bail!("the webhook endpoint must be an https URL");
+    }
+
+    let ip = match endpoint.host() {
+        Some(Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(Host::Ipv6(ip)) => IpAddr::V6(ip),
+        Some(Host::Domain(domain)) => return Ok(Some(domain)),
+        None => bail!("the webhook endpoint has no host"),
+    };
+
+    if !is_public(&ip) {
+        bail!("the webhook endpoint must not be a local address");
+    }
+
+    Ok(None)
+}
+
+/// Resolves `domain`, failing if it is local or if any of its addresses is not public.
+async fn resolve_public(domain: &str) -> Result<Vec<SocketAddr>> {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    if domain == "localhost" || domain.ends_with(".localhost") {
+        bail!("the webhook endpoint must not be a local address");
+    }
+
+    let addresses = lookup_host((domain.as_str(), 0))
+        .await
+        .with_context(|| format!("could not resolve the webhook endpoint {domain}"))?
+        .collect::<Vec<_>>();
+
+    if addresses.is_empty() || !addresses.iter().all(|address| is_public(&address.ip())) {
+        bail!("the webhook endpoint must not be a local address");
+    }
+
+    Ok(addresses)
+}
+
+/// Resolves the hosts of the endpoints at every delivery, to public addresses only, so that a
+/// domain that resolved to a public address when it was registered can't be pointed into the
+/// network of the pool afterwards.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let domain = name.as_str().to_string();
+
+        Box::pin(async move {
+            let addresses: Addrs = Box::new(resolve_public(&domain).await?.into_iter());
+
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(addresses)
+        })
+    }
+}
+
+/// Whether `ip` is reachable on the internet, as opposed to a loopback, private, link-local or
+/// otherwise reserved address.
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+
+            !(first == 0
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // reserved, including the broadcast address
+                || first >= 240
+                // shared address space
+                || (first == 100 && (second & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(&IpAddr::V4(ip)),
+            None => {
+                let segments = ip.segments();
+
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // NAT64, which reaches IPv4 addresses through a translator
+                    || (segments[0] == 0x64 && segments[1] == 0xff9b)
+                    // unique local
+                    || (segments[0] & 0xfe00) == 0xfc00
+                    // link-local
+                    || (segments[0] & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Returns the stakers that an event is about, with the kind of event and the message that each
+/// of them gets.
+fn staker_messages(event: PoolEvent) -> Vec<(Address, StakerWebhookEvent, WebhookMessage)> {
+    let (identity_address, kind) = match &event {
+        PoolEvent::StakeFound { stake, .. } => {
+            (stake.found_by.clone(), StakerWebhookEvent::StakeFound)
+        }
+        PoolEvent::StakeMatured(stake) => {
+            (stake.found_by.clone(), StakerWebhookEvent::StakeMatured)
+        }
+        PoolEvent::NewStaker(staker)
+        | PoolEvent::LeavingStaker(staker)
+        | PoolEvent::ExpiredStaker(staker)
+        | PoolEvent::SettlementScheduled { staker, .. }
+        | PoolEvent::StakerBanned { staker, .. }
+        | PoolEvent::PrimaryAddressRotation { staker, .. } => (
+            staker.identity_address.clone(),
+            StakerWebhookEvent::StakerStatus,
+        ),
+        PoolEvent::StakerDeparted(statement) => (
+            statement.identity_address.clone(),
+            StakerWebhookEvent::StakerStatus,
+        ),
+        // a staker only learns what it was paid itself
+        PoolEvent::PaymentSent { txid, items, .. } => {
+            return items
+                .iter()
+                .map(|item| {
+                    (
+                        item.identity_address.clone(),
+                        StakerWebhookEvent::PaymentSent,
+                        WebhookMessage::StakerPaid {
+                            identity_address: item.identity_address.clone(),
+                            txid: *txid,
+                            amount: item.amount,
+                        },
+                    )
+                })
+                .collect();
+        }
+        _ => return vec![],
+    };
+
+    WebhookMessage::from_event(event)
+        .map(|msg| vec![(identity_address, kind, msg)])
+        .unwrap_or_default()
+}
+
+/// Sends the events about a staker to the endpoint that the staker registered, if the staker
+/// selected the kind of event.
+///
+/// Unlike the webhooks of the pool, a failed delivery is not retried.
+pub struct StakerWebhookSubscriber {
+    pool: PgPool,
+    currency_address: Address,
+    client: reqwest::Client,
+    events: broadcast::Receiver<PoolEvent>,
+    explorer: Option<Explorer>,
+}
+
+impl StakerWebhookSubscriber {
+    pub fn new(
+        pool: PgPool,
+        currency_address: Address,
+        events: broadcast::Receiver<PoolEvent>,
+    ) -> Result<Self> {
+        // a redirect or a proxy could lead past the checks of the endpoint
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+
+        Ok(Self {
+            pool,
+            currency_address,
+            client,
+            events,
+            explorer: None,
+        })
+    }
+
+    /// Adds links to the block explorer of the chain to the messages.
+    pub fn with_explorer(self, explorer: Option<Explorer>) -> Self {
+        Self { explorer, ..self }
+    }
+
+    async fn handle(&self, event: PoolEvent) -> Result<()> {
+        for (identity_address, kind, msg) in staker_messages(event) {
+            let Some(webhook) =
+                database::get_staker_webhook(&self.pool, &self.currency_address, &identity_address)
+                    .await?
+            else {
+                continue;
+            };
+            if !webhook.events.contains(&kind) {
+                continue;
+            }
+            // endpoints that were stored before they were checked
+            if let Err(e) = check_url(&webhook.endpoint) {
+                warn!(endpoint = %webhook.endpoint, error = ?e, "refused to deliver staker webhook");
+
+                continue;
+            }
+
+            let body = serde_json::to_vec(&WebhookBody::new(msg, self.explorer.as_ref()))?;
+            let mut request = self
+                .client
+                .post(webhook.endpoint.clone())
+                .header(CONTENT_TYPE, "application/json");
+            if let Some(secret) = &webhook.secret {
+                request = signature::sign(request, secret, &body);
+            }
+            let request = request.body(body);
+            let endpoint = webhook.endpoint;
+
+            // a slow endpoint of one staker doesn't hold up the others
+            tokio::spawn(async move {
+                match request.send().await.and_then(|res| res.error_for_status()) {
+                    Ok(_) => debug!(%endpoint, "delivered staker webhook"),
+                    Err(e) => warn!(%endpoint, error = ?e, "could not deliver staker webhook"),
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for StakerWebhookSubscriber {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                event = self.events.recv() => match event {
+                    Ok(event) => {
+                        if let Err(e) = self.handle(event).await {
+                            tracing::error!(error = ?e, "Could not send a staker webhook");
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        warn!(missed = n, "staker webhooks fell behind, events were dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_public_addresses_are_public() {
+        for ip in ["1.1.1.1", "2606:4700:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(is_public(&ip.parse().unwrap()), "{ip}");
+        }
+
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "240.0.0.1",
+            "224.0.0.1",
+            "64:ff9b::7f00:1",
+            "ff02::1",
+            "::",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(&ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn local_and_plain_http_endpoints_are_rejected() {
+        for endpoint in [
+            "http://1.1.1.1/hook",
+            "https://127.0.0.1/hook",
+            "https://[::1]/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.1:8443/hook",
+            "https://localhost/hook",
+            "https://pool.localhost/hook",
+        ] {
+            assert!(
+                check_endpoint(&Url::parse(endpoint).unwrap())
+                    .await
+                    .is_err(),
+                "{endpoint}"
+            );
+        }
+
+        assert!(check_endpoint(&Url::parse("https://1.1.1.1/hook").unwrap())
+            .await
+            .is_ok());
+
+        // the resolver of the deliveries refuses them too
+        assert!(resolve_public("localhost").await.is_err());
+    }
+}
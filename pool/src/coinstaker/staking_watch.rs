@@ -0,0 +1,243 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error, info, warn};
+use vrsc_rpc::client::{Client as VerusClient, RpcApi};
+
+use crate::events::{EventBus, PoolEvent};
+
+use super::{coinstaker::CoinStakerMessage, halt::HaltFlag, ChainConfig, StakingWatchConfig};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StakingTransition {
+    Stopped { height: u64 },
+    Resumed { height: u64, stopped_for_secs: u64 },
+}
+
+/// Decides from the staking status it observes whether the daemon stopped or resumed staking,
+/// and how long to wait before checking again.
+#[derive(Debug, Clone)]
+pub struct StakingTracker {
+    check_interval_in_secs: u64,
+    max_backoff_in_secs: u64,
+    /// The unix timestamp (in seconds) at which the daemon was first seen not staking.
+    stopped_since: Option<u64>,
+    /// The number of checks in a row that found the daemon not staking or unreachable.
+    failed_checks: u32,
+}
+
+impl StakingTracker {
+    pub fn new(check_interval_in_secs: u64, max_backoff_in_secs: u64) -> Self {
+        Self {
+            check_interval_in_secs,
+            max_backoff_in_secs,
+            stopped_since: None,
+            failed_checks: 0,
+        }
+    }
+
+    /// Observes the staking status at `now`. Returns a transition if the daemon stopped or
+    /// resumed staking.
+    pub fn observe(&mut self, staking: bool, height: u64, now: u64) -> Option<StakingTransition> {
+        match (staking, self.stopped_since) {
+            (true, Some(since)) => {
+                self.stopped_since = None;
+                self.failed_checks = 0;
+
+                Some(StakingTransition::Resumed {
+                    height,
+                    stopped_for_secs: now.saturating_sub(since),
+                })
+            }
+            (true, None) => {
+                self.failed_checks = 0;
+
+                None
+            }
+            (false, Some(_)) => {
+                self.failed_checks += 1;
+
+                None
+            }
+            (false, None) => {
+                self.stopped_since = Some(now);
+                self.failed_checks = 1;
+
+                Some(StakingTransition::Stopped { height })
+            }
+        }
+    }
+
+    /// A check that could not reach the daemon backs off like a daemon that is not staking.
+    pub fn observe_unreachable(&mut self) {
+        self.failed_checks += 1;
+    }
+
+    /// The time until the next check, which doubles with every failed check in a row, up to
+    /// `max_backoff_in_secs`.
+    pub fn next_check_in(&self) -> Duration {
+        let factor = 2u64.saturating_pow(self.failed_checks.saturating_sub(1));
+        let secs = if self.failed_checks == 0 {
+            self.check_interval_in_secs
+        } else {
+            self.check_interval_in_secs
+                .saturating_mul(factor)
+                .min(self.max_backoff_in_secs)
+        };
+
+        Duration::from_secs(secs)
+    }
+}
+
+/// Watches whether the daemon of a currency is staking, so the pool survives a restart of the
+/// daemon.
+///
+/// No work is added while the daemon is not staking, but blocks and maturing stakes are still
+/// processed. Staking is enabled again if the pool was started with staking enabled.
+pub struct StakingWatcher {
+    chain_config: ChainConfig,
+    coinstaker: mpsc::Sender<CoinStakerMessage>,
+    events: EventBus,
+    halt: HaltFlag,
+    tracker: StakingTracker,
+    /// Whether the pool was started with staking enabled, and staking should be enabled again.
+    enable_staking: bool,
+}
+
+impl StakingWatcher {
+    pub fn new(
+        config: StakingWatchConfig,
+        chain_config: ChainConfig,
+        coinstaker: mpsc::Sender<CoinStakerMessage>,
+        events: EventBus,
+        halt: HaltFlag,
+        enable_staking: bool,
+    ) -> Self {
+        let tracker =
+            StakingTracker::new(config.check_interval_in_secs, config.max_backoff_in_secs);
+
+        Self {
+            chain_config,
+            coinstaker,
+            events,
+            halt,
+            tracker,
+            enable_staking,
+        }
+    }
+
+    async fn check(&mut self) -> Result<()> {
+        let client: VerusClient = (&self.chain_config).try_into()?;
+        let mining_info = client.get_mining_info()?;
+        let height = client.get_blockchain_info()?.blocks;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        match self.tracker.observe(mining_info.staking, height, now) {
+            Some(StakingTransition::Stopped { height }) => {
+                warn!(%height, "daemon stopped staking, not counting work");
+
+                self.events
+                    .publish(PoolEvent::DaemonStakingStopped { height });
+            }
+            Some(StakingTransition::Resumed {
+                height,
+                stopped_for_secs,
+            }) => {
+                info!(%height, %stopped_for_secs, "daemon resumed staking");
+
+                self.events.publish(PoolEvent::DaemonStakingResumed {
+                    height,
+                    stopped_for_secs,
+                });
+            }
+            None => debug!(staking = %mining_info.staking, "staking status checked"),
+        }
+
+        if !mining_info.staking && self.enable_staking {
+            debug!("trying to enable staking again");
+
+            self.coinstaker
+                .send(CoinStakerMessage::SetStaking(true))
+                .await
+                .context("Could not send Coinstaker message")?;
+        }
+
+        Ok(())
+    }
+
+    async fn keep_checking(&mut self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            // staking is disabled on purpose while the chain is halted
+            if self.halt.is_halted() {
+                debug!("chain is halted, not checking the staking status");
+            } else if let Err(e) = self.check().await {
+                error!(error = ?e, "Failed to check the staking status");
+
+                self.tracker.observe_unreachable();
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(self.tracker.next_check_in()) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for StakingWatcher {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_checking(&subsys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_a_stop_once_and_the_resume_with_its_duration() {
+        let mut tracker = StakingTracker::new(60, 900);
+
+        assert_eq!(tracker.observe(true, 100, 0), None);
+        assert_eq!(
+            tracker.observe(false, 101, 60),
+            Some(StakingTransition::Stopped { height: 101 })
+        );
+        assert_eq!(tracker.observe(false, 102, 120), None);
+        assert_eq!(
+            tracker.observe(true, 105, 360),
+            Some(StakingTransition::Resumed {
+                height: 105,
+                stopped_for_secs: 300
+            })
+        );
+        assert_eq!(tracker.observe(true, 106, 420), None);
+    }
+
+    #[test]
+    fn backs_off_while_the_daemon_is_not_staking() {
+        let mut tracker = StakingTracker::new(60, 900);
+
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(60));
+
+        tracker.observe(false, 100, 0);
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(60));
+        tracker.observe(false, 100, 60);
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(120));
+        tracker.observe_unreachable();
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(240));
+
+        for _ in 0..10 {
+            tracker.observe_unreachable();
+        }
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(900));
+
+        tracker.observe(true, 101, 5000);
+        assert_eq!(tracker.next_check_in(), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use rust_decimal::Decimal;
+use vrsc_rpc::bitcoin::BlockHash;
+
+/// What happened while processing a single block, and how long every phase took.
+///
+/// One summary is logged, stored and published for every processed block, so that operators can
+/// follow the per-block pipeline without turning on trace logging.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSummary {
+    pub block_hash: BlockHash,
+    pub block_height: u64,
+    /// The number of stakers that got work added for this block.
+    pub stakers_counted: u64,
+    pub shares_added: Decimal,
+    /// The number of VerusIDs of which the staker status was checked.
+    pub identities_checked: u64,
+    pub stake_found: bool,
+    /// Work is only added while the daemon is staking.
+    pub daemon_staking: bool,
+    /// The duration of every phase, in the order they ran.
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl BlockSummary {
+    pub fn new(block_hash: BlockHash, block_height: u64) -> Self {
+        Self {
+            block_hash,
+            block_height,
+            stakers_counted: 0,
+            shares_added: Decimal::ZERO,
+            identities_checked: 0,
+            stake_found: false,
+            daemon_staking: false,
+            phases: vec![],
+        }
+    }
+
+    /// Records the time since `started` as the duration of `phase`, and starts the next phase.
+    pub fn phase_done(&mut self, phase: &'static str, started: &mut Instant) {
+        let now = Instant::now();
+        self.phases.push((phase, now.duration_since(*started)));
+        *started = now;
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// The duration of every phase in milliseconds, as a JSON object.
+    pub fn phases_json(&self) -> String {
+        let phases = self
+            .phases
+            .iter()
+            .map(|(phase, duration)| (*phase, duration.as_millis() as u64))
+            .collect::<BTreeMap<_, _>>();
+
+        serde_json::to_string(&phases).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn phases_add_up_to_the_total() {
+        let mut summary = BlockSummary::new(
+            BlockHash::from_str("00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0")
+                .unwrap(),
+            513251,
+        );
+
+        summary.phases = vec![
+            ("stakers", Duration::from_millis(12)),
+            ("work", Duration::from_millis(30)),
+        ];
+
+        assert_eq!(summary.total_duration(), Duration::from_millis(42));
+        assert_eq!(summary.phases_json(), r#"{"stakers":12,"work":30}"#);
+
+        let mut started = Instant::now() - Duration::from_millis(5);
+        summary.phase_done("stake", &mut started);
+
+        assert_eq!(summary.phases.len(), 3);
+        assert!(summary.phases[2].1 >= Duration::from_millis(5));
+    }
+}
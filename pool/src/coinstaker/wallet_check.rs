@@ -0,0 +1,207 @@
+use anyhow::Result;
+use tracing::debug;
+use vrsc_rpc::{
+    client::{Client as VerusClient, RpcApi},
+    json::{
+        vrsc::{Amount, SignedAmount},
+        GetTransactionResultDetailCategory,
+    },
+};
+
+use crate::util::verus::coinbase_txid;
+
+use super::constants::Stake;
+
+/// How the wallet of the daemon sees the coinbase transaction of a stake.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletCoinbase {
+    pub category: WalletCategory,
+    pub amount: Amount,
+    pub confirmations: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletCategory {
+    Generate,
+    Immature,
+    Orphan,
+    Other,
+}
+
+/// Looks up the coinbase of a matured stake in the wallet of the daemon, and returns why the
+/// wallet disagrees with the records of the pool, if it does.
+///
+/// A wallet that was restored from an old backup, or that was replaced, doesn't know about
+/// stakes that the pool did record, so the rewards of these stakes can't be paid.
+///
+/// A coinbase needs `maturity_confirmations` before the wallet can spend it.
+pub fn check_stake_in_wallet(
+    client: &VerusClient,
+    stake: &Stake,
+    maturity_confirmations: u32,
+) -> Result<Option<String>> {
+    let block = client.get_block(&stake.block_hash, 2)?;
+    let txid = coinbase_txid(&block)?;
+
+    let wallet_coinbase = match client.get_transaction(&txid, None) {
+        Ok(transaction) => {
+            let details = transaction
+                .details
+                .iter()
+                .filter(|detail| {
+                    matches!(
+                        detail.category,
+                        GetTransactionResultDetailCategory::Generate
+                            | GetTransactionResultDetailCategory::Immature
+                            | GetTransactionResultDetailCategory::Orphan
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let category = match details.first().map(|detail| &detail.category) {
+                Some(GetTransactionResultDetailCategory::Generate) => WalletCategory::Generate,
+                Some(GetTransactionResultDetailCategory::Immature) => WalletCategory::Immature,
+                Some(GetTransactionResultDetailCategory::Orphan) => WalletCategory::Orphan,
+                _ => WalletCategory::Other,
+            };
+
+            let amount = details
+                .iter()
+                .fold(SignedAmount::ZERO, |acc, detail| acc + detail.amount)
+                .to_unsigned()
+                .unwrap_or(Amount::ZERO);
+
+            Some(WalletCoinbase {
+                category,
+                amount,
+                confirmations: transaction.info.confirmations as i64,
+            })
+        }
+        Err(e) => {
+            debug!(%txid, error = ?e, "coinbase of stake not found in wallet");
+
+            None
+        }
+    };
+
+    Ok(wallet_disagreement(
+        stake,
+        wallet_coinbase.as_ref(),
+        maturity_confirmations,
+    ))
+}
+
+/// Compares a matured stake with the wallet view of its coinbase.
+pub fn wallet_disagreement(
+    stake: &Stake,
+    wallet: Option<&WalletCoinbase>,
+    maturity_confirmations: u32,
+) -> Option<String> {
+    let Some(wallet) = wallet else {
+        return Some(format!(
+            "the wallet does not know the coinbase of the stake at height {}",
+            stake.block_height
+        ));
+    };
+
+    if wallet.category != WalletCategory::Generate {
+        return Some(format!(
+            "the wallet sees the coinbase of the stake at height {} as {:?} instead of generated",
+            stake.block_height, wallet.category
+        ));
+    }
+
+    if wallet.amount != stake.amount {
+        return Some(format!(
+            "the wallet credits {} for the stake at height {}, the pool recorded {}",
+            wallet.amount.as_vrsc(),
+            stake.block_height,
+            stake.amount.as_vrsc()
+        ));
+    }
+
+    if wallet.confirmations < maturity_confirmations as i64 {
+        return Some(format!(
+            "the coinbase of the matured stake at height {} has only {} confirmations in the wallet",
+            stake.block_height, wallet.confirmations
+        ));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use vrsc_rpc::{
+        bitcoin::{BlockHash, Txid},
+        json::vrsc::Address,
+    };
+
+    use super::*;
+    use crate::coinstaker::constants::StakeStatus;
+
+    fn stake() -> Stake {
+        Stake {
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            block_hash: BlockHash::from_str(
+                "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+            )
+            .unwrap(),
+            block_height: 513251,
+            found_by: Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Matured,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn wallet_disagreements_are_found() {
+        let stake = stake();
+        let agreeing = WalletCoinbase {
+            category: WalletCategory::Generate,
+            amount: Amount::from_sat(600_000_000),
+            confirmations: 150,
+        };
+
+        assert_eq!(wallet_disagreement(&stake, Some(&agreeing), 100), None);
+        assert!(wallet_disagreement(&stake, None, 100).is_some());
+        assert!(wallet_disagreement(
+            &stake,
+            Some(&WalletCoinbase {
+                category: WalletCategory::Orphan,
+                ..agreeing.clone()
+            }),
+            100
+        )
+        .is_some());
+        assert!(wallet_disagreement(
+            &stake,
+            Some(&WalletCoinbase {
+                amount: Amount::from_sat(500_000_000),
+                ..agreeing.clone()
+            }),
+            100
+        )
+        .is_some());
+        assert!(wallet_disagreement(
+            &stake,
+            Some(&WalletCoinbase {
+                confirmations: 99,
+                ..agreeing.clone()
+            }),
+            100
+        )
+        .is_some());
+        assert!(wallet_disagreement(&stake, Some(&agreeing), 200).is_some());
+    }
+}
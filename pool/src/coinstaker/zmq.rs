@@ -1,38 +1,94 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use anyhow::Result;
 use futures_util::stream::StreamExt;
 use tokio::sync::mpsc;
-use tracing::error;
+use tracing::{error, info, warn};
 use vrsc_rpc::bitcoin::BlockHash;
 
 use super::coinstaker::CoinStakerMessage;
 
+/// How long the listener waits before it subscribes again after the socket failed. The wait
+/// doubles with every failed attempt, up to `MAX_RESUBSCRIBE_BACKOFF`.
+const MIN_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(60);
+/// How often the coinstaker checks for blocks of which the notification was missed.
+const GAP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Listens for new blocks on the ZMQ `hashblock` topic of the daemon at `host`, and sends the
+/// coinstaker the `message` of every block.
+///
+/// When the socket dies, the listener subscribes again with an increasing backoff. Blocks that
+/// are missed in the meantime are recovered by the gap check.
 pub(super) async fn tmq_block_listen(
+    host: String,
     port: u16,
     cx_tx: mpsc::Sender<CoinStakerMessage>,
+    message: fn(BlockHash) -> CoinStakerMessage,
+) -> Result<()> {
+    let mut backoff = MIN_RESUBSCRIBE_BACKOFF;
+
+    loop {
+        match subscribe(&host, port, &cx_tx, message, &mut backoff).await {
+            Ok(()) => warn!(%host, %port, "ZMQ socket closed, subscribing again"),
+            Err(e) => error!(%host, %port, error = ?e, "ZMQ socket failed, subscribing again"),
+        }
+
+        if cx_tx.is_closed() {
+            return Ok(());
+        }
+
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RESUBSCRIBE_BACKOFF);
+    }
+}
+
+/// Forwards the notifications of one subscription until its socket fails. The backoff is reset
+/// once the subscription delivered a block.
+async fn subscribe(
+    host: &str,
+    port: u16,
+    cx_tx: &mpsc::Sender<CoinStakerMessage>,
+    message: fn(BlockHash) -> CoinStakerMessage,
+    backoff: &mut Duration,
 ) -> Result<()> {
     let mut socket = tmq::subscribe(&tmq::Context::new())
-        .connect(&format!("tcp://127.0.0.1:{}", port))?
+        .connect(&format!("tcp://{}:{}", host, port))?
         .subscribe(b"hash")?;
+    info!(%host, %port, "subscribed to ZMQ block notifications");
+
+    while let Some(msg) = socket.next().await {
+        let Some(hash) = msg?.into_iter().nth(1) else {
+            error!("not a valid message!");
+            continue;
+        };
+
+        let block_hash = hash
+            .iter()
+            .map(|byte| format!("{:02x}", *byte))
+            .collect::<Vec<_>>()
+            .join("");
+
+        cx_tx
+            .send(message(BlockHash::from_str(&block_hash)?))
+            .await?;
+        *backoff = MIN_RESUBSCRIBE_BACKOFF;
+    }
+
+    Ok(())
+}
+
+/// Asks the coinstaker to check for missed blocks every `GAP_CHECK_INTERVAL`.
+pub(super) async fn gap_check(cx_tx: mpsc::Sender<CoinStakerMessage>) {
+    let mut interval = tokio::time::interval(GAP_CHECK_INTERVAL);
+    // the first tick completes right away, when there is nothing to check yet
+    interval.tick().await;
 
     loop {
-        if let Some(Ok(msg)) = socket.next().await {
-            if let Some(hash) = msg.into_iter().nth(1) {
-                let block_hash = hash
-                    .iter()
-                    .map(|byte| format!("{:02x}", *byte))
-                    .collect::<Vec<_>>()
-                    .join("");
-
-                cx_tx
-                    .send(CoinStakerMessage::Block(BlockHash::from_str(&block_hash)?))
-                    .await?;
-            } else {
-                error!("not a valid message!");
-            }
-        } else {
-            error!("no correct message received");
+        interval.tick().await;
+
+        if cx_tx.send(CoinStakerMessage::CheckForGaps).await.is_err() {
+            return;
         }
     }
 }
@@ -1,14 +1,22 @@
-use std::net::IpAddr;
+use std::{net::IpAddr, path::PathBuf};
 
 use anyhow::{anyhow, Result};
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
+use url::Url;
+
+use crate::notifications::Severity;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub application: AppConfig,
     pub database: DbConfig,
     pub http: HttpConfig,
+    #[serde(default)]
+    pub webhooks: WebhooksConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    pub chain_discovery: Option<ChainDiscoveryConfig>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -38,7 +46,6 @@ impl DbConfig {
         )
     }
 
-    #[allow(unused)]
     pub fn connection_string_without_db(&self) -> String {
         format!(
             "postgres://{}:{}@{}:{}",
@@ -54,6 +61,162 @@ impl DbConfig {
 pub struct HttpConfig {
     pub host: IpAddr,
     pub port: u16,
+    /// The keys of the operators, of which one is required in the `Authorization: Bearer` header
+    /// of the admin routes.
+    #[serde(default)]
+    pub admin_keys: Vec<Secret<String>>,
+}
+
+/// Limits the webhook deliveries of all currencies together.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhooksConfig {
+    /// The maximum number of webhook requests that are in flight at the same time.
+    #[serde(default = "default_max_concurrent_deliveries")]
+    pub max_concurrent_deliveries: usize,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_deliveries: default_max_concurrent_deliveries(),
+        }
+    }
+}
+
+fn default_max_concurrent_deliveries() -> usize {
+    32
+}
+
+/// Where the operator is notified of the events of every currency, next to the webhooks.
+///
+/// ```json
+/// "notifications": {
+///     "channels": [
+///         {
+///             "kind": "telegram",
+///             "min_severity": "critical",
+///             "bot_token": "123456:ABC-DEF",
+///             "chat_id": "-1001234567890"
+///         },
+///         {
+///             "kind": "email",
+///             "min_severity": "warning",
+///             "smtp_host": "smtp.example.com",
+///             "username": "pool@example.com",
+///             "password": "secret",
+///             "from": "Staking pool <pool@example.com>",
+///             "to": ["operator@example.com"]
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub channels: Vec<NotificationChannelConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotificationChannelConfig {
+    /// The lowest severity of which the channel is notified.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+    #[serde(flatten)]
+    pub channel: NotificationChannel,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Critical
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Email(EmailConfig),
+    Telegram(TelegramConfig),
+    Matrix(MatrixConfig),
+}
+
+/// Sends notifications by email over SMTP with TLS.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    465
+}
+
+/// Sends notifications as a Telegram bot, to a chat that the bot is a member of.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TelegramConfig {
+    pub bot_token: Secret<String>,
+    pub chat_id: String,
+}
+
+/// Sends notifications to a Matrix room, as the user of the access token.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MatrixConfig {
+    pub homeserver: Url,
+    pub access_token: Secret<String>,
+    pub room_id: String,
+}
+
+/// Discovers the PBaaS chains on the daemon of a configured currency and stakes them without a
+/// coin configuration file of their own.
+///
+/// ```json
+/// "chain_discovery": {
+///     "parent": "VRSC",
+///     "template": "discovery/pbaas.toml",
+///     "conf_path": "/home/verus/.verus/pbaas/{id}/{id}.conf",
+///     "include": ["vARRR", "vDEX"]
+/// }
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChainDiscoveryConfig {
+    /// The name or id of the configured currency of which the daemon lists the PBaaS chains.
+    pub parent: String,
+    /// A coin configuration that every discovered chain starts from. The currency id, the
+    /// currency name and the RPC and ZMQ settings of the chain are filled in.
+    pub template: PathBuf,
+    /// Where the daemon of a discovered chain keeps its configuration, with `{name}` and `{id}`
+    /// replaced by the name and the currency id of the chain.
+    pub conf_path: String,
+    /// The names of the chains to stake. All PBaaS chains are staked if empty.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// The names of the chains to never stake.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default = "default_discovery_interval_in_secs")]
+    pub interval_in_secs: u64,
+}
+
+impl ChainDiscoveryConfig {
+    pub fn matches(&self, name: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        (self.include.is_empty() || listed(&self.include)) && !listed(&self.exclude)
+    }
+
+    /// The path of the configuration file of the daemon of a chain.
+    pub fn conf_path(&self, name: &str, currency_id: &str) -> PathBuf {
+        self.conf_path
+            .replace("{name}", name)
+            .replace("{id}", currency_id)
+            .into()
+    }
+}
+
+fn default_discovery_interval_in_secs() -> u64 {
+    600
 }
 
 pub async fn app_config() -> Result<Config> {
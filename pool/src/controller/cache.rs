@@ -0,0 +1,235 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, oneshot,
+};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error, warn};
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::{
+    coinstaker::coinstaker::CoinStakerMessage,
+    events::{EventBus, PoolEvent},
+    http::constants::{StakingSupply, Stats},
+};
+
+/// How long a cached response is served. Every processed block refreshes it sooner, so this
+/// only bounds how stale it gets when a refresh fails.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Responses that are cached until they are older than the TTL.
+///
+/// Clones share the same entries.
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<K, Entry<V>>>>,
+}
+
+struct Entry<V> {
+    value: V,
+    refreshed_at: Instant,
+    requested_at: Instant,
+}
+
+impl<K, V> Clone for TtlCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            ttl: self.ttl,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::default(),
+        }
+    }
+
+    /// Returns the value of `key` if it is not older than the TTL.
+    pub fn get(&self, key: &K, now: Instant) -> Option<V> {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        let entry = entries.get_mut(key)?;
+        entry.requested_at = now;
+
+        (now.duration_since(entry.refreshed_at) <= self.ttl).then(|| entry.value.clone())
+    }
+
+    /// Stores the value of `key`. A new key counts as requested, a refresh of an existing key
+    /// doesn't.
+    pub fn insert(&self, key: K, value: V, now: Instant) {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        let requested_at = entries.get(&key).map_or(now, |entry| entry.requested_at);
+
+        entries.insert(
+            key,
+            Entry {
+                value,
+                refreshed_at: now,
+                requested_at,
+            },
+        );
+    }
+
+    /// Returns the keys that were requested within the TTL, and forgets the other keys so that
+    /// responses nobody asks for anymore are not refreshed.
+    pub fn requested_keys(&self, now: Instant) -> Vec<K> {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        entries.retain(|_, entry| now.duration_since(entry.requested_at) <= self.ttl);
+
+        entries.keys().cloned().collect()
+    }
+}
+
+/// The responses of a currency that call the daemon for every staker, so that dashboards that
+/// poll them don't put that load on the daemon with every request.
+#[derive(Clone)]
+pub struct ChainCache {
+    pub statistics: TtlCache<(), Stats>,
+    /// Per sorted and deduplicated set of identities.
+    pub staking_supply: TtlCache<Vec<Address>, StakingSupply>,
+}
+
+impl Default for ChainCache {
+    fn default() -> Self {
+        Self {
+            statistics: TtlCache::new(CACHE_TTL),
+            staking_supply: TtlCache::new(CACHE_TTL),
+        }
+    }
+}
+
+/// Refreshes the [`ChainCache`] of a currency after every processed block.
+pub struct CacheRefresher {
+    cache: ChainCache,
+    coinstaker: mpsc::Sender<CoinStakerMessage>,
+    events: broadcast::Receiver<PoolEvent>,
+}
+
+impl CacheRefresher {
+    pub fn new(
+        cache: ChainCache,
+        coinstaker: mpsc::Sender<CoinStakerMessage>,
+        events: &EventBus,
+    ) -> Self {
+        Self {
+            cache,
+            coinstaker,
+            events: events.subscribe(),
+        }
+    }
+
+    /// Computes the cached responses again. Only responses that were requested within the TTL
+    /// are refreshed.
+    async fn refresh(&self) -> Result<()> {
+        let now = Instant::now();
+
+        if !self.cache.statistics.requested_keys(now).is_empty() {
+            let (os_tx, os_rx) = oneshot::channel::<Stats>();
+
+            self.coinstaker
+                .send(CoinStakerMessage::GetStatistics(os_tx))
+                .await
+                .context("Could not send Coinstaker message")?;
+
+            let stats = os_rx.await.context("Sender dropped")?;
+            self.cache.statistics.insert((), stats, Instant::now());
+        }
+
+        for identity_addresses in self.cache.staking_supply.requested_keys(now) {
+            let (os_tx, os_rx) = oneshot::channel::<StakingSupply>();
+
+            self.coinstaker
+                .send(CoinStakerMessage::StakingSupply(
+                    os_tx,
+                    identity_addresses.clone(),
+                ))
+                .await
+                .context("Could not send Coinstaker message")?;
+
+            let supply = os_rx.await.context("Sender dropped")?;
+            self.cache
+                .staking_supply
+                .insert(identity_addresses, supply, Instant::now());
+        }
+
+        debug!("refreshed the cached responses");
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for CacheRefresher {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                event = self.events.recv() => match event {
+                    Ok(PoolEvent::BlockProcessed(_)) | Err(RecvError::Lagged(_)) => {
+                        // blocks that are processed in a burst, like during a catch-up, are
+                        // refreshed once
+                        while self.events.try_recv().is_ok() {}
+
+                        if let Err(e) = self.refresh().await {
+                            error!(error = ?e, "Could not refresh the cached responses");
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Closed) => {
+                        warn!("event bus closed, no longer refreshing the cached responses");
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_served_until_the_ttl_passed() {
+        let cache = TtlCache::<u8, u32>::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert_eq!(cache.get(&1, now), None);
+
+        cache.insert(1, 42, now);
+        assert_eq!(cache.get(&1, now + Duration::from_secs(60)), Some(42));
+        assert_eq!(cache.get(&1, now + Duration::from_secs(61)), None);
+
+        cache.insert(1, 43, now + Duration::from_secs(61));
+        assert_eq!(cache.get(&1, now + Duration::from_secs(62)), Some(43));
+    }
+
+    #[test]
+    fn only_requested_keys_are_refreshed() {
+        let cache = TtlCache::<u8, u32>::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        cache.insert(1, 42, now);
+        cache.insert(2, 42, now);
+        assert_eq!(cache.get(&1, now + Duration::from_secs(30)), Some(42));
+
+        // a refresh doesn't count as a request
+        cache.insert(2, 43, now + Duration::from_secs(30));
+
+        let keys = cache.requested_keys(now + Duration::from_secs(80));
+        assert_eq!(keys, vec![1]);
+        assert_eq!(cache.get(&2, now + Duration::from_secs(80)), None);
+    }
+}
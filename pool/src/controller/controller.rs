@@ -1,15 +1,57 @@
 use std::collections::HashMap;
 
-use crate::coinstaker::coinstaker::CoinStakerMessage;
+use crate::{
+    coinstaker::{coinstaker::CoinStakerMessage, http::Webhook},
+    events::EventBus,
+    http::constants::{StakingSupply, Stats},
+    metrics::Metrics,
+    payout_service::LatestReconciliation,
+};
 use tokio::sync::mpsc;
 use vrsc_rpc::json::vrsc::Address;
 
+use super::{ChainCache, Registry, Sessions, SingleFlight};
+
 pub struct Controller {
     pub database: String,
-    pub coin_stakers: HashMap<Address, mpsc::Sender<CoinStakerMessage>>,
+    pub coin_stakers: Registry<mpsc::Sender<CoinStakerMessage>>,
+    pub webhooks: Registry<Webhook>,
+    pub events: Registry<EventBus>,
+    pub metrics: Metrics,
+    /// Coalesces concurrent staking supply requests per currency and set of identities.
+    pub staking_supply: SingleFlight<(Address, Vec<Address>), StakingSupply>,
+    /// Coalesces concurrent statistics requests per currency.
+    pub statistics: SingleFlight<Address, Stats>,
+    /// The cached responses that are expensive to compute, per currency.
+    pub caches: Registry<ChainCache>,
+    /// The latest reconciliation of the wallet with the recorded payments, per currency.
+    pub reconciliations: Registry<LatestReconciliation>,
+    /// The stakers that logged in with their VerusID.
+    pub sessions: Sessions,
 }
 
 impl Controller {
+    pub fn new(
+        database: String,
+        coin_stakers: HashMap<Address, mpsc::Sender<CoinStakerMessage>>,
+        webhooks: HashMap<Address, Webhook>,
+        events: HashMap<Address, EventBus>,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            database,
+            coin_stakers: coin_stakers.into(),
+            webhooks: webhooks.into(),
+            events: events.into(),
+            metrics,
+            staking_supply: SingleFlight::default(),
+            statistics: SingleFlight::default(),
+            caches: Registry::default(),
+            reconciliations: Registry::default(),
+            sessions: Sessions::default(),
+        }
+    }
+
     pub fn version(&self) -> String {
         format!("{}", 0.1)
     }
@@ -1,3 +1,11 @@
+mod cache;
 mod controller;
+mod registry;
+mod sessions;
+mod single_flight;
 
+pub use cache::{CacheRefresher, ChainCache};
 pub use controller::Controller;
+pub use registry::Registry;
+pub use sessions::Sessions;
+pub use single_flight::SingleFlight;
@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use vrsc_rpc::json::vrsc::Address;
+
+/// A map per currency that can change while the pool runs, when chains are discovered or
+/// deactivated.
+///
+/// Clones share the same map. Lookups return clones of the values, so the lock is never held
+/// across an await point.
+#[derive(Debug)]
+pub struct Registry<T> {
+    entries: Arc<Mutex<HashMap<Address, T>>>,
+}
+
+impl<T> Clone for Registry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+        }
+    }
+}
+
+impl<T> From<HashMap<Address, T>> for Registry<T> {
+    fn from(entries: HashMap<Address, T>) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+}
+
+impl<T: Clone> Registry<T> {
+    pub fn get(&self, currency: &Address) -> Option<T> {
+        self.entries
+            .lock()
+            .expect("registry lock poisoned")
+            .get(currency)
+            .cloned()
+    }
+
+    pub fn contains(&self, currency: &Address) -> bool {
+        self.entries
+            .lock()
+            .expect("registry lock poisoned")
+            .contains_key(currency)
+    }
+
+    /// Returns the entries of every currency at this moment.
+    pub fn all(&self) -> Vec<(Address, T)> {
+        self.entries
+            .lock()
+            .expect("registry lock poisoned")
+            .iter()
+            .map(|(currency, value)| (currency.clone(), value.clone()))
+            .collect()
+    }
+
+    pub fn insert(&self, currency: Address, value: T) {
+        self.entries
+            .lock()
+            .expect("registry lock poisoned")
+            .insert(currency, value);
+    }
+
+    pub fn remove(&self, currency: &Address) -> Option<T> {
+        self.entries
+            .lock()
+            .expect("registry lock poisoned")
+            .remove(currency)
+    }
+}
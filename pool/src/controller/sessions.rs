@@ -0,0 +1,135 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use poollib::api::{LoginChallenge, SessionToken};
+use uuid::Uuid;
+use vrsc_rpc::json::vrsc::Address;
+
+/// A challenge must be signed within this time.
+const CHALLENGE_TTL_IN_SECS: u64 = 300;
+
+/// A session has to log in again after this time.
+const SESSION_TTL_IN_SECS: u64 = 3600;
+
+/// The login challenges and sessions of stakers that logged in with their VerusID.
+///
+/// Both are kept in memory only: a restart of the pool logs everyone out.
+#[derive(Default)]
+pub struct Sessions {
+    challenges: Mutex<HashMap<(Address, Address), LoginChallenge>>,
+    sessions: Mutex<HashMap<String, SessionToken>>,
+}
+
+impl Sessions {
+    /// Issues a new challenge for `identity_address`, replacing any previous one.
+    pub fn new_challenge(
+        &self,
+        currency_address: &Address,
+        identity_address: &Address,
+        now: u64,
+    ) -> LoginChallenge {
+        let challenge = LoginChallenge {
+            message: format!(
+                "Log in to the staking pool on {currency_address} as {identity_address}: {}",
+                Uuid::new_v4()
+            ),
+            expires_at: now + CHALLENGE_TTL_IN_SECS,
+        };
+
+        let mut challenges = self.challenges.lock().expect("challenges lock poisoned");
+        challenges.retain(|_, challenge| challenge.expires_at > now);
+        challenges.insert(
+            (currency_address.clone(), identity_address.clone()),
+            challenge.clone(),
+        );
+
+        challenge
+    }
+
+    /// Returns the message of the challenge of `identity_address`, if it didn't expire yet.
+    ///
+    /// A challenge can only be used once, whether its signature turns out to be valid or not.
+    pub fn take_challenge(
+        &self,
+        currency_address: &Address,
+        identity_address: &Address,
+        now: u64,
+    ) -> Option<String> {
+        self.challenges
+            .lock()
+            .expect("challenges lock poisoned")
+            .remove(&(currency_address.clone(), identity_address.clone()))
+            .filter(|challenge| challenge.expires_at > now)
+            .map(|challenge| challenge.message)
+    }
+
+    pub fn start_session(
+        &self,
+        currency_address: &Address,
+        identity_address: &Address,
+        now: u64,
+    ) -> SessionToken {
+        let session = SessionToken {
+            token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            currency_address: currency_address.clone(),
+            identity_address: identity_address.clone(),
+            expires_at: now + SESSION_TTL_IN_SECS,
+        };
+
+        let mut sessions = self.sessions.lock().expect("sessions lock poisoned");
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(session.token.clone(), session.clone());
+
+        session
+    }
+
+    /// Returns the session of `token`, if it didn't expire yet.
+    pub fn session(&self, token: &str, now: u64) -> Option<SessionToken> {
+        self.sessions
+            .lock()
+            .expect("sessions lock poisoned")
+            .get(token)
+            .filter(|session| session.expires_at > now)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn challenges_are_used_once_and_sessions_expire() {
+        let sessions = Sessions::default();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let challenge = sessions.new_challenge(&currency_address, &alice, 1000);
+        assert_eq!(
+            sessions.take_challenge(&currency_address, &alice, 1100),
+            Some(challenge.message)
+        );
+        assert_eq!(
+            sessions.take_challenge(&currency_address, &alice, 1100),
+            None
+        );
+
+        sessions.new_challenge(&currency_address, &alice, 1000);
+        assert_eq!(
+            sessions.take_challenge(&currency_address, &alice, 1000 + CHALLENGE_TTL_IN_SECS),
+            None
+        );
+
+        let session = sessions.start_session(&currency_address, &alice, 1000);
+        assert_eq!(
+            sessions.session(&session.token, 1100),
+            Some(session.clone())
+        );
+        assert_eq!(
+            sessions.session(&session.token, 1000 + SESSION_TTL_IN_SECS),
+            None
+        );
+        assert_eq!(sessions.session("unknown", 1100), None);
+    }
+}
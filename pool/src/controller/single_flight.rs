@@ -0,0 +1,139 @@
+use std::{collections::HashMap, future::Future, hash::Hash, sync::Mutex};
+
+use anyhow::{anyhow, Result};
+use tokio::sync::oneshot;
+
+/// Coalesces concurrent identical requests into one.
+///
+/// The first caller for a key runs the request. Callers with the same key that arrive while
+/// that request is in flight don't run the request themselves, but wait for and share its result.
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, Vec<oneshot::Sender<V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> SingleFlight<K, V> {
+    pub async fn run<F, Fut>(&self, key: K, request: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        let waiting = {
+            let mut in_flight = self.in_flight.lock().expect("single flight lock poisoned");
+
+            if let Some(waiters) = in_flight.get_mut(&key) {
+                let (tx, rx) = oneshot::channel();
+                waiters.push(tx);
+
+                Some(rx)
+            } else {
+                in_flight.insert(key.clone(), vec![]);
+
+                None
+            }
+        };
+
+        if let Some(rx) = waiting {
+            return rx
+                .await
+                .map_err(|_| anyhow!("the in-flight request failed"));
+        }
+
+        // removes the key when the request fails or the caller is dropped, so that the next
+        // caller runs the request again. Waiters are dropped with it and receive an error.
+        let guard = InFlight {
+            single_flight: self,
+            key: Some(key),
+        };
+
+        let value = request().await?;
+
+        for waiter in guard.finish() {
+            let _ = waiter.send(value.clone());
+        }
+
+        Ok(value)
+    }
+
+    fn remove(&self, key: &K) -> Vec<oneshot::Sender<V>> {
+        self.in_flight
+            .lock()
+            .expect("single flight lock poisoned")
+            .remove(key)
+            .unwrap_or_default()
+    }
+}
+
+struct InFlight<'a, K: Hash + Eq + Clone, V: Clone> {
+    single_flight: &'a SingleFlight<K, V>,
+    key: Option<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> InFlight<'_, K, V> {
+    fn finish(mut self) -> Vec<oneshot::Sender<V>> {
+        let key = self.key.take().expect("key is only taken once");
+
+        self.single_flight.remove(&key)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Drop for InFlight<'_, K, V> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.single_flight.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_requests_share_result() {
+        let single_flight = SingleFlight::<u8, u32>::default();
+        let calls = AtomicU32::new(0);
+
+        let request = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            Ok(42)
+        };
+
+        let (a, b) = tokio::join!(single_flight.run(1, request), single_flight.run(1, request));
+
+        assert_eq!(a.unwrap(), 42);
+        assert_eq!(b.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // the key is released once the request finished
+        single_flight.run(1, request).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_request_is_released() {
+        let single_flight = SingleFlight::<u8, u32>::default();
+
+        let res = single_flight
+            .run(1, || async { Err(anyhow!("daemon unavailable")) })
+            .await;
+        assert!(res.is_err());
+
+        let res = single_flight.run(1, || async { Ok(1) }).await;
+        assert_eq!(res.unwrap(), 1);
+    }
+}
@@ -7,11 +7,19 @@ use vrsc_rpc::{
 };
 
 use crate::{
+    accounting::{AccountingEntry, AccountingEntryKind},
     coinstaker::{
-        constants::{Stake, StakeStatus, Staker},
+        constants::{
+            DelegatedAddress, FraudEvidence, KeyRotation, ParticipationKind, RotationProgress,
+            Round, Stake, StakeStatus, Staker, StakerActivity, StakerActivityKind, StakerStatement,
+        },
         StakerStatus,
     },
-    payout_service::{PayoutMember, Worker},
+    http::constants::{NetworkStats, PoolLuck},
+    payout_service::{
+        Payment, PaymentStatus, PayoutMember, PayoutShadowDiff, PayoutSummary, StakerLiability,
+        Worker,
+    },
 };
 
 pub struct DbStaker {
@@ -21,6 +29,10 @@ pub struct DbStaker {
     pub(super) min_payout: i64,
     pub(super) status: StakerStatus,
     pub(super) fee: Decimal,
+    pub(super) participation_kind: ParticipationKind,
+    pub(super) payout_address: Option<String>,
+    pub(super) created_at: i64,
+    pub(super) updated_at: i64,
 }
 
 impl TryFrom<DbStaker> for Staker {
@@ -36,6 +48,15 @@ impl TryFrom<DbStaker> for Staker {
             min_payout: Amount::from_sat(value.min_payout as u64),
             status: value.status,
             fee: value.fee,
+            participation_kind: value.participation_kind,
+            payout_address: value
+                .payout_address
+                .map(|address| Address::from_str(&address))
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            forecast: None,
+            created_at: value.created_at as u64,
+            updated_at: value.updated_at as u64,
         };
 
         Ok(staker)
@@ -52,6 +73,8 @@ pub struct DbStake {
     pub(super) source_vout_num: i32,
     pub(super) source_amount: i64,
     pub(super) status: StakeStatus,
+    pub(super) created_at: i64,
+    pub(super) updated_at: i64,
 }
 
 impl TryFrom<DbStake> for Stake {
@@ -72,6 +95,8 @@ impl TryFrom<DbStake> for Stake {
             source_amount: Amount::from_sat(value.source_amount as u64),
             status: value.status,
             amount: Amount::from_sat(value.amount as u64),
+            created_at: value.created_at as u64,
+            updated_at: value.updated_at as u64,
         };
 
         Ok(stake)
@@ -108,6 +133,8 @@ pub struct DbPayoutMember {
     pub(super) reward: i64,
     pub(super) fee: i64,
     pub(super) txid: Option<String>,
+    pub(super) created_at: i64,
+    pub(super) updated_at: i64,
 }
 
 impl TryFrom<DbPayoutMember> for PayoutMember {
@@ -130,8 +157,440 @@ impl TryFrom<DbPayoutMember> for PayoutMember {
                 .map(|txid| Txid::from_str(&txid))
                 .transpose()
                 .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            created_at: value.created_at as u64,
+            updated_at: value.updated_at as u64,
+        };
+
+        Ok(payout)
+    }
+}
+
+pub struct DbPayoutSummary {
+    pub(super) currency_address: String,
+    pub(super) block_hash: String,
+    pub(super) block_height: i64,
+    pub(super) amount: i64,
+    pub(super) work: Decimal,
+    pub(super) fee: i64,
+    pub(super) amount_paid: i64,
+    pub(super) n_subs: i64,
+    pub(super) created_at: i64,
+}
+
+impl TryFrom<DbPayoutSummary> for PayoutSummary {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbPayoutSummary) -> Result<Self, Self::Error> {
+        let payout = Self {
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_hash: BlockHash::from_str(&value.block_hash)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_height: value.block_height as u64,
+            amount: Amount::from_sat(value.amount as u64),
+            work: value.work,
+            fee: Amount::from_sat(value.fee as u64),
+            amount_paid: Amount::from_sat(value.amount_paid as u64),
+            n_members: value.n_subs as u64,
+            created_at: value.created_at as u64,
         };
 
         Ok(payout)
     }
 }
+
+pub struct DbRound {
+    pub(super) id: i64,
+    pub(super) currency_address: String,
+    pub(super) block_hash: Option<String>,
+    pub(super) block_height: i64,
+}
+
+impl TryFrom<DbRound> for Round {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbRound) -> Result<Self, Self::Error> {
+        let round = Self {
+            id: value.id as u64,
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_hash: value
+                .block_hash
+                .map(|block_hash| BlockHash::from_str(&block_hash))
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_height: value.block_height as u64,
+        };
+
+        Ok(round)
+    }
+}
+
+pub struct DbNetworkStats {
+    pub(super) block_height: i64,
+    pub(super) block_hash: String,
+    pub(super) staking_supply: f64,
+    pub(super) difficulty: f64,
+    pub(super) pos_ratio: f64,
+}
+
+impl TryFrom<DbNetworkStats> for NetworkStats {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbNetworkStats) -> Result<Self, Self::Error> {
+        let network_stats = Self {
+            block_height: value.block_height as u64,
+            block_hash: BlockHash::from_str(&value.block_hash)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            staking_supply: value.staking_supply,
+            difficulty: value.difficulty,
+            pos_ratio: value.pos_ratio,
+        };
+
+        Ok(network_stats)
+    }
+}
+
+pub struct DbPoolLuck {
+    pub(super) from_height: Option<i64>,
+    pub(super) to_height: Option<i64>,
+    pub(super) expected_stakes: f64,
+    pub(super) actual_stakes: i64,
+    pub(super) period_secs: Option<f64>,
+    pub(super) stake_span_secs: Option<f64>,
+}
+
+impl DbPoolLuck {
+    /// Returns `None` if there are no network snapshots in the period.
+    pub(super) fn into_pool_luck(self) -> Option<PoolLuck> {
+        let (from_height, to_height) = self.from_height.zip(self.to_height)?;
+        let actual_stakes = self.actual_stakes as u64;
+
+        Some(PoolLuck {
+            from_height: from_height as u64,
+            to_height: to_height as u64,
+            expected_stakes: self.expected_stakes,
+            actual_stakes,
+            luck: (self.expected_stakes > 0.0)
+                .then(|| actual_stakes as f64 / self.expected_stakes * 100.0),
+            // n stakes have n - 1 intervals between them
+            mean_time_between_stakes_in_secs: self
+                .stake_span_secs
+                .filter(|_| actual_stakes > 1)
+                .map(|span| (span / (actual_stakes - 1) as f64) as u64),
+            expected_time_between_stakes_in_secs: self
+                .period_secs
+                .filter(|_| self.expected_stakes > 0.0)
+                .map(|period| (period / self.expected_stakes) as u64),
+        })
+    }
+}
+
+pub struct DbFraudEvidence {
+    pub(super) currency_address: String,
+    pub(super) block_hash: String,
+    pub(super) block_height: i64,
+    pub(super) identity_address: String,
+    pub(super) source_txid: String,
+    pub(super) source_vout_num: i32,
+    pub(super) spend_txid: String,
+    pub(super) spend_block_hash: Option<String>,
+    pub(super) spend_tx: String,
+    pub(super) proof: Option<String>,
+    pub(super) competing_txid: Option<String>,
+    pub(super) competing_source_txid: Option<String>,
+    pub(super) competing_source_vout_num: Option<i64>,
+    pub(super) created_at: i64,
+}
+
+impl TryFrom<DbFraudEvidence> for FraudEvidence {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbFraudEvidence) -> Result<Self, Self::Error> {
+        let txid = |txid: &str| Txid::from_str(txid).map_err(|e| sqlx::Error::Decode(e.into()));
+
+        let evidence = Self {
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_hash: BlockHash::from_str(&value.block_hash)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_height: value.block_height as u64,
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            source_txid: txid(&value.source_txid)?,
+            source_vout_num: value.source_vout_num as u16,
+            spend_txid: txid(&value.spend_txid)?,
+            spend_block_hash: value
+                .spend_block_hash
+                .as_deref()
+                .map(BlockHash::from_str)
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            spend_tx: value.spend_tx,
+            proof: value.proof,
+            competing_txid: value.competing_txid.as_deref().map(txid).transpose()?,
+            competing_source_txid: value
+                .competing_source_txid
+                .as_deref()
+                .map(txid)
+                .transpose()?,
+            competing_source_vout_num: value.competing_source_vout_num.map(|vout| vout as u32),
+            created_at: value.created_at as u64,
+        };
+
+        Ok(evidence)
+    }
+}
+
+pub struct DbRotationProgress {
+    pub(super) identity_address: String,
+    pub(super) new_address: String,
+    pub(super) migrated: bool,
+    pub(super) notified: bool,
+}
+
+impl TryFrom<DbRotationProgress> for RotationProgress {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbRotationProgress) -> Result<Self, Self::Error> {
+        let progress = Self {
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            new_address: Address::from_str(&value.new_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            migrated: value.migrated,
+            notified: value.notified,
+        };
+
+        Ok(progress)
+    }
+}
+
+pub struct DbKeyRotation {
+    pub(super) currency_address: String,
+    pub(super) old_address: String,
+    pub(super) new_address: String,
+    pub(super) ends_at: i64,
+    pub(super) operator: String,
+    pub(super) retired_at: Option<i64>,
+    pub(super) retired_by: Option<String>,
+    pub(super) created_at: i64,
+}
+
+impl TryFrom<DbKeyRotation> for KeyRotation {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbKeyRotation) -> Result<Self, Self::Error> {
+        let rotation = Self {
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            old_address: Address::from_str(&value.old_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            new_address: Address::from_str(&value.new_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            ends_at: value.ends_at as u64,
+            operator: value.operator,
+            retired_at: value.retired_at.map(|retired_at| retired_at as u64),
+            retired_by: value.retired_by,
+            created_at: value.created_at as u64,
+        };
+
+        Ok(rotation)
+    }
+}
+
+pub struct DbDelegatedAddress {
+    pub(super) identity_address: String,
+    pub(super) address: String,
+}
+
+impl TryFrom<DbDelegatedAddress> for DelegatedAddress {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbDelegatedAddress) -> Result<Self, Self::Error> {
+        let delegated_address = Self {
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            address: Address::from_str(&value.address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+        };
+
+        Ok(delegated_address)
+    }
+}
+
+pub struct DbAccountingEntry {
+    pub(super) kind: AccountingEntryKind,
+    pub(super) currency_address: String,
+    pub(super) reference: String,
+    pub(super) block_height: Option<i64>,
+    pub(super) amount: i64,
+    pub(super) fee: i64,
+    pub(super) timestamp: i64,
+}
+
+impl TryFrom<DbAccountingEntry> for AccountingEntry {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbAccountingEntry) -> Result<Self, Self::Error> {
+        let entry = Self {
+            kind: value.kind,
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            reference: value.reference,
+            block_height: value.block_height.map(|height| height as u64),
+            amount: Amount::from_sat(value.amount as u64),
+            fee: Amount::from_sat(value.fee as u64),
+            timestamp: value.timestamp as u64,
+        };
+
+        Ok(entry)
+    }
+}
+
+pub struct DbStakerStatement {
+    pub(super) currency_address: String,
+    pub(super) identity_address: String,
+    pub(super) identity_name: String,
+    pub(super) joined_at: i64,
+    pub(super) left_at: i64,
+    pub(super) n_rounds: i64,
+    pub(super) rewards: i64,
+    pub(super) fees: i64,
+    pub(super) forfeited: i64,
+    pub(super) created_at: i64,
+}
+
+impl TryFrom<DbStakerStatement> for StakerStatement {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbStakerStatement) -> Result<Self, Self::Error> {
+        Ok(Self {
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            identity_name: value.identity_name,
+            joined_at: value.joined_at as u64,
+            left_at: value.left_at as u64,
+            staked_time_in_secs: value.left_at.saturating_sub(value.joined_at) as u64,
+            n_rounds: value.n_rounds as u64,
+            rewards: Amount::from_sat(value.rewards as u64),
+            fees: Amount::from_sat(value.fees as u64),
+            forfeited: Amount::from_sat(value.forfeited as u64),
+            created_at: value.created_at as u64,
+        })
+    }
+}
+
+pub struct DbStakerActivity {
+    pub(super) kind: StakerActivityKind,
+    pub(super) timestamp: i64,
+    pub(super) block_height: Option<i64>,
+    pub(super) reference: Option<String>,
+    pub(super) amount: Option<i64>,
+    pub(super) shares: Option<Decimal>,
+    pub(super) status: Option<StakerStatus>,
+}
+
+impl TryFrom<DbStakerActivity> for StakerActivity {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbStakerActivity) -> Result<Self, Self::Error> {
+        Ok(Self {
+            kind: value.kind,
+            timestamp: value.timestamp as u64,
+            block_height: value.block_height.map(|height| height as u64),
+            reference: value.reference,
+            amount: value.amount.map(|amount| Amount::from_sat(amount as u64)),
+            shares: value.shares,
+            status: value.status,
+        })
+    }
+}
+
+pub struct DbPayoutShadowDiff {
+    pub(super) block_hash: String,
+    pub(super) block_height: i64,
+    pub(super) identity_address: String,
+    pub(super) reward: Option<i64>,
+    pub(super) shadow_reward: Option<i64>,
+    pub(super) difference: i64,
+}
+
+impl TryFrom<DbPayoutShadowDiff> for PayoutShadowDiff {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbPayoutShadowDiff) -> Result<Self, Self::Error> {
+        Ok(Self {
+            block_hash: BlockHash::from_str(&value.block_hash)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            block_height: value.block_height as u64,
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            reward: value.reward.map(|reward| Amount::from_sat(reward as u64)),
+            shadow_reward: value
+                .shadow_reward
+                .map(|reward| Amount::from_sat(reward as u64)),
+            difference: value.difference,
+        })
+    }
+}
+
+pub struct DbStakerLiability {
+    pub(super) identity_address: String,
+    pub(super) unpaid: i64,
+    pub(super) n_rounds: i64,
+    pub(super) oldest_unpaid_height: i64,
+    pub(super) oldest_unpaid_at: i64,
+}
+
+impl TryFrom<DbStakerLiability> for StakerLiability {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbStakerLiability) -> Result<Self, Self::Error> {
+        Ok(Self {
+            identity_address: Address::from_str(&value.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            unpaid: Amount::from_sat(value.unpaid as u64),
+            n_rounds: value.n_rounds as u64,
+            oldest_unpaid_height: value.oldest_unpaid_height as u64,
+            oldest_unpaid_at: value.oldest_unpaid_at as u64,
+        })
+    }
+}
+
+pub struct DbPayment {
+    pub(super) currency_address: String,
+    pub(super) txid: String,
+    pub(super) amount: i64,
+    pub(super) n_members: i64,
+    pub(super) tx_fee: i64,
+    pub(super) tx_fee_paid_by_pool: bool,
+    pub(super) status: PaymentStatus,
+    pub(super) confirmations: i64,
+    pub(super) created_at: i64,
+    pub(super) updated_at: i64,
+}
+
+impl TryFrom<DbPayment> for Payment {
+    type Error = sqlx::Error;
+
+    fn try_from(value: DbPayment) -> Result<Self, Self::Error> {
+        let payment = Self {
+            currency_address: Address::from_str(&value.currency_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            txid: Txid::from_str(&value.txid).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            amount: Amount::from_sat(value.amount as u64),
+            n_members: value.n_members as u64,
+            tx_fee: Amount::from_sat(value.tx_fee as u64),
+            tx_fee_paid_by_pool: value.tx_fee_paid_by_pool,
+            status: value.status,
+            confirmations: value.confirmations as u64,
+            created_at: value.created_at as u64,
+            updated_at: value.updated_at as u64,
+        };
+
+        Ok(payment)
+    }
+}
@@ -0,0 +1,39 @@
+use vrsc_rpc::{bitcoin::BlockHash, json::vrsc::Address};
+
+use crate::coinstaker::{constants::StakeStatus, StakerStatus};
+
+/// Which rows of a query are returned, in the order of the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub limit: u64,
+    pub offset: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StakerFilter {
+    pub status: Option<StakerStatus>,
+    pub identity_addresses: Option<Vec<Address>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StakeFilter {
+    pub status: Option<StakeStatus>,
+    pub found_by: Option<Address>,
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PayoutMemberFilter {
+    pub identity_address: Option<Address>,
+    pub block_hash: Option<BlockHash>,
+    /// Only the members that were paid, or only the members that were not paid yet.
+    pub paid: Option<bool>,
+}
+
+/// The rows from `from_height` up to and including `to_height`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeightFilter {
+    pub from_height: Option<u64>,
+    pub to_height: Option<u64>,
+}
@@ -1,4 +1,8 @@
 mod constants;
+mod filter;
 mod query;
+mod schema;
 
+pub use filter::{HeightFilter, Page, PayoutMemberFilter, StakeFilter, StakerFilter};
 pub use query::*;
+pub use schema::{check_schema, SchemaDrift, SchemaDriftKind, SchemaReport};
@@ -1,23 +1,43 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use anyhow::Result;
+use poollib::api::ApiKey;
 use sqlx::postgres::PgRow;
+use sqlx::types::chrono::{DateTime, Utc};
 use sqlx::types::Decimal;
-use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row, Transaction};
-use vrsc_rpc::bitcoin::Txid;
+use sqlx::{Connection, PgConnection, PgPool, Postgres, QueryBuilder, Row, Transaction};
+use url::Url;
+use vrsc_rpc::bitcoin::{BlockHash, Txid};
 use vrsc_rpc::json::vrsc::{Address, Amount};
 
-use super::constants::{DbPayoutMember, DbWorker};
+use super::constants::{
+    DbAccountingEntry, DbDelegatedAddress, DbFraudEvidence, DbKeyRotation, DbNetworkStats,
+    DbPayment, DbPayoutMember, DbPayoutShadowDiff, DbPayoutSummary, DbPoolLuck, DbRotationProgress,
+    DbRound, DbStakerActivity, DbStakerLiability, DbStakerStatement, DbWorker,
+};
+use super::filter::{HeightFilter, Page, PayoutMemberFilter, StakeFilter, StakerFilter};
 
-use crate::coinstaker::constants::{Stake, StakeStatus, Staker};
-use crate::coinstaker::StakerStatus;
+use crate::accounting::{AccountingEntry, AccountingEntryKind};
+use crate::coinstaker::accrual::{time_weighted_shares, BalanceSpan};
+use crate::coinstaker::constants::{
+    DelegatedAddress, EarningsGranularity, EarningsPeriod, FraudEvidence, KeyRotation,
+    ParticipationKind, RedistributedShares, RotationProgress, Round, RoundMerge, Stake,
+    StakeStatus, Staker, StakerActivity, StakerActivityKind, StakerStatement, StaleStake,
+};
+use crate::coinstaker::http::{DeadLetter, OutboxEntry, StakerWebhook, StakerWebhookEvent};
+use crate::coinstaker::summary::BlockSummary;
+use crate::coinstaker::{MinPayoutBounds, StakerStatus};
 use crate::database::constants::{DbStake, DbStaker};
-use crate::payout_service::{Payout, PayoutMember, Worker};
+use crate::http::constants::{NetworkStats, PoolLuck};
+use crate::payout_service::{
+    JournalEntry, JournalStatus, ManualPayment, Payment, PaymentBatch, PaymentItem, PaymentStatus,
+    Payout, PayoutMember, PayoutShadowDiff, PayoutSummary, StakerLiability, TxFee, Worker,
+};
 
 #[allow(unused)]
 pub async fn store_staker(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     staker: &Staker, // currency_address: &Address,
                      // identity: &Identity,
                      // status: StakerStatus,
@@ -30,9 +50,14 @@ pub async fn store_staker(
         staker.identity_name,
         &staker.status as _,
         staker.min_payout.as_sat() as i64,
-        staker.fee
+        staker.fee,
+        staker.participation_kind as ParticipationKind,
+        staker
+            .payout_address
+            .as_ref()
+            .map(|address| address.to_string())
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
@@ -70,6 +95,13 @@ pub async fn get_stakers_by_identity_address(
             min_payout: Amount::from_sat(row.get::<i64, &str>("min_payout") as u64),
             status: row.get::<StakerStatus, &str>("status"),
             fee: row.get("fee"),
+            participation_kind: row.get::<ParticipationKind, &str>("participation_kind"),
+            payout_address: row
+                .get::<Option<&str>, &str>("payout_address")
+                .map(|address| Address::from_str(address).unwrap()),
+            forecast: None,
+            created_at: row.get::<DateTime<Utc>, &str>("created_at").timestamp() as u64,
+            updated_at: row.get::<DateTime<Utc>, &str>("updated_at").timestamp() as u64,
         })
         .collect::<Vec<_>>();
 
@@ -77,7 +109,7 @@ pub async fn get_stakers_by_identity_address(
 }
 
 pub async fn get_stakers_by_status(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     currency_address: &Address,
     status: StakerStatus,
 ) -> Result<Vec<Staker>> {
@@ -89,7 +121,11 @@ pub async fn get_stakers_by_status(
             identity_name, 
             min_payout, 
             status AS "status: _",
-            fee
+            fee,
+            participation_kind AS "participation_kind: _",
+            payout_address,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
         FROM stakers 
         WHERE currency_address = $1 
             AND status = $2"#,
@@ -97,14 +133,128 @@ pub async fn get_stakers_by_status(
         status as StakerStatus
     )
     .try_map(Staker::try_from)
-    .fetch_all(pool)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Gets the cold stakers that are not banned.
+pub async fn get_cold_stakers(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+) -> Result<Vec<Staker>> {
+    let rows = sqlx::query_as!(
+        DbStaker,
+        r#"SELECT
+            currency_address,
+            identity_address,
+            identity_name,
+            min_payout,
+            status AS "status: _",
+            fee,
+            participation_kind AS "participation_kind: _",
+            payout_address,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakers
+        WHERE currency_address = $1
+            AND participation_kind = 'COLD_STAKING'
+            AND status <> 'BANNED'"#,
+        currency_address.to_string()
+    )
+    .try_map(Staker::try_from)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Expires the stakers that have been cooling down for more than `days` days and returns them.
+pub async fn expire_cooling_down_stakers(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    days: u32,
+) -> Result<Vec<Staker>> {
+    let rows = sqlx::query_as!(
+        DbStaker,
+        r#"UPDATE stakers s
+        SET status = 'EXPIRED'
+        WHERE s.currency_address = $1
+            AND s.status = 'COOLING_DOWN'
+            AND (
+                SELECT MAX(e.created_at) FROM staker_events e
+                WHERE e.currency_address = s.currency_address
+                    AND e.identity_address = s.identity_address
+            ) < NOW() - make_interval(days => $2)
+        RETURNING
+            currency_address,
+            identity_address,
+            identity_name,
+            min_payout,
+            status AS "status: _",
+            fee,
+            participation_kind AS "participation_kind: _",
+            payout_address,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!""#,
+        currency_address.to_string(),
+        days as i32
+    )
+    .try_map(Staker::try_from)
+    .fetch_all(&mut *conn)
     .await?;
 
     Ok(rows)
 }
 
+/// Bans a staker and takes the rewards of its unpaid payout members. Returns the rewards that
+/// were taken.
+pub async fn ban_staker(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Amount> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "UPDATE stakers SET status = 'BANNED'
+        WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let forfeited = sqlx::query!(
+        r#"WITH unpaid AS (
+            SELECT block_hash, reward
+            FROM payout_members
+            WHERE currency_address = $1 AND identity_address = $2 AND txid IS NULL
+            FOR UPDATE
+        ), zeroed AS (
+            UPDATE payout_members pm
+            SET reward = 0
+            FROM unpaid
+            WHERE pm.currency_address = $1
+                AND pm.identity_address = $2
+                AND pm.block_hash = unpaid.block_hash
+        )
+        SELECT COALESCE(SUM(reward), 0)::bigint AS "forfeited!" FROM unpaid"#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .forfeited;
+
+    tx.commit().await?;
+
+    Ok(Amount::from_sat(forfeited as u64))
+}
+
 pub async fn get_staker(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     currency_address: &Address,
     identity_address: &Address,
 ) -> Result<Option<Staker>> {
@@ -116,7 +266,11 @@ pub async fn get_staker(
             identity_name, 
             min_payout, 
             status AS "status: _", 
-            fee
+            fee,
+            participation_kind AS "participation_kind: _",
+            payout_address,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
         FROM stakers 
         WHERE currency_address = $1 
             AND identity_address = $2"#,
@@ -124,7 +278,7 @@ pub async fn get_staker(
         identity_address.to_string()
     )
     .try_map(Staker::try_from)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?;
 
     Ok(staker)
@@ -134,23 +288,133 @@ pub async fn get_staker(
 ///
 /// Every active staker gets their share (their stake) added as work.
 /// Payload contains all the addresses and their stake, which are written to the database.
+/// Adds the work of the block at `block_height` to the open round (round 0). Returns false if
+/// the work of this height was added before.
+///
+/// A height is processed again when the daemon forks and blocks are reconsidered. Its work is
+/// then skipped, so that it is never counted twice, and the revision of the height goes up.
 pub async fn store_work(
     pool: &PgPool,
     currency_address: &Address,
     payload: HashMap<Address, Decimal>,
-    _last_blockheight: u64,
-) -> Result<()> {
+    block_height: u64,
+) -> Result<bool> {
     let mut tx = pool.begin().await?;
 
+    if !add_work_revision(&mut tx, currency_address, block_height).await? {
+        tx.commit().await?;
+
+        return Ok(false);
+    }
+
+    store_shares(&mut tx, currency_address, payload, block_height).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Stores the balance spans of the blocks up to and including `block_height` and adds their
+/// work: the balance of every span times the blocks it was held. Like `store_work`, the work of
+/// a height is only added once.
+pub async fn store_time_weighted_work(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    spans: &[BalanceSpan],
+    block_height: u64,
+) -> Result<bool> {
+    let mut tx = conn.begin().await?;
+
+    if !add_work_revision(&mut tx, currency_address, block_height).await? {
+        tx.commit().await?;
+
+        return Ok(false);
+    }
+
+    for span in spans {
+        // a span that continues the last span of a staker with the same balance extends it, so
+        // only the changes of a balance are stored
+        let extended = sqlx::query!(
+            "UPDATE balance_spans SET to_height = $4
+            WHERE currency_address = $1 AND identity_address = $2 AND to_height = $3 - 1 AND balance = $5",
+            currency_address.to_string(),
+            span.identity_address.to_string(),
+            span.from_height as i64,
+            span.to_height as i64,
+            span.balance.as_sat() as i64
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if !extended {
+            sqlx::query!(
+                "INSERT INTO balance_spans (currency_address, identity_address, from_height, to_height, balance)
+                VALUES ($1, $2, $3, $4, $5)",
+                currency_address.to_string(),
+                span.identity_address.to_string(),
+                span.from_height as i64,
+                span.to_height as i64,
+                span.balance.as_sat() as i64
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    store_shares(
+        &mut tx,
+        currency_address,
+        time_weighted_shares(spans),
+        block_height,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Records that the work of a height is added. Returns `false` if it was added before.
+async fn add_work_revision(
+    tx: &mut Transaction<'_, Postgres>,
+    currency_address: &Address,
+    block_height: u64,
+) -> Result<bool> {
+    let revision = sqlx::query_scalar!(
+        "INSERT INTO work_revisions (currency_address, block_height)
+        VALUES ($1, $2)
+        ON CONFLICT (currency_address, block_height)
+        DO UPDATE SET revision = work_revisions.revision + 1
+        RETURNING revision",
+        currency_address.to_string(),
+        block_height as i64
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(revision == 1)
+}
+
+/// Appends the work of the block at `block_height` to the share ledger.
+async fn store_shares(
+    tx: &mut Transaction<'_, Postgres>,
+    currency_address: &Address,
+    payload: HashMap<Address, Decimal>,
+    block_height: u64,
+) -> Result<()> {
     for (staker_address, shares) in payload {
-        sqlx::query_file!(
-            "sql/store_work.sql",
+        sqlx::query!(
+            "INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+            VALUES ($1, $2, $3, 'WORK', $4)
+            ON CONFLICT DO NOTHING",
             currency_address.to_string(),
-            0,
             staker_address.to_string(),
+            block_height as i64,
             shares
         )
-        .execute(&mut *tx)
+        .execute(&mut **tx)
         .await?;
 
         // TODO? there was a latest_round here that functions as a sort of
@@ -158,585 +422,7097 @@ pub async fn store_work(
         // it was only used in tests and to get the last round on startup
     }
 
-    tx.commit().await?;
-
     Ok(())
 }
 
+/// The last height of which work was added, or `None` if no work was added yet.
+pub async fn get_last_work_height(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+) -> Result<Option<u64>> {
+    let height = sqlx::query_scalar!(
+        "SELECT MAX(block_height) FROM work_revisions WHERE currency_address = $1",
+        currency_address.to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(height.map(|height| height as u64))
+}
+
+/// The balance spans of a staker that overlap the heights from `from_height` up to and including
+/// `to_height`.
+pub async fn get_balance_spans(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    from_height: u64,
+    to_height: u64,
+) -> Result<Vec<BalanceSpan>> {
+    let spans = sqlx::query!(
+        "SELECT identity_address, from_height, to_height, balance
+        FROM balance_spans
+        WHERE currency_address = $1 AND identity_address = $2 AND to_height >= $3 AND from_height <= $4
+        ORDER BY from_height",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        from_height as i64,
+        to_height as i64
+    )
+    .try_map(|row| {
+        Ok(BalanceSpan {
+            identity_address: Address::from_str(&row.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            from_height: row.from_height as u64,
+            to_height: row.to_height as u64,
+            balance: Amount::from_sat(row.balance as u64),
+        })
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(spans)
+}
+
 // used when a stake was found to be stale or stolen. Work that was assigned to a round
 // before, should be moved back to round 0.
 // rename: undo_work
 pub async fn move_work_to_round_zero(
     pool: &PgPool,
     currency_address: &Address,
-    from_round: u64,
+    from_round_id: u64,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    add_work_to_round_zero(&mut tx, currency_address, from_round_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Moves the work of a round that has no stake back to round 0, so it is paid out with the next
+/// stake of the pool.
+///
+/// Work ends up in such a round when the pool stopped between moving the work of round 0 to a
+/// new round and storing the stake that closed it.
+pub async fn restore_orphaned_work(
+    pool: &PgPool,
+    currency_address: &Address,
+    round_id: u64,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    add_work_to_round_zero(&mut tx, currency_address, round_id).await?;
+
+    sqlx::query!(
+        "DELETE FROM work WHERE currency_address = $1 AND round_id = $2",
+        currency_address.to_string(),
+        round_id as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM forfeited_work WHERE currency_address = $1 AND round_id = $2",
+        currency_address.to_string(),
+        round_id as i64
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Adds the work and forfeited work of a round to round 0. The work stays in its round.
+async fn add_work_to_round_zero(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    from_round_id: u64,
 ) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+        SELECT
+            currency_address,
+            staker_address,
+            (
+                SELECT COALESCE(MAX(block_height), 0) FROM work_revisions
+                WHERE currency_address = $1
+            ),
+            'RESTORED',
+            shares
+        FROM work
+        WHERE currency_address = $1 AND round_id = $2 AND shares <> 0",
+        currency_address.to_string(),
+        from_round_id as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
     sqlx::query!(
         "WITH round_to_move AS (
-            SELECT currency_address, round, staker_address, shares
-            FROM work 
-            WHERE currency_address = $1 AND round = $2
+            SELECT currency_address, round_id, staker_address, shares
+            FROM forfeited_work
+            WHERE currency_address = $1 AND round_id = $2
         )
-        INSERT INTO work (currency_address, round, staker_address, shares) 
+        INSERT INTO forfeited_work (currency_address, round_id, staker_address, shares)
         SELECT currency_address, 0, staker_address, shares
         FROM round_to_move
-        ON CONFLICT (currency_address, round, staker_address)
-        DO UPDATE SET shares = work.shares + EXCLUDED.shares",
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = forfeited_work.shares + EXCLUDED.shares",
         currency_address.to_string(),
-        from_round as i64
+        from_round_id as i64
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
-// used when a stake is found. Work until now needs to be moved to a round
-// to be able to calculate a payout properly
-async fn move_work_to_new_round(
+/// Closes the open round into the round with id `round_id`, when a stake is found: the share
+/// events of every staker since the last closure are added up into the work of that round, so
+/// that a payout can be calculated from it.
+async fn close_open_round(
     tx: &mut Transaction<'_, Postgres>,
     currency_address: &Address,
-    from_round: u64,
-    to_round: u64,
+    round_id: u64,
 ) -> Result<()> {
+    // the closure is not visible to the rest of the statement, so the sum starts after the
+    // previous closure
     sqlx::query!(
-        "UPDATE work SET round = $3 WHERE currency_address = $1 AND round = $2",
+        "WITH closure AS (
+            INSERT INTO round_closures (currency_address, round_id, last_share_event_id)
+            SELECT $1, $2, COALESCE(MAX(id), 0) FROM share_events WHERE currency_address = $1
+            RETURNING last_share_event_id
+        )
+        INSERT INTO work (currency_address, round_id, staker_address, shares)
+        SELECT e.currency_address, $2, e.staker_address, SUM(e.delta)
+        FROM share_events e, closure c
+        WHERE e.currency_address = $1
+            AND e.id <= c.last_share_event_id
+            AND e.id > COALESCE(
+                (
+                    SELECT MAX(last_share_event_id) FROM round_closures
+                    WHERE currency_address = $1
+                ),
+                0
+            )
+        GROUP BY e.currency_address, e.staker_address
+        HAVING SUM(e.delta) <> 0
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = work.shares + EXCLUDED.shares",
         currency_address.to_string(),
-        from_round as i64,
-        to_round as i64
+        round_id as i64
     )
     .execute(&mut **tx)
     .await?;
 
-    Ok(())
-}
-
-pub async fn get_stake(
-    pool: &PgPool,
-    currency_address: &Address,
-    block_height: u64,
-) -> Result<Option<Stake>> {
-    let value = sqlx::query_as!(
-        DbStake,
-        "SELECT currency_address,
-            block_hash,
-            block_height,
-            amount,
-            found_by,
-            source_txid,
-            source_vout_num,
-            source_amount,
-            status AS \"status: _\" 
-        FROM stakes
-        WHERE currency_address = $1 AND block_height = $2",
+    sqlx::query!(
+        "UPDATE forfeited_work SET round_id = $2 WHERE currency_address = $1 AND round_id = 0",
         currency_address.to_string(),
-        block_height as i64
+        round_id as i64
     )
-    .try_map(Stake::try_from)
-    .fetch_optional(pool)
+    .execute(&mut **tx)
     .await?;
 
-    Ok(value)
+    Ok(())
 }
 
-pub async fn store_new_stake(pool: &PgPool, stake: &Stake) -> Result<()> {
-    let mut tx = pool.begin().await?;
-
-    move_work_to_new_round(&mut tx, &stake.currency_address, 0, stake.block_height).await?;
-
-    sqlx::query_file!(
-        "sql/store_stake.sql",
+/// Stores the work in the round with id `round_id` as the snapshot of `stake`. A snapshot that
+/// was stored before is kept as it is.
+async fn store_work_snapshot(
+    tx: &mut Transaction<'_, Postgres>,
+    stake: &Stake,
+    round_id: u64,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO work_snapshots (currency_address, block_hash, identity_address, forfeited, shares, fee)
+        SELECT w.currency_address, $3, w.staker_address, FALSE, w.shares, s.fee
+        FROM work w
+        JOIN stakers s
+        ON s.identity_address = w.staker_address AND s.currency_address = w.currency_address
+        WHERE w.currency_address = $1 AND w.round_id = $2
+        ON CONFLICT DO NOTHING",
         stake.currency_address.to_string(),
-        stake.block_hash.to_string(),
-        stake.block_height as i64,
-        stake.amount.as_sat() as i64,
-        stake.found_by.to_string(),
-        stake.source_txid.to_string(),
-        stake.source_vout_num as i32,
-        stake.source_amount.as_sat() as i64,
-        stake.status as _
+        round_id as i64,
+        stake.block_hash.to_string()
     )
-    .execute(&mut *tx)
+    .execute(&mut **tx)
     .await?;
 
-    tx.commit().await?;
-
-    Ok(())
-}
-
-pub async fn store_stake(pool: &PgPool, stake: &Stake) -> Result<()> {
-    sqlx::query_file!(
-        "sql/store_stake.sql",
+    sqlx::query!(
+        "INSERT INTO work_snapshots (currency_address, block_hash, identity_address, forfeited, shares)
+        SELECT currency_address, $3, staker_address, TRUE, shares
+        FROM forfeited_work
+        WHERE currency_address = $1 AND round_id = $2
+        ON CONFLICT DO NOTHING",
         stake.currency_address.to_string(),
-        stake.block_hash.to_string(),
-        stake.block_height as i64,
-        stake.amount.as_sat() as i64,
-        stake.found_by.to_string(),
-        stake.source_txid.to_string(),
-        stake.source_vout_num as i32,
-        stake.source_amount.as_sat() as i64,
-        stake.status as _
+        round_id as i64,
+        stake.block_hash.to_string()
     )
-    .execute(pool)
+    .execute(&mut **tx)
     .await?;
 
     Ok(())
 }
 
-pub async fn get_stakes_by_status(
+/// Gets the workers and the forfeited shares of the round of the stake in `block_hash`, as they
+/// were when the stake was found. Returns None if the stake has no snapshot.
+pub async fn get_work_snapshot(
     pool: &PgPool,
     currency_address: &Address,
-    status: StakeStatus,
-    from_id: Option<u64>,
-) -> Result<Vec<Stake>> {
-    let rows = sqlx::query_as!(
-        DbStake,
-        r#"SELECT
-            currency_address,
-            block_hash,
-            block_height,
-            amount,
-            found_by,
-            source_txid,
-            source_vout_num,
-            source_amount,
-            status AS "status: _"
-        FROM stakes 
-        WHERE currency_address = $1 AND 
-            status = $2 AND 
-            block_height > $3
-        ORDER BY block_height ASC"#,
+    block_hash: &BlockHash,
+) -> Result<Option<(Vec<Worker>, Decimal)>> {
+    let rows = sqlx::query!(
+        "SELECT identity_address, forfeited, shares, fee
+        FROM work_snapshots
+        WHERE currency_address = $1 AND block_hash = $2",
         currency_address.to_string(),
-        status as StakeStatus,
-        from_id.unwrap_or(0) as i64
+        block_hash.to_string()
     )
-    .try_map(Stake::try_from)
     .fetch_all(pool)
     .await?;
 
-    Ok(rows)
+    if rows.is_empty() {
+        return Ok(None);
+    }
+
+    let mut workers = vec![];
+    let mut forfeited_shares = Decimal::ZERO;
+    for row in rows {
+        if row.forfeited {
+            forfeited_shares += row.shares;
+        } else {
+            workers.push(Worker::try_from(DbWorker {
+                identity_address: row.identity_address,
+                shares: row.shares,
+                fee: row.fee.unwrap_or(Decimal::ZERO),
+            })?);
+        }
+    }
+
+    Ok(Some((workers, forfeited_shares)))
 }
 
-/// Returns stakes that are above <block_height> and are either maturing or have matured.
+/// Creates the round that is closed by `stake` and returns its id.
 ///
-/// This is useful to compensate for work that is lost due to maturing UTXOs because they were
-/// spent because of staking.
-pub async fn get_stakes_to_compensate(
+/// Storing the same stake again returns the id of its existing round, and false.
+async fn create_round(conn: &mut PgConnection, stake: &Stake) -> Result<(u64, bool)> {
+    let row = sqlx::query!(
+        r#"INSERT INTO rounds (currency_address, block_hash, block_height)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (currency_address, block_hash)
+        DO UPDATE SET block_height = EXCLUDED.block_height
+        RETURNING id, (xmax = 0) AS "created!""#,
+        stake.currency_address.to_string(),
+        stake.block_hash.to_string(),
+        stake.block_height as i64
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok((row.id as u64, row.created))
+}
+
+async fn round_is_empty(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    round_id: u64,
+) -> Result<bool> {
+    let has_work = sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM work WHERE currency_address = $1 AND round_id = $2
+        ) AS "exists!""#,
+        currency_address.to_string(),
+        round_id as i64
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(!has_work)
+}
+
+/// Returns the ids and heights of the rounds that were closed at or before `block_height`,
+/// newest first.
+pub async fn get_rounds_until(
     pool: &PgPool,
     currency_address: &Address,
-    block_height: i64,
-) -> Result<Vec<Stake>> {
-    let rows = sqlx::query_as!(
-        DbStake,
-        r#"SELECT
-            currency_address,
-            block_hash,
-            block_height,
-            amount,
-            found_by,
-            source_txid,
-            source_vout_num,
-            source_amount,
-            status AS "status: _"
-        FROM stakes
-        WHERE currency_address = $1 AND
-            block_height > ($2 - 150) AND
-            (status = 'MATURED' OR status = 'MATURING')
-        ORDER BY block_height ASC"#,
+    block_height: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let rounds = sqlx::query!(
+        "SELECT id, block_height FROM rounds
+        WHERE currency_address = $1 AND block_height <= $2
+        ORDER BY block_height DESC, id DESC",
         currency_address.to_string(),
         block_height as i64
     )
-    .try_map(Stake::try_from)
     .fetch_all(pool)
     .await?;
 
-    Ok(rows)
+    Ok(rounds
+        .into_iter()
+        .map(|row| (row.id as u64, row.block_height as u64))
+        .collect())
 }
 
-pub async fn get_stakes(
-    pool: &PgPool,
+/// Gets the id of the round that was closed by the stake in `block_hash`.
+pub async fn get_round_id(
+    conn: &mut PgConnection,
     currency_address: &Address,
-    from_height: Option<u64>,
-) -> Result<Vec<Stake>> {
-    let rows = sqlx::query_as!(
-        DbStake,
-        r#"SELECT
-            currency_address,
-            block_hash,
-            block_height,
-            amount,
-            found_by,
-            source_txid,
-            source_vout_num,
-            source_amount,
-            status AS "status: _"
-        FROM stakes 
-        WHERE currency_address = $1 AND 
-            block_height > $2
-        ORDER BY block_height ASC"#,
+    block_hash: &BlockHash,
+) -> Result<Option<u64>> {
+    let id = sqlx::query_scalar!(
+        "SELECT id FROM rounds WHERE currency_address = $1 AND block_hash = $2",
         currency_address.to_string(),
-        from_height.unwrap_or(0) as i64
+        block_hash.to_string()
     )
-    .try_map(Stake::try_from)
-    .fetch_all(pool)
+    .fetch_optional(conn)
     .await?;
 
-    Ok(rows)
+    Ok(id.map(|id| id as u64))
 }
 
-pub async fn update_last_height(
-    pool: &PgPool,
+/// Moves the work in round 0 of a staker, and the work that was held for it, to the forfeited
+/// work.
+///
+/// Forfeited work counts towards the total work of a round, but the reward for it is kept
+/// by the pool as fee. Any scheduled forfeit for this staker is removed.
+pub async fn forfeit_work(
+    conn: &mut PgConnection,
     currency_address: &Address,
-    block_height: u64,
+    staker_address: &Address,
 ) -> Result<()> {
-    let _res = sqlx::query!(
-        "INSERT INTO synchronization (
-            currency_address, 
-            last_height
-        ) VALUES ($1, $2) 
-        ON CONFLICT (currency_address) 
-        DO UPDATE 
-        SET last_height = $2",
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "WITH forfeited AS (
+            INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+            SELECT
+                currency_address,
+                staker_address,
+                (
+                    SELECT COALESCE(MAX(block_height), 0) FROM work_revisions
+                    WHERE currency_address = $1
+                ),
+                'FORFEITED',
+                -shares
+            FROM open_work
+            WHERE currency_address = $1 AND staker_address = $2
+            RETURNING currency_address, staker_address, -delta AS shares
+        )
+        INSERT INTO forfeited_work (currency_address, round_id, staker_address, shares)
+        SELECT currency_address, 0, staker_address, shares
+        FROM forfeited
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = forfeited_work.shares + EXCLUDED.shares",
         currency_address.to_string(),
-        block_height as i64
+        staker_address.to_string()
     )
-    .execute(pool)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "WITH forfeit AS (
+            DELETE FROM scheduled_forfeits
+            WHERE currency_address = $1 AND staker_address = $2
+            RETURNING currency_address, staker_address, held_shares
+        )
+        INSERT INTO forfeited_work (currency_address, round_id, staker_address, shares)
+        SELECT currency_address, 0, staker_address, held_shares
+        FROM forfeit
+        WHERE held_shares > 0
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = forfeited_work.shares + EXCLUDED.shares",
+        currency_address.to_string(),
+        staker_address.to_string()
+    )
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(())
 }
 
-pub async fn get_last_height(pool: &PgPool, currency_address: &Address) -> Result<Option<u64>> {
-    let row = sqlx::query!(
-        "SELECT last_height 
-        FROM synchronization 
-        WHERE currency_address = $1
-        FOR UPDATE",
-        currency_address.to_string()
+/// Schedules the forfeit of the work of a staker after the pool found `rounds` more stakes. With
+/// 0 rounds, the work is forfeited in the block that is being processed.
+pub async fn schedule_forfeit(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    staker_address: &Address,
+    rounds: u64,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO scheduled_forfeits (
+            currency_address,
+            staker_address,
+            rounds_left
+        ) VALUES ($1, $2, $3)
+        ON CONFLICT (currency_address, staker_address)
+        DO UPDATE
+        SET rounds_left = $3",
+        currency_address.to_string(),
+        staker_address.to_string(),
+        rounds as i64
     )
-    .map(|r| r.last_height as u64)
-    .fetch_optional(pool)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(row)
+    Ok(())
 }
 
-pub async fn get_workers_by_round(
-    pool: &PgPool,
+/// Takes the work in round 0 of the stakers with a scheduled forfeit out of the open round, so
+/// the stakes that are found while the forfeit is pending don't pay for it. Returns the stakers
+/// of which the work was held.
+pub async fn hold_departed_work(
+    conn: &mut PgConnection,
     currency_address: &Address,
-    round: u64,
-) -> Result<Vec<Worker>> {
-    let workers = sqlx::query_as!(
-        DbWorker,
-        "SELECT identity_address, shares, fee FROM stakers s1
-        JOIN work w1
-        ON w1.staker_address = s1.identity_address AND s1.currency_address = w1.currency_address
-        WHERE w1.round = $1 AND w1.currency_address = $2",
-        round as i64,
+) -> Result<Vec<Address>> {
+    let mut tx = conn.begin().await?;
+
+    let rows = sqlx::query!(
+        "WITH held AS (
+            INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+            SELECT
+                f.currency_address,
+                f.staker_address,
+                (
+                    SELECT COALESCE(MAX(block_height), 0) FROM work_revisions
+                    WHERE currency_address = $1
+                ),
+                'HELD',
+                -COALESCE(ow.shares, 0)
+            FROM scheduled_forfeits f
+            LEFT JOIN open_work ow ON ow.currency_address = f.currency_address
+                AND ow.staker_address = f.staker_address
+            WHERE f.currency_address = $1 AND f.rounds_left > 0 AND f.held_shares IS NULL
+            RETURNING staker_address, -delta AS shares
+        )
+        UPDATE scheduled_forfeits f
+        SET held_shares = held.shares
+        FROM held
+        WHERE f.currency_address = $1 AND f.staker_address = held.staker_address
+        RETURNING f.staker_address",
         currency_address.to_string()
     )
-    .try_map(Worker::try_from)
-    .fetch_all(pool)
+    .try_map(|row| {
+        Address::from_str(&row.staker_address).map_err(|e| sqlx::Error::Decode(e.into()))
+    })
+    .fetch_all(&mut *tx)
     .await?;
 
-    Ok(workers)
+    tx.commit().await?;
+
+    Ok(rows)
 }
 
-pub async fn get_payout_sync_id(pool: &PgPool, currency_address: &Address) -> Result<Option<u64>> {
-    let value = sqlx::query!(
-        "SELECT last_payout_height 
-        FROM synchronization 
-        WHERE currency_address = $1 
-        FOR UPDATE",
+/// Counts a stake that was found towards the hold of every staker of which the work is held.
+pub async fn count_held_round(conn: &mut PgConnection, currency_address: &Address) -> Result<()> {
+    sqlx::query!(
+        "UPDATE scheduled_forfeits
+        SET rounds_left = rounds_left - 1
+        WHERE currency_address = $1 AND rounds_left > 0 AND held_shares IS NOT NULL",
         currency_address.to_string()
     )
-    .fetch_optional(pool)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(value.map(|row| row.last_payout_height as u64))
+    Ok(())
 }
 
-pub async fn update_last_payout_height(
-    pool: &mut PgConnection,
+/// Schedules the final payout of a staker that became inactive at `settle_at`, a unix timestamp
+/// in seconds.
+pub async fn schedule_settlement(
+    conn: &mut PgConnection,
     currency_address: &Address,
-    block_height: u64,
+    identity_address: &Address,
+    settle_at: u64,
 ) -> Result<()> {
-    let _res = sqlx::query!(
-        "INSERT INTO synchronization (
-            currency_address, 
-            last_payout_height
-        ) VALUES ($1, $2) 
-        ON CONFLICT (currency_address) 
-        DO UPDATE 
-        SET last_payout_height = $2",
+    sqlx::query!(
+        "INSERT INTO staker_settlements (
+            currency_address,
+            identity_address,
+            settle_at
+        ) VALUES ($1, $2, to_timestamp($3))
+        ON CONFLICT (currency_address, identity_address)
+        DO UPDATE
+        SET settle_at = to_timestamp($3), settled_at = NULL",
         currency_address.to_string(),
-        block_height as i64
+        identity_address.to_string(),
+        settle_at as i64
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
-pub async fn store_payout(conn: &mut PgConnection, payout: &Payout) -> Result<()> {
-    sqlx::query_file!(
-        "sql/store_payout.sql",
-        &payout.currency_address.to_string(),
-        &payout.block_hash.to_string(),
-        payout.block_height as i64,
-        payout.amount.as_sat() as i64,
-        &payout.total_work,
-        payout.fee.as_sat() as i64,
-        payout.paid.as_sat() as i64,
-        payout.members.len() as i64
+/// Cancels the settlement of a staker that became active again before it was closed.
+pub async fn cancel_settlement(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM staker_settlements
+        WHERE currency_address = $1 AND identity_address = $2 AND settled_at IS NULL",
+        currency_address.to_string(),
+        identity_address.to_string()
     )
-    .execute(conn)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
-pub async fn store_payout_member(
+/// Cancels the scheduled forfeit of a staker that became active again. The work that was held
+/// for it goes back to the open round.
+pub async fn cancel_scheduled_forfeit(
     conn: &mut PgConnection,
-    payout_member: &PayoutMember,
+    currency_address: &Address,
+    staker_address: &Address,
 ) -> Result<()> {
-    sqlx::query_file!(
-        "sql/store_payout_member.sql",
-        &payout_member.currency_address.to_string(),
-        &payout_member.identity_address.to_string(),
-        &payout_member.block_hash.to_string(),
-        payout_member.block_height as i64,
-        payout_member.shares,
-        payout_member.reward.as_sat() as i64,
-        payout_member.fee.as_sat() as i64,
-        None::<&str>
+    sqlx::query!(
+        "WITH released AS (
+            DELETE FROM scheduled_forfeits
+            WHERE currency_address = $1 AND staker_address = $2
+            RETURNING currency_address, staker_address, held_shares
+        )
+        INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+        SELECT
+            currency_address,
+            staker_address,
+            (
+                SELECT COALESCE(MAX(block_height), 0) FROM work_revisions
+                WHERE currency_address = $1
+            ),
+            'RELEASED',
+            held_shares
+        FROM released
+        WHERE held_shares > 0",
+        currency_address.to_string(),
+        staker_address.to_string()
     )
-    .execute(conn)
+    .execute(&mut *conn)
     .await?;
 
     Ok(())
 }
 
-pub async fn get_payout_members(
+/// Stores the height at which the VerusID of a staker unlocks.
+pub async fn store_unlock_height(
     conn: &mut PgConnection,
     currency_address: &Address,
-    identity_addresses: &[Address],
-) -> Result<Vec<PayoutMember>> {
-    let values = sqlx::query_as!(
-        DbPayoutMember,
-        "SELECT 
+    identity_address: &Address,
+    unlock_height: u64,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO identity_unlocks (
             currency_address,
             identity_address,
-            block_hash,
-            block_height,
-            shares,
-            reward,
-            fee,
-            txid
-        FROM payout_members 
-        WHERE currency_address = $1 
-        AND identity_address IN (SELECT * FROM UNNEST($2::text[]))",
+            unlock_height
+        ) VALUES ($1, $2, $3)
+        ON CONFLICT (currency_address, identity_address)
+        DO UPDATE
+        SET unlock_height = $3",
         currency_address.to_string(),
-        &identity_addresses
-            .iter()
-            .map(|address| address.to_string())
-            .collect::<Vec<_>>(),
+        identity_address.to_string(),
+        unlock_height as i64
     )
-    .try_map(PayoutMember::try_from)
-    .fetch_all(conn)
+    .execute(&mut *conn)
     .await?;
 
-    Ok(values)
+    Ok(())
 }
 
-/// Get all payout members that have not been paid yet.
-///
-/// The payoutmembers are selected on their min_payout settings.
-/// If a staker has left the pool, all remaining funds will be paid, disregarding
-/// the min_payout settings of the staker.
-///
-/// The query locks the rows until the transaction is committed (or dropped on error).
-pub async fn get_unpaid_payout_members(
+pub async fn remove_unlock_height(
     conn: &mut PgConnection,
     currency_address: &Address,
-) -> Result<Vec<PayoutMember>> {
-    let values = sqlx::query_as!(
-        DbPayoutMember,
-        "WITH pm_sum AS (
-            SELECT currency_address, identity_address, SUM(reward) AS total_rewards
-            FROM payout_members
-            WHERE currency_address = $1
-                AND txid is NULL
-            GROUP BY currency_address, identity_address
+    identity_address: &Address,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM identity_unlocks WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the pool primary address that the VerusID of a staker includes, or removes it if it
+/// includes none.
+pub async fn store_staker_primary_address(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+    primary_address: Option<&Address>,
+) -> Result<()> {
+    match primary_address {
+        Some(primary_address) => {
+            sqlx::query!(
+                "INSERT INTO staker_primary_addresses (
+                    currency_address,
+                    identity_address,
+                    primary_address
+                ) VALUES ($1, $2, $3)
+                ON CONFLICT (currency_address, identity_address)
+                DO UPDATE
+                SET primary_address = $3
+                WHERE staker_primary_addresses.primary_address <> $3",
+                currency_address.to_string(),
+                identity_address.to_string(),
+                primary_address.to_string()
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM staker_primary_addresses
+                WHERE currency_address = $1 AND identity_address = $2",
+                currency_address.to_string(),
+                identity_address.to_string()
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_staker_primary_address(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Option<Address>> {
+    let address = sqlx::query_scalar!(
+        "SELECT primary_address
+        FROM staker_primary_addresses
+        WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(|address| Address::from_str(&address))
+    .transpose()?;
+
+    Ok(address)
+}
+
+/// Returns the stakers of which the VerusID unlocks before `height`.
+pub async fn get_unlocking_stakers(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    height: u64,
+) -> Result<Vec<Address>> {
+    let rows = sqlx::query!(
+        "SELECT identity_address
+        FROM identity_unlocks
+        WHERE currency_address = $1 AND unlock_height < $2",
+        currency_address.to_string(),
+        height as i64
+    )
+    .try_map(|row| {
+        Address::from_str(&row.identity_address).map_err(|e| sqlx::Error::Decode(e.into()))
+    })
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the stakers of which the work should be forfeited: the hold of their work is over.
+pub async fn get_due_forfeits(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+) -> Result<Vec<Address>> {
+    let rows = sqlx::query!(
+        "SELECT staker_address
+        FROM scheduled_forfeits
+        WHERE currency_address = $1 AND rounds_left = 0",
+        currency_address.to_string()
+    )
+    .try_map(|row| {
+        Address::from_str(&row.staker_address).map_err(|e| sqlx::Error::Decode(e.into()))
+    })
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn get_forfeited_shares_by_round(
+    pool: &PgPool,
+    currency_address: &Address,
+    round_id: u64,
+) -> Result<Decimal> {
+    let shares: Option<Decimal> = sqlx::query_scalar!(
+        "SELECT SUM(shares) FROM forfeited_work WHERE currency_address = $1 AND round_id = $2",
+        currency_address.to_string(),
+        round_id as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(shares.unwrap_or(Decimal::ZERO))
+}
+
+pub async fn get_stake(
+    pool: &PgPool,
+    currency_address: &Address,
+    block_height: u64,
+) -> Result<Option<Stake>> {
+    let value = sqlx::query_as!(
+        DbStake,
+        "SELECT currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS \"status: _\",
+            EXTRACT(EPOCH FROM created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS \"updated_at!\"
+        FROM stakes
+        WHERE currency_address = $1 AND block_height = $2",
+        currency_address.to_string(),
+        block_height as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(value)
+}
+
+/// Gets the stake that closed the round with id `round_id`.
+pub async fn get_stake_by_round(
+    pool: &PgPool,
+    currency_address: &Address,
+    round_id: u64,
+) -> Result<Option<Stake>> {
+    let value = sqlx::query_as!(
+        DbStake,
+        "SELECT s.currency_address,
+            s.block_hash,
+            s.block_height,
+            s.amount,
+            s.found_by,
+            s.source_txid,
+            s.source_vout_num,
+            s.source_amount,
+            s.status AS \"status: _\",
+            EXTRACT(EPOCH FROM s.created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM s.updated_at)::bigint AS \"updated_at!\"
+        FROM stakes s
+        JOIN rounds r ON r.currency_address = s.currency_address AND r.block_hash = s.block_hash
+        WHERE r.currency_address = $1 AND r.id = $2",
+        currency_address.to_string(),
+        round_id as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(value)
+}
+
+/// Stores a newly found stake and closes the open round into a new round for this stake.
+pub async fn store_new_stake(conn: &mut PgConnection, stake: &Stake) -> Result<()> {
+    let mut tx = conn.begin().await?;
+
+    // a reconsidered stake already closed its round and the work in round 0 belongs to the next
+    // round, unless the stake went stale before and its work was moved back to round 0.
+    let (round_id, created) = create_round(&mut tx, stake).await?;
+    if created || round_is_empty(&mut tx, &stake.currency_address, round_id).await? {
+        close_open_round(&mut tx, &stake.currency_address, round_id).await?;
+        store_work_snapshot(&mut tx, stake, round_id).await?;
+    }
+
+    sqlx::query_file!(
+        "sql/store_stake.sql",
+        stake.currency_address.to_string(),
+        stake.block_hash.to_string(),
+        stake.block_height as i64,
+        stake.amount.as_sat() as i64,
+        stake.found_by.to_string(),
+        stake.source_txid.to_string(),
+        stake.source_vout_num as i32,
+        stake.source_amount.as_sat() as i64,
+        stake.status as _
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn store_stake(conn: &mut PgConnection, stake: &Stake) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_stake.sql",
+        stake.currency_address.to_string(),
+        stake.block_hash.to_string(),
+        stake.block_height as i64,
+        stake.amount.as_sat() as i64,
+        stake.found_by.to_string(),
+        stake.source_txid.to_string(),
+        stake.source_vout_num as i32,
+        stake.source_amount.as_sat() as i64,
+        stake.status as _
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_stakes_by_status(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    status: StakeStatus,
+    from_id: Option<u64>,
+) -> Result<Vec<Stake>> {
+    let rows = sqlx::query_as!(
+        DbStake,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS "status: _",
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakes 
+        WHERE currency_address = $1 AND 
+            status = $2 AND 
+            block_height > $3
+        ORDER BY block_height ASC"#,
+        currency_address.to_string(),
+        status as StakeStatus,
+        from_id.unwrap_or(0) as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns stakes of the last `eligibility_confirmations` blocks before <block_height> that are
+/// either maturing or have matured.
+///
+/// This is useful to compensate for work that is lost due to maturing UTXOs because they were
+/// spent because of staking.
+pub async fn get_stakes_to_compensate(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_height: i64,
+    eligibility_confirmations: u32,
+) -> Result<Vec<Stake>> {
+    let rows = sqlx::query_as!(
+        DbStake,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS "status: _",
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakes
+        WHERE currency_address = $1 AND
+            block_height > ($2 - $3) AND
+            (status = 'MATURED' OR status = 'MATURING')
+        ORDER BY block_height ASC"#,
+        currency_address.to_string(),
+        block_height as i64,
+        eligibility_confirmations as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn get_stakes(
+    pool: &PgPool,
+    currency_address: &Address,
+    from_height: Option<u64>,
+) -> Result<Vec<Stake>> {
+    let rows = sqlx::query_as!(
+        DbStake,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS "status: _",
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakes 
+        WHERE currency_address = $1 AND 
+            block_height > $2
+        ORDER BY block_height ASC"#,
+        currency_address.to_string(),
+        from_height.unwrap_or(0) as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Begins the transaction in which a block is processed.
+///
+/// Every write of a block goes through this transaction, including the work of the block and the
+/// last processed height, so a block is applied all-or-nothing. If the pool stops halfway through
+/// a block, nothing of it is stored and the block is processed again from the start.
+pub async fn process_block(pool: &PgPool) -> Result<Transaction<'static, Postgres>> {
+    Ok(pool.begin().await?)
+}
+
+pub async fn update_last_height(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_height: u64,
+) -> Result<()> {
+    let _res = sqlx::query!(
+        "INSERT INTO synchronization (
+            currency_address, 
+            last_height
+        ) VALUES ($1, $2) 
+        ON CONFLICT (currency_address) 
+        DO UPDATE 
+        SET last_height = $2",
+        currency_address.to_string(),
+        block_height as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn store_block_summary(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    summary: &BlockSummary,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO block_summaries (
+            currency_address,
+            block_hash,
+            block_height,
+            stakers_counted,
+            shares_added,
+            identities_checked,
+            stake_found,
+            daemon_staking,
+            phases,
+            duration_ms
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (currency_address, block_hash)
+        DO UPDATE SET
+            stakers_counted = EXCLUDED.stakers_counted,
+            shares_added = EXCLUDED.shares_added,
+            identities_checked = EXCLUDED.identities_checked,
+            stake_found = EXCLUDED.stake_found,
+            daemon_staking = EXCLUDED.daemon_staking,
+            phases = EXCLUDED.phases,
+            duration_ms = EXCLUDED.duration_ms",
+        currency_address.to_string(),
+        summary.block_hash.to_string(),
+        summary.block_height as i64,
+        summary.stakers_counted as i64,
+        summary.shares_added,
+        summary.identities_checked as i64,
+        summary.stake_found,
+        summary.daemon_staking,
+        summary.phases_json(),
+        summary.total_duration().as_millis() as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_last_height(pool: &PgPool, currency_address: &Address) -> Result<Option<u64>> {
+    let row = sqlx::query!(
+        "SELECT last_height 
+        FROM synchronization 
+        WHERE currency_address = $1
+        FOR UPDATE",
+        currency_address.to_string()
+    )
+    .map(|r| r.last_height as u64)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+/// Gets the workers of a round. The work of round 0, the open round, is summed from the share
+/// ledger.
+pub async fn get_workers_by_round(
+    pool: &PgPool,
+    currency_address: &Address,
+    round_id: u64,
+) -> Result<Vec<Worker>> {
+    if round_id == 0 {
+        let workers = sqlx::query_as!(
+            DbWorker,
+            r#"SELECT identity_address, w1.shares AS "shares!", fee FROM stakers s1
+            JOIN open_work w1
+            ON w1.staker_address = s1.identity_address AND s1.currency_address = w1.currency_address
+            WHERE w1.currency_address = $1"#,
+            currency_address.to_string()
+        )
+        .try_map(Worker::try_from)
+        .fetch_all(pool)
+        .await?;
+
+        return Ok(workers);
+    }
+
+    let workers = sqlx::query_as!(
+        DbWorker,
+        "SELECT identity_address, shares, fee FROM stakers s1
+        JOIN work w1
+        ON w1.staker_address = s1.identity_address AND s1.currency_address = w1.currency_address
+        WHERE w1.round_id = $1 AND w1.currency_address = $2",
+        round_id as i64,
+        currency_address.to_string()
+    )
+    .try_map(Worker::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(workers)
+}
+
+pub async fn get_payout_sync_id(pool: &PgPool, currency_address: &Address) -> Result<Option<u64>> {
+    let value = sqlx::query!(
+        "SELECT last_payout_height 
+        FROM synchronization 
+        WHERE currency_address = $1 
+        FOR UPDATE",
+        currency_address.to_string()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(value.map(|row| row.last_payout_height as u64))
+}
+
+/// Returns when the unpaid balances below the min_payout of their staker were last paid, as a
+/// Unix timestamp in seconds.
+pub async fn get_last_dust_sweep(pool: &PgPool, currency_address: &Address) -> Result<Option<u64>> {
+    let value = sqlx::query!(
+        "SELECT EXTRACT(EPOCH FROM last_dust_sweep_at)::bigint AS last_dust_sweep_at
+        FROM synchronization
+        WHERE currency_address = $1",
+        currency_address.to_string()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(value
+        .and_then(|row| row.last_dust_sweep_at)
+        .map(|last_dust_sweep_at| last_dust_sweep_at as u64))
+}
+
+pub async fn update_last_dust_sweep(pool: &PgPool, currency_address: &Address) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO synchronization (currency_address, last_dust_sweep_at)
+        VALUES ($1, NOW())
+        ON CONFLICT (currency_address)
+        DO UPDATE
+        SET last_dust_sweep_at = NOW()",
+        currency_address.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn update_last_payout_height(
+    pool: &mut PgConnection,
+    currency_address: &Address,
+    block_height: u64,
+) -> Result<()> {
+    let _res = sqlx::query!(
+        "INSERT INTO synchronization (
+            currency_address, 
+            last_payout_height
+        ) VALUES ($1, $2) 
+        ON CONFLICT (currency_address) 
+        DO UPDATE 
+        SET last_payout_height = $2",
+        currency_address.to_string(),
+        block_height as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn store_payout(conn: &mut PgConnection, payout: &Payout) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_payout.sql",
+        &payout.currency_address.to_string(),
+        &payout.block_hash.to_string(),
+        payout.block_height as i64,
+        payout.amount.as_sat() as i64,
+        &payout.total_work,
+        payout.fee.as_sat() as i64,
+        payout.paid.as_sat() as i64,
+        payout.members.len() as i64,
+        payout
+            .primary_address
+            .as_ref()
+            .map(|address| address.to_string())
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for decision in &payout.fee_history {
+        sqlx::query!(
+            "INSERT INTO fee_history (
+                currency_address, block_hash, identity_address, staker_fee, source, discount, fee,
+                average_balance
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            payout.currency_address.to_string(),
+            payout.block_hash.to_string(),
+            decision.identity_address.to_string(),
+            decision.staker_fee,
+            decision.source.to_string(),
+            decision.discount,
+            decision.fee,
+            decision.average_balance.as_sat() as i64
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Gets the fees that an admin set for stakers of a currency.
+pub async fn get_fee_overrides(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<HashMap<Address, Decimal>> {
+    let overrides = sqlx::query!(
+        "SELECT identity_address, fee FROM fee_overrides WHERE currency_address = $1",
+        currency_address.to_string()
+    )
+    .try_map(|row| {
+        Ok((
+            Address::from_str(&row.identity_address).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            row.fee,
+        ))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(overrides.into_iter().collect())
+}
+
+/// Sets the fee of a staker, replacing the fee the staker signed up with and the fee tiers.
+pub async fn store_fee_override(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    fee: Decimal,
+    reason: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO fee_overrides (currency_address, identity_address, fee, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (currency_address, identity_address)
+        DO UPDATE SET fee = EXCLUDED.fee, reason = EXCLUDED.reason",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        fee,
+        reason
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Removes the fee override of a staker. Returns whether there was one.
+pub async fn delete_fee_override(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM fee_overrides WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn store_payout_member(
+    conn: &mut PgConnection,
+    payout_member: &PayoutMember,
+) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_payout_member.sql",
+        &payout_member.currency_address.to_string(),
+        &payout_member.identity_address.to_string(),
+        &payout_member.block_hash.to_string(),
+        payout_member.block_height as i64,
+        payout_member.shares,
+        payout_member.reward.as_sat() as i64,
+        payout_member.fee.as_sat() as i64,
+        None::<&str>
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores a member of the payout that the shadow payout scheme calculated for a stake.
+pub async fn store_payout_shadow_member(
+    conn: &mut PgConnection,
+    payout_member: &PayoutMember,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO payout_shadow (
+            currency_address, identity_address, block_hash, block_height, shares, reward, fee
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (currency_address, identity_address, block_hash)
+        DO UPDATE SET shares = EXCLUDED.shares, reward = EXCLUDED.reward, fee = EXCLUDED.fee",
+        payout_member.currency_address.to_string(),
+        payout_member.identity_address.to_string(),
+        payout_member.block_hash.to_string(),
+        payout_member.block_height as i64,
+        payout_member.shares,
+        payout_member.reward.as_sat() as i64,
+        payout_member.fee.as_sat() as i64,
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Compares the payouts with the shadow payouts of the same stakes, and returns the stakers of
+/// which the rewards differ by more than `min_difference`, the largest difference first.
+///
+/// Only stakes that have a shadow payout are compared. A staker that is in only one of both
+/// payouts counts as a reward of 0 in the other.
+pub async fn get_payout_shadow_diff(
+    pool: &PgPool,
+    currency_address: &Address,
+    min_difference: Amount,
+) -> Result<Vec<PayoutShadowDiff>> {
+    let diff = sqlx::query_as!(
+        DbPayoutShadowDiff,
+        r#"WITH members AS (
+            SELECT identity_address, block_hash, block_height, reward
+            FROM payout_members
+            WHERE currency_address = $1
+                AND block_hash IN (
+                    SELECT block_hash FROM payout_shadow WHERE currency_address = $1
+                )
+        ), shadow AS (
+            SELECT identity_address, block_hash, block_height, reward
+            FROM payout_shadow
+            WHERE currency_address = $1
+        )
+        SELECT
+            COALESCE(m.block_hash, s.block_hash) AS "block_hash!",
+            COALESCE(m.block_height, s.block_height) AS "block_height!",
+            COALESCE(m.identity_address, s.identity_address) AS "identity_address!",
+            m.reward AS "reward?",
+            s.reward AS "shadow_reward?",
+            COALESCE(s.reward, 0) - COALESCE(m.reward, 0) AS "difference!"
+        FROM members m
+        FULL OUTER JOIN shadow s
+            ON s.block_hash = m.block_hash AND s.identity_address = m.identity_address
+        WHERE ABS(COALESCE(s.reward, 0) - COALESCE(m.reward, 0)) > $2
+        ORDER BY ABS(COALESCE(s.reward, 0) - COALESCE(m.reward, 0)) DESC, "identity_address!" ASC"#,
+        currency_address.to_string(),
+        min_difference.as_sat() as i64
+    )
+    .try_map(PayoutShadowDiff::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(diff)
+}
+
+pub async fn get_payout_members(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_addresses: &[Address],
+) -> Result<Vec<PayoutMember>> {
+    let values = sqlx::query_as!(
+        DbPayoutMember,
+        "SELECT 
+            currency_address,
+            identity_address,
+            block_hash,
+            block_height,
+            shares,
+            reward,
+            fee,
+            txid,
+            EXTRACT(EPOCH FROM created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS \"updated_at!\"
+        FROM payout_members 
+        WHERE currency_address = $1 
+        AND identity_address IN (SELECT * FROM UNNEST($2::text[]))",
+        currency_address.to_string(),
+        &identity_addresses
+            .iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(values)
+}
+
+/// Get all payout members that have not been paid yet.
+///
+/// The payoutmembers are selected on their min_payout settings.
+/// If a staker has left the pool and its settlement is due, all remaining funds will be paid,
+/// disregarding the min_payout settings of the staker.
+/// With an `aligned_chain`, the funds of a staker that was paid on that chain after its oldest
+/// unpaid reward on this chain are paid too, disregarding the min_payout settings of the staker.
+/// During a `dust_sweep`, the funds of every staker are paid, disregarding the min_payout
+/// settings of the staker.
+/// The funds of a staker below `min_payable` are never paid, as they can't be sent.
+///
+/// The query locks the rows until the transaction is committed (or dropped on error).
+pub async fn get_unpaid_payout_members(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    aligned_chain: Option<&Address>,
+    min_payable: Amount,
+    dust_sweep: bool,
+) -> Result<Vec<PayoutMember>> {
+    let values = sqlx::query_as!(
+        DbPayoutMember,
+        "WITH pm_sum AS (
+            SELECT
+                currency_address,
+                identity_address,
+                SUM(reward) AS total_rewards,
+                MIN(created_at) AS oldest_unpaid_at
+            FROM payout_members
+            WHERE currency_address = $1
+                AND txid is NULL
+            GROUP BY currency_address, identity_address
+        )
+        SELECT 
+            pm.currency_address,
+            pm.identity_address,
+            pm.block_hash,
+            pm.block_height,
+            pm.shares,
+            pm.reward,
+            pm.fee,
+            pm.txid,
+            EXTRACT(EPOCH FROM pm.created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM pm.updated_at)::bigint AS \"updated_at!\"
+        FROM payout_members pm
+        JOIN pm_sum ON pm.currency_address = pm_sum.currency_address
+            AND pm.identity_address = pm_sum.identity_address
+            AND pm.txid IS NULL
+        JOIN stakers s ON pm.currency_address = s.currency_address
+            AND pm.identity_address = s.identity_address
+        WHERE pm_sum.total_rewards >= $3
+            AND (
+                pm_sum.total_rewards > s.min_payout
+                OR (
+                    s.status = 'INACTIVE'
+                    AND EXISTS (
+                        SELECT 1 FROM staker_settlements ss
+                        WHERE ss.currency_address = s.currency_address
+                            AND ss.identity_address = s.identity_address
+                            AND ss.settle_at <= NOW()
+                    )
+                )
+                OR EXISTS (
+                    SELECT 1 FROM payment_items pi
+                    JOIN payments p ON p.currency_address = pi.currency_address AND p.txid = pi.txid
+                    WHERE pi.currency_address = $2
+                        AND pi.identity_address = pm.identity_address
+                        AND p.status != 'FAILED'
+                        AND p.created_at > pm_sum.oldest_unpaid_at
+                )
+                OR $4
+            )
+        FOR UPDATE",
+        currency_address.to_string(),
+        aligned_chain.map(|chain| chain.to_string()),
+        min_payable.as_sat() as i64,
+        dust_sweep,
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(values)
+}
+
+/// Gets all unpaid payout members of a single staker, regardless of its min_payout settings.
+///
+/// The query locks the rows until the transaction is committed (or dropped on error), so that
+/// the payout service can't pay them at the same time.
+pub async fn get_unpaid_payout_members_of_staker(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Vec<PayoutMember>> {
+    let values = sqlx::query_as!(
+        DbPayoutMember,
+        "SELECT
+            currency_address,
+            identity_address,
+            block_hash,
+            block_height,
+            shares,
+            reward,
+            fee,
+            txid,
+            EXTRACT(EPOCH FROM created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS \"updated_at!\"
+        FROM payout_members
+        WHERE currency_address = $1
+            AND identity_address = $2
+            AND txid IS NULL
+        ORDER BY block_height
+        FOR UPDATE",
+        currency_address.to_string(),
+        identity_address.to_string(),
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(values)
+}
+
+pub async fn store_manual_payment(conn: &mut PgConnection, payment: &ManualPayment) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO manual_payments (
+            currency_address,
+            identity_address,
+            txid,
+            amount,
+            n_rounds,
+            operator,
+            reason
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        payment.currency_address.to_string(),
+        payment.identity_address.to_string(),
+        payment.txid.to_string(),
+        payment.amount.as_sat() as i64,
+        payment.n_rounds as i64,
+        payment.operator,
+        payment.reason
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_txid_payment_member(
+    conn: &mut PgConnection,
+    payout_member: &PayoutMember,
+    txid: &Txid,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE payout_members 
+        SET txid = $4 
+        WHERE currency_address = $1 AND 
+            identity_address = $2 AND 
+            block_height = $3",
+        payout_member.currency_address.to_string(),
+        payout_member.identity_address.to_string(),
+        payout_member.block_height as i64,
+        txid.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn store_payment(conn: &mut PgConnection, payment: &Payment) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_payment.sql",
+        payment.currency_address.to_string(),
+        payment.txid.to_string(),
+        payment.amount.as_sat() as i64,
+        payment.n_members as i64,
+        payment.status as _,
+        payment.confirmations as i64,
+        payment.tx_fee.as_sat() as i64,
+        payment.tx_fee_paid_by_pool
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the per staker totals of a payment, with the block hashes of the payout members they
+/// cover.
+pub async fn store_payment_items(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    txid: &Txid,
+    items: &[PaymentItem],
+) -> Result<()> {
+    for item in items {
+        let block_hashes = item
+            .block_hashes
+            .iter()
+            .map(|block_hash| block_hash.to_string())
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            "INSERT INTO payment_items (
+                currency_address,
+                txid,
+                identity_address,
+                amount,
+                block_hashes,
+                destination,
+                payout_currency
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            currency_address.to_string(),
+            txid.to_string(),
+            item.identity_address.to_string(),
+            item.amount.as_sat() as i64,
+            &block_hashes,
+            item.destination().to_string(),
+            item.payout_currency
+                .as_ref()
+                .map(|payout_currency| payout_currency.to_string())
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn get_payments_by_status(
+    pool: &PgPool,
+    currency_address: &Address,
+    status: PaymentStatus,
+) -> Result<Vec<Payment>> {
+    let rows = sqlx::query_as!(
+        DbPayment,
+        r#"SELECT
+            currency_address,
+            txid,
+            amount,
+            n_members,
+            tx_fee,
+            tx_fee_paid_by_pool,
+            status AS "status: _",
+            confirmations,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM payments
+        WHERE currency_address = $1 AND status = $2
+        ORDER BY created_at ASC"#,
+        currency_address.to_string(),
+        status as PaymentStatus
+    )
+    .try_map(Payment::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Gets the unix timestamp (in seconds) of the last payment that did not fail.
+pub async fn get_last_payment_time(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Option<u64>> {
+    let timestamp = sqlx::query_scalar!(
+        "SELECT EXTRACT(EPOCH FROM MAX(created_at))::bigint
+        FROM payments
+        WHERE currency_address = $1 AND status <> 'FAILED'",
+        currency_address.to_string()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(timestamp.map(|timestamp| timestamp as u64))
+}
+
+pub async fn store_payment_batch(pool: &PgPool, batch: &PaymentBatch) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO payment_batches (currency_address, txid, size, latency_ms)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id",
+        batch.currency_address.to_string(),
+        batch.txid.map(|txid| txid.to_string()),
+        batch.size as i64,
+        batch.latency.as_millis() as i64
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for (identity_address, block_hash) in &batch.payout_members {
+        sqlx::query!(
+            "INSERT INTO payment_batch_members (payment_batch_id, identity_address, block_hash)
+            VALUES ($1, $2, $3)",
+            id,
+            identity_address.to_string(),
+            block_hash.to_string()
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(id as u64)
+}
+
+/// Gets the ids of the payment batches that included the payout member of `identity_address`
+/// for the stake in `block_hash`, oldest first, with the txid of the batch if it was sent.
+pub async fn get_payment_batches_of_member(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    block_hash: &BlockHash,
+) -> Result<Vec<(u64, Option<Txid>)>> {
+    let batches = sqlx::query!(
+        "SELECT pb.id, pb.txid FROM payment_batches pb
+        JOIN payment_batch_members pbm ON pbm.payment_batch_id = pb.id
+        WHERE pb.currency_address = $1 AND pbm.identity_address = $2 AND pbm.block_hash = $3
+        ORDER BY pb.id",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        block_hash.to_string()
+    )
+    .try_map(|row| {
+        let txid = row
+            .txid
+            .map(|txid| Txid::from_str(&txid))
+            .transpose()
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+        Ok((row.id as u64, txid))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(batches)
+}
+
+/// Marks a payment as failed and reopens its payout members, so they are paid again in the
+/// next payment run.
+pub async fn fail_payment(pool: &PgPool, payment: &Payment) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE payout_members
+        SET txid = NULL
+        WHERE currency_address = $1 AND txid = $2",
+        payment.currency_address.to_string(),
+        payment.txid.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE payments
+        SET status = 'FAILED'
+        WHERE currency_address = $1 AND txid = $2",
+        payment.currency_address.to_string(),
+        payment.txid.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Gets the paid out stakes and confirmed payments that were not yet exported to the bookkeeping
+/// API, oldest first.
+pub async fn get_unexported_accounting_entries(
+    pool: &PgPool,
+    currency_address: &Address,
+    limit: u64,
+) -> Result<Vec<AccountingEntry>> {
+    let rows = sqlx::query_as!(
+        DbAccountingEntry,
+        r#"SELECT
+            'STAKE_INCOME'::accounting_entry_kind AS "kind!: AccountingEntryKind",
+            p.currency_address AS "currency_address!",
+            p.block_hash AS "reference!",
+            p.block_height AS "block_height?",
+            p.amount AS "amount!",
+            p.fee AS "fee!",
+            EXTRACT(EPOCH FROM p.created_at)::bigint AS "timestamp!"
+        FROM payouts p
+        WHERE p.currency_address = $1
+            AND NOT EXISTS (
+                SELECT 1 FROM accounting_exports e
+                WHERE e.currency_address = p.currency_address
+                    AND e.kind = 'STAKE_INCOME'
+                    AND e.reference = p.block_hash
+            )
+        UNION ALL
+        SELECT
+            'PAYMENT'::accounting_entry_kind,
+            pm.currency_address,
+            pm.txid,
+            NULL::bigint,
+            pm.amount,
+            pm.tx_fee,
+            EXTRACT(EPOCH FROM pm.created_at)::bigint
+        FROM payments pm
+        WHERE pm.currency_address = $1
+            AND pm.status = 'CONFIRMED'
+            AND NOT EXISTS (
+                SELECT 1 FROM accounting_exports e
+                WHERE e.currency_address = pm.currency_address
+                    AND e.kind = 'PAYMENT'
+                    AND e.reference = pm.txid
+            )
+        ORDER BY 7 ASC
+        LIMIT $2"#,
+        currency_address.to_string(),
+        limit as i64
+    )
+    .try_map(AccountingEntry::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+pub async fn store_accounting_export(
+    pool: &PgPool,
+    currency_address: &Address,
+    kind: AccountingEntryKind,
+    reference: &str,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO accounting_exports (currency_address, kind, reference)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING",
+        currency_address.to_string(),
+        kind as AccountingEntryKind,
+        reference
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets the ids of the rounds other than round 0 that have work assigned, but no stake that
+/// closed them.
+pub async fn get_orphaned_work_rounds(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<u64>> {
+    let rounds = sqlx::query!(
+        "SELECT DISTINCT w.round_id
+        FROM work w
+        WHERE w.currency_address = $1
+            AND w.round_id <> 0
+            AND NOT EXISTS (
+                SELECT 1 FROM rounds r
+                JOIN stakes s
+                ON s.currency_address = r.currency_address AND s.block_hash = r.block_hash
+                WHERE r.currency_address = w.currency_address
+                    AND r.id = w.round_id
+            )
+        ORDER BY w.round_id ASC",
+        currency_address.to_string()
+    )
+    .map(|row| row.round_id as u64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rounds)
+}
+
+pub async fn get_payout_members_by_block_hash(
+    pool: &PgPool,
+    currency_address: &Address,
+    block_hash: &BlockHash,
+) -> Result<Vec<PayoutMember>> {
+    let values = sqlx::query_as!(
+        DbPayoutMember,
+        "SELECT
+            currency_address,
+            identity_address,
+            block_hash,
+            block_height,
+            shares,
+            reward,
+            fee,
+            txid,
+            EXTRACT(EPOCH FROM created_at)::bigint AS \"created_at!\",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS \"updated_at!\"
+        FROM payout_members
+        WHERE currency_address = $1 AND block_hash = $2",
+        currency_address.to_string(),
+        block_hash.to_string()
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(values)
+}
+
+/// Returns the id of the round that holds the work of the block at `block_height`, and the
+/// height of the stake that closed the round before it.
+///
+/// That is the first round that was closed after `block_height`, or round 0 if there is none.
+pub async fn get_round_containing(
+    pool: &PgPool,
+    currency_address: &Address,
+    block_height: u64,
+) -> Result<(u64, u64)> {
+    let row = sqlx::query!(
+        r#"SELECT
+            (
+                SELECT id FROM rounds
+                WHERE currency_address = $1 AND block_height > $2
+                ORDER BY block_height ASC, id ASC
+                LIMIT 1
+            ) AS round_id,
+            (
+                SELECT MAX(block_height) FROM rounds
+                WHERE currency_address = $1 AND block_height <= $2
+            ) AS start_height"#,
+        currency_address.to_string(),
+        block_height as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok((
+        row.round_id.unwrap_or(0) as u64,
+        row.start_height.unwrap_or(0) as u64,
+    ))
+}
+
+/// Returns the heights and hashes of the last `limit` processed blocks, highest first.
+pub async fn get_processed_blocks(
+    pool: &PgPool,
+    currency_address: &Address,
+    limit: u64,
+) -> Result<Vec<(u64, BlockHash)>> {
+    let rows = sqlx::query!(
+        "SELECT block_height, block_hash
+        FROM block_summaries
+        WHERE currency_address = $1
+        ORDER BY block_height DESC, created_at DESC
+        LIMIT $2",
+        currency_address.to_string(),
+        limit as i64
+    )
+    .try_map(|row| {
+        let block_hash =
+            BlockHash::from_str(&row.block_hash).map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+        Ok((row.block_height as u64, block_hash))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Deletes the summaries of blocks that were orphaned by a reorg.
+pub async fn delete_block_summaries(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_hashes: &[BlockHash],
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM block_summaries WHERE currency_address = $1 AND block_hash = ANY($2)",
+        currency_address.to_string(),
+        &block_hashes
+            .iter()
+            .map(|block_hash| block_hash.to_string())
+            .collect::<Vec<_>>()
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the shares that were added in the blocks after `from_height` up to and including
+/// `to_height`, according to the block summaries.
+pub async fn get_shares_added(
+    pool: &PgPool,
+    currency_address: &Address,
+    from_height: u64,
+    to_height: u64,
+) -> Result<Decimal> {
+    let shares: Option<Decimal> = sqlx::query_scalar!(
+        "SELECT SUM(shares_added) FROM block_summaries
+        WHERE currency_address = $1 AND block_height > $2 AND block_height <= $3",
+        currency_address.to_string(),
+        from_height as i64,
+        to_height as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(shares.unwrap_or_default())
+}
+
+/// Stores a stake that the pool missed, and splits `fraction` of the work and forfeited work of
+/// the round with id `from_round` off into a new round for it. Returns the id of the new round.
+pub async fn store_historical_stake(
+    tx: &mut Transaction<'_, Postgres>,
+    stake: &Stake,
+    from_round: u64,
+    fraction: Decimal,
+) -> Result<u64> {
+    let (round_id, created) = create_round(tx, stake).await?;
+    if !created {
+        anyhow::bail!("stake {} already has a round", stake.block_hash);
+    }
+
+    if from_round == 0 {
+        split_open_round(tx, stake, round_id, fraction).await?;
+    }
+
+    for table in ["work", "forfeited_work"] {
+        // the work of the open round is in the share ledger
+        if table == "work" && from_round == 0 {
+            continue;
+        }
+
+        sqlx::query(&format!(
+            "INSERT INTO {table} (currency_address, round_id, staker_address, shares)
+            SELECT currency_address, $3, staker_address, shares * $4
+            FROM {table}
+            WHERE currency_address = $1 AND round_id = $2"
+        ))
+        .bind(stake.currency_address.to_string())
+        .bind(from_round as i64)
+        .bind(round_id as i64)
+        .bind(fraction)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE {table} SET shares = shares - shares * $3
+            WHERE currency_address = $1 AND round_id = $2"
+        ))
+        .bind(stake.currency_address.to_string())
+        .bind(from_round as i64)
+        .bind(fraction)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    sqlx::query_file!(
+        "sql/store_stake.sql",
+        stake.currency_address.to_string(),
+        stake.block_hash.to_string(),
+        stake.block_height as i64,
+        stake.amount.as_sat() as i64,
+        stake.found_by.to_string(),
+        stake.source_txid.to_string(),
+        stake.source_vout_num as i32,
+        stake.source_amount.as_sat() as i64,
+        stake.status as _
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(round_id)
+}
+
+/// Moves `fraction` of the work of every staker in the open round into the round with id
+/// `round_id`, by appending the split to the share ledger.
+async fn split_open_round(
+    tx: &mut Transaction<'_, Postgres>,
+    stake: &Stake,
+    round_id: u64,
+    fraction: Decimal,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO work (currency_address, round_id, staker_address, shares)
+        SELECT currency_address, $2, staker_address, shares * $3
+        FROM open_work
+        WHERE currency_address = $1",
+        stake.currency_address.to_string(),
+        round_id as i64,
+        fraction
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO share_events (currency_address, staker_address, block_height, kind, delta)
+        SELECT currency_address, staker_address, $2, 'SPLIT', -(shares * $3)
+        FROM open_work
+        WHERE currency_address = $1",
+        stake.currency_address.to_string(),
+        stake.block_height as i64,
+        fraction
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves the work and forfeited work of the round with id `from_round` into the round with id
+/// `into_round`, adding up the shares of stakers that have work in both rounds.
+pub async fn merge_work_rounds(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    from_round: u64,
+    into_round: u64,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO work (currency_address, round_id, staker_address, shares)
+        SELECT currency_address, $3, staker_address, shares
+        FROM work
+        WHERE currency_address = $1 AND round_id = $2
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = work.shares + EXCLUDED.shares",
+        currency_address.to_string(),
+        from_round as i64,
+        into_round as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM work WHERE currency_address = $1 AND round_id = $2",
+        currency_address.to_string(),
+        from_round as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "INSERT INTO forfeited_work (currency_address, round_id, staker_address, shares)
+        SELECT currency_address, $3, staker_address, shares
+        FROM forfeited_work
+        WHERE currency_address = $1 AND round_id = $2
+        ON CONFLICT (currency_address, round_id, staker_address)
+        DO UPDATE SET shares = forfeited_work.shares + EXCLUDED.shares",
+        currency_address.to_string(),
+        from_round as i64,
+        into_round as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM forfeited_work WHERE currency_address = $1 AND round_id = $2",
+        currency_address.to_string(),
+        from_round as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the payout of a stake and its payout members, so that it can be generated again.
+///
+/// Fails if any of the payout members was already paid.
+pub async fn delete_unpaid_payout(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_hash: &BlockHash,
+) -> Result<()> {
+    let paid = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!"
+        FROM payout_members
+        WHERE currency_address = $1 AND block_hash = $2 AND txid IS NOT NULL"#,
+        currency_address.to_string(),
+        block_hash.to_string()
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if paid > 0 {
+        anyhow::bail!("the payout of {block_hash} was already (partially) paid");
+    }
+
+    sqlx::query!(
+        "DELETE FROM payout_members WHERE currency_address = $1 AND block_hash = $2",
+        currency_address.to_string(),
+        block_hash.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM fee_history WHERE currency_address = $1 AND block_hash = $2",
+        currency_address.to_string(),
+        block_hash.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM payouts WHERE currency_address = $1 AND block_hash = $2",
+        currency_address.to_string(),
+        block_hash.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn store_round_merge(conn: &mut PgConnection, merge: &RoundMerge) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO round_merges (currency_address, from_round, into_round, changes)
+        VALUES ($1, $2, $3, $4)",
+        merge.currency_address.to_string(),
+        merge.from_round as i64,
+        merge.into_round as i64,
+        serde_json::to_string(&merge.changes)?
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets the addresses that were whitelisted as a destination for funds in the database.
+pub async fn get_whitelisted_destinations(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<Address>> {
+    let addresses = sqlx::query!(
+        "SELECT address FROM destination_whitelist WHERE currency_address = $1",
+        currency_address.to_string()
+    )
+    .try_map(|row| Address::from_str(&row.address).map_err(|e| sqlx::Error::Decode(e.into())))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(addresses)
+}
+
+/// Gets the sum of the pool fees of all payouts, minus the fees that were swept already and the
+/// tx fees of the payments that the pool paid.
+pub async fn get_unswept_fees(pool: &PgPool, currency_address: &Address) -> Result<Amount> {
+    let sum = sqlx::query!(
+        r#"SELECT (
+            (SELECT COALESCE(SUM(fee), 0) FROM payouts WHERE currency_address = $1)
+            - (SELECT COALESCE(SUM(amount), 0) FROM fee_sweeps WHERE currency_address = $1)
+            - (SELECT COALESCE(SUM(tx_fee), 0) FROM payments
+                WHERE currency_address = $1 AND tx_fee_paid_by_pool AND status <> 'FAILED')
+        )::bigint AS "sum!""#,
+        currency_address.to_string()
+    )
+    .map(|row| Amount::from_sat(row.sum.max(0) as u64))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(sum)
+}
+
+pub async fn store_fee_sweep(
+    pool: &PgPool,
+    currency_address: &Address,
+    txid: &Txid,
+    destination: &Address,
+    amount: Amount,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO fee_sweeps (currency_address, txid, destination, amount)
+        VALUES ($1, $2, $3, $4)",
+        currency_address.to_string(),
+        txid.to_string(),
+        destination.to_string(),
+        amount.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets the sum of the rewards of all payout members that have not been paid yet.
+pub async fn get_unpaid_rewards(pool: &PgPool, currency_address: &Address) -> Result<Amount> {
+    let sum = sqlx::query!(
+        r#"SELECT COALESCE(SUM(reward), 0)::bigint AS "sum!"
+        FROM payout_members
+        WHERE currency_address = $1 AND txid IS NULL"#,
+        currency_address.to_string()
+    )
+    .map(|row| Amount::from_sat(row.sum as u64))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(sum)
+}
+
+/// Gets the unpaid rewards per staker, highest first.
+pub async fn get_staker_liabilities(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<StakerLiability>> {
+    let liabilities = sqlx::query_as!(
+        DbStakerLiability,
+        r#"SELECT
+            identity_address,
+            SUM(reward)::bigint AS "unpaid!",
+            COUNT(*) AS "n_rounds!",
+            MIN(block_height) AS "oldest_unpaid_height!",
+            EXTRACT(EPOCH FROM MIN(created_at))::bigint AS "oldest_unpaid_at!"
+        FROM payout_members
+        WHERE currency_address = $1 AND txid IS NULL
+        GROUP BY identity_address
+        ORDER BY SUM(reward) DESC, identity_address ASC"#,
+        currency_address.to_string()
+    )
+    .try_map(StakerLiability::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(liabilities)
+}
+
+/// Gets the heights of matured stakes up to and including `up_to_height` that have no payout.
+pub async fn get_matured_stakes_without_payout(
+    pool: &PgPool,
+    currency_address: &Address,
+    up_to_height: u64,
+) -> Result<Vec<u64>> {
+    let heights = sqlx::query!(
+        "SELECT s.block_height
+        FROM stakes s
+        WHERE s.currency_address = $1
+            AND s.status = 'MATURED'
+            AND s.block_height <= $2
+            AND NOT EXISTS (
+                SELECT 1 FROM payouts p
+                WHERE p.currency_address = s.currency_address
+                    AND p.block_hash = s.block_hash
+            )
+        ORDER BY s.block_height ASC",
+        currency_address.to_string(),
+        up_to_height as i64
+    )
+    .map(|row| row.block_height as u64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(heights)
+}
+
+pub async fn get_number_of_matured_stakes(
+    conn: &PgPool,
+    currency_address: &Address,
+) -> Result<i64> {
+    let res: Option<i64> = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM stakes WHERE currency_address = $1 AND status = 'MATURED'",
+        currency_address.to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(res.unwrap_or(0))
+}
+
+pub async fn get_number_of_active_stakers(
+    conn: &PgPool,
+    currency_address: &Address,
+) -> Result<i64> {
+    let res: Option<i64> = sqlx::query_scalar!(
+        "SELECT COUNT(*) FROM stakers WHERE currency_address = $1 AND status = 'ACTIVE'",
+        currency_address.to_string()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(res.unwrap_or(0))
+}
+
+pub async fn get_total_rewards(conn: &PgPool, currency_address: &Address) -> Result<Amount> {
+    let res: Option<Amount> = sqlx::query!(
+        "SELECT (
+            COALESCE((
+                SELECT SUM(reward)
+                FROM payout_members
+                WHERE currency_address = $1 AND txid IS NOT NULL
+            ), 0) + COALESCE((
+                SELECT SUM(reward) FROM payout_member_archive WHERE currency_address = $1
+            ), 0)
+        )::bigint AS total",
+        currency_address.to_string()
+    )
+    .fetch_one(conn)
+    .await?
+    .total
+    .map(|r| Amount::from_sat(r as u64));
+
+    Ok(res.unwrap_or(Amount::ZERO))
+}
+
+pub async fn store_network_stats(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_height: u64,
+    block_hash: &BlockHash,
+    staking_supply: f64,
+    difficulty: f64,
+    is_stake: bool,
+) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_network_stats.sql",
+        currency_address.to_string(),
+        block_height as i64,
+        block_hash.to_string(),
+        staking_supply,
+        difficulty,
+        is_stake
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the recorded network conditions above `from_height`, in ascending order.
+///
+/// The PoS ratio is calculated over the last 100 recorded blocks, so the first 100 blocks after
+/// enabling the collector will show a ratio over fewer blocks.
+pub async fn get_network_stats(
+    pool: &PgPool,
+    currency_address: &Address,
+    from_height: Option<u64>,
+    limit: u64,
+) -> Result<Vec<NetworkStats>> {
+    let rows = sqlx::query_as!(
+        DbNetworkStats,
+        r#"SELECT
+            block_height,
+            block_hash,
+            staking_supply,
+            difficulty,
+            pos_ratio AS "pos_ratio!"
+        FROM (
+            SELECT
+                block_height,
+                block_hash,
+                staking_supply,
+                difficulty,
+                (AVG(CASE WHEN is_stake THEN 1.0 ELSE 0.0 END) OVER (
+                    ORDER BY block_height ROWS BETWEEN 99 PRECEDING AND CURRENT ROW
+                ))::float8 AS pos_ratio
+            FROM network_stats
+            WHERE currency_address = $1
+        ) ns
+        WHERE block_height > $2
+        ORDER BY block_height ASC
+        LIMIT $3"#,
+        currency_address.to_string(),
+        from_height.unwrap_or(0) as i64,
+        limit as i64
+    )
+    .try_map(NetworkStats::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Stores the staking supply of the network and the pool at a block, to compare the stakes of
+/// the pool with its share of the staking supply.
+pub async fn store_network_snapshot(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    block_height: u64,
+    block_hash: &BlockHash,
+    network_staking_supply: f64,
+    pool_staking_supply: Amount,
+    is_stake: bool,
+) -> Result<()> {
+    sqlx::query_file!(
+        "sql/store_network_snapshot.sql",
+        currency_address.to_string(),
+        block_height as i64,
+        block_hash.to_string(),
+        network_staking_supply,
+        pool_staking_supply.as_sat() as i64,
+        is_stake
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the luck of the pool over the network snapshots above `from_height`, or `None` if
+/// there are none.
+///
+/// Every PoS block adds the share of the pool in the network staking supply to the expected
+/// stakes. The times are the times at which the blocks were processed.
+pub async fn get_pool_luck(
+    pool: &PgPool,
+    currency_address: &Address,
+    from_height: u64,
+) -> Result<Option<PoolLuck>> {
+    let row = sqlx::query_as!(
+        DbPoolLuck,
+        r#"WITH snapshots AS (
+            SELECT block_height, block_hash, network_staking_supply, pool_staking_supply, is_stake, created_at
+            FROM network_snapshots
+            WHERE currency_address = $1 AND block_height > $2
+        ), found AS (
+            SELECT sn.created_at
+            FROM stakes s
+            JOIN snapshots sn
+                ON sn.block_height = s.block_height AND sn.block_hash = s.block_hash
+            WHERE s.currency_address = $1
+                AND (s.status = 'MATURED' OR s.status = 'MATURING')
+        )
+        SELECT
+            (SELECT MIN(block_height) FROM snapshots) AS from_height,
+            (SELECT MAX(block_height) FROM snapshots) AS to_height,
+            (
+                SELECT COALESCE(
+                    SUM(pool_staking_supply::float8 / 100000000.0 / network_staking_supply)
+                        FILTER (WHERE is_stake AND network_staking_supply > 0),
+                    0
+                )::float8
+                FROM snapshots
+            ) AS "expected_stakes!",
+            (SELECT COUNT(*) FROM found) AS "actual_stakes!",
+            (
+                SELECT EXTRACT(EPOCH FROM MAX(created_at) - MIN(created_at))::float8
+                FROM snapshots
+            ) AS period_secs,
+            (
+                SELECT EXTRACT(EPOCH FROM MAX(created_at) - MIN(created_at))::float8
+                FROM found
+            ) AS stake_span_secs"#,
+        currency_address.to_string(),
+        from_height as i64
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.into_pool_luck())
+}
+
+/// Stores the evidence of a stake that was caught by StakeGuard. Collecting it again replaces
+/// the evidence.
+pub async fn store_fraud_evidence(conn: &mut PgConnection, evidence: &FraudEvidence) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO fraud_evidence (
+            currency_address,
+            block_hash,
+            block_height,
+            identity_address,
+            source_txid,
+            source_vout_num,
+            spend_txid,
+            spend_block_hash,
+            spend_tx,
+            proof,
+            competing_txid,
+            competing_source_txid,
+            competing_source_vout_num
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        ON CONFLICT (currency_address, block_hash) DO
+        UPDATE SET
+            spend_txid = $7,
+            spend_block_hash = $8,
+            spend_tx = $9,
+            proof = $10,
+            competing_txid = $11,
+            competing_source_txid = $12,
+            competing_source_vout_num = $13",
+        evidence.currency_address.to_string(),
+        evidence.block_hash.to_string(),
+        evidence.block_height as i64,
+        evidence.identity_address.to_string(),
+        evidence.source_txid.to_string(),
+        evidence.source_vout_num as i32,
+        evidence.spend_txid.to_string(),
+        evidence.spend_block_hash.map(|hash| hash.to_string()),
+        &evidence.spend_tx,
+        evidence.proof.as_deref(),
+        evidence.competing_txid.map(|txid| txid.to_string()),
+        evidence.competing_source_txid.map(|txid| txid.to_string()),
+        evidence.competing_source_vout_num.map(|vout| vout as i64)
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the evidence of all the stakes that were caught by StakeGuard, the most recent first.
+pub async fn get_fraud_evidence(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<FraudEvidence>> {
+    let rows = sqlx::query_as!(
+        DbFraudEvidence,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            identity_address,
+            source_txid,
+            source_vout_num,
+            spend_txid,
+            spend_block_hash,
+            spend_tx,
+            proof,
+            competing_txid,
+            competing_source_txid,
+            competing_source_vout_num,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM fraud_evidence
+        WHERE currency_address = $1
+        ORDER BY block_height DESC"#,
+        currency_address.to_string()
+    )
+    .try_map(FraudEvidence::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Stores whether the VerusID of a staker includes the new pool primary address.
+///
+/// The `notified` flag is never reset once it was set.
+pub async fn store_rotation_progress(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    progress: &RotationProgress,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO primary_address_rotation (
+            currency_address,
+            identity_address,
+            new_address,
+            migrated,
+            notified
+        ) VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (currency_address, identity_address, new_address)
+        DO UPDATE
+        SET migrated = $4, notified = primary_address_rotation.notified OR $5",
+        currency_address.to_string(),
+        progress.identity_address.to_string(),
+        progress.new_address.to_string(),
+        progress.migrated,
+        progress.notified
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_rotation_progress(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    new_address: &Address,
+) -> Result<Vec<RotationProgress>> {
+    let rows = sqlx::query_as!(
+        DbRotationProgress,
+        "SELECT identity_address, new_address, migrated, notified
+        FROM primary_address_rotation
+        WHERE currency_address = $1 AND new_address = $2",
+        currency_address.to_string(),
+        new_address.to_string()
+    )
+    .try_map(RotationProgress::try_from)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Stores a rotation of the pool primary address that an operator started. Fails if the currency
+/// is already rotating.
+pub async fn store_key_rotation(
+    pool: &PgPool,
+    currency_address: &Address,
+    old_address: &Address,
+    new_address: &Address,
+    ends_at: u64,
+    operator: &str,
+) -> Result<KeyRotation> {
+    let rotation = sqlx::query_as!(
+        DbKeyRotation,
+        r#"INSERT INTO key_rotations (currency_address, old_address, new_address, ends_at, operator)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING
+            currency_address,
+            old_address,
+            new_address,
+            ends_at,
+            operator,
+            EXTRACT(EPOCH FROM retired_at)::bigint AS retired_at,
+            retired_by,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!""#,
+        currency_address.to_string(),
+        old_address.to_string(),
+        new_address.to_string(),
+        ends_at as i64,
+        operator
+    )
+    .try_map(KeyRotation::try_from)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rotation)
+}
+
+/// Retires the old address of the rotation of a currency that is in its transition window.
+/// Returns `None` if there is no such rotation.
+pub async fn retire_key_rotation(
+    pool: &PgPool,
+    currency_address: &Address,
+    operator: &str,
+) -> Result<Option<KeyRotation>> {
+    let rotation = sqlx::query_as!(
+        DbKeyRotation,
+        r#"UPDATE key_rotations
+        SET retired_at = NOW(), retired_by = $2
+        WHERE currency_address = $1 AND retired_at IS NULL
+        RETURNING
+            currency_address,
+            old_address,
+            new_address,
+            ends_at,
+            operator,
+            EXTRACT(EPOCH FROM retired_at)::bigint AS retired_at,
+            retired_by,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!""#,
+        currency_address.to_string(),
+        operator
+    )
+    .try_map(KeyRotation::try_from)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(rotation)
+}
+
+/// Gets the rotations of the pool primary address of a currency, the most recent first.
+pub async fn get_key_rotations(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<KeyRotation>> {
+    let rotations = sqlx::query_as!(
+        DbKeyRotation,
+        r#"SELECT
+            currency_address,
+            old_address,
+            new_address,
+            ends_at,
+            operator,
+            EXTRACT(EPOCH FROM retired_at)::bigint AS retired_at,
+            retired_by,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM key_rotations
+        WHERE currency_address = $1
+        ORDER BY id DESC"#,
+        currency_address.to_string()
+    )
+    .try_map(KeyRotation::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rotations)
+}
+
+/// Stores an address that delegates its staking to the pool on behalf of a staker.
+///
+/// An address can only be delegated by one staker: returns false, without changing anything, if
+/// another staker delegated it already.
+pub async fn store_delegated_address(
+    pool: &PgPool,
+    currency_address: &Address,
+    delegated_address: &DelegatedAddress,
+) -> Result<bool> {
+    sqlx::query!(
+        "INSERT INTO delegated_addresses (currency_address, identity_address, address)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (currency_address, address) DO NOTHING",
+        currency_address.to_string(),
+        delegated_address.identity_address.to_string(),
+        delegated_address.address.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    let row = sqlx::query!(
+        "SELECT identity_address FROM delegated_addresses
+        WHERE currency_address = $1 AND address = $2",
+        currency_address.to_string(),
+        delegated_address.address.to_string()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.identity_address == delegated_address.identity_address.to_string())
+}
+
+pub async fn remove_delegated_address(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    address: &Address,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM delegated_addresses WHERE currency_address = $1 AND address = $2",
+        currency_address.to_string(),
+        address.to_string()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_delegated_addresses(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_addresses: &[Address],
+) -> Result<Vec<DelegatedAddress>> {
+    if identity_addresses.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let identity_addresses = identity_addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect::<Vec<_>>();
+
+    let rows = sqlx::query_as!(
+        DbDelegatedAddress,
+        "SELECT identity_address, address
+        FROM delegated_addresses
+        WHERE currency_address = $1 AND identity_address = ANY($2)",
+        currency_address.to_string(),
+        &identity_addresses
+    )
+    .try_map(DelegatedAddress::try_from)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the history of a staker, newest first.
+///
+/// Status changes come from the `staker_events` table, the rest is derived from the work,
+/// stakes, payouts and confirmed payments of the staker. Only activity before the unix timestamp
+/// `before` is returned, to page through the history.
+pub async fn get_staker_activity(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    before: Option<u64>,
+    limit: u64,
+) -> Result<Vec<StakerActivity>> {
+    let rows = sqlx::query_as!(
+        DbStakerActivity,
+        r#"SELECT
+            kind AS "kind!: StakerActivityKind",
+            happened_at AS "timestamp!",
+            block_height,
+            reference,
+            amount,
+            shares,
+            status AS "status: StakerStatus"
+        FROM (
+            SELECT
+                'STATUS_CHANGED'::staker_activity_kind AS kind,
+                EXTRACT(EPOCH FROM e.created_at)::bigint AS happened_at,
+                NULL::bigint AS block_height,
+                NULL::text AS reference,
+                NULL::bigint AS amount,
+                NULL::decimal AS shares,
+                e.status
+            FROM staker_events e
+            WHERE e.currency_address = $1 AND e.identity_address = $2
+            UNION ALL
+            SELECT
+                'WORK_COUNTED'::staker_activity_kind,
+                EXTRACT(EPOCH FROM w.updated_at)::bigint,
+                r.block_height,
+                NULL::text,
+                NULL::bigint,
+                w.shares,
+                NULL::staker_status
+            FROM work w
+            JOIN rounds r ON r.currency_address = w.currency_address AND r.id = w.round_id
+            WHERE w.currency_address = $1 AND w.staker_address = $2
+            UNION ALL
+            SELECT
+                'STAKE_FOUND'::staker_activity_kind,
+                EXTRACT(EPOCH FROM s.created_at)::bigint,
+                s.block_height,
+                s.block_hash,
+                s.amount,
+                NULL::decimal,
+                NULL::staker_status
+            FROM stakes s
+            WHERE s.currency_address = $1 AND s.found_by = $2 AND s.status != 'STALE'
+            UNION ALL
+            SELECT
+                'PAYOUT_CREDITED'::staker_activity_kind,
+                EXTRACT(EPOCH FROM pm.created_at)::bigint,
+                pm.block_height,
+                pm.block_hash,
+                pm.reward,
+                pm.shares,
+                NULL::staker_status
+            FROM payout_members pm
+            WHERE pm.currency_address = $1 AND pm.identity_address = $2
+            UNION ALL
+            SELECT
+                'PAYOUT_CREDITED'::staker_activity_kind,
+                EXTRACT(EPOCH FROM a.month)::bigint,
+                a.last_block_height,
+                NULL::text,
+                a.reward,
+                a.shares,
+                NULL::staker_status
+            FROM payout_member_archive a
+            WHERE a.currency_address = $1 AND a.identity_address = $2
+            UNION ALL
+            SELECT
+                'PAYMENT_RECEIVED'::staker_activity_kind,
+                EXTRACT(EPOCH FROM p.updated_at)::bigint,
+                NULL::bigint,
+                pi.txid,
+                pi.amount,
+                NULL::decimal,
+                NULL::staker_status
+            FROM payment_items pi
+            JOIN payments p ON p.currency_address = pi.currency_address AND p.txid = pi.txid
+            WHERE pi.currency_address = $1 AND pi.identity_address = $2
+                AND p.status = 'CONFIRMED'
+        ) activity
+        WHERE $3::bigint IS NULL OR happened_at < $3
+        ORDER BY happened_at DESC
+        LIMIT $4"#,
+        currency_address.to_string(),
+        identity_address.to_string(),
+        before.map(|before| before as i64),
+        limit as i64
+    )
+    .try_map(StakerActivity::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the id of the next webhook message of a currency, which is one higher than the id of
+/// the message before it.
+pub async fn next_webhook_event_id(pool: &PgPool, currency_address: &Address) -> Result<u64> {
+    let row = sqlx::query!(
+        "INSERT INTO webhook_event_ids (currency_address, last_event_id)
+        VALUES ($1, 1)
+        ON CONFLICT (currency_address)
+        DO UPDATE SET last_event_id = webhook_event_ids.last_event_id + 1
+        RETURNING last_event_id",
+        currency_address.to_string()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.last_event_id as u64)
+}
+
+/// Stores a webhook message that did not fit in the queue of its endpoint.
+pub async fn store_webhook_outbox(
+    pool: &PgPool,
+    currency_address: &Address,
+    endpoint: &Url,
+    body: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO webhook_outbox (currency_address, endpoint, body)
+        VALUES ($1, $2, $3)",
+        currency_address.to_string(),
+        endpoint.to_string(),
+        body.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores a webhook message of which the delivery failed, to be retried after `retry_in`, or
+/// dead-lettered if `retry_in` is `None`.
+pub async fn store_failed_webhook(
+    pool: &PgPool,
+    currency_address: &Address,
+    endpoint: &Url,
+    body: &serde_json::Value,
+    attempts: u32,
+    last_error: &str,
+    retry_in: Option<std::time::Duration>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO webhook_outbox
+            (currency_address, endpoint, body, attempts, last_error, next_attempt_at, dead_at)
+        VALUES (
+            $1, $2, $3, $4, $5,
+            NOW() + make_interval(secs => $6),
+            CASE WHEN $7 THEN NOW() END
+        )",
+        currency_address.to_string(),
+        endpoint.to_string(),
+        body.to_string(),
+        attempts as i32,
+        last_error,
+        retry_in.unwrap_or_default().as_secs_f64(),
+        retry_in.is_none()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets the oldest webhook messages in the outbox that are due, leaving out the dead-lettered
+/// ones.
+pub async fn get_webhook_outbox(
+    pool: &PgPool,
+    currency_address: &Address,
+    limit: u64,
+) -> Result<Vec<OutboxEntry>> {
+    let entries = sqlx::query!(
+        "SELECT id, endpoint, body, attempts FROM webhook_outbox
+        WHERE currency_address = $1 AND dead_at IS NULL AND next_attempt_at <= NOW()
+        ORDER BY id
+        LIMIT $2",
+        currency_address.to_string(),
+        limit as i64
+    )
+    .try_map(|row| {
+        Ok(OutboxEntry {
+            id: row.id,
+            endpoint: Url::parse(&row.endpoint).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            body: serde_json::from_str(&row.body).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            attempts: row.attempts as u32,
+        })
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Gets the webhook messages that are no longer retried, oldest first.
+pub async fn get_dead_webhooks(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<DeadLetter>> {
+    let dead = sqlx::query!(
+        r#"SELECT
+            id, endpoint, body, attempts, last_error,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM dead_at)::bigint AS "dead_at!"
+        FROM webhook_outbox
+        WHERE currency_address = $1 AND dead_at IS NOT NULL
+        ORDER BY id"#,
+        currency_address.to_string()
+    )
+    .try_map(|row| {
+        Ok(DeadLetter {
+            id: row.id as u64,
+            endpoint: Url::parse(&row.endpoint).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            body: row.body,
+            attempts: row.attempts as u32,
+            last_error: row.last_error,
+            created_at: row.created_at as u64,
+            dead_at: row.dead_at as u64,
+        })
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(dead)
+}
+
+/// Retries the dead-lettered webhook messages with `ids`, or all of them if `ids` is `None`,
+/// as if they were never attempted. Returns the number of messages.
+pub async fn redrive_dead_webhooks(
+    pool: &PgPool,
+    currency_address: &Address,
+    ids: Option<&[u64]>,
+) -> Result<u64> {
+    let ids = ids.map(|ids| ids.iter().map(|id| *id as i64).collect::<Vec<_>>());
+
+    let result = sqlx::query!(
+        "UPDATE webhook_outbox
+        SET dead_at = NULL, attempts = 0, next_attempt_at = NOW()
+        WHERE currency_address = $1 AND dead_at IS NOT NULL
+            AND ($2::bigint[] IS NULL OR id = ANY($2))",
+        currency_address.to_string(),
+        ids.as_deref()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub async fn delete_webhook_outbox_entry(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query!("DELETE FROM webhook_outbox WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Gets the inactive stakers that are owed nothing anymore and that have no statement since
+/// they became inactive.
+///
+/// A staker is owed something as long as it has unpaid rewards of at least `min_payable`, payout
+/// members in a pending payment, work in a round that has no payout yet or work that is held.
+/// Unpaid rewards below `min_payable` can't be paid, so they don't keep a staker open.
+pub async fn get_departed_stakers_without_statement(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    min_payable: Amount,
+) -> Result<Vec<Staker>> {
+    let rows = sqlx::query_as!(
+        DbStaker,
+        r#"SELECT
+            s.currency_address,
+            s.identity_address,
+            s.identity_name,
+            s.min_payout,
+            s.status AS "status: _",
+            s.fee,
+            s.participation_kind AS "participation_kind: _",
+            s.payout_address,
+            EXTRACT(EPOCH FROM s.created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM s.updated_at)::bigint AS "updated_at!"
+        FROM stakers s
+        WHERE s.currency_address = $1
+            AND s.status = 'INACTIVE'
+            AND EXISTS (
+                SELECT 1 FROM staker_settlements ss
+                WHERE ss.currency_address = s.currency_address
+                    AND ss.identity_address = s.identity_address
+                    AND ss.settle_at <= NOW()
+            )
+            AND COALESCE((
+                SELECT SUM(pm.reward) FROM payout_members pm
+                WHERE pm.currency_address = s.currency_address
+                    AND pm.identity_address = s.identity_address
+                    AND pm.txid IS NULL
+            ), 0) < $2
+            AND NOT EXISTS (
+                SELECT 1 FROM payment_items pi
+                JOIN payments p ON p.currency_address = pi.currency_address AND p.txid = pi.txid
+                WHERE pi.currency_address = s.currency_address
+                    AND pi.identity_address = s.identity_address
+                    AND p.status = 'PENDING'
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM work w
+                LEFT JOIN rounds r ON r.currency_address = w.currency_address AND r.id = w.round_id
+                WHERE w.currency_address = s.currency_address
+                    AND w.staker_address = s.identity_address
+                    AND w.shares > 0
+                    AND NOT EXISTS (
+                        SELECT 1 FROM payouts po
+                        WHERE po.currency_address = w.currency_address
+                            AND po.block_hash = r.block_hash
+                    )
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM open_work ow
+                WHERE ow.currency_address = s.currency_address
+                    AND ow.staker_address = s.identity_address
+                    AND ow.shares > 0
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM scheduled_forfeits sf
+                WHERE sf.currency_address = s.currency_address
+                    AND sf.staker_address = s.identity_address
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM staker_statements st
+                WHERE st.currency_address = s.currency_address
+                    AND st.identity_address = s.identity_address
+                    AND st.created_at >= s.updated_at
+            )"#,
+        currency_address.to_string(),
+        min_payable.as_sat() as i64
+    )
+    .try_map(Staker::try_from)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Moves the unpaid payout members of a departed staker, which are too small to be paid, to the
+/// forfeited rewards. Their rewards are added to the fee of their payouts, so the pool keeps them.
+/// Returns the amount that was forfeited.
+pub async fn forfeit_unpaid_rewards(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Amount> {
+    let forfeited = sqlx::query!(
+        r#"WITH unpaid AS (
+            DELETE FROM payout_members
+            WHERE currency_address = $1 AND identity_address = $2 AND txid IS NULL
+            RETURNING
+                currency_address,
+                identity_address,
+                block_hash,
+                block_height,
+                shares,
+                reward,
+                fee
+        ), ledger AS (
+            INSERT INTO forfeited_rewards (
+                currency_address,
+                identity_address,
+                block_hash,
+                block_height,
+                shares,
+                reward,
+                fee
+            )
+            SELECT * FROM unpaid
+        ), pool_fee AS (
+            UPDATE payouts p
+            SET fee = p.fee + unpaid.reward, amount_paid = p.amount_paid - unpaid.reward
+            FROM unpaid
+            WHERE p.currency_address = unpaid.currency_address
+                AND p.block_hash = unpaid.block_hash
+        )
+        SELECT COALESCE(SUM(reward), 0)::bigint AS "forfeited!" FROM unpaid"#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .fetch_one(conn)
+    .await?
+    .forfeited;
+
+    Ok(Amount::from_sat(forfeited as u64))
+}
+
+/// Makes the final statement of a staker from all its payout members, archived ones included,
+/// and stores it. This closes the settlement of the staker.
+pub async fn create_staker_statement(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<StakerStatement> {
+    let mut tx = conn.begin().await?;
+
+    let statement = sqlx::query_as!(
+        DbStakerStatement,
+        r#"INSERT INTO staker_statements (
+            currency_address,
+            identity_address,
+            identity_name,
+            joined_at,
+            left_at,
+            n_rounds,
+            rewards,
+            fees,
+            forfeited
+        )
+        SELECT
+            s.currency_address,
+            s.identity_address,
+            s.identity_name,
+            s.created_at,
+            s.updated_at,
+            (COALESCE(pm.n_rounds, 0) + COALESCE(a.n_rounds, 0) + COALESCE(f.n_rounds, 0))::bigint,
+            (COALESCE(pm.rewards, 0) + COALESCE(a.rewards, 0))::bigint,
+            (COALESCE(pm.fees, 0) + COALESCE(a.fees, 0) + COALESCE(f.fees, 0))::bigint,
+            COALESCE(f.forfeited, 0)::bigint
+        FROM stakers s
+        LEFT JOIN (
+            SELECT COUNT(*) AS n_rounds, SUM(reward) AS rewards, SUM(fee) AS fees
+            FROM payout_members
+            WHERE currency_address = $1 AND identity_address = $2
+        ) pm ON TRUE
+        LEFT JOIN (
+            SELECT SUM(n_rounds) AS n_rounds, SUM(reward) AS rewards, SUM(fee) AS fees
+            FROM payout_member_archive
+            WHERE currency_address = $1 AND identity_address = $2
+        ) a ON TRUE
+        LEFT JOIN (
+            SELECT COUNT(*) AS n_rounds, SUM(reward) AS forfeited, SUM(fee) AS fees
+            FROM forfeited_rewards
+            WHERE currency_address = $1 AND identity_address = $2
+        ) f ON TRUE
+        WHERE s.currency_address = $1 AND s.identity_address = $2
+        RETURNING
+            currency_address,
+            identity_address,
+            identity_name,
+            EXTRACT(EPOCH FROM joined_at)::bigint AS "joined_at!",
+            EXTRACT(EPOCH FROM left_at)::bigint AS "left_at!",
+            n_rounds,
+            rewards,
+            fees,
+            forfeited,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!""#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .try_map(StakerStatement::try_from)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE staker_settlements SET settled_at = NOW()
+        WHERE currency_address = $1 AND identity_address = $2 AND settled_at IS NULL",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(statement)
+}
+
+/// Gets the statements of a staker, newest first.
+pub async fn get_staker_statements(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Vec<StakerStatement>> {
+    let statements = sqlx::query_as!(
+        DbStakerStatement,
+        r#"SELECT
+            currency_address,
+            identity_address,
+            identity_name,
+            EXTRACT(EPOCH FROM joined_at)::bigint AS "joined_at!",
+            EXTRACT(EPOCH FROM left_at)::bigint AS "left_at!",
+            n_rounds,
+            rewards,
+            fees,
+            forfeited,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM staker_statements
+        WHERE currency_address = $1 AND identity_address = $2
+        ORDER BY id DESC"#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .try_map(StakerStatement::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(statements)
+}
+
+/// Stores a new API key of a staker by the hash of the key.
+pub async fn store_api_key(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    key_hash: &str,
+    label: &str,
+) -> Result<ApiKey> {
+    let row = sqlx::query!(
+        r#"INSERT INTO api_keys (currency_address, identity_address, key_hash, label)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!""#,
+        currency_address.to_string(),
+        identity_address.to_string(),
+        key_hash,
+        label
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ApiKey {
+        id: row.id as u64,
+        label: label.to_string(),
+        key: None,
+        created_at: row.created_at as u64,
+        last_used_at: None,
+    })
+}
+
+/// Gets the API keys of a staker that were not revoked, without the keys themselves.
+pub async fn get_api_keys(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query!(
+        r#"SELECT
+            id, label,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM last_used_at)::bigint AS last_used_at
+        FROM api_keys
+        WHERE currency_address = $1 AND identity_address = $2 AND revoked_at IS NULL
+        ORDER BY id"#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .map(|row| ApiKey {
+        id: row.id as u64,
+        label: row.label,
+        key: None,
+        created_at: row.created_at as u64,
+        last_used_at: row.last_used_at.map(|t| t as u64),
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Returns the VerusID of the API key with `key_hash`, if it was not revoked, and marks the key
+/// as used.
+pub async fn use_api_key(
+    pool: &PgPool,
+    currency_address: &Address,
+    key_hash: &str,
+) -> Result<Option<Address>> {
+    let identity_address = sqlx::query_scalar!(
+        "UPDATE api_keys SET last_used_at = NOW()
+        WHERE currency_address = $1 AND key_hash = $2 AND revoked_at IS NULL
+        RETURNING identity_address",
+        currency_address.to_string(),
+        key_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(identity_address
+        .map(|address| Address::from_str(&address))
+        .transpose()?)
+}
+
+/// Revokes an API key of a staker. Returns false if the staker has no such key.
+pub async fn revoke_api_key(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    id: u64,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW()
+        WHERE currency_address = $1 AND identity_address = $2 AND id = $3
+            AND revoked_at IS NULL",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        id as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Changes the min_payout of a staker, if it is within the bounds of the pool. Returns false if
+/// the VerusID is not a staker.
+pub async fn update_min_payout(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    min_payout: Amount,
+    bounds: &MinPayoutBounds,
+) -> Result<bool> {
+    bounds.check(min_payout)?;
+
+    let result = sqlx::query!(
+        "UPDATE stakers SET min_payout = $3
+        WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        min_payout.as_sat() as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sets the endpoint that receives the events about a staker, or removes it.
+pub async fn set_staker_webhook(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    webhook: Option<&StakerWebhook>,
+) -> Result<()> {
+    match webhook {
+        Some(webhook) => {
+            sqlx::query!(
+                "INSERT INTO staker_webhooks (currency_address, identity_address, endpoint, events, secret)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (currency_address, identity_address)
+                DO UPDATE SET endpoint = EXCLUDED.endpoint, events = EXCLUDED.events, secret = EXCLUDED.secret",
+                currency_address.to_string(),
+                identity_address.to_string(),
+                webhook.endpoint.to_string(),
+                &webhook
+                    .events
+                    .iter()
+                    .map(|event| event.as_str().to_string())
+                    .collect::<Vec<_>>(),
+                webhook.secret
+            )
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query!(
+                "DELETE FROM staker_webhooks WHERE currency_address = $1 AND identity_address = $2",
+                currency_address.to_string(),
+                identity_address.to_string()
+            )
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the endpoint that receives the events about a staker, with its secret.
+pub async fn get_staker_webhook(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Option<StakerWebhook>> {
+    let webhook = sqlx::query!(
+        "SELECT endpoint, events, secret FROM staker_webhooks
+        WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .try_map(|row| {
+        Ok(StakerWebhook {
+            endpoint: Url::parse(&row.endpoint).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            events: row
+                .events
+                .iter()
+                .map(|event| StakerWebhookEvent::from_str(event))
+                .collect::<Result<_, _>>()
+                .map_err(|e| sqlx::Error::Decode(e.into()))?,
+            secret: row.secret,
+        })
+    })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(webhook)
+}
+
+/// Moves the work of the round of a stale stake back to round 0, and records how many shares
+/// of every staker were moved in `stale_events`.
+pub async fn move_stale_work_to_round_zero(
+    conn: &mut PgConnection,
+    stake: &Stake,
+    round_id: u64,
+) -> Result<()> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "INSERT INTO stale_events
+            (currency_address, block_hash, block_height, found_by, identity_address, shares)
+        SELECT currency_address, $3, $4, $5, staker_address, shares
+        FROM work
+        WHERE currency_address = $1 AND round_id = $2
+        ON CONFLICT (currency_address, block_hash, identity_address) DO NOTHING",
+        stake.currency_address.to_string(),
+        round_id as i64,
+        stake.block_hash.to_string(),
+        stake.block_height as i64,
+        stake.found_by.to_string()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    add_work_to_round_zero(&mut tx, &stake.currency_address, round_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Gets the stale stakes that were found by a staker or that the staker had work in, newest
+/// first, with the shares of every staker that were moved back to round 0.
+pub async fn get_stale_stakes(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<Vec<StaleStake>> {
+    let rows = sqlx::query!(
+        r#"SELECT
+            block_hash,
+            block_height,
+            found_by,
+            identity_address,
+            shares,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM stale_events
+        WHERE currency_address = $1 AND block_hash IN (
+            SELECT block_hash FROM stale_events
+            WHERE currency_address = $1 AND (found_by = $2 OR identity_address = $2)
+        )
+        ORDER BY block_height DESC, block_hash, identity_address"#,
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut stale_stakes: Vec<StaleStake> = vec![];
+    for row in rows {
+        let block_hash = BlockHash::from_str(&row.block_hash)?;
+        let shares = RedistributedShares {
+            identity_address: Address::from_str(&row.identity_address)?,
+            shares: row.shares,
+        };
+
+        match stale_stakes.last_mut() {
+            Some(stale_stake) if stale_stake.block_hash == block_hash => {
+                stale_stake.redistributed.push(shares)
+            }
+            _ => stale_stakes.push(StaleStake {
+                currency_address: currency_address.clone(),
+                block_hash,
+                block_height: row.block_height as u64,
+                found_by: Address::from_str(&row.found_by)?,
+                redistributed: vec![shares],
+                created_at: row.created_at as u64,
+            }),
+        }
+    }
+
+    Ok(stale_stakes)
+}
+
+/// Stores a stake that was imported from the chain, without a round. Returns false if the stake
+/// is already known.
+pub async fn store_imported_stake(conn: &mut PgConnection, stake: &Stake) -> Result<bool> {
+    let result = sqlx::query!(
+        "INSERT INTO stakes (
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'IMPORTED')
+        ON CONFLICT (currency_address, block_hash) DO NOTHING",
+        stake.currency_address.to_string(),
+        stake.block_hash.to_string(),
+        stake.block_height as i64,
+        stake.amount.as_sat() as i64,
+        stake.found_by.to_string(),
+        stake.source_txid.to_string(),
+        stake.source_vout_num as i32,
+        stake.source_amount.as_sat() as i64
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Records the intent to pay `members` in the payment journal, before the payment is sent, with
+/// the prepared `items` that the payment sends.
+pub async fn open_payment_journal_entry(
+    pool: &PgPool,
+    currency_address: &Address,
+    members: &[PayoutMember],
+    items: &[PaymentItem],
+    tx_fee: TxFee,
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO payment_journal (currency_address, tx_fee, tx_fee_paid_by_pool)
+        VALUES ($1, $2, $3)
+        RETURNING id",
+        currency_address.to_string(),
+        tx_fee.amount.as_sat() as i64,
+        tx_fee.paid_by_pool
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for member in members {
+        sqlx::query!(
+            "INSERT INTO payment_journal_members (payment_journal_id, identity_address, block_hash, reward)
+            VALUES ($1, $2, $3, $4)",
+            id,
+            member.identity_address.to_string(),
+            member.block_hash.to_string(),
+            member.reward.as_sat() as i64
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for item in items {
+        sqlx::query!(
+            "INSERT INTO payment_journal_items (payment_journal_id, identity_address, destination, amount, convert_to)
+            VALUES ($1, $2, $3, $4, $5)",
+            id,
+            item.identity_address.to_string(),
+            item.destination().to_string(),
+            item.amount.as_sat() as i64,
+            item.payout_currency
+                .as_ref()
+                .map(|payout_currency| payout_currency.to_string())
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(id as u64)
+}
+
+/// Records the sendcurrency operation of an open journal entry.
+pub async fn set_payment_journal_opid(pool: &PgPool, id: u64, opid: &str) -> Result<()> {
+    sqlx::query!(
+        "UPDATE payment_journal SET opid = $2 WHERE id = $1",
+        id as i64,
+        opid
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Closes a journal entry, with the txid of the payment if it was sent.
+pub async fn close_payment_journal_entry(
+    conn: &mut PgConnection,
+    id: u64,
+    status: JournalStatus,
+    txid: Option<&Txid>,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE payment_journal SET status = $2, txid = $3 WHERE id = $1",
+        id as i64,
+        status as JournalStatus,
+        txid.map(|txid| txid.to_string())
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Gets the open journal entries of a currency, oldest first, with their payout members and the
+/// items that their payment sends.
+pub async fn get_open_payment_journal_entries(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<Vec<JournalEntry>> {
+    let rows = sqlx::query!(
+        r#"SELECT
+            id,
+            opid,
+            tx_fee,
+            tx_fee_paid_by_pool,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM payment_journal
+        WHERE currency_address = $1 AND status = 'OPEN'
+        ORDER BY id"#,
+        currency_address.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut entries = vec![];
+    for row in rows {
+        let members = sqlx::query_as!(
+            DbPayoutMember,
+            "SELECT
+                pm.currency_address,
+                pm.identity_address,
+                pm.block_hash,
+                pm.block_height,
+                pm.shares,
+                pm.reward,
+                pm.fee,
+                pm.txid,
+                EXTRACT(EPOCH FROM pm.created_at)::bigint AS \"created_at!\",
+                EXTRACT(EPOCH FROM pm.updated_at)::bigint AS \"updated_at!\"
+            FROM payment_journal_members pjm
+            JOIN payout_members pm ON pm.currency_address = $1
+                AND pm.identity_address = pjm.identity_address
+                AND pm.block_hash = pjm.block_hash
+            WHERE pjm.payment_journal_id = $2
+            ORDER BY pm.identity_address, pm.block_height",
+            currency_address.to_string(),
+            row.id
+        )
+        .try_map(PayoutMember::try_from)
+        .fetch_all(pool)
+        .await?;
+
+        let items = sqlx::query!(
+            "SELECT identity_address, destination, amount, convert_to
+            FROM payment_journal_items
+            WHERE payment_journal_id = $1
+            ORDER BY identity_address",
+            row.id
+        )
+        .try_map(|item| {
+            let identity_address = Address::from_str(&item.identity_address)
+                .map_err(|e| sqlx::Error::Decode(e.into()))?;
+            let destination =
+                Address::from_str(&item.destination).map_err(|e| sqlx::Error::Decode(e.into()))?;
+            let payout_currency = item
+                .convert_to
+                .map(|convert_to| Address::from_str(&convert_to))
+                .transpose()
+                .map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+            Ok(PaymentItem {
+                block_hashes: members
+                    .iter()
+                    .filter(|member| member.identity_address == identity_address)
+                    .map(|member| member.block_hash)
+                    .collect(),
+                payout_address: (destination != identity_address).then_some(destination),
+                identity_address,
+                amount: Amount::from_sat(item.amount as u64),
+                payout_currency,
+            })
+        })
+        .fetch_all(pool)
+        .await?;
+
+        entries.push(JournalEntry {
+            id: row.id as u64,
+            currency_address: currency_address.clone(),
+            opid: row.opid,
+            members,
+            items,
+            tx_fee: TxFee {
+                amount: Amount::from_sat(row.tx_fee as u64),
+                paid_by_pool: row.tx_fee_paid_by_pool,
+            },
+            created_at: row.created_at as u64,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Gets the currencies that stakers want their rewards to be converted into, by staker.
+pub async fn get_payout_currencies(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<HashMap<Address, Address>> {
+    let payout_currencies = sqlx::query!(
+        "SELECT identity_address, payout_currency FROM payout_currencies WHERE currency_address = $1",
+        currency_address.to_string()
+    )
+    .try_map(|row| {
+        Ok((
+            Address::from_str(&row.identity_address).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            Address::from_str(&row.payout_currency).map_err(|e| sqlx::Error::Decode(e.into()))?,
+        ))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(payout_currencies.into_iter().collect())
+}
+
+/// Gets the payout address per staker, of the cold stakers.
+pub async fn get_payout_addresses(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<HashMap<Address, Address>> {
+    let payout_addresses = sqlx::query!(
+        r#"SELECT identity_address, payout_address AS "payout_address!"
+        FROM stakers
+        WHERE currency_address = $1 AND payout_address IS NOT NULL"#,
+        currency_address.to_string()
+    )
+    .try_map(|row| {
+        Ok((
+            Address::from_str(&row.identity_address).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            Address::from_str(&row.payout_address).map_err(|e| sqlx::Error::Decode(e.into()))?,
+        ))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(payout_addresses.into_iter().collect())
+}
+
+/// Returns the txids of every transaction that the pool sent: payments, manual payments and fee
+/// sweeps.
+pub async fn get_recorded_txids(
+    pool: &PgPool,
+    currency_address: &Address,
+) -> Result<HashSet<Txid>> {
+    let txids = sqlx::query!(
+        r#"SELECT txid AS "txid!" FROM payout_members WHERE currency_address = $1 AND txid IS NOT NULL
+        UNION SELECT txid FROM payments WHERE currency_address = $1
+        UNION SELECT txid FROM manual_payments WHERE currency_address = $1
+        UNION SELECT txid FROM fee_sweeps WHERE currency_address = $1"#,
+        currency_address.to_string()
+    )
+    .try_map(|row| Txid::from_str(&row.txid).map_err(|e| sqlx::Error::Decode(e.into())))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(txids.into_iter().collect())
+}
+
+/// Returns the transactions that paid payout members in the last `days`, with the number of
+/// members and the rewards that each paid.
+pub async fn get_recent_payout_txids(
+    pool: &PgPool,
+    currency_address: &Address,
+    days: u32,
+) -> Result<Vec<(Txid, u64, Amount)>> {
+    let txids = sqlx::query!(
+        r#"SELECT
+            txid AS "txid!",
+            COUNT(*) AS "n_members!",
+            SUM(reward)::bigint AS "amount!"
+        FROM payout_members
+        WHERE currency_address = $1
+            AND txid IS NOT NULL
+            AND updated_at > NOW() - make_interval(days => $2)
+        GROUP BY txid
+        ORDER BY txid"#,
+        currency_address.to_string(),
+        days as i32
+    )
+    .try_map(|row| {
+        Ok((
+            Txid::from_str(&row.txid).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            row.n_members as u64,
+            Amount::from_sat(row.amount as u64),
+        ))
+    })
+    .fetch_all(pool)
+    .await?;
+
+    Ok(txids)
+}
+
+/// Sets the currency that the rewards of a staker are converted into when they are paid.
+pub async fn store_payout_currency(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    payout_currency: &Address,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO payout_currencies (currency_address, identity_address, payout_currency)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (currency_address, identity_address)
+        DO UPDATE SET payout_currency = EXCLUDED.payout_currency",
+        currency_address.to_string(),
+        identity_address.to_string(),
+        payout_currency.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Lets a staker be paid in the staked currency again. Returns whether it had a payout currency.
+pub async fn delete_payout_currency(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM payout_currencies WHERE currency_address = $1 AND identity_address = $2",
+        currency_address.to_string(),
+        identity_address.to_string()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Rolls the payout members that were paid by a confirmed payment up into monthly totals per
+/// staker, for the months that ended at least `after_months` months ago. The payment of these
+/// members can't fail anymore, so they are never reopened.
+///
+/// Returns the number of payout members that were archived.
+pub async fn archive_payout_members(
+    pool: &PgPool,
+    currency_address: &Address,
+    after_months: u32,
+) -> Result<u64> {
+    let archived = sqlx::query_scalar!(
+        r#"WITH archived AS (
+            DELETE FROM payout_members pm
+            USING payments p
+            WHERE pm.currency_address = $1
+                AND p.currency_address = pm.currency_address
+                AND p.txid = pm.txid
+                AND p.status = 'CONFIRMED'
+                AND pm.created_at < date_trunc('month', NOW()) - make_interval(months => $2)
+            RETURNING pm.*
+        ), rollups AS (
+            INSERT INTO payout_member_archive AS a (
+                currency_address,
+                identity_address,
+                month,
+                n_rounds,
+                shares,
+                reward,
+                fee,
+                first_block_height,
+                last_block_height
+            )
+            SELECT
+                currency_address,
+                identity_address,
+                date_trunc('month', created_at)::date,
+                COUNT(*),
+                SUM(shares),
+                SUM(reward),
+                SUM(fee),
+                MIN(block_height),
+                MAX(block_height)
+            FROM archived
+            GROUP BY currency_address, identity_address, date_trunc('month', created_at)::date
+            ON CONFLICT (currency_address, identity_address, month)
+            DO UPDATE SET
+                n_rounds = a.n_rounds + EXCLUDED.n_rounds,
+                shares = a.shares + EXCLUDED.shares,
+                reward = a.reward + EXCLUDED.reward,
+                fee = a.fee + EXCLUDED.fee,
+                first_block_height = LEAST(a.first_block_height, EXCLUDED.first_block_height),
+                last_block_height = GREATEST(a.last_block_height, EXCLUDED.last_block_height)
+        )
+        SELECT COUNT(*) AS "count!" FROM archived"#,
+        currency_address.to_string(),
+        after_months as i32
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(archived as u64)
+}
+
+/// The rewards of the archived payout members per staker.
+pub async fn get_archived_rewards(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    identity_addresses: &[Address],
+) -> Result<HashMap<Address, Amount>> {
+    let rewards = sqlx::query!(
+        r#"SELECT identity_address, SUM(reward)::bigint AS "reward!"
+        FROM payout_member_archive
+        WHERE currency_address = $1
+            AND identity_address IN (SELECT * FROM UNNEST($2::text[]))
+        GROUP BY identity_address"#,
+        currency_address.to_string(),
+        &identity_addresses
+            .iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .try_map(|row| {
+        Ok((
+            Address::from_str(&row.identity_address).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            Amount::from_sat(row.reward as u64),
+        ))
+    })
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rewards.into_iter().collect())
+}
+
+/// Returns the rewards, fees and stakes of a staker per period, oldest first, by the time the
+/// rewards were credited (in UTC).
+///
+/// Archived payout members are only kept per month, so with a day or week granularity they count
+/// in the period of the first day of their month.
+pub async fn get_earnings_timeseries(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    granularity: EarningsGranularity,
+) -> Result<Vec<EarningsPeriod>> {
+    let periods = sqlx::query!(
+        r#"SELECT
+            EXTRACT(EPOCH FROM period)::bigint AS "period_start!",
+            SUM(reward)::bigint AS "rewards!",
+            SUM(fee)::bigint AS "fees!",
+            SUM(n_stakes)::bigint AS "n_stakes!"
+        FROM (
+            SELECT
+                date_trunc($3, created_at AT TIME ZONE 'UTC') AS period,
+                reward,
+                fee,
+                1::bigint AS n_stakes
+            FROM payout_members
+            WHERE currency_address = $1 AND identity_address = $2
+            UNION ALL
+            SELECT date_trunc($3, month::timestamp), reward, fee, n_rounds
+            FROM payout_member_archive
+            WHERE currency_address = $1 AND identity_address = $2
+        ) earnings
+        GROUP BY period
+        ORDER BY period ASC"#,
+        currency_address.to_string(),
+        identity_address.to_string(),
+        granularity.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(periods
+        .into_iter()
+        .map(|row| EarningsPeriod {
+            period_start: row.period_start as u64,
+            rewards: Amount::from_sat(row.rewards as u64),
+            fees: Amount::from_sat(row.fees as u64),
+            n_stakes: row.n_stakes as u64,
+        })
+        .collect())
+}
+
+/// Returns a batch of the payout members of a staker that were credited from `from` up to and
+/// including `to` (unix timestamps in seconds), ordered by block height. The next batch starts
+/// after the block height and hash of the last member of the batch before it.
+pub async fn get_payout_members_for_export(
+    pool: &PgPool,
+    currency_address: &Address,
+    identity_address: &Address,
+    from: Option<u64>,
+    to: Option<u64>,
+    after: Option<(u64, BlockHash)>,
+    limit: u64,
+) -> Result<Vec<PayoutMember>> {
+    let (after_height, after_hash) = after
+        .map(|(block_height, block_hash)| (block_height as i64, block_hash.to_string()))
+        .unwrap_or((-1, String::new()));
+
+    let members = sqlx::query_as!(
+        DbPayoutMember,
+        r#"SELECT
+            currency_address,
+            identity_address,
+            block_hash,
+            block_height,
+            shares,
+            reward,
+            fee,
+            txid,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM payout_members
+        WHERE currency_address = $1
+            AND identity_address = $2
+            AND ($3::bigint IS NULL OR created_at >= to_timestamp($3))
+            AND ($4::bigint IS NULL OR created_at <= to_timestamp($4))
+            AND (block_height, block_hash) > ($5, $6)
+        ORDER BY block_height ASC, block_hash ASC
+        LIMIT $7"#,
+        currency_address.to_string(),
+        identity_address.to_string(),
+        from.map(|from| from as i64),
+        to.map(|to| to as i64),
+        after_height,
+        after_hash,
+        limit as i64
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(members)
+}
+
+/// Returns the stakers that match `filter`, ordered by identity address.
+pub async fn find_stakers(
+    pool: &PgPool,
+    currency_address: &Address,
+    filter: &StakerFilter,
+    page: Page,
+) -> Result<Vec<Staker>> {
+    let identity_addresses = filter.identity_addresses.as_ref().map(|addresses| {
+        addresses
+            .iter()
+            .map(|address| address.to_string())
+            .collect::<Vec<_>>()
+    });
+
+    let rows = sqlx::query_as!(
+        DbStaker,
+        r#"SELECT
+            currency_address,
+            identity_address,
+            identity_name,
+            min_payout,
+            status AS "status: _",
+            fee,
+            participation_kind AS "participation_kind: _",
+            payout_address,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakers
+        WHERE currency_address = $1
+            AND ($2::staker_status IS NULL OR status = $2)
+            AND ($3::text[] IS NULL OR identity_address = ANY($3))
+        ORDER BY identity_address ASC
+        LIMIT $4 OFFSET $5"#,
+        currency_address.to_string(),
+        filter.status.clone() as Option<StakerStatus>,
+        identity_addresses.as_deref(),
+        page.limit as i64,
+        page.offset as i64
+    )
+    .try_map(Staker::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the stakes that match `filter`, newest first.
+pub async fn find_stakes(
+    pool: &PgPool,
+    currency_address: &Address,
+    filter: &StakeFilter,
+    page: Page,
+) -> Result<Vec<Stake>> {
+    let rows = sqlx::query_as!(
+        DbStake,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS "status: _",
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakes
+        WHERE currency_address = $1
+            AND ($2::stake_status IS NULL OR status = $2)
+            AND ($3::text IS NULL OR found_by = $3)
+            AND block_height >= $4
+            AND ($5::bigint IS NULL OR block_height <= $5)
+        ORDER BY block_height DESC
+        LIMIT $6 OFFSET $7"#,
+        currency_address.to_string(),
+        filter.status.clone() as Option<StakeStatus>,
+        filter
+            .found_by
+            .as_ref()
+            .map(|found_by| found_by.to_string()),
+        filter.from_height.unwrap_or(0) as i64,
+        filter.to_height.map(|height| height as i64),
+        page.limit as i64,
+        page.offset as i64
+    )
+    .try_map(Stake::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the stakes that were found in the blocks of `block_hashes`.
+pub async fn get_stakes_by_block_hashes(
+    pool: &PgPool,
+    currency_address: &Address,
+    block_hashes: &[BlockHash],
+) -> Result<Vec<Stake>> {
+    let rows = sqlx::query_as!(
+        DbStake,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            found_by,
+            source_txid,
+            source_vout_num,
+            source_amount,
+            status AS "status: _",
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM stakes
+        WHERE currency_address = $1 AND block_hash = ANY($2)"#,
+        currency_address.to_string(),
+        &block_hashes
+            .iter()
+            .map(|block_hash| block_hash.to_string())
+            .collect::<Vec<_>>()
+    )
+    .try_map(Stake::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the payouts of the stakes in `filter`, newest first.
+pub async fn find_payouts(
+    pool: &PgPool,
+    currency_address: &Address,
+    filter: HeightFilter,
+    page: Page,
+) -> Result<Vec<PayoutSummary>> {
+    let rows = sqlx::query_as!(
+        DbPayoutSummary,
+        r#"SELECT
+            currency_address,
+            block_hash,
+            block_height,
+            amount,
+            work,
+            fee,
+            amount_paid,
+            n_subs,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!"
+        FROM payouts
+        WHERE currency_address = $1
+            AND block_height >= $2
+            AND ($3::bigint IS NULL OR block_height <= $3)
+        ORDER BY block_height DESC
+        LIMIT $4 OFFSET $5"#,
+        currency_address.to_string(),
+        filter.from_height.unwrap_or(0) as i64,
+        filter.to_height.map(|height| height as i64),
+        page.limit as i64,
+        page.offset as i64
+    )
+    .try_map(PayoutSummary::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the payout members that match `filter`, newest first.
+pub async fn find_payout_members(
+    pool: &PgPool,
+    currency_address: &Address,
+    filter: &PayoutMemberFilter,
+    page: Page,
+) -> Result<Vec<PayoutMember>> {
+    let rows = sqlx::query_as!(
+        DbPayoutMember,
+        r#"SELECT
+            currency_address,
+            identity_address,
+            block_hash,
+            block_height,
+            shares,
+            reward,
+            fee,
+            txid,
+            EXTRACT(EPOCH FROM created_at)::bigint AS "created_at!",
+            EXTRACT(EPOCH FROM updated_at)::bigint AS "updated_at!"
+        FROM payout_members
+        WHERE currency_address = $1
+            AND ($2::text IS NULL OR identity_address = $2)
+            AND ($3::text IS NULL OR block_hash = $3)
+            AND ($4::bool IS NULL OR (txid IS NOT NULL) = $4)
+        ORDER BY block_height DESC, identity_address ASC
+        LIMIT $5 OFFSET $6"#,
+        currency_address.to_string(),
+        filter
+            .identity_address
+            .as_ref()
+            .map(|address| address.to_string()),
+        filter.block_hash.map(|block_hash| block_hash.to_string()),
+        filter.paid,
+        page.limit as i64,
+        page.offset as i64
+    )
+    .try_map(PayoutMember::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Returns the rounds that were closed in `filter`, newest first.
+pub async fn find_rounds(
+    pool: &PgPool,
+    currency_address: &Address,
+    filter: HeightFilter,
+    page: Page,
+) -> Result<Vec<Round>> {
+    let rows = sqlx::query_as!(
+        DbRound,
+        "SELECT id, currency_address, block_hash, block_height
+        FROM rounds
+        WHERE currency_address = $1
+            AND block_height >= $2
+            AND ($3::bigint IS NULL OR block_height <= $3)
+        ORDER BY block_height DESC, id DESC
+        LIMIT $4 OFFSET $5",
+        currency_address.to_string(),
+        filter.from_height.unwrap_or(0) as i64,
+        filter.to_height.map(|height| height as i64),
+        page.limit as i64,
+        page.offset as i64
+    )
+    .try_map(Round::try_from)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_store_work(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+
+        let mut payload = HashMap::new();
+
+        payload.insert(
+            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
+            Decimal::from_f64_retain(1.23).unwrap(),
+        );
+
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT * FROM open_work")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
+        assert!(shares.is_sign_positive());
+        assert_eq!(shares, Decimal::from_f64_retain(1.23).unwrap());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_multiple_store_work(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+
+        let mut payload = HashMap::new();
+
+        payload.insert(
+            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
+            Decimal::from_f32_retain(1.23).unwrap(),
+        );
+
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT * FROM open_work")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
+        assert!(shares.is_sign_positive());
+        assert_eq!(shares, Decimal::from_f32_retain(1.23).unwrap());
+
+        let mut payload = HashMap::new();
+
+        payload.insert(
+            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
+            Decimal::from_f32_retain(3.77).unwrap(),
+        );
+
+        store_work(&pool, &currency_address, payload, 2)
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT * FROM open_work")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        assert!(rows.len() == 1);
+
+        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
+        assert!(shares.is_sign_positive());
+
+        assert_eq!(shares, Decimal::from_f32_retain(5.0).unwrap());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_forfeit_work(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        payload.insert(bob.clone(), Decimal::from(50));
+
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        schedule_forfeit(&mut conn, &currency_address, &bob, 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_due_forfeits(&mut conn, &currency_address)
+                .await
+                .unwrap(),
+            vec![bob.clone()]
+        );
+
+        forfeit_work(&mut conn, &currency_address, &bob)
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT * FROM open_work")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].get::<String, &str>("staker_address"),
+            alice.to_string()
+        );
+
+        assert!(get_due_forfeits(&mut conn, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 20)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(
+            get_forfeited_shares_by_round(&pool, &currency_address, 20)
+                .await
+                .unwrap(),
+            Decimal::from(50)
+        );
+
+        move_work_to_round_zero(&pool, &currency_address, 20)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            get_forfeited_shares_by_round(&pool, &currency_address, 0)
+                .await
+                .unwrap(),
+            Decimal::from(50)
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_held_work(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        payload.insert(bob.clone(), Decimal::from(50));
+
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        schedule_forfeit(&mut conn, &currency_address, &bob, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            hold_departed_work(&mut conn, &currency_address)
+                .await
+                .unwrap(),
+            vec![bob.clone()]
+        );
+        // a hold starts once
+        assert!(hold_departed_work(&mut conn, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // the held work is not in the open round, so the next stake doesn't pay for it
+        let open_work = |pool: PgPool| async move {
+            sqlx::query("SELECT staker_address, shares FROM open_work")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|row| {
+                    (
+                        row.get::<String, &str>("staker_address"),
+                        row.get::<Decimal, &str>("shares"),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            open_work(pool.clone()).await,
+            vec![(alice.to_string(), Decimal::from(100))]
+        );
+
+        count_held_round(&mut conn, &currency_address)
+            .await
+            .unwrap();
+        assert!(get_due_forfeits(&mut conn, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // bob came back: the held work goes back to the open round
+        cancel_scheduled_forfeit(&mut conn, &currency_address, &bob)
+            .await
+            .unwrap();
+        let mut work = open_work(pool.clone()).await;
+        work.sort();
+        assert_eq!(
+            work,
+            vec![
+                (alice.to_string(), Decimal::from(100)),
+                (bob.to_string(), Decimal::from(50))
+            ]
+        );
+
+        // bob left again, and the pool found 2 stakes before bob came back
+        schedule_forfeit(&mut conn, &currency_address, &bob, 2)
+            .await
+            .unwrap();
+        hold_departed_work(&mut conn, &currency_address)
+            .await
+            .unwrap();
+        count_held_round(&mut conn, &currency_address)
+            .await
+            .unwrap();
+        count_held_round(&mut conn, &currency_address)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_due_forfeits(&mut conn, &currency_address)
+                .await
+                .unwrap(),
+            vec![bob.clone()]
+        );
+
+        forfeit_work(&mut conn, &currency_address, &bob)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_forfeited_shares_by_round(&pool, &currency_address, 0)
+                .await
+                .unwrap(),
+            Decimal::from(50)
+        );
+        assert_eq!(
+            open_work(pool.clone()).await,
+            vec![(alice.to_string(), Decimal::from(100))]
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_stakes_at_the_same_height_get_their_own_round(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        let stake = |block_hash: &str| Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(block_hash).unwrap(),
+            block_height: 10,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Maturing,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let stale = stake("000000000000000000000000000000000000000000000000000000000000000a");
+        let replacement = stake("000000000000000000000000000000000000000000000000000000000000000b");
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        store_work(&pool, &currency_address, payload.clone(), 9)
+            .await
+            .unwrap();
+        store_new_stake(&mut conn, &stale).await.unwrap();
+
+        store_work(&pool, &currency_address, payload, 10)
+            .await
+            .unwrap();
+        store_new_stake(&mut conn, &replacement).await.unwrap();
+
+        let stale_round = get_round_id(&mut conn, &currency_address, &stale.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        let replacement_round = get_round_id(&mut conn, &currency_address, &replacement.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(stale_round, replacement_round);
+
+        for round_id in [stale_round, replacement_round] {
+            let workers = get_workers_by_round(&pool, &currency_address, round_id)
+                .await
+                .unwrap();
+            assert_eq!(workers.len(), 1);
+            assert_eq!(workers[0].shares, Decimal::from(100));
+        }
+
+        assert_eq!(
+            get_stake_by_round(&pool, &currency_address, replacement_round)
+                .await
+                .unwrap()
+                .unwrap()
+                .block_hash,
+            replacement.block_hash
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_reconsidered_block_is_not_counted_twice(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let stake = Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000a",
+            )
+            .unwrap(),
+            block_height: 10,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Maturing,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+
+        assert!(store_work(&pool, &currency_address, payload.clone(), 10)
+            .await
+            .unwrap());
+        store_new_stake(&mut conn, &stake).await.unwrap();
+
+        // the daemon forked and block 10 is processed again
+        assert!(!store_work(&pool, &currency_address, payload.clone(), 10)
+            .await
+            .unwrap());
+        store_new_stake(&mut conn, &stake).await.unwrap();
+
+        let revision = sqlx::query_scalar!(
+            "SELECT revision FROM work_revisions WHERE currency_address = $1 AND block_height = 10",
+            currency_address.to_string()
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(revision, 2);
+
+        let round_id = get_round_id(&mut conn, &currency_address, &stake.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        let workers = get_workers_by_round(&pool, &currency_address, round_id)
+            .await
+            .unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].shares, Decimal::from(100));
+
+        // the work of the next block stays in round 0
+        store_work(&pool, &currency_address, payload, 11)
+            .await
+            .unwrap();
+        store_new_stake(&mut conn, &stake).await.unwrap();
+
+        let workers = get_workers_by_round(&pool, &currency_address, 0)
+            .await
+            .unwrap();
+        assert_eq!(workers.len(), 1);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_store_historical_stake(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let stake = |block_hash: &str, block_height: u64| Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(block_hash).unwrap(),
+            block_height,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Matured,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        for height in 1..=20 {
+            let mut payload = HashMap::new();
+            payload.insert(alice.clone(), Decimal::from(10));
+            store_work(&pool, &currency_address, payload, height)
+                .await
+                .unwrap();
+
+            let hash = format!("{height:064x}");
+            let mut summary = BlockSummary::new(BlockHash::from_str(&hash).unwrap(), height);
+            summary.shares_added = Decimal::from(10);
+            store_block_summary(&mut conn, &currency_address, &summary)
+                .await
+                .unwrap();
+        }
+        let known = stake(
+            "00000000000000000000000000000000000000000000000000000000000000aa",
+            20,
+        );
+        store_new_stake(&mut conn, &known).await.unwrap();
+        let known_round = get_round_id(&mut conn, &currency_address, &known.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // a stake at height 5 was missed
+        assert_eq!(
+            get_round_containing(&pool, &currency_address, 5)
+                .await
+                .unwrap(),
+            (known_round, 0)
+        );
+        assert_eq!(
+            get_shares_added(&pool, &currency_address, 0, 5)
+                .await
+                .unwrap(),
+            Decimal::from(50)
+        );
+
+        let missed = stake(
+            "00000000000000000000000000000000000000000000000000000000000000bb",
+            5,
+        );
+        let mut tx = pool.begin().await.unwrap();
+        let round_id = store_historical_stake(&mut tx, &missed, known_round, Decimal::new(25, 2))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let workers = get_workers_by_round(&pool, &currency_address, round_id)
+            .await
+            .unwrap();
+        assert_eq!(workers[0].shares, Decimal::from(50));
+        let workers = get_workers_by_round(&pool, &currency_address, known_round)
+            .await
+            .unwrap();
+        assert_eq!(workers[0].shares, Decimal::from(150));
+
+        // the round of the known stake now starts after the missed stake
+        assert_eq!(
+            get_round_containing(&pool, &currency_address, 10)
+                .await
+                .unwrap(),
+            (known_round, 5)
+        );
+
+        let mut tx = pool.begin().await.unwrap();
+        assert!(
+            store_historical_stake(&mut tx, &missed, known_round, Decimal::ONE)
+                .await
+                .is_err()
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_get_orphaned_work_rounds(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        // round 0 is never orphaned
+        assert!(get_orphaned_work_rounds(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 42)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(
+            get_orphaned_work_rounds(&pool, &currency_address)
+                .await
+                .unwrap(),
+            vec![42]
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_restore_orphaned_work(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        store_staker(
+            &mut conn,
+            &Staker::new(
+                currency_address.clone(),
+                alice.clone(),
+                String::from("alice@"),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::ZERO,
+            ),
+        )
+        .await
+        .unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+
+        store_work(&pool, &currency_address, payload.clone(), 1)
+            .await
+            .unwrap();
+
+        // the pool stopped after the work was moved, before the stake was stored
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 42)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        // new work was added to round 0 after the restart
+        store_work(&pool, &currency_address, payload, 2)
+            .await
+            .unwrap();
+
+        restore_orphaned_work(&pool, &currency_address, 42)
+            .await
+            .unwrap();
+
+        assert!(get_orphaned_work_rounds(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let workers = get_workers_by_round(&pool, &currency_address, 0)
+            .await
+            .unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].shares, Decimal::from(200));
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_delegated_addresses(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let address = Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap();
+
+        let delegated_address = DelegatedAddress {
+            identity_address: alice.clone(),
+            address: address.clone(),
+        };
+
+        assert!(
+            store_delegated_address(&pool, &currency_address, &delegated_address)
+                .await
+                .unwrap()
+        );
+        // delegating it again is a no-op
+        assert!(
+            store_delegated_address(&pool, &currency_address, &delegated_address)
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            get_delegated_addresses(&mut conn, &currency_address, &[alice.clone(), bob.clone()])
+                .await
+                .unwrap(),
+            vec![delegated_address.clone()]
+        );
+
+        // another staker can't take over the delegation
+        assert!(!store_delegated_address(
+            &pool,
+            &currency_address,
+            &DelegatedAddress {
+                identity_address: bob.clone(),
+                address: address.clone(),
+            },
+        )
+        .await
+        .unwrap());
+
+        assert_eq!(
+            get_delegated_addresses(&mut conn, &currency_address, &[alice])
+                .await
+                .unwrap(),
+            vec![delegated_address]
+        );
+        assert!(
+            get_delegated_addresses(&mut conn, &currency_address, &[bob])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_staker_activity(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+        // storing the same status again is not a status change
+        store_staker(&mut conn, &staker).await.unwrap();
+        staker.status = StakerStatus::Inactive;
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::ONE);
+        store_work(&pool, &currency_address, payload, 10)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO rounds (id, currency_address, block_hash, block_height)
+            VALUES (3, $1, NULL, 10)",
+        )
+        .bind(currency_address.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 3)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let activity = get_staker_activity(&pool, &currency_address, &alice, None, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(activity.len(), 3);
+        assert_eq!(
+            activity
+                .iter()
+                .filter(|activity| activity.kind == StakerActivityKind::StatusChanged)
+                .count(),
+            2
+        );
+        let work = activity
+            .iter()
+            .find(|activity| activity.kind == StakerActivityKind::WorkCounted)
+            .unwrap();
+        assert_eq!(work.block_height, Some(10));
+        assert_eq!(work.shares, Some(Decimal::ONE));
+
+        assert!(
+            get_staker_activity(&pool, &currency_address, &alice, Some(0), 100)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_expire_cooling_down_stakers(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        for identity_address in [&alice, &bob] {
+            let staker = Staker::new(
+                currency_address.clone(),
+                identity_address.clone(),
+                "staker@".to_string(),
+                Amount::from_sat(100_000_000),
+                StakerStatus::CoolingDown,
+                Decimal::ZERO,
+            );
+            store_staker(&mut conn, &staker).await.unwrap();
+        }
+
+        sqlx::query(
+            "UPDATE staker_events SET created_at = NOW() - INTERVAL '10 days'
+            WHERE identity_address = $1",
+        )
+        .bind(alice.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let expired = expire_cooling_down_stakers(&mut conn, &currency_address, 7)
+            .await
+            .unwrap();
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].identity_address, alice);
+        assert_eq!(expired[0].status, StakerStatus::Expired);
+        assert_eq!(
+            get_stakers_by_status(&mut conn, &currency_address, StakerStatus::CoolingDown)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_staker_liabilities(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let member = |identity_address: &Address, block_height: u64, reward: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                identity_address.clone(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        for member in [
+            member(&alice, 10, 100),
+            member(&alice, 20, 300),
+            member(&bob, 20, 500),
+            member(&bob, 30, 700),
+        ] {
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+        set_txid_payment_member(&mut conn, &member(&bob, 30, 700), &txid)
+            .await
+            .unwrap();
+
+        let liabilities = get_staker_liabilities(&pool, &currency_address)
+            .await
+            .unwrap();
+
+        assert_eq!(liabilities.len(), 2);
+        assert_eq!(liabilities[0].identity_address, bob);
+        assert_eq!(liabilities[0].unpaid, Amount::from_sat(500));
+        assert_eq!(liabilities[0].n_rounds, 1);
+        assert_eq!(liabilities[1].identity_address, alice);
+        assert_eq!(liabilities[1].unpaid, Amount::from_sat(400));
+        assert_eq!(liabilities[1].n_rounds, 2);
+        assert_eq!(liabilities[1].oldest_unpaid_height, 10);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_unpaid_payout_members_of_staker(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let member = |identity_address: &Address, block_height: u64, reward: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                identity_address.clone(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        for member in [
+            member(&alice, 20, 300),
+            member(&alice, 10, 100),
+            member(&alice, 30, 500),
+            member(&bob, 20, 700),
+        ] {
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+        set_txid_payment_member(&mut conn, &member(&alice, 30, 500), &txid)
+            .await
+            .unwrap();
+
+        // regardless of the min_payout of the staker, which doesn't even exist here
+        let unpaid = get_unpaid_payout_members_of_staker(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap();
+        assert_eq!(
+            unpaid
+                .iter()
+                .map(|member| member.block_hash)
+                .collect::<Vec<_>>(),
+            vec![
+                member(&alice, 10, 100).block_hash,
+                member(&alice, 20, 300).block_hash
+            ]
+        );
+        assert!(unpaid.iter().all(|member| member.created_at > 0));
+
+        store_manual_payment(
+            &mut conn,
+            &ManualPayment {
+                currency_address: currency_address.clone(),
+                identity_address: alice.clone(),
+                txid,
+                amount: Amount::from_sat(400),
+                n_rounds: 2,
+                operator: "operator".to_string(),
+                reason: "support".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_unpaid_payout_members_follow_the_aligned_chain(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let primary_chain = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        let member = PayoutMember::new(
+            currency_address.clone(),
+            BlockHash::from_str(&format!("{:064x}", 10)).unwrap(),
+            10,
+            alice.clone(),
+            Amount::from_sat(10_000),
+            Decimal::ONE,
+            Amount::ZERO,
+        );
+
+        store_payout_member(&mut conn, &member).await.unwrap();
+        sqlx::query("UPDATE payout_members SET created_at = NOW() - INTERVAL '1 day'")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        // below the min_payout and not paid on the primary chain yet
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            Some(&primary_chain),
+            Amount::from_sat(5_460),
+            false
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        let primary_member = PayoutMember::new(
+            primary_chain.clone(),
+            BlockHash::from_str(&format!("{:064x}", 20)).unwrap(),
+            20,
+            alice.clone(),
+            Amount::from_sat(200_000_000),
+            Decimal::ONE,
+            Amount::ZERO,
+        );
+        let payment = Payment::new(primary_chain.clone(), txid, &[primary_member.clone()]);
+        store_payment(&mut conn, &payment).await.unwrap();
+        store_payment_items(
+            &mut conn,
+            &primary_chain,
+            &txid,
+            &PaymentItem::aggregate(&[primary_member]),
+        )
+        .await
+        .unwrap();
+
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            None,
+            Amount::from_sat(5_460),
+            false
+        )
+        .await
+        .unwrap()
+        .is_empty());
+        assert_eq!(
+            get_unpaid_payout_members(
+                &mut conn,
+                &currency_address,
+                Some(&primary_chain),
+                Amount::from_sat(5_460),
+                false
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|member| member.block_hash)
+            .collect::<Vec<_>>(),
+            vec![member.block_hash]
+        );
+        // the alignment doesn't pay dust
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            Some(&primary_chain),
+            Amount::from_sat(10_001),
+            false
+        )
+        .await
+        .unwrap()
+        .is_empty());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_dust_is_swept_above_the_dust_threshold(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Inactive,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        for (height, reward) in [(10, 4_000), (20, 2_000)] {
+            let member = PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{:064x}", height)).unwrap(),
+                height,
+                alice.clone(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            );
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+
+        // below the min_payout
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            None,
+            Amount::from_sat(5_460),
+            false
+        )
+        .await
+        .unwrap()
+        .is_empty());
+        assert_eq!(
+            get_unpaid_payout_members(
+                &mut conn,
+                &currency_address,
+                None,
+                Amount::from_sat(5_460),
+                true
+            )
+            .await
+            .unwrap()
+            .len(),
+            2
+        );
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            None,
+            Amount::from_sat(6_001),
+            true
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        // a due settlement pays the remaining funds, unless they are dust
+        schedule_settlement(&mut conn, &currency_address, &alice, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_unpaid_payout_members(
+                &mut conn,
+                &currency_address,
+                None,
+                Amount::from_sat(5_460),
+                false
+            )
+            .await
+            .unwrap()
+            .len(),
+            2
+        );
+        assert!(get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            None,
+            Amount::from_sat(6_001),
+            false
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        assert_eq!(
+            get_last_dust_sweep(&pool, &currency_address).await.unwrap(),
+            None
+        );
+        update_last_dust_sweep(&pool, &currency_address)
+            .await
+            .unwrap();
+        assert!(get_last_dust_sweep(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_merge_work_rounds(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        payload.insert(bob.clone(), Decimal::from(50));
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 5)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        store_work(&pool, &currency_address, payload, 2)
+            .await
+            .unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        close_open_round(&mut tx, &currency_address, 7)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        merge_work_rounds(&mut conn, &currency_address, 7, 5)
+            .await
+            .unwrap();
+
+        let rows = sqlx::query("SELECT * FROM work ORDER BY shares DESC")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert!(rows.iter().all(|row| row.get::<i64, &str>("round_id") == 5));
+        assert_eq!(
+            rows[0].get::<String, &str>("staker_address"),
+            alice.to_string()
+        );
+        assert_eq!(rows[0].get::<Decimal, &str>("shares"), Decimal::from(200));
+        assert_eq!(rows[1].get::<Decimal, &str>("shares"), Decimal::from(50));
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_staker_statements(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Inactive,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        store_work(&pool, &currency_address, payload, 1)
+            .await
+            .unwrap();
+
+        sqlx::query(
+            "INSERT INTO payout_members
+                (currency_address, identity_address, block_hash, block_height, shares, reward, fee, txid)
+            VALUES ($1, $2, 'hash', 10, 100, 950, 50, 'txid')",
+        )
+        .bind(currency_address.to_string())
+        .bind(alice.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // the work in round 0 is still owed
+        assert!(get_departed_stakers_without_statement(
+            &mut conn,
+            &currency_address,
+            Amount::from_sat(5_460)
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        forfeit_work(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap();
+
+        // the settlement is not due yet
+        schedule_settlement(&mut conn, &currency_address, &alice, u32::MAX as u64)
+            .await
+            .unwrap();
+        assert!(get_departed_stakers_without_statement(
+            &mut conn,
+            &currency_address,
+            Amount::from_sat(5_460)
+        )
+        .await
+        .unwrap()
+        .is_empty());
+
+        schedule_settlement(&mut conn, &currency_address, &alice, 0)
+            .await
+            .unwrap();
+
+        // a remainder below the dust threshold can't be paid, so it is forfeited
+        sqlx::query(
+            "INSERT INTO payouts
+                (currency_address, block_hash, block_height, amount, work, fee, amount_paid, n_subs)
+            VALUES ($1, 'dust', 20, 1100, 100, 100, 1000, 1)",
+        )
+        .bind(currency_address.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO payout_members
+                (currency_address, identity_address, block_hash, block_height, shares, reward, fee)
+            VALUES ($1, $2, 'dust', 20, 100, 1000, 0)",
+        )
+        .bind(currency_address.to_string())
+        .bind(alice.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+        let departed = get_departed_stakers_without_statement(
+            &mut conn,
+            &currency_address,
+            Amount::from_sat(5_460),
+        )
+        .await
+        .unwrap();
+        assert_eq!(departed.len(), 1);
+        assert_eq!(departed[0].identity_address, staker.identity_address);
+        assert_eq!(departed[0].created_at, departed[0].updated_at);
+
+        assert_eq!(
+            forfeit_unpaid_rewards(&mut conn, &currency_address, &alice)
+                .await
+                .unwrap(),
+            Amount::from_sat(1_000)
+        );
+        // the forfeited reward is no longer owed, and the pool keeps it as fee
+        assert!(
+            get_unpaid_payout_members_of_staker(&mut conn, &currency_address, &alice)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let payout = sqlx::query("SELECT fee, amount_paid FROM payouts WHERE block_hash = 'dust'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(payout.get::<i64, &str>("fee"), 1_100);
+        assert_eq!(payout.get::<i64, &str>("amount_paid"), 0);
+
+        let statement = create_staker_statement(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap();
+        assert_eq!(statement.n_rounds, 2);
+        assert_eq!(statement.rewards, Amount::from_sat(950));
+        assert_eq!(statement.fees, Amount::from_sat(50));
+        assert_eq!(statement.forfeited, Amount::from_sat(1_000));
+
+        assert!(get_departed_stakers_without_statement(
+            &mut conn,
+            &currency_address,
+            Amount::from_sat(5_460)
+        )
+        .await
+        .unwrap()
+        .is_empty());
+        assert_eq!(
+            get_staker_statements(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            vec![statement]
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_ban_staker(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO payout_members
+                (currency_address, identity_address, block_hash, block_height, shares, reward, fee, txid)
+            VALUES
+                ($1, $2, 'paid', 10, 100, 950, 50, 'txid'),
+                ($1, $2, 'unpaid1', 20, 100, 950, 50, NULL),
+                ($1, $2, 'unpaid2', 30, 100, 450, 50, NULL)",
+        )
+        .bind(currency_address.to_string())
+        .bind(alice.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let forfeited = ban_staker(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap();
+        assert_eq!(forfeited, Amount::from_sat(1400));
+
+        let banned = get_staker(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(banned.status, StakerStatus::Banned);
+
+        let rewards =
+            sqlx::query!("SELECT block_hash, reward FROM payout_members ORDER BY block_height")
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|row| (row.block_hash, row.reward))
+                .collect::<Vec<_>>();
+        assert_eq!(
+            rewards,
+            vec![
+                ("paid".to_string(), 950),
+                ("unpaid1".to_string(), 0),
+                ("unpaid2".to_string(), 0)
+            ]
+        );
+
+        // nothing is left to take
+        assert_eq!(
+            ban_staker(&mut conn, &currency_address, &alice)
+                .await
+                .unwrap(),
+            Amount::ZERO
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_fee_overrides(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        store_fee_override(&pool, &currency_address, &alice, Decimal::new(2, 2), None)
+            .await
+            .unwrap();
+        store_fee_override(
+            &pool,
+            &currency_address,
+            &alice,
+            Decimal::new(1, 2),
+            Some("early supporter"),
+        )
+        .await
+        .unwrap();
+
+        let overrides = get_fee_overrides(&pool, &currency_address).await.unwrap();
+        assert_eq!(
+            overrides,
+            HashMap::from([(alice.clone(), Decimal::new(1, 2))])
+        );
+
+        assert!(delete_fee_override(&pool, &currency_address, &alice)
+            .await
+            .unwrap());
+        assert!(!delete_fee_override(&pool, &currency_address, &alice)
+            .await
+            .unwrap());
+        assert!(get_fee_overrides(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_payment_batch_members(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let block_hash =
+            BlockHash::from_str("00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0")
+                .unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let mut batch = PaymentBatch {
+            currency_address: currency_address.clone(),
+            txid: None,
+            size: 1,
+            latency: std::time::Duration::from_millis(1500),
+            payout_members: vec![(alice.clone(), block_hash)],
+        };
+        let failed = store_payment_batch(&pool, &batch).await.unwrap();
+
+        batch.txid = Some(txid);
+        let sent = store_payment_batch(&pool, &batch).await.unwrap();
+
+        let batches = get_payment_batches_of_member(&pool, &currency_address, &alice, &block_hash)
+            .await
+            .unwrap();
+        assert_eq!(batches, vec![(failed, None), (sent, Some(txid))]);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_work_snapshots_dont_change(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        for identity_address in [&alice, &bob] {
+            let staker = Staker::new(
+                currency_address.clone(),
+                identity_address.clone(),
+                "staker@".to_string(),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::new(5, 2),
+            );
+            store_staker(&mut conn, &staker).await.unwrap();
+        }
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        payload.insert(bob.clone(), Decimal::from(50));
+        store_work(&pool, &currency_address, payload, 9)
+            .await
+            .unwrap();
+        forfeit_work(&mut conn, &currency_address, &bob)
+            .await
+            .unwrap();
+
+        let stake = Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000a",
+            )
+            .unwrap(),
+            block_height: 10,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Maturing,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+        store_new_stake(&mut conn, &stake).await.unwrap();
+
+        let round_id = get_round_id(&mut conn, &currency_address, &stake.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        move_work_to_round_zero(&pool, &currency_address, round_id)
+            .await
+            .unwrap();
+
+        let (workers, forfeited_shares) =
+            get_work_snapshot(&pool, &currency_address, &stake.block_hash)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!(workers[0].identity_address, alice);
+        assert_eq!(workers[0].shares, Decimal::from(100));
+        assert_eq!(workers[0].fee, Decimal::new(5, 2));
+        assert_eq!(forfeited_shares, Decimal::from(50));
+
+        assert!(sqlx::query("DELETE FROM work_snapshots")
+            .execute(&pool)
+            .await
+            .is_err());
+        assert!(get_work_snapshot(
+            &pool,
+            &currency_address,
+            &BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000b"
+            )
+            .unwrap()
+        )
+        .await
+        .unwrap()
+        .is_none());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_stale_stake_records_the_redistributed_shares(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        for identity_address in [&alice, &bob] {
+            let staker = Staker::new(
+                currency_address.clone(),
+                identity_address.clone(),
+                "staker@".to_string(),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::new(5, 2),
+            );
+            store_staker(&mut conn, &staker).await.unwrap();
+        }
+
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        payload.insert(bob.clone(), Decimal::from(40));
+        store_work(&pool, &currency_address, payload, 9)
+            .await
+            .unwrap();
+
+        let stake = Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000a",
+            )
+            .unwrap(),
+            block_height: 10,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Maturing,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+        store_new_stake(&mut conn, &stake).await.unwrap();
+
+        let round_id = get_round_id(&mut conn, &currency_address, &stake.block_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        move_stale_work_to_round_zero(&mut conn, &stake, round_id)
+            .await
+            .unwrap();
+        // recording a stale stake twice doesn't duplicate its events
+        move_stale_work_to_round_zero(&mut conn, &stake, round_id)
+            .await
+            .unwrap();
+
+        // bob didn't find the stake, but had work in its round
+        let stale_stakes = get_stale_stakes(&pool, &currency_address, &bob)
+            .await
+            .unwrap();
+        assert_eq!(stale_stakes.len(), 1);
+        assert_eq!(stale_stakes[0].found_by, alice);
+        assert_eq!(
+            stale_stakes[0].redistributed,
+            vec![
+                RedistributedShares {
+                    identity_address: alice.clone(),
+                    shares: Decimal::from(100),
+                },
+                RedistributedShares {
+                    identity_address: bob.clone(),
+                    shares: Decimal::from(40),
+                },
+            ]
+        );
+
+        let other = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+        assert!(get_stale_stakes(&pool, &currency_address, &other)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_imported_stakes_are_stored_once(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut stake = Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000a",
+            )
+            .unwrap(),
+            block_height: 10,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Imported,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(store_imported_stake(&mut conn, &stake).await.unwrap());
+        // a stake that the pool already knows is left alone
+        stake.amount = Amount::from_sat(1);
+        assert!(!store_imported_stake(&mut conn, &stake).await.unwrap());
+
+        let imported =
+            get_stakes_by_status(&mut conn, &currency_address, StakeStatus::Imported, None)
+                .await
+                .unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].amount, Amount::from_sat(600_000_000));
+        assert!(
+            get_round_id(&mut conn, &currency_address, &stake.block_hash)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_payment_journal_entries_stay_open_until_closed(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let other = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let member = PayoutMember::new(
+            currency_address.clone(),
+            BlockHash::from_str(&format!("{:064x}", 10)).unwrap(),
+            10,
+            alice.clone(),
+            Amount::from_sat(1_000),
+            Decimal::ONE,
+            Amount::ZERO,
+        );
+        let mut conn = pool.acquire().await.unwrap();
+        store_payout_member(&mut conn, &member).await.unwrap();
+
+        let tx_fee = TxFee {
+            amount: Amount::from_sat(10),
+            paid_by_pool: true,
+        };
+        let mut items = PaymentItem::aggregate(&[member.clone()]);
+        items[0].payout_currency = Some(other);
+        let sent =
+            open_payment_journal_entry(&pool, &currency_address, &[member.clone()], &items, tx_fee)
+                .await
+                .unwrap();
+        set_payment_journal_opid(&pool, sent, "opid-sent")
+            .await
+            .unwrap();
+        let failed = open_payment_journal_entry(
+            &pool,
+            &currency_address,
+            &[member.clone()],
+            &[],
+            TxFee::default(),
+        )
+        .await
+        .unwrap();
+
+        let entries = get_open_payment_journal_entries(&pool, &currency_address)
+            .await
+            .unwrap();
+        assert_eq!(
+            entries.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+            vec![sent, failed]
+        );
+        assert_eq!(entries[0].opid.as_deref(), Some("opid-sent"));
+        assert_eq!(entries[1].opid, None);
+        assert_eq!(entries[0].members.len(), 1);
+        assert_eq!(entries[0].members[0].identity_address, alice);
+        assert_eq!(entries[0].members[0].reward, Amount::from_sat(1_000));
+        assert_eq!(entries[0].tx_fee, tx_fee);
+        assert_eq!(entries[0].items, items);
+        assert!(entries[1].items.is_empty());
+
+        close_payment_journal_entry(&mut conn, sent, JournalStatus::Sent, Some(&txid))
+            .await
+            .unwrap();
+        close_payment_journal_entry(&mut conn, failed, JournalStatus::Failed, None)
+            .await
+            .unwrap();
+
+        assert!(get_open_payment_journal_entries(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_payout_currencies(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let other = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+
+        store_payout_currency(&pool, &currency_address, &alice, &currency_address)
+            .await
+            .unwrap();
+        store_payout_currency(&pool, &currency_address, &alice, &other)
+            .await
+            .unwrap();
+
+        let payout_currencies = get_payout_currencies(&pool, &currency_address)
+            .await
+            .unwrap();
+        assert_eq!(payout_currencies, HashMap::from([(alice.clone(), other)]));
+
+        assert!(delete_payout_currency(&pool, &currency_address, &alice)
+            .await
+            .unwrap());
+        assert!(!delete_payout_currency(&pool, &currency_address, &alice)
+            .await
+            .unwrap());
+        assert!(get_payout_currencies(&pool, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_time_weighted_work_stores_balance_deltas(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let span = |from_height: u64, to_height: u64, balance: u64| BalanceSpan {
+            identity_address: alice.clone(),
+            from_height,
+            to_height,
+            balance: Amount::from_sat(balance),
+        };
+
+        assert_eq!(
+            get_last_work_height(&mut conn, &currency_address)
+                .await
+                .unwrap(),
+            None
+        );
+
+        assert!(
+            store_time_weighted_work(&mut conn, &currency_address, &[span(10, 10, 100)], 10)
+                .await
+                .unwrap()
+        );
+        // the pool was down for 4 blocks, during which the balance changed once
+        assert!(store_time_weighted_work(
+            &mut conn,
+            &currency_address,
+            &[span(11, 12, 100), span(13, 15, 150)],
+            15
+        )
+        .await
+        .unwrap());
+        // the work of a height is only added once
+        assert!(
+            !store_time_weighted_work(&mut conn, &currency_address, &[span(15, 15, 150)], 15)
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            get_last_work_height(&mut conn, &currency_address)
+                .await
+                .unwrap(),
+            Some(15)
+        );
+        assert_eq!(
+            get_balance_spans(&pool, &currency_address, &alice, 0, 100)
+                .await
+                .unwrap(),
+            vec![span(10, 12, 100), span(13, 15, 150)]
+        );
+
+        let shares = sqlx::query_scalar!(
+            r#"SELECT shares AS "shares!" FROM open_work WHERE currency_address = $1"#,
+            currency_address.to_string()
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(shares, Decimal::from(100 * 3 + 150 * 3));
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_archive_payout_members(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let confirmed =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+        let pending =
+            Txid::from_str("0ba4a4d4e6e8c8a94fd4a8d8e8e9d7d1e2f2a3b4c5d6e7f8091a2b3c4d5e6f70")
+                .unwrap();
+
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
+        store_staker(&mut conn, &staker).await.unwrap();
+
+        let member = |height: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{:064x}", height)).unwrap(),
+                height,
+                alice.clone(),
+                Amount::from_sat(1_000),
+                Decimal::ONE,
+                Amount::from_sat(10),
+            )
+        };
+
+        for height in [10, 11, 12, 13] {
+            store_payout_member(&mut conn, &member(height))
+                .await
+                .unwrap();
+        }
+        for height in [10, 11] {
+            set_txid_payment_member(&mut conn, &member(height), &confirmed)
+                .await
+                .unwrap();
+        }
+        set_txid_payment_member(&mut conn, &member(12), &pending)
+            .await
+            .unwrap();
+
+        let mut payment = Payment::new(
+            currency_address.clone(),
+            confirmed,
+            &[member(10), member(11)],
+        );
+        payment.status = PaymentStatus::Confirmed;
+        store_payment(&mut conn, &payment).await.unwrap();
+        store_payment(
+            &mut conn,
+            &Payment::new(currency_address.clone(), pending, &[member(12)]),
+        )
+        .await
+        .unwrap();
+
+        sqlx::query("UPDATE payout_members SET created_at = NOW() - INTERVAL '2 months'")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+        let total_rewards = get_total_rewards(&pool, &currency_address).await.unwrap();
+
+        // the month of the members didn't end 3 months ago yet
+        assert_eq!(
+            archive_payout_members(&pool, &currency_address, 3)
+                .await
+                .unwrap(),
+            0
+        );
+        // only the members of the confirmed payment are archived
+        assert_eq!(
+            archive_payout_members(&pool, &currency_address, 0)
+                .await
+                .unwrap(),
+            2
+        );
+
+        let members = get_payout_members(&mut conn, &currency_address, &[alice.clone()])
+            .await
+            .unwrap();
+        assert_eq!(
+            members
+                .iter()
+                .map(|member| member.block_height)
+                .collect::<Vec<_>>(),
+            vec![12, 13]
+        );
+        assert_eq!(
+            get_archived_rewards(&mut conn, &currency_address, &[alice.clone()])
+                .await
+                .unwrap(),
+            HashMap::from([(alice.clone(), Amount::from_sat(2_000))])
+        );
+        assert_eq!(
+            get_total_rewards(&pool, &currency_address).await.unwrap(),
+            total_rewards
+        );
+
+        let statement = create_staker_statement(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap();
+        assert_eq!(statement.n_rounds, 4);
+        assert_eq!(statement.rewards, Amount::from_sat(4_000));
+        assert_eq!(statement.fees, Amount::from_sat(40));
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_staker_webhooks(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        assert_eq!(
+            get_staker_webhook(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let webhook = StakerWebhook {
+            endpoint: Url::parse("https://example.com/hooks/pool").unwrap(),
+            events: vec![
+                StakerWebhookEvent::StakeMatured,
+                StakerWebhookEvent::PaymentSent,
+            ],
+            secret: Some("secret".to_string()),
+        };
+        set_staker_webhook(&pool, &currency_address, &alice, Some(&webhook))
+            .await
+            .unwrap();
+        assert_eq!(
+            get_staker_webhook(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            Some(webhook)
+        );
+
+        set_staker_webhook(&pool, &currency_address, &alice, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_staker_webhook(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_webhook_event_ids(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let other = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        for expected in 1..=3 {
+            assert_eq!(
+                next_webhook_event_id(&pool, &currency_address)
+                    .await
+                    .unwrap(),
+                expected
+            );
+        }
+
+        assert_eq!(next_webhook_event_id(&pool, &other).await.unwrap(), 1);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_find_payout_members(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let member = |identity_address: &Address, block_height: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                identity_address.clone(),
+                Amount::from_sat(100),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        for member in [
+            member(&alice, 10),
+            member(&alice, 20),
+            member(&alice, 30),
+            member(&bob, 20),
+        ] {
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+        set_txid_payment_member(&mut conn, &member(&alice, 10), &txid)
+            .await
+            .unwrap();
+
+        let heights = |filter: PayoutMemberFilter, page: Page| {
+            let pool = pool.clone();
+            let currency_address = currency_address.clone();
+
+            async move {
+                find_payout_members(&pool, &currency_address, &filter, page)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|member| (member.identity_address, member.block_height))
+                    .collect::<Vec<_>>()
+            }
+        };
+        let all = Page {
+            limit: 100,
+            offset: 0,
+        };
+
+        assert_eq!(
+            heights(
+                PayoutMemberFilter {
+                    identity_address: Some(alice.clone()),
+                    paid: Some(false),
+                    ..Default::default()
+                },
+                all
+            )
+            .await,
+            vec![(alice.clone(), 30), (alice.clone(), 20)]
+        );
+        assert_eq!(
+            heights(
+                PayoutMemberFilter {
+                    block_hash: Some(BlockHash::from_str(&format!("{:064x}", 20)).unwrap()),
+                    ..Default::default()
+                },
+                all
+            )
+            .await,
+            vec![(alice.clone(), 20), (bob.clone(), 20)]
+        );
+        assert_eq!(
+            heights(
+                PayoutMemberFilter::default(),
+                Page {
+                    limit: 2,
+                    offset: 1
+                }
+            )
+            .await,
+            vec![(alice.clone(), 20), (bob, 20)]
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_earnings_timeseries(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        for (block_height, credited_at) in [
+            (10, "2024-11-04 10:00:00+00"),
+            (20, "2024-11-04 23:00:00+00"),
+            (30, "2024-11-06 01:00:00+00"),
+        ] {
+            let member = PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                alice.clone(),
+                Amount::from_sat(100),
+                Decimal::ONE,
+                Amount::from_sat(5),
+            );
+            store_payout_member(&mut conn, &member).await.unwrap();
+            sqlx::query(
+                "UPDATE payout_members SET created_at = $1::timestamptz WHERE block_height = $2",
+            )
+            .bind(credited_at)
+            .bind(block_height as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let summary = |granularity| {
+            let pool = pool.clone();
+            let currency_address = currency_address.clone();
+            let alice = alice.clone();
+
+            async move {
+                get_earnings_timeseries(&pool, &currency_address, &alice, granularity)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|period| {
+                        (
+                            period.period_start,
+                            period.rewards.as_sat(),
+                            period.fees.as_sat(),
+                            period.n_stakes,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // 2024-11-04 and 2024-11-06
+        assert_eq!(
+            summary(EarningsGranularity::Day).await,
+            vec![(1730678400, 200, 10, 2), (1730851200, 100, 5, 1)]
+        );
+        // the week of monday 2024-11-04
+        assert_eq!(
+            summary(EarningsGranularity::Week).await,
+            vec![(1730678400, 300, 15, 3)]
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_payout_members_are_exported_in_batches(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        for block_height in [10, 20, 30] {
+            let member = PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                alice.clone(),
+                Amount::from_sat(100),
+                Decimal::ONE,
+                Amount::ZERO,
+            );
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+
+        let first =
+            get_payout_members_for_export(&pool, &currency_address, &alice, None, None, None, 2)
+                .await
+                .unwrap();
+        assert_eq!(
+            first.iter().map(|m| m.block_height).collect::<Vec<_>>(),
+            vec![10, 20]
+        );
+
+        let last = first.last().unwrap();
+        let second = get_payout_members_for_export(
+            &pool,
+            &currency_address,
+            &alice,
+            None,
+            None,
+            Some((last.block_height, last.block_hash)),
+            2,
         )
-        SELECT 
-            pm.currency_address,
-            pm.identity_address,
-            pm.block_hash,
-            pm.block_height,
-            pm.shares,
-            pm.reward,
-            pm.fee,
-            pm.txid
-        FROM payout_members pm
-        JOIN pm_sum ON pm.currency_address = pm_sum.currency_address
-            AND pm.identity_address = pm_sum.identity_address
-            AND pm.txid IS NULL
-        JOIN stakers s ON pm.currency_address = s.currency_address
-            AND pm.identity_address = s.identity_address
-        WHERE pm_sum.total_rewards > s.min_payout 
-            OR s.status = 'INACTIVE'
-        FOR UPDATE",
-        currency_address.to_string(),
-    )
-    .try_map(PayoutMember::try_from)
-    .fetch_all(conn)
-    .await?;
+        .await
+        .unwrap();
+        assert_eq!(
+            second.iter().map(|m| m.block_height).collect::<Vec<_>>(),
+            vec![30]
+        );
 
-    Ok(values)
-}
+        // nothing was credited before the epoch
+        assert!(get_payout_members_for_export(
+            &pool,
+            &currency_address,
+            &alice,
+            None,
+            Some(0),
+            None,
+            2
+        )
+        .await
+        .unwrap()
+        .is_empty());
+    }
 
-pub async fn set_txid_payment_member(
-    conn: &mut PgConnection,
-    payout_member: &PayoutMember,
-    txid: &Txid,
-) -> Result<()> {
-    sqlx::query!(
-        "UPDATE payout_members 
-        SET txid = $4 
-        WHERE currency_address = $1 AND 
-            identity_address = $2 AND 
-            block_height = $3",
-        payout_member.currency_address.to_string(),
-        payout_member.identity_address.to_string(),
-        payout_member.block_height as i64,
-        txid.to_string()
-    )
-    .execute(&mut *conn)
-    .await?;
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_pool_luck(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
 
-    Ok(())
-}
+        assert_eq!(
+            get_pool_luck(&pool, &currency_address, 0).await.unwrap(),
+            None
+        );
 
-pub async fn get_number_of_matured_stakes(
-    conn: &PgPool,
-    currency_address: &Address,
-) -> Result<i64> {
-    let res: Option<i64> = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM stakes WHERE currency_address = $1 AND status = 'MATURED'",
-        currency_address.to_string()
-    )
-    .fetch_one(conn)
-    .await?;
+        // the pool has a quarter of the staking supply, and blocks 1 to 3 are PoS blocks
+        for block_height in 1..=4 {
+            store_network_snapshot(
+                &mut conn,
+                &currency_address,
+                block_height,
+                &BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                100.0,
+                Amount::from_sat(2_500_000_000),
+                block_height < 4,
+            )
+            .await
+            .unwrap();
+        }
 
-    Ok(res.unwrap_or(0))
-}
+        for (block_height, status) in [(2, StakeStatus::Matured), (3, StakeStatus::Stale)] {
+            let stake = Stake {
+                currency_address: currency_address.clone(),
+                block_hash: BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                found_by: alice.clone(),
+                source_txid: Txid::from_str(
+                    "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+                )
+                .unwrap(),
+                source_vout_num: 0,
+                source_amount: Amount::from_sat(100_000_000),
+                status,
+                amount: Amount::from_sat(600_000_000),
+                created_at: 0,
+                updated_at: 0,
+            };
+            store_stake(&mut conn, &stake).await.unwrap();
+        }
 
-pub async fn get_number_of_active_stakers(
-    conn: &PgPool,
-    currency_address: &Address,
-) -> Result<i64> {
-    let res: Option<i64> = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM stakers WHERE currency_address = $1 AND status = 'ACTIVE'",
-        currency_address.to_string()
-    )
-    .fetch_one(conn)
-    .await?;
+        let luck = get_pool_luck(&pool, &currency_address, 0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((luck.from_height, luck.to_height), (1, 4));
+        assert!((luck.expected_stakes - 0.75).abs() < 1e-9);
+        // the stale stake is not counted
+        assert_eq!(luck.actual_stakes, 1);
+        assert!((luck.luck.unwrap() - 400.0 / 3.0).abs() < 1e-9);
+        // one stake has no time between stakes
+        assert_eq!(luck.mean_time_between_stakes_in_secs, None);
 
-    Ok(res.unwrap_or(0))
-}
+        let window = get_pool_luck(&pool, &currency_address, 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((window.from_height, window.actual_stakes), (4, 0));
+        assert_eq!(window.expected_stakes, 0.0);
+        assert_eq!(window.luck, None);
+    }
 
-pub async fn get_total_rewards(conn: &PgPool, currency_address: &Address) -> Result<Amount> {
-    let res: Option<Amount> = sqlx::query!(
-        "SELECT SUM(reward)::bigint as total 
-        FROM payout_members 
-        WHERE 
-            currency_address = $1 AND 
-            txid is not null",
-        currency_address.to_string()
-    )
-    .fetch_one(conn)
-    .await?
-    .total
-    .map(|r| Amount::from_sat(r as u64));
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_fraud_evidence(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
 
-    Ok(res.unwrap_or(Amount::ZERO))
-}
+        let mut evidence = FraudEvidence {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(&format!("{:064x}", 10)).unwrap(),
+            block_height: 10,
+            identity_address: alice,
+            source_txid: txid,
+            source_vout_num: 1,
+            spend_txid: txid,
+            spend_block_hash: None,
+            spend_tx: "0400008085202f89".to_string(),
+            proof: None,
+            competing_txid: None,
+            competing_source_txid: None,
+            competing_source_vout_num: None,
+            created_at: 0,
+        };
+        store_fraud_evidence(&mut conn, &evidence).await.unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // collecting the evidence again replaces it
+        evidence.competing_source_txid = Some(txid);
+        evidence.competing_source_vout_num = Some(1);
+        store_fraud_evidence(&mut conn, &evidence).await.unwrap();
+
+        let stored = get_fraud_evidence(&pool, &currency_address).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(
+            FraudEvidence {
+                created_at: 0,
+                ..stored[0].clone()
+            },
+            evidence
+        );
+    }
 
     #[sqlx::test(migrations = "sql/migrations")]
-    async fn test_store_work(pool: PgPool) {
+    async fn test_unlocking_stakers(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
         let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
 
-        let mut payload = HashMap::new();
+        store_unlock_height(&mut conn, &currency_address, &alice, 2000)
+            .await
+            .unwrap();
+        store_unlock_height(&mut conn, &currency_address, &bob, 1000)
+            .await
+            .unwrap();
+        // bob extended its lock
+        store_unlock_height(&mut conn, &currency_address, &bob, 3000)
+            .await
+            .unwrap();
 
-        payload.insert(
-            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
-            Decimal::from_f64_retain(1.23).unwrap(),
+        assert!(get_unlocking_stakers(&mut conn, &currency_address, 2000)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            get_unlocking_stakers(&mut conn, &currency_address, 2001)
+                .await
+                .unwrap(),
+            vec![alice.clone()]
         );
 
-        store_work(&pool, &currency_address, payload, 1)
+        remove_unlock_height(&mut conn, &currency_address, &alice)
             .await
             .unwrap();
+        assert_eq!(
+            get_unlocking_stakers(&mut conn, &currency_address, 3001)
+                .await
+                .unwrap(),
+            vec![bob]
+        );
+    }
 
-        let rows = sqlx::query("SELECT * FROM work")
-            .fetch_all(&pool)
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_staker_primary_addresses(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let old = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let new = Address::from_str("RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK").unwrap();
+
+        store_staker_primary_address(&mut conn, &currency_address, &alice, Some(&old))
+            .await
+            .unwrap();
+        // alice updated its VerusID to the new address
+        store_staker_primary_address(&mut conn, &currency_address, &alice, Some(&new))
             .await
             .unwrap();
+        assert_eq!(
+            get_staker_primary_address(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            Some(new)
+        );
 
-        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
-        assert!(shares.is_sign_positive());
-        assert_eq!(shares, Decimal::from_f64_retain(1.23).unwrap());
+        store_staker_primary_address(&mut conn, &currency_address, &alice, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_staker_primary_address(&pool, &currency_address, &alice)
+                .await
+                .unwrap(),
+            None
+        );
     }
 
     #[sqlx::test(migrations = "sql/migrations")]
-    async fn test_multiple_store_work(pool: PgPool) {
+    async fn test_process_block_is_all_or_nothing(pool: PgPool) {
         let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let staker = Staker::new(
+            currency_address.clone(),
+            alice.clone(),
+            "alice@".to_string(),
+            Amount::from_sat(100_000_000),
+            StakerStatus::Active,
+            Decimal::ZERO,
+        );
 
-        let mut payload = HashMap::new();
+        // a block that fails halfway is rolled back
+        let mut tx = process_block(&pool).await.unwrap();
+        store_staker(&mut tx, &staker).await.unwrap();
+        update_last_height(&mut tx, &currency_address, 100)
+            .await
+            .unwrap();
+        drop(tx);
 
-        payload.insert(
-            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
-            Decimal::from_f32_retain(1.23).unwrap(),
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(get_staker(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            get_last_height(&pool, &currency_address).await.unwrap(),
+            None
         );
 
-        store_work(&pool, &currency_address, payload, 1)
+        let mut tx = process_block(&pool).await.unwrap();
+        store_staker(&mut tx, &staker).await.unwrap();
+        update_last_height(&mut tx, &currency_address, 100)
             .await
             .unwrap();
+        tx.commit().await.unwrap();
 
-        let rows = sqlx::query("SELECT * FROM work")
-            .fetch_all(&pool)
+        assert!(get_staker(&mut conn, &currency_address, &alice)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            get_last_height(&pool, &currency_address).await.unwrap(),
+            Some(100)
+        );
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_payout_shadow_diff(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        let member = |identity_address: &Address, block_height: u64, reward: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                identity_address.clone(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        for member in [
+            member(&alice, 10, 100),
+            member(&bob, 10, 500),
+            member(&alice, 20, 300),
+        ] {
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+        // bob is not in the shadow payout and the stake at 20 has no shadow payout
+        store_payout_shadow_member(&mut conn, &member(&alice, 10, 150))
             .await
             .unwrap();
 
-        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
-        assert!(shares.is_sign_positive());
-        assert_eq!(shares, Decimal::from_f32_retain(1.23).unwrap());
+        let diff = get_payout_shadow_diff(&pool, &currency_address, Amount::from_sat(40))
+            .await
+            .unwrap();
 
-        let mut payload = HashMap::new();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0].identity_address, bob);
+        assert_eq!(diff[0].reward, Some(Amount::from_sat(500)));
+        assert_eq!(diff[0].shadow_reward, None);
+        assert_eq!(diff[0].difference, -500);
+        assert_eq!(diff[1].identity_address, alice);
+        assert_eq!(diff[1].block_height, 10);
+        assert_eq!(diff[1].difference, 50);
 
-        payload.insert(
-            Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap(),
-            Decimal::from_f32_retain(3.77).unwrap(),
+        let diff = get_payout_shadow_diff(&pool, &currency_address, Amount::from_sat(50))
+            .await
+            .unwrap();
+
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].identity_address, bob);
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_cold_stakers(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let cold = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let payout_address = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        store_staker(
+            &mut conn,
+            &Staker::new(
+                currency_address.clone(),
+                alice.clone(),
+                String::from("alice@"),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::ZERO,
+            ),
+        )
+        .await
+        .unwrap();
+        store_staker(
+            &mut conn,
+            &Staker::cold_staker(
+                currency_address.clone(),
+                cold.clone(),
+                payout_address.clone(),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::ZERO,
+            ),
+        )
+        .await
+        .unwrap();
+
+        let cold_stakers = get_cold_stakers(&mut conn, &currency_address)
+            .await
+            .unwrap();
+        assert_eq!(cold_stakers.len(), 1);
+        assert_eq!(cold_stakers[0].identity_address, cold);
+        assert_eq!(
+            cold_stakers[0].participation_kind,
+            ParticipationKind::ColdStaking
         );
+        assert_eq!(cold_stakers[0].payout_address, Some(payout_address.clone()));
 
-        store_work(&pool, &currency_address, payload, 1)
+        assert_eq!(
+            get_payout_addresses(&pool, &currency_address)
+                .await
+                .unwrap(),
+            HashMap::from([(cold.clone(), payout_address)])
+        );
+
+        ban_staker(&mut conn, &currency_address, &cold)
             .await
             .unwrap();
+        assert!(get_cold_stakers(&mut conn, &currency_address)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 
-        let rows = sqlx::query("SELECT * FROM work")
-            .fetch_all(&pool)
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_recorded_and_recent_payout_txids(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let payout_txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+        let sweep_txid =
+            Txid::from_str("f7b960c2b3e098926401ee3acafc3e1724fd0bbc180d72440229eb461d44976b")
+                .unwrap();
+
+        let member = |identity_address: &Address, block_height: u64, reward: u64| {
+            PayoutMember::new(
+                currency_address.clone(),
+                BlockHash::from_str(&format!("{block_height:064x}")).unwrap(),
+                block_height,
+                identity_address.clone(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let mut conn = pool.acquire().await.unwrap();
+        for member in [
+            member(&alice, 10, 100),
+            member(&bob, 10, 300),
+            member(&alice, 20, 500),
+        ] {
+            store_payout_member(&mut conn, &member).await.unwrap();
+        }
+        for member in [member(&alice, 10, 100), member(&bob, 10, 300)] {
+            set_txid_payment_member(&mut conn, &member, &payout_txid)
+                .await
+                .unwrap();
+        }
+        store_fee_sweep(
+            &pool,
+            &currency_address,
+            &sweep_txid,
+            &alice,
+            Amount::from_sat(1_000),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            get_recorded_txids(&pool, &currency_address).await.unwrap(),
+            HashSet::from([payout_txid, sweep_txid])
+        );
+        assert_eq!(
+            get_recent_payout_txids(&pool, &currency_address, 30)
+                .await
+                .unwrap(),
+            vec![(payout_txid, 2, Amount::from_sat(400))]
+        );
+
+        sqlx::query!("UPDATE payout_members SET updated_at = NOW() - INTERVAL '31 days'")
+            .execute(&pool)
             .await
             .unwrap();
+        assert!(get_recent_payout_txids(&pool, &currency_address, 30)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 
-        assert!(rows.len() == 1);
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn test_share_ledger(pool: PgPool) {
+        let mut conn = pool.acquire().await.unwrap();
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
 
-        let shares = rows.first().unwrap().get::<Decimal, &str>("shares");
-        assert!(shares.is_sign_positive());
+        store_staker(
+            &mut conn,
+            &Staker::new(
+                currency_address.clone(),
+                alice.clone(),
+                String::from("alice@"),
+                Amount::from_sat(100_000_000),
+                StakerStatus::Active,
+                Decimal::ZERO,
+            ),
+        )
+        .await
+        .unwrap();
 
-        assert_eq!(shares, Decimal::from_f32_retain(5.0).unwrap());
+        let mut payload = HashMap::new();
+        payload.insert(alice.clone(), Decimal::from(100));
+        store_work(&pool, &currency_address, payload.clone(), 1)
+            .await
+            .unwrap();
+
+        // the work of a block that is processed again is not appended twice
+        let mut tx = pool.begin().await.unwrap();
+        store_shares(&mut tx, &currency_address, payload, 1)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        assert!(sqlx::query("UPDATE share_events SET delta = 0")
+            .execute(&pool)
+            .await
+            .is_err());
+
+        let missed = Stake {
+            currency_address: currency_address.clone(),
+            block_hash: BlockHash::from_str(
+                "000000000000000000000000000000000000000000000000000000000000000a",
+            )
+            .unwrap(),
+            block_height: 1,
+            found_by: alice.clone(),
+            source_txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            source_vout_num: 0,
+            source_amount: Amount::from_sat(100_000_000),
+            status: StakeStatus::Maturing,
+            amount: Amount::from_sat(600_000_000),
+            created_at: 0,
+            updated_at: 0,
+        };
+
+        let mut tx = pool.begin().await.unwrap();
+        let round_id = store_historical_stake(&mut tx, &missed, 0, Decimal::new(25, 2))
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let split = get_workers_by_round(&pool, &currency_address, round_id)
+            .await
+            .unwrap();
+        assert_eq!(split.len(), 1);
+        assert_eq!(split[0].shares, Decimal::from(25));
+
+        let open = get_workers_by_round(&pool, &currency_address, 0)
+            .await
+            .unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].shares, Decimal::from(75));
+
+        let kinds = sqlx::query_scalar!(
+            "SELECT kind::text AS \"kind!\" FROM share_events WHERE currency_address = $1 ORDER BY id",
+            currency_address.to_string()
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(kinds, vec!["WORK", "SPLIT"]);
     }
 }
@@ -0,0 +1,1072 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use sqlx::{PgPool, Row};
+
+use crate::MIGRATOR;
+
+/// A table that the pool expects, with the columns and indexes that its queries rely on.
+struct ExpectedTable {
+    name: &'static str,
+    /// Tables that hold work, rewards or payments. Drift in these tables can lead to wrong
+    /// payouts, so the pool refuses to start when they drifted.
+    financial: bool,
+    columns: &'static [&'static str],
+    /// The name of every index, with the statement that recreates it.
+    indexes: &'static [(&'static str, &'static str)],
+}
+
+/// The schema after the last migration.
+const EXPECTED_SCHEMA: &[ExpectedTable] = &[
+    ExpectedTable {
+        name: "stakers",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "identity_name",
+            "status",
+            "min_payout",
+            "fee",
+            "participation_kind",
+            "payout_address",
+        ],
+        indexes: &[(
+            "stakers_pkey",
+            "ALTER TABLE stakers ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "work",
+        financial: true,
+        columns: &["currency_address", "round_id", "staker_address", "shares"],
+        indexes: &[(
+            "work_pkey",
+            "ALTER TABLE work ADD PRIMARY KEY (currency_address, round_id, staker_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "forfeited_work",
+        financial: true,
+        columns: &["currency_address", "round_id", "staker_address", "shares"],
+        indexes: &[(
+            "forfeited_work_pkey",
+            "ALTER TABLE forfeited_work ADD PRIMARY KEY (currency_address, round_id, staker_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "rounds",
+        financial: true,
+        columns: &["id", "currency_address", "block_hash", "block_height"],
+        indexes: &[
+            ("rounds_pkey", "ALTER TABLE rounds ADD PRIMARY KEY (id)"),
+            (
+                "rounds_currency_address_block_hash_key",
+                "ALTER TABLE rounds ADD UNIQUE (currency_address, block_hash)",
+            ),
+            (
+                "rounds_block_height_idx",
+                "CREATE INDEX rounds_block_height_idx ON rounds (currency_address, block_height)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "stakes",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "block_hash",
+            "block_height",
+            "amount",
+            "found_by",
+            "source_txid",
+            "source_vout_num",
+            "source_amount",
+            "status",
+        ],
+        indexes: &[(
+            "stakes_pkey",
+            "ALTER TABLE stakes ADD PRIMARY KEY (currency_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "payouts",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "block_hash",
+            "block_height",
+            "amount",
+            "work",
+            "fee",
+            "amount_paid",
+            "n_subs",
+        ],
+        indexes: &[(
+            "payouts_pkey",
+            "ALTER TABLE payouts ADD PRIMARY KEY (currency_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "payout_members",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "block_hash",
+            "block_height",
+            "shares",
+            "reward",
+            "fee",
+            "txid",
+            "created_at",
+        ],
+        indexes: &[
+            (
+                "payout_members_pkey",
+                "ALTER TABLE payout_members ADD PRIMARY KEY (currency_address, identity_address, block_hash)",
+            ),
+            (
+                "payout_members_unpaid_idx",
+                "CREATE INDEX payout_members_unpaid_idx ON payout_members (currency_address, identity_address) WHERE txid IS NULL",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "payments",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "txid",
+            "amount",
+            "n_members",
+            "status",
+            "confirmations",
+            "tx_fee",
+            "tx_fee_paid_by_pool",
+            "created_at",
+        ],
+        indexes: &[(
+            "payments_pkey",
+            "ALTER TABLE payments ADD PRIMARY KEY (currency_address, txid)",
+        )],
+    },
+    ExpectedTable {
+        name: "payment_items",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "txid",
+            "identity_address",
+            "amount",
+            "block_hashes",
+            "destination",
+            "payout_currency",
+        ],
+        indexes: &[(
+            "payment_items_pkey",
+            "ALTER TABLE payment_items ADD PRIMARY KEY (currency_address, txid, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "scheduled_forfeits",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "staker_address",
+            "rounds_left",
+            "held_shares",
+        ],
+        indexes: &[(
+            "scheduled_forfeits_pkey",
+            "ALTER TABLE scheduled_forfeits ADD PRIMARY KEY (currency_address, staker_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "synchronization",
+        financial: false,
+        columns: &["currency_address", "last_height", "last_payout_height"],
+        indexes: &[(
+            "synchronization_pkey",
+            "ALTER TABLE synchronization ADD PRIMARY KEY (currency_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "network_stats",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "block_height",
+            "block_hash",
+            "staking_supply",
+            "difficulty",
+            "is_stake",
+        ],
+        indexes: &[(
+            "network_stats_pkey",
+            "ALTER TABLE network_stats ADD PRIMARY KEY (currency_address, block_height)",
+        )],
+    },
+    ExpectedTable {
+        name: "primary_address_rotation",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "new_address",
+            "migrated",
+            "notified",
+        ],
+        indexes: &[(
+            "primary_address_rotation_pkey",
+            "ALTER TABLE primary_address_rotation ADD PRIMARY KEY (currency_address, identity_address, new_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "staker_primary_addresses",
+        financial: false,
+        columns: &["currency_address", "identity_address", "primary_address"],
+        indexes: &[(
+            "staker_primary_addresses_pkey",
+            "ALTER TABLE staker_primary_addresses ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "key_rotations",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "old_address",
+            "new_address",
+            "ends_at",
+            "operator",
+            "retired_at",
+            "retired_by",
+        ],
+        indexes: &[
+            (
+                "key_rotations_pkey",
+                "ALTER TABLE key_rotations ADD PRIMARY KEY (id)",
+            ),
+            (
+                "key_rotations_open_idx",
+                "CREATE UNIQUE INDEX key_rotations_open_idx ON key_rotations (currency_address) WHERE retired_at IS NULL",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "delegated_addresses",
+        financial: false,
+        columns: &["currency_address", "identity_address", "address"],
+        indexes: &[
+            (
+                "delegated_addresses_pkey",
+                "ALTER TABLE delegated_addresses ADD PRIMARY KEY (currency_address, address)",
+            ),
+            (
+                "delegated_addresses_identity_address_idx",
+                "CREATE INDEX delegated_addresses_identity_address_idx ON delegated_addresses (currency_address, identity_address)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "accounting_exports",
+        financial: false,
+        columns: &["currency_address", "kind", "reference"],
+        indexes: &[(
+            "accounting_exports_pkey",
+            "ALTER TABLE accounting_exports ADD PRIMARY KEY (currency_address, kind, reference)",
+        )],
+    },
+    ExpectedTable {
+        name: "staker_events",
+        financial: false,
+        columns: &["id", "currency_address", "identity_address", "status"],
+        indexes: &[
+            ("staker_events_pkey", "ALTER TABLE staker_events ADD PRIMARY KEY (id)"),
+            (
+                "staker_events_identity_idx",
+                "CREATE INDEX staker_events_identity_idx ON staker_events (currency_address, identity_address)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "round_merges",
+        financial: false,
+        columns: &["id", "currency_address", "from_round", "into_round", "changes"],
+        indexes: &[("round_merges_pkey", "ALTER TABLE round_merges ADD PRIMARY KEY (id)")],
+    },
+    ExpectedTable {
+        name: "fee_sweeps",
+        financial: true,
+        columns: &["currency_address", "txid", "destination", "amount"],
+        indexes: &[(
+            "fee_sweeps_pkey",
+            "ALTER TABLE fee_sweeps ADD PRIMARY KEY (currency_address, txid)",
+        )],
+    },
+    ExpectedTable {
+        name: "destination_whitelist",
+        financial: false,
+        columns: &["currency_address", "address", "note"],
+        indexes: &[(
+            "destination_whitelist_pkey",
+            "ALTER TABLE destination_whitelist ADD PRIMARY KEY (currency_address, address)",
+        )],
+    },
+    ExpectedTable {
+        name: "manual_payments",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "identity_address",
+            "txid",
+            "amount",
+            "n_rounds",
+            "operator",
+            "reason",
+        ],
+        indexes: &[(
+            "manual_payments_pkey",
+            "ALTER TABLE manual_payments ADD PRIMARY KEY (id)",
+        )],
+    },
+    ExpectedTable {
+        name: "block_summaries",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "block_hash",
+            "block_height",
+            "stakers_counted",
+            "shares_added",
+            "identities_checked",
+            "stake_found",
+            "daemon_staking",
+            "phases",
+            "duration_ms",
+        ],
+        indexes: &[
+            (
+                "block_summaries_pkey",
+                "ALTER TABLE block_summaries ADD PRIMARY KEY (currency_address, block_hash)",
+            ),
+            (
+                "block_summaries_height_idx",
+                "CREATE INDEX block_summaries_height_idx ON block_summaries (currency_address, block_height)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "webhook_outbox",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "endpoint",
+            "body",
+            "attempts",
+            "next_attempt_at",
+            "last_error",
+            "dead_at",
+        ],
+        indexes: &[(
+            "webhook_outbox_pkey",
+            "ALTER TABLE webhook_outbox ADD PRIMARY KEY (id)",
+        )],
+    },
+    ExpectedTable {
+        name: "webhook_event_ids",
+        financial: false,
+        columns: &["currency_address", "last_event_id"],
+        indexes: &[(
+            "webhook_event_ids_pkey",
+            "ALTER TABLE webhook_event_ids ADD PRIMARY KEY (currency_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "network_snapshots",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "block_height",
+            "block_hash",
+            "network_staking_supply",
+            "pool_staking_supply",
+            "is_stake",
+        ],
+        indexes: &[(
+            "network_snapshots_pkey",
+            "ALTER TABLE network_snapshots ADD PRIMARY KEY (currency_address, block_height)",
+        )],
+    },
+    ExpectedTable {
+        name: "fraud_evidence",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "block_hash",
+            "block_height",
+            "identity_address",
+            "source_txid",
+            "source_vout_num",
+            "spend_txid",
+            "spend_block_hash",
+            "spend_tx",
+            "proof",
+            "competing_txid",
+            "competing_source_txid",
+            "competing_source_vout_num",
+        ],
+        indexes: &[(
+            "fraud_evidence_pkey",
+            "ALTER TABLE fraud_evidence ADD PRIMARY KEY (currency_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "staker_settlements",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "settle_at",
+            "settled_at",
+        ],
+        indexes: &[(
+            "staker_settlements_pkey",
+            "ALTER TABLE staker_settlements ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "identity_unlocks",
+        financial: false,
+        columns: &["currency_address", "identity_address", "unlock_height"],
+        indexes: &[(
+            "identity_unlocks_pkey",
+            "ALTER TABLE identity_unlocks ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "payout_shadow",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "block_hash",
+            "block_height",
+            "shares",
+            "reward",
+            "fee",
+        ],
+        indexes: &[(
+            "payout_shadow_pkey",
+            "ALTER TABLE payout_shadow ADD PRIMARY KEY (currency_address, identity_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "share_events",
+        financial: true,
+        columns: &[
+            "id",
+            "currency_address",
+            "staker_address",
+            "block_height",
+            "kind",
+            "delta",
+        ],
+        indexes: &[
+            ("share_events_pkey", "ALTER TABLE share_events ADD PRIMARY KEY (id)"),
+            (
+                "share_events_work_idx",
+                "CREATE UNIQUE INDEX share_events_work_idx ON share_events (currency_address, staker_address, block_height) WHERE kind = 'WORK'",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "round_closures",
+        financial: true,
+        columns: &["id", "currency_address", "round_id", "last_share_event_id"],
+        indexes: &[
+            ("round_closures_pkey", "ALTER TABLE round_closures ADD PRIMARY KEY (id)"),
+            (
+                "round_closures_last_share_event_id_idx",
+                "CREATE INDEX round_closures_last_share_event_id_idx ON round_closures (currency_address, last_share_event_id)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "payment_batches",
+        financial: false,
+        columns: &["id", "currency_address", "txid", "size", "latency_ms"],
+        indexes: &[(
+            "payment_batches_pkey",
+            "ALTER TABLE payment_batches ADD PRIMARY KEY (id)",
+        )],
+    },
+    ExpectedTable {
+        name: "payment_batch_members",
+        financial: false,
+        columns: &["payment_batch_id", "identity_address", "block_hash"],
+        indexes: &[(
+            "payment_batch_members_pkey",
+            "ALTER TABLE payment_batch_members ADD PRIMARY KEY (payment_batch_id, identity_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "staker_statements",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "identity_address",
+            "identity_name",
+            "joined_at",
+            "left_at",
+            "n_rounds",
+            "rewards",
+            "fees",
+            "forfeited",
+        ],
+        indexes: &[
+            (
+                "staker_statements_pkey",
+                "ALTER TABLE staker_statements ADD PRIMARY KEY (id)",
+            ),
+            (
+                "staker_statements_identity_idx",
+                "CREATE INDEX staker_statements_identity_idx ON staker_statements (currency_address, identity_address)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "work_revisions",
+        financial: true,
+        columns: &["currency_address", "block_height", "revision"],
+        indexes: &[(
+            "work_revisions_pkey",
+            "ALTER TABLE work_revisions ADD PRIMARY KEY (currency_address, block_height)",
+        )],
+    },
+    ExpectedTable {
+        name: "fee_overrides",
+        financial: true,
+        columns: &["currency_address", "identity_address", "fee", "reason"],
+        indexes: &[(
+            "fee_overrides_pkey",
+            "ALTER TABLE fee_overrides ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "fee_history",
+        financial: true,
+        columns: &[
+            "id",
+            "currency_address",
+            "block_hash",
+            "identity_address",
+            "staker_fee",
+            "source",
+            "discount",
+            "fee",
+            "average_balance",
+        ],
+        indexes: &[
+            (
+                "fee_history_pkey",
+                "ALTER TABLE fee_history ADD PRIMARY KEY (id)",
+            ),
+            (
+                "fee_history_block_hash_idx",
+                "CREATE INDEX fee_history_block_hash_idx ON fee_history (currency_address, block_hash)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "work_snapshots",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "block_hash",
+            "identity_address",
+            "forfeited",
+            "shares",
+            "fee",
+        ],
+        indexes: &[(
+            "work_snapshots_pkey",
+            "ALTER TABLE work_snapshots ADD PRIMARY KEY (currency_address, block_hash, identity_address, forfeited)",
+        )],
+    },
+    ExpectedTable {
+        name: "api_keys",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "identity_address",
+            "key_hash",
+            "label",
+            "last_used_at",
+            "revoked_at",
+        ],
+        indexes: &[
+            ("api_keys_pkey", "ALTER TABLE api_keys ADD PRIMARY KEY (id)"),
+            (
+                "api_keys_key_hash_key",
+                "ALTER TABLE api_keys ADD UNIQUE (key_hash)",
+            ),
+            (
+                "api_keys_identity_idx",
+                "CREATE INDEX api_keys_identity_idx ON api_keys (currency_address, identity_address)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "staker_webhooks",
+        financial: false,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "endpoint",
+            "events",
+            "secret",
+        ],
+        indexes: &[(
+            "staker_webhooks_pkey",
+            "ALTER TABLE staker_webhooks ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "stale_events",
+        financial: false,
+        columns: &[
+            "id",
+            "currency_address",
+            "block_hash",
+            "block_height",
+            "found_by",
+            "identity_address",
+            "shares",
+        ],
+        indexes: &[
+            ("stale_events_pkey", "ALTER TABLE stale_events ADD PRIMARY KEY (id)"),
+            (
+                "stale_events_currency_address_block_hash_identity_address_key",
+                "ALTER TABLE stale_events ADD UNIQUE (currency_address, block_hash, identity_address)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "payment_journal",
+        financial: true,
+        columns: &["id", "currency_address", "opid", "status", "txid"],
+        indexes: &[
+            (
+                "payment_journal_pkey",
+                "ALTER TABLE payment_journal ADD PRIMARY KEY (id)",
+            ),
+            (
+                "payment_journal_status_idx",
+                "CREATE INDEX payment_journal_status_idx ON payment_journal (currency_address, status)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "payment_journal_members",
+        financial: true,
+        columns: &["payment_journal_id", "identity_address", "block_hash", "reward"],
+        indexes: &[(
+            "payment_journal_members_pkey",
+            "ALTER TABLE payment_journal_members ADD PRIMARY KEY (payment_journal_id, identity_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "payment_journal_items",
+        financial: true,
+        columns: &[
+            "payment_journal_id",
+            "identity_address",
+            "destination",
+            "amount",
+            "convert_to",
+        ],
+        indexes: &[(
+            "payment_journal_items_pkey",
+            "ALTER TABLE payment_journal_items ADD PRIMARY KEY (payment_journal_id, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "payout_currencies",
+        financial: true,
+        columns: &["currency_address", "identity_address", "payout_currency"],
+        indexes: &[(
+            "payout_currencies_pkey",
+            "ALTER TABLE payout_currencies ADD PRIMARY KEY (currency_address, identity_address)",
+        )],
+    },
+    ExpectedTable {
+        name: "balance_spans",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "from_height",
+            "to_height",
+            "balance",
+        ],
+        indexes: &[
+            (
+                "balance_spans_pkey",
+                "ALTER TABLE balance_spans ADD PRIMARY KEY (currency_address, identity_address, from_height)",
+            ),
+            (
+                "balance_spans_to_height_idx",
+                "CREATE INDEX balance_spans_to_height_idx ON balance_spans (currency_address, to_height)",
+            ),
+        ],
+    },
+    ExpectedTable {
+        name: "forfeited_rewards",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "block_hash",
+            "block_height",
+            "shares",
+            "reward",
+            "fee",
+        ],
+        indexes: &[(
+            "forfeited_rewards_pkey",
+            "ALTER TABLE forfeited_rewards ADD PRIMARY KEY (currency_address, identity_address, block_hash)",
+        )],
+    },
+    ExpectedTable {
+        name: "payout_member_archive",
+        financial: true,
+        columns: &[
+            "currency_address",
+            "identity_address",
+            "month",
+            "n_rounds",
+            "shares",
+            "reward",
+            "fee",
+            "first_block_height",
+            "last_block_height",
+        ],
+        indexes: &[(
+            "payout_member_archive_pkey",
+            "ALTER TABLE payout_member_archive ADD PRIMARY KEY (currency_address, identity_address, month)",
+        )],
+    },
+];
+
+/// A migration as it was recorded by `sqlx migrate run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub success: bool,
+    pub checksum: Vec<u8>,
+}
+
+/// Something that the schema is missing, compared to what the migrations create.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDrift {
+    pub table: &'static str,
+    pub kind: SchemaDriftKind,
+    pub financial: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaDriftKind {
+    MissingTable,
+    MissingColumn(&'static str),
+    MissingIndex {
+        name: &'static str,
+        statement: &'static str,
+    },
+}
+
+impl SchemaDrift {
+    fn remediation(&self) -> String {
+        match &self.kind {
+            SchemaDriftKind::MissingTable => format!(
+                "table `{}` is missing: restore it from a backup",
+                self.table
+            ),
+            SchemaDriftKind::MissingColumn(column) => format!(
+                "column `{}.{column}` is missing: restore it from a backup, the migrations in \
+                 sql/migrations define its type",
+                self.table
+            ),
+            SchemaDriftKind::MissingIndex { name, statement } => {
+                format!("index `{name}` is missing: {statement};")
+            }
+        }
+    }
+}
+
+/// How the database compares to the migrations that this version of the pool ships with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaReport {
+    /// Migrations that `sqlx migrate run` would apply, as `<version> <description>`.
+    pub pending_migrations: Vec<String>,
+    pub failed_migrations: Vec<String>,
+    /// Migrations that were changed after they were applied.
+    pub modified_migrations: Vec<String>,
+    /// Migrations that were applied, but that this version of the pool doesn't know about.
+    pub unknown_migrations: Vec<String>,
+    pub drift: Vec<SchemaDrift>,
+}
+
+impl SchemaReport {
+    /// Drift in the tables that hold work, rewards or payments.
+    pub fn has_financial_drift(&self) -> bool {
+        self.drift.iter().any(|drift| drift.financial)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The steps that bring the database in line with the migrations, if there are any.
+    pub fn remediation_plan(&self) -> Option<String> {
+        if self.is_clean() {
+            return None;
+        }
+
+        let mut steps = vec![];
+
+        for migration in &self.failed_migrations {
+            steps.push(format!(
+                "migration {migration} failed halfway: fix the database by hand and remove its \
+                 row from _sqlx_migrations before running it again"
+            ));
+        }
+
+        for migration in &self.modified_migrations {
+            steps.push(format!(
+                "migration {migration} was changed after it was applied: restore its original file"
+            ));
+        }
+
+        for migration in &self.unknown_migrations {
+            steps.push(format!(
+                "migration {migration} is applied but unknown to this version: upgrade the pool"
+            ));
+        }
+
+        if !self.pending_migrations.is_empty() {
+            steps.push(format!(
+                "run `cargo sqlx migrate run --source=pool/sql/migrations` to apply:\n{}",
+                self.pending_migrations
+                    .iter()
+                    .map(|migration| format!("   - {migration}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+
+        for drift in &self.drift {
+            steps.push(format!(
+                "{}{}",
+                if drift.financial { "[financial] " } else { "" },
+                drift.remediation()
+            ));
+        }
+
+        Some(
+            steps
+                .iter()
+                .enumerate()
+                .map(|(i, step)| format!("{}. {step}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+/// Compares the applied migrations with [`MIGRATOR`] and the tables, columns and indexes in the
+/// database with what the migrations create, without changing anything.
+pub async fn check_schema(pool: &PgPool) -> Result<SchemaReport> {
+    let has_migrations_table: bool =
+        sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+
+    let applied = if has_migrations_table {
+        sqlx::query(
+            "SELECT version, description, success, checksum
+            FROM _sqlx_migrations
+            ORDER BY version",
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| AppliedMigration {
+            version: row.get("version"),
+            description: row.get("description"),
+            success: row.get("success"),
+            checksum: row.get("checksum"),
+        })
+        .collect()
+    } else {
+        vec![]
+    };
+
+    let mut columns: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in sqlx::query(
+        "SELECT table_name::TEXT, column_name::TEXT
+        FROM information_schema.columns
+        WHERE table_schema = current_schema()",
+    )
+    .fetch_all(pool)
+    .await?
+    {
+        columns
+            .entry(row.get("table_name"))
+            .or_default()
+            .insert(row.get("column_name"));
+    }
+
+    let indexes = sqlx::query_scalar::<_, String>(
+        "SELECT indexname::TEXT FROM pg_indexes WHERE schemaname = current_schema()",
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    Ok(compare_schema(&applied, &columns, &indexes))
+}
+
+fn compare_schema(
+    applied: &[AppliedMigration],
+    columns: &HashMap<String, HashSet<String>>,
+    indexes: &HashSet<String>,
+) -> SchemaReport {
+    let mut report = SchemaReport::default();
+
+    let known = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| (migration.version, migration))
+        .collect::<HashMap<_, _>>();
+    let applied_versions = applied
+        .iter()
+        .map(|migration| migration.version)
+        .collect::<HashSet<_>>();
+
+    for migration in applied {
+        let name = format!("{} {}", migration.version, migration.description);
+
+        match known.get(&migration.version) {
+            None => report.unknown_migrations.push(name),
+            Some(_) if !migration.success => report.failed_migrations.push(name),
+            Some(known) if *known.checksum != *migration.checksum => {
+                report.modified_migrations.push(name)
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut pending = known
+        .values()
+        .filter(|migration| !applied_versions.contains(&migration.version))
+        .map(|migration| (migration.version, migration.description.to_string()))
+        .collect::<Vec<_>>();
+    pending.sort();
+    report.pending_migrations = pending
+        .into_iter()
+        .map(|(version, description)| format!("{version} {description}"))
+        .collect();
+
+    for table in EXPECTED_SCHEMA {
+        let Some(existing) = columns.get(table.name) else {
+            report.drift.push(SchemaDrift {
+                table: table.name,
+                kind: SchemaDriftKind::MissingTable,
+                financial: table.financial,
+            });
+
+            continue;
+        };
+
+        for column in table.columns {
+            if !existing.contains(*column) {
+                report.drift.push(SchemaDrift {
+                    table: table.name,
+                    kind: SchemaDriftKind::MissingColumn(column),
+                    financial: table.financial,
+                });
+            }
+        }
+
+        for (name, statement) in table.indexes {
+            if !indexes.contains(*name) {
+                report.drift.push(SchemaDrift {
+                    table: table.name,
+                    kind: SchemaDriftKind::MissingIndex { name, statement },
+                    financial: table.financial,
+                });
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_migrated_schema_is_clean(pool: PgPool) {
+        let report = check_schema(&pool).await.unwrap();
+
+        assert_eq!(report, SchemaReport::default());
+        assert_eq!(report.remediation_plan(), None);
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_schema_drift_is_detected(pool: PgPool) {
+        sqlx::query("DROP INDEX staker_events_identity_idx")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let report = check_schema(&pool).await.unwrap();
+        assert!(!report.has_financial_drift());
+        assert_eq!(
+            report.remediation_plan().unwrap(),
+            "1. index `staker_events_identity_idx` is missing: CREATE INDEX \
+             staker_events_identity_idx ON staker_events (currency_address, identity_address);"
+        );
+
+        sqlx::query("ALTER TABLE payments DROP COLUMN confirmations")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = 20241116210000")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let report = check_schema(&pool).await.unwrap();
+        assert!(report.has_financial_drift());
+        assert_eq!(
+            report.pending_migrations,
+            vec!["20241116210000 block summaries".to_string()]
+        );
+        assert!(report.drift.contains(&SchemaDrift {
+            table: "payments",
+            kind: SchemaDriftKind::MissingColumn("confirmations"),
+            financial: true,
+        }));
+    }
+}
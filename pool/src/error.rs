@@ -0,0 +1,73 @@
+use std::fmt::Display;
+
+use poollib::api::ErrorCode;
+use vrsc_rpc::json::vrsc::Address;
+
+/// The errors that the API reports with their own code.
+///
+/// They travel as an `anyhow::Error` like any other error, and are found again by downcasting,
+/// so that a coinstaker can fail with them without knowing about the HTTP layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolError {
+    CurrencyNotFound,
+    StakerNotFound(Address),
+    /// None of the daemons of the chain answers.
+    DaemonUnavailable {
+        daemons: usize,
+    },
+    /// The coinstaker is still catching up with the chain.
+    ChainSyncing,
+}
+
+impl PoolError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PoolError::CurrencyNotFound => ErrorCode::CurrencyNotFound,
+            PoolError::StakerNotFound(_) => ErrorCode::StakerNotFound,
+            PoolError::DaemonUnavailable { .. } => ErrorCode::DaemonUnavailable,
+            PoolError::ChainSyncing => ErrorCode::ChainSyncing,
+        }
+    }
+
+    /// Returns the first `PoolError` in the chain of an error.
+    pub fn find(error: &anyhow::Error) -> Option<&Self> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Self>())
+    }
+}
+
+impl Display for PoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::CurrencyNotFound => write!(f, "the pool doesn't stake this currency"),
+            PoolError::StakerNotFound(identity_address) => {
+                write!(f, "{identity_address} is not a staker")
+            }
+            PoolError::DaemonUnavailable { daemons } => {
+                write!(
+                    f,
+                    "none of the {daemons} daemons of this chain is reachable"
+                )
+            }
+            PoolError::ChainSyncing => write!(f, "the pool is catching up with the chain"),
+        }
+    }
+}
+
+impl std::error::Error for PoolError {}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn is_found_behind_context() {
+        let error = Err::<(), _>(PoolError::ChainSyncing)
+            .context("could not merge the rounds")
+            .unwrap_err();
+
+        assert_eq!(PoolError::find(&error), Some(&PoolError::ChainSyncing));
+        assert_eq!(PoolError::find(&anyhow::anyhow!("something else")), None);
+    }
+}
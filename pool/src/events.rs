@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::trace;
+use vrsc_rpc::{
+    bitcoin::{BlockHash, Txid},
+    json::vrsc::{Address, Amount},
+};
+
+use crate::{
+    coinstaker::{
+        constants::{Stake, Staker, StakerStatement},
+        summary::BlockSummary,
+    },
+    payout_service::{Payment, PaymentItem},
+};
+
+/// The number of events a subscriber can fall behind before it misses events.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+/// Something that happened in the pool of a single currency.
+///
+/// Events are published by the coinstaker and the payout service. Consumers such as webhooks
+/// subscribe to the [`EventBus`] of a currency, so the publishers don't need to know about them.
+#[derive(Debug, Clone)]
+pub enum PoolEvent {
+    /// The pool started to process historical blocks.
+    CatchUpStarted,
+    /// The pool caught up with the chain.
+    CatchUpFinished {
+        from_height: u64,
+        to_height: u64,
+    },
+    /// A block was processed.
+    BlockProcessed(BlockSummary),
+    StakeFound {
+        currency_name: String,
+        stake: Stake,
+    },
+    StakeMatured(Stake),
+    StakeStale(Stake),
+    /// The wallet of the daemon disagrees with the pool about the coinbase of a matured stake.
+    StakeWalletMismatch {
+        stake: Stake,
+        reason: String,
+    },
+    NewStaker(Staker),
+    LeavingStaker(Staker),
+    /// The final payout of a staker that became inactive is due at `settle_at`, a unix
+    /// timestamp in seconds.
+    SettlementScheduled {
+        staker: Staker,
+        settle_at: u64,
+    },
+    /// A staker staked a block of which the coinbase was spent by StakeGuard, and was banned.
+    StakerBanned {
+        staker: Staker,
+        stake: Stake,
+        spend_txid: Txid,
+        /// The rewards of the staker that were not paid yet and were taken.
+        forfeited: Amount,
+    },
+    /// A staker was cooling down for too long and is no longer checked with every block.
+    ExpiredStaker(Staker),
+    /// An inactive staker is owed nothing anymore and got its final statement.
+    StakerDeparted(StakerStatement),
+    /// A staker still needs to add the new pool primary address to its VerusID.
+    PrimaryAddressRotation {
+        staker: Staker,
+        new_address: Address,
+        ends_at: u64,
+    },
+    /// A payment to a batch of stakers was sent.
+    PaymentSent {
+        txid: Txid,
+        n_stakers: u64,
+        amount: Amount,
+        /// What every staker was paid.
+        items: Vec<PaymentItem>,
+    },
+    PaymentFailed {
+        payment: Payment,
+        confirmations: i64,
+    },
+    /// Blocks that were processed are no longer in the chain. The stakes in them became stale.
+    ChainReorganized {
+        /// The lowest height of which the block was replaced.
+        fork_height: u64,
+        /// The number of processed blocks that were replaced.
+        depth: u64,
+        orphaned_stakes: Vec<BlockHash>,
+    },
+    /// The chain tip didn't advance for too long. Staking is disabled and payouts are paused.
+    ChainHalted {
+        height: u64,
+        stalled_for_secs: u64,
+    },
+    /// The chain tip advanced again after a halt.
+    ChainResumed {
+        height: u64,
+    },
+    /// No daemon of the chain answers. Blocks are caught up with once one answers again.
+    DaemonUnreachable {
+        error: String,
+    },
+    /// A daemon answers again after none did.
+    DaemonReachable {
+        unreachable_for_secs: u64,
+    },
+    /// The daemon stopped staking. No work is added until it stakes again.
+    DaemonStakingStopped {
+        height: u64,
+    },
+    /// The daemon stakes again after it stopped.
+    DaemonStakingResumed {
+        height: u64,
+        stopped_for_secs: u64,
+    },
+}
+
+/// Broadcasts the [`PoolEvent`]s of a currency to every subscriber.
+///
+/// Clones publish to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<PoolEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: PoolEvent) {
+        if let Err(e) = self.tx.send(event) {
+            trace!(event = ?e.0, "no subscribers for event");
+        }
+    }
+
+    /// Returns a receiver for the events that are published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<PoolEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Waits at most `timeout` for the first event published from now on for which `select`
+    /// returns a value.
+    pub async fn next<T>(
+        &self,
+        timeout: Duration,
+        select: impl Fn(PoolEvent) -> Option<T>,
+    ) -> Option<T> {
+        let mut rx = self.subscribe();
+
+        let wait = async {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if let Some(value) = select(event) {
+                            return Some(value);
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, wait).await.ok().flatten()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn subscribers_receive_events_in_order() {
+        let bus = EventBus::new();
+
+        bus.publish(PoolEvent::CatchUpStarted);
+
+        let mut first = bus.subscribe();
+        let mut second = bus.clone().subscribe();
+
+        bus.publish(PoolEvent::CatchUpStarted);
+        bus.clone().publish(PoolEvent::CatchUpFinished {
+            from_height: 10,
+            to_height: 20,
+        });
+
+        for rx in [&mut first, &mut second] {
+            assert!(matches!(rx.recv().await, Ok(PoolEvent::CatchUpStarted)));
+            assert!(matches!(
+                rx.recv().await,
+                Ok(PoolEvent::CatchUpFinished {
+                    from_height: 10,
+                    to_height: 20
+                })
+            ));
+            assert!(rx.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn next_waits_for_a_selected_event() {
+        let bus = EventBus::new();
+        let select = |event| match event {
+            PoolEvent::CatchUpFinished { to_height, .. } => Some(to_height),
+            _ => None,
+        };
+
+        assert_eq!(bus.next(Duration::from_millis(10), select).await, None);
+
+        let publisher = bus.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            publisher.publish(PoolEvent::CatchUpStarted);
+            publisher.publish(PoolEvent::CatchUpFinished {
+                from_height: 10,
+                to_height: 20,
+            });
+        });
+
+        assert_eq!(bus.next(Duration::from_secs(5), select).await, Some(20));
+    }
+}
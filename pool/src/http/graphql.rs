@@ -0,0 +1,754 @@
+//! The GraphQL API over the data of the pool, for dashboards that need other shapes than the
+//! REST handlers return.
+//!
+//! Every list takes `first` and `offset` for pagination: at most 1000 (100 by default) at the
+//! root of a query, and at most 20 (10 by default) for the lists of an object. Queries that would
+//! return too much are rejected before they run. Amounts are in satoshis and statuses are the
+//! snake_case names of the REST API.
+
+use std::{collections::HashMap, fmt::Display, hash::Hash, str::FromStr, sync::Arc};
+
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    Context, EmptyMutation, EmptySubscription, Object, Result, Schema,
+};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::PgPool;
+use vrsc_rpc::{bitcoin::BlockHash, json::vrsc::Address};
+
+use crate::{
+    coinstaker::constants::{Round, Stake, Staker},
+    database::{self, HeightFilter, Page, PayoutMemberFilter, StakeFilter, StakerFilter},
+    payout_service::{PayoutMember, PayoutSummary},
+};
+
+const DEFAULT_PAGE_SIZE: u64 = 100;
+const MAX_PAGE_SIZE: u64 = 1000;
+/// The page size of the lists of an object, which run a query for every object in the list
+/// around them.
+const DEFAULT_NESTED_PAGE_SIZE: u64 = 10;
+const MAX_NESTED_PAGE_SIZE: u64 = 20;
+/// Limits how deep the nested lists of a query go, as every level queries the database again.
+const MAX_DEPTH: usize = 6;
+/// Limits the fields that a query returns, in which a list counts as `first` times its fields.
+const MAX_COMPLEXITY: usize = 10_000;
+
+pub type PoolSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema(pool: PgPool) -> PoolSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(DataLoader::new(PoolLoader(pool.clone()), tokio::spawn))
+        .data(pool)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+fn page(first: Option<u64>, offset: Option<u64>) -> Page {
+    Page {
+        limit: first.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE),
+        offset: offset.unwrap_or_default(),
+    }
+}
+
+fn nested_page(first: Option<u64>, offset: Option<u64>) -> Page {
+    Page {
+        limit: first
+            .unwrap_or(DEFAULT_NESTED_PAGE_SIZE)
+            .min(MAX_NESTED_PAGE_SIZE),
+        offset: offset.unwrap_or_default(),
+    }
+}
+
+/// The complexity of a list of `page`, of which every item counts as `child_complexity`.
+fn complexity(page: Page, child_complexity: usize) -> usize {
+    (page.limit as usize).saturating_mul(child_complexity)
+}
+
+/// Loads the stakers and stakes that the objects of a query refer to in a query per currency,
+/// instead of a query per object.
+pub struct PoolLoader(PgPool);
+
+impl Loader<(Address, Address)> for PoolLoader {
+    type Value = Staker;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[(Address, Address)],
+    ) -> std::result::Result<HashMap<(Address, Address), Staker>, Self::Error> {
+        let mut stakers = HashMap::new();
+
+        for (currency_address, identity_addresses) in by_currency(keys) {
+            let page = Page {
+                limit: identity_addresses.len() as u64,
+                offset: 0,
+            };
+            let filter = StakerFilter {
+                identity_addresses: Some(identity_addresses),
+                ..Default::default()
+            };
+
+            for staker in database::find_stakers(&self.0, &currency_address, &filter, page).await? {
+                stakers.insert(
+                    (currency_address.clone(), staker.identity_address.clone()),
+                    staker,
+                );
+            }
+        }
+
+        Ok(stakers)
+    }
+}
+
+impl Loader<(Address, BlockHash)> for PoolLoader {
+    type Value = Stake;
+    type Error = Arc<anyhow::Error>;
+
+    async fn load(
+        &self,
+        keys: &[(Address, BlockHash)],
+    ) -> std::result::Result<HashMap<(Address, BlockHash), Stake>, Self::Error> {
+        let mut stakes = HashMap::new();
+
+        for (currency_address, block_hashes) in by_currency(keys) {
+            for stake in
+                database::get_stakes_by_block_hashes(&self.0, &currency_address, &block_hashes)
+                    .await?
+            {
+                stakes.insert((currency_address.clone(), stake.block_hash), stake);
+            }
+        }
+
+        Ok(stakes)
+    }
+}
+
+fn by_currency<K: Clone + Eq + Hash>(keys: &[(Address, K)]) -> HashMap<Address, Vec<K>> {
+    keys.iter()
+        .fold(HashMap::new(), |mut acc, (currency_address, key)| {
+            acc.entry(currency_address.clone())
+                .or_insert_with(Vec::new)
+                .push(key.clone());
+
+            acc
+        })
+}
+
+fn parse<T>(name: &str, value: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(value).map_err(|e| format!("invalid {name} `{value}`: {e}").into())
+}
+
+fn parse_status<T: DeserializeOwned>(value: &str) -> Result<T> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown status `{value}`").into())
+}
+
+fn status_name<T: Serialize>(status: &T) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The stakers of a currency, ordered by identity address.
+    #[graphql(complexity = "complexity(page(first, offset), child_complexity)")]
+    async fn stakers(
+        &self,
+        ctx: &Context<'_>,
+        currency: String,
+        status: Option<String>,
+        identity_addresses: Option<Vec<String>>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<StakerObject>> {
+        let filter = StakerFilter {
+            status: status.as_deref().map(parse_status).transpose()?,
+            identity_addresses: identity_addresses
+                .map(|addresses| {
+                    addresses
+                        .iter()
+                        .map(|address| parse("identity address", address))
+                        .collect::<Result<Vec<Address>>>()
+                })
+                .transpose()?,
+        };
+
+        let stakers = database::find_stakers(
+            ctx.data::<PgPool>()?,
+            &parse("currency", &currency)?,
+            &filter,
+            page(first, offset),
+        )
+        .await?;
+
+        Ok(stakers.into_iter().map(StakerObject).collect())
+    }
+
+    /// The stakes of a currency, newest first.
+    #[graphql(complexity = "complexity(page(first, offset), child_complexity)")]
+    #[allow(clippy::too_many_arguments)]
+    async fn stakes(
+        &self,
+        ctx: &Context<'_>,
+        currency: String,
+        status: Option<String>,
+        found_by: Option<String>,
+        from_height: Option<u64>,
+        to_height: Option<u64>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<StakeObject>> {
+        let filter = StakeFilter {
+            status: status.as_deref().map(parse_status).transpose()?,
+            found_by: found_by
+                .as_deref()
+                .map(|found_by| parse("identity address", found_by))
+                .transpose()?,
+            from_height,
+            to_height,
+        };
+
+        let stakes = database::find_stakes(
+            ctx.data::<PgPool>()?,
+            &parse("currency", &currency)?,
+            &filter,
+            page(first, offset),
+        )
+        .await?;
+
+        Ok(stakes.into_iter().map(StakeObject).collect())
+    }
+
+    /// The payouts of the stakes of a currency, newest first.
+    #[graphql(complexity = "complexity(page(first, offset), child_complexity)")]
+    async fn payouts(
+        &self,
+        ctx: &Context<'_>,
+        currency: String,
+        from_height: Option<u64>,
+        to_height: Option<u64>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutObject>> {
+        let payouts = database::find_payouts(
+            ctx.data::<PgPool>()?,
+            &parse("currency", &currency)?,
+            HeightFilter {
+                from_height,
+                to_height,
+            },
+            page(first, offset),
+        )
+        .await?;
+
+        Ok(payouts.into_iter().map(PayoutObject).collect())
+    }
+
+    /// The rewards of the stakers of a currency, newest first.
+    #[graphql(complexity = "complexity(page(first, offset), child_complexity)")]
+    #[allow(clippy::too_many_arguments)]
+    async fn payout_members(
+        &self,
+        ctx: &Context<'_>,
+        currency: String,
+        identity_address: Option<String>,
+        block_hash: Option<String>,
+        paid: Option<bool>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutMemberObject>> {
+        let filter = PayoutMemberFilter {
+            identity_address: identity_address
+                .as_deref()
+                .map(|address| parse("identity address", address))
+                .transpose()?,
+            block_hash: block_hash
+                .as_deref()
+                .map(|block_hash| parse("block hash", block_hash))
+                .transpose()?,
+            paid,
+        };
+
+        let members = database::find_payout_members(
+            ctx.data::<PgPool>()?,
+            &parse("currency", &currency)?,
+            &filter,
+            page(first, offset),
+        )
+        .await?;
+
+        Ok(members.into_iter().map(PayoutMemberObject).collect())
+    }
+
+    /// The rounds of a currency, newest first.
+    #[graphql(complexity = "complexity(page(first, offset), child_complexity)")]
+    async fn rounds(
+        &self,
+        ctx: &Context<'_>,
+        currency: String,
+        from_height: Option<u64>,
+        to_height: Option<u64>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<RoundObject>> {
+        let rounds = database::find_rounds(
+            ctx.data::<PgPool>()?,
+            &parse("currency", &currency)?,
+            HeightFilter {
+                from_height,
+                to_height,
+            },
+            page(first, offset),
+        )
+        .await?;
+
+        Ok(rounds.into_iter().map(RoundObject).collect())
+    }
+}
+
+/// Loads the stake that was found in the block of `block_hash`.
+async fn stake_at(
+    ctx: &Context<'_>,
+    currency_address: &Address,
+    block_hash: &BlockHash,
+) -> Result<Option<StakeObject>> {
+    let stake = ctx
+        .data::<DataLoader<PoolLoader>>()?
+        .load_one((currency_address.clone(), *block_hash))
+        .await?;
+
+    Ok(stake.map(StakeObject))
+}
+
+async fn members_of(
+    ctx: &Context<'_>,
+    currency_address: &Address,
+    filter: PayoutMemberFilter,
+    page: Page,
+) -> Result<Vec<PayoutMemberObject>> {
+    let members =
+        database::find_payout_members(ctx.data::<PgPool>()?, currency_address, &filter, page)
+            .await?;
+
+    Ok(members.into_iter().map(PayoutMemberObject).collect())
+}
+
+pub struct StakerObject(Staker);
+
+#[Object(name = "Staker")]
+impl StakerObject {
+    async fn currency_address(&self) -> String {
+        self.0.currency_address.to_string()
+    }
+
+    async fn identity_address(&self) -> String {
+        self.0.identity_address.to_string()
+    }
+
+    async fn identity_name(&self) -> &str {
+        &self.0.identity_name
+    }
+
+    async fn min_payout(&self) -> u64 {
+        self.0.min_payout.as_sat()
+    }
+
+    async fn status(&self) -> String {
+        status_name(&self.0.status)
+    }
+
+    async fn fee(&self) -> Decimal {
+        self.0.fee
+    }
+
+    async fn participation_kind(&self) -> String {
+        status_name(&self.0.participation_kind)
+    }
+
+    /// The address that the rewards of a cold staker are paid to.
+    async fn payout_address(&self) -> Option<String> {
+        self.0
+            .payout_address
+            .as_ref()
+            .map(|address| address.to_string())
+    }
+
+    async fn created_at(&self) -> u64 {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> u64 {
+        self.0.updated_at
+    }
+
+    /// The stakes that the staker found, newest first.
+    #[graphql(complexity = "complexity(nested_page(first, offset), child_complexity)")]
+    async fn stakes(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<StakeObject>> {
+        let filter = StakeFilter {
+            found_by: Some(self.0.identity_address.clone()),
+            ..Default::default()
+        };
+
+        let stakes = database::find_stakes(
+            ctx.data::<PgPool>()?,
+            &self.0.currency_address,
+            &filter,
+            nested_page(first, offset),
+        )
+        .await?;
+
+        Ok(stakes.into_iter().map(StakeObject).collect())
+    }
+
+    /// The rewards of the staker, newest first.
+    #[graphql(complexity = "complexity(nested_page(first, offset), child_complexity)")]
+    async fn payout_members(
+        &self,
+        ctx: &Context<'_>,
+        paid: Option<bool>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutMemberObject>> {
+        members_of(
+            ctx,
+            &self.0.currency_address,
+            PayoutMemberFilter {
+                identity_address: Some(self.0.identity_address.clone()),
+                paid,
+                ..Default::default()
+            },
+            nested_page(first, offset),
+        )
+        .await
+    }
+}
+
+pub struct StakeObject(Stake);
+
+#[Object(name = "Stake")]
+impl StakeObject {
+    async fn currency_address(&self) -> String {
+        self.0.currency_address.to_string()
+    }
+
+    async fn block_hash(&self) -> String {
+        self.0.block_hash.to_string()
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.block_height
+    }
+
+    async fn found_by(&self) -> String {
+        self.0.found_by.to_string()
+    }
+
+    async fn source_txid(&self) -> String {
+        self.0.source_txid.to_string()
+    }
+
+    async fn source_vout_num(&self) -> u16 {
+        self.0.source_vout_num
+    }
+
+    async fn source_amount(&self) -> u64 {
+        self.0.source_amount.as_sat()
+    }
+
+    async fn status(&self) -> String {
+        status_name(&self.0.status)
+    }
+
+    async fn amount(&self) -> u64 {
+        self.0.amount.as_sat()
+    }
+
+    async fn created_at(&self) -> u64 {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> u64 {
+        self.0.updated_at
+    }
+
+    /// The rewards of the stake, by staker.
+    #[graphql(complexity = "complexity(nested_page(first, offset), child_complexity)")]
+    async fn payout_members(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutMemberObject>> {
+        members_of(
+            ctx,
+            &self.0.currency_address,
+            PayoutMemberFilter {
+                block_hash: Some(self.0.block_hash),
+                ..Default::default()
+            },
+            nested_page(first, offset),
+        )
+        .await
+    }
+}
+
+pub struct PayoutObject(PayoutSummary);
+
+#[Object(name = "Payout")]
+impl PayoutObject {
+    async fn currency_address(&self) -> String {
+        self.0.currency_address.to_string()
+    }
+
+    async fn block_hash(&self) -> String {
+        self.0.block_hash.to_string()
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.block_height
+    }
+
+    async fn amount(&self) -> u64 {
+        self.0.amount.as_sat()
+    }
+
+    async fn work(&self) -> Decimal {
+        self.0.work
+    }
+
+    async fn fee(&self) -> u64 {
+        self.0.fee.as_sat()
+    }
+
+    async fn amount_paid(&self) -> u64 {
+        self.0.amount_paid.as_sat()
+    }
+
+    async fn n_members(&self) -> u64 {
+        self.0.n_members
+    }
+
+    async fn created_at(&self) -> u64 {
+        self.0.created_at
+    }
+
+    async fn stake(&self, ctx: &Context<'_>) -> Result<Option<StakeObject>> {
+        stake_at(ctx, &self.0.currency_address, &self.0.block_hash).await
+    }
+
+    #[graphql(complexity = "complexity(nested_page(first, offset), child_complexity)")]
+    async fn members(
+        &self,
+        ctx: &Context<'_>,
+        paid: Option<bool>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutMemberObject>> {
+        members_of(
+            ctx,
+            &self.0.currency_address,
+            PayoutMemberFilter {
+                block_hash: Some(self.0.block_hash),
+                paid,
+                ..Default::default()
+            },
+            nested_page(first, offset),
+        )
+        .await
+    }
+}
+
+pub struct PayoutMemberObject(PayoutMember);
+
+#[Object(name = "PayoutMember")]
+impl PayoutMemberObject {
+    async fn currency_address(&self) -> String {
+        self.0.currency_address.to_string()
+    }
+
+    async fn block_hash(&self) -> String {
+        self.0.block_hash.to_string()
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.block_height
+    }
+
+    async fn identity_address(&self) -> String {
+        self.0.identity_address.to_string()
+    }
+
+    async fn reward(&self) -> u64 {
+        self.0.reward.as_sat()
+    }
+
+    async fn shares(&self) -> Decimal {
+        self.0.shares
+    }
+
+    async fn fee(&self) -> u64 {
+        self.0.fee.as_sat()
+    }
+
+    /// The payment that paid the reward, if it was paid.
+    async fn txid(&self) -> Option<String> {
+        self.0.txid.map(|txid| txid.to_string())
+    }
+
+    async fn created_at(&self) -> u64 {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> u64 {
+        self.0.updated_at
+    }
+
+    async fn staker(&self, ctx: &Context<'_>) -> Result<Option<StakerObject>> {
+        let staker = ctx
+            .data::<DataLoader<PoolLoader>>()?
+            .load_one((
+                self.0.currency_address.clone(),
+                self.0.identity_address.clone(),
+            ))
+            .await?;
+
+        Ok(staker.map(StakerObject))
+    }
+
+    async fn stake(&self, ctx: &Context<'_>) -> Result<Option<StakeObject>> {
+        stake_at(ctx, &self.0.currency_address, &self.0.block_hash).await
+    }
+}
+
+pub struct RoundObject(Round);
+
+#[Object(name = "Round")]
+impl RoundObject {
+    async fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    async fn currency_address(&self) -> String {
+        self.0.currency_address.to_string()
+    }
+
+    /// The stake that closed the round, if it is known.
+    async fn block_hash(&self) -> Option<String> {
+        self.0.block_hash.map(|block_hash| block_hash.to_string())
+    }
+
+    async fn block_height(&self) -> u64 {
+        self.0.block_height
+    }
+
+    async fn stake(&self, ctx: &Context<'_>) -> Result<Option<StakeObject>> {
+        let Some(block_hash) = &self.0.block_hash else {
+            return Ok(None);
+        };
+
+        stake_at(ctx, &self.0.currency_address, block_hash).await
+    }
+
+    /// The rewards of the round, once its stake is paid out.
+    #[graphql(complexity = "complexity(nested_page(first, offset), child_complexity)")]
+    async fn payout_members(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<Vec<PayoutMemberObject>> {
+        let Some(block_hash) = self.0.block_hash else {
+            return Ok(vec![]);
+        };
+
+        members_of(
+            ctx,
+            &self.0.currency_address,
+            PayoutMemberFilter {
+                block_hash: Some(block_hash),
+                ..Default::default()
+            },
+            nested_page(first, offset),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn queries_are_validated(pool: PgPool) {
+        let schema = schema(pool);
+
+        let response = schema
+            .execute(
+                r#"{
+                    stakers(currency: "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", status: "active") {
+                        identityAddress
+                        payoutMembers(paid: false) { reward }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({ "stakers": [] })
+        );
+
+        let response = schema
+            .execute(r#"{ stakes(currency: "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", status: "lost") { blockHash } }"#)
+            .await;
+        assert_eq!(response.errors[0].message, "unknown status `lost`");
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn large_nested_queries_are_rejected(pool: PgPool) {
+        let schema = schema(pool);
+
+        let response = schema
+            .execute(
+                r#"{
+                    stakers(currency: "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", first: 1000) {
+                        stakes(first: 1000) {
+                            payoutMembers(first: 1000) { reward }
+                        }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(!response.errors.is_empty());
+        assert!(response.data.into_json().unwrap().is_null());
+
+        // the nested lists are counted with their own page size
+        let response = schema
+            .execute(
+                r#"{
+                    stakers(currency: "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", first: 2) {
+                        stakes(first: 1000) {
+                            payoutMembers(first: 1000) { reward staker { identityName } }
+                        }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+}
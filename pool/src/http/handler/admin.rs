@@ -0,0 +1,963 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    Extension,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+use vrsc_rpc::{
+    bitcoin::BlockHash,
+    json::vrsc::{Address, Amount},
+};
+
+use crate::{
+    coinstaker::{
+        coinstaker::CoinStakerMessage,
+        constants::{
+            AuditReport, ChainPause, FeeOverride, FraudEvidence, HistoricalStake, KeyRotation,
+            KeyRotationStatus, PayoutRecalculation, RoundMerge, StakerBan,
+        },
+        http::{DeadLetter, EndpointStatus},
+        Config as CoinstakerConfig,
+    },
+    database,
+    error::PoolError,
+    http::{handler::AppJson, routing::AppState},
+    payout_service::{ManualPayment, PayoutShadowDiff, Reconciliation},
+};
+
+use super::AppError;
+
+/// Returns the delivery statistics of every configured webhook endpoint, per currency.
+///
+/// An endpoint is marked unhealthy after 3 consecutive failed deliveries, and becomes healthy
+/// again after the next successful delivery.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": [
+///         {
+///             "endpoint": "http://localhost:8080/",
+///             "delivered": 120,
+///             "failed": 3,
+///             "success_rate": 0.975609756097561,
+///             "average_latency_ms": 12.4,
+///             "last_latency_ms": 10,
+///             "consecutive_failures": 0,
+///             "healthy": true,
+///             "last_error": "error sending request for url (http://localhost:8080/webhook)"
+///         }
+///     ]
+/// }
+/// ```
+pub async fn webhook_status(
+    State(state): State<AppState>,
+) -> AppJson<HashMap<Address, Vec<EndpointStatus>>> {
+    let status = state
+        .controller
+        .webhooks
+        .all()
+        .into_iter()
+        .map(|(currency, webhook)| (currency, webhook.status()))
+        .collect();
+
+    AppJson(status)
+}
+
+/// Returns the webhook messages that could not be delivered after all retries, per currency.
+///
+/// A failed delivery is retried with an exponential backoff, and dead-lettered after 10
+/// attempts.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": [
+///         {
+///             "id": 512,
+///             "endpoint": "http://localhost:8080/",
+///             "body": "{\"message\":\"stake_matured\",\"data\":\"...\"}",
+///             "attempts": 10,
+///             "last_error": "error sending request for url (http://localhost:8080/webhook)",
+///             "created_at": 1731715200,
+///             "dead_at": 1731760000
+///         }
+///     ]
+/// }
+/// ```
+pub async fn dead_webhooks(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, Vec<DeadLetter>>>, AppError> {
+    let mut dead = HashMap::new();
+
+    for (currency, webhook) in state.controller.webhooks.all() {
+        dead.insert(currency, webhook.dead_letters().await?);
+    }
+
+    Ok(AppJson(dead))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RedriveWebhooksArgs {
+    pub currency_address: Address,
+    /// The ids of the dead-lettered messages to retry. All of them are retried if not set.
+    pub ids: Option<Vec<u64>>,
+}
+
+/// Retries dead-lettered webhook messages as if they were never attempted, and returns the
+/// number of messages that are retried.
+///
+/// ```json
+/// 1
+/// ```
+pub async fn redrive_webhooks(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<RedriveWebhooksArgs>,
+) -> Result<AppJson<u64>, AppError> {
+    let webhook = state
+        .controller
+        .webhooks
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    Ok(AppJson(webhook.redrive(args.ids.as_deref()).await?))
+}
+
+/// Returns the report of the consistency audit that ran when the pool started, per currency.
+///
+/// Currencies of which the audit failed to run are left out.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "created_at": 1731715200,
+///         "findings": [
+///             {
+///                 "category": "pending_stakes",
+///                 "description": "maturing stake at height 513251 has 120 confirmations",
+///                 "suggestion": "mark the stake as matured",
+///                 "repaired": true
+///             }
+///         ]
+///     }
+/// }
+/// ```
+pub async fn startup_audit(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, AuditReport>>, AppError> {
+    let mut reports = HashMap::new();
+
+    for (currency, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<Option<AuditReport>>();
+
+        tx.send(CoinStakerMessage::GetStartupAudit(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        if let Some(report) = os_rx.await.context("Sender dropped")? {
+            reports.insert(currency, report);
+        }
+    }
+
+    Ok(AppJson(reports))
+}
+
+/// Returns the latest reconciliation of the wallet with the recorded payments, per currency.
+///
+/// `unmatched_sends` are transactions that the wallet sent but that the pool has no record of.
+/// `missing_payments` are payments of payout members that are recorded as paid, but that the
+/// wallet doesn't know or that are no longer in the chain. Currencies of which no reconciliation
+/// finished yet are left out.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "created_at": 1731715200,
+///         "wallet_transactions": 10000,
+///         "unmatched_sends": [
+///             {
+///                 "txid": "f7b960c2b3e098926401ee3acafc3e1724fd0bbc180d72440229eb461d44976b",
+///                 "addresses": ["RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7"],
+///                 "amount": 250000000,
+///                 "time": 1731700000
+///             }
+///         ],
+///         "missing_payments": [
+///             {
+///                 "txid": "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+///                 "n_members": 12,
+///                 "amount": 1520000000,
+///                 "reason": "the transaction is unknown to the wallet"
+///             }
+///         ]
+///     }
+/// }
+/// ```
+pub async fn reconciliation(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, Reconciliation>>, AppError> {
+    let reconciliations = state
+        .controller
+        .reconciliations
+        .all()
+        .into_iter()
+        .filter_map(|(currency, latest)| Some((currency, latest.get()?)))
+        .collect();
+
+    Ok(AppJson(reconciliations))
+}
+
+/// Returns the evidence of the stakes that were caught by StakeGuard, per currency, the most
+/// recent first.
+///
+/// The evidence shows that the staker staked a competing block with the same UTXO as the stake
+/// of the pool: `competing_source_txid` and `competing_source_vout_num` match the source of the
+/// stake. They are `null` if the competing stake could not be decoded from the `proof`, which is
+/// the OP_RETURN data of the StakeGuard spend.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": [
+///         {
+///             "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///             "block_hash": "000000000043d3b2b1e4a5bbac4ad1c3b8b4a9b8c0f5cc1b7d3be4b0b6a1f3a2",
+///             "block_height": 513251,
+///             "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "source_txid": "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+///             "source_vout_num": 0,
+///             "spend_txid": "0a6f2b1f0b4e5b7a3fd0e5e1c3a0e2d9d1b0a3c8e7f6d5c4b3a291807f6e5d4c",
+///             "spend_block_hash": "00000000001b7c1d2e3f4a5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d",
+///             "spend_tx": "0400008085202f89...",
+///             "proof": "01000400008085202f89...",
+///             "competing_txid": "9f1e2d3c4b5a69788796a5b4c3d2e1f00f1e2d3c4b5a69788796a5b4c3d2e1f0",
+///             "competing_source_txid": "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+///             "competing_source_vout_num": 0,
+///             "created_at": 1731715200
+///         }
+///     ]
+/// }
+/// ```
+pub async fn fraud_evidence(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, Vec<FraudEvidence>>>, AppError> {
+    let mut evidence = HashMap::new();
+
+    for (currency, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<Vec<FraudEvidence>>();
+
+        tx.send(CoinStakerMessage::GetFraudEvidence(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        evidence.insert(currency, os_rx.await.context("Sender dropped")?);
+    }
+
+    Ok(AppJson(evidence))
+}
+
+/// Returns the configuration that every coinstaker is running with, per currency, with the
+/// secrets redacted.
+///
+/// This is the configuration after defaults were applied, including the enabled features, so
+/// operators don't have to guess what the running process loaded from the files on disk.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": {
+///         "currency_name": "VRSCTEST",
+///         "currency_id": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "fee": "0.05",
+///         "min_payout": 100000000,
+///         "chain_config": {
+///             "rpc_user": "user",
+///             "rpc_password": "<redacted>",
+///             "rpc_host": "127.0.0.1",
+///             "rpc_port": 18843,
+///             "zmq_port_blocknotify": 59790
+///         },
+///         "features": {
+///             "pplns": true
+///         },
+///         ...
+///     }
+/// }
+/// ```
+pub async fn config(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, CoinstakerConfig>>, AppError> {
+    let mut configs = HashMap::new();
+
+    for (currency, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<CoinstakerConfig>();
+
+        tx.send(CoinStakerMessage::GetConfig(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        configs.insert(currency, os_rx.await.context("Sender dropped")?);
+    }
+
+    Ok(AppJson(configs))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MergeRoundsArgs {
+    pub currency_address: Address,
+    pub from_round: u64,
+    pub into_round: u64,
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+/// Merges the work of the round with id `from_round` into the round with id `into_round` and
+/// generates the payout of the stake of that round again, for when work of the same stake ended
+/// up in two rounds. Round ids are listed in the findings of the startup audit.
+///
+/// This is a dry run unless `dry_run` is set to `false` explicitly: the returned changes show
+/// what the merge would do to the work and rewards of every staker. A payout that was already
+/// (partially) paid cannot be changed. Returns a 400 with the reason if the rounds can't be
+/// merged.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "from_round": 1043,
+///     "into_round": 1041,
+///     "dry_run": true,
+///     "payout_regenerated": true,
+///     "changes": [
+///         {
+///             "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "shares_before": "4512.5",
+///             "shares_after": "4630.0",
+///             "reward_before": 120000000,
+///             "reward_after": 118500000
+///         }
+///     ]
+/// }
+/// ```
+pub async fn merge_rounds(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<MergeRoundsArgs>,
+) -> Result<AppJson<RoundMerge>, AppError> {
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<RoundMerge>>();
+
+    tx.send(CoinStakerMessage::MergeRounds(
+        os_tx,
+        args.from_round,
+        args.into_round,
+        args.dry_run,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let merge = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(merge))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PayStakerArgs {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    /// Who requested the payment.
+    pub operator: String,
+    /// Why the payment was requested, for example a support ticket.
+    pub reason: String,
+}
+
+/// Pays the outstanding balance of a single staker right away, regardless of its min_payout
+/// and the payout schedule, for support escalations.
+///
+/// The payment is sent like a regular payment. The operator and reason are recorded together
+/// with the payment. Returns a 400 with the reason if the staker has no outstanding balance or
+/// if the payment could not be sent.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "txid": "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+///     "amount": 42000000,
+///     "n_rounds": 3,
+///     "operator": "alice",
+///     "reason": "ticket 123: leaving the pool"
+/// }
+/// ```
+pub async fn pay_staker(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<PayStakerArgs>,
+) -> Result<AppJson<ManualPayment>, AppError> {
+    if args.operator.trim().is_empty() || args.reason.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "an operator and a reason are required".to_string(),
+        ));
+    }
+
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<ManualPayment>>();
+
+    tx.send(CoinStakerMessage::PayStaker(
+        os_tx,
+        args.identity_address,
+        args.operator,
+        args.reason,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let payment = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(payment))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BanStakerArgs {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    /// Who banned the staker.
+    pub operator: String,
+    /// Why the staker was banned.
+    pub reason: String,
+}
+
+/// Bans a staker, like a staker that was caught by StakeGuard: it forfeits its work in the
+/// current round and the rewards that were not paid yet, and it can't stake in the pool anymore.
+///
+/// Returns a 400 with the reason if the address is not a staker or is already banned.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "forfeited": 42000000,
+///     "operator": "alice",
+///     "reason": "ticket 123: abuse"
+/// }
+/// ```
+pub async fn ban_staker(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<BanStakerArgs>,
+) -> Result<AppJson<StakerBan>, AppError> {
+    if args.operator.trim().is_empty() || args.reason.trim().is_empty() {
+        return Err(AppError::BadRequest(
+            "an operator and a reason are required".to_string(),
+        ));
+    }
+
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<StakerBan>>();
+
+    tx.send(CoinStakerMessage::BanStaker(
+        os_tx,
+        args.identity_address,
+        args.operator,
+        args.reason,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let ban = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(ban))
+}
+
+/// Returns the pool primary address per currency, the rotation to a new address that is going
+/// on and whether every staker added the new address to its VerusID.
+///
+/// ```json
+/// {
+///     "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq": {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "primary_address": "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7",
+///         "new_address": "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK",
+///         "ends_at": 1732320000,
+///         "stakers": [
+///             {
+///                 "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///                 "new_address": "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK",
+///                 "migrated": false,
+///                 "notified": true
+///             }
+///         ]
+///     }
+/// }
+/// ```
+pub async fn key_rotation(
+    State(state): State<AppState>,
+) -> Result<AppJson<HashMap<Address, KeyRotationStatus>>, AppError> {
+    let mut statuses = HashMap::new();
+
+    for (currency, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<KeyRotationStatus>();
+
+        tx.send(CoinStakerMessage::GetKeyRotation(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        statuses.insert(currency, os_rx.await.context("Sender dropped")?);
+    }
+
+    Ok(AppJson(statuses))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StartKeyRotationArgs {
+    pub currency_address: Address,
+    /// The address that replaces the pool primary address.
+    pub new_address: Address,
+    /// Unix timestamp (in seconds) at which the transition window ends.
+    pub ends_at: u64,
+    /// Who started the rotation.
+    pub operator: String,
+}
+
+/// Starts a rotation of the pool primary address to a new address. Until `ends_at`, VerusIDs
+/// that include either address are eligible, and new stakers are given the new address. Every
+/// staker that did not add the new address to its VerusID is notified once with a
+/// `primary_address_rotation` webhook.
+///
+/// Returns a 400 with the reason if a rotation is already going on, if the new address is the
+/// pool primary address already, if the pool wallet doesn't hold the key of the new address or
+/// if `ends_at` is not in the future.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "old_address": "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7",
+///     "new_address": "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK",
+///     "ends_at": 1732320000,
+///     "operator": "alice",
+///     "retired_at": null,
+///     "retired_by": null,
+///     "created_at": 1731715200
+/// }
+/// ```
+pub async fn start_key_rotation(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<StartKeyRotationArgs>,
+) -> Result<AppJson<KeyRotation>, AppError> {
+    if args.operator.trim().is_empty() {
+        return Err(AppError::BadRequest("an operator is required".to_string()));
+    }
+
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<KeyRotation>>();
+
+    tx.send(CoinStakerMessage::StartKeyRotation(
+        os_tx,
+        args.new_address,
+        args.ends_at,
+        args.operator,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let rotation = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(rotation))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RetireKeyRotationArgs {
+    pub currency_address: Address,
+    /// Who retired the old address.
+    pub operator: String,
+}
+
+/// Retires the old pool primary address once the transition window of the rotation has ended:
+/// the new address becomes the pool primary address, and stakers that did not add it to their
+/// VerusID become inactive.
+///
+/// Returns a 400 with the reason if no rotation is going on, if its transition window has not
+/// ended yet or if the rotation is configured instead of started with this endpoint.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "old_address": "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7",
+///     "new_address": "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK",
+///     "ends_at": 1732320000,
+///     "operator": "alice",
+///     "retired_at": 1732406400,
+///     "retired_by": "bob",
+///     "created_at": 1731715200
+/// }
+/// ```
+pub async fn retire_key_rotation(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<RetireKeyRotationArgs>,
+) -> Result<AppJson<KeyRotation>, AppError> {
+    if args.operator.trim().is_empty() {
+        return Err(AppError::BadRequest("an operator is required".to_string()));
+    }
+
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<KeyRotation>>();
+
+    tx.send(CoinStakerMessage::RetireKeyRotation(os_tx, args.operator))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let rotation = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(rotation))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RunPayoutsArgs {
+    pub currency_address: Address,
+}
+
+/// Starts a payout run of a currency right away, instead of at the next payout interval: the
+/// matured stakes get their payouts, which are then paid.
+///
+/// The run happens in the background. Only stakers that reached their min_payout are paid, and
+/// nothing is paid while the chain is halted or paused.
+pub async fn run_payouts(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<RunPayoutsArgs>,
+) -> Result<(), AppError> {
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<()>();
+
+    tx.send(CoinStakerMessage::ProcessPayments(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    os_rx.await.context("Sender dropped")?;
+
+    Ok(())
+}
+
+/// Pauses a currency, for example while a fork is investigated: no work is added and no payouts
+/// are created or sent, while the other currencies of the pool keep running.
+///
+/// The blocks that arrive while the currency is paused are recorded, and processed when it is
+/// resumed. A pause doesn't survive a restart of the pool.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "paused": true,
+///     "recorded_blocks": 0
+/// }
+/// ```
+pub async fn pause_chain(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<ChainPause>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<ChainPause>();
+
+    tx.send(CoinStakerMessage::Pause(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let pause = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(pause))
+}
+
+/// Resumes a paused currency. `recorded_blocks` is the number of blocks that arrived while it was
+/// paused, which are processed in the background.
+pub async fn resume_chain(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<ChainPause>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<ChainPause>();
+
+    tx.send(CoinStakerMessage::Resume(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let pause = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(pause))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PayoutShadowArgs {
+    pub currency_address: Address,
+    /// In sats. Only the stakers of which the reward differs by more than this are returned.
+    #[serde(default)]
+    pub min_difference: u64,
+}
+
+/// Compares the payouts with the payouts that the `shadow_scheme` of the payout config
+/// calculated for the same stakes, to see what switching to that scheme would change.
+///
+/// Returns the stakers of which the reward in a payout differs by more than `min_difference`
+/// sats, the largest difference first. `difference` is the shadow reward minus the reward. A
+/// reward is `null` if the staker is not in that payout. Stakes without a shadow payout, such
+/// as the stakes before the shadow scheme was configured, are not compared.
+///
+/// ```json
+/// [
+///     {
+///         "block_hash": "000000000043d3b2b1e4a5bbac4ad1c3b8b4a9b8c0f5cc1b7d3be4b0b6a1f3a2",
+///         "block_height": 513251,
+///         "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///         "reward": 120000000,
+///         "shadow_reward": 118500000,
+///         "difference": -1500000
+///     }
+/// ]
+/// ```
+pub async fn payout_shadow_diff(
+    State(state): State<AppState>,
+    Query(args): Query<PayoutShadowArgs>,
+) -> Result<AppJson<Vec<PayoutShadowDiff>>, AppError> {
+    if state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .is_none()
+    {
+        return Err(PoolError::CurrencyNotFound.into());
+    }
+
+    let diff = database::get_payout_shadow_diff(
+        &state.pool,
+        &args.currency_address,
+        Amount::from_sat(args.min_difference),
+    )
+    .await?;
+
+    Ok(AppJson(diff))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SetFeeOverrideArgs {
+    pub currency_address: Address,
+    pub identity_address: Address,
+    /// The fee in basis points: 1% is `0.01`. Removes the override if not set.
+    pub fee: Option<Decimal>,
+    /// Why the fee was changed.
+    pub reason: Option<String>,
+}
+
+/// Sets the fee of a staker, instead of the fee the staker signed up with and the fee tiers of
+/// the pool, or removes it if no `fee` is given. Promotions don't lower an overridden fee.
+///
+/// Only payouts that are created afterwards use the new fee. Returns a 400 with the reason if
+/// the address is not a staker or the fee is not between 0 and 1.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "fee": "0.02",
+///     "reason": "early supporter"
+/// }
+/// ```
+pub async fn set_fee_override(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<SetFeeOverrideArgs>,
+) -> Result<AppJson<FeeOverride>, AppError> {
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<FeeOverride>>();
+
+    tx.send(CoinStakerMessage::SetFeeOverride(
+        os_tx,
+        args.identity_address,
+        args.fee,
+        args.reason,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let fee_override = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(fee_override))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct InsertHistoricalStakeArgs {
+    pub currency_address: Address,
+    pub block_hash: BlockHash,
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+/// Inserts a stake of the pool that was not counted, for example because it was staked while
+/// the pool was down. The block is fetched from the daemon.
+///
+/// The stake gets its own round, with the part of the work of the round that holds it that was
+/// added up to and including the stake, according to the block summaries. A matured stake
+/// before the last payout gets a payout right away; later stakes are picked up by the payout
+/// service.
+///
+/// This is a dry run unless `dry_run` is set to `false` explicitly. Returns a 400 with the
+/// reason if the stake can't be inserted.
+///
+/// ```json
+/// {
+///     "stake": {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "block_hash": "000000000029a4fc1a3ab31f9d1d2b2e1fcf9c6e44b5bdfb1e1e0f9c2b7a4c11",
+///         "block_height": 513240,
+///         "found_by": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///         ...
+///     },
+///     "round_id": 0,
+///     "from_round": 1043,
+///     "work_fraction": "0.4",
+///     "dry_run": true,
+///     "payout_created": true,
+///     "shares": [
+///         {
+///             "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "shares": "1805.0",
+///             "reward": 570000000
+///         }
+///     ]
+/// }
+/// ```
+pub async fn insert_historical_stake(
+    State(state): State<AppState>,
+    AppJson(args): AppJson<InsertHistoricalStakeArgs>,
+) -> Result<AppJson<HistoricalStake>, AppError> {
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<HistoricalStake>>();
+
+    tx.send(CoinStakerMessage::InsertHistoricalStake(
+        os_tx,
+        args.block_hash,
+        args.dry_run,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let stake = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(stake))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecalculatePayoutArgs {
+    pub currency_address: Address,
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+/// Recalculates the payout of the stake in `block_hash` from the snapshot of the work in its
+/// round that was taken when the stake was found, for when the work in the round was changed by
+/// a bug afterwards.
+///
+/// This is a dry run unless `dry_run` is set to `false` explicitly: the returned changes compare
+/// the work in the round and the stored rewards with the snapshot and the recalculated rewards.
+/// A payout that was already (partially) paid cannot be changed. Returns a 400 with the reason if
+/// the payout can't be recalculated.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "block_hash": "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+///     "dry_run": true,
+///     "changes": [
+///         {
+///             "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "shares_before": "4630.0",
+///             "shares_after": "4512.5",
+///             "reward_before": 118500000,
+///             "reward_after": 120000000
+///         }
+///     ]
+/// }
+/// ```
+pub async fn recalculate_payout(
+    State(state): State<AppState>,
+    Path(block_hash): Path<BlockHash>,
+    AppJson(args): AppJson<RecalculatePayoutArgs>,
+) -> Result<AppJson<PayoutRecalculation>, AppError> {
+    let tx = state
+        .controller
+        .coin_stakers
+        .get(&args.currency_address)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<Result<PayoutRecalculation>>();
+
+    tx.send(CoinStakerMessage::RecalculatePayout(
+        os_tx,
+        block_hash,
+        args.dry_run,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let recalculation = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(recalculation))
+}
@@ -1,10 +1,24 @@
+use std::time::Instant;
+
 use anyhow::Context;
-use axum::{debug_handler, extract::State, Extension, Json};
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
 use tokio::sync::{mpsc, oneshot};
+use vrsc_rpc::json::vrsc::Address;
 
 use crate::{
-    coinstaker::coinstaker::CoinStakerMessage,
-    http::{constants::Stats, handler::AppJson, routing::AppState},
+    coinstaker::{coinstaker::CoinStakerMessage, constants::RotationProgress},
+    http::{
+        constants::{RewardOutlook, Stats},
+        handler::AppJson,
+        routing::AppState,
+    },
+    status_page::{ChainHealth, ChainLiveness, PoolLiveness},
 };
 
 use super::AppError;
@@ -16,6 +30,93 @@ pub async fn info(State(controller): State<AppState>) -> Json<String> {
     Json(version)
 }
 
+/// Returns the counters and gauges of every currency in the Prometheus text format.
+///
+/// ```text
+/// # HELP pool_blocks_processed_total Blocks that were processed.
+/// # TYPE pool_blocks_processed_total counter
+/// pool_blocks_processed_total{currency="iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq"} 1204
+/// ```
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.controller.metrics.render(),
+    )
+}
+
+/// Returns the health of every currency that this pool stakes, sorted by name.
+///
+/// The routes of a currency are served under `/v1/chains/{currency}/...` as well as
+/// `/v1/currency/{currency}/...`.
+pub async fn chains(State(state): State<AppState>) -> Result<AppJson<Vec<ChainHealth>>, AppError> {
+    let mut chains = vec![];
+
+    for (_, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<ChainHealth>();
+
+        tx.send(CoinStakerMessage::GetHealth(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        chains.push(os_rx.await.context("Sender dropped")?);
+    }
+
+    chains.sort_by(|a, b| a.currency_name.cmp(&b.currency_name));
+
+    Ok(AppJson(chains))
+}
+
+/// Returns the liveness of the subsystems of every currency: the database, the daemon, the last
+/// processed block and the last payout run. Responds with `503 Service Unavailable` if any of
+/// them is unhealthy, for example when the pool is more than `health_max_blocks_behind` blocks
+/// behind the daemon.
+///
+/// ```json
+/// {
+///     "healthy": true,
+///     "chains": [
+///         {
+///             "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///             "currency_name": "VRSCTEST",
+///             "healthy": true,
+///             "reason": null,
+///             "database_reachable": true,
+///             "daemon_reachable": true,
+///             "chain_tip": 100,
+///             "last_block": 100,
+///             "blocks_behind": 0,
+///             "last_block_processed_at": 1731715140,
+///             "last_block_age_in_secs": 60,
+///             "last_payout_run_at": 1731711600
+///         }
+///     ]
+/// }
+/// ```
+pub async fn health(
+    State(state): State<AppState>,
+) -> Result<(StatusCode, AppJson<PoolLiveness>), AppError> {
+    let mut chains = vec![];
+
+    for (_, tx) in state.controller.coin_stakers.all() {
+        let (os_tx, os_rx) = oneshot::channel::<ChainLiveness>();
+
+        tx.send(CoinStakerMessage::GetLiveness(os_tx))
+            .await
+            .context("Could not send Coinstaker message")?;
+
+        chains.push(os_rx.await.context("Sender dropped")?);
+    }
+
+    let liveness = PoolLiveness::new(chains);
+    let status = if liveness.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok((status, AppJson(liveness)))
+}
+
 /// Returns the primary address of the pool.
 ///
 /// Is to be added to the `primaryaddresses` field of VerusIDs that want to stake in this pool.
@@ -33,16 +134,89 @@ pub async fn pool_primary_address(
     Ok(AppJson(res))
 }
 
+/// Returns the statistics of this pool.
+///
+/// Concurrent requests for the same currency share a single computation. The statistics are
+/// cached and refreshed after every block.
 pub async fn statistics(
+    State(state): State<AppState>,
+    Path(currency): Path<Address>,
     Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
 ) -> Result<AppJson<Stats>, AppError> {
-    let (os_tx, os_rx) = oneshot::channel::<Stats>();
+    let cache = state.controller.caches.get(&currency);
+    if let Some(stats) = cache
+        .as_ref()
+        .and_then(|cache| cache.statistics.get(&(), Instant::now()))
+    {
+        return Ok(AppJson(stats));
+    }
 
-    tx.send(CoinStakerMessage::GetStatistics(os_tx))
+    let stats = state
+        .controller
+        .statistics
+        .run(currency, || async move {
+            let (os_tx, os_rx) = oneshot::channel::<Stats>();
+
+            tx.send(CoinStakerMessage::GetStatistics(os_tx))
+                .await
+                .context("Could not send Coinstaker message")?;
+
+            os_rx.await.context("Sender dropped")
+        })
+        .await?;
+
+    if let Some(cache) = cache {
+        cache.statistics.insert((), stats.clone(), Instant::now());
+    }
+
+    Ok(AppJson(stats))
+}
+
+/// Returns the expected staking returns, taking upcoming halvings into account.
+///
+/// `network_apy` is the return of staking on the network at the current block reward, and
+/// `network_apy_after_halving` the return after the next halving, both at the current staking
+/// supply. `pool_apy` is the return of this pool over the last 30 days, with rewards from
+/// before a halving scaled to the current block reward. Amounts are in sats.
+///
+/// ```json
+/// {
+///     "block_height": 3500000,
+///     "block_reward": 300000000,
+///     "next_halving_height": 4433760,
+///     "next_block_reward": 150000000,
+///     "network_apy": 0.0417,
+///     "network_apy_after_halving": 0.0208,
+///     "pool_apy": 0.0398
+/// }
+/// ```
+pub async fn reward_outlook(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<RewardOutlook>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<RewardOutlook>();
+
+    tx.send(CoinStakerMessage::GetRewardOutlook(os_tx))
         .await
         .context("Could not send Coinstaker message")?;
 
-    let stats = os_rx.await.context("Sender dropped")?;
+    let outlook = os_rx.await.context("Sender dropped")?;
 
-    Ok(AppJson(stats))
+    Ok(AppJson(outlook))
+}
+
+/// Returns per staker whether its VerusID was updated with the new pool primary address.
+///
+/// Returns an empty array if no rotation of the pool primary address is configured.
+pub async fn rotation_progress(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<Vec<RotationProgress>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<RotationProgress>>();
+
+    tx.send(CoinStakerMessage::GetRotationProgress(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let progress = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(progress))
 }
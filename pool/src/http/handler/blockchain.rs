@@ -1,13 +1,19 @@
+use std::time::Instant;
+
 use anyhow::Context;
-use axum::Extension;
+use axum::{
+    extract::{Path, Query, State},
+    Extension,
+};
 use serde::Deserialize;
 use tokio::sync::{mpsc, oneshot};
 use tracing::debug;
 use vrsc_rpc::json::vrsc::Address;
 
 use crate::coinstaker::coinstaker::CoinStakerMessage;
-use crate::http::constants::StakingSupply;
+use crate::http::constants::{NetworkStats, StakingSupply};
 use crate::http::handler::AppJson;
+use crate::http::routing::AppState;
 
 use super::AppError;
 
@@ -31,22 +37,99 @@ pub struct Identities {
 ///     "network": 75565.23456789
 /// }
 /// ```
+///
+/// Concurrent requests for the same currency and identities share a single computation. The
+/// staking supply is cached and refreshed after every block.
 pub async fn staking_supply(
+    State(state): State<AppState>,
+    Path(currency): Path<Address>,
     Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
     axum_extra::extract::Query(items): axum_extra::extract::Query<Identities>,
 ) -> Result<AppJson<StakingSupply>, AppError> {
-    let (os_tx, os_rx) = oneshot::channel::<StakingSupply>();
-
     debug!(?items);
 
-    tx.send(CoinStakerMessage::StakingSupply(
+    let mut identity_addresses = items.identity_addresses;
+    identity_addresses.sort_by_key(|address| address.to_string());
+    identity_addresses.dedup();
+
+    let cache = state.controller.caches.get(&currency);
+    if let Some(ss) = cache.as_ref().and_then(|cache| {
+        cache
+            .staking_supply
+            .get(&identity_addresses, Instant::now())
+    }) {
+        return Ok(AppJson(ss));
+    }
+
+    let ss = state
+        .controller
+        .staking_supply
+        .run((currency, identity_addresses.clone()), || {
+            let identity_addresses = identity_addresses.clone();
+
+            async move {
+                let (os_tx, os_rx) = oneshot::channel::<StakingSupply>();
+
+                tx.send(CoinStakerMessage::StakingSupply(os_tx, identity_addresses))
+                    .await
+                    .context("Could not send Coinstaker message")?;
+
+                os_rx.await.context("Sender dropped")
+            }
+        })
+        .await?;
+
+    if let Some(cache) = cache {
+        cache
+            .staking_supply
+            .insert(identity_addresses, ss.clone(), Instant::now());
+    }
+
+    Ok(AppJson(ss))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NetworkStatsArgs {
+    from_height: Option<u64>,
+    #[serde(default = "default_network_stats_limit")]
+    limit: u64,
+}
+
+fn default_network_stats_limit() -> u64 {
+    1000
+}
+
+/// Returns the historical network conditions as recorded by the network stats collector.
+///
+/// Only returns data if `collect_network_stats` is enabled for this currency. At most `limit`
+/// blocks above `from_height` are returned, in ascending order.
+///
+/// ```json
+/// [
+///     {
+///         "block_height": 513251,
+///         "block_hash": "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+///         "staking_supply": 75565.23456789,
+///         "difficulty": 123456789.0,
+///         "pos_ratio": 0.52
+///     }
+/// ]
+/// ```
+pub async fn network_stats(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<NetworkStatsArgs>,
+) -> Result<AppJson<Vec<NetworkStats>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<NetworkStats>>();
+
+    tx.send(CoinStakerMessage::GetNetworkStats(
         os_tx,
-        items.identity_addresses,
+        args.from_height,
+        args.limit,
     ))
     .await
     .context("Could not send Coinstaker message")?;
 
-    let ss = os_rx.await.context("Sender dropped")?;
+    let res = os_rx.await.context("Sender dropped")?;
 
-    Ok(AppJson(ss))
+    Ok(AppJson(res))
 }
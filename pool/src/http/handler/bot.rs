@@ -0,0 +1,164 @@
+//! The commands of the Discord bot, which used to request them over NATS.
+//!
+//! The bot is a trusted client of the operator: it acts on behalf of any staker, so these routes
+//! require an admin key.
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::Query,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures_util::stream;
+use poollib::chunk::into_chunks;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+use vrsc_rpc::json::vrsc::{util::amount::serde::as_sat, Address, Amount};
+
+use crate::{
+    coinstaker::{
+        coinstaker::CoinStakerMessage,
+        constants::{Stake, Staker},
+    },
+    http::handler::{check_min_payout, AppError, AppJson},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct SetMinPayoutArgs {
+    pub identity_address: Address,
+    #[serde(with = "as_sat")]
+    pub min_payout: Amount,
+}
+
+/// Changes the min_payout of a staker, in sats. It can't be lower than the min_payout_floor or
+/// higher than the min_payout_ceiling of the pool.
+///
+/// Returns a 400 with the reason if the min_payout can't be changed.
+pub async fn set_min_payout(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<SetMinPayoutArgs>,
+) -> Result<(), AppError> {
+    check_min_payout(&tx, args.min_payout).await?;
+
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetMinPayout(
+        os_tx,
+        args.identity_address,
+        args.min_payout,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SetPayoutCurrencyArgs {
+    pub identity_address: Address,
+    /// Paid in the staked currency if not set.
+    pub payout_currency: Option<Address>,
+}
+
+/// Changes the currency that the rewards of a staker are converted into when they are paid.
+///
+/// Returns a 400 with the reason if the payout currency can't be changed.
+pub async fn set_payout_currency(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<SetPayoutCurrencyArgs>,
+) -> Result<(), AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetPayoutCurrency(
+        os_tx,
+        args.identity_address,
+        args.payout_currency,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecentStakesArgs {
+    #[serde(default = "default_recent_stakes_limit")]
+    pub limit: usize,
+    /// Streams the stakes in chunks of at most this many stakes, instead of as a single array.
+    pub chunk_size: Option<usize>,
+}
+
+fn default_recent_stakes_limit() -> usize {
+    10
+}
+
+/// Returns the last `limit` stakes of the pool, newest first.
+///
+/// With `chunk_size`, the stakes are streamed as newline-delimited JSON chunks (see
+/// `poollib::chunk::ReplyChunk`), so that a large reply doesn't have to fit in a single message.
+/// The chunk with `last` set ends the reply.
+pub async fn recent_stakes(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<RecentStakesArgs>,
+) -> Result<Response, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<Stake>>();
+
+    tx.send(CoinStakerMessage::GetStakes(os_tx, None))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let mut stakes = os_rx.await.context("Sender dropped")?;
+    stakes.sort_by(|a, b| b.block_height.cmp(&a.block_height));
+    stakes.truncate(args.limit);
+
+    let Some(chunk_size) = args.chunk_size else {
+        return Ok(AppJson(stakes).into_response());
+    };
+
+    let lines = into_chunks(stakes, chunk_size)
+        .into_iter()
+        .map(|chunk| serde_json::to_string(&chunk).map(|line| format!("{line}\n")));
+
+    Ok((
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream::iter(lines)),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CheckSubscriberArgs {
+    pub identity_address: Address,
+}
+
+/// Returns the staker of a VerusID, or `null` if the VerusID never subscribed to the pool.
+pub async fn check_subscriber(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<CheckSubscriberArgs>,
+) -> Result<AppJson<Option<Staker>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<Staker>>();
+
+    tx.send(CoinStakerMessage::GetStakers(
+        os_tx,
+        vec![args.identity_address],
+        None,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let staker = os_rx.await.context("Sender dropped")?.into_iter().next();
+
+    Ok(AppJson(staker))
+}
@@ -3,8 +3,10 @@ use axum::{
     extract::{rejection::JsonRejection, FromRequest},
     response::IntoResponse,
 };
+use poollib::api::{ApiError, ErrorCode};
 use reqwest::StatusCode;
-use serde::Serialize;
+
+use crate::error::PoolError;
 
 #[derive(FromRequest)]
 #[from_request(via(axum::Json), rejection(AppError))]
@@ -22,21 +24,36 @@ where
 pub enum AppError {
     JsonRejection(JsonRejection),
     GenericError(anyhow::Error),
+    /// The request could not be handled, the message is returned to the client.
+    BadRequest(String),
+    /// The request needs a valid session token.
+    Unauthorized,
     NotFound,
+    /// An error with its own code.
+    Pool(PoolError),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        // How we want errors responses to be serialized
-        #[derive(Serialize)]
-        struct ErrorResponse {
-            message: String,
+impl AppError {
+    /// For the errors of a request that the coinstaker refused. The message is returned to the
+    /// client, with the code of the error if it has one.
+    pub fn rejected(error: anyhow::Error) -> Self {
+        match PoolError::find(&error) {
+            Some(pool_error) => Self::Pool(pool_error.clone()),
+            None => Self::BadRequest(error.to_string()),
         }
+    }
+}
 
-        let (status, message) = match self {
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = match self {
             AppError::JsonRejection(rejection) => {
                 // This error is caused by bad user input so don't log it
-                (rejection.status(), rejection.body_text())
+                (
+                    rejection.status(),
+                    ErrorCode::InvalidJson,
+                    rejection.body_text(),
+                )
             }
             AppError::GenericError(err) => {
                 // Because `TraceLayer` wraps each request in a span that contains the request
@@ -46,13 +63,38 @@ impl IntoResponse for AppError {
                 // Don't expose any details about the error to the client
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorCode::Internal,
                     "Something went wrong".to_owned(),
                 )
             }
-            AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".to_owned()),
+            AppError::BadRequest(message) => {
+                (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest, message)
+            }
+            AppError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                ErrorCode::Unauthorized,
+                "Unauthorized".to_owned(),
+            ),
+            AppError::NotFound => (
+                StatusCode::NOT_FOUND,
+                ErrorCode::NotFound,
+                "Resource not found".to_owned(),
+            ),
+            AppError::Pool(err) => {
+                let status = match err {
+                    PoolError::CurrencyNotFound | PoolError::StakerNotFound(_) => {
+                        StatusCode::NOT_FOUND
+                    }
+                    PoolError::DaemonUnavailable { .. } | PoolError::ChainSyncing => {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    }
+                };
+
+                (status, err.code(), err.to_string())
+            }
         };
 
-        (status, AppJson(ErrorResponse { message })).into_response()
+        (status, AppJson(ApiError { code, message })).into_response()
     }
 }
 
@@ -62,8 +104,18 @@ impl From<JsonRejection> for AppError {
     }
 }
 
+impl From<PoolError> for AppError {
+    fn from(value: PoolError) -> Self {
+        Self::Pool(value)
+    }
+}
+
+/// An error with its own code keeps its code, anything else is an internal error.
 impl From<anyhow::Error> for AppError {
     fn from(value: anyhow::Error) -> Self {
-        Self::GenericError(value)
+        match PoolError::find(&value) {
+            Some(pool_error) => Self::Pool(pool_error.clone()),
+            None => Self::GenericError(value),
+        }
     }
 }
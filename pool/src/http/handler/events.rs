@@ -0,0 +1,97 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    response::Response,
+};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tracing::{debug, warn};
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::{
+    coinstaker::http::{WebhookBody, WebhookMessage},
+    error::PoolError,
+    events::PoolEvent,
+    http::routing::AppState,
+};
+
+use super::AppError;
+
+/// Streams the events of a currency over a websocket, as the same messages that the webhooks
+/// send, so front-ends can show the activity of the pool without polling.
+///
+/// Only events that happen after connecting are sent. While the pool catches up with the chain,
+/// only alerts are sent. A client that falls too far behind misses events.
+///
+/// ```json
+/// {
+///     "message": "stake_matured",
+///     "data": "{\"stake_matured\":{\"hash\":\"00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0\",\"height\":513251}}"
+/// }
+/// ```
+pub async fn ws(
+    State(state): State<AppState>,
+    Path(currency): Path<Address>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    let events = state
+        .controller
+        .events
+        .get(&currency)
+        .ok_or(PoolError::CurrencyNotFound)?
+        .subscribe();
+
+    Ok(upgrade.on_upgrade(|socket| stream_events(socket, events)))
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: Receiver<PoolEvent>) {
+    let mut catching_up = false;
+
+    loop {
+        let event = tokio::select! {
+            message = socket.recv() => match message {
+                // clients have nothing to say, but a closed socket ends the stream
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(RecvError::Lagged(n)) => {
+                    warn!(missed = n, "websocket client fell behind, events were dropped");
+
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        match &event {
+            PoolEvent::CatchUpStarted => catching_up = true,
+            PoolEvent::CatchUpFinished { .. } => catching_up = false,
+            _ => {}
+        }
+
+        let Some(msg) = WebhookMessage::from_event(event) else {
+            continue;
+        };
+        if catching_up && !msg.is_alert() {
+            continue;
+        }
+
+        let body = match serde_json::to_string(&WebhookBody::from(msg)) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(error = ?e, "could not serialize websocket message");
+
+                continue;
+            }
+        };
+
+        if socket.send(Message::Text(body)).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("websocket closed");
+}
@@ -0,0 +1,19 @@
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::State;
+
+use crate::http::routing::AppState;
+
+/// Executes a GraphQL query over the stakers, stakes, payouts, payout members and rounds of the
+/// pool (see `http::graphql`).
+///
+/// ```graphql
+/// {
+///     stakers(currency: "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq", status: "active", first: 10) {
+///         identityName
+///         payoutMembers(paid: false) { blockHeight reward }
+///     }
+/// }
+/// ```
+pub async fn graphql(State(state): State<AppState>, request: GraphQLRequest) -> GraphQLResponse {
+    state.graphql.execute(request.into_inner()).await.into()
+}
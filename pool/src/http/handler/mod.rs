@@ -1,8 +1,52 @@
+pub(super) mod admin;
 pub(super) mod app;
 pub(super) mod blockchain;
+pub(super) mod bot;
 pub(super) mod error;
+pub(super) mod events;
+pub(super) mod graphql;
 pub(super) mod payout;
+pub(super) mod session;
 pub(super) mod stake;
 pub(super) mod staker;
 
+use anyhow::Context;
+use tokio::sync::{mpsc, oneshot};
+
+use vrsc_rpc::json::vrsc::Amount;
+
+use crate::{
+    coinstaker::{coinstaker::CoinStakerMessage, MinPayoutBounds},
+    util::explorer::Explorer,
+};
+
 pub use error::{AppError, AppJson};
+
+/// The block explorer of the chain of the coinstaker, if it has one.
+async fn get_explorer(tx: &mpsc::Sender<CoinStakerMessage>) -> Result<Option<Explorer>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Option<Explorer>>();
+
+    tx.send(CoinStakerMessage::GetExplorer(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    Ok(os_rx.await.context("Sender dropped")?)
+}
+
+/// Rejects a min_payout outside the min_payout bounds of the chain of the coinstaker.
+async fn check_min_payout(
+    tx: &mpsc::Sender<CoinStakerMessage>,
+    min_payout: Amount,
+) -> Result<(), AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<MinPayoutBounds>();
+
+    tx.send(CoinStakerMessage::GetMinPayoutBounds(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    os_rx
+        .await
+        .context("Sender dropped")?
+        .check(min_payout)
+        .map_err(AppError::rejected)
+}
@@ -1,15 +1,34 @@
 use anyhow::Context;
-use axum::{debug_handler, extract::Query, Extension};
+use axum::{
+    body::Body,
+    debug_handler,
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
 use serde::Deserialize;
+use sqlx::PgPool;
 use tokio::sync::{mpsc, oneshot};
-use vrsc_rpc::json::vrsc::Address;
+use vrsc_rpc::{bitcoin::BlockHash, json::vrsc::Address};
 
 use crate::{
     coinstaker::coinstaker::CoinStakerMessage,
-    http::handler::{AppError, AppJson},
-    payout_service::PayoutMember,
+    database,
+    error::PoolError,
+    http::{
+        handler::{get_explorer, AppError, AppJson},
+        routing::AppState,
+    },
+    payout_service::{Liabilities, PayoutMember},
+    util::explorer::WithExplorerLinks,
 };
 
+/// The number of payout members that an export reads from the database at a time.
+const EXPORT_BATCH_SIZE: u64 = 500;
+const CSV_HEADER: &str = "credited_at,block_height,block_hash,reward,fee,shares,txid\n";
+
 #[derive(Deserialize, Debug)]
 pub struct GetPayoutsArgs {
     pub identity_addresses: Vec<Address>,
@@ -19,7 +38,7 @@ pub struct GetPayoutsArgs {
 pub async fn get_payouts(
     Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
     Query(args): Query<GetPayoutsArgs>,
-) -> Result<AppJson<Vec<PayoutMember>>, AppError> {
+) -> Result<AppJson<Vec<WithExplorerLinks<PayoutMember>>>, AppError> {
     let (os_tx, os_rx) = oneshot::channel::<Vec<PayoutMember>>();
 
     tx.send(CoinStakerMessage::GetPayouts(
@@ -29,7 +48,214 @@ pub async fn get_payouts(
     .await
     .context("Could not send Coinstaker message")?;
 
+    let res = os_rx.await.context("Sender dropped")?;
+    let explorer = get_explorer(&tx).await?;
+
+    Ok(AppJson(
+        res.into_iter()
+            .map(|member| WithExplorerLinks::payout_member(member, explorer.as_ref()))
+            .collect(),
+    ))
+}
+
+/// Returns the rewards that are not paid yet, per staker and in total, for solvency monitoring.
+#[debug_handler]
+pub async fn get_liabilities(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<Liabilities>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Liabilities>();
+
+    tx.send(CoinStakerMessage::GetLiabilities(os_tx))
+        .await
+        .context("Could not send Coinstaker message")?;
+
     let res = os_rx.await.context("Sender dropped")?;
 
     Ok(AppJson(res))
 }
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExportArgs {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Unix timestamp (in seconds) of the first credited reward to export.
+    pub from: Option<u64>,
+    /// Unix timestamp (in seconds) of the last credited reward to export.
+    pub to: Option<u64>,
+}
+
+/// Exports every reward of a staker, for example for a tax report, as CSV (`format=csv`, the
+/// default) or as a JSON array of payout members (`format=json`). `from` and `to` limit the
+/// export to the rewards that were credited in between, as unix timestamps.
+///
+/// The rewards are ordered by block height and streamed, so long histories are not held in
+/// memory. Amounts are in sats, `txid` is empty while a reward is not paid yet:
+///
+/// ```csv
+/// credited_at,block_height,block_hash,reward,fee,shares,txid
+/// 2024-11-16T00:00:00+00:00,513251,00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0,120000000,6000000,4512.5,6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7
+/// ```
+pub async fn export_payouts(
+    State(state): State<AppState>,
+    Path((currency, identity_address)): Path<(Address, Address)>,
+    Query(args): Query<ExportArgs>,
+) -> Result<Response, AppError> {
+    if state.controller.coin_stakers.get(&currency).is_none() {
+        return Err(PoolError::CurrencyNotFound.into());
+    }
+
+    let members = export_stream(
+        state.pool.clone(),
+        currency,
+        identity_address.clone(),
+        args.from,
+        args.to,
+    );
+
+    let (content_type, extension, body) = match args.format {
+        ExportFormat::Csv => (
+            "text/csv",
+            "csv",
+            Body::from_stream(
+                stream::once(async { Ok(CSV_HEADER.to_string()) })
+                    .chain(members.map_ok(|member| csv_row(&member))),
+            ),
+        ),
+        ExportFormat::Json => (
+            "application/json",
+            "json",
+            Body::from_stream(
+                stream::once(async { Ok("[".to_string()) })
+                    .chain(members.enumerate().map(|(i, member)| {
+                        let separator = if i == 0 { "" } else { "," };
+
+                        Ok::<_, anyhow::Error>(format!(
+                            "{separator}{}",
+                            serde_json::to_string(&member?)?
+                        ))
+                    }))
+                    .chain(stream::once(async { Ok("]".to_string()) })),
+            ),
+        ),
+    };
+
+    Ok((
+        [
+            (CONTENT_TYPE, content_type.to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"payouts-{identity_address}.{extension}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Reads the payout members of the export in batches.
+fn export_stream(
+    pool: PgPool,
+    currency_address: Address,
+    identity_address: Address,
+    from: Option<u64>,
+    to: Option<u64>,
+) -> impl Stream<Item = anyhow::Result<PayoutMember>> + Send + 'static {
+    // `None` once the last batch was read, otherwise where the next batch starts
+    let start: Option<Option<(u64, BlockHash)>> = Some(None);
+
+    stream::try_unfold(start, move |after| {
+        let pool = pool.clone();
+        let currency_address = currency_address.clone();
+        let identity_address = identity_address.clone();
+
+        async move {
+            let Some(after) = after else {
+                return Ok(None);
+            };
+
+            let batch = database::get_payout_members_for_export(
+                &pool,
+                &currency_address,
+                &identity_address,
+                from,
+                to,
+                after,
+                EXPORT_BATCH_SIZE,
+            )
+            .await?;
+            if batch.is_empty() {
+                return Ok(None);
+            }
+
+            let next = (batch.len() as u64 == EXPORT_BATCH_SIZE).then(|| {
+                batch
+                    .last()
+                    .map(|last| (last.block_height, last.block_hash))
+            });
+
+            Ok(Some((stream::iter(batch.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+}
+
+fn csv_row(member: &PayoutMember) -> String {
+    let credited_at = sqlx::types::chrono::DateTime::from_timestamp(member.created_at as i64, 0)
+        .map(|credited_at| credited_at.to_rfc3339())
+        .unwrap_or_default();
+
+    format!(
+        "{credited_at},{},{},{},{},{},{}\n",
+        member.block_height,
+        member.block_hash,
+        member.reward.as_sat(),
+        member.fee.as_sat(),
+        member.shares,
+        member.txid.map(|txid| txid.to_string()).unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+    use vrsc_rpc::{bitcoin::Txid, json::vrsc::Amount};
+
+    use super::*;
+
+    #[test]
+    fn rewards_are_exported_as_csv_rows() {
+        let mut member = PayoutMember::new(
+            Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            BlockHash::from_str("00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0")
+                .unwrap(),
+            513251,
+            Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap(),
+            Amount::from_sat(120000000),
+            Decimal::new(45125, 1),
+            Amount::from_sat(6000000),
+        );
+        member.created_at = 1731715200;
+
+        assert_eq!(
+            csv_row(&member),
+            "2024-11-16T00:00:00+00:00,513251,00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0,120000000,6000000,4512.5,\n"
+        );
+
+        member.txid = Some(
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap(),
+        );
+        assert!(csv_row(&member)
+            .ends_with(",6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7\n"));
+    }
+}
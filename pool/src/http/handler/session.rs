@@ -0,0 +1,562 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::{header::AUTHORIZATION, request::Parts},
+    Extension,
+};
+use poollib::api::{ApiKey, LoginChallenge, SessionToken};
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+use url::Url;
+use uuid::Uuid;
+use vrsc_rpc::{
+    bitcoin::hashes::{sha256, Hash},
+    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+};
+
+use crate::{
+    coinstaker::{
+        coinstaker::CoinStakerMessage,
+        constants::{Staker, StakerEarnings},
+        http::{StakerWebhook, StakerWebhookEvent},
+        staker_webhooks,
+    },
+    error::PoolError,
+    http::{handler::AppJson, routing::AppState},
+    payout_service::PayoutMember,
+    util::explorer::WithExplorerLinks,
+};
+
+use super::{check_min_payout, get_explorer, AppError};
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// API keys start with this prefix, to tell them apart from session tokens.
+const API_KEY_PREFIX: &str = "vsp_";
+
+fn new_api_key() -> String {
+    format!(
+        "{API_KEY_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Only the hash of an API key is stored, so a leaked database doesn't leak the keys.
+fn hash_api_key(key: &str) -> String {
+    sha256::Hash::hash(key.as_bytes()).to_string()
+}
+
+/// The VerusID of a staker that logged in, taken from the `Authorization: Bearer` header, which
+/// holds either a session token or an API key.
+///
+/// Both are only valid for the currency that they were created on.
+pub struct Session {
+    pub currency_address: Address,
+    pub identity_address: Address,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Session {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, AppError> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AppError::Unauthorized)?
+            .to_string();
+
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| PoolError::CurrencyNotFound)?;
+        let currency = params
+            .get("currency")
+            .and_then(|currency| Address::from_str(currency).ok())
+            .ok_or(PoolError::CurrencyNotFound)?;
+
+        if token.starts_with(API_KEY_PREFIX) {
+            let tx = parts
+                .extensions
+                .get::<mpsc::Sender<CoinStakerMessage>>()
+                .cloned()
+                .ok_or(PoolError::CurrencyNotFound)?;
+            let (os_tx, os_rx) = oneshot::channel::<Option<Address>>();
+
+            tx.send(CoinStakerMessage::UseApiKey(os_tx, hash_api_key(&token)))
+                .await
+                .context("Could not send Coinstaker message")?;
+
+            let identity_address = os_rx
+                .await
+                .context("Sender dropped")?
+                .ok_or(AppError::Unauthorized)?;
+
+            return Ok(Self {
+                currency_address: currency,
+                identity_address,
+            });
+        }
+
+        let session = state
+            .controller
+            .sessions
+            .session(&token, now())
+            .ok_or(AppError::Unauthorized)?;
+        if session.currency_address != currency {
+            return Err(AppError::Unauthorized);
+        }
+
+        Ok(Self {
+            currency_address: session.currency_address,
+            identity_address: session.identity_address,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginChallengeArgs {
+    pub identity_address: Address,
+}
+
+/// Returns a message that the VerusID has to sign to log in, valid for 5 minutes.
+///
+/// Sign the message with `signmessage "<identity>@" "<message>"` and send the signature to
+/// `login`.
+///
+/// ```json
+/// {
+///     "message": "Log in to the staking pool on iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq as iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU: 0c1c4f2e-4f6b-4d0c-9b39-7d7d2f0a1c55",
+///     "expires_at": 1731715500
+/// }
+/// ```
+pub async fn login_challenge(
+    State(state): State<AppState>,
+    Path(currency): Path<Address>,
+    AppJson(args): AppJson<LoginChallengeArgs>,
+) -> AppJson<LoginChallenge> {
+    AppJson(
+        state
+            .controller
+            .sessions
+            .new_challenge(&currency, &args.identity_address, now()),
+    )
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginArgs {
+    pub identity_address: Address,
+    pub signature: String,
+}
+
+/// Exchanges a signed login challenge for a session token that is valid for an hour.
+///
+/// A challenge can only be used once. Returns a 401 if the challenge expired, was already used
+/// or if the signature is not valid for the VerusID.
+///
+/// ```json
+/// {
+///     "token": "5f0c6a1e2b7d4c8f9a3e1d2c4b6a8f0e1d3c5b7a9f2e4d6c8b0a1f3e5d7c9b2a",
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "expires_at": 1731718800
+/// }
+/// ```
+pub async fn login(
+    State(state): State<AppState>,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path(currency): Path<Address>,
+    AppJson(args): AppJson<LoginArgs>,
+) -> Result<AppJson<SessionToken>, AppError> {
+    let sessions = &state.controller.sessions;
+
+    let message = sessions
+        .take_challenge(&currency, &args.identity_address, now())
+        .ok_or(AppError::Unauthorized)?;
+
+    let (os_tx, os_rx) = oneshot::channel::<bool>();
+
+    tx.send(CoinStakerMessage::VerifyMessage(
+        os_tx,
+        args.identity_address.clone(),
+        message,
+        args.signature,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    if !os_rx.await.context("Sender dropped")? {
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(AppJson(sessions.start_session(
+        &currency,
+        &args.identity_address,
+        now(),
+    )))
+}
+
+/// Returns the staker of the VerusID that is logged in (see `staker_status`), or 404 if the
+/// VerusID is not a staker of this pool.
+pub async fn me(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<Staker>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<Staker>>();
+
+    tx.send(CoinStakerMessage::GetStakers(
+        os_tx,
+        vec![session.identity_address.clone()],
+        None,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let staker = os_rx
+        .await
+        .context("Sender dropped")?
+        .into_iter()
+        .next()
+        .ok_or(PoolError::StakerNotFound(session.identity_address))?;
+
+    Ok(AppJson(staker))
+}
+
+/// Returns the payouts of the VerusID that is logged in (see `get_payouts`).
+pub async fn my_payouts(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<Vec<WithExplorerLinks<PayoutMember>>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<PayoutMember>>();
+
+    tx.send(CoinStakerMessage::GetPayouts(
+        os_tx,
+        vec![session.identity_address],
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let res = os_rx.await.context("Sender dropped")?;
+    let explorer = get_explorer(&tx).await?;
+
+    Ok(AppJson(
+        res.into_iter()
+            .map(|member| WithExplorerLinks::payout_member(member, explorer.as_ref()))
+            .collect(),
+    ))
+}
+
+/// Returns the earnings of the VerusID that is logged in (see `get_staker_earnings`).
+pub async fn my_earnings(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<StakerEarnings>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<HashMap<Address, StakerEarnings>>();
+
+    tx.send(CoinStakerMessage::GetStakerEarnings(
+        os_tx,
+        vec![session.identity_address.clone()],
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let earnings = os_rx
+        .await
+        .context("Sender dropped")?
+        .remove(&session.identity_address)
+        .ok_or(PoolError::StakerNotFound(session.identity_address))?;
+
+    Ok(AppJson(earnings))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MinPayoutArgs {
+    #[serde(with = "as_sat")]
+    pub min_payout: Amount,
+}
+
+/// Changes the min_payout of the VerusID that is logged in, in sats. It can't be lower than the
+/// min_payout_floor or higher than the min_payout_ceiling of the pool.
+///
+/// Returns a 400 with the reason if the min_payout can't be changed.
+pub async fn set_my_min_payout(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<MinPayoutArgs>,
+) -> Result<(), AppError> {
+    check_min_payout(&tx, args.min_payout).await?;
+
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetMinPayout(
+        os_tx,
+        session.identity_address,
+        args.min_payout,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PayoutCurrencyArgs {
+    /// Paid in the staked currency if not set.
+    pub payout_currency: Option<Address>,
+}
+
+/// Changes the currency that the rewards of the VerusID that is logged in are converted into
+/// when they are paid. It must be one of the currencies that the pool converts into.
+///
+/// Returns a 400 with the reason if the payout currency can't be changed.
+pub async fn set_my_payout_currency(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<PayoutCurrencyArgs>,
+) -> Result<(), AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetPayoutCurrency(
+        os_tx,
+        session.identity_address,
+        args.payout_currency,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WebhookArgs {
+    /// The endpoint is removed if not set.
+    pub endpoint: Option<Url>,
+}
+
+/// Registers the endpoint that receives the webhook messages about the VerusID that is logged
+/// in, such as a ban or its final statement, replacing an earlier endpoint.
+///
+/// The endpoint receives all events, unsigned. Register it with
+/// `POST /{currency}/stakers/{identity}/webhooks` to select the events and sign the payloads.
+/// The endpoint must be an https URL that doesn't point into a local network.
+pub async fn set_my_webhook(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<WebhookArgs>,
+) -> Result<(), AppError> {
+    if let Some(endpoint) = &args.endpoint {
+        staker_webhooks::check_endpoint(endpoint)
+            .await
+            .map_err(AppError::rejected)?;
+    }
+
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetStakerWebhook(
+        os_tx,
+        session.identity_address,
+        args.endpoint.map(|endpoint| StakerWebhook {
+            endpoint,
+            events: StakerWebhookEvent::ALL.to_vec(),
+            secret: None,
+        }),
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx.await.context("Sender dropped")??;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StakerWebhookArgs {
+    pub endpoint: Url,
+    /// All events if not set.
+    pub events: Option<Vec<StakerWebhookEvent>>,
+}
+
+/// Registers the endpoint that receives the selected events about the VerusID that is logged in,
+/// replacing an earlier endpoint. Only the staker itself can register its endpoint, which must be
+/// an https URL that doesn't point into a local network.
+///
+/// The payloads are signed with the secret in the response, which is only returned once (see
+/// `signature::sign`).
+///
+/// ```json
+/// {
+///     "endpoint": "https://example.com/hooks/pool",
+///     "events": ["stake_matured", "payment_sent"],
+///     "secret": "5f0c6a1e2b7d4c8f9a3e1d2c4b6a8f0e1d3c5b7a9f2e4d6c8b0a1f3e5d7c9b2a"
+/// }
+/// ```
+pub async fn register_webhook(
+    session: Session,
+    Path((_, identity_address)): Path<(Address, Address)>,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<StakerWebhookArgs>,
+) -> Result<AppJson<StakerWebhook>, AppError> {
+    if session.identity_address != identity_address {
+        return Err(AppError::Unauthorized);
+    }
+
+    let events = args
+        .events
+        .unwrap_or_else(|| StakerWebhookEvent::ALL.to_vec());
+    if events.is_empty() {
+        return Err(AppError::BadRequest(
+            "select at least one event".to_string(),
+        ));
+    }
+    staker_webhooks::check_endpoint(&args.endpoint)
+        .await
+        .map_err(AppError::rejected)?;
+
+    let webhook = StakerWebhook {
+        endpoint: args.endpoint,
+        events,
+        secret: Some(format!(
+            "{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        )),
+    };
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<()>>();
+
+    tx.send(CoinStakerMessage::SetStakerWebhook(
+        os_tx,
+        session.identity_address,
+        Some(webhook.clone()),
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    os_rx.await.context("Sender dropped")??;
+
+    Ok(AppJson(webhook))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyArgs {
+    pub label: String,
+}
+
+/// Creates an API key for the VerusID that is logged in. Use it like a session token; it
+/// doesn't expire until it is revoked.
+///
+/// The key is only returned once.
+///
+/// ```json
+/// {
+///     "id": 3,
+///     "label": "dashboard",
+///     "key": "vsp_5f0c6a1e2b7d4c8f9a3e1d2c4b6a8f0e1d3c5b7a9f2e4d6c8b0a1f3e5d7c9b2a",
+///     "created_at": 1731715200,
+///     "last_used_at": null
+/// }
+/// ```
+pub async fn create_api_key(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<ApiKeyArgs>,
+) -> Result<AppJson<ApiKey>, AppError> {
+    let key = new_api_key();
+    let (os_tx, os_rx) = oneshot::channel::<ApiKey>();
+
+    tx.send(CoinStakerMessage::CreateApiKey(
+        os_tx,
+        session.identity_address,
+        hash_api_key(&key),
+        args.label,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let api_key = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(ApiKey {
+        key: Some(key),
+        ..api_key
+    }))
+}
+
+/// Returns the API keys of the VerusID that is logged in that were not revoked, without the
+/// keys themselves.
+pub async fn my_api_keys(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+) -> Result<AppJson<Vec<ApiKey>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<ApiKey>>();
+
+    tx.send(CoinStakerMessage::GetApiKeys(
+        os_tx,
+        session.identity_address,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    Ok(AppJson(os_rx.await.context("Sender dropped")?))
+}
+
+/// Revokes an API key of the VerusID that is logged in, or returns 404 if it has no such key.
+pub async fn revoke_api_key(
+    session: Session,
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path((_, id)): Path<(Address, u64)>,
+) -> Result<(), AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<bool>();
+
+    tx.send(CoinStakerMessage::RevokeApiKey(
+        os_tx,
+        session.identity_address,
+        id,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    if !os_rx.await.context("Sender dropped")? {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_keys_are_told_apart_from_session_tokens() {
+        let key = new_api_key();
+
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_ne!(key, new_api_key());
+        assert_eq!(hash_api_key(&key), hash_api_key(&key));
+        assert_ne!(hash_api_key(&key), key);
+        assert_eq!(hash_api_key(&key).len(), 64);
+    }
+}
@@ -1,16 +1,32 @@
+use std::time::Duration;
+
 use anyhow::Context;
-use axum::{debug_handler, extract::Query, Extension};
+use axum::{
+    debug_handler,
+    extract::{Path, Query, State},
+    Extension,
+};
 use serde::Deserialize;
 use tokio::sync::{mpsc, oneshot};
+use vrsc_rpc::json::vrsc::Address;
 
 use crate::{
     coinstaker::{
         coinstaker::CoinStakerMessage,
         constants::{Stake, StakeStatus},
     },
-    http::handler::{AppError, AppJson},
+    error::PoolError,
+    events::PoolEvent,
+    http::{
+        handler::{get_explorer, AppError, AppJson},
+        routing::AppState,
+    },
+    util::explorer::WithExplorerLinks,
 };
 
+/// The longest a client can wait for the next stake in one request.
+const MAX_NEXT_STAKE_TIMEOUT_IN_SECS: u64 = 300;
+
 #[derive(Deserialize, Debug)]
 pub struct GetStakesArgs {
     pub stake_status: Option<StakeStatus>,
@@ -20,7 +36,7 @@ pub struct GetStakesArgs {
 pub async fn get_stakes(
     Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
     Query(args): Query<GetStakesArgs>,
-) -> Result<AppJson<Vec<Stake>>, AppError> {
+) -> Result<AppJson<Vec<WithExplorerLinks<Stake>>>, AppError> {
     let (os_tx, os_rx) = oneshot::channel::<Vec<Stake>>();
 
     tx.send(CoinStakerMessage::GetStakes(os_tx, args.stake_status))
@@ -28,6 +44,49 @@ pub async fn get_stakes(
         .context("Could not send Coinstaker message")?;
 
     let res = os_rx.await.context("Sender dropped")?;
+    let explorer = get_explorer(&tx).await?;
+
+    Ok(AppJson(
+        res.into_iter()
+            .map(|stake| WithExplorerLinks::stake(stake, explorer.as_ref()))
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NextStakeArgs {
+    /// The number of seconds to wait for the next stake, at most 300.
+    #[serde(default = "default_next_stake_timeout")]
+    pub timeout: u64,
+}
+
+fn default_next_stake_timeout() -> u64 {
+    60
+}
+
+/// Waits for the next stake of the pool and returns it, or returns `null` if no stake was found
+/// within `timeout` seconds.
+///
+/// A long-poll alternative to webhooks for clients that want to be notified of new stakes: a
+/// client can call this endpoint again right after it returns.
+pub async fn next_stake(
+    State(state): State<AppState>,
+    Path(currency): Path<Address>,
+    Query(args): Query<NextStakeArgs>,
+) -> Result<AppJson<Option<Stake>>, AppError> {
+    let events = state
+        .controller
+        .events
+        .get(&currency)
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    let timeout = Duration::from_secs(args.timeout.min(MAX_NEXT_STAKE_TIMEOUT_IN_SECS));
+    let stake = events
+        .next(timeout, |event| match event {
+            PoolEvent::StakeFound { stake, .. } => Some(stake),
+            _ => None,
+        })
+        .await;
 
-    Ok(AppJson(res))
+    Ok(AppJson(stake))
 }
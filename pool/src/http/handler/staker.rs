@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use anyhow::Context;
-use axum::{extract::Query, Extension};
+use axum::{
+    extract::{Path, Query},
+    Extension,
+};
 use serde::Deserialize;
 use tokio::sync::{mpsc, oneshot};
 use vrsc_rpc::json::vrsc::{Address, Amount};
@@ -9,9 +12,13 @@ use vrsc_rpc::json::vrsc::{Address, Amount};
 use crate::{
     coinstaker::{
         coinstaker::CoinStakerMessage,
-        constants::{Staker, StakerEarnings},
+        constants::{
+            EarningsGranularity, EarningsPeriod, Eligibility, Staker, StakerActivity,
+            StakerEarnings, StakerStatement, StaleStake, UtxoBreakdown,
+        },
         StakerStatus,
     },
+    error::PoolError,
     http::handler::AppJson,
 };
 
@@ -34,6 +41,13 @@ pub struct StakerStatusArgs {
 /// - min_payout: the amount (in sats) of the minimum payout threshold
 /// - status: The status of this staker. One of ["active", "cooling_down", "inactive"].
 /// - fee: the fee percentage in decimals, expressed as basispoints. 0.01 = 1%.
+/// - participation_kind: how the staker takes part. One of ["vault", "cold_staking"].
+/// - payout_address: the address the rewards of a cold staker are paid to. Not set for VerusIDs.
+/// - forecast: the deposits that don't have the 150 confirmations yet to stake, with the height
+///   at which they become eligible, and the share of the staker in the work of the current round
+///   before and after they do.
+/// - created_at: unix timestamp (in seconds) of when the staker joined the pool.
+/// - updated_at: unix timestamp (in seconds) of the last change to the staker.
 ///
 /// Response example:
 /// ```json
@@ -44,6 +58,25 @@ pub struct StakerStatusArgs {
 ///     "min_payout": 100000000,
 ///     "status": "cooling_down",
 ///     "fee": 0.003,
+///     "participation_kind": "vault",
+///     "forecast": {
+///         "height": 3165400,
+///         "pending_deposits": [
+///             {
+///                 "address": "iJcwZBwQ1CHDLp9jmFJxi3k6wCMkWk8Cpz",
+///                 "txid": "1f6c7e5c3bbd0b2bfa9d5e9a2e2b0d6a3c3f3d3c8f3ad6d35a4d6c0d5f6a7b8c",
+///                 "vout": 0,
+///                 "amount": 50000000000,
+///                 "height": 3165391,
+///                 "eligible_at_height": 3165541
+///             }
+///         ],
+///         "round_share": 0.0125,
+///         "block_share": 0.0125,
+///         "forecast_block_share": 0.05
+///     },
+///     "created_at": 1731628800,
+///     "updated_at": 1731715200
 /// }
 /// ```
 ///
@@ -54,7 +87,7 @@ pub async fn staker_status(
 ) -> Result<AppJson<Staker>, AppError> {
     let (os_tx, os_rx) = oneshot::channel::<Option<Staker>>();
 
-    tx.send(CoinStakerMessage::StakerStatus(os_tx, args.address))
+    tx.send(CoinStakerMessage::StakerStatus(os_tx, args.address.clone()))
         .await
         .context("Could not send Coinstaker message")?;
 
@@ -63,10 +96,110 @@ pub async fn staker_status(
     if let Some(staker) = res {
         Ok(AppJson(staker))
     } else {
-        Err(AppError::NotFound)
+        Err(PoolError::StakerNotFound(args.address).into())
     }
 }
 
+#[derive(Deserialize, Debug)]
+pub struct DelegateStakingArgs {
+    pub identity_address: Address,
+    pub address: Address,
+    /// The signature by `address` of the message
+    /// `delegate staking of {address} on {currency} to {identity_address}`, as created with
+    /// `signmessage`.
+    pub signature: String,
+}
+
+/// Lets the pool stake the funds of `address` on behalf of the VerusID `identity_address`,
+/// without moving the funds into the VerusID.
+///
+/// Only available on chains that support delegated staking. The address must be a primary
+/// address of the VerusID that is not an address of the pool, and the pool must be allowed to
+/// stake its funds. An address can only be delegated by one VerusID. The work of the address is
+/// added to the work of the staker.
+///
+/// Returns the staker object (see `staker_status`), or a 400 with the reason if the address
+/// can't be delegated.
+pub async fn delegate_staking(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<DelegateStakingArgs>,
+) -> Result<AppJson<Staker>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<Staker>>();
+
+    tx.send(CoinStakerMessage::DelegateStaking(
+        os_tx,
+        args.identity_address,
+        args.address,
+        args.signature,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let staker = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(staker))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RegisterColdStakerArgs {
+    /// The plain address of which the funds are staked.
+    pub address: Address,
+    /// The address that the rewards are paid to.
+    pub payout_address: Address,
+    /// The signature by `address` of the message
+    /// `cold stake {address} on {currency}, pay rewards to {payout_address}`, as created with
+    /// `signmessage`.
+    pub signature: String,
+}
+
+/// Lets the pool stake the funds of a plain address, without a VerusID. Registering an address
+/// again changes its payout address.
+///
+/// Only available on chains that support cold staking. The pool follows the balance of the
+/// address: it is active while its balance with 150 confirmations is at least the minimum
+/// balance of the pool. Returns the staker object (see `staker_status`), or a 400 with the
+/// reason if the address can't be registered.
+///
+/// ```json
+/// {
+///     "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///     "identity_address": "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7",
+///     "identity_name": "RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7",
+///     "min_payout": 100000000,
+///     "status": "active",
+///     "fee": 0.003,
+///     "participation_kind": "cold_staking",
+///     "payout_address": "RQ5ByJmHkyjkhfbMqhs1UfU8xsYjBdUd2y",
+///     "created_at": 0,
+///     "updated_at": 0
+/// }
+/// ```
+pub async fn register_cold_staker(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    AppJson(args): AppJson<RegisterColdStakerArgs>,
+) -> Result<AppJson<Staker>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<anyhow::Result<Staker>>();
+
+    tx.send(CoinStakerMessage::RegisterColdStaker(
+        os_tx,
+        args.address,
+        args.payout_address,
+        args.signature,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let staker = os_rx
+        .await
+        .context("Sender dropped")?
+        .map_err(AppError::rejected)?;
+
+    Ok(AppJson(staker))
+}
+
 #[derive(Deserialize, Debug)]
 pub struct GetStakerArgs {
     pub identity_addresses: Vec<Address>,
@@ -76,7 +209,8 @@ pub struct GetStakerArgs {
 /// Finds and returns an array of stakers based on the supplied `identity_addresses` argument,
 /// if they are found, optionally filtered by staker status.
 ///
-/// `staker_status` can be one of ["active", "cooling_down", "inactive"].
+/// `staker_status` can be one of ["active", "cooling_down", "inactive", "expired",
+/// "banned"].
 ///
 /// Ignores VerusIDs that are not found.
 pub async fn get_stakers(
@@ -98,6 +232,303 @@ pub async fn get_stakers(
     Ok(AppJson(res))
 }
 
+#[derive(Deserialize, Debug)]
+pub struct StakerActivityArgs {
+    pub identity_address: Address,
+    /// Only returns activity before this unix timestamp, to page through the history.
+    pub before: Option<u64>,
+    #[serde(default = "default_staker_activity_limit")]
+    pub limit: u64,
+}
+
+fn default_staker_activity_limit() -> u64 {
+    100
+}
+
+/// Returns the history of a staker, newest first.
+///
+/// Merges the status changes of the staker, the work that was counted in the round of a stake,
+/// the stakes it found, the payouts it was credited and the payments it received.
+///
+/// `kind` can be one of ["status_changed", "work_counted", "stake_found", "payout_credited",
+/// "payment_received"]. Amounts are in sats.
+///
+/// ```json
+/// [
+///     {
+///         "kind": "payout_credited",
+///         "timestamp": 1731715200,
+///         "block_height": 513251,
+///         "reference": "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+///         "amount": 120000000,
+///         "shares": "4512.5",
+///         "status": null
+///     },
+///     {
+///         "kind": "status_changed",
+///         "timestamp": 1731628800,
+///         "block_height": null,
+///         "reference": null,
+///         "amount": null,
+///         "shares": null,
+///         "status": "active"
+///     }
+/// ]
+/// ```
+pub async fn get_staker_activity(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<StakerActivityArgs>,
+) -> Result<AppJson<Vec<StakerActivity>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<StakerActivity>>();
+
+    tx.send(CoinStakerMessage::GetStakerActivity(
+        os_tx,
+        args.identity_address,
+        args.before,
+        args.limit,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let activity = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(activity))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StakerStatementArgs {
+    pub identity_address: Address,
+}
+
+/// Returns the final statements of a staker, newest first.
+///
+/// A statement is made once a staker is inactive and the pool owes it nothing anymore. It
+/// summarizes the entire history of the staker in the pool. Amounts are in sats.
+///
+/// ```json
+/// [
+///     {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///         "identity_name": "alice@",
+///         "joined_at": 1725148800,
+///         "left_at": 1731628800,
+///         "staked_time_in_secs": 6480000,
+///         "n_rounds": 42,
+///         "rewards": 5040000000,
+///         "fees": 252000000,
+///         "forfeited": 1000,
+///         "created_at": 1731715200
+///     }
+/// ]
+/// ```
+pub async fn get_staker_statements(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Query(args): Query<StakerStatementArgs>,
+) -> Result<AppJson<Vec<StakerStatement>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<StakerStatement>>();
+
+    tx.send(CoinStakerMessage::GetStakerStatements(
+        os_tx,
+        args.identity_address,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let statements = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(statements))
+}
+
+/// Returns the stakes that went stale which were found by a VerusID or which it had work in,
+/// newest first.
+///
+/// The work in the round of a stale stake is moved back to round 0, so it counts towards the
+/// next stake of the pool. `redistributed` shows the shares of every staker that were moved.
+///
+/// ```json
+/// [
+///     {
+///         "currency_address": "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq",
+///         "block_hash": "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+///         "block_height": 513251,
+///         "found_by": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///         "redistributed": [
+///             { "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU", "shares": "100" },
+///             { "identity_address": "iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi", "shares": "40" }
+///         ],
+///         "created_at": 1731715200
+///     }
+/// ]
+/// ```
+pub async fn get_stale_stakes(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path((_, identity_address)): Path<(Address, Address)>,
+) -> Result<AppJson<Vec<StaleStake>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<StaleStake>>();
+
+    tx.send(CoinStakerMessage::GetStaleStakes(os_tx, identity_address))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let stale_stakes = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(stale_stakes))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EarningsTimeseriesArgs {
+    #[serde(default)]
+    pub granularity: EarningsGranularity,
+}
+
+/// Returns the earnings history of a staker: the rewards and fees it was credited, and the
+/// number of stakes it got a reward of, per day, week or month (`granularity`, `day` by
+/// default), oldest first. Periods start at midnight UTC, weeks on monday. Periods without
+/// rewards are left out. Amounts are in sats.
+///
+/// ```json
+/// [
+///     {
+///         "period_start": 1730678400,
+///         "rewards": 240000000,
+///         "fees": 12000000,
+///         "n_stakes": 2
+///     }
+/// ]
+/// ```
+pub async fn get_earnings_timeseries(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path((_, identity_address)): Path<(Address, Address)>,
+    Query(args): Query<EarningsTimeseriesArgs>,
+) -> Result<AppJson<Vec<EarningsPeriod>>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Vec<EarningsPeriod>>();
+
+    tx.send(CoinStakerMessage::GetEarningsTimeseries(
+        os_tx,
+        identity_address,
+        args.granularity,
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let periods = os_rx.await.context("Sender dropped")?;
+
+    Ok(AppJson(periods))
+}
+
+/// Returns the UTXOs of a staker, including the ones of addresses that let the pool stake their
+/// funds for the staker, and whether each of them counts towards its work. A UTXO counts once it
+/// has 150 confirmations, and only while the staker is active. This explains why the work of a
+/// staker can differ from its balance.
+///
+/// Returns a 404 if the VerusID is not a staker.
+///
+/// ```json
+/// {
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "status": "active",
+///     "height": 3152010,
+///     "utxos": [
+///         {
+///             "address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "txid": "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+///             "vout": 0,
+///             "amount": 100000000000,
+///             "confirmations": 2048,
+///             "eligible": true
+///         },
+///         {
+///             "address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///             "txid": "1f6c7e5c3bbd0b2bfa9d5e9a2e2b0d6a3c3f3d3c8f3ad6d35a4d6c0d5f6a7b8c",
+///             "vout": 1,
+///             "amount": 25000000000,
+///             "confirmations": 12,
+///             "eligible": false
+///         }
+///     ],
+///     "eligible_balance": 100000000000,
+///     "ineligible_balance": 25000000000
+/// }
+/// ```
+pub async fn get_utxos(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path((_, identity_address)): Path<(Address, Address)>,
+) -> Result<AppJson<UtxoBreakdown>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Option<UtxoBreakdown>>();
+
+    tx.send(CoinStakerMessage::GetUtxoBreakdown(
+        os_tx,
+        identity_address.clone(),
+    ))
+    .await
+    .context("Could not send Coinstaker message")?;
+
+    let breakdown = os_rx
+        .await
+        .context("Sender dropped")?
+        .ok_or(PoolError::StakerNotFound(identity_address))?;
+
+    Ok(AppJson(breakdown))
+}
+
+/// Checks whether a VerusID can join the pool, with the same checks the pool runs when the
+/// VerusID is updated, so a staker can check its VerusID before locking it. `identity` is the
+/// name or the i-address of the VerusID.
+///
+/// Returns a 404 if the daemon doesn't know the VerusID.
+///
+/// ```json
+/// {
+///     "identity_address": "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU",
+///     "eligible": false,
+///     "checks": [
+///         {
+///             "condition": "minimum_signatures",
+///             "passed": true,
+///             "detail": "minimumsignatures is 1, it must be 1"
+///         },
+///         {
+///             "condition": "primary_addresses",
+///             "passed": true,
+///             "detail": "2 primary addresses, at least 2 are required"
+///         },
+///         {
+///             "condition": "pool_primary_address",
+///             "passed": true,
+///             "detail": "one of the primary addresses must be RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2"
+///         },
+///         {
+///             "condition": "max_primary_addresses",
+///             "passed": true,
+///             "detail": "2 primary addresses, at most 64 are allowed"
+///         },
+///         {
+///             "condition": "time_lock",
+///             "passed": false,
+///             "detail": "the timelock is 10, at least 1440 is required"
+///         }
+///     ]
+/// }
+/// ```
+pub async fn check_eligibility(
+    Extension(tx): Extension<mpsc::Sender<CoinStakerMessage>>,
+    Path((_, identity)): Path<(Address, String)>,
+) -> Result<AppJson<Eligibility>, AppError> {
+    let (os_tx, os_rx) = oneshot::channel::<Option<Eligibility>>();
+
+    tx.send(CoinStakerMessage::CheckEligibility(os_tx, identity))
+        .await
+        .context("Could not send Coinstaker message")?;
+
+    let eligibility = os_rx
+        .await
+        .context("Sender dropped")?
+        .ok_or(AppError::NotFound)?;
+
+    Ok(AppJson(eligibility))
+}
+
 /// Returns an array of balances, based on the provided VerusIDs.
 ///
 /// The balances represent how much each staker has earned in the pool
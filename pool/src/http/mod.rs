@@ -1,4 +1,5 @@
 pub mod constants;
+mod graphql;
 mod handler;
 mod routing;
 mod service;
@@ -1,20 +1,25 @@
-use std::sync::Arc;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
 use axum::{
     extract::{MatchedPath, Path, Request, State},
+    http::header::AUTHORIZATION,
     middleware::{self, Next},
     response::Response,
-    routing::{get, put},
+    routing::{delete, get, post, put},
 };
-use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
 use tower_http::trace::{DefaultOnRequest, DefaultOnResponse};
 use tracing::Level;
 use uuid::Uuid;
 use vrsc_rpc::json::vrsc::Address;
 
-use crate::controller::Controller;
+use crate::{controller::Controller, error::PoolError};
 
-use super::handler;
+use super::{
+    graphql::{self, PoolSchema},
+    handler::{self, AppError},
+};
 
 pub fn base_path() -> &'static str {
     "/v1"
@@ -23,15 +28,37 @@ pub fn base_path() -> &'static str {
 #[derive(Clone)]
 pub struct AppState {
     pub controller: Arc<Controller>,
+    /// The keys of the operators, of which one is required for the admin routes.
+    pub admin_keys: Arc<Vec<Secret<String>>>,
+    /// For the handlers that read more from the database than fits in a coinstaker message.
+    pub pool: PgPool,
+    pub graphql: PoolSchema,
 }
 
-pub fn router(controller: Arc<Controller>) -> axum::Router {
-    let state = AppState { controller };
+pub fn router(
+    controller: Arc<Controller>,
+    admin_keys: Vec<Secret<String>>,
+    pool: PgPool,
+) -> axum::Router {
+    let state = AppState {
+        controller,
+        admin_keys: Arc::new(admin_keys),
+        graphql: graphql::schema(pool.clone()),
+        pool,
+    };
 
     axum::Router::new()
+        .route("/metrics", get(handler::app::metrics))
+        .route("/health", get(handler::app::health))
+        .route("/graphql", post(handler::graphql::graphql))
+        .with_state(state.clone())
         .nest(
             base_path(),
-            main_router(state.clone()).nest("/currency", currency_router(state)),
+            main_router(state.clone())
+                .nest("/currency", currency_router(state.clone()))
+                .nest("/chains", currency_router(state.clone()))
+                .nest("/admin", admin_router(state.clone()))
+                .nest("/bot", bot_router(state)),
         )
         .layer(
             tower_http::trace::TraceLayer::new_for_http()
@@ -59,6 +86,70 @@ pub fn router(controller: Arc<Controller>) -> axum::Router {
 pub fn main_router(state: AppState) -> axum::Router {
     axum::Router::new()
         .route("/info", get(handler::app::info))
+        .route("/chains", get(handler::app::chains))
+        .with_state(state)
+}
+
+pub fn admin_router(state: AppState) -> axum::Router {
+    axum::Router::new()
+        .route("/config", get(handler::admin::config))
+        .route("/webhooks/status", get(handler::admin::webhook_status))
+        .route("/webhooks/dead", get(handler::admin::dead_webhooks))
+        .route("/webhooks/redrive", post(handler::admin::redrive_webhooks))
+        .route("/audit/startup", get(handler::admin::startup_audit))
+        .route("/reconciliation", get(handler::admin::reconciliation))
+        .route("/fraud", get(handler::admin::fraud_evidence))
+        .route("/rounds/merge", post(handler::admin::merge_rounds))
+        .route("/stakers/pay", post(handler::admin::pay_staker))
+        .route("/stakers/ban", post(handler::admin::ban_staker))
+        .route(
+            "/key-rotation",
+            get(handler::admin::key_rotation).post(handler::admin::start_key_rotation),
+        )
+        .route(
+            "/key-rotation/retire",
+            post(handler::admin::retire_key_rotation),
+        )
+        .route("/payouts/run", post(handler::admin::run_payouts))
+        .route("/payouts/shadow", get(handler::admin::payout_shadow_diff))
+        .route("/stakers/fee", put(handler::admin::set_fee_override))
+        .route("/stakes", post(handler::admin::insert_historical_stake))
+        .route(
+            "/payouts/:block_hash/recalculate",
+            post(handler::admin::recalculate_payout),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_key,
+        ))
+        .with_state(state)
+}
+
+/// The commands of the Discord bot, with the shapes it used over NATS.
+pub fn bot_router(state: AppState) -> axum::Router {
+    axum::Router::new()
+        .route(
+            "/:currency/stakingsupply",
+            get(handler::blockchain::staking_supply),
+        )
+        .route(
+            "/:currency/setminpayout",
+            post(handler::bot::set_min_payout),
+        )
+        .route(
+            "/:currency/setpayoutcurrency",
+            post(handler::bot::set_payout_currency),
+        )
+        .route("/:currency/recentstakes", get(handler::bot::recent_stakes))
+        .route(
+            "/:currency/checksubscriber",
+            get(handler::bot::check_subscriber),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), my_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_key,
+        ))
         .with_state(state)
 }
 
@@ -69,40 +160,194 @@ pub fn currency_router(state: AppState) -> axum::Router {
             "/:currency/poolprimaryaddress",
             get(handler::app::pool_primary_address),
         )
+        .route(
+            "/:currency/rotationprogress",
+            get(handler::app::rotation_progress),
+        )
+        .route(
+            "/:currency/rewardoutlook",
+            get(handler::app::reward_outlook),
+        )
         .route(
             "/:currency/stakingsupply",
             get(handler::blockchain::staking_supply),
         )
+        .route(
+            "/:currency/networkstats",
+            get(handler::blockchain::network_stats),
+        )
         .route(
             "/:currency/stakerstatus",
             put(handler::staker::staker_status),
         )
+        .route(
+            "/:currency/eligibility/:identity",
+            get(handler::staker::check_eligibility),
+        )
+        .route(
+            "/:currency/delegatedstaking",
+            put(handler::staker::delegate_staking),
+        )
+        .route(
+            "/:currency/coldstaking",
+            put(handler::staker::register_cold_staker),
+        )
         .route("/:currency/staker", get(handler::staker::get_stakers))
+        .route(
+            "/:currency/stakeractivity",
+            get(handler::staker::get_staker_activity),
+        )
+        .route(
+            "/:currency/stakerstatement",
+            get(handler::staker::get_staker_statements),
+        )
         .route(
             "/:currency/stakerearnings",
             get(handler::staker::get_staker_earnings),
         )
+        .route(
+            "/:currency/stakers/:identity_address/stale-stakes",
+            get(handler::staker::get_stale_stakes),
+        )
+        .route(
+            "/:currency/stakers/:identity_address/earnings",
+            get(handler::staker::get_earnings_timeseries),
+        )
+        .route(
+            "/:currency/stakers/:identity_address/payouts/export",
+            get(handler::payout::export_payouts),
+        )
+        .route(
+            "/:currency/stakers/:identity_address/utxos",
+            get(handler::staker::get_utxos),
+        )
         .route(
             "/:currency/stakingbalance",
             get(handler::staker::get_staking_balance),
         )
         .route("/:currency/stake", get(handler::stake::get_stakes))
+        .route("/:currency/stake/next", get(handler::stake::next_stake))
         .route("/:currency/payout", get(handler::payout::get_payouts))
+        .route(
+            "/:currency/liabilities",
+            get(handler::payout::get_liabilities),
+        )
+        .route(
+            "/:currency/login/challenge",
+            post(handler::session::login_challenge),
+        )
+        .route("/:currency/login", post(handler::session::login))
+        .route("/:currency/me", get(handler::session::me))
+        .route("/:currency/me/payouts", get(handler::session::my_payouts))
+        .route("/:currency/me/earnings", get(handler::session::my_earnings))
+        .route(
+            "/:currency/me/minpayout",
+            put(handler::session::set_my_min_payout),
+        )
+        .route(
+            "/:currency/me/payoutcurrency",
+            put(handler::session::set_my_payout_currency),
+        )
+        .route(
+            "/:currency/me/webhook",
+            put(handler::session::set_my_webhook),
+        )
+        .route(
+            "/:currency/stakers/:identity_address/webhooks",
+            post(handler::session::register_webhook),
+        )
+        .route(
+            "/:currency/me/apikeys",
+            get(handler::session::my_api_keys).post(handler::session::create_api_key),
+        )
+        .route(
+            "/:currency/me/apikeys/:id",
+            delete(handler::session::revoke_api_key),
+        )
+        .route("/:currency/ws", get(handler::events::ws))
+        .route(
+            "/:currency/pause",
+            post(handler::admin::pause_chain).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_admin_key,
+            )),
+        )
+        .route(
+            "/:currency/resume",
+            post(handler::admin::resume_chain).route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_admin_key,
+            )),
+        )
         .route_layer(middleware::from_fn_with_state(state.clone(), my_middleware))
         .with_state(state)
 }
 
 async fn my_middleware(
     State(state): State<AppState>,
-    Path(currency): Path<Address>,
+    Path(params): Path<HashMap<String, String>>,
     mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    if let Some(currency_id) = state.controller.coin_stakers.get(&currency).cloned() {
+) -> Result<Response, AppError> {
+    // some routes have more parameters than the currency
+    let currency = params
+        .get("currency")
+        .and_then(|currency| Address::from_str(currency).ok())
+        .ok_or(PoolError::CurrencyNotFound)?;
+
+    if let Some(currency_id) = state.controller.coin_stakers.get(&currency) {
         request.extensions_mut().insert(currency_id);
 
         Ok(next.run(request).await)
     } else {
-        Err(StatusCode::NOT_FOUND)
+        Err(PoolError::CurrencyNotFound.into())
+    }
+}
+
+/// Only lets requests through that have one of the admin keys in the `Authorization: Bearer`
+/// header. Without admin keys, the admin routes can't be used.
+async fn require_admin_key(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AppError::Unauthorized)?;
+
+    if state
+        .admin_keys
+        .iter()
+        .any(|admin_key| keys_match(admin_key.expose_secret(), key))
+    {
+        Ok(next.run(request).await)
+    } else {
+        Err(AppError::Unauthorized)
+    }
+}
+
+/// Compares two keys in a time that doesn't depend on where they differ.
+fn keys_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_keys_must_match_exactly() {
+        assert!(keys_match("secret", "secret"));
+        assert!(!keys_match("secret", "secreT"));
+        assert!(!keys_match("secret", "secret2"));
+        assert!(!keys_match("secret", ""));
     }
 }
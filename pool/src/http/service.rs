@@ -3,8 +3,10 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use axum::async_trait;
+use sqlx::PgPool;
 use tokio::net::TcpListener;
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::warn;
 
 use crate::{config::HttpConfig, controller::Controller};
 
@@ -13,12 +15,22 @@ use super::routing::router;
 pub struct HttpService {
     pub state: Arc<Controller>,
     pub config: HttpConfig,
+    /// The database that the GraphQL API and the exports read from.
+    pub pool: PgPool,
 }
 
 #[async_trait]
 impl IntoSubsystem<anyhow::Error> for HttpService {
     async fn run(self, subsys: SubsystemHandle) -> Result<()> {
-        let router = router(Arc::clone(&self.state));
+        if self.config.admin_keys.is_empty() {
+            warn!("no admin keys are configured, the admin routes can't be used");
+        }
+
+        let router = router(
+            Arc::clone(&self.state),
+            self.config.admin_keys.clone(),
+            self.pool,
+        );
 
         let socket = SocketAddr::new(self.config.host, self.config.port);
         let listener = TcpListener::bind(&socket).await?;
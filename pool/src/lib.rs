@@ -1,10 +1,16 @@
+pub mod accounting;
 pub mod app;
 pub mod coinstaker;
 pub mod config;
 pub mod controller;
 pub mod database;
+pub mod error;
+pub mod events;
 pub mod http;
+pub mod metrics;
+pub mod notifications;
 pub mod payout_service;
+pub mod status_page;
 pub mod util;
 
 pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("sql/migrations");
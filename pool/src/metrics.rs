@@ -0,0 +1,237 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use vrsc_rpc::json::vrsc::{Address, Amount};
+
+/// A counter or gauge of the pool, per currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Metric {
+    BlocksProcessed,
+    /// Unix timestamp (in seconds) of the last processed block, to alert on a stuck coinstaker.
+    LastBlockProcessedAt,
+    StakesFound,
+    StakesStale,
+    /// Stakes of which the coinbase was spent by StakeGuard.
+    StakesStolen,
+    /// The shares that were added to the current round.
+    RoundShares,
+    PayoutsCreated,
+    PayoutAmount,
+    PaymentsSent,
+    PaymentAmount,
+    PaymentsFailed,
+    /// Unix timestamp (in seconds) of the last payout run, to alert on a stuck payout service.
+    LastPayoutRunAt,
+    RpcLatencySum,
+    RpcLatencyCount,
+}
+
+impl Metric {
+    const ALL: [Metric; 14] = [
+        Metric::BlocksProcessed,
+        Metric::LastBlockProcessedAt,
+        Metric::StakesFound,
+        Metric::StakesStale,
+        Metric::StakesStolen,
+        Metric::RoundShares,
+        Metric::PayoutsCreated,
+        Metric::PayoutAmount,
+        Metric::PaymentsSent,
+        Metric::PaymentAmount,
+        Metric::PaymentsFailed,
+        Metric::LastPayoutRunAt,
+        Metric::RpcLatencySum,
+        Metric::RpcLatencyCount,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::BlocksProcessed => "pool_blocks_processed_total",
+            Metric::LastBlockProcessedAt => "pool_last_block_processed_timestamp_seconds",
+            Metric::StakesFound => "pool_stakes_found_total",
+            Metric::StakesStale => "pool_stakes_stale_total",
+            Metric::StakesStolen => "pool_stakes_stolen_total",
+            Metric::RoundShares => "pool_round_shares",
+            Metric::PayoutsCreated => "pool_payouts_created_total",
+            Metric::PayoutAmount => "pool_payout_amount_sats_total",
+            Metric::PaymentsSent => "pool_payments_sent_total",
+            Metric::PaymentAmount => "pool_payment_amount_sats_total",
+            Metric::PaymentsFailed => "pool_payments_failed_total",
+            Metric::LastPayoutRunAt => "pool_last_payout_run_timestamp_seconds",
+            Metric::RpcLatencySum => "pool_daemon_rpc_latency_seconds_sum",
+            Metric::RpcLatencyCount => "pool_daemon_rpc_latency_seconds_count",
+        }
+    }
+
+    /// Returns the name, type and help of the metric family, or None for a metric that belongs
+    /// to the family of another metric.
+    fn family(&self) -> Option<(&'static str, &'static str, &'static str)> {
+        let (name, kind, help) = match self {
+            Metric::BlocksProcessed => (self.name(), "counter", "Blocks that were processed."),
+            Metric::LastBlockProcessedAt => (
+                self.name(),
+                "gauge",
+                "Unix timestamp of the last processed block.",
+            ),
+            Metric::StakesFound => (self.name(), "counter", "Stakes that were found."),
+            Metric::StakesStale => (self.name(), "counter", "Stakes that became stale."),
+            Metric::StakesStolen => (
+                self.name(),
+                "counter",
+                "Stakes of which the coinbase was spent by StakeGuard.",
+            ),
+            Metric::RoundShares => (
+                self.name(),
+                "gauge",
+                "Shares that were added to the current round.",
+            ),
+            Metric::PayoutsCreated => (self.name(), "counter", "Payouts that were created."),
+            Metric::PayoutAmount => (
+                self.name(),
+                "counter",
+                "The staked amount of the payouts that were created.",
+            ),
+            Metric::PaymentsSent => (self.name(), "counter", "Payments that were sent."),
+            Metric::PaymentAmount => (
+                self.name(),
+                "counter",
+                "The amount of the payments that were sent.",
+            ),
+            Metric::PaymentsFailed => (self.name(), "counter", "Payments that failed."),
+            Metric::LastPayoutRunAt => (
+                self.name(),
+                "gauge",
+                "Unix timestamp of the last payout run.",
+            ),
+            Metric::RpcLatencySum => (
+                "pool_daemon_rpc_latency_seconds",
+                "summary",
+                "Latency of the getblock calls to the daemon.",
+            ),
+            // part of the summary above
+            Metric::RpcLatencyCount => return None,
+        };
+
+        Some((name, kind, help))
+    }
+}
+
+/// The counters and gauges of the pool, exposed in the Prometheus text format on `/metrics`.
+///
+/// Clones share the same values. The coinstaker and the payout service of every currency push
+/// into it, with the currency as label.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<HashMap<Metric, BTreeMap<String, f64>>>>);
+
+impl Metrics {
+    pub fn inc(&self, currency: &Address, metric: Metric) {
+        self.add(currency, metric, 1.0);
+    }
+
+    pub fn add(&self, currency: &Address, metric: Metric, value: f64) {
+        let mut metrics = self.0.lock().expect("metrics lock is not poisoned");
+
+        *metrics
+            .entry(metric)
+            .or_default()
+            .entry(currency.to_string())
+            .or_default() += value;
+    }
+
+    pub fn add_amount(&self, currency: &Address, metric: Metric, amount: Amount) {
+        self.add(currency, metric, amount.as_sat() as f64);
+    }
+
+    pub fn set(&self, currency: &Address, metric: Metric, value: f64) {
+        let mut metrics = self.0.lock().expect("metrics lock is not poisoned");
+
+        metrics
+            .entry(metric)
+            .or_default()
+            .insert(currency.to_string(), value);
+    }
+
+    pub fn get(&self, currency: &Address, metric: Metric) -> Option<f64> {
+        let metrics = self.0.lock().expect("metrics lock is not poisoned");
+
+        metrics
+            .get(&metric)
+            .and_then(|values| values.get(&currency.to_string()))
+            .copied()
+    }
+
+    /// Sets a timestamp gauge to now.
+    pub fn set_now(&self, currency: &Address, metric: Metric) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        self.set(currency, metric, now as f64);
+    }
+
+    pub fn observe_rpc_latency(&self, currency: &Address, latency: Duration) {
+        self.add(currency, Metric::RpcLatencySum, latency.as_secs_f64());
+        self.inc(currency, Metric::RpcLatencyCount);
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let metrics = self.0.lock().expect("metrics lock is not poisoned");
+        let mut out = String::new();
+
+        for metric in Metric::ALL {
+            if let Some((name, kind, help)) = metric.family() {
+                let _ = writeln!(out, "# HELP {name} {help}");
+                let _ = writeln!(out, "# TYPE {name} {kind}");
+            }
+
+            for (currency, value) in metrics.get(&metric).into_iter().flatten() {
+                let _ = writeln!(out, "{}{{currency=\"{currency}\"}} {value}", metric.name());
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn renders_the_values_per_currency() {
+        let vrsc = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+        let vrsctest = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+
+        let metrics = Metrics::default();
+        metrics.inc(&vrsc, Metric::BlocksProcessed);
+        metrics.inc(&vrsc, Metric::BlocksProcessed);
+        metrics.inc(&vrsctest, Metric::BlocksProcessed);
+        metrics.set(&vrsc, Metric::RoundShares, 5.0);
+        metrics.set(&vrsc, Metric::RoundShares, 2.5);
+        metrics.observe_rpc_latency(&vrsc, Duration::from_millis(250));
+
+        let rendered = metrics.clone().render();
+
+        assert!(rendered.contains("# TYPE pool_blocks_processed_total counter\n"));
+        assert!(rendered.contains(
+            "pool_blocks_processed_total{currency=\"i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "pool_blocks_processed_total{currency=\"iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq\"} 1\n"
+        ));
+        assert!(rendered
+            .contains("pool_round_shares{currency=\"i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV\"} 2.5\n"));
+        assert!(rendered.contains("# TYPE pool_daemon_rpc_latency_seconds summary\n"));
+        assert!(rendered.contains(
+            "pool_daemon_rpc_latency_seconds_count{currency=\"i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV\"} 1\n"
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{bail, Context, Result};
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+
+use crate::config::EmailConfig;
+
+use super::{Notification, NotificationSender};
+
+pub struct EmailSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailSender {
+    pub fn new(config: &EmailConfig) -> Result<Self> {
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)
+            .context("invalid SMTP host")?
+            .port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            transport = transport.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+
+        let from = config
+            .from
+            .parse()
+            .with_context(|| format!("invalid from address `{}`", config.from))?;
+        let to = config
+            .to
+            .iter()
+            .map(|to| {
+                to.parse()
+                    .with_context(|| format!("invalid to address `{to}`"))
+            })
+            .collect::<Result<Vec<Mailbox>>>()?;
+        if to.is_empty() {
+            bail!("an email notification channel needs at least one to address");
+        }
+
+        Ok(Self {
+            transport: transport.build(),
+            from,
+            to,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for EmailSender {
+    fn kind(&self) -> &'static str {
+        "email"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(&notification.subject);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+
+        self.transport
+            .send(message.body(notification.text.clone())?)
+            .await?;
+
+        Ok(())
+    }
+}
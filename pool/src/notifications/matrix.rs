@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use url::Url;
+use uuid::Uuid;
+
+use crate::config::MatrixConfig;
+
+use super::{Notification, NotificationSender};
+
+pub struct MatrixSender {
+    client: reqwest::Client,
+    homeserver: Url,
+    access_token: Secret<String>,
+    room_id: String,
+}
+
+impl MatrixSender {
+    pub fn new(config: &MatrixConfig) -> Result<Self> {
+        if config.homeserver.cannot_be_a_base() {
+            bail!("invalid Matrix homeserver {}", config.homeserver);
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            client,
+            homeserver: config.homeserver.clone(),
+            access_token: config.access_token.clone(),
+            room_id: config.room_id.clone(),
+        })
+    }
+
+    /// The url to send a message to the room. Every message gets its own transaction id, so
+    /// that the homeserver doesn't take a new message for a retry of an earlier one.
+    fn message_url(&self) -> Url {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("the homeserver is checked to be a base")
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room_id,
+                "send",
+                "m.room.message",
+                &Uuid::new_v4().to_string(),
+            ]);
+
+        url
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for MatrixSender {
+    fn kind(&self) -> &'static str {
+        "matrix"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        self.client
+            .put(self.message_url())
+            .bearer_auth(self.access_token.expose_secret())
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": format!("{}\n\n{}", notification.subject, notification.text),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_sent_to_the_room_on_the_homeserver() {
+        let sender = MatrixSender::new(&MatrixConfig {
+            homeserver: Url::parse("https://matrix.example.com/").unwrap(),
+            access_token: Secret::new("token".to_string()),
+            room_id: "!abc:example.com".to_string(),
+        })
+        .unwrap();
+
+        let url = sender.message_url().to_string();
+        assert!(url.starts_with(
+            "https://matrix.example.com/_matrix/client/v3/rooms/!abc:example.com/send/m.room.message/"
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+//! Notifies the operator of the events that need attention, over channels that don't depend on
+//! the webhook consumer: email, Telegram and Matrix.
+
+mod email;
+mod matrix;
+mod notifier;
+mod telegram;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{
+    config::{NotificationChannel, NotificationsConfig},
+    events::PoolEvent,
+};
+
+pub use email::EmailSender;
+pub use matrix::MatrixSender;
+pub use notifier::Notifier;
+pub use telegram::TelegramSender;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    /// The pool can't do its work, or lost or might lose funds.
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub subject: String,
+    pub text: String,
+}
+
+impl Notification {
+    /// Returns the notification of an event, if the operator is notified of it.
+    pub fn from_event(currency_name: &str, event: &PoolEvent) -> Option<Self> {
+        let (severity, subject, text) = match event {
+            PoolEvent::DaemonUnreachable { error } => (
+                Severity::Critical,
+                "daemon unreachable",
+                format!("No daemon answers, no blocks are processed: {error}"),
+            ),
+            PoolEvent::StakerBanned {
+                staker,
+                stake,
+                spend_txid,
+                forfeited,
+            } => (
+                Severity::Critical,
+                "StakeGuard detection",
+                format!(
+                    "The coinbase of the stake at height {} by {} was spent by StakeGuard in {}. \
+                    The staker was banned and forfeited {}.",
+                    stake.block_height,
+                    staker.identity_name,
+                    spend_txid,
+                    forfeited.as_vrsc()
+                ),
+            ),
+            PoolEvent::PaymentFailed {
+                payment,
+                confirmations,
+            } => (
+                Severity::Critical,
+                "payment failed",
+                format!(
+                    "The payment {} of {} to {} stakers failed with {} confirmations.",
+                    payment.txid,
+                    payment.amount.as_vrsc(),
+                    payment.n_members,
+                    confirmations
+                ),
+            ),
+            PoolEvent::StakeWalletMismatch { stake, reason } => (
+                Severity::Critical,
+                "wallet mismatch",
+                format!(
+                    "The wallet disagrees about the stake at height {}: {reason}",
+                    stake.block_height
+                ),
+            ),
+            PoolEvent::ChainHalted {
+                height,
+                stalled_for_secs,
+            } => (
+                Severity::Critical,
+                "chain halted",
+                format!(
+                    "The chain is stuck at height {height} for {stalled_for_secs} seconds. \
+                    Staking is disabled and payouts are paused."
+                ),
+            ),
+            PoolEvent::DaemonStakingStopped { height } => (
+                Severity::Critical,
+                "daemon stopped staking",
+                format!("The daemon stopped staking at height {height}."),
+            ),
+            PoolEvent::ChainReorganized {
+                fork_height,
+                depth,
+                orphaned_stakes,
+            } => (
+                Severity::Warning,
+                "chain reorganized",
+                format!(
+                    "{depth} blocks from height {fork_height} were replaced, {} stakes became \
+                    stale.",
+                    orphaned_stakes.len()
+                ),
+            ),
+            PoolEvent::StakeStale(stake) => (
+                Severity::Warning,
+                "stake stale",
+                format!("The stake at height {} became stale.", stake.block_height),
+            ),
+            PoolEvent::DaemonReachable {
+                unreachable_for_secs,
+            } => (
+                Severity::Info,
+                "daemon reachable",
+                format!("A daemon answers again after {unreachable_for_secs} seconds."),
+            ),
+            PoolEvent::ChainResumed { height } => (
+                Severity::Info,
+                "chain resumed",
+                format!("The chain advanced to height {height} after a halt."),
+            ),
+            PoolEvent::DaemonStakingResumed {
+                height,
+                stopped_for_secs,
+            } => (
+                Severity::Info,
+                "daemon staking again",
+                format!(
+                    "The daemon stakes again at height {height}, after {stopped_for_secs} seconds."
+                ),
+            ),
+            PoolEvent::StakeFound { stake, .. } => (
+                Severity::Info,
+                "stake found",
+                format!(
+                    "The pool staked block {} for {}.",
+                    stake.block_height,
+                    stake.amount.as_vrsc()
+                ),
+            ),
+            PoolEvent::PaymentSent {
+                txid,
+                n_stakers,
+                amount,
+                ..
+            } => (
+                Severity::Info,
+                "payment sent",
+                format!(
+                    "Paid {} to {n_stakers} stakers in {txid}.",
+                    amount.as_vrsc()
+                ),
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            severity,
+            subject: format!("[{currency_name}] {subject}"),
+            text,
+        })
+    }
+}
+
+/// Delivers notifications to the operator.
+#[async_trait::async_trait]
+pub trait NotificationSender: Send + Sync {
+    /// The kind of channel, for logging.
+    fn kind(&self) -> &'static str;
+
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+struct Channel {
+    min_severity: Severity,
+    sender: Box<dyn NotificationSender>,
+}
+
+/// The configured notification channels. Clones share the same channels.
+#[derive(Clone, Default)]
+pub struct Channels(Arc<Vec<Channel>>);
+
+impl Channels {
+    pub fn new(config: &NotificationsConfig) -> Result<Self> {
+        let channels = config
+            .channels
+            .iter()
+            .map(|channel_config| {
+                let sender: Box<dyn NotificationSender> = match &channel_config.channel {
+                    NotificationChannel::Email(email) => Box::new(EmailSender::new(email)?),
+                    NotificationChannel::Telegram(telegram) => {
+                        Box::new(TelegramSender::new(telegram)?)
+                    }
+                    NotificationChannel::Matrix(matrix) => Box::new(MatrixSender::new(matrix)?),
+                };
+
+                Ok(Channel {
+                    min_severity: channel_config.min_severity,
+                    sender,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self(Arc::new(channels)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Sends a notification to every channel that takes its severity. A channel that fails
+    /// doesn't keep the others from receiving it.
+    pub async fn send(&self, notification: &Notification) {
+        for channel in self
+            .0
+            .iter()
+            .filter(|channel| notification.severity >= channel.min_severity)
+        {
+            if let Err(e) = channel.sender.send(notification).await {
+                error!(
+                    channel = channel.sender.kind(),
+                    subject = notification.subject,
+                    error = ?e,
+                    "Could not send notification"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn critical_events_are_critical() {
+        let halted = Notification::from_event(
+            "VRSC",
+            &PoolEvent::ChainHalted {
+                height: 100,
+                stalled_for_secs: 600,
+            },
+        )
+        .unwrap();
+        assert_eq!(halted.severity, Severity::Critical);
+        assert_eq!(halted.subject, "[VRSC] chain halted");
+
+        let unreachable = Notification::from_event(
+            "VRSC",
+            &PoolEvent::DaemonUnreachable {
+                error: "connection refused".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(unreachable.severity, Severity::Critical);
+
+        let reorg = Notification::from_event(
+            "VRSC",
+            &PoolEvent::ChainReorganized {
+                fork_height: 100,
+                depth: 2,
+                orphaned_stakes: vec![],
+            },
+        )
+        .unwrap();
+        assert_eq!(reorg.severity, Severity::Warning);
+
+        assert_eq!(
+            Notification::from_event("VRSC", &PoolEvent::CatchUpStarted),
+            None
+        );
+    }
+
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    #[async_trait::async_trait]
+    impl NotificationSender for Recorder {
+        fn kind(&self) -> &'static str {
+            "recorder"
+        }
+
+        async fn send(&self, notification: &Notification) -> Result<()> {
+            self.0.lock().unwrap().push(notification.subject.clone());
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn channels_only_receive_their_severities() {
+        let critical = Arc::new(Mutex::new(vec![]));
+        let everything = Arc::new(Mutex::new(vec![]));
+        let channels = Channels(Arc::new(vec![
+            Channel {
+                min_severity: Severity::Critical,
+                sender: Box::new(Recorder(critical.clone())),
+            },
+            Channel {
+                min_severity: Severity::Info,
+                sender: Box::new(Recorder(everything.clone())),
+            },
+        ]));
+
+        for severity in [Severity::Info, Severity::Critical] {
+            channels
+                .send(&Notification {
+                    severity,
+                    subject: format!("{severity:?}"),
+                    text: String::new(),
+                })
+                .await;
+        }
+
+        assert_eq!(*critical.lock().unwrap(), vec!["Critical"]);
+        assert_eq!(*everything.lock().unwrap(), vec!["Info", "Critical"]);
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::warn;
+
+use crate::events::PoolEvent;
+
+use super::{Channels, Notification, Severity};
+
+/// Notifies the operator of the events of a currency.
+///
+/// While the pool catches up with the chain, only critical events are notified, as the others
+/// happened in the past.
+pub struct Notifier {
+    channels: Channels,
+    currency_name: String,
+    events: broadcast::Receiver<PoolEvent>,
+    catching_up: bool,
+}
+
+impl Notifier {
+    pub fn new(
+        channels: Channels,
+        currency_name: String,
+        events: broadcast::Receiver<PoolEvent>,
+    ) -> Self {
+        Self {
+            channels,
+            currency_name,
+            events,
+            catching_up: false,
+        }
+    }
+
+    async fn handle(&mut self, event: PoolEvent) {
+        match event {
+            PoolEvent::CatchUpStarted => self.catching_up = true,
+            PoolEvent::CatchUpFinished { .. } => self.catching_up = false,
+            event => {
+                if let Some(notification) = Notification::from_event(&self.currency_name, &event) {
+                    if !self.catching_up || notification.severity == Severity::Critical {
+                        self.channels.send(&notification).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for Notifier {
+    async fn run(mut self, subsys: SubsystemHandle) -> Result<()> {
+        loop {
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => break,
+                event = self.events.recv() => match event {
+                    Ok(event) => self.handle(event).await,
+                    Err(RecvError::Lagged(n)) => {
+                        warn!(missed = n, "notifications fell behind, events were dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
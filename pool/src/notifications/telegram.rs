@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::config::TelegramConfig;
+
+use super::{Notification, NotificationSender};
+
+pub struct TelegramSender {
+    client: reqwest::Client,
+    bot_token: Secret<String>,
+    chat_id: String,
+}
+
+impl TelegramSender {
+    pub fn new(config: &TelegramConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            client,
+            bot_token: config.bot_token.clone(),
+            chat_id: config.chat_id.clone(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for TelegramSender {
+    fn kind(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            self.bot_token.expose_secret()
+        );
+
+        self.client
+            .post(url)
+            .json(&json!({
+                "chat_id": self.chat_id,
+                "text": format!("{}\n\n{}", notification.subject, notification.text),
+            }))
+            .send()
+            .await?
+            // the url holds the bot token, which must not end up in the logs
+            .error_for_status()
+            .map_err(|e| e.without_url())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use vrsc_rpc::{
+    bitcoin::{BlockHash, Txid},
+    json::vrsc::Address,
+};
+
+use crate::coinstaker::PayoutBatching;
+
+/// Decides the number of stakers that are paid in one payment, from how the previous payments
+/// went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchSizer {
+    size: usize,
+    min_size: usize,
+    max_size: usize,
+    max_latency: Duration,
+}
+
+impl BatchSizer {
+    /// Without batching, every payment pays all stakers and the size never changes.
+    pub fn new(batching: Option<&PayoutBatching>) -> Self {
+        match batching {
+            Some(batching) => {
+                let max_size = batching.max_size.max(1);
+                let min_size = batching.min_size.clamp(1, max_size);
+
+                Self {
+                    size: min_size,
+                    min_size,
+                    max_size,
+                    max_latency: Duration::from_secs(batching.max_latency_in_secs),
+                }
+            }
+            None => Self {
+                size: usize::MAX,
+                min_size: usize::MAX,
+                max_size: usize::MAX,
+                max_latency: Duration::MAX,
+            },
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Grows the size by half, or shrinks it by a quarter if the payment was slow.
+    pub fn record_success(&mut self, latency: Duration) {
+        self.size = if latency > self.max_latency {
+            self.size - self.size / 4
+        } else {
+            self.size.saturating_add((self.size / 2).max(1))
+        }
+        .clamp(self.min_size, self.max_size);
+    }
+
+    /// Halves the size.
+    pub fn record_failure(&mut self) {
+        self.size = (self.size / 2).clamp(self.min_size, self.max_size);
+    }
+}
+
+/// A payment to a batch of stakers. Every batch is recorded, including the ones that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentBatch {
+    pub currency_address: Address,
+    /// `None` if the payment failed.
+    pub txid: Option<Txid>,
+    /// The number of stakers in the payment.
+    pub size: usize,
+    /// How long the sendcurrency operation took.
+    pub latency: Duration,
+    /// The identity address and block hash of every payout member in the payment.
+    pub payout_members: Vec<(Address, BlockHash)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizer() -> BatchSizer {
+        BatchSizer::new(Some(&PayoutBatching {
+            max_size: 100,
+            min_size: 10,
+            max_latency_in_secs: 60,
+        }))
+    }
+
+    #[test]
+    fn size_grows_up_to_the_max() {
+        let mut sizer = sizer();
+        assert_eq!(sizer.size(), 10);
+
+        sizer.record_success(Duration::from_secs(5));
+        assert_eq!(sizer.size(), 15);
+
+        for _ in 0..10 {
+            sizer.record_success(Duration::from_secs(5));
+        }
+        assert_eq!(sizer.size(), 100);
+    }
+
+    #[test]
+    fn size_shrinks_on_failures_and_slow_payments() {
+        let mut sizer = sizer();
+        for _ in 0..10 {
+            sizer.record_success(Duration::from_secs(5));
+        }
+
+        sizer.record_success(Duration::from_secs(90));
+        assert_eq!(sizer.size(), 75);
+
+        sizer.record_failure();
+        assert_eq!(sizer.size(), 37);
+
+        for _ in 0..10 {
+            sizer.record_failure();
+        }
+        assert_eq!(sizer.size(), 10);
+    }
+
+    #[test]
+    fn without_batching_all_stakers_are_paid_at_once() {
+        let mut sizer = BatchSizer::new(None);
+
+        sizer.record_failure();
+        assert_eq!(sizer.size(), usize::MAX);
+    }
+}
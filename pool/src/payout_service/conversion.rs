@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+use vrsc_rpc::{
+    client::{Client, RpcApi},
+    json::vrsc::{Address, Amount},
+};
+
+use crate::coinstaker::PayoutConversion;
+
+use super::PaymentItem;
+
+/// The amount (in sats) of which the conversion rate is the reference for the slippage of a
+/// reward.
+const REFERENCE_AMOUNT_IN_SATS: u64 = 1_000_000;
+
+/// The answer of `estimateconversion`.
+#[derive(Debug, Deserialize)]
+struct ConversionEstimate {
+    estimatedcurrencyout: f64,
+}
+
+/// Decides which payment items are converted into the payout currency of their staker.
+pub struct Converter<'a> {
+    client: &'a Client,
+    currency_address: &'a Address,
+    config: &'a PayoutConversion,
+}
+
+impl<'a> Converter<'a> {
+    pub fn new(
+        client: &'a Client,
+        currency_address: &'a Address,
+        config: &'a PayoutConversion,
+    ) -> Self {
+        Self {
+            client,
+            currency_address,
+            config,
+        }
+    }
+
+    pub fn via(&self) -> Option<&Address> {
+        self.config.via.as_ref()
+    }
+
+    /// The currency that `item` is converted into, or `None` if it is paid in the staked
+    /// currency: when the staker has no payout currency, when the pool no longer pays in it, when
+    /// the conversion can't be estimated or when the conversion of the item would slip more than
+    /// the pool allows.
+    pub fn convert_to(&self, item: &PaymentItem) -> Option<Address> {
+        let payout_currency = item.payout_currency.as_ref()?;
+
+        if !self.config.currencies.contains(payout_currency) {
+            warn!(
+                identity_address = %item.identity_address,
+                %payout_currency,
+                "the pool doesn't pay in this currency anymore, paying in the staked currency"
+            );
+
+            return None;
+        }
+
+        let reference_amount = Amount::from_sat(REFERENCE_AMOUNT_IN_SATS).min(item.amount);
+        let estimates = self
+            .estimate(reference_amount, payout_currency)
+            .and_then(|reference| Ok((reference, self.estimate(item.amount, payout_currency)?)));
+        let (reference, estimate) = match estimates {
+            Ok(estimates) => estimates,
+            Err(e) => {
+                warn!(
+                    identity_address = %item.identity_address,
+                    %payout_currency,
+                    error = ?e,
+                    "could not estimate the conversion, paying in the staked currency"
+                );
+
+                return None;
+            }
+        };
+
+        let slippage = slippage((reference_amount, reference), (item.amount, estimate));
+        if slippage > self.config.max_slippage {
+            warn!(
+                identity_address = %item.identity_address,
+                %payout_currency,
+                %slippage,
+                "conversion would slip too much, paying in the staked currency"
+            );
+
+            return None;
+        }
+
+        Some(payout_currency.clone())
+    }
+
+    /// The amount of `convert_to` that converting `amount` would get.
+    fn estimate(&self, amount: Amount, convert_to: &Address) -> Result<Decimal> {
+        let mut conversion = json!({
+            "currency": self.currency_address.to_string(),
+            "convertto": convert_to.to_string(),
+            "amount": amount.as_vrsc(),
+        });
+        if let Some(via) = &self.config.via {
+            conversion["via"] = json!(via.to_string());
+        }
+
+        let estimate = self
+            .client
+            .call::<ConversionEstimate>("estimateconversion", &[conversion])?;
+
+        Decimal::from_f64(estimate.estimatedcurrencyout)
+            .context("Could not create Decimal from conversion estimate")
+    }
+}
+
+/// How much worse the rate of the `estimate` is than the rate of the `reference`, as a fraction:
+/// 0.02 is 2% less than at the reference rate.
+fn slippage(reference: (Amount, Decimal), estimate: (Amount, Decimal)) -> Decimal {
+    let rate = |(amount, out): (Amount, Decimal)| {
+        if amount == Amount::ZERO {
+            Decimal::ZERO
+        } else {
+            out / Decimal::from(amount.as_sat())
+        }
+    };
+
+    let reference_rate = rate(reference);
+    if reference_rate.is_zero() {
+        return Decimal::ONE;
+    }
+
+    Decimal::ONE - rate(estimate) / reference_rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slippage_compares_the_rates() {
+        let reference = (Amount::from_sat(1_000_000), Decimal::new(5, 3));
+
+        // the same rate as the reference
+        assert_eq!(
+            slippage(
+                reference,
+                (Amount::from_sat(100_000_000), Decimal::new(5, 1))
+            ),
+            Decimal::ZERO
+        );
+        // 2% less than at the reference rate
+        assert_eq!(
+            slippage(
+                reference,
+                (Amount::from_sat(100_000_000), Decimal::new(49, 2))
+            ),
+            Decimal::new(2, 2)
+        );
+        // no liquidity at all
+        assert_eq!(
+            slippage(
+                (Amount::from_sat(1_000_000), Decimal::ZERO),
+                (Amount::from_sat(100_000_000), Decimal::ZERO)
+            ),
+            Decimal::ONE
+        );
+    }
+}
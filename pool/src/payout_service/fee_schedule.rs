@@ -0,0 +1,258 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::Result;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sqlx::PgPool;
+use vrsc_rpc::json::vrsc::{Address, Amount};
+
+use crate::{
+    coinstaker::{constants::Stake, FeeSchedule},
+    database,
+};
+
+use super::Worker;
+
+/// Where the fee of a payout member came from, before a promotional discount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeSource {
+    /// The fee the staker signed up with.
+    Staker,
+    /// The fee an admin set for the staker.
+    Override,
+    /// The fee of the tier of the staked balance.
+    Tier,
+}
+
+impl Display for FeeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeSource::Staker => write!(f, "staker"),
+            FeeSource::Override => write!(f, "override"),
+            FeeSource::Tier => write!(f, "tier"),
+        }
+    }
+}
+
+/// The fee that applies to a worker in a payout and why, stored in the `fee_history` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeDecision {
+    pub identity_address: Address,
+    /// The fee the staker signed up with.
+    pub staker_fee: Decimal,
+    pub source: FeeSource,
+    pub discount: Decimal,
+    /// The fee that is applied, after the discount.
+    pub fee: Decimal,
+    pub average_balance: Amount,
+}
+
+/// Decides the fee of every worker in a payout from the fee schedule and the fee overrides of a
+/// currency.
+///
+/// The default only applies the fees the stakers signed up with.
+#[derive(Debug, Clone, Default)]
+pub struct Fees {
+    schedule: FeeSchedule,
+    overrides: HashMap<Address, Decimal>,
+    /// The number of blocks in which the shares of the payout were added, to determine the
+    /// average balance of a staker.
+    blocks: u64,
+}
+
+impl Fees {
+    pub fn new(schedule: FeeSchedule, overrides: HashMap<Address, Decimal>, blocks: u64) -> Self {
+        Self {
+            schedule,
+            overrides,
+            blocks,
+        }
+    }
+
+    /// Loads the fee overrides of the currency of `stake`, of which the shares were added in the
+    /// blocks after `since_height`.
+    pub async fn load(
+        pool: &PgPool,
+        schedule: &FeeSchedule,
+        stake: &Stake,
+        since_height: u64,
+    ) -> Result<Self> {
+        let overrides = database::get_fee_overrides(pool, &stake.currency_address).await?;
+
+        Ok(Self::new(
+            schedule.clone(),
+            overrides,
+            stake.block_height.saturating_sub(since_height),
+        ))
+    }
+
+    /// Loads the fees of a payout of the shares in the round of `stake`.
+    pub async fn load_for_round(
+        pool: &PgPool,
+        schedule: &FeeSchedule,
+        stake: &Stake,
+    ) -> Result<Self> {
+        let (_, round_start) = database::get_round_containing(
+            pool,
+            &stake.currency_address,
+            stake.block_height.saturating_sub(1),
+        )
+        .await?;
+
+        Self::load(pool, schedule, stake, round_start).await
+    }
+
+    /// Decides the fee of `worker` in the payout of a stake at `block_height`.
+    pub fn decide(&self, worker: &Worker, block_height: u64) -> FeeDecision {
+        // a share is a sat that was eligible to stake during one block
+        let average_balance = (worker.shares / Decimal::from(self.blocks.max(1)))
+            .floor()
+            .to_u64()
+            .map(Amount::from_sat)
+            .unwrap_or(Amount::ZERO);
+
+        let (source, fee) = if let Some(fee) = self.overrides.get(&worker.identity_address) {
+            (FeeSource::Override, *fee)
+        } else {
+            match self
+                .schedule
+                .tiers
+                .iter()
+                .filter(|tier| tier.min_balance <= average_balance)
+                .max_by_key(|tier| tier.min_balance)
+            {
+                Some(tier) if tier.fee < worker.fee => (FeeSource::Tier, tier.fee),
+                _ => (FeeSource::Staker, worker.fee),
+            }
+        };
+
+        let discount = match source {
+            FeeSource::Override => Decimal::ZERO,
+            FeeSource::Staker | FeeSource::Tier => self
+                .schedule
+                .promotions
+                .iter()
+                .filter(|promotion| {
+                    (promotion.from_height..=promotion.until_height).contains(&block_height)
+                })
+                .map(|promotion| promotion.discount)
+                .max()
+                .unwrap_or(Decimal::ZERO),
+        };
+
+        FeeDecision {
+            identity_address: worker.identity_address.clone(),
+            staker_fee: worker.fee,
+            source,
+            discount,
+            fee: fee
+                .checked_sub(discount)
+                .unwrap_or(Decimal::ZERO)
+                .max(Decimal::ZERO),
+            average_balance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::coinstaker::{FeePromotion, FeeTier};
+
+    use super::*;
+
+    const ALICE: &str = "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU";
+    const BOB: &str = "iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi";
+
+    fn worker(address: &str, balance_in_vrsc: u64, blocks: u64) -> Worker {
+        Worker {
+            identity_address: Address::from_str(address).unwrap(),
+            shares: Decimal::from(balance_in_vrsc * 100_000_000 * blocks),
+            fee: Decimal::new(5, 2),
+        }
+    }
+
+    fn schedule() -> FeeSchedule {
+        FeeSchedule {
+            tiers: vec![
+                FeeTier {
+                    min_balance: Amount::from_vrsc(10_000.0).unwrap(),
+                    fee: Decimal::new(1, 2),
+                },
+                FeeTier {
+                    min_balance: Amount::from_vrsc(1_000.0).unwrap(),
+                    fee: Decimal::new(3, 2),
+                },
+            ],
+            promotions: vec![
+                FeePromotion {
+                    from_height: 100,
+                    until_height: 200,
+                    discount: Decimal::new(2, 2),
+                },
+                FeePromotion {
+                    from_height: 150,
+                    until_height: 250,
+                    discount: Decimal::new(4, 2),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn applies_the_highest_tier_of_the_average_balance() {
+        let fees = Fees::new(schedule(), HashMap::new(), 10);
+
+        let small = fees.decide(&worker(ALICE, 500, 10), 50);
+        assert_eq!(small.source, FeeSource::Staker);
+        assert_eq!(small.fee, Decimal::new(5, 2));
+
+        let medium = fees.decide(&worker(ALICE, 5_000, 10), 50);
+        assert_eq!(medium.source, FeeSource::Tier);
+        assert_eq!(medium.fee, Decimal::new(3, 2));
+        assert_eq!(medium.average_balance, Amount::from_vrsc(5_000.0).unwrap());
+
+        let large = fees.decide(&worker(ALICE, 20_000, 10), 50);
+        assert_eq!(large.fee, Decimal::new(1, 2));
+
+        // a tier never raises the fee a staker signed up with
+        let mut cheap = worker(ALICE, 5_000, 10);
+        cheap.fee = Decimal::new(2, 2);
+        assert_eq!(fees.decide(&cheap, 50).source, FeeSource::Staker);
+    }
+
+    #[test]
+    fn overrides_replace_tiers_and_promotions() {
+        let overrides = HashMap::from([(Address::from_str(BOB).unwrap(), Decimal::new(4, 2))]);
+        let fees = Fees::new(schedule(), overrides, 10);
+
+        let bob = fees.decide(&worker(BOB, 20_000, 10), 160);
+        assert_eq!(bob.source, FeeSource::Override);
+        assert_eq!(bob.discount, Decimal::ZERO);
+        assert_eq!(bob.fee, Decimal::new(4, 2));
+    }
+
+    #[test]
+    fn applies_the_highest_promotion_at_the_height_of_the_stake() {
+        let fees = Fees::new(schedule(), HashMap::new(), 10);
+
+        assert_eq!(
+            fees.decide(&worker(ALICE, 500, 10), 100).fee,
+            Decimal::new(3, 2)
+        );
+        assert_eq!(
+            fees.decide(&worker(ALICE, 500, 10), 175).fee,
+            Decimal::new(1, 2)
+        );
+        assert_eq!(
+            fees.decide(&worker(ALICE, 500, 10), 251).fee,
+            Decimal::new(5, 2)
+        );
+
+        // a discount doesn't make the fee negative
+        assert_eq!(
+            fees.decide(&worker(ALICE, 20_000, 10), 175).fee,
+            Decimal::ZERO
+        );
+    }
+}
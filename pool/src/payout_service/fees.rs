@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error, info};
+use vrsc_rpc::{
+    bitcoin::Txid,
+    client::{Client, SendCurrencyOutput},
+    json::vrsc::Address,
+};
+
+use crate::{
+    coinstaker::{ChainConfig, FeeSweepConfig},
+    database,
+};
+
+use super::{send_payment, whitelist::ensure_whitelisted};
+
+/// Periodically moves the fees that the pool earned to a whitelisted cold wallet.
+pub struct FeeSweeper {
+    database: PgPool,
+    config: FeeSweepConfig,
+    whitelist: Vec<Address>,
+    chain_id: Address,
+    pool_address: Address,
+    chain_config: ChainConfig,
+}
+
+impl FeeSweeper {
+    pub fn new(
+        config: FeeSweepConfig,
+        whitelist: Vec<Address>,
+        database: PgPool,
+        chain_id: Address,
+        pool_address: Address,
+        chain_config: ChainConfig,
+    ) -> Self {
+        Self {
+            database,
+            config,
+            whitelist,
+            chain_id,
+            pool_address,
+            chain_config,
+        }
+    }
+
+    async fn sweep_fees(&self) -> Result<Option<Txid>> {
+        let unswept = database::get_unswept_fees(&self.database, &self.chain_id).await?;
+
+        if unswept < self.config.min_amount {
+            debug!(%unswept, "not enough fees to sweep");
+
+            return Ok(None);
+        }
+
+        ensure_whitelisted(
+            &self.database,
+            &self.chain_id,
+            &self.whitelist,
+            &self.config.destination,
+        )
+        .await?;
+
+        let client: Client = (&self.chain_config).try_into()?;
+        let destination = self.config.destination.to_string();
+        let outputs = vec![SendCurrencyOutput::new(
+            None,
+            &unswept,
+            &destination,
+            None,
+            None,
+        )];
+
+        let Some(txid) = send_payment(outputs, &self.pool_address, &client).await? else {
+            return Ok(None);
+        };
+
+        if let Err(e) = database::store_fee_sweep(
+            &self.database,
+            &self.chain_id,
+            &txid,
+            &self.config.destination,
+            unswept,
+        )
+        .await
+        {
+            error!(%txid, amount = %unswept, ?e);
+
+            bail!("A fee sweep was sent but the database failed to update.");
+        }
+
+        info!(%txid, amount = %unswept, %destination, "swept pool fees");
+
+        Ok(Some(txid))
+    }
+
+    async fn keep_sweeping_fees(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.sweep_fees().await {
+                error!(error = ?e, "Failed to sweep fees");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for FeeSweeper {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_sweeping_fees(&subsys).await
+    }
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use tracing::{info, warn};
+use vrsc_rpc::{
+    bitcoin::Txid,
+    client::{Client, RpcApi, SendCurrencyOutput},
+    json::vrsc::{Address, Amount},
+};
+
+use crate::database;
+
+use super::{
+    service::wait_for_sendcurrency_finish, store_sent_payment, PaymentItem, PayoutMember, TxFee,
+};
+
+/// How long an open journal entry that the daemon knows nothing about may still turn into a
+/// payment, for example when the process died while the daemon was still accepting the
+/// sendcurrency call.
+const UNKNOWN_OUTCOME_GRACE_PERIOD: Duration = Duration::from_secs(600);
+/// How far the clocks of the database and the daemon can be apart.
+const CLOCK_SKEW: Duration = Duration::from_secs(60);
+/// The number of recent wallet transactions that are searched for the payment of a journal entry.
+const WALLET_TRANSACTIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(
+    type_name = "payment_journal_status",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum JournalStatus {
+    /// The payment may or may not have been sent.
+    Open,
+    Sent,
+    Failed,
+}
+
+/// The intent to pay a set of payout members, recorded before the payment is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub currency_address: Address,
+    /// The sendcurrency operation, `None` until the daemon accepted it.
+    pub opid: Option<String>,
+    pub members: Vec<PayoutMember>,
+    /// The items that the payment sends, as they were prepared: to their destination, after the
+    /// tx fee and with the currency they are converted into. Empty for entries that were opened
+    /// before the items were journaled.
+    pub items: Vec<PaymentItem>,
+    /// The tx fee of the payment, which decides the amounts it sends if the stakers bear it.
+    pub tx_fee: TxFee,
+    pub created_at: u64,
+}
+
+/// What happened to the payment of an open journal entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Sent(Txid),
+    NotSent,
+    /// The payment can still be sent, so the entry stays open.
+    Unknown,
+}
+
+/// A transaction as listed by `listtransactions`.
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct WalletTransaction {
+    pub address: Option<String>,
+    pub category: String,
+    pub amount: f64,
+    pub txid: Txid,
+    pub time: u64,
+}
+
+/// Sends a payment of the prepared `items` to `members` and returns the id of its journal entry,
+/// with the txid of the payment or `None` if the daemon failed to send it.
+///
+/// The intent is journaled before the payment is sent, so that a payment is never sent twice if
+/// the process dies before it is stored. The caller closes the journal entry of a sent payment
+/// in the transaction that stores it.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_journaled_payment<'a>(
+    pool: &PgPool,
+    currency_address: &Address,
+    members: &[PayoutMember],
+    items: &[PaymentItem],
+    tx_fee: TxFee,
+    outputs: Vec<SendCurrencyOutput<'a>>,
+    pool_address: &Address,
+    client: &Client,
+) -> Result<(u64, Option<Txid>)> {
+    let journal_id =
+        database::open_payment_journal_entry(pool, currency_address, members, items, tx_fee)
+            .await?;
+
+    // if this fails, the entry stays open and is reconciled against the wallet later, as the
+    // daemon could have sent the payment anyway
+    let opid = client.send_currency(&pool_address.to_string(), outputs, None, None)?;
+    database::set_payment_journal_opid(pool, journal_id, &opid).await?;
+
+    let txid = wait_for_sendcurrency_finish(client, &opid).await?;
+    if txid.is_none() {
+        let mut conn = pool.acquire().await?;
+        database::close_payment_journal_entry(&mut conn, journal_id, JournalStatus::Failed, None)
+            .await?;
+    }
+
+    Ok((journal_id, txid))
+}
+
+/// Resolves the open journal entries of a chain: payments that were sent are stored, entries of
+/// payments that were not sent are closed.
+///
+/// Returns `false` if the outcome of an entry is not known yet, in which case no new payments
+/// should be sent.
+pub async fn reconcile_payment_journal(
+    pool: &PgPool,
+    client: &Client,
+    currency_address: &Address,
+) -> Result<bool> {
+    let entries = database::get_open_payment_journal_entries(pool, currency_address).await?;
+    if entries.is_empty() {
+        return Ok(true);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut wallet_transactions: Option<Vec<WalletTransaction>> = None;
+    let payout_addresses = database::get_payout_addresses(pool, currency_address).await?;
+    let mut resolved = true;
+
+    for entry in entries {
+        let outcome = match operation_outcome(client, &entry)? {
+            Some(outcome) => outcome,
+            None => {
+                // the daemon doesn't know the operation (anymore), so look for the payment in
+                // the wallet
+                if wallet_transactions.is_none() {
+                    wallet_transactions = Some(client.call::<Vec<WalletTransaction>>(
+                        "listtransactions",
+                        &[json!("*"), json!(WALLET_TRANSACTIONS)],
+                    )?);
+                }
+
+                match find_payment(
+                    &entry_items(&entry, &payout_addresses)?,
+                    entry.created_at,
+                    wallet_transactions.as_deref().unwrap_or_default(),
+                ) {
+                    Some(txid) => Outcome::Sent(txid),
+                    None if now.saturating_sub(entry.created_at)
+                        > UNKNOWN_OUTCOME_GRACE_PERIOD.as_secs() =>
+                    {
+                        Outcome::NotSent
+                    }
+                    None => Outcome::Unknown,
+                }
+            }
+        };
+
+        match outcome {
+            Outcome::Sent(txid) => {
+                let items = entry_items(&entry, &payout_addresses)?;
+                let mut tx = pool.begin().await?;
+
+                store_sent_payment(
+                    &mut tx,
+                    currency_address,
+                    txid,
+                    &entry.members,
+                    &items,
+                    entry.tx_fee,
+                )
+                .await?;
+                database::close_payment_journal_entry(
+                    &mut tx,
+                    entry.id,
+                    JournalStatus::Sent,
+                    Some(&txid),
+                )
+                .await?;
+
+                tx.commit().await?;
+
+                warn!(
+                    journal_id = entry.id,
+                    %txid,
+                    n_stakers = items.len(),
+                    "stored a payment that was sent before the pool stopped"
+                );
+            }
+            Outcome::NotSent => {
+                let mut conn = pool.acquire().await?;
+                database::close_payment_journal_entry(
+                    &mut conn,
+                    entry.id,
+                    JournalStatus::Failed,
+                    None,
+                )
+                .await?;
+
+                info!(
+                    journal_id = entry.id,
+                    "payment of open journal entry was not sent"
+                );
+            }
+            Outcome::Unknown => {
+                warn!(?entry, "outcome of open journal entry is not known yet");
+
+                resolved = false;
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// The outcome of the sendcurrency operation of a journal entry, or `None` if the daemon doesn't
+/// know the operation.
+fn operation_outcome(client: &Client, entry: &JournalEntry) -> Result<Option<Outcome>> {
+    let Some(opid) = &entry.opid else {
+        return Ok(None);
+    };
+
+    let operation_status = client.z_get_operation_status(vec![opid.as_str()])?;
+    let Some(Some(opstatus)) = operation_status.first() else {
+        return Ok(None);
+    };
+
+    if ["queued", "executing"].contains(&opstatus.status.as_ref()) {
+        return Ok(Some(Outcome::Unknown));
+    }
+
+    Ok(Some(match &opstatus.result {
+        Some(txid) => Outcome::Sent(txid.txid),
+        None => Outcome::NotSent,
+    }))
+}
+
+/// The items that the payment of a journal entry sends.
+///
+/// Entries that were opened before the items were journaled are rebuilt from their members: the
+/// items of cold stakers were sent to their current payout address, in the staked currency, and
+/// bore the tx fee if the pool didn't pay it.
+fn entry_items(
+    entry: &JournalEntry,
+    payout_addresses: &HashMap<Address, Address>,
+) -> Result<Vec<PaymentItem>> {
+    if !entry.items.is_empty() {
+        return Ok(entry.items.clone());
+    }
+
+    let mut items = PaymentItem::aggregate(&entry.members);
+    PaymentItem::set_payout_addresses(&mut items, payout_addresses);
+    entry.tx_fee.deduct_from(&mut items)?;
+
+    Ok(items)
+}
+
+/// Finds the transaction in the wallet that sent exactly `items`, after the journal entry was
+/// created at `created_at`.
+///
+/// An item that was converted is not sent to its destination but into the conversion, so only
+/// its amount is matched.
+fn find_payment(
+    items: &[PaymentItem],
+    created_at: u64,
+    transactions: &[WalletTransaction],
+) -> Option<Txid> {
+    let since = created_at.saturating_sub(CLOCK_SKEW.as_secs());
+
+    let mut txids: Vec<Txid> = vec![];
+    for transaction in transactions {
+        if transaction.category == "send"
+            && transaction.time >= since
+            && !txids.contains(&transaction.txid)
+        {
+            txids.push(transaction.txid);
+        }
+    }
+
+    txids.into_iter().find(|txid| {
+        let sends = transactions
+            .iter()
+            .filter(|transaction| transaction.txid == *txid && transaction.category == "send")
+            .collect::<Vec<_>>();
+
+        sends.len() == items.len()
+            && items.iter().all(|item| {
+                let destination = item.destination().to_string();
+
+                sends.iter().any(|send| {
+                    (item.payout_currency.is_some()
+                        || send.address.as_deref() == Some(destination.as_str()))
+                        && Amount::from_vrsc(-send.amount).ok() == Some(item.amount)
+                })
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+    use vrsc_rpc::bitcoin::BlockHash;
+
+    use super::*;
+
+    fn member(identity_address: &Address, reward: u64) -> PayoutMember {
+        PayoutMember::new(
+            Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            BlockHash::from_str("00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0")
+                .unwrap(),
+            100,
+            identity_address.clone(),
+            Amount::from_sat(reward),
+            Decimal::ONE,
+            Amount::ZERO,
+        )
+    }
+
+    fn send(address: &Address, amount: f64, txid: Txid, time: u64) -> WalletTransaction {
+        WalletTransaction {
+            address: Some(address.to_string()),
+            category: String::from("send"),
+            amount: -amount,
+            txid,
+            time,
+        }
+    }
+
+    fn find(
+        entry: &JournalEntry,
+        payout_addresses: &HashMap<Address, Address>,
+        transactions: &[WalletTransaction],
+    ) -> Option<Txid> {
+        find_payment(
+            &entry_items(entry, payout_addresses).unwrap(),
+            entry.created_at,
+            transactions,
+        )
+    }
+
+    #[test]
+    fn finds_the_payment_of_a_journal_entry_in_the_wallet() {
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let payment =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+        let other =
+            Txid::from_str("0ba4a4d4e6e8c8a94fd4a8d8e8e9d7d1e2f2a3b4c5d6e7f8091a2b3c4d5e6f70")
+                .unwrap();
+
+        let entry = JournalEntry {
+            id: 1,
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            opid: None,
+            members: vec![member(&alice, 150_000_000), member(&bob, 50_000_000)],
+            items: vec![],
+            tx_fee: TxFee::default(),
+            created_at: 1_000_000,
+        };
+
+        // an older payment of the same amounts is not the payment of this entry
+        let transactions = vec![
+            send(&alice, 1.5, other, 900_000),
+            send(&bob, 0.5, other, 900_000),
+        ];
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), None);
+
+        // a payment that pays only some of the stakers is not the payment of this entry
+        let transactions = vec![send(&alice, 1.5, payment, 1_000_010)];
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), None);
+
+        let transactions = vec![
+            send(&alice, 1.5, other, 900_000),
+            send(&bob, 0.5, other, 900_000),
+            send(&alice, 1.5, payment, 1_000_010),
+            send(&bob, 0.5, payment, 1_000_010),
+        ];
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), Some(payment));
+    }
+
+    #[test]
+    fn finds_the_payment_of_which_the_stakers_bore_the_tx_fee() {
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let payment =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let mut entry = JournalEntry {
+            id: 1,
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            opid: None,
+            members: vec![member(&alice, 150_000_000), member(&bob, 50_000_000)],
+            items: vec![],
+            tx_fee: TxFee {
+                amount: Amount::from_sat(20_000),
+                paid_by_pool: true,
+            },
+            created_at: 1_000_000,
+        };
+        let transactions = vec![
+            send(&alice, 1.49985, payment, 1_000_010),
+            send(&bob, 0.49995, payment, 1_000_010),
+        ];
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), None);
+
+        entry.tx_fee.paid_by_pool = false;
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), Some(payment));
+    }
+
+    #[test]
+    fn finds_the_payment_to_the_payout_address_of_a_cold_staker() {
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let payout_address = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let payment =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let entry = JournalEntry {
+            id: 1,
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            opid: None,
+            members: vec![member(&alice, 150_000_000)],
+            items: vec![],
+            tx_fee: TxFee::default(),
+            created_at: 1_000_000,
+        };
+        let transactions = vec![send(&payout_address, 1.5, payment, 1_000_010)];
+
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), None);
+        assert_eq!(
+            find(
+                &entry,
+                &HashMap::from([(alice, payout_address)]),
+                &transactions
+            ),
+            Some(payment)
+        );
+    }
+
+    #[test]
+    fn finds_the_payment_of_the_journaled_items() {
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        let payout_address = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let conversion = Address::from_str("RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK").unwrap();
+        let payment =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        let members = vec![member(&alice, 150_000_000), member(&bob, 50_000_000)];
+        let mut items = PaymentItem::aggregate(&members);
+        items[0].payout_address = Some(payout_address.clone());
+        items[1].payout_currency =
+            Some(Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap());
+        let entry = JournalEntry {
+            id: 1,
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            opid: None,
+            members,
+            items,
+            tx_fee: TxFee::default(),
+            created_at: 1_000_000,
+        };
+
+        // the converted item is sent into the conversion instead of to bob, and the payout
+        // address of alice that is registered now doesn't matter
+        let transactions = vec![
+            send(&payout_address, 1.5, payment, 1_000_010),
+            send(&conversion, 0.5, payment, 1_000_010),
+        ];
+        assert_eq!(
+            find(
+                &entry,
+                &HashMap::from([(alice.clone(), bob.clone())]),
+                &transactions
+            ),
+            Some(payment)
+        );
+
+        let transactions = vec![
+            send(&alice, 1.5, payment, 1_000_010),
+            send(&conversion, 0.5, payment, 1_000_010),
+        ];
+        assert_eq!(find(&entry, &HashMap::new(), &transactions), None);
+    }
+}
@@ -1,7 +1,47 @@
+mod batching;
+mod conversion;
+mod fee_schedule;
+mod fees;
+mod journal;
 mod payout;
+mod reconciliation;
 mod service;
+mod sweep;
+mod trigger;
+mod whitelist;
 
+pub use batching::BatchSizer;
+pub use batching::PaymentBatch;
+pub use conversion::Converter;
+pub use fee_schedule::FeeDecision;
+pub use fee_schedule::FeeSource;
+pub use fee_schedule::Fees;
+pub use fees::FeeSweeper;
+pub use journal::reconcile_payment_journal;
+pub use journal::send_journaled_payment;
+pub use journal::JournalEntry;
+pub use journal::JournalStatus;
+pub use payout::Liabilities;
+pub use payout::ManualPayment;
+pub use payout::MissingPayment;
+pub use payout::Payment;
+pub use payout::PaymentItem;
+pub use payout::PaymentStatus;
 pub use payout::Payout;
 pub use payout::PayoutMember;
+pub use payout::PayoutShadowDiff;
+pub use payout::PayoutSummary;
+pub use payout::Reconciliation;
+pub use payout::StakerLiability;
+pub use payout::TxFee;
+pub use payout::UnmatchedSend;
 pub use payout::Worker;
+pub use reconciliation::LatestReconciliation;
+pub use reconciliation::Reconciler;
+pub use service::prepare_payment;
+pub use service::send_payment;
+pub use service::store_sent_payment;
 pub use service::Service;
+pub use sweep::Sweeper;
+pub use trigger::PayoutTrigger;
+pub use whitelist::ensure_whitelisted;
@@ -1,13 +1,21 @@
-use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
 use rust_decimal::{prelude::FromPrimitive, prelude::ToPrimitive, Decimal, RoundingStrategy};
-use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 use vrsc_rpc::{
-    bitcoin::{BlockHash, Txid},
-    json::vrsc::{util::amount::serde::as_sat, Address, Amount},
+    bitcoin::BlockHash,
+    json::vrsc::{Address, Amount},
 };
 
-use crate::coinstaker::constants::Stake;
+use crate::coinstaker::{constants::Stake, DUST_THRESHOLD_IN_SATS};
+
+use super::fee_schedule::{FeeDecision, Fees};
+
+pub use poollib::api::{
+    Liabilities, ManualPayment, MissingPayment, Payment, PaymentStatus, PayoutMember,
+    PayoutShadowDiff, PayoutSummary, Reconciliation, StakerLiability, UnmatchedSend,
+};
 
 pub struct Payout {
     /// Currency for which the payout is generated
@@ -24,18 +32,29 @@ pub struct Payout {
     pub paid: Amount,
     /// All the workers that participated in this round
     pub members: Vec<PayoutMember>,
+    /// The fee that was applied to every member and why
+    pub fee_history: Vec<FeeDecision>,
+    /// The pool primary address with which the pool signed the stake, `None` if the pool staked
+    /// it itself
+    pub primary_address: Option<Address>,
 }
 
 impl Payout {
     /// A payout is the translation from work that workers put in to how much every worker
     /// gets from the staked amount, dividing the pie into every worker's fair share.
     ///
-    /// First we need to determine the fee of every staker. Since the pool can set a
-    /// fee discount and every staker has an individual fee percentage, it is different
-    /// for every staker.
-    ///
+    /// First we need to determine the fee of every staker. Every staker has an individual fee
+    /// percentage, which `fees` can replace by an override or a tier and lower by a promotion,
+    /// so it is different for every staker.
     ///
-    pub fn new(stake: &Stake, workers: Vec<Worker>, pool_fee_discount: Decimal) -> Result<Self> {
+    /// `forfeited_shares` is the work of stakers that left the pool and forfeited their work.
+    /// It counts towards the total work, but its part of the stake is kept by the pool.
+    pub fn new(
+        stake: &Stake,
+        workers: Vec<Worker>,
+        forfeited_shares: Decimal,
+        fees: &Fees,
+    ) -> Result<Self> {
         // get work and fee by round
         let amount = Decimal::from_f64(stake.amount.as_vrsc())
             .context("Could not create Decimal from stake amount")?;
@@ -43,19 +62,19 @@ impl Payout {
 
         let sum_of_shares = workers
             .iter()
-            .fold(Decimal::ZERO, |acc, member| acc + member.shares);
+            .fold(forfeited_shares, |acc, member| acc + member.shares);
 
         let mut payout_members = vec![];
+        let mut fee_history = vec![];
         for worker in workers {
             if worker.shares == Decimal::ZERO {
                 debug!("a worker with 0 shares was included");
                 continue;
             }
 
-            let worker_fee = worker
-                .fee
-                .checked_sub(pool_fee_discount)
-                .unwrap_or(Decimal::ZERO);
+            let decision = fees.decide(&worker, stake.block_height);
+            let worker_fee = decision.fee;
+            fee_history.push(decision);
 
             let portion = sum_of_shares / worker.shares;
 
@@ -119,48 +138,144 @@ impl Payout {
             fee: pool_fee,
             paid: reward_sum,
             members: payout_members,
+            fee_history,
+            primary_address: None,
         })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct PayoutMember {
-    pub currency_address: Address,
-    pub block_hash: BlockHash,
-    pub block_height: u64,
+/// The total reward of one staker in a payment, which becomes a single output of the payment
+/// transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentItem {
     pub identity_address: Address,
-    #[serde(with = "as_sat")]
-    pub reward: Amount,
-    pub shares: Decimal,
-    // fee is in basis points: 5% should be entered as 0.05
-    #[serde(with = "as_sat")]
-    pub fee: Amount,
-    pub txid: Option<Txid>,
+    pub amount: Amount,
+    /// The block hashes of the payout members that are covered by this item.
+    pub block_hashes: Vec<BlockHash>,
+    /// The currency that the staker wants the amount to be converted into, `None` to be paid in
+    /// the staked currency.
+    pub payout_currency: Option<Address>,
+    /// The address that the amount is sent to instead of the staker itself, for cold stakers.
+    pub payout_address: Option<Address>,
 }
 
-impl PayoutMember {
-    fn new(
-        currency_address: Address,
-        block_hash: BlockHash,
-        block_height: u64,
-        identity_address: Address,
-        reward: Amount,
-        shares: Decimal,
-        fee: Amount,
-    ) -> Self {
-        Self {
-            currency_address,
-            block_hash,
-            block_height,
-            identity_address,
-            reward,
-            shares,
-            fee,
-            txid: None,
+impl PaymentItem {
+    /// Aggregates the rounds of unpaid payout members into one item per staker.
+    ///
+    /// Items are ordered by identity address.
+    pub fn aggregate(payout_members: &[PayoutMember]) -> Vec<PaymentItem> {
+        let mut items: Vec<PaymentItem> = vec![];
+
+        for member in payout_members {
+            if let Some(item) = items
+                .iter_mut()
+                .find(|item| item.identity_address == member.identity_address)
+            {
+                item.amount += member.reward;
+                item.block_hashes.push(member.block_hash);
+            } else {
+                items.push(PaymentItem {
+                    identity_address: member.identity_address.clone(),
+                    amount: member.reward,
+                    block_hashes: vec![member.block_hash],
+                    payout_currency: None,
+                    payout_address: None,
+                });
+            }
+        }
+
+        items.sort_by_key(|item| item.identity_address.to_string());
+
+        items
+    }
+
+    /// Sets the payout currency of every item of which the staker chose one.
+    pub fn set_payout_currencies(
+        items: &mut [PaymentItem],
+        payout_currencies: &HashMap<Address, Address>,
+    ) {
+        for item in items {
+            item.payout_currency = payout_currencies.get(&item.identity_address).cloned();
+        }
+    }
+
+    /// Sets the address that the amount of an item is sent to, for the stakers that registered
+    /// a payout address.
+    pub fn set_payout_addresses(
+        items: &mut [PaymentItem],
+        payout_addresses: &HashMap<Address, Address>,
+    ) {
+        for item in items {
+            item.payout_address = payout_addresses.get(&item.identity_address).cloned();
         }
     }
+
+    /// The address that the amount of this item is sent to.
+    pub fn destination(&self) -> &Address {
+        self.payout_address
+            .as_ref()
+            .unwrap_or(&self.identity_address)
+    }
+}
+
+/// The network fee of a payment transaction, and who bears it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxFee {
+    pub amount: Amount,
+    /// The fee is paid out of the pool fees. If not, it is deducted from the items of the
+    /// payment, in proportion to their amounts.
+    pub paid_by_pool: bool,
 }
 
+impl TxFee {
+    /// The smallest balance that can be paid: the dust threshold, plus the fee if the stakers
+    /// bear it, so that the output of the balance can still be sent after the fee is deducted.
+    pub fn min_payable(&self) -> Amount {
+        let mut min_payable = Amount::from_sat(DUST_THRESHOLD_IN_SATS);
+        if !self.paid_by_pool {
+            min_payable += self.amount;
+        }
+
+        min_payable
+    }
+
+    /// Deducts the fee from `items` if the stakers bear it. The sats that can't be split evenly
+    /// are deducted from the first items.
+    pub fn deduct_from(&self, items: &mut [PaymentItem]) -> Result<()> {
+        if self.paid_by_pool || self.amount == Amount::ZERO {
+            return Ok(());
+        }
+
+        let total = items.iter().map(|item| item.amount.as_sat()).sum::<u64>();
+        if total <= self.amount.as_sat() {
+            bail!(
+                "the payment of {} does not cover the tx fee of {}",
+                Amount::from_sat(total),
+                self.amount
+            );
+        }
+
+        let fee = self.amount.as_sat() as u128;
+        let mut deducted = 0;
+        for item in items.iter_mut() {
+            let share = (fee * item.amount.as_sat() as u128 / total as u128) as u64;
+            item.amount = Amount::from_sat(item.amount.as_sat() - share);
+            deducted += share;
+        }
+
+        // a share is rounded down and smaller than its item, so every item can take a sat more
+        for item in items
+            .iter_mut()
+            .take((self.amount.as_sat() - deducted) as usize)
+        {
+            item.amount = Amount::from_sat(item.amount.as_sat() - 1);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Worker {
     pub identity_address: Address,
     pub shares: Decimal,
@@ -168,10 +283,69 @@ pub struct Worker {
     pub fee: Decimal,
 }
 
+impl Worker {
+    /// Combines the workers of two rounds, adding up the shares of workers that are in both.
+    pub fn merge(workers: Vec<Worker>, other: Vec<Worker>) -> Vec<Worker> {
+        let mut merged: Vec<Worker> = workers;
+
+        for worker in other {
+            if let Some(existing) = merged
+                .iter_mut()
+                .find(|existing| existing.identity_address == worker.identity_address)
+            {
+                existing.shares += worker.shares;
+            } else {
+                merged.push(worker);
+            }
+        }
+
+        merged
+    }
+
+    /// Takes the last `window` shares from the workers and forfeited shares of rounds, newest
+    /// round first. The oldest round that is needed to fill the window only counts in part.
+    pub fn pplns(rounds: Vec<(Vec<Worker>, Decimal)>, window: Decimal) -> (Vec<Worker>, Decimal) {
+        let mut workers = vec![];
+        let mut forfeited_shares = Decimal::ZERO;
+        let mut remaining = window;
+
+        for (round_workers, round_forfeited) in rounds {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+
+            let total = round_workers
+                .iter()
+                .fold(round_forfeited, |acc, worker| acc + worker.shares);
+            if total.is_zero() {
+                continue;
+            }
+
+            let part = (remaining / total).min(Decimal::ONE);
+            remaining -= total * part;
+
+            forfeited_shares += round_forfeited * part;
+            workers = Worker::merge(
+                workers,
+                round_workers
+                    .into_iter()
+                    .map(|worker| Worker {
+                        shares: worker.shares * part,
+                        ..worker
+                    })
+                    .collect(),
+            );
+        }
+
+        (workers, forfeited_shares)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use sqlx::PgPool;
     use std::str::FromStr;
+    use vrsc_rpc::bitcoin::Txid;
 
     use crate::database::{self, get_unpaid_payout_members};
 
@@ -181,7 +355,7 @@ mod tests {
     const _VRSCTEST: &str = "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq";
     const ALICE: &str = "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU";
     const BOB: &str = "iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi";
-    // const CHARLIE: &str = "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK";
+    const CHARLIE: &str = "RDebEHgiTFDRDUN5Uisx7ntUuRdRJHt6SK";
     // const DIRK: &str = "RSTWA7QcQaEbhS4iJha2p1b5eYvUPpVXGP";
     // const EMILY: &str = "RRVdSds5Zck6YnhYgchL8qCKqARhob64vk";
     const _POOL_ADDRESS: &str = "iBnKXQnD1BFyvE8V4UVr4UKQz8h7FqfVu9";
@@ -204,7 +378,7 @@ mod tests {
             fee: Decimal::from_f32(0.01).unwrap(),
         });
 
-        let payout = Payout::new(&stake, stake_members, Decimal::ZERO).unwrap();
+        let payout = Payout::new(&stake, stake_members, Decimal::ZERO, &Fees::default()).unwrap();
 
         let mut to_test_against = vec![];
 
@@ -220,6 +394,8 @@ mod tests {
             shares: Decimal::from_f64(123.456).unwrap(),
             fee: Amount::from_sat(6_001_000),
             txid: None,
+            created_at: 0,
+            updated_at: 0,
         });
 
         assert_eq!(payout.members, to_test_against);
@@ -251,7 +427,7 @@ mod tests {
             fee: Decimal::from_f32(0.01).unwrap(),
         });
 
-        let payout = Payout::new(&stake, stake_members, Decimal::ZERO).unwrap();
+        let payout = Payout::new(&stake, stake_members, Decimal::ZERO, &Fees::default()).unwrap();
 
         let alice = PayoutMember {
             currency_address: Address::from_str(_VRSC).unwrap(),
@@ -265,6 +441,8 @@ mod tests {
             shares: Decimal::from_f64(5.0).unwrap(),
             fee: Amount::from_sat(3_000_500),
             txid: None,
+            created_at: 0,
+            updated_at: 0,
         };
 
         assert!(payout.members.contains(&alice));
@@ -281,6 +459,8 @@ mod tests {
             shares: Decimal::from_f64(5.0).unwrap(),
             fee: Amount::from_sat(3_000_500),
             txid: None,
+            created_at: 0,
+            updated_at: 0,
         };
 
         assert!(payout.members.contains(&bob));
@@ -288,6 +468,179 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn forfeited_shares_go_to_pool() {
+        let stake = Stake::new(
+            &Address::from_str(_VRSC).unwrap(),
+            &BlockHash::from_str(
+                "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0",
+            )
+            .unwrap(),
+            513251,
+            &Address::from_str(ALICE).unwrap(),
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap(),
+            0,
+            Amount::from_sat(600_000_000),
+            crate::coinstaker::constants::StakeStatus::Matured,
+            Amount::from_sat(600_000_000),
+        );
+
+        let workers = vec![Worker {
+            identity_address: Address::from_str(ALICE).unwrap(),
+            shares: Decimal::from(50),
+            fee: Decimal::ZERO,
+        }];
+
+        let payout = Payout::new(&stake, workers, Decimal::from(50), &Fees::default()).unwrap();
+
+        assert_eq!(payout.members.len(), 1);
+        assert_eq!(payout.members[0].reward, Amount::from_sat(300_000_000));
+        assert_eq!(payout.paid, Amount::from_sat(300_000_000));
+        assert_eq!(payout.fee, Amount::from_sat(300_000_000));
+        assert_eq!(payout.total_work, Decimal::from(100));
+    }
+
+    #[test]
+    fn workers_of_rounds_are_merged() {
+        let worker = |identity_address: &str, shares: i64| Worker {
+            identity_address: Address::from_str(identity_address).unwrap(),
+            shares: Decimal::from(shares),
+            fee: Decimal::ZERO,
+        };
+
+        let merged = Worker::merge(
+            vec![worker(ALICE, 100), worker(BOB, 50)],
+            vec![worker(ALICE, 25), worker(CHARLIE, 10)],
+        );
+
+        assert_eq!(
+            merged
+                .iter()
+                .map(|worker| (worker.identity_address.to_string(), worker.shares))
+                .collect::<Vec<_>>(),
+            vec![
+                (ALICE.to_string(), Decimal::from(125)),
+                (BOB.to_string(), Decimal::from(50)),
+                (CHARLIE.to_string(), Decimal::from(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pplns_takes_the_last_shares_across_rounds() {
+        let worker = |identity_address: &str, shares: i64| Worker {
+            identity_address: Address::from_str(identity_address).unwrap(),
+            shares: Decimal::from(shares),
+            fee: Decimal::ZERO,
+        };
+
+        let rounds = vec![
+            (vec![worker(ALICE, 60)], Decimal::from(40)),
+            (vec![worker(ALICE, 100), worker(BOB, 100)], Decimal::ZERO),
+            (vec![worker(CHARLIE, 1000)], Decimal::ZERO),
+        ];
+
+        // the second round fills the window for half
+        let (workers, forfeited_shares) = Worker::pplns(rounds.clone(), Decimal::from(200));
+        assert_eq!(
+            workers
+                .iter()
+                .map(|worker| (worker.identity_address.to_string(), worker.shares))
+                .collect::<Vec<_>>(),
+            vec![
+                (ALICE.to_string(), Decimal::from(110)),
+                (BOB.to_string(), Decimal::from(50)),
+            ]
+        );
+        assert_eq!(forfeited_shares, Decimal::from(40));
+
+        // a window that is larger than all rounds takes all shares
+        let (workers, _) = Worker::pplns(rounds, Decimal::from(10_000));
+        assert_eq!(
+            workers.iter().map(|worker| worker.shares).sum::<Decimal>(),
+            Decimal::from(1260)
+        );
+    }
+
+    #[test]
+    fn payment_items_are_aggregated_per_staker() {
+        let member = |identity_address: &str, block_hash: &str, reward: u64| {
+            PayoutMember::new(
+                Address::from_str(_VRSC).unwrap(),
+                BlockHash::from_str(block_hash).unwrap(),
+                1,
+                Address::from_str(identity_address).unwrap(),
+                Amount::from_sat(reward),
+                Decimal::ONE,
+                Amount::ZERO,
+            )
+        };
+
+        let first = "00000000000797cb62652d5901ab30e907f9a5657947eba15f1c9e7e19abe2e0";
+        let second = "0000000000078c4c4a09c3b2b4b37deb5a74ae4ac1ce7b4e21dd3cd9ac14c4b4";
+
+        let items = PaymentItem::aggregate(&[
+            member(BOB, first, 100),
+            member(ALICE, first, 200),
+            member(BOB, second, 300),
+        ]);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].identity_address, Address::from_str(ALICE).unwrap());
+        assert_eq!(items[0].amount, Amount::from_sat(200));
+        assert_eq!(items[1].identity_address, Address::from_str(BOB).unwrap());
+        assert_eq!(items[1].amount, Amount::from_sat(400));
+        assert_eq!(
+            items[1].block_hashes,
+            vec![
+                BlockHash::from_str(first).unwrap(),
+                BlockHash::from_str(second).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn tx_fee_is_deducted_in_proportion() {
+        let item = |identity_address: &str, amount: u64| PaymentItem {
+            identity_address: Address::from_str(identity_address).unwrap(),
+            amount: Amount::from_sat(amount),
+            block_hashes: vec![],
+            payout_currency: None,
+            payout_address: None,
+        };
+        let mut items = vec![
+            item(ALICE, 30_000),
+            item(BOB, 60_000),
+            item(CHARLIE, 10_001),
+        ];
+
+        let tx_fee = TxFee {
+            amount: Amount::from_sat(10_000),
+            paid_by_pool: false,
+        };
+        tx_fee.deduct_from(&mut items).unwrap();
+
+        let amounts = items
+            .iter()
+            .map(|item| item.amount.as_sat())
+            .collect::<Vec<_>>();
+        assert_eq!(amounts, vec![27_000, 54_000, 9_001]);
+        assert_eq!(amounts.iter().sum::<u64>(), 100_001 - 10_000);
+
+        // the pool pays the fee out of its own fees
+        let mut sponsored = vec![item(ALICE, 30_000)];
+        TxFee {
+            paid_by_pool: true,
+            ..tx_fee
+        }
+        .deduct_from(&mut sponsored)
+        .unwrap();
+        assert_eq!(sponsored[0].amount, Amount::from_sat(30_000));
+
+        assert!(tx_fee.deduct_from(&mut [item(ALICE, 10_000)]).is_err());
+    }
+
     #[sqlx::test(
         fixtures("stakes", "stakers", "payout_members"),
         migrator = "crate::MIGRATOR"
@@ -295,9 +648,15 @@ mod tests {
     async fn test_get_unpaid_payout_members(pool: PgPool) {
         let mut conn = pool.acquire().await.unwrap();
         let currency_address = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
-        let pms = get_unpaid_payout_members(&mut conn, &currency_address)
-            .await
-            .unwrap();
+        let pms = get_unpaid_payout_members(
+            &mut conn,
+            &currency_address,
+            None,
+            TxFee::default().min_payable(),
+            false,
+        )
+        .await
+        .unwrap();
 
         dbg!(pms);
         // need to insert a couple of payout_members that both fulfill and not
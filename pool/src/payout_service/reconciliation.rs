@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{error, info, warn};
+use vrsc_rpc::{
+    bitcoin::Txid,
+    client::{Client, RpcApi},
+    json::vrsc::{Address, Amount},
+};
+
+use crate::{
+    coinstaker::{ChainConfig, ReconciliationConfig},
+    database,
+};
+
+use super::{journal::WalletTransaction, MissingPayment, Reconciliation, UnmatchedSend};
+
+/// The latest reconciliation of a currency, `None` until the first one finished.
+///
+/// Clones share the same reconciliation.
+#[derive(Clone, Default)]
+pub struct LatestReconciliation(Arc<Mutex<Option<Reconciliation>>>);
+
+impl LatestReconciliation {
+    pub fn get(&self) -> Option<Reconciliation> {
+        self.0.lock().expect("reconciliation lock poisoned").clone()
+    }
+
+    fn set(&self, reconciliation: Reconciliation) {
+        *self.0.lock().expect("reconciliation lock poisoned") = Some(reconciliation);
+    }
+}
+
+/// Periodically compares the transactions that the wallet sent with the payments that the pool
+/// recorded.
+pub struct Reconciler {
+    database: PgPool,
+    config: ReconciliationConfig,
+    chain_id: Address,
+    pool_address: Address,
+    chain_config: ChainConfig,
+    latest: LatestReconciliation,
+}
+
+impl Reconciler {
+    pub fn new(
+        config: ReconciliationConfig,
+        database: PgPool,
+        chain_id: Address,
+        pool_address: Address,
+        chain_config: ChainConfig,
+        latest: LatestReconciliation,
+    ) -> Self {
+        Self {
+            database,
+            config,
+            chain_id,
+            pool_address,
+            chain_config,
+            latest,
+        }
+    }
+
+    async fn reconcile(&self) -> Result<Reconciliation> {
+        let client: Client = (&self.chain_config).try_into()?;
+
+        let transactions = client.call::<Vec<WalletTransaction>>(
+            "listtransactions",
+            &[json!("*"), json!(self.config.wallet_transactions)],
+        )?;
+        let recorded = database::get_recorded_txids(&self.database, &self.chain_id).await?;
+        let unmatched_sends = unmatched_sends(&transactions, &recorded, &self.pool_address);
+
+        let mut missing_payments = vec![];
+        for (txid, n_members, amount) in database::get_recent_payout_txids(
+            &self.database,
+            &self.chain_id,
+            self.config.lookback_in_days,
+        )
+        .await?
+        {
+            let reason = match client.get_transaction(&txid, None) {
+                Ok(transaction) if transaction.info.confirmations < 0 => {
+                    "the transaction is no longer in the chain"
+                }
+                Ok(_) => continue,
+                Err(_) => "the transaction is unknown to the wallet",
+            };
+
+            missing_payments.push(MissingPayment {
+                txid,
+                n_members,
+                amount,
+                reason: reason.to_string(),
+            });
+        }
+
+        Ok(Reconciliation {
+            currency_address: self.chain_id.clone(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            wallet_transactions: transactions.len() as u64,
+            unmatched_sends,
+            missing_payments,
+        })
+    }
+
+    async fn keep_reconciling(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            match self.reconcile().await {
+                Ok(reconciliation) => {
+                    for send in &reconciliation.unmatched_sends {
+                        warn!(
+                            txid = %send.txid,
+                            amount = %send.amount,
+                            addresses = ?send.addresses,
+                            "the wallet sent funds that the pool has no record of"
+                        );
+                    }
+                    for payment in &reconciliation.missing_payments {
+                        warn!(
+                            txid = %payment.txid,
+                            n_members = payment.n_members,
+                            amount = %payment.amount,
+                            "recorded payment is missing: {}",
+                            payment.reason
+                        );
+                    }
+                    info!(
+                        wallet_transactions = reconciliation.wallet_transactions,
+                        unmatched_sends = reconciliation.unmatched_sends.len(),
+                        missing_payments = reconciliation.missing_payments.len(),
+                        "reconciled the wallet with the recorded payments"
+                    );
+
+                    self.latest.set(reconciliation);
+                }
+                Err(e) => error!(error = ?e, "Failed to reconcile the wallet"),
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for Reconciler {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_reconciling(&subsys).await
+    }
+}
+
+/// Groups the sends of the wallet per transaction and returns the ones that the pool has no
+/// record of. Sends back to the pool address, like UTXO sweeps, don't move funds out of the pool
+/// and are left out.
+fn unmatched_sends(
+    transactions: &[WalletTransaction],
+    recorded: &HashSet<Txid>,
+    pool_address: &Address,
+) -> Vec<UnmatchedSend> {
+    let pool_address = pool_address.to_string();
+    let mut sends: HashMap<Txid, UnmatchedSend> = HashMap::new();
+
+    for transaction in transactions {
+        if transaction.category != "send"
+            || recorded.contains(&transaction.txid)
+            || transaction.address.as_ref() == Some(&pool_address)
+        {
+            continue;
+        }
+
+        let send = sends
+            .entry(transaction.txid)
+            .or_insert_with(|| UnmatchedSend {
+                txid: transaction.txid,
+                addresses: vec![],
+                amount: Amount::ZERO,
+                time: transaction.time,
+            });
+
+        if let Some(address) = &transaction.address {
+            if !send.addresses.contains(address) {
+                send.addresses.push(address.clone());
+            }
+        }
+        send.amount += Amount::from_vrsc(transaction.amount.abs()).unwrap_or(Amount::ZERO);
+    }
+
+    let mut sends = sends.into_values().collect::<Vec<_>>();
+    sends.sort_by_key(|send| (send.time, send.txid));
+
+    sends
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn transaction(category: &str, address: &str, amount: f64, txid: Txid) -> WalletTransaction {
+        WalletTransaction {
+            address: Some(address.to_string()),
+            category: category.to_string(),
+            amount,
+            txid,
+            time: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn only_sends_without_a_record_are_unmatched() {
+        let pool_address = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let alice = "iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU";
+        let bob = "iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi";
+        let payout =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+        let unknown =
+            Txid::from_str("f7b960c2b3e098926401ee3acafc3e1724fd0bbc180d72440229eb461d44976b")
+                .unwrap();
+        let sweep =
+            Txid::from_str("0000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+
+        let transactions = vec![
+            transaction("send", alice, -1.0, payout),
+            transaction("send", alice, -2.0, unknown),
+            transaction("send", bob, -0.5, unknown),
+            transaction("receive", alice, 3.0, unknown),
+            transaction("send", &pool_address.to_string(), -10.0, sweep),
+            transaction("receive", &pool_address.to_string(), 10.0, sweep),
+        ];
+
+        assert_eq!(
+            unmatched_sends(&transactions, &HashSet::from([payout]), &pool_address),
+            vec![UnmatchedSend {
+                txid: unknown,
+                addresses: vec![alice.to_string(), bob.to_string()],
+                amount: Amount::from_sat(250_000_000),
+                time: 1_700_000_000,
+            }]
+        );
+    }
+}
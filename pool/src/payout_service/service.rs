@@ -1,10 +1,13 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use rust_decimal::Decimal;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 use vrsc_rpc::{
     bitcoin::Txid,
     client::{Client, RpcApi, SendCurrencyOutput},
@@ -12,11 +15,22 @@ use vrsc_rpc::{
 };
 
 use crate::{
-    coinstaker::{ChainConfig, PayoutConfig as PayoutServiceConfig},
+    coinstaker::{
+        constants::Stake, halt::HaltFlag, pause::PauseFlag, ChainConfig,
+        PayoutConfig as PayoutServiceConfig, PayoutScheme,
+    },
     database::{self},
+    events::{EventBus, PoolEvent},
+    metrics::{Metric, Metrics},
 };
 
-use super::{payout::Payout, PayoutMember};
+use super::{
+    fee_schedule::Fees,
+    journal::{reconcile_payment_journal, send_journaled_payment, JournalStatus},
+    payout::{Payout, TxFee, Worker},
+    trigger::PayoutTrigger,
+    BatchSizer, Converter, Payment, PaymentBatch, PaymentItem, PaymentStatus, PayoutMember,
+};
 
 pub struct Service {
     database: PgPool,
@@ -24,6 +38,13 @@ pub struct Service {
     chain_id: Address,
     pool_address: Address,
     chain_config: ChainConfig,
+    tx_fee: TxFee,
+    events: EventBus,
+    batch_sizer: Mutex<BatchSizer>,
+    halt: HaltFlag,
+    pause: PauseFlag,
+    trigger: PayoutTrigger,
+    metrics: Metrics,
 }
 
 impl Service {
@@ -33,24 +54,68 @@ impl Service {
         chain_id: Address,
         pool_address: Address,
         chain_config: ChainConfig,
+        tx_fee: Amount,
+        events: EventBus,
     ) -> Self {
+        let batch_sizer = Mutex::new(BatchSizer::new(config.batching.as_ref()));
+        let tx_fee = TxFee {
+            amount: tx_fee,
+            paid_by_pool: config.pool_pays_tx_fee,
+        };
+
         Self {
             database,
             config,
             chain_id,
             pool_address,
             chain_config,
+            tx_fee,
+            events,
+            batch_sizer,
+            halt: HaltFlag::default(),
+            pause: PauseFlag::default(),
+            trigger: PayoutTrigger::default(),
+            metrics: Metrics::default(),
         }
     }
 
+    /// Pauses the creation and sending of payouts while the chain is halted.
+    pub fn with_halt_flag(mut self, halt: HaltFlag) -> Self {
+        self.halt = halt;
+
+        self
+    }
+
+    /// Pauses the creation and sending of payouts while an operator paused the chain.
+    pub fn with_pause_flag(mut self, pause: PauseFlag) -> Self {
+        self.pause = pause;
+
+        self
+    }
+
+    /// Lets an operator start a payout run right away.
+    pub fn with_payout_trigger(mut self, trigger: PayoutTrigger) -> Self {
+        self.trigger = trigger;
+
+        self
+    }
+
+    /// Pushes the created payouts and sent payments into the metrics that are exposed on `/metrics`.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+
+        self
+    }
+
     async fn new_payout(&self) -> Result<()> {
         // TODO can be null at first start
         let last_sync_id = database::get_payout_sync_id(&self.database, &self.chain_id)
             .await?
             .unwrap_or(0);
 
+        let mut conn = self.database.acquire().await?;
         let stakes = database::get_stakes_by_status(
-            &self.database,
+            &mut conn,
             &self.chain_id,
             crate::coinstaker::constants::StakeStatus::Matured,
             Some(last_sync_id),
@@ -58,13 +123,39 @@ impl Service {
         .await?;
 
         for stake in stakes {
-            let workers =
-                database::get_workers_by_round(&self.database, &self.chain_id, stake.block_height)
-                    .await?;
+            let round_id = database::get_round_id(&mut conn, &self.chain_id, &stake.block_hash)
+                .await?
+                .with_context(|| format!("stake {} has no round", stake.block_hash))?;
 
-            let mut tx = self.database.begin().await?;
+            let mut payout = self
+                .calculate_payout(&self.config.scheme, &stake, round_id)
+                .await?;
+            payout.primary_address = database::get_staker_primary_address(
+                &self.database,
+                &self.chain_id,
+                &stake.found_by,
+            )
+            .await?;
+            let payout_amount = payout.amount;
+
+            // the shadow scheme is only compared against, so it can't hold up the payouts
+            let shadow_payout = match &self.config.shadow_scheme {
+                Some(scheme) => match self.calculate_payout(scheme, &stake, round_id).await {
+                    Ok(shadow_payout) => Some(shadow_payout),
+                    Err(e) => {
+                        warn!(
+                            block_hash = %stake.block_hash,
+                            error = ?e,
+                            "could not calculate shadow payout"
+                        );
+
+                        None
+                    }
+                },
+                None => None,
+            };
 
-            let payout = Payout::new(&stake, workers, Decimal::ZERO)?;
+            let mut tx = self.database.begin().await?;
 
             database::store_payout(&mut tx, &payout).await?;
 
@@ -72,58 +163,453 @@ impl Service {
                 database::store_payout_member(&mut tx, &member).await?;
             }
 
+            if let Some(shadow_payout) = shadow_payout {
+                for member in shadow_payout.members {
+                    database::store_payout_shadow_member(&mut tx, &member).await?;
+                }
+            }
+
             database::update_last_payout_height(&mut tx, &self.chain_id, stake.block_height)
                 .await?;
 
             tx.commit().await?;
+
+            self.metrics.inc(&self.chain_id, Metric::PayoutsCreated);
+            self.metrics
+                .add_amount(&self.chain_id, Metric::PayoutAmount, payout_amount);
         }
 
         Ok(())
     }
 
+    /// Calculates the payout of `stake` with `scheme`, from the work in the round with id
+    /// `round_id` or in the rounds before it.
+    async fn calculate_payout(
+        &self,
+        scheme: &PayoutScheme,
+        stake: &Stake,
+        round_id: u64,
+    ) -> Result<Payout> {
+        let schedule = &self.config.fee_schedule;
+        let (workers, forfeited_shares, fees) = match scheme {
+            PayoutScheme::Proportional => tokio::try_join!(
+                database::get_workers_by_round(&self.database, &self.chain_id, round_id),
+                database::get_forfeited_shares_by_round(&self.database, &self.chain_id, round_id),
+                Fees::load_for_round(&self.database, schedule, stake),
+            )?,
+            PayoutScheme::Pplns { window } => {
+                let (workers, forfeited_shares, since_height) =
+                    self.pplns_workers(stake, *window).await?;
+                let fees = Fees::load(&self.database, schedule, stake, since_height).await?;
+
+                (workers, forfeited_shares, fees)
+            }
+        };
+
+        Payout::new(stake, workers, forfeited_shares, &fees)
+    }
+
+    /// Collects the workers of the rounds up to and including the round of `stake`, until they
+    /// hold `window` shares. Also returns the height after which the collected rounds started.
+    async fn pplns_workers(
+        &self,
+        stake: &Stake,
+        window: u64,
+    ) -> Result<(Vec<Worker>, Decimal, u64)> {
+        let window = Decimal::from(window);
+        let mut rounds = vec![];
+        let mut shares = Decimal::ZERO;
+        let mut since_height = 0;
+
+        for (round_id, block_height) in
+            database::get_rounds_until(&self.database, &self.chain_id, stake.block_height).await?
+        {
+            if shares >= window {
+                since_height = block_height;
+                break;
+            }
+
+            let (workers, forfeited_shares) = tokio::try_join!(
+                database::get_workers_by_round(&self.database, &self.chain_id, round_id),
+                database::get_forfeited_shares_by_round(&self.database, &self.chain_id, round_id),
+            )?;
+            shares += workers
+                .iter()
+                .fold(forfeited_shares, |acc, worker| acc + worker.shares);
+
+            rounds.push((workers, forfeited_shares));
+        }
+
+        let (workers, forfeited_shares) = Worker::pplns(rounds, window);
+
+        Ok((workers, forfeited_shares, since_height))
+    }
+
+    /// Returns whether a dust sweep is due.
+    async fn dust_sweep_due(&self) -> Result<bool> {
+        let Some(dust_sweep) = &self.config.dust_sweep else {
+            return Ok(false);
+        };
+
+        let last_swept_at = database::get_last_dust_sweep(&self.database, &self.chain_id).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Ok(dust_sweep.is_due(last_swept_at, now))
+    }
+
+    /// Pays the unpaid payout members, in batches of stakers of which the size adapts to how the
+    /// previous payments went. A failed payment ends the run; it is tried again in the next run.
+    /// When a dust sweep is due, the balances below the min_payout of their staker are paid too,
+    /// and the sweep is done once all batches are sent.
+    ///
+    /// Payments of a previous run that may have been sent are reconciled first. Nothing is paid
+    /// while the outcome of one of them is unknown, as its payout members could be paid twice.
     async fn send_unsent_payouts(&self) -> Result<()> {
-        let mut tx = self.database.begin().await?;
+        let client: Client = (&self.chain_config).try_into()?;
+        match reconcile_payment_journal(&self.database, &client, &self.chain_id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("a previous payment is not resolved yet, not sending payments");
+
+                return Ok(());
+            }
+            Err(e) => {
+                warn!(error = ?e, "could not reconcile the payment journal, not sending payments");
+
+                return Ok(());
+            }
+        }
+
+        let dust_sweep = self.dust_sweep_due().await?;
+        if dust_sweep {
+            info!("sweeping the balances below the min_payout");
+        }
+
+        loop {
+            let mut tx = self.database.begin().await?;
+
+            let unpaid_payout_members = self.payable_members(&mut tx, dust_sweep).await?;
+
+            if unpaid_payout_members.is_empty() {
+                return self.finish_dust_sweep(dust_sweep).await;
+            }
+
+            let batch_size = self.batch_sizer.lock().expect("lock poisoned").size();
+            let mut items = PaymentItem::aggregate(&unpaid_payout_members);
+            let is_last_batch = items.len() <= batch_size;
+            items.truncate(batch_size);
+            self.tx_fee.deduct_from(&mut items)?;
+
+            let members = unpaid_payout_members
+                .into_iter()
+                .filter(|member| {
+                    items
+                        .iter()
+                        .any(|item| item.identity_address == member.identity_address)
+                })
+                .collect::<Vec<_>>();
+            PaymentItem::set_payout_currencies(
+                &mut items,
+                &database::get_payout_currencies(&self.database, &self.chain_id).await?,
+            );
+            PaymentItem::set_payout_addresses(
+                &mut items,
+                &database::get_payout_addresses(&self.database, &self.chain_id).await?,
+            );
+            let converter = self
+                .config
+                .conversion
+                .as_ref()
+                .map(|conversion| Converter::new(&client, &self.chain_id, conversion));
+            let outputs = prepare_payment(&mut items, converter.as_ref());
+
+            let start = Instant::now();
+            let result = send_journaled_payment(
+                &self.database,
+                &self.chain_id,
+                &members,
+                &items,
+                self.tx_fee,
+                outputs,
+                &self.pool_address,
+                &client,
+            )
+            .await;
+            let latency = start.elapsed();
+
+            let txid = match result {
+                Ok((journal_id, Some(txid))) => {
+                    store_sent_payment(
+                        &mut tx,
+                        &self.chain_id,
+                        txid,
+                        &members,
+                        &items,
+                        self.tx_fee,
+                    )
+                    .await?;
+                    database::close_payment_journal_entry(
+                        &mut tx,
+                        journal_id,
+                        JournalStatus::Sent,
+                        Some(&txid),
+                    )
+                    .await?;
+
+                    tx.commit().await?;
+
+                    info!(?txid, n_stakers = items.len(), "Sent payment");
+
+                    self.metrics.inc(&self.chain_id, Metric::PaymentsSent);
+                    for item in &items {
+                        self.metrics
+                            .add_amount(&self.chain_id, Metric::PaymentAmount, item.amount);
+                    }
+
+                    self.events.publish(PoolEvent::PaymentSent {
+                        txid,
+                        n_stakers: items.len() as u64,
+                        amount: items
+                            .iter()
+                            .fold(Amount::ZERO, |acc, item| acc + item.amount),
+                        items: items.clone(),
+                    });
+
+                    self.batch_sizer
+                        .lock()
+                        .expect("lock poisoned")
+                        .record_success(latency);
+
+                    Some(txid)
+                }
+                Ok((_, None)) | Err(_) => {
+                    self.metrics.inc(&self.chain_id, Metric::PaymentsFailed);
+                    self.batch_sizer
+                        .lock()
+                        .expect("lock poisoned")
+                        .record_failure();
+
+                    None
+                }
+            };
+
+            let batch = PaymentBatch {
+                currency_address: self.chain_id.clone(),
+                txid,
+                size: items.len(),
+                latency,
+                payout_members: members
+                    .iter()
+                    .map(|member| (member.identity_address.clone(), member.block_hash))
+                    .collect(),
+            };
+            if let Err(e) = database::store_payment_batch(&self.database, &batch).await {
+                warn!(?batch, error = ?e, "could not record payment batch");
+            }
+
+            match result {
+                Ok((_, Some(_))) if !is_last_batch => continue,
+                Ok((_, Some(_))) => return self.finish_dust_sweep(dust_sweep).await,
+                Ok((_, None)) => {
+                    warn!(
+                        n_stakers = items.len(),
+                        "payment was not sent, retrying next run"
+                    );
+
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Selects and locks the unpaid payout members that are paid in this run: those of stakers
+    /// that reached their min_payout, settle, were paid on the primary chain or are swept, as long as their balance
+    /// can be sent after the tx fee.
+    async fn payable_members(
+        &self,
+        conn: &mut PgConnection,
+        dust_sweep: bool,
+    ) -> Result<Vec<PayoutMember>> {
+        let aligned_chain = self
+            .config
+            .alignment
+            .as_ref()
+            .map(|alignment| &alignment.primary_chain);
+
+        database::get_unpaid_payout_members(
+            conn,
+            &self.chain_id,
+            aligned_chain,
+            self.tx_fee.min_payable(),
+            dust_sweep,
+        )
+        .await
+    }
+
+    async fn finish_dust_sweep(&self, dust_sweep: bool) -> Result<()> {
+        if dust_sweep {
+            database::update_last_dust_sweep(&self.database, &self.chain_id).await?;
+            info!("swept the balances below the min_payout");
+        }
+
+        Ok(())
+    }
 
-        // NB: these are already filtered on min_payout settings
-        let unpaid_payout_members =
-            database::get_unpaid_payout_members(&mut tx, &self.chain_id).await?;
+    /// Follows the confirmations of pending payments.
+    ///
+    /// A payment is confirmed once it reaches the required number of confirmations. If a
+    /// payment was double spent (negative confirmations), or is still unconfirmed after the
+    /// timeout and left the mempool, it is considered failed: an evicted payment is abandoned,
+    /// its payout members are reopened to be paid again and the operator is alerted.
+    async fn verify_payments(&self) -> Result<()> {
+        let pending_payments = database::get_payments_by_status(
+            &self.database,
+            &self.chain_id,
+            PaymentStatus::Pending,
+        )
+        .await?;
 
-        if unpaid_payout_members.is_empty() {
+        if pending_payments.is_empty() {
             return Ok(());
         }
 
-        let outputs = prepare_payment(&unpaid_payout_members)?;
+        let client: Client = (&self.chain_config).try_into()?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        for mut payment in pending_payments {
+            let confirmations = match client.get_transaction(&payment.txid, None) {
+                Ok(transaction) => transaction.info.confirmations as i64,
+                Err(e) => {
+                    warn!(txid = %payment.txid, error = ?e, "could not get payment transaction");
+
+                    continue;
+                }
+            };
+
+            let timed_out = confirmations == 0
+                && now.saturating_sub(payment.created_at) > self.config.unconfirmed_timeout_in_secs;
+            let in_mempool = timed_out && in_mempool(&client, &payment.txid);
+
+            let failed = match pending_action(confirmations, timed_out, in_mempool) {
+                PendingAction::Wait => {
+                    if timed_out {
+                        warn!(txid = %payment.txid, "payment is unconfirmed after the timeout, but still in the mempool");
+                    }
+
+                    false
+                }
+                PendingAction::Abandon => {
+                    // an abandoned transaction can't confirm anymore, so its payout members can
+                    // be paid again without paying them twice
+                    if let Err(e) = client.call::<serde_json::Value>(
+                        "abandontransaction",
+                        &[payment.txid.to_string().into()],
+                    ) {
+                        warn!(txid = %payment.txid, error = ?e, "could not abandon evicted payment");
+
+                        continue;
+                    }
+
+                    true
+                }
+                PendingAction::Fail => true,
+            };
+
+            if failed {
+                error!(?payment, %confirmations, "payment failed, reopening payout members");
+
+                database::fail_payment(&self.database, &payment).await?;
+                self.metrics.inc(&self.chain_id, Metric::PaymentsFailed);
+
+                self.events.publish(PoolEvent::PaymentFailed {
+                    payment,
+                    confirmations,
+                });
 
-        let client: vrsc_rpc::client::Client = (&self.chain_config).try_into()?;
-        if let Some(txid) = send_payment(outputs, &self.pool_address, &client).await? {
-            for member in unpaid_payout_members.iter() {
-                if let Err(e) = database::set_txid_payment_member(&mut tx, member, &txid).await {
-                    error!(failed_member = ?member);
-                    error!(?unpaid_payout_members);
-                    error!(?txid);
-                    error!(?e);
+                continue;
+            }
+
+            payment.confirmations = confirmations as u64;
 
-                    bail!("A payment was sent but the database failed to update.");
-                };
+            if payment.confirmations >= self.config.required_confirmations {
+                info!(txid = %payment.txid, "payment confirmed");
+                payment.status = PaymentStatus::Confirmed;
             }
 
-            tx.commit().await?;
+            let mut conn = self.database.acquire().await?;
+            database::store_payment(&mut conn, &payment).await?;
+        }
 
-            info!(?txid, "Sent payment");
+        Ok(())
+    }
+
+    /// Archives the payout members of confirmed payments, so that the payout members that still
+    /// need to be paid are a small part of the table.
+    async fn archive_payout_members(&self) -> Result<()> {
+        let Some(after_months) = self.config.archive_after_months else {
+            return Ok(());
+        };
+
+        let archived =
+            database::archive_payout_members(&self.database, &self.chain_id, after_months).await?;
+        if archived > 0 {
+            info!(%archived, "archived payout members");
+        }
+
+        Ok(())
+    }
+
+    async fn keep_verifying_payments(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.verify_payments().await {
+                error!(error = ?e, "Failed to verify payments");
+            } else if let Err(e) = self.archive_payout_members().await {
+                error!(error = ?e, "Failed to archive payout members");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.check_interval_in_secs)) => {}
+            }
         }
 
         Ok(())
     }
 
     async fn keep_creating_payouts(&self, subsys: &SubsystemHandle) -> Result<()> {
+        let mut triggered = false;
+
         while !subsys.is_shutdown_requested() {
-            if let Err(e) = self.new_payout().await {
+            if self.halt.is_halted() {
+                debug!("chain is halted, not creating payouts");
+            } else if self.pause.is_paused() {
+                debug!("chain is paused, not creating payouts");
+            } else if let Err(e) = self.new_payout().await {
                 error!(error = ?e, "Failed to create new payout");
+            } else {
+                self.metrics
+                    .set_now(&self.chain_id, Metric::LastPayoutRunAt);
+            }
+
+            if triggered {
+                self.trigger.request_payments();
+                triggered = false;
             }
 
             tokio::select! {
                 _ = subsys.on_shutdown_requested() => {},
                 _ = tokio::time::sleep(Duration::from_secs(self.config.check_interval_in_secs)) => {}
+                _ = self.trigger.payouts_requested() => {
+                    info!("payout run was triggered");
+                    triggered = true;
+                }
             }
         }
 
@@ -132,7 +618,11 @@ impl Service {
 
     async fn keep_sending_payments(&self, subsys: &SubsystemHandle) -> Result<()> {
         while !subsys.is_shutdown_requested() {
-            if let Err(e) = self.send_unsent_payouts().await {
+            if self.halt.is_halted() {
+                debug!("chain is halted, not sending payments");
+            } else if self.pause.is_paused() {
+                debug!("chain is paused, not sending payments");
+            } else if let Err(e) = self.send_unsent_payouts().await {
                 error!(error = ?e, "Failed to send payment");
 
                 bail!("Failed to send payments");
@@ -141,6 +631,7 @@ impl Service {
             tokio::select! {
                 _ = subsys.on_shutdown_requested() => {},
                 _ = tokio::time::sleep(Duration::from_secs(self.config.send_interval_in_secs)) => {}
+                _ = self.trigger.payments_requested() => {}
             }
         }
 
@@ -148,37 +639,116 @@ impl Service {
     }
 }
 
+/// What to do with a pending payment.
+#[derive(Debug, PartialEq, Eq)]
+enum PendingAction {
+    Wait,
+    /// The payment was double spent, so it can't confirm anymore.
+    Fail,
+    /// The payment left the mempool without confirming. It could still be mined if it was
+    /// rebroadcast, so it has to be abandoned before its payout members are paid again.
+    Abandon,
+}
+
+/// Decides what to do with a pending payment with `confirmations`. A payment that is unconfirmed
+/// after the timeout is only given up on once it is no longer `in_mempool`.
+fn pending_action(confirmations: i64, timed_out: bool, in_mempool: bool) -> PendingAction {
+    if confirmations < 0 {
+        PendingAction::Fail
+    } else if timed_out && !in_mempool {
+        PendingAction::Abandon
+    } else {
+        PendingAction::Wait
+    }
+}
+
+/// Whether the mempool of the daemon holds `txid`. If the mempool can't be listed, the
+/// transaction is assumed to be in it, so that nothing is paid twice.
+fn in_mempool(client: &Client, txid: &Txid) -> bool {
+    match client.call::<Vec<Txid>>("getrawmempool", &[]) {
+        Ok(mempool) => mempool.contains(txid),
+        Err(e) => {
+            warn!(error = ?e, "could not list the mempool");
+
+            true
+        }
+    }
+}
+
+/// Marks the payout members as paid by `txid` and stores the payment, with its tx fee, and its
+/// items.
+///
+/// The payment was already sent at this point, so a failure logs everything that is needed to
+/// repair the database by hand.
+pub async fn store_sent_payment(
+    conn: &mut PgConnection,
+    currency_address: &Address,
+    txid: Txid,
+    members: &[PayoutMember],
+    items: &[PaymentItem],
+    tx_fee: TxFee,
+) -> Result<Payment> {
+    for member in members.iter() {
+        if let Err(e) = database::set_txid_payment_member(conn, member, &txid).await {
+            error!(failed_member = ?member);
+            error!(?members);
+            error!(?txid);
+            error!(?e);
+
+            bail!("A payment was sent but the database failed to update.");
+        };
+    }
+
+    let mut payment = Payment::new(currency_address.clone(), txid, members);
+    payment.tx_fee = tx_fee.amount;
+    payment.tx_fee_paid_by_pool = tx_fee.paid_by_pool;
+    if let Err(e) = database::store_payment(conn, &payment).await {
+        error!(?payment, ?e);
+
+        bail!("A payment was sent but the database failed to update.");
+    }
+
+    if let Err(e) = database::store_payment_items(conn, currency_address, &txid, items).await {
+        error!(?items, ?txid, ?e);
+
+        bail!("A payment was sent but the database failed to update.");
+    }
+
+    Ok(payment)
+}
+
+/// Creates one output per payment item, so that every staker receives a single output.
+///
+/// With a `converter`, the amount of an item with a payout currency is converted into that
+/// currency on the way, if the conversion doesn't slip too much. The payout currency of every
+/// item is set to the currency that its output converts into, so that the items record what the
+/// payment sends.
 pub fn prepare_payment<'a>(
-    payout_members: &Vec<PayoutMember>,
-) -> Result<Vec<SendCurrencyOutput<'a>>> {
-    let mut payout_members_map: HashMap<Address, Amount> = HashMap::new();
-    for member in payout_members.into_iter() {
-        payout_members_map
-            .entry(member.identity_address.clone())
-            .and_modify(|sum| *sum += member.reward)
-            .or_insert(member.reward);
-    }
-
-    // let payment_vouts = payout_members
-    //     .iter()
-    //     .map(|pm| {
-    //         (
-    //             pm.identity_address,
-    //             pm.iter().fold(Amount::ZERO, |acc, pm| acc + pm.reward),
-    //         )
-    //     })
-    //     .collect::<HashMap<&Address, Amount>>();
-
-    debug!("payment_vouts {:#?}", payout_members_map);
-
-    let outputs = payout_members_map
-        .iter()
-        .map(|(address, amount)| {
-            SendCurrencyOutput::new(None, amount, &address.to_string(), None, None)
-        })
-        .collect::<Vec<_>>();
-
-    Ok(outputs)
+    items: &mut [PaymentItem],
+    converter: Option<&Converter>,
+) -> Vec<SendCurrencyOutput<'a>> {
+    debug!("payment_items {:#?}", items);
+
+    let mut outputs = vec![];
+    for item in items {
+        item.payout_currency = converter.and_then(|converter| converter.convert_to(item));
+        let via = converter
+            .and_then(|converter| converter.via())
+            .filter(|_| item.payout_currency.is_some());
+
+        outputs.push(SendCurrencyOutput::new(
+            None,
+            &item.amount,
+            &item.destination().to_string(),
+            item.payout_currency
+                .as_ref()
+                .map(|currency| currency.to_string())
+                .as_deref(),
+            via.map(|via| via.to_string()).as_deref(),
+        ));
+    }
+
+    outputs
 }
 
 pub async fn send_payment<'a>(
@@ -196,7 +766,10 @@ pub async fn send_payment<'a>(
     Ok(None)
 }
 
-async fn wait_for_sendcurrency_finish(client: &Client, opid: &str) -> Result<Option<Txid>> {
+pub(super) async fn wait_for_sendcurrency_finish(
+    client: &Client,
+    opid: &str,
+) -> Result<Option<Txid>> {
     // from https://buildmedia.readthedocs.org/media/pdf/zcash/english-docs/zcash.pdf
     // status can be one of queued, executing, failed or success.
     // we should sleep if status is one of queued or executing
@@ -222,6 +795,8 @@ async fn wait_for_sendcurrency_finish(client: &Client, opid: &str) -> Result<Opt
                 return Ok(Some(txid.txid));
             } else {
                 error!("execution failed with status: {}", opstatus.status);
+
+                return Ok(None);
             }
         } else {
             trace!("there was NO operation_status");
@@ -235,9 +810,155 @@ impl IntoSubsystem<anyhow::Error> for Service {
     async fn run(self, subsys: SubsystemHandle) -> Result<()> {
         tokio::try_join!(
             self.keep_creating_payouts(&subsys),
-            self.keep_sending_payments(&subsys)
+            self.keep_sending_payments(&subsys),
+            self.keep_verifying_payments(&subsys)
         )?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use vrsc_rpc::bitcoin::BlockHash;
+
+    use crate::coinstaker::{
+        constants::{Staker, StakerStatus},
+        DUST_THRESHOLD_IN_SATS,
+    };
+
+    use super::*;
+
+    const CURRENCY: &str = "iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq";
+
+    fn service(pool: PgPool, tx_fee: Amount) -> Service {
+        let config: PayoutServiceConfig = serde_json::from_value(json!({
+            "check_interval_in_secs": 60,
+            "send_interval_in_secs": 60,
+            "pool_pays_tx_fee": false,
+            "dust_sweep": {}
+        }))
+        .unwrap();
+        let chain_config: ChainConfig = serde_json::from_value(json!({
+            "rpc_user": "user",
+            "rpc_password": "password",
+            "rpc_host": "127.0.0.1",
+            "rpc_port": 27486,
+            "zmq_port_blocknotify": 59790
+        }))
+        .unwrap();
+
+        Service::new(
+            config,
+            pool,
+            Address::from_str(CURRENCY).unwrap(),
+            Address::from_str("RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2").unwrap(),
+            chain_config,
+            tx_fee,
+            EventBus::new(),
+        )
+    }
+
+    async fn store_balance(
+        conn: &mut PgConnection,
+        identity_address: &Address,
+        min_payout: u64,
+        status: StakerStatus,
+        reward: u64,
+    ) {
+        let currency_address = Address::from_str(CURRENCY).unwrap();
+        let staker = Staker::new(
+            currency_address.clone(),
+            identity_address.clone(),
+            "staker@".to_string(),
+            Amount::from_sat(min_payout),
+            status,
+            Decimal::ZERO,
+        );
+        database::store_staker(conn, &staker).await.unwrap();
+
+        let member = PayoutMember::new(
+            currency_address,
+            BlockHash::from_str(&format!("{:064x}", reward)).unwrap(),
+            10,
+            identity_address.clone(),
+            Amount::from_sat(reward),
+            Decimal::ONE,
+            Amount::ZERO,
+        );
+        database::store_payout_member(conn, &member).await.unwrap();
+    }
+
+    #[sqlx::test(migrations = "sql/migrations")]
+    async fn a_dust_sweep_only_pays_what_can_be_sent(pool: PgPool) {
+        // the stakers bear the tx fee, so a balance must cover it on top of the dust threshold
+        let service = service(pool.clone(), Amount::from_sat(1_000));
+        let min_payable = DUST_THRESHOLD_IN_SATS + 1_000;
+        let mut conn = pool.acquire().await.unwrap();
+
+        // alice settles with a balance below the dust threshold
+        let alice = Address::from_str("iB5PRXMHLYcNtM8dfLB6KwfJrHU2mKDYuU").unwrap();
+        store_balance(
+            &mut conn,
+            &alice,
+            100_000_000,
+            StakerStatus::Inactive,
+            1_000,
+        )
+        .await;
+        database::schedule_settlement(&mut conn, &service.chain_id, &alice, 0)
+            .await
+            .unwrap();
+        // bob is below his min_payout, but above the dust threshold
+        let bob = Address::from_str("iGLN3bFv6uY2HAgQgVwiGriTRgQmTyJrwi").unwrap();
+        store_balance(&mut conn, &bob, 100_000_000, StakerStatus::Active, 10_000).await;
+        // carol reached her min_payout, but her balance doesn't cover the tx fee
+        let carol = Address::from_str("i5w5MuNik5NtLcYmNzcvaoixooEebB6MGV").unwrap();
+        store_balance(
+            &mut conn,
+            &carol,
+            DUST_THRESHOLD_IN_SATS,
+            StakerStatus::Active,
+            min_payable - 1,
+        )
+        .await;
+
+        assert!(service
+            .payable_members(&mut conn, false)
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert!(service.dust_sweep_due().await.unwrap());
+        let members = service.payable_members(&mut conn, true).await.unwrap();
+        assert_eq!(
+            members
+                .iter()
+                .map(|member| &member.identity_address)
+                .collect::<Vec<_>>(),
+            vec![&bob]
+        );
+
+        let mut items = PaymentItem::aggregate(&members);
+        service.tx_fee.deduct_from(&mut items).unwrap();
+        assert!(items
+            .iter()
+            .all(|item| item.amount.as_sat() >= DUST_THRESHOLD_IN_SATS));
+
+        service.finish_dust_sweep(true).await.unwrap();
+        assert!(!service.dust_sweep_due().await.unwrap());
+    }
+
+    #[test]
+    fn timed_out_payments_are_only_abandoned_once_evicted() {
+        assert_eq!(pending_action(0, false, false), PendingAction::Wait);
+        assert_eq!(pending_action(0, true, true), PendingAction::Wait);
+        assert_eq!(pending_action(0, true, false), PendingAction::Abandon);
+        assert_eq!(pending_action(-1, false, false), PendingAction::Fail);
+        assert_eq!(pending_action(3, false, false), PendingAction::Wait);
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use sqlx::PgPool;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error, info};
+use vrsc_rpc::{
+    bitcoin::Txid,
+    client::{Client, RpcApi},
+    json::{
+        vrsc::{Address, Amount},
+        CreateRawTransactionInput,
+    },
+};
+
+use crate::{
+    coinstaker::{ChainConfig, UtxoSweepConfig},
+    database,
+};
+
+use super::PaymentStatus;
+
+/// UTXOs need this many confirmations before they are swept, so that immature stake rewards and
+/// outputs that can still be reorganized are left alone.
+const MIN_CONFIRMATIONS: u32 = 150;
+
+/// Periodically consolidates the small UTXOs of the pool address into one output.
+pub struct Sweeper {
+    database: PgPool,
+    config: UtxoSweepConfig,
+    chain_id: Address,
+    pool_address: Address,
+    chain_config: ChainConfig,
+    tx_fee: Amount,
+}
+
+/// An unspent output of the pool address that is a candidate to be swept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepInput {
+    pub txid: Txid,
+    pub vout: u32,
+    pub amount: Amount,
+}
+
+impl Sweeper {
+    pub fn new(
+        config: UtxoSweepConfig,
+        database: PgPool,
+        chain_id: Address,
+        pool_address: Address,
+        chain_config: ChainConfig,
+        tx_fee: Amount,
+    ) -> Self {
+        Self {
+            database,
+            config,
+            chain_id,
+            pool_address,
+            chain_config,
+            tx_fee,
+        }
+    }
+
+    async fn sweep(&self) -> Result<Option<Txid>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        if !self.config.in_window(now) {
+            debug!("outside of the sweep window");
+
+            return Ok(None);
+        }
+
+        // a pending payment might still be replaced or reorganized, don't touch its change
+        let pending_payments = database::get_payments_by_status(
+            &self.database,
+            &self.chain_id,
+            PaymentStatus::Pending,
+        )
+        .await?;
+
+        if !pending_payments.is_empty() {
+            debug!("payments are pending, skipping sweep");
+
+            return Ok(None);
+        }
+
+        let client: Client = (&self.chain_config).try_into()?;
+
+        let locked = client.list_lock_unspent()?;
+        let addresses = vec![self.pool_address.clone()];
+        let utxos = client
+            .list_unspent(Some(MIN_CONFIRMATIONS), None, Some(addresses.as_ref()))?
+            .into_iter()
+            .filter(|utxo| {
+                !locked
+                    .iter()
+                    .any(|outpoint| outpoint.txid == utxo.txid && outpoint.vout == utxo.vout)
+            })
+            .filter_map(|utxo| {
+                Some(SweepInput {
+                    txid: utxo.txid,
+                    vout: utxo.vout,
+                    amount: utxo.amount.to_unsigned().ok()?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let inputs = select_sweep_inputs(
+            utxos,
+            self.config.max_utxo_amount,
+            self.config.min_utxos,
+            self.config.max_inputs,
+        );
+
+        if inputs.is_empty() {
+            return Ok(None);
+        }
+
+        let total = inputs
+            .iter()
+            .fold(Amount::ZERO, |acc, input| acc + input.amount);
+        let Some(output) = total.checked_sub(self.tx_fee) else {
+            return Ok(None);
+        };
+
+        let raw_inputs = inputs
+            .iter()
+            .map(|input| CreateRawTransactionInput {
+                txid: input.txid,
+                vout: input.vout,
+                sequence: None,
+            })
+            .collect::<Vec<_>>();
+        let outputs = HashMap::from([(self.pool_address.to_string(), output)]);
+
+        let hex = client.create_raw_transaction_hex(&raw_inputs, &outputs, None, None)?;
+        let signed = client.sign_raw_transaction_with_wallet(&hex, None, None)?;
+        let txid = client
+            .send_raw_transaction(&signed.hex)
+            .context("could not send sweep transaction")?;
+
+        info!(%txid, n_inputs = inputs.len(), amount = %output, "swept pool utxos");
+
+        Ok(Some(txid))
+    }
+
+    async fn keep_sweeping(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.sweep().await {
+                error!(error = ?e, "Failed to sweep utxos");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Selects the smallest UTXOs of at most `max_amount`, up to `max_inputs` of them.
+///
+/// Returns nothing if there are fewer than `min_utxos` small UTXOs, as a sweep would not be worth
+/// its fee.
+pub fn select_sweep_inputs(
+    mut utxos: Vec<SweepInput>,
+    max_amount: Amount,
+    min_utxos: usize,
+    max_inputs: usize,
+) -> Vec<SweepInput> {
+    utxos.retain(|utxo| utxo.amount <= max_amount);
+
+    if utxos.len() < min_utxos.max(2) {
+        return vec![];
+    }
+
+    utxos.sort_by_key(|utxo| utxo.amount);
+    utxos.truncate(max_inputs);
+
+    utxos
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for Sweeper {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_sweeping(&subsys).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn input(vout: u32, sats: u64) -> SweepInput {
+        SweepInput {
+            txid: Txid::from_str(
+                "6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7",
+            )
+            .unwrap(),
+            vout,
+            amount: Amount::from_sat(sats),
+        }
+    }
+
+    #[test]
+    fn smallest_utxos_are_selected() {
+        let utxos = vec![
+            input(0, 5_000),
+            input(1, 200_000_000),
+            input(2, 1_000),
+            input(3, 3_000),
+        ];
+
+        assert_eq!(
+            select_sweep_inputs(utxos.clone(), Amount::from_sat(100_000), 3, 2),
+            vec![input(2, 1_000), input(3, 3_000)]
+        );
+        assert!(select_sweep_inputs(utxos, Amount::from_sat(100_000), 4, 10).is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Starts a payout run of the payout service right away, instead of at its next interval. Clones
+/// share the same state.
+///
+/// A run creates the payouts of the matured stakes first, and then pays them.
+#[derive(Debug, Clone, Default)]
+pub struct PayoutTrigger {
+    payouts: Arc<Notify>,
+    payments: Arc<Notify>,
+}
+
+impl PayoutTrigger {
+    pub fn trigger(&self) {
+        self.payouts.notify_one();
+    }
+
+    pub(super) async fn payouts_requested(&self) {
+        self.payouts.notified().await
+    }
+
+    /// Lets the payments follow the payouts of a triggered run.
+    pub(super) fn request_payments(&self) {
+        self.payments.notify_one();
+    }
+
+    pub(super) async fn payments_requested(&self) {
+        self.payments.notified().await
+    }
+}
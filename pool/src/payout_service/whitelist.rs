@@ -0,0 +1,73 @@
+use anyhow::{bail, Result};
+use sqlx::PgPool;
+use tracing::error;
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::database;
+
+/// Refuses to send funds to a destination that is not whitelisted in the configuration or in the
+/// `destination_whitelist` table.
+///
+/// The whitelist can't be changed through the API, so a leaked admin key can't redirect the
+/// funds of the pool. Payments to stakers are not checked: they go to the VerusIDs that earned
+/// the rewards, or to the payout address of a cold staker, which is only set by a registration
+/// that the staked address signed (see `register_cold_staker`), never through the admin API.
+pub async fn ensure_whitelisted(
+    pool: &PgPool,
+    currency_address: &Address,
+    configured: &[Address],
+    destination: &Address,
+) -> Result<()> {
+    if configured.contains(destination) {
+        return Ok(());
+    }
+
+    let stored = database::get_whitelisted_destinations(pool, currency_address).await?;
+
+    if !stored.contains(destination) {
+        error!(%destination, "refused to send funds to a destination that is not whitelisted");
+
+        bail!("{destination} is not a whitelisted destination");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn test_only_whitelisted_destinations_are_allowed(pool: PgPool) {
+        let currency_address = Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap();
+        let configured = Address::from_str("RCdXBieidGuXmK8Tw2gBoXWxi16UgqyKc7").unwrap();
+        let stored = Address::from_str("RJgnAuLfBwakw6VnBjzqQaksejtX8HEwNG").unwrap();
+        let unknown = Address::from_str("RDVXn9BFJMwtXsCkxs6Ru6wDSVe8jH9Qy2").unwrap();
+
+        sqlx::query(
+            "INSERT INTO destination_whitelist (currency_address, address) VALUES ($1, $2)",
+        )
+        .bind(currency_address.to_string())
+        .bind(stored.to_string())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let configured_whitelist = vec![configured.clone()];
+        for (destination, allowed) in [(configured, true), (stored, true), (unknown, false)] {
+            assert_eq!(
+                ensure_whitelisted(
+                    &pool,
+                    &currency_address,
+                    &configured_whitelist,
+                    &destination
+                )
+                .await
+                .is_ok(),
+                allowed
+            );
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use serde_json::json;
+use vrsc_rpc::json::vrsc::Address;
+
+use crate::coinstaker::StatusPageFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Up,
+    Degraded,
+    Down,
+}
+
+/// What the pool observed of a chain, to decide its health.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    pub chain_tip: u64,
+    /// The height of the last block that the pool processed.
+    pub last_block: Option<u64>,
+    pub staking: bool,
+    /// Unix timestamp (in seconds) of the last payment.
+    pub last_payout: Option<u64>,
+}
+
+/// The health of a chain, as it is published to a status page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainHealth {
+    pub currency_address: Address,
+    pub currency_name: String,
+    pub status: HealthStatus,
+    /// Why the chain is not up.
+    pub reason: Option<String>,
+    pub chain_tip: Option<u64>,
+    pub last_block: Option<u64>,
+    pub last_payout: Option<u64>,
+    /// Unix timestamp (in seconds) of when the health was checked.
+    pub timestamp: u64,
+}
+
+impl ChainHealth {
+    /// Decides the health from an observation. Without an observation, the daemon or the
+    /// database could not be reached and the chain is down for `error`.
+    pub fn new(
+        currency_address: Address,
+        currency_name: String,
+        observation: Result<Observation, String>,
+        max_blocks_behind: u64,
+        timestamp: u64,
+    ) -> Self {
+        let mut health = Self {
+            currency_address,
+            currency_name,
+            status: HealthStatus::Up,
+            reason: None,
+            chain_tip: None,
+            last_block: None,
+            last_payout: None,
+            timestamp,
+        };
+
+        let observation = match observation {
+            Ok(observation) => observation,
+            Err(error) => {
+                health.status = HealthStatus::Down;
+                health.reason = Some(error);
+
+                return health;
+            }
+        };
+
+        let blocks_behind = observation
+            .chain_tip
+            .saturating_sub(observation.last_block.unwrap_or_default());
+
+        if blocks_behind > max_blocks_behind {
+            health.status = HealthStatus::Degraded;
+            health.reason = Some(format!("the pool is {blocks_behind} blocks behind"));
+        } else if !observation.staking {
+            health.status = HealthStatus::Degraded;
+            health.reason = Some("the daemon is not staking".to_string());
+        }
+
+        health.chain_tip = Some(observation.chain_tip);
+        health.last_block = observation.last_block;
+        health.last_payout = observation.last_payout;
+
+        health
+    }
+
+    /// Returns the request body for a status page with `format`.
+    pub fn to_body(&self, format: StatusPageFormat) -> serde_json::Value {
+        match format {
+            StatusPageFormat::Generic => json!(self),
+            StatusPageFormat::Statuspage => {
+                let status = match self.status {
+                    HealthStatus::Up => "operational",
+                    HealthStatus::Degraded => "degraded_performance",
+                    HealthStatus::Down => "major_outage",
+                };
+
+                json!({ "component": { "status": status } })
+            }
+            StatusPageFormat::Instatus => {
+                let status = match self.status {
+                    HealthStatus::Up => "OPERATIONAL",
+                    HealthStatus::Degraded => "DEGRADEDPERFORMANCE",
+                    HealthStatus::Down => "MAJOROUTAGE",
+                };
+
+                json!({ "status": status })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn health(observation: Result<Observation, String>) -> ChainHealth {
+        ChainHealth::new(
+            Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            "VRSCTEST".to_string(),
+            observation,
+            5,
+            1731715200,
+        )
+    }
+
+    fn observation(last_block: u64, staking: bool) -> Observation {
+        Observation {
+            chain_tip: 100,
+            last_block: Some(last_block),
+            staking,
+            last_payout: Some(1731711600),
+        }
+    }
+
+    #[test]
+    fn health_follows_the_observation() {
+        assert_eq!(health(Ok(observation(98, true))).status, HealthStatus::Up);
+        assert_eq!(
+            health(Ok(observation(90, true))).reason.as_deref(),
+            Some("the pool is 10 blocks behind")
+        );
+        assert_eq!(
+            health(Ok(observation(100, false))).status,
+            HealthStatus::Degraded
+        );
+
+        let down = health(Err("connection refused".to_string()));
+        assert_eq!(down.status, HealthStatus::Down);
+        assert_eq!(down.chain_tip, None);
+    }
+
+    #[test]
+    fn bodies_match_the_format() {
+        let health = health(Ok(observation(90, true)));
+
+        assert_eq!(
+            health.to_body(StatusPageFormat::Generic)["status"],
+            json!("degraded")
+        );
+        assert_eq!(
+            health.to_body(StatusPageFormat::Statuspage),
+            json!({ "component": { "status": "degraded_performance" } })
+        );
+        assert_eq!(
+            health.to_body(StatusPageFormat::Instatus),
+            json!({ "status": "DEGRADEDPERFORMANCE" })
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use vrsc_rpc::json::vrsc::Address;
+
+/// What the coinstaker of a chain observed of its subsystems. An unreachable database or daemon
+/// is the error it failed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsystemObservation {
+    /// The height of the last block that the pool processed.
+    pub last_block: Result<Option<u64>, String>,
+    pub chain_tip: Result<u64, String>,
+    /// Unix timestamp (in seconds) of when the coinstaker processed its last block.
+    pub last_block_processed_at: Option<u64>,
+    /// Unix timestamp (in seconds) of the last payout run of the payout service.
+    pub last_payout_run_at: Option<u64>,
+}
+
+/// The liveness of the subsystems of a chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainLiveness {
+    pub currency_address: Address,
+    pub currency_name: String,
+    pub healthy: bool,
+    /// Why the chain is not healthy.
+    pub reason: Option<String>,
+    pub database_reachable: bool,
+    pub daemon_reachable: bool,
+    pub chain_tip: Option<u64>,
+    pub last_block: Option<u64>,
+    pub blocks_behind: Option<u64>,
+    pub last_block_processed_at: Option<u64>,
+    /// The number of seconds since the last block was processed.
+    pub last_block_age_in_secs: Option<u64>,
+    pub last_payout_run_at: Option<u64>,
+}
+
+impl ChainLiveness {
+    /// Decides the liveness from an observation at `now`. A chain is unhealthy when its database
+    /// or daemon can't be reached, or when it is more than `max_blocks_behind` blocks behind.
+    pub fn new(
+        currency_address: Address,
+        currency_name: String,
+        observation: SubsystemObservation,
+        max_blocks_behind: u64,
+        now: u64,
+    ) -> Self {
+        let mut reasons = vec![];
+
+        let last_block = observation
+            .last_block
+            .map_err(|e| reasons.push(format!("the database can't be reached: {e}")));
+        let chain_tip = observation
+            .chain_tip
+            .map_err(|e| reasons.push(format!("the daemon can't be reached: {e}")));
+
+        let blocks_behind = match (&last_block, &chain_tip) {
+            (Ok(last_block), Ok(chain_tip)) => {
+                Some(chain_tip.saturating_sub(last_block.unwrap_or_default()))
+            }
+            _ => None,
+        };
+        if let Some(blocks_behind) = blocks_behind.filter(|behind| *behind > max_blocks_behind) {
+            reasons.push(format!("the pool is {blocks_behind} blocks behind"));
+        }
+
+        Self {
+            currency_address,
+            currency_name,
+            healthy: reasons.is_empty(),
+            reason: (!reasons.is_empty()).then(|| reasons.join(", ")),
+            database_reachable: last_block.is_ok(),
+            daemon_reachable: chain_tip.is_ok(),
+            chain_tip: chain_tip.ok(),
+            last_block: last_block.ok().flatten(),
+            blocks_behind,
+            last_block_processed_at: observation.last_block_processed_at,
+            last_block_age_in_secs: observation
+                .last_block_processed_at
+                .map(|processed_at| now.saturating_sub(processed_at)),
+            last_payout_run_at: observation.last_payout_run_at,
+        }
+    }
+}
+
+/// The liveness of every chain of the pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PoolLiveness {
+    pub healthy: bool,
+    pub chains: Vec<ChainLiveness>,
+}
+
+impl PoolLiveness {
+    pub fn new(mut chains: Vec<ChainLiveness>) -> Self {
+        chains.sort_by(|a, b| a.currency_name.cmp(&b.currency_name));
+
+        Self {
+            healthy: chains.iter().all(|chain| chain.healthy),
+            chains,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn liveness(
+        last_block: Result<Option<u64>, String>,
+        chain_tip: Result<u64, String>,
+    ) -> ChainLiveness {
+        ChainLiveness::new(
+            Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            "VRSCTEST".to_string(),
+            SubsystemObservation {
+                last_block,
+                chain_tip,
+                last_block_processed_at: Some(1731715140),
+                last_payout_run_at: Some(1731711600),
+            },
+            5,
+            1731715200,
+        )
+    }
+
+    #[test]
+    fn liveness_follows_the_observation() {
+        let live = liveness(Ok(Some(98)), Ok(100));
+        assert!(live.healthy);
+        assert_eq!(live.blocks_behind, Some(2));
+        assert_eq!(live.last_block_age_in_secs, Some(60));
+
+        let behind = liveness(Ok(Some(90)), Ok(100));
+        assert!(!behind.healthy);
+        assert_eq!(
+            behind.reason.as_deref(),
+            Some("the pool is 10 blocks behind")
+        );
+
+        let daemon_down = liveness(Ok(Some(98)), Err("connection refused".to_string()));
+        assert!(!daemon_down.healthy);
+        assert!(daemon_down.database_reachable);
+        assert!(!daemon_down.daemon_reachable);
+        assert_eq!(daemon_down.blocks_behind, None);
+
+        assert!(!PoolLiveness::new(vec![live, behind]).healthy);
+    }
+}
@@ -0,0 +1,7 @@
+mod health;
+mod liveness;
+mod publisher;
+
+pub use health::{ChainHealth, HealthStatus, Observation};
+pub use liveness::{ChainLiveness, PoolLiveness, SubsystemObservation};
+pub use publisher::{observe, Publisher};
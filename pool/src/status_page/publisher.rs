@@ -0,0 +1,133 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method,
+};
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use tokio_graceful_shutdown::{IntoSubsystem, SubsystemHandle};
+use tracing::{debug, error};
+use vrsc_rpc::{
+    client::{Client as VerusClient, RpcApi},
+    json::vrsc::Address,
+};
+
+use crate::{
+    coinstaker::{ChainConfig, StatusPageConfig, StatusPageFormat},
+    database,
+};
+
+use super::health::{ChainHealth, Observation};
+
+/// Observes the daemon and the database of a chain, to decide its health.
+pub async fn observe(
+    chain_config: &ChainConfig,
+    pool: &PgPool,
+    chain_id: &Address,
+) -> Result<Observation> {
+    let client: VerusClient = chain_config.try_into()?;
+
+    Ok(Observation {
+        chain_tip: client.get_blockchain_info()?.blocks,
+        last_block: database::get_last_height(pool, chain_id).await?,
+        staking: client.get_mining_info()?.staking,
+        last_payout: database::get_last_payment_time(pool, chain_id).await?,
+    })
+}
+
+/// Pushes the health of a chain to a status page.
+pub struct Publisher {
+    database: PgPool,
+    config: StatusPageConfig,
+    chain_id: Address,
+    currency_name: String,
+    chain_config: ChainConfig,
+    client: reqwest::Client,
+}
+
+impl Publisher {
+    pub fn new(
+        config: StatusPageConfig,
+        database: PgPool,
+        chain_id: Address,
+        currency_name: String,
+        chain_config: ChainConfig,
+    ) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in config.headers.iter() {
+            let mut value = HeaderValue::from_str(value.expose_secret())
+                .context(format!("invalid value for status page header `{name}`"))?;
+            value.set_sensitive(true);
+
+            headers.insert(HeaderName::from_bytes(name.as_bytes())?, value);
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(Self {
+            database,
+            config,
+            chain_id,
+            currency_name,
+            chain_config,
+            client,
+        })
+    }
+
+    async fn publish(&self) -> Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let health = ChainHealth::new(
+            self.chain_id.clone(),
+            self.currency_name.clone(),
+            observe(&self.chain_config, &self.database, &self.chain_id)
+                .await
+                .map_err(|e| e.to_string()),
+            self.config.max_blocks_behind,
+            timestamp,
+        );
+
+        let method = match self.config.format {
+            StatusPageFormat::Generic => Method::POST,
+            StatusPageFormat::Statuspage => Method::PATCH,
+            StatusPageFormat::Instatus => Method::PUT,
+        };
+
+        self.client
+            .request(method, self.config.endpoint.clone())
+            .json(&health.to_body(self.config.format))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        debug!(status = ?health.status, reason = ?health.reason, "published health");
+
+        Ok(())
+    }
+
+    async fn keep_publishing(&self, subsys: &SubsystemHandle) -> Result<()> {
+        while !subsys.is_shutdown_requested() {
+            if let Err(e) = self.publish().await {
+                error!(error = ?e, "Failed to publish health to the status page");
+            }
+
+            tokio::select! {
+                _ = subsys.on_shutdown_requested() => {},
+                _ = tokio::time::sleep(Duration::from_secs(self.config.interval_in_secs)) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IntoSubsystem<anyhow::Error> for Publisher {
+    async fn run(self, subsys: SubsystemHandle) -> Result<()> {
+        self.keep_publishing(&subsys).await
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+use tracing::{info, warn};
+use vrsc_rpc::{
+    bitcoin::hashes::{sha256, Hash, HashEngine},
+    client::{Client as VerusClient, RpcApi},
+};
+
+use crate::coinstaker::{BootstrapConfig, ChainConfig};
+
+/// The directories of the data dir that a bootstrap replaces.
+const CHAIN_DIRS: [&str; 2] = ["blocks", "chainstate"];
+
+/// Computes the SHA-256 checksum of a download while it comes in.
+#[derive(Default)]
+pub struct Checksum(sha256::HashEngine);
+
+impl Checksum {
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0.input(bytes);
+    }
+
+    /// Returns the checksum as lowercase hex.
+    pub fn finish(self) -> String {
+        sha256::Hash::from_engine(self.0).to_string()
+    }
+}
+
+/// Downloads the bootstrap of a chain, verifies its checksum and unpacks it into the data dir
+/// of the daemon.
+///
+/// The daemon must be stopped, as it can't have its chainstate replaced while it runs. The pool
+/// must be stopped too, so it doesn't process blocks while the daemon is down.
+pub async fn provision(config: &BootstrapConfig, chain_config: &ChainConfig) -> Result<()> {
+    let client: VerusClient = chain_config.try_into()?;
+    if client.get_blockchain_info().is_ok() {
+        bail!("the daemon is still running, stop it before provisioning a bootstrap");
+    }
+
+    let archive = config.data_dir.join("bootstrap.tar.gz");
+    let checksum = download(config, &archive)
+        .await
+        .context("could not download the bootstrap")?;
+
+    if !checksum.eq_ignore_ascii_case(config.sha256.trim()) {
+        fs::remove_file(&archive)?;
+        bail!(
+            "the checksum of the bootstrap is {checksum}, expected {}",
+            config.sha256
+        );
+    }
+    info!(%checksum, "verified the checksum of the bootstrap");
+
+    set_aside_chain_dirs(&config.data_dir)?;
+
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&config.data_dir)
+        .status()
+        .context("could not run tar")?;
+    if !status.success() {
+        bail!("could not unpack the bootstrap: tar exited with {status}");
+    }
+
+    fs::remove_file(&archive)?;
+    info!(data_dir = ?config.data_dir, "bootstrap is in place, the daemon can be started");
+
+    Ok(())
+}
+
+/// Streams the bootstrap to `path` and returns its checksum.
+async fn download(config: &BootstrapConfig, path: &Path) -> Result<String> {
+    let mut response = reqwest::get(config.url.clone()).await?.error_for_status()?;
+
+    info!(url = %config.url, size = ?response.content_length(), "downloading bootstrap");
+
+    let mut file = File::create(path)?;
+    let mut checksum = Checksum::default();
+    while let Some(chunk) = response.chunk().await? {
+        checksum.update(&chunk);
+        file.write_all(&chunk)?;
+    }
+    file.sync_all()?;
+
+    Ok(checksum.finish())
+}
+
+/// Renames the chain directories that the bootstrap replaces, instead of deleting them.
+fn set_aside_chain_dirs(data_dir: &Path) -> Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    for dir in CHAIN_DIRS {
+        let path = data_dir.join(dir);
+        if path.exists() {
+            let aside = data_dir.join(format!("{dir}.{timestamp}"));
+            warn!(from = ?path, to = ?aside, "setting aside chain directory");
+
+            fs::rename(&path, &aside)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_is_computed_over_all_chunks() {
+        let mut checksum = Checksum::default();
+        checksum.update(b"a");
+        checksum.update(b"bc");
+
+        assert_eq!(
+            checksum.finish(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
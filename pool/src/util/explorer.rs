@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+use url::Url;
+use vrsc_rpc::bitcoin::{BlockHash, Txid};
+
+use crate::coinstaker::constants::Stake;
+use crate::payout_service::PayoutMember;
+
+/// Builds the links to the block explorer of a chain from its `explorer_url_template`, in which
+/// `{kind}` is replaced by `block` or `tx` and `{id}` by the block hash or txid:
+///
+/// ```toml
+/// explorer_url_template = "https://insight.verus.io/{kind}/{id}"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explorer {
+    template: String,
+}
+
+impl Explorer {
+    pub fn new(template: &str) -> Result<Self> {
+        if !template.contains("{id}") {
+            bail!("explorer_url_template `{template}` has no {{id}}");
+        }
+
+        let explorer = Self {
+            template: template.to_string(),
+        };
+        if explorer.url("block", "0").is_none() {
+            bail!("explorer_url_template `{template}` does not make a valid URL");
+        }
+
+        Ok(explorer)
+    }
+
+    pub fn block_url(&self, block_hash: &BlockHash) -> Option<Url> {
+        self.url("block", &block_hash.to_string())
+    }
+
+    pub fn tx_url(&self, txid: &Txid) -> Option<Url> {
+        self.url("tx", &txid.to_string())
+    }
+
+    fn url(&self, kind: &str, id: &str) -> Option<Url> {
+        Url::parse(&self.template.replace("{kind}", kind).replace("{id}", id)).ok()
+    }
+}
+
+/// An API response with the explorer links of its block and transaction, if the chain has an
+/// explorer.
+#[derive(Debug, Clone, Serialize)]
+pub struct WithExplorerLinks<T> {
+    #[serde(flatten)]
+    pub inner: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_url: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_url: Option<Url>,
+}
+
+impl WithExplorerLinks<Stake> {
+    pub fn stake(stake: Stake, explorer: Option<&Explorer>) -> Self {
+        Self {
+            block_url: explorer.and_then(|explorer| explorer.block_url(&stake.block_hash)),
+            tx_url: None,
+            inner: stake,
+        }
+    }
+}
+
+impl WithExplorerLinks<PayoutMember> {
+    /// Links the stake of the payout member and the payment that paid it.
+    pub fn payout_member(member: PayoutMember, explorer: Option<&Explorer>) -> Self {
+        Self {
+            block_url: explorer.and_then(|explorer| explorer.block_url(&member.block_hash)),
+            tx_url: explorer
+                .zip(member.txid.as_ref())
+                .and_then(|(explorer, txid)| explorer.tx_url(txid)),
+            inner: member,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn links_follow_the_template() {
+        let explorer = Explorer::new("https://insight.verus.io/{kind}/{id}").unwrap();
+        let txid =
+            Txid::from_str("6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7")
+                .unwrap();
+
+        assert_eq!(
+            explorer.tx_url(&txid).unwrap().as_str(),
+            "https://insight.verus.io/tx/6b97441d46eb290244720d18bc0bfd24173efcca3aee01649298e0b3c2b960f7"
+        );
+
+        assert!(Explorer::new("https://insight.verus.io/tx").is_err());
+        assert!(Explorer::new("insight/{id}").is_err());
+    }
+}
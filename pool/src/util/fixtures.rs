@@ -0,0 +1,386 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tracing::info;
+use vrsc_rpc::{
+    bitcoin::{
+        hashes::{sha256d, Hash},
+        BlockHash, Txid,
+    },
+    json::vrsc::{Address, Amount},
+};
+
+use crate::{
+    coinstaker::{
+        constants::{Stake, StakeStatus, Staker},
+        StakerStatus,
+    },
+    database,
+    payout_service::{store_sent_payment, Fees, PaymentItem, PaymentStatus, Payout, TxFee},
+};
+
+/// The version byte of a VerusID address.
+const IDENTITY_ADDRESS_VERSION: u8 = 102;
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// The number of blocks before a stake matures.
+const MATURITY: u64 = 100;
+
+/// What to seed a test database with.
+#[derive(Debug, Clone)]
+pub struct FixtureConfig {
+    pub currency_address: Address,
+    pub n_stakers: usize,
+    pub n_rounds: u64,
+    /// The average number of blocks in a round.
+    pub blocks_per_round: u64,
+    /// The number of rounds between payments.
+    pub rounds_per_payment: u64,
+    /// The seed of the random numbers, so that a database can be seeded again with the same data.
+    pub seed: u64,
+}
+
+/// What was seeded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FixtureSummary {
+    pub stakers: usize,
+    pub stakes: u64,
+    pub payouts: u64,
+    pub payments: u64,
+    pub chain_tip: u64,
+}
+
+/// A xorshift generator. Fixtures don't need good randomness, only the same numbers for the same
+/// seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0
+    }
+
+    /// Returns a number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0; N];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+
+        bytes
+    }
+
+    fn hex32(&mut self) -> String {
+        self.bytes::<32>()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Picks an index, with a chance proportional to its weight.
+    fn pick(&mut self, weights: &[u64]) -> usize {
+        let total = weights.iter().sum::<u64>();
+        let mut target = self.next_u64() % total.max(1);
+
+        weights
+            .iter()
+            .position(|weight| {
+                if target < *weight {
+                    true
+                } else {
+                    target -= weight;
+                    false
+                }
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Returns a random, valid VerusID address.
+pub fn identity_address(rng: &mut Rng) -> Address {
+    let mut payload = vec![IDENTITY_ADDRESS_VERSION];
+    payload.extend_from_slice(&rng.bytes::<20>());
+    let checksum = sha256d::Hash::hash(&payload);
+    payload.extend_from_slice(&checksum[..4]);
+
+    Address::from_str(&base58(&payload)).expect("a base58check encoded identity address")
+}
+
+fn base58(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = vec![];
+    for byte in bytes {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    let zeros = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    std::iter::repeat(b'1')
+        .take(zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+/// Returns a staking balance between 100 and 1,000,000 coins.
+///
+/// Balances follow a Pareto distribution, in which 20% of the stakers hold 80% of the funds,
+/// like they do in a real pool.
+pub fn staking_balance(rng: &mut Rng) -> Amount {
+    let coins = 100.0 * (1.0 - rng.next_f64()).powf(-1.0 / 1.16);
+
+    Amount::from_sat((coins.min(1_000_000.0) * 100_000_000.0) as u64)
+}
+
+/// Returns the length of a round, exponentially distributed around `mean` blocks.
+pub fn round_length(rng: &mut Rng, mean: u64) -> u64 {
+    (-(1.0 - rng.next_f64()).ln() * mean as f64).ceil().max(1.0) as u64
+}
+
+/// Seeds a database with stakers, and with rounds of work that end in a stake, their payouts and
+/// the payments of those payouts.
+///
+/// Everything is stored with the same queries that the pool uses, so the data is consistent. The
+/// stakes of the last 100 blocks are still maturing and have no payout yet.
+pub async fn seed(pool: &PgPool, config: &FixtureConfig) -> Result<FixtureSummary> {
+    let mut rng = Rng::new(config.seed);
+    let mut summary = FixtureSummary::default();
+
+    let mut conn = pool.acquire().await?;
+    let mut stakers = vec![];
+    for i in 0..config.n_stakers {
+        // 1 in 10 stakers left the pool already
+        let status = match rng.next_u64() % 10 {
+            0 => StakerStatus::Inactive,
+            _ => StakerStatus::Active,
+        };
+        let staker = Staker::new(
+            config.currency_address.clone(),
+            identity_address(&mut rng),
+            format!("staker{i}@"),
+            Amount::from_sat(100_000_000),
+            status,
+            Decimal::new(5, 2),
+        );
+        database::store_staker(&mut conn, &staker).await?;
+
+        stakers.push((staker, staking_balance(&mut rng)));
+    }
+    summary.stakers = stakers.len();
+
+    let active = stakers
+        .iter()
+        .filter(|(staker, _)| staker.status == StakerStatus::Active)
+        .collect::<Vec<_>>();
+    let balances = active
+        .iter()
+        .map(|(_, balance)| balance.as_sat())
+        .collect::<Vec<_>>();
+
+    let lengths = (0..config.n_rounds)
+        .map(|_| round_length(&mut rng, config.blocks_per_round))
+        .collect::<Vec<_>>();
+    let chain_tip = 1_000 + lengths.iter().sum::<u64>();
+
+    let mut height = 1_000;
+    for (round, length) in lengths.into_iter().enumerate() {
+        height += length;
+
+        if !active.is_empty() {
+            let work = active
+                .iter()
+                .map(|(staker, balance)| {
+                    (
+                        staker.identity_address.clone(),
+                        Decimal::from(balance.as_sat() * length),
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            database::store_work(pool, &config.currency_address, work, height).await?;
+        }
+
+        let found_by = match active.is_empty() {
+            true => identity_address(&mut rng),
+            false => active[rng.pick(&balances)].0.identity_address.clone(),
+        };
+        let status = match height + MATURITY <= chain_tip {
+            true => StakeStatus::Matured,
+            false => StakeStatus::Maturing,
+        };
+        let stake = Stake::new(
+            &config.currency_address,
+            &BlockHash::from_str(&rng.hex32())?,
+            height,
+            &found_by,
+            Txid::from_str(&rng.hex32())?,
+            0,
+            Amount::from_sat(100_000_000_000),
+            status,
+            Amount::from_sat(600_000_000 + rng.next_u64() % 10_000_000),
+        );
+        database::store_new_stake(&mut conn, &stake).await?;
+        summary.stakes += 1;
+
+        if stake.status == StakeStatus::Matured {
+            store_payout(pool, &stake).await?;
+            summary.payouts += 1;
+        }
+
+        if (round as u64 + 1) % config.rounds_per_payment.max(1) == 0
+            && store_payment(pool, &config.currency_address, &mut rng).await?
+        {
+            summary.payments += 1;
+        }
+    }
+    summary.chain_tip = chain_tip;
+
+    database::update_last_height(&mut conn, &config.currency_address, chain_tip).await?;
+
+    info!(?summary, "seeded fixtures");
+
+    Ok(summary)
+}
+
+async fn store_payout(pool: &PgPool, stake: &Stake) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    let round_id = database::get_round_id(&mut conn, &stake.currency_address, &stake.block_hash)
+        .await?
+        .with_context(|| format!("stake {} has no round", stake.block_hash))?;
+    let workers = database::get_workers_by_round(pool, &stake.currency_address, round_id).await?;
+    let forfeited_shares =
+        database::get_forfeited_shares_by_round(pool, &stake.currency_address, round_id).await?;
+
+    let payout = Payout::new(stake, workers, forfeited_shares, &Fees::default())?;
+
+    let mut tx = pool.begin().await?;
+    database::store_payout(&mut tx, &payout).await?;
+    for member in payout.members.iter() {
+        database::store_payout_member(&mut tx, member).await?;
+    }
+    database::update_last_payout_height(&mut tx, &stake.currency_address, stake.block_height)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Pays all unpaid payout members in a confirmed payment. Returns false if nothing was unpaid.
+async fn store_payment(pool: &PgPool, currency_address: &Address, rng: &mut Rng) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let members = database::get_unpaid_payout_members(
+        &mut tx,
+        currency_address,
+        None,
+        TxFee::default().min_payable(),
+        false,
+    )
+    .await?;
+    if members.is_empty() {
+        return Ok(false);
+    }
+
+    let items = PaymentItem::aggregate(&members);
+    let txid = Txid::from_str(&rng.hex32())?;
+    let mut payment = store_sent_payment(
+        &mut tx,
+        currency_address,
+        txid,
+        &members,
+        &items,
+        TxFee::default(),
+    )
+    .await?;
+
+    payment.status = PaymentStatus::Confirmed;
+    payment.confirmations = 10;
+    database::store_payment(&mut tx, &payment).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_data_is_valid() {
+        let mut rng = Rng::new(42);
+
+        for _ in 0..100 {
+            let address = identity_address(&mut rng);
+            assert!(address.to_string().starts_with('i'));
+
+            let balance = staking_balance(&mut rng);
+            assert!(balance >= Amount::from_sat(100 * 100_000_000));
+            assert!(balance <= Amount::from_sat(1_000_000 * 100_000_000));
+
+            assert!(round_length(&mut rng, 20) >= 1);
+        }
+
+        assert_eq!(
+            identity_address(&mut Rng::new(7)),
+            identity_address(&mut Rng::new(7))
+        );
+    }
+
+    #[sqlx::test(migrator = "crate::MIGRATOR")]
+    async fn seeds_a_consistent_pool(pool: PgPool) {
+        let config = FixtureConfig {
+            currency_address: Address::from_str("iJhCezBExJHvtyH3fGhNnt2NhU4Ztkf2yq").unwrap(),
+            n_stakers: 20,
+            n_rounds: 30,
+            blocks_per_round: 10,
+            rounds_per_payment: 5,
+            seed: 42,
+        };
+
+        let summary = seed(&pool, &config).await.unwrap();
+        assert_eq!(summary.stakers, 20);
+        assert_eq!(summary.stakes, 30);
+        assert!(summary.payouts > 0);
+        assert!(summary.payments > 0);
+
+        assert_eq!(
+            database::get_last_height(&pool, &config.currency_address)
+                .await
+                .unwrap(),
+            Some(summary.chain_tip)
+        );
+        assert_eq!(
+            database::get_number_of_matured_stakes(&pool, &config.currency_address)
+                .await
+                .unwrap(),
+            summary.payouts as i64
+        );
+    }
+}
@@ -1 +1,5 @@
+pub mod bootstrap;
+pub mod explorer;
+pub mod fixtures;
+pub mod reward;
 pub mod verus;
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use vrsc_rpc::json::vrsc::{util::amount::serde::as_sat, Amount};
+
+/// The number of blocks in a year, at a block time of 60 seconds.
+pub const BLOCKS_PER_YEAR: u64 = 525_600;
+
+/// The block reward schedule of a chain, as a list of eras.
+///
+/// Follows the `-ac_reward`, `-ac_halving`, `-ac_decay` and `-ac_end` launch parameters of a
+/// chain. Every era starts where the previous era ended. Within an era, the reward halves every
+/// `halving_interval` blocks. A `linear` era moves from its reward to the reward of the next era
+/// over the length of the era.
+///
+/// ```toml
+/// [[reward_schedule.eras]]
+/// reward = 0
+/// linear = true
+/// end = 10080
+///
+/// [[reward_schedule.eras]]
+/// reward = 38400000000 # in sats
+/// halving_interval = 43200
+/// end = 226080
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RewardSchedule {
+    pub eras: Vec<RewardEra>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RewardEra {
+    #[serde(with = "as_sat")]
+    pub reward: Amount,
+    pub halving_interval: Option<u64>,
+    /// The height at which the next era starts. The last era has no end.
+    pub end: Option<u64>,
+    #[serde(default)]
+    pub linear: bool,
+}
+
+impl RewardSchedule {
+    /// The reward schedule of VRSC.
+    pub fn verus() -> Self {
+        Self {
+            eras: vec![
+                RewardEra {
+                    reward: Amount::ZERO,
+                    halving_interval: None,
+                    end: Some(10_080),
+                    linear: true,
+                },
+                RewardEra {
+                    reward: Amount::from_sat(38_400_000_000),
+                    halving_interval: Some(43_200),
+                    end: Some(226_080),
+                    linear: false,
+                },
+                RewardEra {
+                    reward: Amount::from_sat(2_400_000_000),
+                    halving_interval: Some(1_051_920),
+                    end: None,
+                    linear: false,
+                },
+            ],
+        }
+    }
+
+    /// Returns the block reward at `height`, without fees.
+    pub fn reward_at(&self, height: u64) -> Amount {
+        let mut start = 0;
+
+        for (i, era) in self.eras.iter().enumerate() {
+            if era.end.is_some_and(|end| height >= end) {
+                start = era.end.unwrap_or_default();
+
+                continue;
+            }
+
+            if era.linear {
+                let (Some(end), Some(next)) = (era.end, self.eras.get(i + 1)) else {
+                    return era.reward;
+                };
+
+                let from = era.reward.to_sat() as i128;
+                let to = next.reward.to_sat() as i128;
+                let progress = (height - start) as i128;
+                let length = (end - start).max(1) as i128;
+
+                return Amount::from_sat((from + (to - from) * progress / length) as u64);
+            }
+
+            let halvings = era
+                .halving_interval
+                .filter(|interval| *interval > 0)
+                .map(|interval| (height - start) / interval)
+                .unwrap_or(0);
+
+            return Amount::from_sat(
+                era.reward
+                    .to_sat()
+                    .checked_shr(halvings as u32)
+                    .unwrap_or(0),
+            );
+        }
+
+        Amount::ZERO
+    }
+
+    /// Returns the first height after `height` at which the block reward drops.
+    pub fn next_halving(&self, height: u64) -> Option<u64> {
+        let current = self.reward_at(height);
+        let mut start = 0;
+
+        for era in self.eras.iter() {
+            let end = era.end;
+
+            if end.is_some_and(|end| height >= end) {
+                start = end.unwrap_or_default();
+
+                continue;
+            }
+
+            let mut boundaries = vec![];
+            if let Some(interval) = era.halving_interval.filter(|interval| *interval > 0) {
+                let mut next = start + ((height.max(start) - start) / interval + 1) * interval;
+                while end.map_or(boundaries.is_empty(), |end| next < end) {
+                    boundaries.push(next);
+                    next += interval;
+                }
+            }
+            boundaries.extend(end);
+
+            if let Some(halving) = boundaries
+                .into_iter()
+                .filter(|boundary| *boundary > height)
+                .find(|boundary| self.reward_at(*boundary) < current)
+            {
+                return Some(halving);
+            }
+
+            start = end?;
+        }
+
+        None
+    }
+
+    /// Scales an amount that was earned at `height` to what it would have been at `to_height`,
+    /// so that rewards from different eras can be compared.
+    pub fn normalize(&self, amount: Amount, height: u64, to_height: u64) -> Amount {
+        let from = self.reward_at(height).to_sat() as u128;
+        if from == 0 {
+            return amount;
+        }
+
+        let to = self.reward_at(to_height).to_sat() as u128;
+
+        Amount::from_sat((amount.to_sat() as u128 * to / from) as u64)
+    }
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        Self::verus()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vrsc(n: u64) -> Amount {
+        Amount::from_sat(n * 100_000_000)
+    }
+
+    #[test]
+    fn verus_rewards_follow_the_eras() {
+        let schedule = RewardSchedule::verus();
+
+        assert_eq!(schedule.reward_at(0), Amount::ZERO);
+        assert_eq!(schedule.reward_at(5_040), vrsc(192));
+        assert_eq!(schedule.reward_at(10_080), vrsc(384));
+        assert_eq!(schedule.reward_at(53_280), vrsc(192));
+        assert_eq!(schedule.reward_at(226_079), vrsc(24));
+        assert_eq!(schedule.reward_at(226_080), vrsc(24));
+        assert_eq!(schedule.reward_at(1_277_999), vrsc(24));
+        assert_eq!(schedule.reward_at(1_278_000), vrsc(12));
+        assert_eq!(schedule.reward_at(2_329_920), vrsc(6));
+        assert_eq!(schedule.reward_at(3_381_840), vrsc(3));
+    }
+
+    #[test]
+    fn next_halving_skips_unchanged_era_boundaries() {
+        let schedule = RewardSchedule::verus();
+
+        assert_eq!(schedule.next_halving(20_000), Some(53_280));
+        assert_eq!(schedule.next_halving(200_000), Some(1_278_000));
+        assert_eq!(schedule.next_halving(1_278_000), Some(2_329_920));
+        assert_eq!(schedule.next_halving(3_500_000), Some(4_433_760));
+    }
+
+    #[test]
+    fn rewards_are_normalized_by_era() {
+        let schedule = RewardSchedule::verus();
+
+        assert_eq!(schedule.normalize(vrsc(24), 1_000_000, 3_500_000), vrsc(3));
+        assert_eq!(schedule.normalize(vrsc(3), 3_500_000, 1_000_000), vrsc(24));
+        assert_eq!(schedule.normalize(vrsc(1), 0, 3_500_000), vrsc(1));
+    }
+}
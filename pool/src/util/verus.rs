@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use vrsc_rpc::{
+    bitcoin::Txid,
     client::{Client, RpcApi},
     json::{
         vrsc::{Address, Amount, SignedAmount},
@@ -9,18 +10,23 @@ use vrsc_rpc::{
 
 use crate::http::constants::StakingSupply;
 
+/// The supply of the stakers counts the UTXOs that have `eligibility_confirmations`.
 pub fn get_staking_supply(
     _currency_address: &Address,
     identity_addresses: &Vec<Address>,
     client: &Client,
+    eligibility_confirmations: u32,
 ) -> Result<StakingSupply> {
     let pool_supply = client.get_wallet_info()?.eligible_staking_balance.as_vrsc();
     let network_supply = client.get_mining_info()?.stakingsupply;
 
     let mut staker_supply = 0.0;
     if !identity_addresses.is_empty() {
-        let list_unspent =
-            client.list_unspent(Some(150), Some(99999999), Some(identity_addresses))?;
+        let list_unspent = client.list_unspent(
+            Some(eligibility_confirmations as usize),
+            Some(99999999),
+            Some(identity_addresses),
+        )?;
         staker_supply = list_unspent
             .iter()
             .fold(SignedAmount::ZERO, |acc, sum| acc + sum.amount)
@@ -47,6 +53,16 @@ pub fn coinbase_value(block: &Block) -> Result<Amount> {
     Ok(coinbase_value)
 }
 
+pub fn coinbase_txid(block: &Block) -> Result<Txid> {
+    let txid = block
+        .tx
+        .first()
+        .context("there should always be a coinbase transaction")?
+        .txid;
+
+    Ok(txid)
+}
+
 pub fn staker_utxo_value(block: &Block) -> Result<Amount> {
     let utxo_value = block
         .tx
@@ -71,25 +87,21 @@ pub fn postxddest(block: &Block) -> Result<Address> {
     Ok(postxddest)
 }
 
-/// Returns true if a stake was stolen and caught by StakeGuard
-pub async fn check_stake_guard(block: &Block) -> Result<bool> {
-    if block
+/// Returns the txid that spent the coinbase of a stake that was stolen and caught by StakeGuard
+pub async fn check_stake_guard(block: &Block) -> Result<Option<Txid>> {
+    let spent_tx_id = block
         .tx
         .first() // we always need the coinbase, it is always first
         .expect("every block has a coinbase tx")
         .vout
         .first()
         .expect("every tx has at least 1 vout")
-        .spent_tx_id
-        .is_some()
-    {
-        return Ok(true);
-    }
+        .spent_tx_id;
 
-    Ok(false)
+    Ok(spent_tx_id)
 }
 
-pub fn disable_staking(client: Client) -> Result<()> {
+pub fn disable_staking(client: &Client) -> Result<()> {
     client.set_generate(false, 0)?;
 
     Ok(())